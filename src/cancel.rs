@@ -0,0 +1,180 @@
+// ABOUTME: Cooperative cancellation for long-running init, sync, verify, and copy pipelines
+// ABOUTME: Cancelling a token kills running pg_dump/pg_restore/psql children and drops open COPY streams
+
+use crate::errors::ReplicatorError;
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// How often a running subprocess is checked for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Fail with [`ReplicatorError::Aborted`] if cancellation was requested
+///
+/// Called between units of work (databases, tables, batches) so a cancelled
+/// job stops at the next boundary.
+pub fn check(token: &CancellationToken) -> Result<()> {
+    if token.is_cancelled() {
+        return Err(ReplicatorError::Aborted.into());
+    }
+    Ok(())
+}
+
+/// Run a future until it finishes or the token is cancelled
+///
+/// On cancellation the future is dropped, which tears down any COPY stream or
+/// query it holds: tokio-postgres aborts a COPY whose sink is dropped without
+/// being finished, so the target never commits a partial table.
+///
+/// # Errors
+///
+/// Returns the future's error, or [`ReplicatorError::Aborted`] if cancelled first.
+pub async fn run<T>(
+    token: &CancellationToken,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(ReplicatorError::Aborted.into()),
+        result = future => result,
+    }
+}
+
+/// Run a subprocess to completion, killing it if the token is cancelled
+///
+/// A drop-in replacement for [`Command::status`] inside the
+/// [`crate::utils::retry_subprocess_with_backoff`] closures. The aborted
+/// error is not retryable, so the retry loop stops immediately. `chaos` may
+/// kill the subprocess for resilience testing.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be started, or
+/// [`ReplicatorError::Aborted`] after killing it.
+pub fn status(
+    cmd: &mut Command,
+    token: &CancellationToken,
+    chaos: &crate::chaos::Chaos,
+) -> Result<ExitStatus> {
+    check(token)?;
+    let started = std::time::Instant::now();
+    let mut child = cmd.spawn()?;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if chaos.kill_subprocess(started.elapsed()) {
+            child
+                .kill()
+                .context("Failed to stop subprocess for chaos")?;
+            child.wait()?;
+            tracing::warn!(
+                "⚠ Chaos: killed {} after {:?}",
+                cmd.get_program().to_string_lossy(),
+                started.elapsed()
+            );
+            anyhow::bail!(
+                "{} was killed by --chaos",
+                cmd.get_program().to_string_lossy()
+            );
+        }
+        if token.is_cancelled() {
+            child
+                .kill()
+                .context("Failed to stop subprocess after cancellation")?;
+            child.wait()?;
+            return Err(ReplicatorError::Aborted.into());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// A token cancelled by Ctrl-C, for the CLI
+///
+/// The first Ctrl-C requests a clean stop: subprocesses are killed and open
+/// COPY streams are aborted before the command exits. A second Ctrl-C exits
+/// immediately.
+pub fn ctrl_c_token() -> CancellationToken {
+    let token = CancellationToken::new();
+    let on_signal = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        tracing::warn!(
+            "⚠ Interrupted; stopping running operations (press Ctrl-C again to exit now)..."
+        );
+        on_signal.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_check() {
+        let token = CancellationToken::new();
+        assert!(check(&token).is_ok());
+        token.cancel();
+        let err = check(&token).unwrap_err();
+        assert!(matches!(
+            crate::errors::find(&err),
+            Some(ReplicatorError::Aborted)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_drops_future_on_cancel() {
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            cancel.cancel();
+        });
+        let result: Result<()> = run(&token, async {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        })
+        .await;
+        assert!(result.is_err());
+
+        let finished = run(&CancellationToken::new(), async { Ok(7) }).await;
+        assert_eq!(finished.unwrap(), 7);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_status_kills_child_on_cancel() {
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            cancel.cancel();
+        });
+        let started = Instant::now();
+        let result = status(
+            Command::new("sleep").arg("30"),
+            &token,
+            &crate::chaos::Chaos::default(),
+        );
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let ok = status(
+            &mut Command::new("true"),
+            &CancellationToken::new(),
+            &crate::chaos::Chaos::default(),
+        )
+        .unwrap();
+        assert!(ok.success());
+    }
+}
@@ -0,0 +1,191 @@
+// ABOUTME: Developer failure injection (--chaos, gated by SEREN_CHAOS=1) for exercising retry and resume in CI
+// ABOUTME: Kills a subprocess, drops a connection, or corrupts a JSONB batch, each once per run
+
+use anyhow::{bail, Result};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Must be `1` for `--chaos` to be accepted
+pub const ENABLE_ENV: &str = "SEREN_CHAOS";
+
+/// Failures to inject; each fires at most once per run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChaosConfig {
+    /// Kill the first pg_dump/pg_restore/psql child still running after this long
+    pub kill_subprocess_after: Option<Duration>,
+    /// Close the first PostgreSQL connection still open after this long
+    pub drop_connection_after: Option<Duration>,
+    /// Corrupt one row of this JSONB batch (1-based, counted across the run)
+    pub corrupt_batch: Option<u64>,
+}
+
+impl ChaosConfig {
+    /// Parse `--chaos`, e.g. `kill-subprocess=30,drop-connection=10,corrupt-batch=3`
+    ///
+    /// Durations are in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an unknown fault, a missing or non-numeric value,
+    /// or when [`ENABLE_ENV`] is not `1`.
+    pub fn parse(spec: &str, enabled: Option<&str>) -> Result<Self> {
+        if enabled != Some("1") {
+            bail!(
+                "--chaos is a developer mode that deliberately breaks the run.\n\
+                 Set {}=1 to use it, and only against throwaway databases.",
+                ENABLE_ENV
+            );
+        }
+        let mut config = ChaosConfig::default();
+        for fault in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let Some((name, value)) = fault.split_once('=') else {
+                bail!("Invalid --chaos fault '{}'. Expected NAME=VALUE", fault);
+            };
+            let value: u64 = value.trim().parse().map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid --chaos value in '{}'. Expected a whole number",
+                    fault
+                )
+            })?;
+            match name.trim() {
+                "kill-subprocess" => {
+                    config.kill_subprocess_after = Some(Duration::from_secs(value))
+                }
+                "drop-connection" => {
+                    config.drop_connection_after = Some(Duration::from_secs(value))
+                }
+                "corrupt-batch" if value > 0 => config.corrupt_batch = Some(value),
+                "corrupt-batch" => bail!("--chaos corrupt-batch counts from 1"),
+                other => bail!(
+                    "Unknown --chaos fault '{}'. Expected one of: kill-subprocess, drop-connection, corrupt-batch",
+                    other
+                ),
+            }
+        }
+        if config == ChaosConfig::default() {
+            bail!("--chaos needs at least one fault, e.g. kill-subprocess=30");
+        }
+        Ok(config)
+    }
+}
+
+/// The faults of one run, and which of them have fired
+///
+/// Clones share what has fired, so each fault still fires once across the
+/// tasks of a run.
+#[derive(Debug, Clone, Default)]
+pub struct Chaos {
+    config: ChaosConfig,
+    fired: Arc<Fired>,
+}
+
+#[derive(Debug, Default)]
+struct Fired {
+    subprocess_killed: AtomicBool,
+    connection_dropped: AtomicBool,
+    batches_seen: AtomicU64,
+}
+
+impl Chaos {
+    /// Inject `config`'s faults into the run that uses this
+    pub fn new(config: ChaosConfig) -> Self {
+        if config != ChaosConfig::default() {
+            tracing::warn!("⚠ Chaos mode: injecting failures {:?}", config);
+        }
+        Chaos {
+            config,
+            fired: Arc::default(),
+        }
+    }
+
+    /// Whether a subprocess running for `elapsed` should be killed now
+    ///
+    /// Returns `true` once; the caller kills the child and fails the attempt.
+    pub fn kill_subprocess(&self, elapsed: Duration) -> bool {
+        match self.config.kill_subprocess_after {
+            Some(after) if elapsed >= after => {
+                !self.fired.subprocess_killed.swap(true, Ordering::SeqCst)
+            }
+            _ => false,
+        }
+    }
+
+    /// How long a new connection may stay open, if chaos will drop it
+    pub fn connection_lifetime(&self) -> Option<Duration> {
+        if self.fired.connection_dropped.load(Ordering::SeqCst) {
+            return None;
+        }
+        self.config.drop_connection_after
+    }
+
+    /// Claim the connection drop; `false` if another connection already took it
+    pub fn claim_connection_drop(&self) -> bool {
+        !self.fired.connection_dropped.swap(true, Ordering::SeqCst)
+    }
+
+    /// Whether the JSONB batch about to be written should be corrupted
+    ///
+    /// Every call counts one batch, so call it once per batch.
+    pub fn corrupt_next_batch(&self) -> bool {
+        let Some(target) = self.config.corrupt_batch else {
+            return false;
+        };
+        self.fired.batches_seen.fetch_add(1, Ordering::SeqCst) + 1 == target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requires_env_gate() {
+        let err = ChaosConfig::parse("kill-subprocess=5", None).unwrap_err();
+        assert!(err.to_string().contains("SEREN_CHAOS=1"));
+        assert!(ChaosConfig::parse("kill-subprocess=5", Some("0")).is_err());
+    }
+
+    #[test]
+    fn test_parse_faults() {
+        let config = ChaosConfig::parse(
+            "kill-subprocess=30, drop-connection=10,corrupt-batch=3",
+            Some("1"),
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ChaosConfig {
+                kill_subprocess_after: Some(Duration::from_secs(30)),
+                drop_connection_after: Some(Duration::from_secs(10)),
+                corrupt_batch: Some(3),
+            }
+        );
+        for bad in [
+            "",
+            "kill-subprocess",
+            "kill-subprocess=soon",
+            "corrupt-batch=0",
+            "fill-disk=1",
+        ] {
+            assert!(ChaosConfig::parse(bad, Some("1")).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_faults_fire_once_per_run() {
+        let chaos = Chaos::new(ChaosConfig {
+            kill_subprocess_after: Some(Duration::from_secs(5)),
+            corrupt_batch: Some(2),
+            ..Default::default()
+        });
+        let other = Chaos::new(chaos.config);
+        assert!(!chaos.kill_subprocess(Duration::from_secs(1)));
+        assert!(chaos.clone().kill_subprocess(Duration::from_secs(5)));
+        assert!(!chaos.kill_subprocess(Duration::from_secs(9)));
+        assert!(other.kill_subprocess(Duration::from_secs(5)));
+        assert!(!chaos.corrupt_next_batch());
+        assert!(chaos.corrupt_next_batch());
+        assert!(!chaos.corrupt_next_batch());
+    }
+}
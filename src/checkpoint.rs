@@ -1,14 +1,30 @@
 // ABOUTME: Persistent checkpoint tracking for long-running operations
-// ABOUTME: Provides init command resume support with hashed identities
+// ABOUTME: Provides init command resume support per database and restore phase, and ties schema-only/data-only runs together
 
+use crate::migration::phases::RestorePhase;
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const INIT_CHECKPOINT_VERSION: u32 = 1;
+const SCHEMA_STAGE_VERSION: u32 = 1;
+
+/// Which part of the copy an init run performs
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InitScope {
+    /// Schema and data in one run (default)
+    #[default]
+    Full,
+    /// Only create databases and restore schema (`init --schema-only`)
+    SchemaOnly,
+    /// Only copy data into a previously staged schema (`init --data-only`)
+    DataOnly,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct InitCheckpointMetadata {
@@ -17,6 +33,8 @@ pub struct InitCheckpointMetadata {
     pub filter_hash: String,
     pub drop_existing: bool,
     pub enable_sync: bool,
+    #[serde(default)]
+    pub scope: InitScope,
 }
 
 impl InitCheckpointMetadata {
@@ -33,8 +51,15 @@ impl InitCheckpointMetadata {
             filter_hash,
             drop_existing,
             enable_sync,
+            scope: InitScope::Full,
         }
     }
+
+    /// Record which part of the copy this run performs
+    pub fn with_scope(mut self, scope: InitScope) -> Self {
+        self.scope = scope;
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +68,17 @@ struct InitCheckpointData {
     metadata: InitCheckpointMetadata,
     databases: Vec<String>,
     completed: BTreeSet<String>,
+    #[serde(default)]
+    phases: BTreeMap<String, BTreeMap<RestorePhase, PhaseState>>,
+}
+
+/// Progress of one restore phase for one database
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PhaseState {
+    /// The phase began but did not finish; it may have left partial work on the target
+    Started,
+    Completed,
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +94,7 @@ impl InitCheckpoint {
                 metadata,
                 databases: databases.to_vec(),
                 completed: BTreeSet::new(),
+                phases: BTreeMap::new(),
             },
         }
     }
@@ -119,6 +156,23 @@ impl InitCheckpoint {
         self.data.completed.contains(db_name)
     }
 
+    /// State of `phase` for `db_name`, or `None` if it never started
+    pub fn phase_state(&self, db_name: &str, phase: RestorePhase) -> Option<PhaseState> {
+        self.data
+            .phases
+            .get(db_name)
+            .and_then(|phases| phases.get(&phase))
+            .copied()
+    }
+
+    pub fn mark_phase(&mut self, db_name: &str, phase: RestorePhase, state: PhaseState) {
+        self.data
+            .phases
+            .entry(db_name.to_string())
+            .or_default()
+            .insert(phase, state);
+    }
+
     pub fn completed_count(&self) -> usize {
         self.data.completed.len()
     }
@@ -171,6 +225,127 @@ pub fn remove_checkpoint(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Record left by `init --schema-only` for a later `init --data-only` run
+///
+/// Captures which source, target, filter, and databases the schema was staged
+/// for so the data copy cannot be pointed at a different plan by mistake.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchemaStage {
+    version: u32,
+    source_hash: String,
+    target_hash: String,
+    filter_hash: String,
+    databases: Vec<String>,
+    staged_at_unix: u64,
+}
+
+impl SchemaStage {
+    pub fn new(
+        source_url: &str,
+        target_url: &str,
+        filter_hash: String,
+        databases: &[String],
+    ) -> Self {
+        let staged_at_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            version: SCHEMA_STAGE_VERSION,
+            source_hash: hash_string(source_url),
+            target_hash: hash_string(target_url),
+            filter_hash,
+            databases: databases.to_vec(),
+            staged_at_unix,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read schema stage record at {}", path.display()))?;
+        let stage: SchemaStage = serde_json::from_str(&content).with_context(|| {
+            format!("Failed to parse schema stage record at {}", path.display())
+        })?;
+
+        if stage.version != SCHEMA_STAGE_VERSION {
+            bail!(
+                "Schema stage record version mismatch (found {}, expected {}). Re-run init --schema-only.",
+                stage.version,
+                SCHEMA_STAGE_VERSION
+            );
+        }
+
+        Ok(Some(stage))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize schema stage record")?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write schema stage record at {}", path.display()))
+    }
+
+    pub fn databases(&self) -> &[String] {
+        &self.databases
+    }
+
+    /// Seconds since the Unix epoch when the schema was staged
+    pub fn staged_at_unix(&self) -> u64 {
+        self.staged_at_unix
+    }
+
+    /// Check that a data-only run matches the plan the schema was staged for
+    pub fn validate(
+        &self,
+        source_url: &str,
+        target_url: &str,
+        filter_hash: &str,
+        databases: &[String],
+    ) -> Result<()> {
+        if self.source_hash != hash_string(source_url)
+            || self.target_hash != hash_string(target_url)
+        {
+            bail!(
+                "Schema was staged for a different source/target pair.\n\
+                 Run init --schema-only with the same --source and --target first."
+            );
+        }
+
+        if self.filter_hash != filter_hash {
+            bail!(
+                "Database/table filters differ from the ones used by init --schema-only.\n\
+                 Use the same filter flags for both steps, or re-run init --schema-only."
+            );
+        }
+
+        if self.databases != databases {
+            bail!(
+                "Databases discovered now ({}) differ from those staged by init --schema-only ({}).\n\
+                 Re-run init --schema-only to stage the current database list.",
+                databases.join(", "),
+                self.databases.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Location of the schema stage record for a source/target pair
+pub fn schema_stage_path(source_url: &str, target_url: &str) -> Result<PathBuf> {
+    let init_path = checkpoint_path(source_url, target_url)?;
+    let file_name = init_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid checkpoint file name")?
+        .replacen("init-", "schema-stage-", 1);
+    Ok(init_path.with_file_name(file_name))
+}
+
 fn hash_string(input: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(input.as_bytes());
@@ -205,6 +380,75 @@ mod tests {
         assert!(!loaded.is_completed("db2"));
     }
 
+    #[test]
+    fn phase_states_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cp.json");
+        let metadata = InitCheckpointMetadata::new("src", "tgt", "filter".into(), false, true);
+        let databases = vec!["db1".to_string()];
+        let mut checkpoint = InitCheckpoint::new(metadata, &databases);
+        checkpoint.mark_phase("db1", RestorePhase::PreData, PhaseState::Completed);
+        checkpoint.mark_phase("db1", RestorePhase::Data, PhaseState::Started);
+        checkpoint.save(&path).unwrap();
+
+        let loaded = InitCheckpoint::load(&path).unwrap().unwrap();
+        assert_eq!(
+            loaded.phase_state("db1", RestorePhase::PreData),
+            Some(PhaseState::Completed)
+        );
+        assert_eq!(
+            loaded.phase_state("db1", RestorePhase::Data),
+            Some(PhaseState::Started)
+        );
+        assert_eq!(loaded.phase_state("db1", RestorePhase::PostData), None);
+        assert_eq!(loaded.phase_state("db2", RestorePhase::PreData), None);
+    }
+
+    #[test]
+    fn metadata_scope_is_part_of_identity() {
+        let full = InitCheckpointMetadata::new("src", "tgt", "filter".into(), false, true);
+        let schema_only = full.clone().with_scope(InitScope::SchemaOnly);
+        assert_eq!(full.scope, InitScope::Full);
+        assert_ne!(full, schema_only);
+    }
+
+    #[test]
+    fn metadata_without_scope_deserializes_as_full() {
+        let json = r#"{"source_hash":"a","target_hash":"b","filter_hash":"c","drop_existing":false,"enable_sync":true}"#;
+        let metadata: InitCheckpointMetadata = serde_json::from_str(json).unwrap();
+        assert_eq!(metadata.scope, InitScope::Full);
+    }
+
+    #[test]
+    fn schema_stage_roundtrip_and_validate() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stage.json");
+        let databases = vec!["db1".to_string(), "db2".to_string()];
+        let stage = SchemaStage::new("src", "tgt", "filter".into(), &databases);
+        stage.save(&path).unwrap();
+
+        let loaded = SchemaStage::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, stage);
+        loaded.validate("src", "tgt", "filter", &databases).unwrap();
+        assert!(loaded
+            .validate("other", "tgt", "filter", &databases)
+            .is_err());
+        assert!(loaded
+            .validate("src", "tgt", "changed", &databases)
+            .is_err());
+        assert!(loaded
+            .validate("src", "tgt", "filter", &["db1".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn schema_stage_path_is_distinct_from_checkpoint() {
+        let stage = schema_stage_path("postgres://src/db", "postgres://tgt/db").unwrap();
+        let init = checkpoint_path("postgres://src/db", "postgres://tgt/db").unwrap();
+        assert_ne!(stage, init);
+        assert_eq!(stage.parent(), init.parent());
+    }
+
     #[test]
     fn checkpoint_path_is_deterministic() {
         let path_a = checkpoint_path("postgres://src/db", "postgres://tgt/db").unwrap();
@@ -0,0 +1,151 @@
+// ABOUTME: Accept-diff command - records source/target differences that verify should not fail on
+// ABOUTME: Whole tables, columns, or rows matching a predicate; listed and removed by id
+
+use crate::migration::accepted::{self, AcceptedDiff};
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::settings::RunSettings;
+use anyhow::{bail, Context, Result};
+
+/// Record a known, acceptable difference for a table in the target URL's database
+///
+/// Later `verify` runs re-check a mismatched table without the accepted
+/// columns and rows. If it then matches, the table is reported as an accepted
+/// diff and does not count as a failure. The entry is stored in
+/// `seren_replicator.accepted_diffs` in the same database as the table.
+///
+/// # Arguments
+///
+/// * `target_url` - PostgreSQL connection string for the target database holding the table
+/// * `table` - `schema.table`, or `table` for `public.table`
+/// * `columns` - Columns allowed to differ; with no predicate either, the whole table
+/// * `predicate` - SQL predicate selecting rows allowed to differ, e.g. `id < 1000`
+/// * `reason` - Why the difference is acceptable, shown by verify
+///
+/// # Returns
+///
+/// The recorded entry.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The table or a column does not exist on the target, or the predicate is invalid
+/// - Cannot connect to the target or write the accepted differences table
+pub async fn accept_diff(
+    target_url: &str,
+    table: &str,
+    columns: &[String],
+    predicate: Option<&str>,
+    reason: Option<&str>,
+    settings: &RunSettings,
+) -> Result<AcceptedDiff> {
+    let (schema, table) = parse_table(table)?;
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+
+    let id = accepted::add(&client, &schema, &table, columns, predicate, reason).await?;
+    let diff = accepted::load(&client)
+        .await?
+        .into_iter()
+        .find(|diff| diff.id == id)
+        .context("Accepted difference disappeared right after it was recorded")?;
+    tracing::info!(
+        "✓ Accepted difference #{} on {}.{}: {}",
+        diff.id,
+        diff.schema,
+        diff.table,
+        diff.scope()
+    );
+    tracing::info!("  verify reports it as an accepted diff instead of a mismatch");
+    Ok(diff)
+}
+
+/// Print the accepted differences recorded in the target URL's database
+///
+/// # Errors
+///
+/// Returns an error if the target cannot be reached or read.
+pub async fn list_accepted_diffs(target_url: &str, settings: &RunSettings) -> Result<()> {
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let recorded = accepted::load(&client).await?;
+    if recorded.is_empty() {
+        tracing::info!("No accepted differences recorded in this database");
+        return Ok(());
+    }
+    tracing::info!(
+        "{:<6} {:<32} {:<20} {:<40} Reason",
+        "Id",
+        "Table",
+        "Accepted (UTC)",
+        "Scope"
+    );
+    for diff in recorded {
+        tracing::info!(
+            "{:<6} {:<32} {:<20} {:<40} {}",
+            diff.id,
+            format!("{}.{}", diff.schema, diff.table),
+            diff.accepted_at,
+            diff.scope(),
+            diff.reason.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+/// Delete an accepted difference so verify counts it as a mismatch again
+///
+/// # Errors
+///
+/// Returns an error if no entry has this id or the target cannot be reached.
+pub async fn remove_accepted_diff(target_url: &str, id: i64, settings: &RunSettings) -> Result<()> {
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    if !accepted::remove(&client, id).await? {
+        bail!(
+            "No accepted difference #{} in this database.\n\
+             List them with: seren-replicator accept-diff --list --target <url>",
+            id
+        );
+    }
+    tracing::info!("✓ Removed accepted difference #{}", id);
+    Ok(())
+}
+
+fn parse_table(table: &str) -> Result<(String, String)> {
+    let (schema, name) = table.split_once('.').unwrap_or(("public", table));
+    if schema.is_empty() || name.is_empty() || name.contains('.') {
+        bail!(
+            "Invalid table '{}': expected schema.table or table (in the public schema).\n\
+             Choose the database with the --target URL.",
+            table
+        );
+    }
+    Ok((schema.to_string(), name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table() {
+        assert_eq!(
+            parse_table("events").unwrap(),
+            ("public".to_string(), "events".to_string())
+        );
+        assert_eq!(
+            parse_table("audit.events").unwrap(),
+            ("audit".to_string(), "events".to_string())
+        );
+        assert!(parse_table("db.audit.events").is_err());
+        assert!(parse_table(".events").is_err());
+        assert!(parse_table("").is_err());
+    }
+}
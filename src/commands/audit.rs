@@ -0,0 +1,106 @@
+// ABOUTME: Audit command - shows the destructive operations recorded by earlier runs
+// ABOUTME: Reads seren_replicator.audit_log on the target, or the local audit file with --local
+
+use crate::audit;
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::settings::RunSettings;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Print the most recent audit entries from the target's audit table
+///
+/// # Arguments
+///
+/// * `target_url` - PostgreSQL connection string for the target
+/// * `action` - Only show this action, e.g. `drop_database`
+/// * `limit` - Show at most this many entries
+///
+/// # Errors
+///
+/// Returns an error if `action` is not a known action, or the target cannot
+/// be reached or read.
+pub async fn audit(
+    target_url: &str,
+    action: Option<&str>,
+    limit: i64,
+    settings: &RunSettings,
+) -> Result<()> {
+    check_action(action)?;
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let target_client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let rows = audit::load_entries(&target_client, action, limit).await?;
+    if rows.is_empty() {
+        tracing::info!(
+            "No destructive operations recorded in {}",
+            audit::AUDIT_TABLE
+        );
+        return Ok(());
+    }
+    for row in rows.iter().rev() {
+        tracing::info!(
+            "{}  {:<22} {:<24} {}",
+            row.recorded_at,
+            row.action,
+            row.database.as_deref().unwrap_or("-"),
+            row.object
+        );
+        tracing::info!(
+            "    by {} (db user {}): {}",
+            row.os_user,
+            row.db_user,
+            row.invocation
+        );
+    }
+    Ok(())
+}
+
+/// Print the most recent entries from a local audit file
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed, or
+/// `action` is not a known action.
+pub fn audit_local(path: &Path, action: Option<&str>, limit: usize) -> Result<()> {
+    check_action(action)?;
+    let entries: Vec<_> = audit::read_file(path)?
+        .into_iter()
+        .filter(|entry| action.is_none_or(|action| entry.action.as_str() == action))
+        .collect();
+    if entries.is_empty() {
+        tracing::info!("No destructive operations recorded in {}", path.display());
+        return Ok(());
+    }
+    for entry in &entries[entries.len().saturating_sub(limit)..] {
+        tracing::info!(
+            "{}  {:<22} {:<24} {}",
+            entry.at,
+            entry.action.as_str(),
+            entry.database.as_deref().unwrap_or("-"),
+            entry.object
+        );
+        tracing::info!("    by {}: {}", entry.user, entry.invocation);
+    }
+    Ok(())
+}
+
+/// Reject an `--action` filter that matches no known action
+fn check_action(action: Option<&str>) -> Result<()> {
+    let Some(action) = action else {
+        return Ok(());
+    };
+    if audit::AuditAction::ALL.iter().any(|a| a.as_str() == action) {
+        return Ok(());
+    }
+    bail!(
+        "Unknown audit action '{}'. Known actions: {}",
+        action,
+        audit::AuditAction::ALL
+            .iter()
+            .map(|a| a.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
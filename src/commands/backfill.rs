@@ -0,0 +1,666 @@
+// ABOUTME: Backfill command - re-copies one table, or a predicate slice of it, while replication runs
+// ABOUTME: Each batch locks the source table, waits for the subscription to catch up, then upserts
+
+use crate::cancel::{self, CancellationToken};
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::run_lock::RunLock;
+use crate::settings::RunSettings;
+use crate::utils::{quote_ident, quote_literal, quote_qualified};
+use anyhow::{bail, Context, Result};
+use std::time::{Duration, Instant};
+use tokio_postgres::{Client, GenericClient};
+
+/// How long to wait for the SHARE lock on the source table before giving up
+const LOCK_TIMEOUT_SECS: u64 = 10;
+
+/// How often the subscription's replay position is polled
+const CATCH_UP_POLL: Duration = Duration::from_millis(200);
+
+/// Table named by `--table`: `database.table` (schema `public`) or `database.schema.table`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackfillTable {
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+}
+
+impl BackfillTable {
+    /// Parse a `--table` value, honoring double-quoted parts
+    ///
+    /// # Errors
+    ///
+    /// Returns an error unless the value has two or three parts.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let parts = crate::utils::split_qualified_name(spec)?;
+        match parts.as_slice() {
+            [database, table] => Ok(Self {
+                database: database.name.clone(),
+                schema: "public".to_string(),
+                table: table.name.clone(),
+            }),
+            [database, schema, table] => Ok(Self {
+                database: database.name.clone(),
+                schema: schema.name.clone(),
+                table: table.name.clone(),
+            }),
+            _ => bail!(
+                "Invalid --table '{}': expected database.table or database.schema.table",
+                spec
+            ),
+        }
+    }
+
+    fn qualified(&self) -> String {
+        quote_qualified(&self.schema, &self.table)
+    }
+}
+
+/// Insertable columns and primary key of the table, as quoted identifiers
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TableShape {
+    columns: Vec<String>,
+    primary_key: Vec<String>,
+}
+
+/// Rows written by a backfill
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillSummary {
+    pub batches: u64,
+    pub upserted: u64,
+    pub deleted: u64,
+}
+
+/// Re-copy a table, or the rows matching a predicate, while replication keeps running
+///
+/// The table is walked in primary-key order, `batch_size` rows at a time. For
+/// each batch:
+/// 1. The source table is locked in SHARE mode, which blocks writes to it (but
+///    not reads) until the batch is written
+/// 2. The batch is read, and the source's current WAL position recorded
+/// 3. The command waits until the subscription has replayed up to that
+///    position, so every change committed before the lock is on the target
+/// 4. Target rows in the batch's key range that match the predicate but are
+///    missing from the source are deleted, and the batch is upserted
+///    (`INSERT ... ON CONFLICT DO UPDATE`)
+/// 5. The lock is released; later changes replicate on top of the backfilled rows
+///
+/// Because no change to the table can commit between the read and the write,
+/// the subscription never replays a stale change over a backfilled row, and a
+/// backfilled row never overwrites a newer replicated one.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source (any database)
+/// * `target_url` - PostgreSQL connection string for the target (any database)
+/// * `table` - Table to backfill
+/// * `predicate` - Optional WHERE clause selecting the slice to re-copy; it is
+///   evaluated on both source and target
+/// * `batch_size` - Rows per batch, i.e. per source lock
+/// * `subscription_name` - Subscription replicating the table; optional when
+///   the target database has exactly one
+/// * `catch_up_timeout_secs` - How long each batch waits for the subscription
+/// * `run_lock` - The run lock the caller already holds on the target, if any
+/// * `settings` - Retry, pooler, audit, hook, and resource settings of this run
+/// * `cancel` - Stops between batches; batches already written stay written
+///
+/// # Returns
+///
+/// The number of batches, upserted rows, and deleted rows.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The table has no primary key on the source, or a different one on the target
+/// - No enabled subscription is found, or several are and none was named
+/// - The source lock is not granted within 10 seconds
+/// - The subscription does not catch up within `catch_up_timeout_secs`
+/// - `cancel` is cancelled (`SR-0034`)
+#[allow(clippy::too_many_arguments)]
+pub async fn backfill(
+    source_url: &str,
+    target_url: &str,
+    table: &BackfillTable,
+    predicate: Option<&str>,
+    batch_size: usize,
+    subscription_name: Option<&str>,
+    catch_up_timeout_secs: u64,
+    run_lock: Option<&RunLock>,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<BackfillSummary> {
+    if batch_size == 0 {
+        bail!("--batch-size must be at least 1");
+    }
+    crate::utils::validate_source_target_different(source_url, target_url)
+        .context("Source and target validation failed")?;
+    crate::protected::check(
+        target_url,
+        std::slice::from_ref(&table.database),
+        "backfill",
+    )?;
+    let _run_lock =
+        crate::run_lock::acquire_unless_held(run_lock, target_url, "backfill", settings).await?;
+
+    let source_db_url = crate::utils::replace_database_in_url(source_url, &table.database)?;
+    let target_db_url = crate::utils::replace_database_in_url(target_url, &table.database)?;
+    let target_db_url =
+        route_target(&target_db_url, TargetOperation::Copy, &settings.pooler).await?;
+
+    let mut source = connect(&source_db_url)
+        .await
+        .with_context(|| format!("Failed to connect to source database '{}'", table.database))?;
+    let mut target = connect(&target_db_url)
+        .await
+        .with_context(|| format!("Failed to connect to target database '{}'", table.database))?;
+
+    let subscription = find_subscription(&target, subscription_name).await?;
+    tracing::info!("Subscription: '{}'", subscription);
+    if !is_subscribed(&target, &subscription, table).await? {
+        tracing::warn!(
+            "⚠ {} is not replicated by subscription '{}'; the backfill is a one-off copy",
+            table.qualified(),
+            subscription
+        );
+    }
+
+    let shape = table_shape(&source, table).await?;
+    let target_shape = table_shape(&target, table).await?;
+    if shape.primary_key.is_empty() {
+        bail!(
+            "Table {} has no primary key on the source.\n\
+             Backfill pages and upserts by primary key; re-copy this table with init instead.",
+            table.qualified()
+        );
+    }
+    if target_shape.primary_key != shape.primary_key {
+        bail!(
+            "Table {} has primary key ({}) on the target but ({}) on the source.\n\
+             Align the target schema before backfilling.",
+            table.qualified(),
+            target_shape.primary_key.join(", "),
+            shape.primary_key.join(", ")
+        );
+    }
+
+    let predicate = predicate.unwrap_or("true");
+    let sql = BackfillSql::new(&table.qualified(), &shape, predicate, batch_size);
+    let timeout = Duration::from_secs(catch_up_timeout_secs);
+    let mut summary = BackfillSummary::default();
+    let mut lower: Option<String> = None;
+
+    tracing::info!(
+        "Backfilling {}.{} in batches of {} (where {})...",
+        table.database,
+        table.qualified(),
+        batch_size,
+        predicate
+    );
+    loop {
+        cancel::check(cancel)?;
+
+        let source_tx = source.transaction().await?;
+        source_tx
+            .batch_execute(&format!(
+                "SET LOCAL lock_timeout = '{}s'; LOCK TABLE {} IN SHARE MODE",
+                LOCK_TIMEOUT_SECS,
+                table.qualified()
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Could not lock {} on the source within {}s.\n\
+                     A long-running transaction is writing to it; retry once it finishes.",
+                    table.qualified(),
+                    LOCK_TIMEOUT_SECS
+                )
+            })?;
+
+        let rows = source_tx
+            .query(&sql.select_batch(lower.as_deref()), &[])
+            .await
+            .with_context(|| format!("Failed to read a batch of {}", table.qualified()))?;
+        let mut batch: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+        let upper: Option<String> = if rows.len() == batch_size {
+            rows.last().map(|row| row.get(1))
+        } else {
+            None
+        };
+        let lsn: String = source_tx
+            .query_one("SELECT pg_current_wal_lsn()::text", &[])
+            .await?
+            .get(0);
+
+        cancel::run(
+            cancel,
+            wait_for_replay(&source_tx, &subscription, &lsn, timeout),
+        )
+        .await?;
+
+        // Keys the target has in this range but the batch lacks: either deleted
+        // on the source, or no longer matching the predicate there
+        let candidates: Vec<String> = target
+            .query(
+                &sql.target_extra_keys(lower.as_deref(), upper.as_deref()),
+                &[&json_array(&batch)],
+            )
+            .await
+            .context("Failed to compare the batch with the target")?
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        let mut deleted = Vec::new();
+        if !candidates.is_empty() {
+            let candidates = json_array(&candidates);
+            let still_present: Vec<String> = source_tx
+                .query(&sql.select_by_key(), &[&candidates])
+                .await?
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+            deleted = source_tx
+                .query(&sql.missing_keys(), &[&candidates])
+                .await?
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+            batch.extend(still_present);
+        }
+
+        let target_tx = target.transaction().await?;
+        if !deleted.is_empty() {
+            target_tx
+                .execute(&sql.delete_keys(), &[&json_array(&deleted)])
+                .await
+                .with_context(|| format!("Failed to delete rows from {}", table.qualified()))?;
+        }
+        if !batch.is_empty() {
+            target_tx
+                .execute(&sql.upsert(), &[&json_array(&batch)])
+                .await
+                .with_context(|| format!("Failed to upsert rows into {}", table.qualified()))?;
+        }
+        target_tx.commit().await?;
+        source_tx.commit().await?;
+
+        summary.batches += 1;
+        summary.upserted += batch.len() as u64;
+        summary.deleted += deleted.len() as u64;
+        tracing::info!(
+            "  Batch {}: {} row(s) upserted, {} deleted",
+            summary.batches,
+            batch.len(),
+            deleted.len()
+        );
+
+        match upper {
+            Some(key) => lower = Some(key),
+            None => break,
+        }
+    }
+
+    tracing::info!(
+        "✓ Backfilled {}: {} row(s) upserted, {} deleted in {} batch(es)",
+        table.qualified(),
+        summary.upserted,
+        summary.deleted,
+        summary.batches
+    );
+    Ok(summary)
+}
+
+/// The subscription to wait on: the named one, or the target database's only one
+async fn find_subscription(client: &Client, name: Option<&str>) -> Result<String> {
+    let rows = client
+        .query(
+            "SELECT subname::text, subenabled
+             FROM pg_subscription
+             WHERE subdbid = (SELECT oid FROM pg_database WHERE datname = current_database())
+             ORDER BY subname",
+            &[],
+        )
+        .await
+        .context("Failed to list subscriptions on the target")?;
+    let subscriptions: Vec<(String, bool)> =
+        rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+    let (subscription, enabled) = match name {
+        Some(name) => match subscriptions.iter().find(|(sub, _)| sub == name) {
+            Some(found) => found.clone(),
+            None => bail!(
+                "Subscription '{}' does not exist in the target database.\n\
+                 Run sync first, or pass the name shown by status.",
+                name
+            ),
+        },
+        None => match subscriptions.as_slice() {
+            [] => bail!(
+                "The target database has no subscription.\n\
+                 Run sync first; to copy a table without replication, use init."
+            ),
+            [only] => only.clone(),
+            _ => bail!(
+                "The target database has several subscriptions ({}).\n\
+                 Name the one replicating this table with --subscription.",
+                subscriptions
+                    .iter()
+                    .map(|(sub, _)| sub.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        },
+    };
+    if !enabled {
+        bail!(
+            "Subscription '{}' is disabled, so the backfill cannot wait for it to catch up.\n\
+             Enable it with ALTER SUBSCRIPTION {} ENABLE.",
+            subscription,
+            quote_ident(&subscription)
+        );
+    }
+    Ok(subscription)
+}
+
+/// Whether the table is part of the subscription on the target
+async fn is_subscribed(client: &Client, subscription: &str, table: &BackfillTable) -> Result<bool> {
+    let row = client
+        .query_one(
+            "SELECT EXISTS (
+                 SELECT 1
+                 FROM pg_subscription_rel r
+                 JOIN pg_subscription s ON s.oid = r.srsubid
+                 JOIN pg_class c ON c.oid = r.srrelid
+                 JOIN pg_namespace n ON n.oid = c.relnamespace
+                 WHERE s.subname = $1 AND n.nspname = $2 AND c.relname = $3
+             )",
+            &[&subscription, &table.schema, &table.table],
+        )
+        .await
+        .context("Failed to check the subscription's tables")?;
+    Ok(row.get(0))
+}
+
+/// Insertable columns (no generated or dropped ones) and primary key columns
+async fn table_shape(client: &Client, table: &BackfillTable) -> Result<TableShape> {
+    let relation = table.qualified();
+    let columns: Vec<String> = client
+        .query(
+            "SELECT attname::text
+             FROM pg_attribute
+             WHERE attrelid = $1::text::regclass
+               AND attnum > 0 AND NOT attisdropped AND attgenerated = ''
+             ORDER BY attnum",
+            &[&relation],
+        )
+        .await
+        .with_context(|| format!("Failed to get columns of {}", relation))?
+        .iter()
+        .map(|row| quote_ident(row.get(0)))
+        .collect();
+    if columns.is_empty() {
+        bail!("Table {} does not exist or has no columns", relation);
+    }
+    let primary_key = client
+        .query(
+            "SELECT a.attname::text
+             FROM pg_index i
+             JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+             WHERE i.indrelid = $1::text::regclass AND i.indisprimary
+             ORDER BY array_position(i.indkey, a.attnum)",
+            &[&relation],
+        )
+        .await
+        .with_context(|| format!("Failed to get primary key of {}", relation))?
+        .iter()
+        .map(|row| quote_ident(row.get(0)))
+        .collect();
+    Ok(TableShape {
+        columns,
+        primary_key,
+    })
+}
+
+/// Wait until the subscription has replayed the source's WAL up to `lsn`
+async fn wait_for_replay(
+    client: &impl GenericClient,
+    subscription: &str,
+    lsn: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let started = Instant::now();
+    loop {
+        let caught_up: bool = client
+            .query_one(
+                "SELECT coalesce(bool_or(replay_lsn >= $1::text::pg_lsn), false)
+                 FROM pg_stat_replication
+                 WHERE application_name = $2",
+                &[&lsn, &subscription],
+            )
+            .await
+            .context("Failed to query pg_stat_replication on the source")?
+            .get(0);
+        if caught_up {
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            bail!(
+                "Subscription '{}' did not replay up to {} within {}s.\n\
+                 Check status for lag or errors, or raise --catch-up-timeout.",
+                subscription,
+                lsn,
+                timeout.as_secs()
+            );
+        }
+        tokio::time::sleep(CATCH_UP_POLL).await;
+    }
+}
+
+/// `[row, row, ...]` from rows already serialized as JSON
+fn json_array(rows: &[String]) -> String {
+    format!("[{}]", rows.join(","))
+}
+
+/// SQL for one table; rows travel between servers as JSON and are rebuilt with
+/// `json_populate_recordset`, which parses each value with its column type's input function
+struct BackfillSql {
+    relation: String,
+    columns: String,
+    primary_key: String,
+    key_object: String,
+    conflict_action: String,
+    predicate: String,
+    batch_size: usize,
+}
+
+impl BackfillSql {
+    fn new(relation: &str, shape: &TableShape, predicate: &str, batch_size: usize) -> Self {
+        let key_object = shape
+            .primary_key
+            .iter()
+            .map(|column| format!("{}, t.{}", quote_literal(&unquote(column)), column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let updates: Vec<String> = shape
+            .columns
+            .iter()
+            .filter(|column| !shape.primary_key.contains(column))
+            .map(|column| format!("{} = EXCLUDED.{}", column, column))
+            .collect();
+        let conflict_action = if updates.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", updates.join(", "))
+        };
+        Self {
+            relation: relation.to_string(),
+            columns: shape.columns.join(", "),
+            primary_key: shape.primary_key.join(", "),
+            key_object: format!("json_build_object({})", key_object),
+            conflict_action,
+            predicate: predicate.to_string(),
+            batch_size,
+        }
+    }
+
+    /// `AND (pk) > lower AND (pk) <= upper`, with keys given as JSON objects
+    fn key_range(&self, lower: Option<&str>, upper: Option<&str>) -> String {
+        let bound = |op: &str, key: &str| {
+            format!(
+                " AND ({pk}) {op} (SELECT {pk} FROM json_populate_record(NULL::{rel}, {key}::json))",
+                pk = self.primary_key,
+                op = op,
+                rel = self.relation,
+                key = quote_literal(key)
+            )
+        };
+        let mut range = String::new();
+        if let Some(lower) = lower {
+            range.push_str(&bound(">", lower));
+        }
+        if let Some(upper) = upper {
+            range.push_str(&bound("<=", upper));
+        }
+        range
+    }
+
+    /// Source rows after `lower`, with each row's key, in key order
+    fn select_batch(&self, lower: Option<&str>) -> String {
+        format!(
+            "SELECT row_to_json(t)::text, {key}::text FROM {rel} t WHERE ({pred}){range} \
+             ORDER BY {pk} LIMIT {limit}",
+            key = self.key_object,
+            rel = self.relation,
+            pred = self.predicate,
+            range = self.key_range(lower, None),
+            pk = self.primary_key,
+            limit = self.batch_size
+        )
+    }
+
+    /// Target keys in the range matching the predicate that are not in the batch ($1)
+    fn target_extra_keys(&self, lower: Option<&str>, upper: Option<&str>) -> String {
+        format!(
+            "SELECT {key}::text FROM {rel} t WHERE ({pred}){range} \
+             AND ({pk}) NOT IN (SELECT {pk} FROM json_populate_recordset(NULL::{rel}, $1::text::json))",
+            key = self.key_object,
+            rel = self.relation,
+            pred = self.predicate,
+            range = self.key_range(lower, upper),
+            pk = self.primary_key
+        )
+    }
+
+    /// Source rows with the keys in $1
+    fn select_by_key(&self) -> String {
+        format!(
+            "SELECT row_to_json(t)::text FROM {rel} t \
+             WHERE ({pk}) IN (SELECT {pk} FROM json_populate_recordset(NULL::{rel}, $1::text::json))",
+            rel = self.relation,
+            pk = self.primary_key
+        )
+    }
+
+    /// Keys in $1 that no source row has
+    fn missing_keys(&self) -> String {
+        format!(
+            "SELECT k.value::text FROM json_array_elements($1::text::json) AS k(value) \
+             WHERE NOT EXISTS (SELECT 1 FROM {rel} t \
+             WHERE ({pk}) = (SELECT {pk} FROM json_populate_record(NULL::{rel}, k.value)))",
+            rel = self.relation,
+            pk = self.primary_key
+        )
+    }
+
+    /// Delete target rows with the keys in $1
+    fn delete_keys(&self) -> String {
+        format!(
+            "DELETE FROM {rel} WHERE ({pk}) IN \
+             (SELECT {pk} FROM json_populate_recordset(NULL::{rel}, $1::text::json))",
+            rel = self.relation,
+            pk = self.primary_key
+        )
+    }
+
+    /// Insert or overwrite the target rows in $1
+    fn upsert(&self) -> String {
+        format!(
+            "INSERT INTO {rel} ({cols}) OVERRIDING SYSTEM VALUE \
+             SELECT {cols} FROM json_populate_recordset(NULL::{rel}, $1::text::json) \
+             ON CONFLICT ({pk}) {action}",
+            rel = self.relation,
+            cols = self.columns,
+            pk = self.primary_key,
+            action = self.conflict_action
+        )
+    }
+}
+
+/// Catalog name of a quoted identifier
+fn unquote(identifier: &str) -> String {
+    identifier
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .map(|name| name.replace("\"\"", "\""))
+        .unwrap_or_else(|| identifier.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orders_sql() -> BackfillSql {
+        let shape = TableShape {
+            columns: vec!["\"id\"".into(), "\"status\"".into()],
+            primary_key: vec!["\"id\"".into()],
+        };
+        BackfillSql::new("\"public\".\"orders\"", &shape, "status = 'open'", 500)
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let table = BackfillTable::parse("shop.orders").unwrap();
+        assert_eq!(table.schema, "public");
+        let table = BackfillTable::parse(r#"shop.sales."Order.Items""#).unwrap();
+        assert_eq!(table.database, "shop");
+        assert_eq!(table.schema, "sales");
+        assert_eq!(table.table, "Order.Items");
+        assert!(BackfillTable::parse("orders").is_err());
+        assert!(BackfillTable::parse("a.b.c.d").is_err());
+    }
+
+    #[test]
+    fn test_batch_sql() {
+        let sql = orders_sql();
+        let first = sql.select_batch(None);
+        assert!(first.contains("WHERE (status = 'open') ORDER BY \"id\" LIMIT 500"));
+        assert!(first.contains("json_build_object('id', t.\"id\")"));
+
+        let next = sql.select_batch(Some(r#"{"id": 7}"#));
+        assert!(next.contains(
+            "AND (\"id\") > (SELECT \"id\" FROM json_populate_record(NULL::\"public\".\"orders\", '{\"id\": 7}'::json))"
+        ));
+
+        let extra = sql.target_extra_keys(Some(r#"{"id": 7}"#), Some(r#"{"id": 9}"#));
+        assert!(extra.contains("> (SELECT"));
+        assert!(extra.contains("<= (SELECT"));
+        assert!(extra.contains("NOT IN"));
+    }
+
+    #[test]
+    fn test_upsert_sql() {
+        let sql = orders_sql();
+        assert!(sql
+            .upsert()
+            .ends_with("ON CONFLICT (\"id\") DO UPDATE SET \"status\" = EXCLUDED.\"status\""));
+
+        let keys_only = TableShape {
+            columns: vec!["\"a\"".into(), "\"b\"".into()],
+            primary_key: vec!["\"a\"".into(), "\"b\"".into()],
+        };
+        let sql = BackfillSql::new("\"public\".\"links\"", &keys_only, "true", 10);
+        assert!(sql
+            .upsert()
+            .ends_with("ON CONFLICT (\"a\", \"b\") DO NOTHING"));
+        assert_eq!(unquote("\"Odd\"\"Name\""), "Odd\"Name");
+    }
+}
@@ -0,0 +1,105 @@
+// ABOUTME: Bookmark command - records the source's current WAL position under a label
+// ABOUTME: `status --since-bookmark` later shows whether replication has passed it
+
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::replication::bookmarks;
+use crate::settings::RunSettings;
+use anyhow::{Context, Result};
+
+/// Record the source's current WAL position as a named bookmark on the target
+///
+/// Every transaction committed on the source before this call is at or before
+/// the recorded LSN, so once a subscription's replay position passes the
+/// bookmark, all of those writes are on the target. Typical use: bookmark
+/// right after a deploy, then check `status --since-bookmark` before cutover.
+///
+/// The LSN is server-wide, so one bookmark covers every replicated database.
+/// Bookmarks are stored in `seren_replicator.bookmarks` in the target URL's
+/// database, next to the status history.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source
+/// * `target_url` - PostgreSQL connection string for the target
+/// * `name` - Bookmark label, e.g. `before-deploy-1402`
+/// * `force` - Move an existing bookmark of the same name to the current position
+///
+/// # Returns
+///
+/// The recorded bookmark.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The name is empty, too long, or already taken (without `force`)
+/// - Cannot connect to source or target
+/// - Cannot create or write the bookmark table on the target
+pub async fn bookmark(
+    source_url: &str,
+    target_url: &str,
+    name: &str,
+    force: bool,
+    settings: &RunSettings,
+) -> Result<bookmarks::Bookmark> {
+    bookmarks::validate_name(name)?;
+    crate::utils::validate_source_target_different(source_url, target_url)
+        .context("Source and target validation failed")?;
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+
+    let source_client = connect(source_url)
+        .await
+        .context("Failed to connect to source database")?;
+    let target_client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+
+    bookmarks::ensure_bookmark_table(&target_client).await?;
+    let lsn: String = source_client
+        .query_one("SELECT pg_current_wal_lsn()::text", &[])
+        .await
+        .context("Failed to read current WAL position on source")?
+        .get(0);
+    bookmarks::save_bookmark(&target_client, name, &lsn, force).await?;
+
+    let bookmark = bookmarks::get_bookmark(&target_client, name).await?;
+    tracing::info!(
+        "✓ Bookmark '{}' recorded at source LSN {} ({} UTC)",
+        bookmark.name,
+        bookmark.lsn,
+        bookmark.created_at
+    );
+    tracing::info!(
+        "  Check progress with: seren-replicator status --since-bookmark {:?}",
+        bookmark.name
+    );
+    Ok(bookmark)
+}
+
+/// Print the bookmarks recorded on the target, oldest first
+///
+/// # Errors
+///
+/// Returns an error if the target cannot be reached or read.
+pub async fn list_bookmarks(target_url: &str, settings: &RunSettings) -> Result<()> {
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let target_client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let recorded = bookmarks::list_bookmarks(&target_client).await?;
+    if recorded.is_empty() {
+        tracing::info!("No bookmarks recorded on the target");
+        tracing::info!("  Create one with: seren-replicator bookmark --name <label>");
+        return Ok(());
+    }
+    tracing::info!("{:<32} {:<20} Recorded (UTC)", "Name", "Source LSN");
+    for bookmark in recorded {
+        tracing::info!(
+            "{:<32} {:<20} {}",
+            bookmark.name,
+            bookmark.lsn,
+            bookmark.created_at
+        );
+    }
+    Ok(())
+}
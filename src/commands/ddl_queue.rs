@@ -0,0 +1,384 @@
+// ABOUTME: DDL-queue command - watches the source for schema changes and queues the target DDL for approval
+// ABOUTME: Lists, approves (applies), and rejects queued changes; safe changes can be applied automatically
+
+use crate::cancel::{self, CancellationToken};
+use crate::events;
+use crate::filters::ReplicationFilter;
+use crate::hooks::{self, HookContext, HookStage};
+use crate::migration;
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::replication::ddl_queue::{self, DdlStatus};
+use crate::settings::RunSettings;
+use crate::utils::replace_database_in_url;
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// How `ddl-queue --watch` paces itself and what it applies unattended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// Time between schema comparisons
+    pub interval: Duration,
+    /// Apply safe changes (nullable ADD COLUMN without a default, DROP NOT NULL)
+    /// as soon as they are found instead of queueing them
+    pub auto_apply_safe: bool,
+    /// Stop after this many cycles; `None` runs until interrupted
+    pub cycles: Option<u64>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            auto_apply_safe: false,
+            cycles: None,
+        }
+    }
+}
+
+/// Watch the source schema and queue the DDL that would bring the target in line
+///
+/// Each cycle compares the columns of every replicated table on source and
+/// target. A difference not yet queued is stored in
+/// `seren_replicator.ddl_queue` in the target URL's database with the
+/// statement to run on the target, then:
+/// - With `auto_apply_safe`, safe changes are applied immediately
+/// - Otherwise the operator is notified (log, `ddl-pending` hooks) and the
+///   change waits for `ddl-queue --approve <id>` or `--reject <id>`
+///
+/// Logical replication does not carry DDL, so until a column change is
+/// applied, rows using it stop replicating; approve promptly.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source
+/// * `target_url` - PostgreSQL connection string for the target; its database holds the queue
+/// * `filter` - Databases and tables to watch
+/// * `options` - Pacing and auto-apply
+/// * `cancel` - Stops after the current cycle's queries are abandoned
+///
+/// # Errors
+///
+/// Returns an error if the source or target cannot be reached at startup or
+/// the queue table cannot be created. Failures during a cycle are logged and
+/// retried next cycle.
+pub async fn watch_ddl(
+    source_url: &str,
+    target_url: &str,
+    mut filter: ReplicationFilter,
+    options: &WatchOptions,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    crate::utils::validate_source_target_different(source_url, target_url)
+        .context("Source and target validation failed")?;
+    if options.auto_apply_safe {
+        crate::protected::check(target_url, &[], "ddl-queue watch --auto-apply-safe")?;
+    }
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+
+    let source_client = connect(source_url)
+        .await
+        .context("Failed to connect to source database")?;
+    let databases: Vec<String> = migration::list_databases(&source_client)
+        .await
+        .context("Failed to list databases on source")?
+        .into_iter()
+        .map(|db| db.name)
+        .filter(|name| filter.should_replicate_database(name))
+        .collect();
+    filter
+        .resolve_table_schemas_for(source_url, &databases, settings)
+        .await?;
+
+    let queue = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    ddl_queue::ensure_queue_table(&queue).await?;
+
+    tracing::info!(
+        "Watching {} database(s) for schema changes every {}s{}; press Ctrl-C to stop",
+        databases.len(),
+        options.interval.as_secs(),
+        if options.auto_apply_safe {
+            " (applying safe changes automatically)"
+        } else {
+            ""
+        }
+    );
+    tracing::info!("");
+
+    let mut cycle = 0u64;
+    loop {
+        cycle += 1;
+        let result = cancel::run(
+            cancel,
+            run_cycle(
+                source_url, target_url, &databases, &filter, options, &queue, settings,
+            ),
+        )
+        .await;
+        match result {
+            Ok(0) => tracing::debug!("Cycle {}: no new schema changes", cycle),
+            Ok(found) => tracing::info!("Cycle {}: {} new schema change(s)", cycle, found),
+            Err(_) if cancel.is_cancelled() => break,
+            Err(e) => tracing::warn!("⚠ Cycle {} failed: {:#}", cycle, e),
+        }
+
+        if options.cycles.is_some_and(|limit| cycle >= limit) {
+            break;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(options.interval) => {}
+        }
+    }
+
+    let pending = ddl_queue::list(&queue, Some(DdlStatus::Pending)).await?;
+    tracing::info!("");
+    if pending.is_empty() {
+        tracing::info!("✓ No schema changes awaiting approval");
+    } else {
+        tracing::warn!(
+            "⚠ {} schema change(s) awaiting approval; review them with: seren-replicator ddl-queue --target <url>",
+            pending.len()
+        );
+    }
+    Ok(())
+}
+
+/// Compare every database once and queue (or apply) what is new
+///
+/// # Returns
+///
+/// The number of newly found changes.
+async fn run_cycle(
+    source_url: &str,
+    target_url: &str,
+    databases: &[String],
+    filter: &ReplicationFilter,
+    options: &WatchOptions,
+    queue: &Client,
+    settings: &RunSettings,
+) -> Result<usize> {
+    let mut found = 0;
+    for database in databases {
+        let source = connect(&replace_database_in_url(source_url, database)?)
+            .await
+            .with_context(|| format!("Failed to connect to source database '{}'", database))?;
+        let target_db_url = replace_database_in_url(target_url, database)?;
+        let target = connect(&target_db_url)
+            .await
+            .with_context(|| format!("Failed to connect to target database '{}'", database))?;
+
+        let keep = |snapshot: ddl_queue::SchemaSnapshot| -> ddl_queue::SchemaSnapshot {
+            snapshot
+                .into_iter()
+                .filter(|((schema, table), _)| {
+                    filter.should_replicate_schema_table(database, schema, table)
+                        && !crate::replication::heartbeat::is_heartbeat_table(schema, table)
+                })
+                .collect()
+        };
+        let source_schema = keep(ddl_queue::snapshot(&source).await?);
+        let target_schema = keep(ddl_queue::snapshot(&target).await?);
+
+        // A protected database still gets its changes queued, just never applied
+        let auto_apply_allowed = !options.auto_apply_safe
+            || match crate::protected::check(
+                target_url,
+                std::slice::from_ref(database),
+                "ddl-queue watch --auto-apply-safe",
+            ) {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("  ⚠ {}: changes left for approval: {:#}", database, e);
+                    false
+                }
+            };
+        let mut queued = 0;
+        for change in ddl_queue::diff(&source_schema, &target_schema) {
+            let Some(id) = ddl_queue::enqueue(queue, database, &change).await? else {
+                continue;
+            };
+            found += 1;
+            if change.safe && options.auto_apply_safe && auto_apply_allowed {
+                let result = ddl_queue::apply(
+                    &target,
+                    change.kind.as_str(),
+                    &change.object,
+                    &change.statement,
+                    &settings.audit,
+                )
+                .await;
+                match result {
+                    Ok(()) => {
+                        ddl_queue::set_status(queue, id, DdlStatus::Applied, None).await?;
+                        tracing::info!(
+                            "  ✓ #{} {}.{}: applied {}",
+                            id,
+                            database,
+                            change.object,
+                            change.statement
+                        );
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "  ⚠ #{} {}.{}: automatic apply failed, left for approval: {:#}",
+                            id,
+                            database,
+                            change.object,
+                            e
+                        );
+                    }
+                }
+            }
+            let message = format!(
+                "Schema change #{} in {}.{} awaits approval: {}",
+                id, database, change.object, change.statement
+            );
+            tracing::warn!("  ⚠ {}", message);
+            events::warning(&message);
+            queued += 1;
+        }
+
+        if queued > 0 {
+            hooks::run_hooks(
+                HookStage::DdlPending,
+                HookContext {
+                    source_url,
+                    target_url,
+                    database: Some(database),
+                },
+                settings,
+            )
+            .await?;
+        }
+    }
+    Ok(found)
+}
+
+/// Print the queued schema changes recorded in the target URL's database
+///
+/// # Errors
+///
+/// Returns an error if the target cannot be reached or read.
+pub async fn list_ddl_queue(
+    target_url: &str,
+    status: Option<DdlStatus>,
+    settings: &RunSettings,
+) -> Result<()> {
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let entries = ddl_queue::list(&client, status).await?;
+    if entries.is_empty() {
+        tracing::info!("No queued schema changes");
+        return Ok(());
+    }
+    tracing::info!(
+        "{:<6} {:<9} {:<20} {:<40} Statement",
+        "Id",
+        "Status",
+        "Detected (UTC)",
+        "Object"
+    );
+    for entry in entries {
+        tracing::info!(
+            "{:<6} {:<9} {:<20} {:<40} {}{}",
+            entry.id,
+            entry.status,
+            entry.detected_at,
+            format!("{}.{}", entry.database, entry.object),
+            entry.statement.replace('\n', " "),
+            if entry.safe { "  [safe]" } else { "" }
+        );
+        if let Some(error) = &entry.error {
+            tracing::info!("       error: {}", error);
+        }
+    }
+    Ok(())
+}
+
+/// Apply a queued schema change to its target database
+///
+/// Pending and failed entries can be approved; a failed apply marks the entry
+/// failed with the error so it can be fixed and approved again.
+///
+/// # Errors
+///
+/// Returns an error if no entry has this id, it was already applied or
+/// rejected, or its statement fails on the target.
+pub async fn approve_ddl(target_url: &str, id: i64, settings: &RunSettings) -> Result<()> {
+    crate::protected::check(target_url, &[], "ddl-queue approve")?;
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let queue = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let entry = ddl_queue::get(&queue, id).await?;
+    if matches!(entry.status, DdlStatus::Applied | DdlStatus::Rejected) {
+        bail!(
+            "Queued DDL #{} was already {}; only pending or failed changes can be approved.",
+            id,
+            entry.status
+        );
+    }
+    crate::protected::check(
+        target_url,
+        std::slice::from_ref(&entry.database),
+        "ddl-queue approve",
+    )?;
+
+    let target = connect(&replace_database_in_url(target_url, &entry.database)?)
+        .await
+        .with_context(|| format!("Failed to connect to target database '{}'", entry.database))?;
+    tracing::info!(
+        "Applying #{} to '{}': {}",
+        id,
+        entry.database,
+        entry.statement
+    );
+    if let Err(e) = ddl_queue::apply(
+        &target,
+        &entry.kind,
+        &entry.object,
+        &entry.statement,
+        &settings.audit,
+    )
+    .await
+    {
+        ddl_queue::set_status(&queue, id, DdlStatus::Failed, Some(&format!("{:#}", e))).await?;
+        return Err(e);
+    }
+    ddl_queue::set_status(&queue, id, DdlStatus::Applied, None).await?;
+    tracing::info!("✓ Applied #{} to {}.{}", id, entry.database, entry.object);
+    Ok(())
+}
+
+/// Mark a queued schema change as rejected so it is neither applied nor queued again
+///
+/// # Errors
+///
+/// Returns an error if no entry has this id or it was already applied.
+pub async fn reject_ddl(target_url: &str, id: i64, settings: &RunSettings) -> Result<()> {
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    let queue = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let entry = ddl_queue::get(&queue, id).await?;
+    if entry.status == DdlStatus::Applied {
+        bail!(
+            "Queued DDL #{} was already applied; undo it on the target by hand if needed.",
+            id
+        );
+    }
+    ddl_queue::set_status(&queue, id, DdlStatus::Rejected, None).await?;
+    tracing::info!("✓ Rejected #{} ({}.{})", id, entry.database, entry.object);
+    tracing::info!(
+        "  Rows using the change on the source will not replicate until the schemas match"
+    );
+    Ok(())
+}
@@ -0,0 +1,255 @@
+// ABOUTME: Export command - writes a consistent snapshot of selected source tables to files for analytics
+// ABOUTME: Streams each table over COPY into one Parquet file per table, honoring filters and table rules
+
+use crate::cancel::{self, CancellationToken};
+use crate::events::{self, Phase, TableEvent, TableOutcome};
+use crate::filters::ReplicationFilter;
+use crate::migration::parquet;
+use crate::settings::RunSettings;
+use crate::table_rules::TableRuleKind;
+use crate::utils::quote_qualified;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// File format written by `export` (`--format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Parquet,
+}
+
+impl ExportFormat {
+    /// File extension of the exported tables
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "parquet" => Ok(ExportFormat::Parquet),
+            "duckdb" => bail!(
+                "export does not write DuckDB database files.\n\
+                 Export with --format parquet and load the files into DuckDB, e.g. \
+                 CREATE TABLE users AS FROM 'export/app/public.users.parquet';"
+            ),
+            _ => bail!("Unknown export format '{}'. Expected: parquet", s),
+        }
+    }
+}
+
+/// Export selected source tables to `output_dir`
+///
+/// Each database is read in one repeatable-read, read-only transaction, so
+/// its tables form a consistent snapshot. Every table becomes
+/// `<output_dir>/<database>/<schema>.<table>.parquet`. Table rules apply as
+/// they do for init: WHERE and time filters narrow the rows, transforms
+/// replace the query, and schema-only tables are skipped.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source
+/// * `output_dir` - Directory the files are written under
+/// * `format` - File format
+/// * `filter` - Databases, tables, and table rules
+/// * `cancel` - Stops the export between chunks
+///
+/// # Errors
+///
+/// Returns an error if the source is not PostgreSQL, no database matches the
+/// filter, or a table cannot be exported.
+pub async fn export(
+    source_url: &str,
+    output_dir: &Path,
+    format: ExportFormat,
+    filter: &ReplicationFilter,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    if crate::detect_source_type(source_url)? != crate::SourceType::PostgreSQL {
+        bail!(
+            "export reads PostgreSQL sources only.\n\
+             Replicate SQLite, MySQL, or MongoDB sources into PostgreSQL with init first, then export from there."
+        );
+    }
+    let client = crate::postgres::connect_with_retry(source_url, settings)
+        .await
+        .context("Failed to connect to source database")?;
+    let databases: Vec<String> = crate::migration::list_databases(&client)
+        .await?
+        .into_iter()
+        .map(|database| database.name)
+        .filter(|name| filter.should_replicate_database(name))
+        .collect();
+    if databases.is_empty() {
+        bail!(
+            "No databases matched the filter on the source.\n\
+             Check --include-databases/--exclude-databases against `seren-replicator list --source ...`."
+        );
+    }
+
+    let mut exported = 0usize;
+    let mut total_rows = 0u64;
+    for database in &databases {
+        cancel::check(cancel)?;
+        let (tables, rows) = export_database(
+            source_url, output_dir, format, database, filter, settings, cancel,
+        )
+        .await?;
+        exported += tables;
+        total_rows += rows;
+    }
+    tracing::info!(
+        "✓ Exported {} table(s), {} row(s) from {} database(s) to {}",
+        exported,
+        total_rows,
+        databases.len(),
+        output_dir.display()
+    );
+    Ok(())
+}
+
+/// Export one database; returns the tables and rows written
+async fn export_database(
+    source_url: &str,
+    output_dir: &Path,
+    format: ExportFormat,
+    database: &str,
+    filter: &ReplicationFilter,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<(usize, u64)> {
+    tracing::info!("Exporting database '{}'...", database);
+    let db_url = crate::utils::replace_database_in_url(source_url, database)?;
+    let client = crate::postgres::connect_with_retry(&db_url, settings)
+        .await
+        .with_context(|| format!("Failed to connect to source database '{}'", database))?;
+    if !filter.table_rules().partition_rules(database).is_empty() {
+        tracing::warn!(
+            "⚠ Partition rules for '{}' shape PostgreSQL targets and are ignored by export",
+            database
+        );
+    }
+
+    client
+        .batch_execute(&format!(
+            "{}; BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY",
+            parquet::SESSION_SETTINGS
+        ))
+        .await
+        .context("Failed to open the export snapshot")?;
+    let inventory = crate::inventory::postgres_database(&client, database, filter).await?;
+    let directory = output_dir.join(database);
+    std::fs::create_dir_all(&directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    let mut exported = 0;
+    let mut total_rows = 0;
+    for table in &inventory.tables {
+        let Some(schema) = table.schema.as_deref() else {
+            continue;
+        };
+        cancel::check(cancel)?;
+        let qualified = quote_qualified(schema, &table.name);
+        let select = match filter
+            .table_rules()
+            .rule_for_table(database, schema, &table.name)
+        {
+            Some(TableRuleKind::SchemaOnly) => {
+                tracing::info!("  ↷ Skipping {}.{}: schema-only table", schema, table.name);
+                continue;
+            }
+            Some(TableRuleKind::Transform(select)) => select,
+            Some(TableRuleKind::Predicate(predicate)) => {
+                format!("SELECT * FROM {} WHERE {}", qualified, predicate)
+            }
+            None => format!("SELECT * FROM {}", qualified),
+        };
+
+        let path = directory.join(file_name(schema, &table.name, format));
+        let event = TableEvent::new(Phase::Copy, database, schema, &table.name);
+        events::table_started(&event);
+        let started = std::time::Instant::now();
+        let result = async {
+            let columns = parquet::query_columns(&client, &select).await?;
+            parquet::export_table(&client, &select, &columns, &path, cancel).await
+        }
+        .await
+        .with_context(|| format!("Failed to export {}.{}", schema, table.name));
+        match result {
+            Ok(rows) => {
+                events::table_completed(&event, &TableOutcome::Success { rows: Some(rows) });
+                tracing::info!(
+                    "  ✓ {}.{}: {} row(s) to {} in {}",
+                    schema,
+                    table.name,
+                    rows,
+                    path.display(),
+                    crate::migration::format_duration(started.elapsed())
+                );
+                exported += 1;
+                total_rows += rows;
+            }
+            Err(e) => {
+                events::table_completed(
+                    &event,
+                    &TableOutcome::Failed {
+                        error: format!("{:#}", e),
+                    },
+                );
+                return Err(e);
+            }
+        }
+    }
+
+    client
+        .batch_execute("COMMIT")
+        .await
+        .context("Failed to close the export snapshot")?;
+    Ok((exported, total_rows))
+}
+
+/// `<schema>.<table>.<extension>`, with path separators in names replaced
+fn file_name(schema: &str, table: &str, format: ExportFormat) -> PathBuf {
+    let safe = |name: &str| name.replace(['/', '\\', '\0'], "_");
+    PathBuf::from(format!(
+        "{}.{}.{}",
+        safe(schema),
+        safe(table),
+        format.extension()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_format_parse() {
+        assert_eq!(
+            "Parquet".parse::<ExportFormat>().unwrap(),
+            ExportFormat::Parquet
+        );
+        let err = "duckdb".parse::<ExportFormat>().unwrap_err().to_string();
+        assert!(err.contains("--format parquet"));
+        assert!("csv".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_file_name_replaces_path_separators() {
+        assert_eq!(
+            file_name("public", "users", ExportFormat::Parquet),
+            PathBuf::from("public.users.parquet")
+        );
+        assert_eq!(
+            file_name("Sales", "a/b\\c", ExportFormat::Parquet),
+            PathBuf::from("Sales.a_b_c.parquet")
+        );
+    }
+}
@@ -0,0 +1,170 @@
+// ABOUTME: Inspect command - prints a structured inventory of a source or target as text or JSON
+// ABOUTME: Databases, schemas, tables, row estimates, sizes, primary keys, replica identity, and unsupported features
+
+use crate::filters::ReplicationFilter;
+use crate::inventory::{self, DatabaseInventory, Inventory};
+use crate::migration;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::str::FromStr;
+
+/// How `inspect` prints the inventory (`--format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InspectFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for InspectFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(InspectFormat::Text),
+            "json" => Ok(InspectFormat::Json),
+            _ => bail!(
+                "Unknown inspect format '{}'. Expected one of: text, json",
+                s
+            ),
+        }
+    }
+}
+
+/// Print the inventory of a source or target
+///
+/// The inventory is the one `list` and `validate` read (see
+/// [`inventory::collect`]). Text goes to the log like every other command's
+/// output; JSON goes to `output`, or to stdout when there is none.
+///
+/// # Arguments
+///
+/// * `url` - PostgreSQL, MySQL, or MongoDB URL, or SQLite file path
+/// * `filter` - Databases and tables to include
+/// * `format` - Text or JSON
+/// * `output` - File to write the JSON inventory to, whatever the format
+///
+/// # Errors
+///
+/// Returns an error if the endpoint cannot be reached, its catalog cannot be
+/// read, or `output` cannot be written.
+pub async fn inspect(
+    url: &str,
+    filter: &ReplicationFilter,
+    format: InspectFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let inventory = inventory::collect(url, filter).await?;
+    if let Some(path) = output {
+        let json = serde_json::to_string_pretty(&inventory)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write inventory to {}", path.display()))?;
+        tracing::info!("✓ Inventory written to {}", path.display());
+    }
+    match format {
+        InspectFormat::Json if output.is_none() => println!(
+            "{}",
+            serde_json::to_string_pretty(&inventory).context("Failed to serialize inventory")?
+        ),
+        InspectFormat::Json => {}
+        InspectFormat::Text => print_text(&inventory),
+    }
+    Ok(())
+}
+
+fn print_text(inventory: &Inventory) {
+    tracing::info!(
+        "{} {}",
+        inventory.source_type,
+        inventory
+            .server_version
+            .as_deref()
+            .unwrap_or("(version unknown)")
+    );
+    if inventory.databases.is_empty() {
+        tracing::info!("No databases matched");
+        return;
+    }
+    for database in &inventory.databases {
+        print_database(database);
+    }
+}
+
+fn print_database(database: &DatabaseInventory) {
+    let rows: u64 = database.tables.iter().filter_map(|table| table.rows).sum();
+    tracing::info!(
+        "Database '{}': {}, {} table(s), {}{} row(s)",
+        database.name,
+        database.size_bytes.map_or_else(
+            || "size unknown".to_string(),
+            |size| migration::format_bytes(size as i64)
+        ),
+        database.tables.len(),
+        if database.exact_counts { "" } else { "~" },
+        rows
+    );
+    if !database.tables.is_empty() {
+        tracing::info!(
+            "  {:<40} {:>12} {:>10} {:>10} {:>4}  {}",
+            "TABLE",
+            "ROWS",
+            "SIZE",
+            "AVG ROW",
+            "PK",
+            "REPLICA IDENTITY"
+        );
+    }
+    for table in &database.tables {
+        tracing::info!(
+            "  {:<40} {:>12} {:>10} {:>10} {:>4}  {}",
+            table.qualified_name(),
+            optional(table.rows.map(|rows| rows.to_string())),
+            optional(
+                table
+                    .size_bytes
+                    .map(|size| migration::format_bytes(size as i64))
+            ),
+            optional(
+                table
+                    .avg_row_bytes
+                    .map(|size| migration::format_bytes(size as i64))
+            ),
+            optional(
+                table
+                    .has_primary_key
+                    .map(|pk| if pk { "yes" } else { "no" }.to_string())
+            ),
+            table.replica_identity.unwrap_or("-")
+        );
+    }
+    for table in &database.tables {
+        for issue in &table.unsupported {
+            tracing::warn!("  ⚠ {}: {}", table.qualified_name(), issue);
+        }
+    }
+    for issue in &database.unsupported {
+        tracing::warn!("  ⚠ {}", issue);
+    }
+}
+
+fn optional(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_format_parse() {
+        assert_eq!(
+            "text".parse::<InspectFormat>().unwrap(),
+            InspectFormat::Text
+        );
+        assert_eq!(
+            "JSON".parse::<InspectFormat>().unwrap(),
+            InspectFormat::Json
+        );
+        assert!("yaml".parse::<InspectFormat>().is_err());
+    }
+}
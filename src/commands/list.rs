@@ -0,0 +1,110 @@
+// ABOUTME: List command - prints each source database's tables or collections with counts and average sizes
+// ABOUTME: Works for PostgreSQL, SQLite, MySQL, and MongoDB sources, for scoping a migration before running init
+
+use crate::filters::ReplicationFilter;
+use crate::inventory::{self, DatabaseInventory, TableInventory};
+use crate::migration;
+use anyhow::Result;
+
+/// Print every database's tables or collections with row counts and average sizes
+///
+/// A compact view of [`inventory::collect`]: PostgreSQL sources list every
+/// non-template database; MySQL and MongoDB sources list the database in the
+/// URL, or every user database when the URL names none. A SQLite file is
+/// listed as the single database `main`. Nothing is scanned, so listing is
+/// cheap even on large sources.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL, MySQL, or MongoDB URL, or SQLite file path
+/// * `filter` - Databases to list
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be reached or its catalog cannot be
+/// read.
+pub async fn list(source_url: &str, filter: &ReplicationFilter) -> Result<()> {
+    let inventory = inventory::collect(source_url, filter).await?;
+    if inventory.databases.is_empty() {
+        tracing::info!("No databases matched");
+        return Ok(());
+    }
+    let mongodb = inventory.source_type == "mongodb";
+    for database in &inventory.databases {
+        print_database(database, mongodb);
+    }
+    if matches!(inventory.source_type, "postgresql" | "mysql") {
+        tracing::info!(
+            "ℹ Row counts are estimates from table statistics; run ANALYZE (or ANALYZE TABLE on MySQL) to refresh them"
+        );
+    }
+    Ok(())
+}
+
+fn print_database(database: &DatabaseInventory, mongodb: bool) {
+    let noun = if mongodb { "collection" } else { "table" };
+    let total: u64 = database.tables.iter().filter_map(|table| table.rows).sum();
+    tracing::info!(
+        "Database '{}': {} {}(s), {} {}",
+        database.name,
+        database.tables.len(),
+        noun,
+        total,
+        if mongodb { "document(s)" } else { "row(s)" }
+    );
+    if database.tables.is_empty() {
+        return;
+    }
+    tracing::info!(
+        "  {:<40} {:>14} {:>12}",
+        noun.to_uppercase(),
+        "COUNT",
+        "AVG SIZE"
+    );
+    for table in &database.tables {
+        tracing::info!("{}", table_line(table));
+    }
+}
+
+fn table_line(table: &TableInventory) -> String {
+    format!(
+        "  {:<40} {:>14} {:>12}",
+        table.qualified_name(),
+        table
+            .rows
+            .map_or_else(|| "-".to_string(), |rows| rows.to_string()),
+        table.avg_row_bytes.map_or_else(
+            || "-".to_string(),
+            |size| migration::format_bytes(size as i64)
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, rows: Option<u64>, avg_row_bytes: Option<u64>) -> TableInventory {
+        TableInventory {
+            schema: None,
+            name: name.to_string(),
+            rows,
+            size_bytes: None,
+            avg_row_bytes,
+            has_primary_key: None,
+            replica_identity: None,
+            unsupported: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_table_line_marks_unknown_values() {
+        let line = table_line(&table("orders", Some(1200), Some(2048)));
+        assert!(line.contains("orders"));
+        assert!(line.contains("1200"));
+        assert!(line.contains("2.0 KB"));
+
+        let line = table_line(&table("recent_orders", None, None));
+        assert_eq!(line.matches('-').count(), 2);
+    }
+}
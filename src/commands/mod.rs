@@ -1,14 +1,48 @@
 // ABOUTME: Command implementations for each migration phase
-// ABOUTME: Exports validate, init, sync, status, and verify commands
+// ABOUTME: Exports accept-diff, audit, cleanup, ddl-queue, doctor, export, validate, init, inspect, list, onboard, relay, sync, backfill, bookmark, status, topology, tune, verify, validate-stream, readiness, and run-hooks commands
 
+pub mod accept_diff;
+pub mod audit;
+pub mod backfill;
+pub mod bookmark;
+pub mod cleanup;
+pub mod ddl_queue;
+pub mod doctor;
+pub mod export;
 pub mod init;
+pub mod inspect;
+pub mod list;
+pub mod onboard;
+pub mod readiness;
+pub mod reconcile;
+pub mod relay;
+pub mod run_hooks;
 pub mod status;
 pub mod sync;
+pub mod topology;
+pub mod tune;
 pub mod validate;
+pub mod validate_stream;
 pub mod verify;
 
+pub use accept_diff::accept_diff;
+pub use audit::audit;
+pub use backfill::backfill;
+pub use bookmark::bookmark;
+pub use cleanup::cleanup_orphans;
+pub use doctor::doctor;
+pub use export::export;
 pub use init::init;
+pub use inspect::inspect;
+pub use list::list;
+pub use onboard::onboard;
+pub use readiness::readiness;
+pub use reconcile::reconcile;
+pub use relay::relay;
+pub use run_hooks::run_hooks;
 pub use status::status;
 pub use sync::sync;
+pub use topology::topology;
 pub use validate::validate;
+pub use validate_stream::validate_stream;
 pub use verify::verify;
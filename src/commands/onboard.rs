@@ -0,0 +1,491 @@
+// ABOUTME: Onboard command - watches the source for databases and tables matching the filter and replicates new ones
+// ABOUTME: Runs init and sync for new databases, publishes and copies new tables, and notifies when databases disappear
+
+use crate::cancel::{self, CancellationToken};
+use crate::checkpoint::InitScope;
+use crate::events;
+use crate::filters::ReplicationFilter;
+use crate::hooks::{self, HookContext, HookStage};
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::replication::ddl_queue::{self, DdlChange, DdlKind};
+use crate::replication::new_tables::{self, NewTable, OnboardConfig};
+use crate::replication::orphans::SUBSCRIPTION_PREFIX;
+use crate::settings::RunSettings;
+use crate::utils::replace_database_in_url;
+use crate::{migration, postgres::connect};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Pacing for `onboard`
+#[derive(Debug, Clone)]
+pub struct OnboardOptions {
+    /// Time between rescans of the source databases
+    pub interval: Duration,
+    /// Stop after this many rescans (None: until cancelled)
+    pub cycles: Option<u64>,
+    /// New-table handling from the `[onboard]` config section
+    pub tables: OnboardConfig,
+    /// Settings of the init that copies each new database
+    pub init: super::init::InitOptions,
+}
+
+/// What one rescan found
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CyclePlan {
+    /// Matching source databases to copy and subscribe
+    added: Vec<String>,
+    /// Watched databases that no longer exist on the source
+    removed: Vec<String>,
+    /// Databases already replicating, checked for new tables
+    replicating: Vec<String>,
+}
+
+/// Databases seen across rescans
+#[derive(Debug, Default)]
+struct Tracker {
+    /// Matching source databases at the last rescan
+    known: BTreeSet<String>,
+    /// Databases whose onboarding failed, retried on the next rescan
+    retry: BTreeSet<String>,
+    /// Databases already reported as removed, so each is reported once
+    reported_missing: BTreeSet<String>,
+}
+
+impl Tracker {
+    /// Compare a rescan with the previous one
+    ///
+    /// * `source` - Source databases matching the filter
+    /// * `target` - Every database on the target
+    /// * `replicated` - Target databases with a sync-created subscription, matching the filter
+    fn plan(
+        &mut self,
+        source: &BTreeSet<String>,
+        target: &BTreeSet<String>,
+        replicated: &BTreeSet<String>,
+    ) -> CyclePlan {
+        self.retry.retain(|db| source.contains(db));
+        let added = source
+            .iter()
+            .filter(|db| !target.contains(*db) || self.retry.contains(*db))
+            .cloned()
+            .collect();
+
+        self.reported_missing.retain(|db| !source.contains(db));
+        let removed: Vec<String> = self
+            .known
+            .union(replicated)
+            .filter(|db| !source.contains(*db) && !self.reported_missing.contains(*db))
+            .cloned()
+            .collect();
+        self.reported_missing.extend(removed.iter().cloned());
+
+        let replicating = source
+            .intersection(replicated)
+            .filter(|db| !self.retry.contains(*db))
+            .cloned()
+            .collect();
+
+        self.known = source.clone();
+        CyclePlan {
+            added,
+            removed,
+            replicating,
+        }
+    }
+}
+
+/// Watch the source for new databases and tables matching the filter and replicate them
+///
+/// Every `interval`, the source's databases are listed and matched against the
+/// filter (patterns such as `tenant_%` included). Then:
+/// - A matching database that does not exist on the target is copied and
+///   subscribed with init (schema, data, then sync), using the filter's table
+///   rules. If that fails, it is retried at the next rescan.
+/// - In each database already replicating, source tables that match the filter
+///   but are not in the subscription are created on the target, added to the
+///   publication, and copied by refreshing the subscription (see
+///   [`OnboardConfig`] for turning this off or requiring approval first).
+/// - A database that was matched earlier, or has a sync-created subscription on
+///   the target, but no longer exists on the source is reported once: logged,
+///   sent to observers as a warning, and passed to the `database-removed` hooks.
+///   Nothing is dropped on the target.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source
+/// * `target_url` - PostgreSQL connection string for the target
+/// * `filter` - Databases to watch and table rules for new ones
+/// * `options` - Pacing and new-table handling
+/// * `cancel` - Stops the running init and the watch
+///
+/// # Errors
+///
+/// Returns an error if the source or target cannot be reached at startup, or
+/// if `cancel` is cancelled while a database is being copied. Other failures
+/// during a rescan are logged and retried at the next one.
+pub async fn onboard(
+    source_url: &str,
+    target_url: &str,
+    filter: ReplicationFilter,
+    options: &OnboardOptions,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    crate::utils::validate_source_target_different(source_url, target_url)
+        .context("Source and target validation failed")?;
+    let query_url = route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+    connect(source_url)
+        .await
+        .context("Failed to connect to source database")?;
+    connect(&query_url)
+        .await
+        .context("Failed to connect to target database")?;
+
+    tracing::info!(
+        "Watching the source for new databases and tables every {}; press Ctrl-C to stop",
+        migration::format_duration(options.interval)
+    );
+    tracing::info!("");
+
+    let mut tracker = Tracker::default();
+    let mut cycle = 0u64;
+    loop {
+        cycle += 1;
+        let plan = match cancel::run(
+            cancel,
+            rescan(source_url, &query_url, &filter, &mut tracker),
+        )
+        .await
+        {
+            Ok(plan) => plan,
+            Err(_) if cancel.is_cancelled() => break,
+            Err(e) => {
+                tracing::warn!("⚠ Rescan {} failed: {:#}", cycle, e);
+                CyclePlan::default()
+            }
+        };
+
+        for database in &plan.removed {
+            report_removed(source_url, target_url, database, settings).await;
+        }
+        for database in &plan.added {
+            tracing::info!("========================================");
+            tracing::info!("Onboarding new database '{}'", database);
+            tracing::info!("========================================");
+            let result = super::init(
+                source_url,
+                target_url,
+                true,
+                filter.for_database(database),
+                false,
+                true,
+                true,
+                InitScope::Full,
+                false,
+                &options.init,
+                None,
+                settings,
+                cancel,
+            )
+            .await;
+            match result {
+                Ok(()) => {
+                    tracker.retry.remove(database);
+                    tracing::info!("✓ Database '{}' is replicating", database);
+                }
+                Err(e) if cancel.is_cancelled() => return Err(e),
+                Err(e) => {
+                    tracker.retry.insert(database.clone());
+                    let message = format!(
+                        "Onboarding database '{}' failed; retrying at the next rescan: {:#}",
+                        database, e
+                    );
+                    tracing::warn!("⚠ {}", message);
+                    events::warning(&message);
+                }
+            }
+        }
+        if options.tables.new_tables {
+            for database in &plan.replicating {
+                let result = cancel::run(
+                    cancel,
+                    onboard_tables(
+                        source_url, target_url, &query_url, database, &filter, options, settings,
+                    ),
+                )
+                .await;
+                match result {
+                    Ok(_) => {}
+                    Err(_) if cancel.is_cancelled() => break,
+                    Err(e) => tracing::warn!(
+                        "⚠ Checking '{}' for new tables failed; retrying at the next rescan: {:#}",
+                        database,
+                        e
+                    ),
+                }
+            }
+        }
+        if plan.added.is_empty() && plan.removed.is_empty() {
+            tracing::debug!("Rescan {}: no new or removed databases", cycle);
+        }
+
+        if options.cycles.is_some_and(|limit| cycle >= limit) {
+            break;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(options.interval) => {}
+        }
+    }
+
+    tracing::info!("");
+    tracing::info!(
+        "✓ Stopped watching; {} database(s) matched the filter at the last rescan",
+        tracker.known.len()
+    );
+    if !tracker.retry.is_empty() {
+        tracing::warn!(
+            "⚠ Not yet replicating (onboarding failed): {}",
+            tracker.retry.iter().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// List source and target databases once and compare them with the last rescan
+async fn rescan(
+    source_url: &str,
+    target_url: &str,
+    filter: &ReplicationFilter,
+    tracker: &mut Tracker,
+) -> Result<CyclePlan> {
+    let source_client = connect(source_url)
+        .await
+        .context("Failed to connect to source database")?;
+    let source: BTreeSet<String> = migration::list_databases(&source_client)
+        .await
+        .context("Failed to list databases on source")?
+        .into_iter()
+        .map(|db| db.name)
+        .filter(|name| filter.should_replicate_database(name))
+        .collect();
+
+    let target_client = connect(target_url)
+        .await
+        .context("Failed to connect to target database")?;
+    let target: BTreeSet<String> = migration::list_databases(&target_client)
+        .await
+        .context("Failed to list databases on target")?
+        .into_iter()
+        .map(|db| db.name)
+        .collect();
+    let replicated: BTreeSet<String> = target_client
+        .query(
+            "SELECT DISTINCT d.datname::text \
+             FROM pg_subscription s JOIN pg_database d ON d.oid = s.subdbid \
+             WHERE s.subname LIKE $1::text || '%'",
+            &[&SUBSCRIPTION_PREFIX.replace('_', "\\_")],
+        )
+        .await
+        .context("Failed to list subscriptions on target")?
+        .iter()
+        .map(|row| row.get::<_, String>(0))
+        .filter(|name| filter.should_replicate_database(name))
+        .collect();
+
+    Ok(tracker.plan(&source, &target, &replicated))
+}
+
+/// Bring the source tables a replicating database's subscription lacks into replication
+///
+/// Without approval, each new table is created on the target (if missing),
+/// added to the publication, and copied by refreshing the subscription. With
+/// `require_table_approval`, a table missing on the target is queued in the
+/// DDL queue instead; once `ddl-queue --approve` has created it, the next
+/// rescan publishes and copies it.
+///
+/// # Returns
+///
+/// The number of tables added to replication.
+async fn onboard_tables(
+    source_url: &str,
+    target_url: &str,
+    queue_url: &str,
+    database: &str,
+    filter: &ReplicationFilter,
+    options: &OnboardOptions,
+    settings: &RunSettings,
+) -> Result<usize> {
+    let target_db_url = replace_database_in_url(
+        &route_target(target_url, TargetOperation::Subscription, &settings.pooler).await?,
+        database,
+    )?;
+    let target = connect(&target_db_url)
+        .await
+        .with_context(|| format!("Failed to connect to target database '{}'", database))?;
+    let Some(subscribed) = new_tables::subscription_tables(&target).await? else {
+        return Ok(0);
+    };
+    let source = connect(&replace_database_in_url(source_url, database)?)
+        .await
+        .with_context(|| format!("Failed to connect to source database '{}'", database))?;
+
+    let found = new_tables::find_new_tables(
+        &source,
+        database,
+        filter,
+        &subscribed,
+        &options.init.sync.publication,
+    )
+    .await?;
+    let mut added = 0;
+    let mut queued = 0;
+    for table in &found {
+        if !new_tables::target_has_table(&target, table).await? {
+            let statement = new_tables::create_table_statement(&source, table).await?;
+            if options.tables.require_table_approval {
+                if queue_for_approval(queue_url, database, table, statement).await? {
+                    queued += 1;
+                }
+                continue;
+            }
+            target.batch_execute(&statement).await.with_context(|| {
+                format!(
+                    "Failed to create {}.{} on the target",
+                    database,
+                    table.object()
+                )
+            })?;
+        }
+        new_tables::add_to_replication(&source, &target, &subscribed.subscription, table).await?;
+        tracing::info!(
+            "✓ New table {}.{} added to replication; its rows are being copied",
+            database,
+            table.object()
+        );
+        added += 1;
+    }
+
+    if queued > 0 {
+        hooks::run_hooks(
+            HookStage::DdlPending,
+            HookContext {
+                source_url,
+                target_url: queue_url,
+                database: Some(database),
+            },
+            settings,
+        )
+        .await?;
+    }
+    Ok(added)
+}
+
+/// Queue a new table's CREATE TABLE for approval
+///
+/// # Returns
+///
+/// Whether it was newly queued (false if already queued or rejected).
+async fn queue_for_approval(
+    queue_url: &str,
+    database: &str,
+    table: &NewTable,
+    statement: String,
+) -> Result<bool> {
+    let queue = connect(queue_url)
+        .await
+        .context("Failed to connect to target database")?;
+    ddl_queue::ensure_queue_table(&queue).await?;
+    let change = DdlChange {
+        kind: DdlKind::CreateTable,
+        object: table.object(),
+        statement,
+        safe: false,
+    };
+    let Some(id) = ddl_queue::enqueue(&queue, database, &change).await? else {
+        return Ok(false);
+    };
+    let message = format!(
+        "New table {}.{} awaits approval before it is replicated: seren-replicator ddl-queue --approve {}",
+        database,
+        table.object(),
+        id
+    );
+    tracing::warn!("⚠ {}", message);
+    events::warning(&message);
+    Ok(true)
+}
+
+/// Tell the operator that a replicated database is gone from the source
+async fn report_removed(
+    source_url: &str,
+    target_url: &str,
+    database: &str,
+    settings: &RunSettings,
+) {
+    let message = format!(
+        "Database '{}' no longer exists on the source; its copy on the target receives no more changes. \
+         Drop its subscription on the target (DROP SUBSCRIPTION ...) once the copy is no longer needed",
+        database
+    );
+    tracing::warn!("⚠ {}", message);
+    events::warning(&message);
+    let result = hooks::run_hooks(
+        HookStage::DatabaseRemoved,
+        HookContext {
+            source_url,
+            target_url,
+            database: Some(database),
+        },
+        settings,
+    )
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("⚠ database-removed hook failed: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tracker_plans_added_and_removed_databases() {
+        let mut tracker = Tracker::default();
+
+        // First rescan: tenant_b is new; tenant_x was replicated but is gone
+        let plan = tracker.plan(
+            &set(&["tenant_a", "tenant_b"]),
+            &set(&["tenant_a", "tenant_x", "postgres"]),
+            &set(&["tenant_a", "tenant_x"]),
+        );
+        assert_eq!(plan.added, vec!["tenant_b"]);
+        assert_eq!(plan.removed, vec!["tenant_x"]);
+        assert_eq!(plan.replicating, vec!["tenant_a"]);
+
+        // tenant_b failed to onboard; tenant_x is not reported twice
+        tracker.retry.insert("tenant_b".to_string());
+        let plan = tracker.plan(
+            &set(&["tenant_a", "tenant_b"]),
+            &set(&["tenant_a", "tenant_b", "tenant_x"]),
+            &set(&["tenant_a", "tenant_x"]),
+        );
+        assert_eq!(plan.added, vec!["tenant_b"]);
+        assert!(plan.removed.is_empty());
+
+        // tenant_a is dropped on the source
+        tracker.retry.clear();
+        let plan = tracker.plan(
+            &set(&["tenant_b"]),
+            &set(&["tenant_a", "tenant_b", "tenant_x"]),
+            &set(&["tenant_a", "tenant_b", "tenant_x"]),
+        );
+        assert!(plan.added.is_empty());
+        assert_eq!(plan.removed, vec!["tenant_a"]);
+    }
+}
@@ -0,0 +1,310 @@
+// ABOUTME: Reconcile command - deletes JSONB rows whose SQLite row, MySQL row, or MongoDB document was deleted
+// ABOUTME: Compares each source table's IDs with its JSONB copy once, or on a cadence with --interval
+
+use crate::cancel::{self, CancellationToken};
+use crate::filters::ReplicationFilter;
+use crate::jsonb::collisions::{self, JsonbSource};
+use crate::jsonb::reconcile::{self, ReconcileCounts};
+use crate::settings::RunSettings;
+use crate::{migration, postgres, SourceType};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// Database name SQLite tables are filtered under (`--include-tables main.users`)
+const SQLITE_DATABASE: &str = "main";
+
+/// Pacing and behaviour of `reconcile`
+#[derive(Debug, Clone)]
+pub struct ReconcileOptions {
+    /// Time between passes; None runs a single pass
+    pub interval: Option<Duration>,
+    /// Stop after this many passes (None: until cancelled)
+    pub cycles: Option<u64>,
+    /// Target IDs read per batch
+    pub batch_size: i64,
+    /// Report stale rows without deleting them
+    pub dry_run: bool,
+    /// Schema holding the JSONB tables
+    pub jsonb: crate::jsonb::JsonbOptions,
+}
+
+/// Delete target rows whose source row no longer exists, once or on a cadence
+///
+/// Full `init` runs recreate what they copy, but a `--refresh hash` run only
+/// sees the tables it refreshes and a MongoDB import never removes documents.
+/// Each pass reads only the source IDs of every table (subject to the table
+/// filter), pages through the JSONB copy's IDs, and deletes the rows the
+/// source no longer has. Tables whose IDs are row positions are skipped, as
+/// are empty source tables, where a misconfigured source would otherwise
+/// empty the target.
+///
+/// # Arguments
+///
+/// * `source_url` - SQLite file, MySQL URL, or MongoDB URL
+/// * `target_url` - PostgreSQL target holding the JSONB tables
+/// * `filter` - Tables to reconcile (`database.table`; SQLite tables are `main.table`)
+/// * `options` - Cadence, batch size, dry run, and target schema
+/// * `cancel` - Stops between tables and batches
+///
+/// # Errors
+///
+/// Returns an error for PostgreSQL sources, if the target cannot be reached,
+/// or if a single pass fails. With an interval, failed passes are logged and
+/// retried at the next one.
+pub async fn reconcile(
+    source_url: &str,
+    target_url: &str,
+    filter: &ReplicationFilter,
+    options: &ReconcileOptions,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let source_type =
+        crate::detect_source_type(source_url).context("Failed to detect source database type")?;
+    if source_type == SourceType::PostgreSQL {
+        bail!(
+            "reconcile applies to SQLite, MySQL, and MongoDB sources.\n\
+             PostgreSQL targets receive source deletes through logical replication; run 'sync' instead."
+        );
+    }
+    if !options.dry_run {
+        crate::protected::check(target_url, &[], "reconcile")?;
+    }
+    let target = postgres::connect_with_retry(target_url, settings).await?;
+
+    if let Some(interval) = options.interval {
+        tracing::info!(
+            "Reconciling every {}; press Ctrl-C to stop",
+            migration::format_duration(interval)
+        );
+    }
+    let mut pass = 0u64;
+    loop {
+        pass += 1;
+        let result = cancel::run(
+            cancel,
+            reconcile_pass(
+                source_url,
+                &source_type,
+                &target,
+                filter,
+                options,
+                settings,
+                cancel,
+            ),
+        )
+        .await;
+        match (result, options.interval) {
+            (Ok(tables), _) => log_pass(pass, &tables, options.dry_run),
+            (Err(e), None) => return Err(e),
+            (Err(_), Some(_)) if cancel.is_cancelled() => break,
+            (Err(e), Some(_)) => tracing::warn!(
+                "⚠ Reconciliation pass {} failed; retrying at the next one: {:#}",
+                pass,
+                e
+            ),
+        }
+
+        let Some(interval) = options.interval else {
+            break;
+        };
+        if options.cycles.is_some_and(|limit| pass >= limit) {
+            break;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+    Ok(())
+}
+
+fn log_pass(pass: u64, tables: &[(String, ReconcileCounts)], dry_run: bool) {
+    let stale: usize = tables.iter().map(|(_, counts)| counts.stale).sum();
+    let checked: usize = tables.iter().map(|(_, counts)| counts.checked).sum();
+    tracing::info!(
+        "✓ Reconciliation pass {} complete: {} stale row(s) {} of {} checked across {} table(s)",
+        pass,
+        stale,
+        if dry_run { "found" } else { "deleted" },
+        checked,
+        tables.len()
+    );
+}
+
+/// Reconcile every table once
+async fn reconcile_pass(
+    source_url: &str,
+    source_type: &SourceType,
+    target: &Client,
+    filter: &ReplicationFilter,
+    options: &ReconcileOptions,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<Vec<(String, ReconcileCounts)>> {
+    let mut reconciled = Vec::new();
+    match source_type {
+        SourceType::SQLite => {
+            let conn =
+                crate::sqlite::open_sqlite(source_url).context("Failed to open SQLite database")?;
+            let tables = crate::sqlite::reader::list_tables(&conn)
+                .context("Failed to list tables from SQLite database")?;
+            let source = JsonbSource::sqlite(
+                &crate::sqlite::validate_sqlite_path(source_url)
+                    .context("SQLite file validation failed")?,
+            );
+            for table in tables
+                .iter()
+                .filter(|table| filter.should_replicate_table(SQLITE_DATABASE, table))
+            {
+                cancel::check(cancel)?;
+                let started = reconcile::target_now(target).await?;
+                let ids = crate::sqlite::converter::read_table_ids(&conn, table)?;
+                if let Some(counts) =
+                    reconcile_table(target, &source, table, ids, started, options, settings).await?
+                {
+                    reconciled.push((table.clone(), counts));
+                }
+            }
+        }
+        SourceType::MySQL => {
+            let mut conn = crate::mysql::connect_mysql(source_url)
+                .await
+                .context("MySQL connection failed")?;
+            let db_name = crate::mysql::extract_database_name(source_url)
+                .context("MySQL URL must include database name (e.g., mysql://host:3306/dbname)")?;
+            let tables = crate::mysql::reader::list_tables(&mut conn, &db_name)
+                .await
+                .context("Failed to list tables from MySQL database")?;
+            let source = JsonbSource::from_url("mysql", source_url, &db_name);
+            for table in tables
+                .iter()
+                .filter(|table| filter.should_replicate_table(&db_name, table))
+            {
+                cancel::check(cancel)?;
+                let started = reconcile::target_now(target).await?;
+                let ids =
+                    crate::mysql::converter::read_table_ids(&mut conn, &db_name, table).await?;
+                if let Some(counts) =
+                    reconcile_table(target, &source, table, ids, started, options, settings).await?
+                {
+                    reconciled.push((table.clone(), counts));
+                }
+            }
+        }
+        SourceType::MongoDB => {
+            let client = crate::mongodb::connect_mongodb(source_url)
+                .await
+                .context("MongoDB connection failed")?;
+            let db_name = crate::mongodb::extract_database_name(source_url)
+                .await
+                .context("Failed to parse MongoDB connection string")?
+                .context(
+                    "MongoDB URL must include database name (e.g., mongodb://host:27017/dbname)",
+                )?;
+            let db = client.database(&db_name);
+            let collections = crate::mongodb::reader::list_collections(&client, &db_name)
+                .await
+                .context("Failed to list collections from MongoDB database")?;
+            // GridFS buckets are replaced as a whole on re-import
+            let (_, collections) = crate::mongodb::gridfs::detect_buckets(&collections);
+            let source = JsonbSource::from_url("mongodb", source_url, &db_name);
+            for collection in collections
+                .iter()
+                .filter(|collection| filter.should_replicate_table(&db_name, collection))
+            {
+                cancel::check(cancel)?;
+                let started = reconcile::target_now(target).await?;
+                let ids = crate::mongodb::converter::read_collection_ids(&db, collection).await?;
+                if let Some(counts) =
+                    reconcile_table(target, &source, collection, ids, started, options, settings)
+                        .await?
+                {
+                    reconciled.push((collection.clone(), counts));
+                }
+            }
+        }
+        SourceType::PostgreSQL => unreachable!("rejected before the first pass"),
+    }
+    Ok(reconciled)
+}
+
+/// Delete one table's stale rows; None when the table is skipped
+///
+/// The JSONB copy of `source_table` may have been renamed by
+/// `--collision-policy suffix`.
+async fn reconcile_table(
+    target: &Client,
+    source: &JsonbSource,
+    source_table: &str,
+    source_ids: Option<HashSet<String>>,
+    started: std::time::SystemTime,
+    options: &ReconcileOptions,
+    settings: &RunSettings,
+) -> Result<Option<ReconcileCounts>> {
+    let Some(source_ids) = source_ids else {
+        tracing::warn!(
+            "  ⚠ Skipping '{}': its rows are identified by position, so deleted rows cannot be told apart",
+            source_table
+        );
+        return Ok(None);
+    };
+    let schema = options.jsonb.target_schema.as_str();
+    let lookup = collisions::target_table(target, schema, source, source_table).await?;
+    let table = lookup.name.as_str();
+    if lookup.shared {
+        tracing::warn!(
+            "  ⚠ Skipping '{}': it also holds rows merged from other sources, which reconciling would delete",
+            table
+        );
+        return Ok(None);
+    }
+    if !reconcile::table_exists(target, schema, table).await? {
+        tracing::debug!("Skipping '{}': no JSONB table on the target", table);
+        return Ok(None);
+    }
+    if source_ids.is_empty() {
+        tracing::warn!(
+            "  ⚠ Skipping '{}': the source table is empty, so every target row would be deleted.\n\
+             If it was emptied on purpose, re-run init for it with --refresh hash.",
+            table
+        );
+        return Ok(None);
+    }
+
+    let counts = reconcile::delete_stale_rows(
+        target,
+        schema,
+        table,
+        &source_ids,
+        started,
+        options.batch_size,
+        options.dry_run,
+        &settings.audit,
+    )
+    .await?;
+    if counts.stale == 0 {
+        tracing::info!(
+            "  ✓ '{}': no stale rows ({} checked)",
+            table,
+            counts.checked
+        );
+    } else if options.dry_run {
+        tracing::info!(
+            "  ℹ '{}': {} stale row(s) of {} would be deleted",
+            table,
+            counts.stale,
+            counts.checked
+        );
+    } else {
+        tracing::info!(
+            "  ✓ '{}': deleted {} stale row(s) of {}",
+            table,
+            counts.stale,
+            counts.checked
+        );
+    }
+    Ok(Some(counts))
+}
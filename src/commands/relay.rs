@@ -0,0 +1,332 @@
+// ABOUTME: Relay command - a zstd-compressed TCP tunnel for PostgreSQL connections across slow links
+// ABOUTME: A client-side relay compresses toward a server-side relay near the database, which decompresses and forwards
+
+use crate::cancel::CancellationToken;
+use anyhow::{bail, Context, Result};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Sent by the client-side relay when it connects, so a misdirected connection fails clearly
+const MAGIC: &[u8; 8] = b"SRRELAY1";
+
+/// Largest chunk compressed as one frame
+const MAX_CHUNK: usize = 64 * 1024;
+
+/// How often the relay logs its throughput while data is flowing
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Which end of the tunnel this relay is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RelaySide {
+    /// Near the replicator: accepts PostgreSQL connections and compresses them toward the server-side relay
+    Client,
+    /// Near the database: accepts compressed connections and forwards them to PostgreSQL
+    Server,
+}
+
+/// Bytes through the relay: as PostgreSQL sent them, and as they crossed the compressed link
+#[derive(Debug, Default)]
+pub struct RelayStats {
+    raw_bytes: AtomicU64,
+    wire_bytes: AtomicU64,
+}
+
+impl RelayStats {
+    /// (raw, wire) bytes so far
+    pub fn totals(&self) -> (u64, u64) {
+        (
+            self.raw_bytes.load(Ordering::Relaxed),
+            self.wire_bytes.load(Ordering::Relaxed),
+        )
+    }
+
+    fn add(&self, raw: usize, wire: usize) {
+        self.raw_bytes.fetch_add(raw as u64, Ordering::Relaxed);
+        self.wire_bytes.fetch_add(wire as u64, Ordering::Relaxed);
+    }
+}
+
+/// Run a relay until Ctrl-C
+///
+/// Point the replicator at a client-side relay (with `sslmode=disable`, since
+/// TLS traffic does not compress) and run a server-side relay next to the
+/// database. COPY traffic between the two crosses the slow link zstd
+/// compressed. The relays do not encrypt: run the link over a VPN, SSH
+/// tunnel, or private network.
+///
+/// # Arguments
+///
+/// * `listen` - Address to accept connections on (e.g. `127.0.0.1:6543`)
+/// * `upstream` - Where to forward: the server-side relay for `Client`, PostgreSQL for `Server`
+/// * `side` - Which end of the tunnel this relay is
+/// * `level` - zstd compression level (1-19)
+/// * `cancel` - Stops accepting connections when cancelled
+///
+/// # Returns
+///
+/// The relay's byte counts when it stops.
+///
+/// # Errors
+///
+/// Returns an error if the listen address cannot be bound or the compression
+/// level is invalid.
+pub async fn relay(
+    listen: &str,
+    upstream: &str,
+    side: RelaySide,
+    level: i32,
+    cancel: &CancellationToken,
+) -> Result<Arc<RelayStats>> {
+    if !(1..=19).contains(&level) {
+        bail!(
+            "Compression level {} is out of range.\n\
+             Use a zstd level from 1 (fastest) to 19 (smallest); 3 suits most links.",
+            level
+        );
+    }
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    tracing::info!(
+        "✓ {} relay listening on {}, forwarding to {} (zstd level {})",
+        match side {
+            RelaySide::Client => "Client-side",
+            RelaySide::Server => "Server-side",
+        },
+        listen,
+        upstream,
+        level
+    );
+
+    let stats = Arc::new(RelayStats::default());
+    let mut report = tokio::time::interval(REPORT_INTERVAL);
+    report.tick().await;
+    let mut reported = (0, 0);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = report.tick() => {
+                let totals = stats.totals();
+                if totals != reported {
+                    log_throughput(
+                        "last minute",
+                        totals.0 - reported.0,
+                        totals.1 - reported.1,
+                        REPORT_INTERVAL,
+                    );
+                    reported = totals;
+                }
+            }
+            accepted = listener.accept() => {
+                let (socket, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("⚠ Failed to accept a connection: {}", e);
+                        continue;
+                    }
+                };
+                let upstream = upstream.to_string();
+                let stats = stats.clone();
+                tokio::spawn(async move {
+                    let started = Instant::now();
+                    let before = stats.totals();
+                    match serve(socket, &upstream, side, level, &stats).await {
+                        Ok(()) => {
+                            let after = stats.totals();
+                            tracing::debug!(
+                                "Connection from {} closed after {}: {} raw, {} on the link",
+                                peer,
+                                crate::migration::format_duration(started.elapsed()),
+                                crate::migration::format_bytes((after.0 - before.0) as i64),
+                                crate::migration::format_bytes((after.1 - before.1) as i64),
+                            );
+                        }
+                        Err(e) => tracing::warn!("⚠ Connection from {}: {:#}", peer, e),
+                    }
+                });
+            }
+        }
+    }
+
+    let (raw, wire) = stats.totals();
+    tracing::info!(
+        "✓ Relay stopped: {} relayed as {} on the link",
+        crate::migration::format_bytes(raw as i64),
+        crate::migration::format_bytes(wire as i64)
+    );
+    Ok(stats)
+}
+
+fn log_throughput(period: &str, raw: u64, wire: u64, elapsed: Duration) {
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    tracing::info!(
+        "  Relayed {} as {} ({:.1}x) over the {}: {}/s effective, {}/s on the link",
+        crate::migration::format_bytes(raw as i64),
+        crate::migration::format_bytes(wire as i64),
+        raw as f64 / (wire.max(1)) as f64,
+        period,
+        crate::migration::format_bytes((raw as f64 / secs) as i64),
+        crate::migration::format_bytes((wire as f64 / secs) as i64),
+    );
+}
+
+/// Relay one accepted connection until both directions close
+async fn serve(
+    mut socket: TcpStream,
+    upstream: &str,
+    side: RelaySide,
+    level: i32,
+    stats: &RelayStats,
+) -> Result<()> {
+    if side == RelaySide::Server {
+        let mut magic = [0u8; MAGIC.len()];
+        socket
+            .read_exact(&mut magic)
+            .await
+            .context("Peer closed before the relay handshake")?;
+        if &magic != MAGIC {
+            bail!(
+                "Peer is not a client-side seren-replicator relay.\n\
+                 Point the replicator at a relay started with --side client, not at the server-side relay."
+            );
+        }
+    }
+    let mut forward = TcpStream::connect(upstream)
+        .await
+        .with_context(|| format!("Failed to connect to {}", upstream))?;
+    let _ = socket.set_nodelay(true);
+    let _ = forward.set_nodelay(true);
+
+    // The compressed end is the peer relay: the upstream for the client side,
+    // the accepted socket for the server side
+    let (plain, compressed) = match side {
+        RelaySide::Client => {
+            forward.write_all(MAGIC).await?;
+            (&mut socket, &mut forward)
+        }
+        RelaySide::Server => (&mut forward, &mut socket),
+    };
+    let (plain_read, plain_write) = plain.split();
+    let (compressed_read, compressed_write) = compressed.split();
+    let (sent, received) = tokio::join!(
+        compress_stream(plain_read, compressed_write, level, stats),
+        decompress_stream(compressed_read, plain_write, stats),
+    );
+    sent.and(received)
+}
+
+/// Read plain bytes and write them as compressed frames, until EOF
+///
+/// Each frame is `compressed length (u32 BE) | raw length (u32 BE) | zstd data`.
+/// Every read is sent at once, so interactive traffic is not held back.
+async fn compress_stream<R, W>(
+    mut reader: R,
+    mut writer: W,
+    level: i32,
+    stats: &RelayStats,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut compressor = zstd::bulk::Compressor::new(level)?;
+    let mut buf = vec![0u8; MAX_CHUNK];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        let data = compressor.compress(&buf[..n])?;
+        writer.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        writer.write_all(&(n as u32).to_be_bytes()).await?;
+        writer.write_all(&data).await?;
+        writer.flush().await?;
+        stats.add(n, data.len() + 8);
+    }
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Read compressed frames and write the plain bytes, until EOF
+async fn decompress_stream<R, W>(mut reader: R, mut writer: W, stats: &RelayStats) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut decompressor = zstd::bulk::Decompressor::new()?;
+    let mut header = [0u8; 8];
+    let mut data = Vec::new();
+    loop {
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let wire = u32::from_be_bytes(header[..4].try_into()?) as usize;
+        let raw = u32::from_be_bytes(header[4..].try_into()?) as usize;
+        if raw > MAX_CHUNK || wire > zstd::zstd_safe::compress_bound(MAX_CHUNK) {
+            bail!(
+                "Received a malformed relay frame ({} bytes, {} uncompressed).\n\
+                 Both relays must run the same seren-replicator version.",
+                wire,
+                raw
+            );
+        }
+        data.resize(wire, 0);
+        reader.read_exact(&mut data).await?;
+        let plain = decompressor.decompress(&data, raw)?;
+        writer.write_all(&plain).await?;
+        writer.flush().await?;
+        stats.add(plain.len(), wire + 8);
+    }
+    writer.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compressed_round_trip() {
+        let payload: Vec<u8> = b"1\tAlice\talice@example.com\n"
+            .iter()
+            .copied()
+            .cycle()
+            .take(200_000)
+            .collect();
+        let stats = RelayStats::default();
+        let (link_write, link_read) = tokio::io::duplex(1024 * 1024);
+        let (mut out_write, mut out_read) = tokio::io::duplex(1024 * 1024);
+
+        compress_stream(payload.as_slice(), link_write, 3, &stats)
+            .await
+            .unwrap();
+        let (raw, wire) = stats.totals();
+        assert_eq!(raw, payload.len() as u64);
+        assert!(wire * 10 < raw, "repetitive COPY data should compress well");
+
+        decompress_stream(link_read, &mut out_write, &stats)
+            .await
+            .unwrap();
+        drop(out_write);
+        let mut received = Vec::new();
+        out_read.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, payload);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_frame_rejected() {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&16u32.to_be_bytes());
+        frame.extend_from_slice(&(MAX_CHUNK as u32 + 1).to_be_bytes());
+        frame.extend_from_slice(&[0u8; 16]);
+        let error = decompress_stream(frame.as_slice(), tokio::io::sink(), &RelayStats::default())
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains("malformed relay frame"));
+    }
+}
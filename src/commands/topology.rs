@@ -0,0 +1,123 @@
+// ABOUTME: Topology command - maps the logical replication streams between a source and a target cluster
+// ABOUTME: Prints publications, slots, subscriptions, and origins as text, Graphviz dot, or Mermaid
+
+use crate::filters::ReplicationFilter;
+use crate::replication::topology::{self, Topology};
+use crate::settings::RunSettings;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::str::FromStr;
+
+/// How `topology` renders the map (`--format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TopologyFormat {
+    #[default]
+    Text,
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for TopologyFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(TopologyFormat::Text),
+            "dot" => Ok(TopologyFormat::Dot),
+            "mermaid" => Ok(TopologyFormat::Mermaid),
+            _ => bail!(
+                "Unknown topology format '{}'. Expected one of: text, dot, mermaid",
+                s
+            ),
+        }
+    }
+}
+
+/// Print the replication topology between a source and a target
+///
+/// Both clusters are read the same way, so streams in either direction (and
+/// slots or subscriptions created by other tools over time) show up. Each
+/// subscription is linked to the slot of the same name on the other cluster,
+/// and to its publications in that slot's database. Text goes to the log like
+/// every other command's output; dot and Mermaid go to `output`, or to stdout
+/// when there is none.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source
+/// * `target_url` - PostgreSQL connection string for the target
+/// * `filter` - Databases to include
+/// * `format` - Text, dot, or Mermaid
+/// * `output` - File to write the rendered map to
+///
+/// # Errors
+///
+/// Returns an error if a cluster cannot be queried or `output` cannot be
+/// written.
+pub async fn topology(
+    source_url: &str,
+    target_url: &str,
+    filter: &ReplicationFilter,
+    format: TopologyFormat,
+    output: Option<&Path>,
+    settings: &RunSettings,
+) -> Result<()> {
+    let topology = Topology {
+        clusters: vec![
+            topology::collect("source", source_url, filter, settings).await?,
+            topology::collect("target", target_url, filter, settings).await?,
+        ],
+    };
+
+    let rendered = match format {
+        TopologyFormat::Text => {
+            for line in topology.render_text() {
+                tracing::info!("{}", line);
+            }
+            None
+        }
+        TopologyFormat::Dot => Some(topology.render_dot()),
+        TopologyFormat::Mermaid => Some(topology.render_mermaid()),
+    };
+    for warning in topology.warnings() {
+        tracing::warn!("⚠ {}", warning);
+    }
+
+    match (rendered, output) {
+        (Some(rendered), Some(path)) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("Failed to write topology to {}", path.display()))?;
+            tracing::info!("✓ Topology written to {}", path.display());
+        }
+        (Some(rendered), None) => print!("{}", rendered),
+        (None, Some(path)) => {
+            std::fs::write(path, topology.render_text().join("\n") + "\n")
+                .with_context(|| format!("Failed to write topology to {}", path.display()))?;
+            tracing::info!("✓ Topology written to {}", path.display());
+        }
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topology_format_parse() {
+        assert_eq!(
+            "text".parse::<TopologyFormat>().unwrap(),
+            TopologyFormat::Text
+        );
+        assert_eq!(
+            "DOT".parse::<TopologyFormat>().unwrap(),
+            TopologyFormat::Dot
+        );
+        assert_eq!(
+            "mermaid".parse::<TopologyFormat>().unwrap(),
+            TopologyFormat::Mermaid
+        );
+        assert!("svg".parse::<TopologyFormat>().is_err());
+    }
+}
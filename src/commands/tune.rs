@@ -0,0 +1,122 @@
+// ABOUTME: Tune command - recommends temporary target settings for a bulk load
+// ABOUTME: Optionally applies them with ALTER SYSTEM and reverts them afterwards; init --tune-for-load brackets a run with both
+
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, PoolerSettings, TargetOperation};
+use crate::postgres::tuning;
+use crate::utils::replace_database_in_url;
+use anyhow::{Context, Result};
+
+/// Print the recommended load settings for the target, and apply them if asked
+///
+/// # Arguments
+///
+/// * `target_url` - PostgreSQL connection string for the target
+/// * `apply` - Apply the recommendations with ALTER SYSTEM instead of only printing them
+///
+/// # Returns
+///
+/// The settings that were recommended (and applied, with `apply`).
+///
+/// # Errors
+///
+/// Returns an error if the target cannot be reached, or `apply` is set and
+/// ALTER SYSTEM is not permitted or the target is protected (`SR-0037`).
+pub async fn tune_for_load(
+    target_url: &str,
+    apply: bool,
+    pooler: &PoolerSettings,
+) -> Result<Vec<tuning::LoadSetting>> {
+    if apply {
+        // ALTER SYSTEM changes the whole cluster, not only the URL's database
+        crate::protected::check(target_url, &[], "tune --apply")?;
+    }
+    let client = connect_tuning_database(target_url, pooler).await?;
+
+    let current = tuning::current_settings(&client).await?;
+    let settings = tuning::recommend_for_load(&current);
+    if settings.is_empty() {
+        tracing::info!("✓ Target settings are already suited to a bulk load");
+        return Ok(settings);
+    }
+
+    tracing::info!("Recommended temporary settings for the load:");
+    tracing::info!(
+        "{:<20} {:<10} {:<12} Why",
+        "Setting",
+        "Current",
+        "During load"
+    );
+    for setting in &settings {
+        tracing::info!(
+            "{:<20} {:<10} {:<12} {}",
+            setting.name,
+            setting.current,
+            setting.recommended,
+            setting.reason
+        );
+    }
+
+    if !apply {
+        tracing::info!("");
+        tracing::info!("Apply them with: seren-replicator tune --for-load --apply --target <url>");
+        tracing::info!("Or run SQL as a superuser, then SELECT pg_reload_conf():");
+        for setting in &settings {
+            tracing::info!(
+                "  ALTER SYSTEM SET {} = '{}';",
+                setting.name,
+                setting.recommended
+            );
+        }
+        return Ok(settings);
+    }
+
+    tuning::apply(&client, &settings).await?;
+    tracing::info!(
+        "✓ Applied {} setting(s); revert with: seren-replicator tune --revert --target <url>",
+        settings.len()
+    );
+    Ok(settings)
+}
+
+/// Revert settings applied by [`tune_for_load`] to their previous values
+///
+/// # Errors
+///
+/// Returns an error if the target cannot be reached, ALTER SYSTEM is not
+/// permitted, or the target is protected (`SR-0037`).
+pub async fn revert_load_tuning(target_url: &str, pooler: &PoolerSettings) -> Result<()> {
+    crate::protected::check(target_url, &[], "tune --revert")?;
+    let client = connect_tuning_database(target_url, pooler).await?;
+    let reverted = tuning::revert(&client).await?;
+    if reverted.is_empty() {
+        tracing::info!("No load tuning to revert on the target");
+        if !tuning::current_settings(&client).await?.autovacuum {
+            tracing::warn!(
+                "⚠ autovacuum is off on the target, but no saved load tuning was found in the '{}' database",
+                tuning::TUNING_DATABASE
+            );
+            tracing::warn!(
+                "  If an earlier tune --apply turned it off, run ALTER SYSTEM RESET autovacuum; SELECT pg_reload_conf();"
+            );
+        }
+    } else {
+        tracing::info!("✓ Reverted {}", reverted.join(", "));
+    }
+    Ok(())
+}
+
+/// Connect to [`tuning::TUNING_DATABASE`] on the target's cluster, where load tuning is saved
+async fn connect_tuning_database(
+    target_url: &str,
+    pooler: &PoolerSettings,
+) -> Result<tokio_postgres::Client> {
+    let target_url = route_target(target_url, TargetOperation::Query, pooler).await?;
+    let tuning_url = replace_database_in_url(&target_url, tuning::TUNING_DATABASE)?;
+    connect(&tuning_url).await.with_context(|| {
+        format!(
+            "Failed to connect to the '{}' database on the target, where load tuning is saved",
+            tuning::TUNING_DATABASE
+        )
+    })
+}
@@ -0,0 +1,585 @@
+// ABOUTME: Validate-stream command - continuously samples recently changed source rows and compares them on the target
+// ABOUTME: Rows that still differ on a second check while unchanged on the source are reported as divergent
+
+use crate::cancel::{self, CancellationToken};
+use crate::errors::ReplicatorError;
+use crate::events;
+use crate::filters::ReplicationFilter;
+use crate::migration;
+use crate::postgres::connect;
+use crate::postgres::pooler::{route_target, TargetOperation};
+use crate::settings::RunSettings;
+use crate::utils::replace_database_in_url;
+use crate::utils::{quote_ident, quote_literal};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// How validate-stream samples and paces its checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Timestamp column marking when a row last changed
+    pub updated_at_column: String,
+    /// Time between cycles
+    pub interval: Duration,
+    /// Rows changed more recently than this are left for the next cycle, so
+    /// replication has time to apply them
+    pub grace: Duration,
+    /// Most rows sampled per table per cycle (the most recently changed)
+    pub sample_size: i64,
+    /// Stop after this many cycles; `None` runs until interrupted
+    pub cycles: Option<u64>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            updated_at_column: "updated_at".to_string(),
+            interval: Duration::from_secs(60),
+            grace: Duration::from_secs(30),
+            sample_size: 100,
+            cycles: None,
+        }
+    }
+}
+
+/// A row, identified by its primary key as canonical JSON
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct RowRef {
+    database: String,
+    schema: String,
+    table: String,
+    key: String,
+}
+
+/// What one comparison of a row showed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Observation {
+    /// Same on both sides
+    Match,
+    /// Differs; checked again next cycle before it is reported
+    Suspect,
+    /// Differed twice with the source unchanged in between
+    Diverged,
+    /// Still divergent, already reported
+    StillDiverged,
+    /// A reported divergence now matches
+    Resolved,
+}
+
+#[derive(Debug, Clone)]
+struct Suspect {
+    source_hash: Option<String>,
+    reported: bool,
+}
+
+/// Rows that differed on their last check, waiting for a recheck
+#[derive(Debug, Default)]
+struct DivergenceTracker {
+    pending: BTreeMap<RowRef, Suspect>,
+}
+
+impl DivergenceTracker {
+    /// Record a comparison; `None` hashes mean the row does not exist on that side
+    fn observe(
+        &mut self,
+        row: RowRef,
+        source_hash: Option<String>,
+        target_hash: Option<String>,
+    ) -> Observation {
+        if source_hash == target_hash {
+            return match self.pending.remove(&row) {
+                Some(suspect) if suspect.reported => Observation::Resolved,
+                _ => Observation::Match,
+            };
+        }
+        match self.pending.get_mut(&row) {
+            Some(suspect) if suspect.source_hash == source_hash => {
+                if suspect.reported {
+                    Observation::StillDiverged
+                } else {
+                    suspect.reported = true;
+                    Observation::Diverged
+                }
+            }
+            // The source row changed again since the last check: replication
+            // may simply be behind, so start over
+            Some(suspect) => {
+                suspect.source_hash = source_hash;
+                suspect.reported = false;
+                Observation::Suspect
+            }
+            None => {
+                self.pending.insert(
+                    row,
+                    Suspect {
+                        source_hash,
+                        reported: false,
+                    },
+                );
+                Observation::Suspect
+            }
+        }
+    }
+
+    fn diverged(&self) -> usize {
+        self.pending.values().filter(|s| s.reported).count()
+    }
+
+    /// Pending row keys, grouped by (database, schema, table)
+    fn pending_by_table(&self) -> HashMap<(String, String, String), Vec<String>> {
+        let mut grouped: HashMap<_, Vec<String>> = HashMap::new();
+        for row in self.pending.keys() {
+            grouped
+                .entry((row.database.clone(), row.schema.clone(), row.table.clone()))
+                .or_default()
+                .push(row.key.clone());
+        }
+        grouped
+    }
+}
+
+/// A table that can be sampled: it has a primary key and the timestamp column
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StreamTable {
+    schema: String,
+    name: String,
+    key: Vec<String>,
+}
+
+/// Compare recently changed rows between source and target, cycle after cycle
+///
+/// Meant to run alongside `sync` during the weeks before cutover. Each cycle,
+/// for every table with a primary key and the `updated_at_column`:
+/// 1. Rows whose timestamp falls between the previous cycle and `grace` ago
+///    are read on the source (at most `sample_size`, most recent first)
+/// 2. The same primary keys are looked up on the target and whole-row hashes compared
+/// 3. Rows that differ are checked again next cycle; if they still differ and
+///    the source row has not changed in between, they are reported as divergent
+///
+/// Tables without the timestamp column or a primary key are not sampled; run
+/// `verify` for those.
+///
+/// # Arguments
+///
+/// * `source_url` - PostgreSQL connection string for the source
+/// * `target_url` - PostgreSQL connection string for the target
+/// * `filter` - Databases and tables to sample
+/// * `options` - Timestamp column, pacing, and sample size
+/// * `cancel` - Stops after the current cycle's queries are abandoned
+///
+/// # Errors
+///
+/// Returns `SR-0050` (verification failed) if divergent rows remain when the
+/// run ends (after `options.cycles` cycles or an interrupt), or an error if
+/// the source cannot be reached.
+pub async fn validate_stream(
+    source_url: &str,
+    target_url: &str,
+    mut filter: ReplicationFilter,
+    options: &StreamOptions,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    crate::utils::validate_source_target_different(source_url, target_url)
+        .context("Source and target validation failed")?;
+    let target_url = &route_target(target_url, TargetOperation::Query, &settings.pooler).await?;
+
+    let source_client = connect(source_url)
+        .await
+        .context("Failed to connect to source database")?;
+    let databases: Vec<String> = migration::list_databases(&source_client)
+        .await
+        .context("Failed to list databases on source")?
+        .into_iter()
+        .map(|db| db.name)
+        .filter(|name| filter.should_replicate_database(name))
+        .collect();
+    filter
+        .resolve_table_schemas_for(source_url, &databases, settings)
+        .await?;
+
+    let mut tables: Vec<(String, Vec<StreamTable>)> = Vec::new();
+    for database in &databases {
+        let db_url = replace_database_in_url(source_url, database)?;
+        let client = connect(&db_url)
+            .await
+            .with_context(|| format!("Failed to connect to source database '{}'", database))?;
+        let found: Vec<StreamTable> = sampleable_tables(&client, &options.updated_at_column)
+            .await
+            .with_context(|| format!("Failed to list tables in '{}'", database))?
+            .into_iter()
+            .filter(|table| {
+                filter.should_replicate_schema_table(database, &table.schema, &table.name)
+                    && !crate::replication::heartbeat::is_heartbeat_table(
+                        &table.schema,
+                        &table.name,
+                    )
+            })
+            .collect();
+        tracing::info!(
+            "Database '{}': sampling {} table(s) with a primary key and '{}'",
+            database,
+            found.len(),
+            options.updated_at_column
+        );
+        if !found.is_empty() {
+            tables.push((database.clone(), found));
+        }
+    }
+    if tables.is_empty() {
+        tracing::warn!(
+            "⚠ No replicated tables have both a primary key and a '{}' column; nothing to sample.\n  \
+             Pick another column with --updated-at-column, or run verify instead.",
+            options.updated_at_column
+        );
+        return Ok(());
+    }
+
+    tracing::info!(
+        "Checking rows changed more than {}s ago every {}s (up to {} per table); press Ctrl-C to stop",
+        options.grace.as_secs(),
+        options.interval.as_secs(),
+        options.sample_size
+    );
+    tracing::info!("");
+
+    let mut tracker = DivergenceTracker::default();
+    // Upper bound of the previous window, as a source timestamp
+    let mut since: Option<String> = None;
+    let mut cycle = 0u64;
+    loop {
+        cycle += 1;
+        let until: String = source_client
+            .query_one(
+                "SELECT (now() - make_interval(secs => $1))::text",
+                &[&(options.grace.as_secs_f64())],
+            )
+            .await
+            .context("Failed to read the source clock")?
+            .get(0);
+        let lower = since.clone().unwrap_or_else(|| until.clone());
+        let first = since.is_none();
+
+        let result = cancel::run(
+            cancel,
+            run_cycle(
+                source_url,
+                target_url,
+                &tables,
+                options,
+                &lower,
+                &until,
+                first,
+                &mut tracker,
+            ),
+        )
+        .await;
+        match result {
+            Ok(sampled) => tracing::info!(
+                "Cycle {}: compared {} changed row(s); {} awaiting recheck, {} divergent",
+                cycle,
+                sampled,
+                tracker.pending.len() - tracker.diverged(),
+                tracker.diverged()
+            ),
+            Err(_) if cancel.is_cancelled() => break,
+            Err(e) => tracing::warn!("⚠ Cycle {} failed: {:#}", cycle, e),
+        }
+        since = Some(until);
+
+        if options.cycles.is_some_and(|limit| cycle >= limit) {
+            break;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(options.interval) => {}
+        }
+    }
+
+    let diverged = tracker.diverged();
+    tracing::info!("");
+    if diverged > 0 {
+        tracing::error!(
+            "✗ {} row(s) differ between source and target; run verify on the tables listed above",
+            diverged
+        );
+        return Err(ReplicatorError::VerificationFailed {
+            summary: format!("{} divergent row(s) found by validate-stream", diverged),
+        }
+        .into());
+    }
+    tracing::info!("✓ No divergent rows found in {} cycle(s)", cycle);
+    Ok(())
+}
+
+/// Recheck pending rows, then sample the new window
+///
+/// # Returns
+///
+/// The number of newly sampled rows.
+#[allow(clippy::too_many_arguments)]
+async fn run_cycle(
+    source_url: &str,
+    target_url: &str,
+    tables: &[(String, Vec<StreamTable>)],
+    options: &StreamOptions,
+    since: &str,
+    until: &str,
+    first: bool,
+    tracker: &mut DivergenceTracker,
+) -> Result<usize> {
+    let pending = tracker.pending_by_table();
+    let mut sampled = 0;
+    for (database, db_tables) in tables {
+        let source = connect(&replace_database_in_url(source_url, database)?)
+            .await
+            .with_context(|| format!("Failed to connect to source database '{}'", database))?;
+        let target = connect(&replace_database_in_url(target_url, database)?)
+            .await
+            .with_context(|| format!("Failed to connect to target database '{}'", database))?;
+
+        for table in db_tables {
+            let mut keys = pending
+                .get(&(database.clone(), table.schema.clone(), table.name.clone()))
+                .cloned()
+                .unwrap_or_default();
+            let recent = source
+                .query(
+                    &sample_sql(table, &options.updated_at_column, first),
+                    &[&since, &until, &options.sample_size],
+                )
+                .await
+                .with_context(|| format!("Failed to sample {}.{}", table.schema, table.name))?;
+            sampled += recent.len();
+            keys.extend(recent.iter().map(|row| row.get::<_, String>(0)));
+            keys.sort();
+            keys.dedup();
+            if keys.is_empty() {
+                continue;
+            }
+
+            let source_hashes = row_hashes(&source, table, &keys).await?;
+            let target_hashes = row_hashes(&target, table, &keys).await?;
+            for key in keys {
+                let row = RowRef {
+                    database: database.clone(),
+                    schema: table.schema.clone(),
+                    table: table.name.clone(),
+                    key: key.clone(),
+                };
+                let source_hash = source_hashes.get(&key).cloned();
+                let target_hash = target_hashes.get(&key).cloned();
+                let problem = match (&source_hash, &target_hash) {
+                    (Some(_), None) => "missing on the target",
+                    (None, Some(_)) => "deleted on the source but present on the target",
+                    _ => "differs on the target",
+                };
+                match tracker.observe(row, source_hash, target_hash) {
+                    Observation::Diverged => {
+                        let message = format!(
+                            "{}.{}.{} {}: {} (source unchanged since the last check)",
+                            database, table.schema, table.name, key, problem
+                        );
+                        tracing::error!("  ✗ {}", message);
+                        events::warning(&message);
+                    }
+                    Observation::Resolved => tracing::info!(
+                        "  ✓ {}.{}.{} {}: now matches",
+                        database,
+                        table.schema,
+                        table.name,
+                        key
+                    ),
+                    Observation::Match | Observation::Suspect | Observation::StillDiverged => {}
+                }
+            }
+        }
+    }
+    Ok(sampled)
+}
+
+/// Tables with a primary key and a timestamp column named `updated_at_column`
+async fn sampleable_tables(client: &Client, updated_at_column: &str) -> Result<Vec<StreamTable>> {
+    let rows = client
+        .query(
+            "SELECT n.nspname::text, c.relname::text,
+                    array_agg(a.attname::text ORDER BY array_position(i.indkey, a.attnum))
+             FROM pg_index i
+             JOIN pg_class c ON c.oid = i.indrelid
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum = ANY(i.indkey)
+             WHERE i.indisprimary
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'seren_replicator')
+               AND EXISTS (
+                   SELECT 1 FROM pg_attribute u
+                   WHERE u.attrelid = c.oid AND u.attname = $1 AND NOT u.attisdropped
+                     AND u.atttypid IN ('timestamptz'::regtype, 'timestamp'::regtype)
+               )
+             GROUP BY n.nspname, c.relname
+             ORDER BY 1, 2",
+            &[&updated_at_column],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| StreamTable {
+            schema: row.get(0),
+            name: row.get(1),
+            key: row.get(2),
+        })
+        .collect())
+}
+
+/// `jsonb_build_object('k1', t.k1, ...)`: the row's key as canonical JSON
+fn key_sql(table: &StreamTable) -> String {
+    let pairs = table
+        .key
+        .iter()
+        .map(|column| format!("{}, t.{}", quote_literal(column), quote_ident(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("jsonb_build_object({})::text", pairs)
+}
+
+/// Keys of rows changed in the window ($1, $2], newest first, at most $3
+///
+/// The first cycle has no previous window, so it takes the latest rows up to $2.
+fn sample_sql(table: &StreamTable, updated_at_column: &str, first: bool) -> String {
+    let column = format!("t.{}", quote_ident(updated_at_column));
+    let lower = if first {
+        "$1::text IS NOT NULL".to_string()
+    } else {
+        format!("{} > $1::text::timestamptz", column)
+    };
+    format!(
+        "SELECT {} FROM {}.{} AS t
+         WHERE {} AND {} <= $2::text::timestamptz
+         ORDER BY {} DESC
+         LIMIT $3",
+        key_sql(table),
+        quote_ident(&table.schema),
+        quote_ident(&table.name),
+        lower,
+        column,
+        column
+    )
+}
+
+/// Whole-row hashes of the rows with these keys, keyed by the key JSON
+async fn row_hashes(
+    client: &Client,
+    table: &StreamTable,
+    keys: &[String],
+) -> Result<HashMap<String, String>> {
+    let join = table
+        .key
+        .iter()
+        .map(|column| format!("t.{0} = k.{0}", quote_ident(column)))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let qualified = format!(
+        "{}.{}",
+        quote_ident(&table.schema),
+        quote_ident(&table.name)
+    );
+    let query = format!(
+        "SELECT {}, md5(t::text)
+         FROM {} AS t
+         JOIN jsonb_populate_recordset(NULL::{}, $1::text::jsonb) AS k ON {}",
+        key_sql(table),
+        qualified,
+        qualified,
+        join
+    );
+    let keys_json = format!("[{}]", keys.join(","));
+    let rows = client
+        .query(&query, &[&keys_json])
+        .await
+        .with_context(|| format!("Failed to read rows of {}", qualified))?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(key: &str) -> RowRef {
+        RowRef {
+            database: "app".to_string(),
+            schema: "public".to_string(),
+            table: "orders".to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    fn hash(h: &str) -> Option<String> {
+        Some(h.to_string())
+    }
+
+    #[test]
+    fn test_divergence_needs_two_checks() {
+        let mut tracker = DivergenceTracker::default();
+        assert_eq!(
+            tracker.observe(row("1"), hash("a"), hash("a")),
+            Observation::Match
+        );
+        assert_eq!(
+            tracker.observe(row("2"), hash("a"), hash("b")),
+            Observation::Suspect
+        );
+        assert_eq!(tracker.diverged(), 0);
+        assert_eq!(
+            tracker.observe(row("2"), hash("a"), hash("b")),
+            Observation::Diverged
+        );
+        assert_eq!(
+            tracker.observe(row("2"), hash("a"), hash("b")),
+            Observation::StillDiverged
+        );
+        assert_eq!(tracker.diverged(), 1);
+        assert_eq!(
+            tracker.observe(row("2"), hash("a"), hash("a")),
+            Observation::Resolved
+        );
+        assert!(tracker.pending.is_empty());
+    }
+
+    #[test]
+    fn test_source_change_restarts_suspicion() {
+        let mut tracker = DivergenceTracker::default();
+        tracker.observe(row("1"), hash("a"), None);
+        // The source moved on; replication may just be behind
+        assert_eq!(
+            tracker.observe(row("1"), hash("b"), hash("a")),
+            Observation::Suspect
+        );
+        // Caught up
+        assert_eq!(
+            tracker.observe(row("1"), hash("b"), hash("b")),
+            Observation::Match
+        );
+        // Deleted on the source but not on the target, twice
+        tracker.observe(row("3"), None, hash("c"));
+        assert_eq!(
+            tracker.observe(row("3"), None, hash("c")),
+            Observation::Diverged
+        );
+    }
+
+    #[test]
+    fn test_sample_sql() {
+        let table = StreamTable {
+            schema: "public".to_string(),
+            name: "Orders".to_string(),
+            key: vec!["tenant".to_string(), "id".to_string()],
+        };
+        let sql = sample_sql(&table, "updated_at", false);
+        assert!(sql.contains("jsonb_build_object('tenant', t.\"tenant\", 'id', t.\"id\")::text"));
+        assert!(sql.contains("FROM \"public\".\"Orders\" AS t"));
+        assert!(sql.contains("t.\"updated_at\" > $1::text::timestamptz"));
+        assert!(sql.contains("ORDER BY t.\"updated_at\" DESC"));
+        assert!(!sample_sql(&table, "updated_at", true).contains("> $1"));
+    }
+}
@@ -1,7 +1,26 @@
-// ABOUTME: Parses replication configuration files for table-level rules
-// ABOUTME: Converts TOML format into TableRules structures
+// ABOUTME: Parses replication configuration files for table rules, retry policy, hooks, object policies, publication and subscription options, onboarding, parallelism, lag thresholds, smoke queries, JSONB batching, partitioning, and target schemas, BSON type mapping, telemetry, remote pricing, Neon targets, and extensions
+// ABOUTME: Converts TOML format into TableRules, RetryConfig, HooksConfig, ParallelismConfig, LagThresholdsConfig, JSONB batching bounds, partitioning, and target schemas, BsonTypeMap, TelemetryConfig, RemotePricingConfig, NeonConfig, ExtensionInstallConfig, PublicationConfig, SubscriptionConfig, OnboardConfig, and per-object policy configs (foreign servers, event triggers, unsupported objects)
 
-use crate::table_rules::{QualifiedTable, TableRules};
+use crate::hooks::HooksConfig;
+use crate::jsonb::batching::JsonbBatchingConfig;
+use crate::jsonb::partitioning::JsonbPartitioningConfig;
+use crate::jsonb::target_schema::JsonbTargetSchemaConfig;
+use crate::migration::event_triggers::EventTriggerConfig;
+use crate::migration::foreign_servers::ForeignServerConfig;
+use crate::migration::unsupported::UnsupportedObjectConfig;
+use crate::mongodb::type_map::BsonTypeMap;
+use crate::parallelism::ParallelismConfig;
+use crate::postgres::extensions::ExtensionInstallConfig;
+use crate::postgres::neon::NeonConfig;
+use crate::remote::pricing::RemotePricingConfig;
+use crate::replication::lag_thresholds::LagThresholdsConfig;
+use crate::replication::new_tables::OnboardConfig;
+use crate::replication::publication::PublicationConfig;
+use crate::replication::smoke::SmokeQuery;
+use crate::replication::subscription::SubscriptionConfig;
+use crate::retry::RetryConfig;
+use crate::table_rules::{PartitionRule, QualifiedTable, TableRules};
+use crate::telemetry::TelemetryConfig;
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -11,6 +30,46 @@ use std::fs;
 struct ReplicationConfig {
     #[serde(default)]
     databases: HashMap<String, DatabaseConfig>,
+    #[serde(default)]
+    copy_order: Option<String>,
+    #[serde(default)]
+    retry: Option<RetryConfig>,
+    #[serde(default)]
+    hooks: Option<HooksConfig>,
+    #[serde(default)]
+    foreign_servers: Option<ForeignServerConfig>,
+    #[serde(default)]
+    event_triggers: Option<EventTriggerConfig>,
+    #[serde(default)]
+    unsupported_objects: Option<UnsupportedObjectConfig>,
+    #[serde(default)]
+    publication: Option<PublicationConfig>,
+    #[serde(default)]
+    subscription: Option<SubscriptionConfig>,
+    #[serde(default)]
+    onboard: Option<OnboardConfig>,
+    #[serde(default)]
+    parallelism: Option<ParallelismConfig>,
+    #[serde(default)]
+    lag_thresholds: Option<LagThresholdsConfig>,
+    #[serde(default)]
+    smoke_queries: Vec<SmokeQuery>,
+    #[serde(default)]
+    jsonb_batching: Option<JsonbBatchingConfig>,
+    #[serde(default)]
+    jsonb_partitioning: Option<JsonbPartitioningConfig>,
+    #[serde(default)]
+    jsonb_target_schema: Option<JsonbTargetSchemaConfig>,
+    #[serde(default)]
+    bson_types: Option<BsonTypeMap>,
+    #[serde(default)]
+    telemetry: Option<TelemetryConfig>,
+    #[serde(default)]
+    remote_pricing: Option<RemotePricingConfig>,
+    #[serde(default)]
+    neon: Option<NeonConfig>,
+    #[serde(default)]
+    extensions: Option<ExtensionInstallConfig>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -21,6 +80,12 @@ struct DatabaseConfig {
     table_filters: Vec<TableFilterConfig>,
     #[serde(default)]
     time_filters: Vec<TimeFilterConfig>,
+    #[serde(default)]
+    transforms: Vec<TransformConfig>,
+    #[serde(default)]
+    partitions: Vec<PartitionConfig>,
+    #[serde(default)]
+    priorities: Vec<PriorityConfig>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,11 +106,268 @@ struct TimeFilterConfig {
     last: String,
 }
 
-pub fn load_table_rules_from_file(path: &str) -> Result<TableRules> {
+#[derive(Debug, Deserialize)]
+struct TransformConfig {
+    table: String,
+    #[serde(default)]
+    schema: Option<String>,
+    select: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PartitionConfig {
+    table: String,
+    #[serde(default)]
+    schema: Option<String>,
+    column: String,
+    interval: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriorityConfig {
+    table: String,
+    #[serde(default)]
+    schema: Option<String>,
+    priority: i64,
+}
+
+fn read_config(path: &str) -> Result<ReplicationConfig> {
     let raw = fs::read_to_string(path)
         .with_context(|| format!("Failed to read config file at {}", path))?;
-    let parsed: ReplicationConfig =
-        toml::from_str(&raw).with_context(|| format!("Failed to parse TOML config at {}", path))?;
+    toml::from_str(&raw).with_context(|| format!("Failed to parse TOML config at {}", path))
+}
+
+/// Load the optional `[retry]` section from a replication config file
+///
+/// ```toml
+/// [retry]
+/// max_retries = 5
+/// base_delay_secs = 2
+/// max_delay_secs = 120
+/// jitter = true
+/// ```
+pub fn load_retry_config_from_file(path: &str) -> Result<Option<RetryConfig>> {
+    Ok(read_config(path)?.retry)
+}
+
+/// Load the optional `[hooks]` section from a replication config file
+///
+/// ```toml
+/// [hooks]
+/// post-schema = [{ sql = "hooks/rls_policies.sql" }]
+/// post-data = [{ command = "./grant_app_roles.sh" }]
+/// ```
+pub fn load_hooks_config_from_file(path: &str) -> Result<Option<HooksConfig>> {
+    Ok(read_config(path)?.hooks)
+}
+
+/// Load the optional `[foreign_servers]` section from a replication config file
+///
+/// ```toml
+/// [foreign_servers]
+/// policy = "skip"  # or "stub", "keep" (default)
+/// ```
+pub fn load_foreign_server_config_from_file(path: &str) -> Result<Option<ForeignServerConfig>> {
+    Ok(read_config(path)?.foreign_servers)
+}
+
+/// Load the optional `[event_triggers]` section from a replication config file
+///
+/// ```toml
+/// [event_triggers]
+/// policy = "defer"  # or "keep" (default)
+/// ```
+pub fn load_event_trigger_config_from_file(path: &str) -> Result<Option<EventTriggerConfig>> {
+    Ok(read_config(path)?.event_triggers)
+}
+
+/// Load the optional `[unsupported_objects]` section from a replication config file
+///
+/// ```toml
+/// [unsupported_objects]
+/// policy = "fail"  # or "strip" (default)
+/// ```
+pub fn load_unsupported_object_config_from_file(
+    path: &str,
+) -> Result<Option<UnsupportedObjectConfig>> {
+    Ok(read_config(path)?.unsupported_objects)
+}
+
+/// Load the optional `[publication]` section from a replication config file
+///
+/// ```toml
+/// [publication]
+/// publish = "insert,update,delete"
+///
+/// [[publication.groups]]
+/// name = "append_only"
+/// tables = ["events"]
+/// publish = "insert"
+/// ```
+pub fn load_publication_config_from_file(path: &str) -> Result<Option<PublicationConfig>> {
+    Ok(read_config(path)?.publication)
+}
+
+/// Load the optional `[subscription]` section from a replication config file
+///
+/// ```toml
+/// [subscription]
+/// streaming = "parallel"
+/// ```
+pub fn load_subscription_config_from_file(path: &str) -> Result<Option<SubscriptionConfig>> {
+    Ok(read_config(path)?.subscription)
+}
+
+/// Load the optional `[onboard]` section from a replication config file
+///
+/// ```toml
+/// [onboard]
+/// require_table_approval = true
+/// ```
+pub fn load_onboard_config_from_file(path: &str) -> Result<Option<OnboardConfig>> {
+    Ok(read_config(path)?.onboard)
+}
+
+/// Load the optional `[parallelism]` section from a replication config file
+///
+/// ```toml
+/// [parallelism]
+/// max_total_jobs = 2
+/// split_tables_larger_than = "200GB"
+/// ```
+pub fn load_parallelism_config_from_file(path: &str) -> Result<Option<ParallelismConfig>> {
+    Ok(read_config(path)?.parallelism)
+}
+
+/// Load the optional `[lag_thresholds]` section from a replication config file
+///
+/// ```toml
+/// [lag_thresholds]
+/// default = "5m"
+///
+/// [[lag_thresholds.groups]]
+/// name = "payments"
+/// databases = ["payments"]
+/// max_lag = "10s"
+/// ```
+pub fn load_lag_thresholds_config_from_file(path: &str) -> Result<Option<LagThresholdsConfig>> {
+    Ok(read_config(path)?.lag_thresholds)
+}
+
+/// Load the `[[smoke_queries]]` entries from a replication config file
+///
+/// ```toml
+/// [[smoke_queries]]
+/// name = "admin account"
+/// databases = ["app"]
+/// sql = "SELECT id FROM users WHERE email = 'admin@example.com'"
+/// ```
+pub fn load_smoke_queries_from_file(path: &str) -> Result<Vec<SmokeQuery>> {
+    Ok(read_config(path)?.smoke_queries)
+}
+
+/// Load the optional `[jsonb_batching]` section from a replication config file
+///
+/// ```toml
+/// [jsonb_batching.mongodb]
+/// min_rows = 10
+/// max_rows = 2000
+/// max_batch_size = "8MB"
+/// target_latency_ms = 500
+/// ```
+pub fn load_jsonb_batching_config_from_file(path: &str) -> Result<Option<JsonbBatchingConfig>> {
+    Ok(read_config(path)?.jsonb_batching)
+}
+
+/// Load the optional `[jsonb_partitioning]` section from a replication config file
+///
+/// ```toml
+/// [jsonb_partitioning.events]
+/// field = "tenant_id"  # omit to partition by document id
+/// partitions = 16
+/// ```
+pub fn load_jsonb_partitioning_config_from_file(
+    path: &str,
+) -> Result<Option<JsonbPartitioningConfig>> {
+    Ok(read_config(path)?.jsonb_partitioning)
+}
+
+/// Load the optional `[jsonb_target_schema]` section from a replication config file
+///
+/// ```toml
+/// [jsonb_target_schema]
+/// mysql = "mysql_import"  # sqlite and mongodb keys work the same way
+/// ```
+pub fn load_jsonb_target_schema_config_from_file(
+    path: &str,
+) -> Result<Option<JsonbTargetSchemaConfig>> {
+    Ok(read_config(path)?.jsonb_target_schema)
+}
+
+/// Load the optional `[bson_types]` section from a replication config file
+///
+/// ```toml
+/// [bson_types]
+/// objectid = "text"
+/// decimal128 = "number"
+/// ```
+pub fn load_bson_types_config_from_file(path: &str) -> Result<Option<BsonTypeMap>> {
+    Ok(read_config(path)?.bson_types)
+}
+
+/// Load the optional `[telemetry]` section from a replication config file
+///
+/// ```toml
+/// [telemetry]
+/// enabled = true
+/// endpoint = "https://telemetry.example.com/events"
+/// ```
+pub fn load_telemetry_config_from_file(path: &str) -> Result<Option<TelemetryConfig>> {
+    Ok(read_config(path)?.telemetry)
+}
+
+/// Load the optional `[remote_pricing]` section from a replication config file
+///
+/// ```toml
+/// [[remote_pricing.tiers]]
+/// max_size = "100GB"
+/// worker = "c6i.xlarge"
+/// hourly_usd = 0.17
+///
+/// [[remote_pricing.tiers]]
+/// worker = "c6i.4xlarge"
+/// hourly_usd = 0.68
+/// ```
+pub fn load_remote_pricing_config_from_file(path: &str) -> Result<Option<RemotePricingConfig>> {
+    Ok(read_config(path)?.remote_pricing)
+}
+
+/// Load the optional `[neon]` section from a replication config file
+///
+/// ```toml
+/// [neon]
+/// heartbeat_secs = 60              # 0 disables the heartbeat
+/// backup_branch_before_drop = true
+/// project_id = "cool-project-123456"
+/// ```
+pub fn load_neon_config_from_file(path: &str) -> Result<Option<NeonConfig>> {
+    Ok(read_config(path)?.neon)
+}
+
+/// Load the optional `[extensions]` section from a replication config file
+///
+/// ```toml
+/// [extensions]
+/// install = ["postgis", "vector"]  # or ["*"]
+/// ```
+pub fn load_extension_install_config_from_file(
+    path: &str,
+) -> Result<Option<ExtensionInstallConfig>> {
+    Ok(read_config(path)?.extensions)
+}
+
+pub fn load_table_rules_from_file(path: &str) -> Result<TableRules> {
+    let parsed = read_config(path)?;
 
     let mut rules = TableRules::default();
     for (db_name, db) in parsed.databases {
@@ -73,6 +395,37 @@ pub fn load_table_rules_from_file(path: &str) -> Result<TableRules> {
             };
             rules.add_time_filter(qualified, filter.column, filter.last)?;
         }
+        for transform in db.transforms {
+            let qualified = if let Some(schema) = transform.schema {
+                QualifiedTable::new(Some(db_name.clone()), schema, transform.table)
+            } else {
+                QualifiedTable::parse(&transform.table)?.with_database(Some(db_name.clone()))
+            };
+            rules.add_transform(qualified, transform.select)?;
+        }
+        for partition in db.partitions {
+            let qualified = if let Some(schema) = partition.schema {
+                QualifiedTable::new(Some(db_name.clone()), schema, partition.table)
+            } else {
+                QualifiedTable::parse(&partition.table)?.with_database(Some(db_name.clone()))
+            };
+            let rule = PartitionRule::parse(&format!(
+                "range({}):{}",
+                partition.column, partition.interval
+            ))?;
+            rules.add_partition_rule(qualified, rule)?;
+        }
+        for priority in db.priorities {
+            let qualified = if let Some(schema) = priority.schema {
+                QualifiedTable::new(Some(db_name.clone()), schema, priority.table)
+            } else {
+                QualifiedTable::parse(&priority.table)?.with_database(Some(db_name.clone()))
+            };
+            rules.add_priority(qualified, priority.priority)?;
+        }
+    }
+    if let Some(order) = parsed.copy_order {
+        rules.set_copy_order(order.parse()?);
     }
 
     Ok(rules)
@@ -81,6 +434,7 @@ pub fn load_table_rules_from_file(path: &str) -> Result<TableRules> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::table_rules::CopyOrder;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -155,6 +509,71 @@ mod tests {
         assert!(rules.table_filter("db1", "public", "orders").is_some());
     }
 
+    #[test]
+    fn test_toml_transforms() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            [[databases.db1.transforms]]
+            table = "users"
+            select = "SELECT id, lower(email) AS email FROM public.users;"
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let rules = load_table_rules_from_file(tmp.path().to_str().unwrap()).unwrap();
+        assert_eq!(
+            rules
+                .transform("db1", "public", "users")
+                .map(String::as_str),
+            Some("SELECT id, lower(email) AS email FROM public.users")
+        );
+        assert!(rules.transform("db2", "public", "users").is_none());
+    }
+
+    #[test]
+    fn test_toml_partitions() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            [[databases.db1.partitions]]
+            table = "events"
+            column = "created_at"
+            interval = "monthly"
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let rules = load_table_rules_from_file(tmp.path().to_str().unwrap()).unwrap();
+        let partitions = rules.partition_rules("db1");
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].1, "events");
+        assert_eq!(partitions[0].2.column, "created_at");
+        assert!(rules.partition_rules("db2").is_empty());
+    }
+
+    #[test]
+    fn test_toml_priorities_and_copy_order() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            copy_order = "smallest-first"
+
+            [[databases.db1.priorities]]
+            table = "orders"
+            priority = 10
+
+            [[databases.db1.priorities]]
+            table = "audit.events"
+            priority = -1
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let rules = load_table_rules_from_file(tmp.path().to_str().unwrap()).unwrap();
+        assert_eq!(rules.copy_order(), CopyOrder::SmallestFirst);
+        assert_eq!(rules.priority("db1", "public", "orders"), 10);
+        assert_eq!(rules.priority("db1", "audit", "events"), -1);
+        assert_eq!(rules.priority("db2", "public", "orders"), 0);
+    }
+
     #[test]
     fn test_toml_mixed_notation() {
         let mut tmp = NamedTempFile::new().unwrap();
@@ -187,4 +606,323 @@ mod tests {
         // Check default to public when no schema
         assert!(rules.table_filter("db1", "public", "logs").is_some());
     }
+
+    #[test]
+    fn test_retry_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            [retry]
+            max_retries = 5
+            max_delay_secs = 120
+
+            [databases.kong]
+            schema_only = ["price"]
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let retry = load_retry_config_from_file(path).unwrap().unwrap();
+        assert_eq!(retry.max_retries, Some(5));
+        assert_eq!(retry.base_delay_secs, None);
+        assert_eq!(retry.max_delay_secs, Some(120));
+
+        // Table rules still load from the same file
+        let rules = load_table_rules_from_file(path).unwrap();
+        assert_eq!(rules.schema_only_tables("kong").len(), 1);
+    }
+
+    #[test]
+    fn test_retry_section_absent() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(tmp, "[databases.kong]").unwrap();
+
+        let retry = load_retry_config_from_file(tmp.path().to_str().unwrap()).unwrap();
+        assert!(retry.is_none());
+    }
+
+    #[test]
+    fn test_hooks_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            [databases.kong]
+            schema_only = ["price"]
+
+            [hooks]
+            post-schema = [{ sql = "hooks/rls.sql" }]
+
+            [[hooks.post-data]]
+            command = "./grant_roles.sh"
+            database = "kong"
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let hooks = load_hooks_config_from_file(tmp.path().to_str().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(hooks.post_schema[0].sql.as_deref(), Some("hooks/rls.sql"));
+        assert_eq!(hooks.post_data[0].database.as_deref(), Some("kong"));
+        assert!(hooks.pre_init.is_empty());
+    }
+
+    #[test]
+    fn test_object_policy_sections() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            [foreign_servers]
+            policy = "skip"
+
+            [event_triggers]
+            policy = "defer"
+
+            [unsupported_objects]
+            policy = "fail"
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_foreign_server_config_from_file(path).unwrap().unwrap();
+        assert_eq!(
+            config.policy,
+            crate::migration::foreign_servers::ForeignServerPolicy::Skip
+        );
+        let config = load_event_trigger_config_from_file(path).unwrap().unwrap();
+        assert_eq!(
+            config.policy,
+            crate::migration::event_triggers::EventTriggerPolicy::Defer
+        );
+        let config = load_unsupported_object_config_from_file(path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            config.policy,
+            crate::migration::unsupported::UnsupportedObjectPolicy::Fail
+        );
+    }
+
+    #[test]
+    fn test_parallelism_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[parallelism]\nmax_total_jobs = 2\nsplit_tables_larger_than = \"200GB\"\nmax_memory = \"2G\""
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_parallelism_config_from_file(path).unwrap().unwrap();
+        assert_eq!(config.max_total_jobs, Some(2));
+        assert_eq!(config.split_tables_larger_than.as_deref(), Some("200GB"));
+        assert_eq!(config.max_memory.as_deref(), Some("2G"));
+    }
+
+    #[test]
+    fn test_publication_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        let contents = r#"
+            [publication]
+            publish = "insert,update,delete"
+
+            [[publication.groups]]
+            name = "append_only"
+            tables = ["events", "audit.log"]
+            publish = "insert"
+        "#;
+        use std::io::Write;
+        write!(tmp, "{}", contents).unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_publication_config_from_file(path).unwrap().unwrap();
+        assert_eq!(config.publish.as_deref(), Some("insert,update,delete"));
+        assert_eq!(config.groups[0].name, "append_only");
+        assert_eq!(config.groups[0].tables, vec!["events", "audit.log"]);
+    }
+
+    #[test]
+    fn test_subscription_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(tmp, "[subscription]\nstreaming = \"parallel\"").unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_subscription_config_from_file(path).unwrap().unwrap();
+        assert_eq!(config.streaming.as_deref(), Some("parallel"));
+    }
+
+    #[test]
+    fn test_onboard_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(tmp, "[onboard]\nrequire_table_approval = true").unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_onboard_config_from_file(path).unwrap().unwrap();
+        assert!(config.require_table_approval);
+        assert!(config.new_tables);
+    }
+
+    #[test]
+    fn test_jsonb_batching_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[jsonb_batching.mysql]\nmax_rows = 500\nmax_batch_size = \"4MB\"\n\n\
+             [jsonb_batching.mongodb]\ntarget_latency_ms = 250"
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_jsonb_batching_config_from_file(path).unwrap().unwrap();
+        assert_eq!(config["mysql"].max_rows, Some(500));
+        assert_eq!(config["mysql"].max_batch_size.as_deref(), Some("4MB"));
+        assert_eq!(config["mongodb"].target_latency_ms, Some(250));
+    }
+
+    #[test]
+    fn test_jsonb_partitioning_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[jsonb_partitioning.events]\nfield = \"tenant_id\"\npartitions = 16\n\n\
+             [jsonb_partitioning.users]\npartitions = 4"
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_jsonb_partitioning_config_from_file(path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(config["events"].field.as_deref(), Some("tenant_id"));
+        assert_eq!(config["events"].partitions, 16);
+        assert_eq!(config["users"].field, None);
+    }
+
+    #[test]
+    fn test_jsonb_target_schema_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[jsonb_target_schema]\nmysql = \"mysql_import\"\nmongodb = \"mongo_import\""
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_jsonb_target_schema_config_from_file(path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.mysql.as_deref(), Some("mysql_import"));
+        assert_eq!(config.mongodb.as_deref(), Some("mongo_import"));
+        assert_eq!(config.sqlite, None);
+    }
+
+    #[test]
+    fn test_bson_types_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(tmp, "[bson_types]\nobjectid = \"text\"\nregex = \"text\"").unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_bson_types_config_from_file(path).unwrap().unwrap();
+        assert_eq!(
+            config.objectid,
+            crate::mongodb::type_map::ObjectIdMapping::Text
+        );
+        assert_eq!(
+            config.decimal128,
+            crate::mongodb::type_map::DecimalMapping::Text
+        );
+    }
+
+    #[test]
+    fn test_telemetry_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[telemetry]\nenabled = true\nendpoint = \"https://telemetry.example.com/events\""
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_telemetry_config_from_file(path).unwrap().unwrap();
+        assert!(config.enabled);
+        assert_eq!(
+            config.endpoint.as_deref(),
+            Some("https://telemetry.example.com/events")
+        );
+    }
+
+    #[test]
+    fn test_remote_pricing_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[[remote_pricing.tiers]]\nmax_size = \"100GB\"\nworker = \"small\"\nhourly_usd = 0.5\n\n[[remote_pricing.tiers]]\nworker = \"large\"\nhourly_usd = 2.0"
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_remote_pricing_config_from_file(path).unwrap().unwrap();
+        assert_eq!(config.tiers.len(), 2);
+        assert_eq!(config.tiers[0].max_size.as_deref(), Some("100GB"));
+        assert_eq!(config.tiers[1].worker, "large");
+        assert!(config.tiers[1].max_size.is_none());
+    }
+
+    #[test]
+    fn test_smoke_queries_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[[smoke_queries]]\nname = \"admin\"\ndatabases = [\"app\"]\nsql = \"SELECT 1\"\nexpect = \"1\"\n\n[[smoke_queries]]\nname = \"orders\"\nsql = \"SELECT * FROM orders\"\nmin_rows = 10"
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let queries = load_smoke_queries_from_file(path).unwrap();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].databases, vec!["app"]);
+        assert_eq!(queries[0].expect.as_deref(), Some("1"));
+        assert!(queries[1].databases.is_empty());
+        assert_eq!(queries[1].min_rows, Some(10));
+    }
+
+    #[test]
+    fn test_neon_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(
+            tmp,
+            "[neon]\nheartbeat_secs = 30\nbackup_branch_before_drop = true\nproject_id = \"proj-1\""
+        )
+        .unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_neon_config_from_file(path).unwrap().unwrap();
+        assert_eq!(config.heartbeat_secs, Some(30));
+        assert_eq!(config.backup_branch_before_drop, Some(true));
+        assert_eq!(config.project_id.as_deref(), Some("proj-1"));
+    }
+
+    #[test]
+    fn test_extensions_section() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        use std::io::Write;
+        writeln!(tmp, "[extensions]\ninstall = [\"postgis\", \"vector\"]").unwrap();
+
+        let path = tmp.path().to_str().unwrap();
+        let config = load_extension_install_config_from_file(path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.install, vec!["postgis", "vector"]);
+    }
 }
@@ -0,0 +1,220 @@
+// ABOUTME: Confirmation tokens for destructive runs in automation (`init --drop-existing --yes`, `sync --force`)
+// ABOUTME: Each target database a run would drop, or drop the subscription of, must be named with --confirm
+
+use crate::errors::ReplicatorError;
+use anyhow::{Context, Result};
+use tokio_postgres::Client;
+
+/// Environment variable that, set to `0`, lets `--drop-existing --yes` and `sync --force` drop without --confirm
+pub const REQUIRE_ENV: &str = "SEREN_REQUIRE_CONFIRM";
+
+/// Confirmation settings of one run, set from the CLI
+///
+/// Confirmation is required by default, so runs without a prompt (`--yes`,
+/// `sync --force`, [`crate::MigrationJob`]) drop nothing that was not named.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmSettings {
+    /// Database names passed with --confirm
+    pub tokens: Vec<String>,
+    /// Refuse to drop anything when no --confirm is given
+    pub required: bool,
+}
+
+impl Default for ConfirmSettings {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            required: true,
+        }
+    }
+}
+
+impl ConfirmSettings {
+    /// Settings for the given --confirm values, required unless `SEREN_REQUIRE_CONFIRM` is falsy
+    pub fn from_env(tokens: Vec<String>) -> Self {
+        Self {
+            tokens,
+            required: required_by(std::env::var(REQUIRE_ENV).ok().as_deref()),
+        }
+    }
+}
+
+/// Whether a `SEREN_REQUIRE_CONFIRM` value keeps confirmation required
+fn required_by(value: Option<&str>) -> bool {
+    !value.is_some_and(|value| matches!(value.trim(), "0" | "false" | "no" | "off"))
+}
+
+/// What a destructive run drops in each database it must be confirmed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropKind {
+    /// `init --drop-existing` drops the target database itself
+    Database,
+    /// `sync --force` drops the database's subscription, and with it the source replication slot
+    Subscription,
+}
+
+impl DropKind {
+    fn flag(self) -> &'static str {
+        match self {
+            DropKind::Database => "--drop-existing",
+            DropKind::Subscription => "sync --force",
+        }
+    }
+
+    fn describe(self, databases: &[String]) -> String {
+        match self {
+            DropKind::Database => quoted(databases),
+            DropKind::Subscription => format!(
+                "the subscription and source replication slot of {}",
+                quoted(databases)
+            ),
+        }
+    }
+}
+
+/// Check that every database about to be dropped, or to lose its subscription, was named with --confirm
+///
+/// With no tokens and confirmation opted out (`SEREN_REQUIRE_CONFIRM=0`),
+/// this only warns.
+///
+/// # Errors
+///
+/// Returns `SR-0036` listing the databases that are not confirmed.
+pub fn check_drop(kind: DropKind, at_risk: &[String], settings: &ConfirmSettings) -> Result<()> {
+    if at_risk.is_empty() {
+        return Ok(());
+    }
+    if settings.tokens.is_empty() && !settings.required {
+        tracing::warn!(
+            "⚠ {} will drop {} on the target without --confirm",
+            kind.flag(),
+            kind.describe(at_risk)
+        );
+        tracing::warn!(
+            "  {}=0 is set; pass --confirm {} to guard against a misconfigured --target",
+            REQUIRE_ENV,
+            at_risk.join(",")
+        );
+        return Ok(());
+    }
+    let unconfirmed: Vec<String> = at_risk
+        .iter()
+        .filter(|name| !settings.tokens.contains(name))
+        .cloned()
+        .collect();
+    if !unconfirmed.is_empty() {
+        return Err(ReplicatorError::DropNotConfirmed {
+            flag: kind.flag().to_string(),
+            databases: kind.describe(&unconfirmed),
+        }
+        .into());
+    }
+    tracing::info!("✓ Drop of {} confirmed", kind.describe(at_risk));
+    Ok(())
+}
+
+/// Which of `databases` already exist on the target, and so would be dropped by --drop-existing
+///
+/// # Errors
+///
+/// Returns an error if the target catalog cannot be read.
+pub async fn databases_at_risk(target: &Client, databases: &[String]) -> Result<Vec<String>> {
+    let rows = target
+        .query(
+            "SELECT datname FROM pg_database WHERE datname = ANY($1) ORDER BY datname",
+            &[&databases],
+        )
+        .await
+        .context("Failed to list existing databases on target")?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Which databases already have their subscription on the target, and so would lose it to `sync --force`
+///
+/// `subscriptions` pairs each database with the subscription name sync uses for it.
+///
+/// # Errors
+///
+/// Returns an error if the target catalog cannot be read.
+pub async fn subscriptions_at_risk(
+    target: &Client,
+    subscriptions: &[(String, String)],
+) -> Result<Vec<String>> {
+    let rows = target
+        .query(
+            "SELECT d.datname, s.subname FROM pg_subscription s
+             JOIN pg_database d ON d.oid = s.subdbid
+             ORDER BY d.datname",
+            &[],
+        )
+        .await
+        .context("Failed to list existing subscriptions on target")?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get::<_, String>(0), row.get::<_, String>(1)))
+        .filter(|existing| subscriptions.contains(existing))
+        .map(|(database, _)| database)
+        .collect())
+}
+
+fn quoted(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(tokens: &[&str], required: bool) -> ConfirmSettings {
+        ConfirmSettings {
+            tokens: tokens.iter().map(|t| t.to_string()).collect(),
+            required,
+        }
+    }
+
+    #[test]
+    fn test_check_drop() {
+        let at_risk = vec!["app".to_string(), "analytics".to_string()];
+        let kind = DropKind::Database;
+
+        assert!(check_drop(kind, &at_risk, &settings(&[], false)).is_ok());
+        assert!(check_drop(kind, &at_risk, &settings(&["app", "analytics"], false)).is_ok());
+        assert!(check_drop(kind, &[], &settings(&[], true)).is_ok());
+
+        let err = check_drop(kind, &at_risk, &settings(&["app"], false)).unwrap_err();
+        assert!(err.to_string().contains("'analytics'"));
+        assert!(!err.to_string().contains("'app'"));
+
+        let err = check_drop(kind, &at_risk, &settings(&[], true)).unwrap_err();
+        assert!(err.to_string().contains("'app', 'analytics'"));
+    }
+
+    #[test]
+    fn test_confirmation_required_unless_opted_out() {
+        assert!(ConfirmSettings::default().required);
+        assert!(required_by(None));
+        assert!(required_by(Some("1")));
+        assert!(required_by(Some("")));
+        assert!(!required_by(Some("0")));
+        assert!(!required_by(Some(" false ")));
+        assert!(!required_by(Some("off")));
+    }
+
+    #[test]
+    fn test_check_drop_subscription() {
+        let at_risk = vec!["app".to_string()];
+        let kind = DropKind::Subscription;
+
+        assert!(check_drop(kind, &at_risk, &settings(&["app"], true)).is_ok());
+
+        let err = check_drop(kind, &at_risk, &settings(&["analytics"], false)).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("sync --force would drop the subscription"));
+        assert!(err.to_string().contains("'app'"));
+    }
+}
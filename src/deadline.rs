@@ -0,0 +1,208 @@
+// ABOUTME: Time-boxed runs (--deadline / --max-runtime) that stop starting new work before a window closes
+// ABOUTME: init checks the deadline between databases, phases, and tables, and stops at a resumable point
+
+use crate::errors::ReplicatorError;
+use anyhow::{bail, Result};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Largest safety margin kept before the deadline
+const MAX_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// When a time-boxed run must stop starting new work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    /// The deadline itself, in seconds since the Unix epoch
+    pub at: u64,
+    /// No new table or phase starts within this many seconds of `at`
+    pub margin: u64,
+}
+
+impl Deadline {
+    /// Combine `--deadline` and `--max-runtime`; the earlier one wins
+    ///
+    /// The margin is a tenth of the time left, at most five minutes, so the
+    /// unit of work already running has room to finish and checkpoint.
+    pub fn new(now: u64, deadline: Option<u64>, max_runtime: Option<Duration>) -> Option<Self> {
+        let runtime_end = max_runtime.map(|runtime| now + runtime.as_secs());
+        let at = match (deadline, runtime_end) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(a), None) | (None, Some(a)) => a,
+            (None, None) => return None,
+        };
+        let margin = (at.saturating_sub(now) / 10).min(MAX_MARGIN.as_secs());
+        Some(Deadline { at, margin })
+    }
+
+    /// The moment after which no new work starts
+    pub fn stop_at(&self) -> u64 {
+        self.at.saturating_sub(self.margin)
+    }
+
+    /// Whether work starting at `now` should be left for the next run
+    pub fn should_stop(&self, now: u64) -> bool {
+        now >= self.stop_at()
+    }
+
+    /// Log when the run stops starting new work
+    pub fn log(&self) {
+        tracing::info!(
+            "ℹ Time-boxed run: no new work starts after {} (deadline {})",
+            crate::audit::format_utc(self.stop_at()),
+            crate::audit::format_utc(self.at)
+        );
+    }
+}
+
+/// Parse `--deadline`: `HH:MM` (the next time the UTC clock reads it) or a
+/// UTC timestamp `YYYY-MM-DDTHH:MM[:SS][Z]`
+///
+/// # Errors
+///
+/// Returns an error for another format or a timestamp already in the past.
+pub fn parse_deadline(value: &str, now: u64) -> Result<u64> {
+    let value = value.trim();
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid --deadline '{}'.\n\
+             Use HH:MM in UTC (e.g. 04:00) or a UTC timestamp (e.g. 2026-03-01T04:00Z).",
+            value
+        )
+    };
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (Some(date), time),
+        None => (None, value),
+    };
+    let time = time.strip_suffix('Z').unwrap_or(time);
+    let mut fields = time.split(':');
+    let mut next = |max: u64| -> Result<Option<u64>> {
+        match fields.next() {
+            None => Ok(None),
+            Some(field) => match field.parse::<u64>() {
+                Ok(n) if n <= max && field.len() == 2 => Ok(Some(n)),
+                _ => Err(invalid()),
+            },
+        }
+    };
+    let (Some(hour), Some(minute)) = (next(23)?, next(59)?) else {
+        return Err(invalid());
+    };
+    let second = next(59)?.unwrap_or(0);
+    if next(59)?.is_some() {
+        return Err(invalid());
+    }
+    let time_of_day = hour * 3600 + minute * 60 + second;
+
+    let Some(date) = date else {
+        let today = now - now % 86_400;
+        let at = today + time_of_day;
+        return Ok(if at > now { at } else { at + 86_400 });
+    };
+    let parts: Vec<i64> = date
+        .split('-')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    let [year, month, day] = parts[..] else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    let days = crate::jsonb::metadata::days_from_civil(year, month, day);
+    let at = u64::try_from(days).map_err(|_| invalid())? * 86_400 + time_of_day;
+    if at <= now {
+        bail!(
+            "--deadline {} is already in the past (now {}).\n\
+             Pass a future UTC time, or HH:MM for the next time the clock reads it.",
+            crate::audit::format_utc(at),
+            crate::audit::format_utc(now)
+        );
+    }
+    Ok(at)
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Fail with [`ReplicatorError::DeadlineReached`] once the deadline is near
+///
+/// Called next to [`crate::cancel::check`] before each database, restore
+/// phase, and JSONB table, so a time-boxed run stops at a point the next run
+/// resumes from. `next` names the work that was not started, for the log.
+///
+/// # Errors
+///
+/// Returns [`ReplicatorError::DeadlineReached`] when no new work may start.
+pub fn check(deadline: Option<Deadline>, next: &str) -> Result<()> {
+    let Some(deadline) = deadline else {
+        return Ok(());
+    };
+    if !deadline.should_stop(now()) {
+        return Ok(());
+    }
+    tracing::warn!(
+        "⚠ Deadline near: not starting {}; progress so far is kept for the next run",
+        next
+    );
+    Err(ReplicatorError::DeadlineReached {
+        deadline: crate::audit::format_utc(deadline.at),
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-03-01T02:30:00Z
+    const NOW: u64 = 1_772_332_200;
+
+    #[test]
+    fn test_parse_deadline_clock_and_timestamp() {
+        assert_eq!(parse_deadline("04:00", NOW).unwrap(), NOW + 90 * 60);
+        // 02:00 has passed today, so it means tomorrow
+        assert_eq!(
+            parse_deadline("02:00", NOW).unwrap(),
+            NOW - 30 * 60 + 86_400
+        );
+        assert_eq!(
+            parse_deadline("2026-03-01T04:00Z", NOW).unwrap(),
+            NOW + 90 * 60
+        );
+        assert_eq!(
+            parse_deadline("2026-03-02T04:00:30", NOW).unwrap(),
+            NOW + 90 * 60 + 86_400 + 30
+        );
+        assert!(parse_deadline("2026-03-01T02:00Z", NOW)
+            .unwrap_err()
+            .to_string()
+            .contains("in the past"));
+        for bad in [
+            "4",
+            "24:00",
+            "4:00",
+            "04:00:00:00",
+            "tomorrow",
+            "2026-13-01T04:00",
+        ] {
+            assert!(parse_deadline(bad, NOW).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_earliest_limit_wins_and_margin_is_capped() {
+        assert_eq!(Deadline::new(NOW, None, None), None);
+        let both = Deadline::new(NOW, Some(NOW + 3600), Some(Duration::from_secs(600))).unwrap();
+        assert_eq!(both.at, NOW + 600);
+        assert_eq!(both.margin, 60);
+
+        let long = Deadline::new(NOW, None, Some(Duration::from_secs(6 * 3600))).unwrap();
+        assert_eq!(long.margin, 300);
+        assert!(!long.should_stop(NOW + 6 * 3600 - 301));
+        assert!(long.should_stop(NOW + 6 * 3600 - 300));
+    }
+}
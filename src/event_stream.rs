@@ -0,0 +1,289 @@
+// ABOUTME: Machine-readable event stream - one JSON object per line for each state transition of a run
+// ABOUTME: Written by `--events-file` or `--porcelain` so wrappers can build their own UIs without parsing logs
+
+use crate::errors::ErrorReport;
+use crate::events::{Plan, Progress, ReplicationObserver, TableEvent, TableOutcome};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+
+/// One line of the stream, tagged by `event`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum StreamEvent<'a> {
+    Started {
+        command: &'a str,
+        version: &'static str,
+    },
+    Plan {
+        phase: &'static str,
+        databases: &'a [String],
+        tables: &'a [String],
+    },
+    TableStarted {
+        phase: &'static str,
+        database: &'a str,
+        schema: &'a str,
+        table: &'a str,
+    },
+    TableDone {
+        phase: &'static str,
+        database: &'a str,
+        schema: &'a str,
+        table: &'a str,
+        status: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rows: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<&'a str>,
+    },
+    Progress {
+        phase: &'static str,
+        database: &'a str,
+        completed: u64,
+        total: u64,
+        message: &'a str,
+    },
+    Warning {
+        message: &'a str,
+    },
+    Error {
+        #[serde(flatten)]
+        error: &'a ErrorReport,
+    },
+    Finished {
+        status: &'static str,
+        elapsed_ms: u64,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    /// Milliseconds since the Unix epoch
+    ts: u64,
+    #[serde(flatten)]
+    event: StreamEvent<'a>,
+}
+
+/// Writes every replication event as newline-delimited JSON
+///
+/// Register a clone with [`crate::events::register`] for table and plan
+/// events, and add [`EventStream::layer`] to the tracing subscriber so every
+/// logged warning is streamed as it happens. Call [`EventStream::start`] and
+/// [`EventStream::finish`] around the command.
+#[derive(Clone)]
+pub struct EventStream {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+    started: std::time::Instant,
+}
+
+impl EventStream {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        EventStream {
+            out: Arc::new(Mutex::new(out)),
+            started: std::time::Instant::now(),
+        }
+    }
+
+    /// Stream to a file, replacing any previous contents
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create events file {}", path.display()))?;
+        Ok(Self::new(Box::new(LineWriter::new(file))))
+    }
+
+    /// Stream to stdout, for `--porcelain`
+    pub fn stdout() -> Self {
+        Self::new(Box::new(std::io::stdout()))
+    }
+
+    /// Tracing layer that streams WARN events
+    pub fn layer(&self) -> WarningEvents {
+        WarningEvents(self.clone())
+    }
+
+    /// Announce the command before any work starts
+    pub fn start(&self, command: &str) {
+        self.emit(StreamEvent::Started {
+            command,
+            version: env!("CARGO_PKG_VERSION"),
+        });
+    }
+
+    /// Close the stream with the command's result; a failure also gets an `error` event
+    pub fn finish(&self, result: &Result<()>) {
+        if let Err(error) = result {
+            self.emit(StreamEvent::Error {
+                error: &ErrorReport::from_error(error),
+            });
+        }
+        self.emit(StreamEvent::Finished {
+            status: if result.is_ok() { "ok" } else { "error" },
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+        });
+    }
+
+    fn emit(&self, event: StreamEvent<'_>) {
+        let line = Line {
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            event,
+        };
+        let Ok(json) = serde_json::to_string(&line) else {
+            return;
+        };
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // A closed pipe must not fail the run; the wrapper has gone away
+        let _ = writeln!(out, "{}", json).and_then(|()| out.flush());
+    }
+}
+
+impl ReplicationObserver for EventStream {
+    fn on_plan(&self, plan: &Plan) {
+        self.emit(StreamEvent::Plan {
+            phase: plan.phase.as_str(),
+            databases: &plan.databases,
+            tables: &plan.tables,
+        });
+    }
+
+    fn on_table_start(&self, table: &TableEvent) {
+        self.emit(StreamEvent::TableStarted {
+            phase: table.phase.as_str(),
+            database: &table.database,
+            schema: &table.schema,
+            table: &table.table,
+        });
+    }
+
+    fn on_table_complete(&self, table: &TableEvent, outcome: &TableOutcome) {
+        let (status, rows, detail) = match outcome {
+            TableOutcome::Success { rows } => ("success", *rows, None),
+            TableOutcome::Mismatch { detail } => ("mismatch", None, Some(detail.as_str())),
+            TableOutcome::Failed { error } => ("failed", None, Some(error.as_str())),
+        };
+        self.emit(StreamEvent::TableDone {
+            phase: table.phase.as_str(),
+            database: &table.database,
+            schema: &table.schema,
+            table: &table.table,
+            status,
+            rows,
+            detail,
+        });
+    }
+
+    fn on_progress(&self, progress: &Progress) {
+        self.emit(StreamEvent::Progress {
+            phase: progress.phase.as_str(),
+            database: &progress.database,
+            completed: progress.completed,
+            total: progress.total,
+            message: &progress.message,
+        });
+    }
+
+    // Warnings arrive through `WarningEvents` instead: callers of
+    // `events::warning` also log, and most warnings are only logged.
+}
+
+/// Streams each logged warning as a `warning` event
+pub struct WarningEvents(EventStream);
+
+impl<S: Subscriber> Layer<S> for WarningEvents {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        if let Some(message) = crate::warnings::warning_message(event) {
+            self.0.emit(StreamEvent::Warning { message: &message });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Phase;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn lines(buffer: &Buffer) -> Vec<serde_json::Value> {
+        String::from_utf8(buffer.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_writes_one_tagged_object_per_event() {
+        let buffer = Buffer::default();
+        let stream = EventStream::new(Box::new(buffer.clone()));
+        let table = TableEvent::new(Phase::Copy, "app", "public", "users");
+
+        stream.start("init");
+        stream.on_plan(&Plan {
+            phase: Phase::Copy,
+            databases: vec!["app".to_string()],
+            tables: vec!["users".to_string()],
+        });
+        stream.on_table_start(&table);
+        stream.on_table_complete(&table, &TableOutcome::Success { rows: Some(7) });
+        stream.finish(&Ok(()));
+
+        let events = lines(&buffer);
+        let kinds: Vec<_> = events.iter().map(|e| e["event"].clone()).collect();
+        assert_eq!(
+            kinds,
+            vec!["started", "plan", "table_started", "table_done", "finished"]
+        );
+        assert_eq!(events[1]["tables"][0], "users");
+        assert_eq!(events[3]["status"], "success");
+        assert_eq!(events[3]["rows"], 7);
+        assert!(events[3].get("detail").is_none());
+        assert_eq!(events[4]["status"], "ok");
+        assert!(events.iter().all(|e| e["ts"].as_u64().is_some()));
+    }
+
+    #[test]
+    fn test_failure_emits_error_before_finished() {
+        let buffer = Buffer::default();
+        let stream = EventStream::new(Box::new(buffer.clone()));
+        let table = TableEvent::new(Phase::Verify, "app", "public", "orders");
+
+        stream.on_table_complete(
+            &table,
+            &TableOutcome::Mismatch {
+                detail: "row count differs".to_string(),
+            },
+        );
+        stream.finish(&Err(anyhow::anyhow!("target unreachable")));
+
+        let events = lines(&buffer);
+        assert_eq!(events[0]["status"], "mismatch");
+        assert_eq!(events[0]["detail"], "row count differs");
+        assert_eq!(events[1]["event"], "error");
+        assert_eq!(events[1]["message"], "target unreachable");
+        assert_eq!(events[2]["event"], "finished");
+        assert_eq!(events[2]["status"], "error");
+    }
+}
@@ -0,0 +1,297 @@
+// ABOUTME: Observer hooks for table-level progress, so embedders can drive their own UIs and metrics
+// ABOUTME: The CLI's progress bars are one observer registered through the same interface
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Stage of the work an event belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Copying data to the target (init)
+    Copy,
+    /// Comparing source and target (verify)
+    Verify,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Copy => "copy",
+            Phase::Verify => "verify",
+        }
+    }
+}
+
+/// A table (or collection) being copied or verified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableEvent {
+    pub phase: Phase,
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+}
+
+impl TableEvent {
+    pub fn new(phase: Phase, database: &str, schema: &str, table: &str) -> Self {
+        TableEvent {
+            phase,
+            database: database.to_string(),
+            schema: schema.to_string(),
+            table: table.to_string(),
+        }
+    }
+}
+
+/// How a table finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableOutcome {
+    /// Copied or verified clean; `rows` when known
+    Success { rows: Option<u64> },
+    /// Verified with differences between source and target
+    Mismatch { detail: String },
+    /// The operation failed
+    Failed { error: String },
+}
+
+/// Units of work finished so far in one phase of one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub phase: Phase,
+    /// Empty when the units are whole databases
+    pub database: String,
+    pub completed: u64,
+    pub total: u64,
+    pub message: String,
+}
+
+/// The work a phase is about to do, once it is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub phase: Phase,
+    pub databases: Vec<String>,
+    /// Empty when tables are only discovered per database later on
+    pub tables: Vec<String>,
+}
+
+/// Receives replication events
+///
+/// All methods default to doing nothing, so implementors override only what
+/// they need. Calls come from worker tasks and may arrive concurrently, so
+/// implementations must be cheap and must not block.
+///
+/// # Examples
+///
+/// ```
+/// # use seren_replicator::events::{self, ReplicationObserver, TableEvent, TableOutcome};
+/// # use std::sync::Arc;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// #[derive(Default)]
+/// struct TableCounter(AtomicUsize);
+///
+/// impl ReplicationObserver for TableCounter {
+///     fn on_table_complete(&self, _table: &TableEvent, _outcome: &TableOutcome) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let counter = Arc::new(TableCounter::default());
+/// let _registration = events::register(counter.clone());
+/// // ... run a MigrationJob; the counter sees every finished table
+/// ```
+pub trait ReplicationObserver: Send + Sync {
+    fn on_plan(&self, _plan: &Plan) {}
+    fn on_table_start(&self, _table: &TableEvent) {}
+    fn on_table_complete(&self, _table: &TableEvent, _outcome: &TableOutcome) {}
+    fn on_progress(&self, _progress: &Progress) {}
+    fn on_warning(&self, _message: &str) {}
+}
+
+type Observers = Vec<(u64, Arc<dyn ReplicationObserver>)>;
+
+static OBSERVERS: RwLock<Observers> = RwLock::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Keeps an observer registered until dropped
+#[must_use = "the observer is unregistered when this is dropped"]
+pub struct Registration {
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut observers = OBSERVERS.write().unwrap_or_else(|e| e.into_inner());
+        observers.retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Register an observer for all replication work in this process
+pub fn register(observer: Arc<dyn ReplicationObserver>) -> Registration {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    OBSERVERS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, observer));
+    Registration { id }
+}
+
+fn each(f: impl Fn(&dyn ReplicationObserver)) {
+    let observers = OBSERVERS.read().unwrap_or_else(|e| e.into_inner());
+    for (_, observer) in observers.iter() {
+        f(observer.as_ref());
+    }
+}
+
+/// Notify observers of the databases (and tables, when known) a phase will cover
+pub fn plan(phase: Phase, databases: Vec<String>, tables: Vec<String>) {
+    let plan = Plan {
+        phase,
+        databases,
+        tables,
+    };
+    each(|o| o.on_plan(&plan));
+}
+
+/// Notify observers that a table has started
+pub fn table_started(table: &TableEvent) {
+    each(|o| o.on_table_start(table));
+}
+
+/// Notify observers that a table has finished
+pub fn table_completed(table: &TableEvent, outcome: &TableOutcome) {
+    each(|o| o.on_table_complete(table, outcome));
+}
+
+/// Notify observers of progress within a phase
+pub fn progress(phase: Phase, database: &str, completed: u64, total: u64, message: String) {
+    let progress = Progress {
+        phase,
+        database: database.to_string(),
+        completed,
+        total,
+        message,
+    };
+    each(|o| o.on_progress(&progress));
+}
+
+/// Notify observers of a warning; callers still log it
+pub fn warning(message: &str) {
+    each(|o| o.on_warning(message));
+}
+
+/// Terminal progress bars, one per database and phase, as shown by the CLI
+#[derive(Default)]
+pub struct ProgressBars {
+    current: Mutex<Option<((Phase, String), ProgressBar)>>,
+}
+
+impl ReplicationObserver for ProgressBars {
+    fn on_progress(&self, progress: &Progress) {
+        let key = (progress.phase, progress.database.clone());
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if current.as_ref().map(|(k, _)| k) != Some(&key) {
+            if let Some((_, bar)) = current.take() {
+                bar.abandon();
+            }
+            let bar = ProgressBar::new(progress.total);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
+                    .unwrap()
+                    .progress_chars("##-"),
+            );
+            *current = Some((key, bar));
+        }
+        let Some((_, bar)) = current.as_ref() else {
+            return;
+        };
+        bar.set_length(progress.total);
+        bar.set_position(progress.completed);
+        if progress.completed >= progress.total {
+            bar.finish_with_message(progress.message.clone());
+            *current = None;
+        } else {
+            bar.set_message(progress.message.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl ReplicationObserver for Recorder {
+        fn on_table_start(&self, table: &TableEvent) {
+            self.push(format!("start {}.{}", table.schema, table.table));
+        }
+        fn on_table_complete(&self, table: &TableEvent, outcome: &TableOutcome) {
+            self.push(format!(
+                "done {}.{} {:?}",
+                table.schema, table.table, outcome
+            ));
+        }
+        fn on_progress(&self, progress: &Progress) {
+            self.push(format!(
+                "{} {}/{}",
+                progress.phase.as_str(),
+                progress.completed,
+                progress.total
+            ));
+        }
+        fn on_warning(&self, message: &str) {
+            self.push(format!("warn {}", message));
+        }
+    }
+
+    impl Recorder {
+        fn push(&self, event: String) {
+            // Observers are process-wide; ignore events from other tests
+            if event.contains("observer_test") {
+                self.events.lock().unwrap().push(event);
+            }
+        }
+    }
+
+    #[test]
+    fn test_registered_observer_receives_events_until_dropped() {
+        let recorder = Arc::new(Recorder::default());
+        let registration = register(recorder.clone());
+
+        let table = TableEvent::new(Phase::Verify, "app", "public", "observer_test");
+        table_started(&table);
+        table_completed(&table, &TableOutcome::Success { rows: Some(3) });
+        warning("observer_test slow table");
+        drop(registration);
+        table_started(&table);
+
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "start public.observer_test".to_string(),
+                "done public.observer_test Success { rows: Some(3) }".to_string(),
+                "warn observer_test slow table".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_progress_bars_handle_database_changes() {
+        let bars = ProgressBars::default();
+        let update = |database: &str, completed, total| Progress {
+            phase: Phase::Verify,
+            database: database.to_string(),
+            completed,
+            total,
+            message: String::new(),
+        };
+        bars.on_progress(&update("a", 1, 3));
+        bars.on_progress(&update("b", 1, 2));
+        bars.on_progress(&update("b", 2, 2));
+        assert!(bars.current.lock().unwrap().is_none());
+    }
+}
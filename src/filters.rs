@@ -1,9 +1,10 @@
 // ABOUTME: Central filtering logic for selective replication
 // ABOUTME: Handles database and table include/exclude patterns
 
-use crate::table_rules::TableRules;
-use anyhow::{bail, Result};
+use crate::table_rules::{PartitionRule, TableRules};
+use anyhow::{bail, Context, Result};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
 use tokio_postgres::Client;
 
 /// Represents replication filtering rules
@@ -14,6 +15,8 @@ pub struct ReplicationFilter {
     include_tables: Option<Vec<String>>, // Format: "db.table"
     exclude_tables: Option<Vec<String>>, // Format: "db.table"
     table_rules: TableRules,
+    /// Schemas found in the source catalog for two-part `db.table` entries
+    resolved_schemas: BTreeMap<(String, String), String>,
 }
 
 impl ReplicationFilter {
@@ -32,26 +35,9 @@ impl ReplicationFilter {
             bail!("Cannot use both --include-tables and --exclude-tables");
         }
 
-        // Validate table format (must be "database.table")
-        if let Some(ref tables) = include_tables {
-            for table in tables {
-                if !table.contains('.') {
-                    bail!(
-                        "Table must be specified as 'database.table', got '{}'",
-                        table
-                    );
-                }
-            }
-        }
-        if let Some(ref tables) = exclude_tables {
-            for table in tables {
-                if !table.contains('.') {
-                    bail!(
-                        "Table must be specified as 'database.table', got '{}'",
-                        table
-                    );
-                }
-            }
+        // Validate table format (must be "database.table" or "database.schema.table")
+        for table in include_tables.iter().chain(exclude_tables.iter()).flatten() {
+            parse_table_entry(table)?;
         }
 
         Ok(Self {
@@ -60,6 +46,7 @@ impl ReplicationFilter {
             include_tables,
             exclude_tables,
             table_rules: TableRules::default(),
+            resolved_schemas: BTreeMap::new(),
         })
     }
 
@@ -124,6 +111,14 @@ impl ReplicationFilter {
         self.table_rules.predicate_tables(database)
     }
 
+    pub fn transform_tables(&self, database: &str) -> Vec<(String, String)> {
+        self.table_rules.transform_tables(database)
+    }
+
+    pub fn partition_rules(&self, database: &str) -> Vec<(String, String, PartitionRule)> {
+        self.table_rules.partition_rules(database)
+    }
+
     /// Gets the list of tables to exclude
     pub fn exclude_tables(&self) -> Option<&Vec<String>> {
         self.exclude_tables.as_ref()
@@ -135,17 +130,26 @@ impl ReplicationFilter {
     }
 
     /// Determines if a database should be replicated
+    ///
+    /// Entries containing `%` are LIKE patterns (`tenant_%`), matched against
+    /// the names discovered in pg_database; other entries are exact names.
     pub fn should_replicate_database(&self, db_name: &str) -> bool {
-        // If include list exists, database must be in it
+        // If include list exists, database must match an entry
         if let Some(ref include) = self.include_databases {
-            if !include.contains(&db_name.to_string()) {
+            if !include
+                .iter()
+                .any(|entry| database_entry_matches(entry, db_name))
+            {
                 return false;
             }
         }
 
-        // If exclude list exists, database must not be in it
+        // If exclude list exists, database must not match any entry
         if let Some(ref exclude) = self.exclude_databases {
-            if exclude.contains(&db_name.to_string()) {
+            if exclude
+                .iter()
+                .any(|entry| database_entry_matches(entry, db_name))
+            {
                 return false;
             }
         }
@@ -153,20 +157,70 @@ impl ReplicationFilter {
         true
     }
 
+    /// The same table filters and rules, limited to one database
+    pub fn for_database(&self, database: &str) -> Self {
+        let entry = if is_database_pattern(database) {
+            // Escaped so the name matches only itself
+            database
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        } else {
+            database.to_string()
+        };
+        Self {
+            include_databases: Some(vec![entry]),
+            exclude_databases: None,
+            ..self.clone()
+        }
+    }
+
+    /// Database patterns (entries containing `%`) in the include or exclude list
+    ///
+    /// The databases they select change as databases are created on the
+    /// source, so init pins the list resolved when the run was planned.
+    pub fn database_patterns(&self) -> Vec<&str> {
+        self.include_databases
+            .iter()
+            .chain(self.exclude_databases.iter())
+            .flatten()
+            .map(String::as_str)
+            .filter(|entry| is_database_pattern(entry))
+            .collect()
+    }
+
     /// Determines if a table should be replicated
+    ///
+    /// `table_name` is `table` for a table in `public` or `schema.table`
+    /// otherwise; parts may be double-quoted as in the filter entries.
     pub fn should_replicate_table(&self, db_name: &str, table_name: &str) -> bool {
-        let full_name = format!("{}.{}", db_name, table_name);
+        let parts = crate::utils::split_qualified_name(table_name).unwrap_or_default();
+        match parts.as_slice() {
+            [table] => self.should_replicate_schema_table(db_name, "public", &table.name),
+            [schema, table] => {
+                self.should_replicate_schema_table(db_name, &schema.name, &table.name)
+            }
+            _ => self.should_replicate_schema_table(db_name, "public", table_name),
+        }
+    }
+
+    /// Determines if a schema-qualified table should be replicated
+    ///
+    /// Names are compared exactly as they appear in the catalog, so mixed-case,
+    /// dotted, and reserved-word names need no quoting here.
+    pub fn should_replicate_schema_table(&self, db_name: &str, schema: &str, table: &str) -> bool {
+        let key = (schema.to_string(), table.to_string());
 
         // If include list exists, table must be in it
         if let Some(ref include) = self.include_tables {
-            if !include.contains(&full_name) {
+            if !self.tables_for_db(include, db_name).contains(&key) {
                 return false;
             }
         }
 
         // If exclude list exists, table must not be in it
         if let Some(ref exclude) = self.exclude_tables {
-            if exclude.contains(&full_name) {
+            if self.tables_for_db(exclude, db_name).contains(&key) {
                 return false;
             }
         }
@@ -174,6 +228,148 @@ impl ReplicationFilter {
         true
     }
 
+    /// Tables named by `--include-tables` for a database as `(schema, table)`
+    ///
+    /// Returns `None` when no include list is set.
+    pub fn included_tables_for_db(&self, db_name: &str) -> Option<Vec<(String, String)>> {
+        self.include_tables
+            .as_ref()
+            .map(|tables| self.tables_for_db(tables, db_name))
+    }
+
+    /// Tables named by `--exclude-tables` for a database as `(schema, table)`
+    pub fn excluded_tables_for_db(&self, db_name: &str) -> Vec<(String, String)> {
+        self.exclude_tables
+            .as_ref()
+            .map(|tables| self.tables_for_db(tables, db_name))
+            .unwrap_or_default()
+    }
+
+    /// Filter entries for one database as `(schema, table)`
+    ///
+    /// Two-part entries use the schema found by [`Self::resolve_table_schemas`],
+    /// or `public` if the table was not found (or not yet resolved).
+    fn tables_for_db(&self, entries: &[String], db_name: &str) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .filter_map(|entry| parse_table_entry(entry).ok())
+            .filter(|(db, _, _)| db == db_name)
+            .map(|(db, schema, table)| {
+                let schema = schema.unwrap_or_else(|| {
+                    self.resolved_schemas
+                        .get(&(db, table.clone()))
+                        .cloned()
+                        .unwrap_or_else(|| "public".to_string())
+                });
+                (schema, table)
+            })
+            .collect()
+    }
+
+    /// Two-part `db.table` entries naming tables in `db_name`
+    fn unqualified_tables(&self, db_name: &str) -> BTreeSet<String> {
+        self.include_tables
+            .iter()
+            .chain(self.exclude_tables.iter())
+            .flatten()
+            .filter_map(|entry| parse_table_entry(entry).ok())
+            .filter(|(db, schema, _)| db == db_name && schema.is_none())
+            .map(|(_, _, table)| table)
+            .collect()
+    }
+
+    /// Resolve two-part `db.table` entries against the database's catalog
+    ///
+    /// A table in `public` wins; otherwise the single schema holding a table of
+    /// that name is used. Tables found nowhere keep the `public` default.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - Connection to the source database `db_name`
+    /// * `db_name` - Database the entries name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the catalog cannot be queried or a name exists in
+    /// several non-public schemas.
+    pub async fn resolve_table_schemas(&mut self, client: &Client, db_name: &str) -> Result<()> {
+        for table in self.unqualified_tables(db_name) {
+            let rows = client
+                .query(
+                    "SELECT n.nspname
+                     FROM pg_class c
+                     JOIN pg_namespace n ON n.oid = c.relnamespace
+                     WHERE c.relname = $1
+                       AND c.relkind IN ('r', 'p', 'v', 'm', 'f')
+                       AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+                       AND n.nspname NOT LIKE 'pg_toast%'
+                     ORDER BY n.nspname",
+                    &[&table],
+                )
+                .await
+                .with_context(|| {
+                    format!("Failed to look up schema of table '{}.{}'", db_name, table)
+                })?;
+            let schemas: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+
+            let schema = match schemas.as_slice() {
+                [] => {
+                    tracing::warn!(
+                        "⚠ Table filter '{}' matches no table; assuming schema 'public'",
+                        crate::utils::filter_table_name(&[db_name, &table])
+                    );
+                    continue;
+                }
+                _ if schemas.iter().any(|schema| schema == "public") => "public".to_string(),
+                [schema] => schema.clone(),
+                _ => bail!(
+                    "Table filter '{}' is ambiguous: a table of that name exists in schemas {}.\n\
+                     Name the schema explicitly, e.g. '{}'.",
+                    crate::utils::filter_table_name(&[db_name, &table]),
+                    schemas.join(", "),
+                    crate::utils::filter_table_name(&[db_name, &schemas[0], &table])
+                ),
+            };
+            if schema != "public" {
+                tracing::info!(
+                    "  Table filter '{}' resolved to schema '{}'",
+                    crate::utils::filter_table_name(&[db_name, &table]),
+                    schema
+                );
+            }
+            self.resolved_schemas
+                .insert((db_name.to_string(), table), schema);
+        }
+        Ok(())
+    }
+
+    /// Resolve two-part table entries for each database that has any
+    ///
+    /// Connects to each such database on the source; see
+    /// [`Self::resolve_table_schemas`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a database cannot be reached or a name is ambiguous.
+    pub async fn resolve_table_schemas_for(
+        &mut self,
+        source_url: &str,
+        databases: &[String],
+        settings: &crate::settings::RunSettings,
+    ) -> Result<()> {
+        for db_name in databases {
+            if self.unqualified_tables(db_name).is_empty() {
+                continue;
+            }
+            let db_url = crate::utils::replace_database_in_url(source_url, db_name)?;
+            let client = crate::postgres::connect_with_retry(&db_url, settings)
+                .await
+                .with_context(|| format!("Failed to connect to source database '{}'", db_name))?;
+            self.resolve_table_schemas(&client, db_name).await?;
+        }
+        Ok(())
+    }
+
     /// Gets list of databases to replicate (queries source if needed)
     pub async fn get_databases_to_replicate(&self, source_conn: &Client) -> Result<Vec<String>> {
         // Get all databases from source
@@ -213,6 +409,83 @@ impl ReplicationFilter {
     }
 }
 
+/// Whether a database filter entry is a pattern rather than a name
+fn is_database_pattern(entry: &str) -> bool {
+    entry.contains('%')
+}
+
+fn database_entry_matches(entry: &str, db_name: &str) -> bool {
+    if is_database_pattern(entry) {
+        like_match(entry, db_name)
+    } else {
+        entry == db_name
+    }
+}
+
+/// Match `name` against a LIKE pattern: `%` is any run of characters, `_` any
+/// one character, and `\` makes the next character literal (`tenant\_%`)
+fn like_match(pattern: &str, name: &str) -> bool {
+    #[derive(Clone, Copy)]
+    enum Token {
+        Any,
+        One,
+        Char(char),
+    }
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '%' => Token::Any,
+            '_' => Token::One,
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            c => Token::Char(c),
+        });
+    }
+
+    // matched[i]: the first i tokens match the characters consumed so far
+    let mut matched = vec![false; tokens.len() + 1];
+    matched[0] = true;
+    for i in 0..tokens.len() {
+        if matches!(tokens[i], Token::Any) {
+            matched[i + 1] = matched[i];
+        }
+    }
+    for c in name.chars() {
+        let mut next = vec![false; tokens.len() + 1];
+        for i in 0..tokens.len() {
+            next[i + 1] = match tokens[i] {
+                Token::Any => next[i] || matched[i + 1],
+                Token::One => matched[i],
+                Token::Char(expected) => matched[i] && expected == c,
+            };
+        }
+        matched = next;
+    }
+    matched[tokens.len()]
+}
+
+/// Parse a table filter entry into `(database, schema, table)`
+///
+/// `db.table` leaves the schema to be resolved (see
+/// [`ReplicationFilter::resolve_table_schemas`]); `db.schema.table` names it.
+/// Parts may be double-quoted to keep dots or quotes literal.
+fn parse_table_entry(entry: &str) -> Result<(String, Option<String>, String)> {
+    let parts = crate::utils::split_qualified_name(entry).unwrap_or_default();
+    match parts.as_slice() {
+        [db, table] => Ok((db.name.clone(), None, table.name.clone())),
+        [db, schema, table] => Ok((
+            db.name.clone(),
+            Some(schema.name.clone()),
+            table.name.clone(),
+        )),
+        _ => bail!(
+            "Table must be specified as 'database.table' or 'database.schema.table', got '{}'. \
+             Quote names containing dots, e.g. 'db.\"my.table\"'",
+            entry
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,6 +557,37 @@ mod tests {
         assert!(!filter.should_replicate_database("db3"));
     }
 
+    #[test]
+    fn test_should_replicate_database_with_patterns() {
+        let filter = ReplicationFilter::new(
+            Some(vec!["tenant_%".to_string(), "billing".to_string()]),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(filter.should_replicate_database("tenant_0042"));
+        assert!(filter.should_replicate_database("tenant_"));
+        assert!(filter.should_replicate_database("billing"));
+        assert!(!filter.should_replicate_database("tenant"));
+        assert!(!filter.should_replicate_database("billing_archive"));
+        assert_eq!(filter.database_patterns(), vec!["tenant_%"]);
+
+        let filter =
+            ReplicationFilter::new(None, Some(vec!["%\\_test".to_string()]), None, None).unwrap();
+        assert!(!filter.should_replicate_database("app_test"));
+        assert!(filter.should_replicate_database("apptest"));
+        assert!(filter.should_replicate_database("app_tests"));
+
+        let single = filter.for_database("50%_off");
+        assert!(single.should_replicate_database("50%_off"));
+        assert!(!single.should_replicate_database("50%xoff_more"));
+
+        assert!(like_match("a_c%", "abcdef"));
+        assert!(!like_match("a_c", "ac"));
+        assert!(like_match("%%", ""));
+    }
+
     #[test]
     fn test_should_replicate_database_with_exclude_list() {
         let filter = ReplicationFilter::new(
@@ -423,4 +727,120 @@ mod tests {
             "Filters with different table rule schemas should produce different fingerprints"
         );
     }
+
+    #[test]
+    fn test_table_filters_keep_case_dots_and_reserved_words() {
+        let filter = ReplicationFilter::new(
+            None,
+            None,
+            None,
+            Some(vec![
+                "db1.Orders".to_string(),
+                r#"db1."order.items""#.to_string(),
+                "db1.user".to_string(),
+                "db1.Sales.Invoices".to_string(),
+            ]),
+        )
+        .unwrap();
+
+        assert!(!filter.should_replicate_schema_table("db1", "public", "Orders"));
+        assert!(filter.should_replicate_schema_table("db1", "public", "orders"));
+        assert!(!filter.should_replicate_schema_table("db1", "public", "order.items"));
+        assert!(!filter.should_replicate_schema_table("db1", "public", "user"));
+        assert!(!filter.should_replicate_schema_table("db1", "Sales", "Invoices"));
+        assert!(filter.should_replicate_schema_table("db1", "sales", "Invoices"));
+        assert!(!filter.should_replicate_table("db1", "Sales.Invoices"));
+        assert!(!filter.should_replicate_table("db1", r#""order.items""#));
+        assert!(filter.should_replicate_schema_table("db2", "public", "Orders"));
+
+        assert_eq!(
+            filter.excluded_tables_for_db("db1"),
+            vec![
+                ("public".to_string(), "Orders".to_string()),
+                ("public".to_string(), "order.items".to_string()),
+                ("public".to_string(), "user".to_string()),
+                ("Sales".to_string(), "Invoices".to_string()),
+            ]
+        );
+        assert_eq!(filter.included_tables_for_db("db1"), None);
+    }
+
+    #[test]
+    fn test_new_rejects_malformed_quoted_table() {
+        let result =
+            ReplicationFilter::new(None, None, Some(vec![r#"db1."users"#.to_string()]), None);
+        assert!(result.is_err());
+        let result = ReplicationFilter::new(None, None, Some(vec!["db1.a.b.c".to_string()]), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_two_part_entries_use_resolved_schema() {
+        let mut filter = ReplicationFilter::new(
+            None,
+            None,
+            Some(vec!["db1.events".to_string(), "db1.users".to_string()]),
+            None,
+        )
+        .unwrap();
+        filter.resolved_schemas.insert(
+            ("db1".to_string(), "events".to_string()),
+            "analytics".to_string(),
+        );
+
+        assert!(filter.should_replicate_schema_table("db1", "analytics", "events"));
+        assert!(!filter.should_replicate_schema_table("db1", "public", "events"));
+        assert!(filter.should_replicate_schema_table("db1", "public", "users"));
+        assert_eq!(
+            filter.included_tables_for_db("db1"),
+            Some(vec![
+                ("analytics".to_string(), "events".to_string()),
+                ("public".to_string(), "users".to_string()),
+            ])
+        );
+        assert_eq!(
+            filter
+                .unqualified_tables("db1")
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["events", "users"]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_resolve_table_schemas() {
+        let url = std::env::var("TEST_SOURCE_URL").unwrap();
+        let client = crate::postgres::connect(&url).await.unwrap();
+        client
+            .batch_execute(
+                "CREATE SCHEMA IF NOT EXISTS filter_resolve_test;
+                 CREATE TABLE IF NOT EXISTS filter_resolve_test.only_here (id int)",
+            )
+            .await
+            .unwrap();
+        let db_name: String = client
+            .query_one("SELECT current_database()", &[])
+            .await
+            .unwrap()
+            .get(0);
+
+        let mut filter = ReplicationFilter::new(
+            None,
+            None,
+            Some(vec![format!("{}.only_here", db_name)]),
+            None,
+        )
+        .unwrap();
+        filter
+            .resolve_table_schemas(&client, &db_name)
+            .await
+            .unwrap();
+        assert!(filter.should_replicate_schema_table(&db_name, "filter_resolve_test", "only_here"));
+
+        client
+            .batch_execute("DROP SCHEMA filter_resolve_test CASCADE")
+            .await
+            .unwrap();
+    }
 }
@@ -0,0 +1,439 @@
+// ABOUTME: User-defined hooks that run SQL files or shell commands at pipeline stages
+// ABOUTME: Executes against the target with job context exposed through environment variables
+
+use crate::utils::replace_database_in_url;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Point in the pipeline at which hooks run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// Before init copies anything (once, against the target URL as given)
+    PreInit,
+    /// After the schema of a database has been restored (once per database)
+    PostSchema,
+    /// After the data of a database has been copied (once per database)
+    PostData,
+    /// After the subscription of a database is active (once per database)
+    PostSync,
+    /// Before traffic is switched to the target (run with `run-hooks`)
+    PreCutover,
+    /// Source DDL was queued for approval by `ddl-queue --watch` (once per database)
+    DdlPending,
+    /// A replicated database no longer exists on the source (`onboard`, once per database)
+    DatabaseRemoved,
+}
+
+impl HookStage {
+    pub const ALL: [HookStage; 7] = [
+        HookStage::PreInit,
+        HookStage::PostSchema,
+        HookStage::PostData,
+        HookStage::PostSync,
+        HookStage::PreCutover,
+        HookStage::DdlPending,
+        HookStage::DatabaseRemoved,
+    ];
+
+    /// Name used in the config file and the SEREN_HOOK_STAGE variable
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::PreInit => "pre-init",
+            HookStage::PostSchema => "post-schema",
+            HookStage::PostData => "post-data",
+            HookStage::PostSync => "post-sync",
+            HookStage::PreCutover => "pre-cutover",
+            HookStage::DdlPending => "ddl-pending",
+            HookStage::DatabaseRemoved => "database-removed",
+        }
+    }
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HookStage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        HookStage::ALL
+            .into_iter()
+            .find(|stage| stage.as_str() == s)
+            .with_context(|| {
+                format!(
+                    "Unknown hook stage '{}'. Expected one of: pre-init, post-schema, post-data, post-sync, pre-cutover, ddl-pending, database-removed",
+                    s
+                )
+            })
+    }
+}
+
+/// A single hook: either a SQL file or a shell command
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HookAction {
+    /// SQL file executed on the target over a native connection
+    pub sql: Option<String>,
+    /// Shell command run with `sh -c`
+    pub command: Option<String>,
+    /// For per-database stages, only run for this database; for pre-init and
+    /// pre-cutover, the database to connect to instead of the one in the target URL
+    pub database: Option<String>,
+    /// Log a warning instead of aborting when the hook fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+impl HookAction {
+    fn describe(&self) -> String {
+        match (&self.sql, &self.command) {
+            (Some(sql), _) => format!("SQL file {}", sql),
+            (_, Some(command)) => format!("command `{}`", command),
+            _ => "empty hook".to_string(),
+        }
+    }
+}
+
+/// `[hooks]` section of replication-config.toml
+///
+/// ```toml
+/// [hooks]
+/// post-schema = [{ sql = "hooks/rls_policies.sql" }]
+/// post-data = [{ command = "./grant_app_roles.sh", database = "app" }]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub pre_init: Vec<HookAction>,
+    #[serde(default)]
+    pub post_schema: Vec<HookAction>,
+    #[serde(default)]
+    pub post_data: Vec<HookAction>,
+    #[serde(default)]
+    pub post_sync: Vec<HookAction>,
+    #[serde(default)]
+    pub pre_cutover: Vec<HookAction>,
+    #[serde(default)]
+    pub ddl_pending: Vec<HookAction>,
+    #[serde(default)]
+    pub database_removed: Vec<HookAction>,
+}
+
+impl HooksConfig {
+    /// Hooks configured for a stage, in config order
+    pub fn for_stage(&self, stage: HookStage) -> &[HookAction] {
+        match stage {
+            HookStage::PreInit => &self.pre_init,
+            HookStage::PostSchema => &self.post_schema,
+            HookStage::PostData => &self.post_data,
+            HookStage::PostSync => &self.post_sync,
+            HookStage::PreCutover => &self.pre_cutover,
+            HookStage::DdlPending => &self.ddl_pending,
+            HookStage::DatabaseRemoved => &self.database_removed,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        HookStage::ALL
+            .iter()
+            .all(|stage| self.for_stage(*stage).is_empty())
+    }
+
+    /// Check that every hook names exactly one of `sql` or `command`
+    pub fn validate(&self) -> Result<()> {
+        for stage in HookStage::ALL {
+            for (idx, action) in self.for_stage(stage).iter().enumerate() {
+                match (&action.sql, &action.command) {
+                    (Some(_), None) | (None, Some(_)) => {}
+                    _ => bail!(
+                        "Hook #{} for stage '{}' must set exactly one of 'sql' or 'command'",
+                        idx + 1,
+                        stage
+                    ),
+                }
+                if let Some(db) = &action.database {
+                    crate::utils::validate_postgres_identifier(db).with_context(|| {
+                        format!(
+                            "Invalid database in hook #{} for stage '{}'",
+                            idx + 1,
+                            stage
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hooks of one run, with relative paths resolved against `base_dir`
+#[derive(Debug, Clone, Default)]
+pub struct HookSet {
+    config: HooksConfig,
+    base_dir: PathBuf,
+}
+
+impl HookSet {
+    /// Hooks from the `[hooks]` section of the config file
+    ///
+    /// Relative SQL file paths and commands run from the config file's directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hook is invalid (see [`HooksConfig::validate`]).
+    pub fn new(config: HooksConfig, base_dir: PathBuf) -> Result<Self> {
+        config.validate()?;
+        Ok(Self { config, base_dir })
+    }
+}
+
+/// Job context passed to hooks
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    pub source_url: &'a str,
+    pub target_url: &'a str,
+    /// Database being processed, for per-database stages
+    pub database: Option<&'a str>,
+}
+
+/// Run all hooks configured for a stage
+///
+/// Hooks run in config order. SQL files are executed on the target database in a
+/// single batch; shell commands receive the job context as environment variables:
+///
+/// - `SEREN_HOOK_STAGE` - stage name (e.g. `post-schema`)
+/// - `SEREN_DATABASE` - database being processed (per-database stages only)
+/// - `SEREN_SOURCE_URL` / `SEREN_TARGET_URL` - connection URLs without passwords
+/// - `PGHOST`, `PGPORT`, `PGDATABASE`, `PGUSER`, `PGPASSFILE` - target connection,
+///   so `psql` inside the command connects without extra arguments
+///
+/// # Returns
+///
+/// The number of hooks that ran.
+///
+/// # Errors
+///
+/// Returns an error if a hook fails and `continue_on_error` is not set for it.
+pub async fn run_hooks(
+    stage: HookStage,
+    ctx: HookContext<'_>,
+    settings: &crate::settings::RunSettings,
+) -> Result<usize> {
+    let hooks = &settings.hooks;
+    let actions: Vec<&HookAction> = hooks
+        .config
+        .for_stage(stage)
+        .iter()
+        .filter(|action| applies_to(action, ctx.database))
+        .collect();
+    if actions.is_empty() {
+        return Ok(0);
+    }
+
+    tracing::info!("  Running {} {} hook(s)...", actions.len(), stage);
+    for action in &actions {
+        let target_db_url = match ctx.database.or(action.database.as_deref()) {
+            Some(db) => replace_database_in_url(ctx.target_url, db)?,
+            None => ctx.target_url.to_string(),
+        };
+
+        let result = match (&action.sql, &action.command) {
+            (Some(sql), _) => run_sql_hook(&hooks.base_dir, sql, &target_db_url, settings).await,
+            (_, Some(command)) => {
+                run_command_hook(&hooks.base_dir, command, stage, &ctx, &target_db_url)
+            }
+            _ => Ok(()),
+        };
+
+        match result {
+            Ok(()) => tracing::info!("  ✓ {} hook: {}", stage, action.describe()),
+            Err(e) if action.continue_on_error => {
+                tracing::warn!("⚠ {} hook failed ({}): {:#}", stage, action.describe(), e);
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("{} hook failed: {}", stage, action.describe()));
+            }
+        }
+    }
+
+    Ok(actions.len())
+}
+
+/// Whether a hook applies to the database being processed
+fn applies_to(action: &HookAction, database: Option<&str>) -> bool {
+    match (database, &action.database) {
+        (Some(current), Some(only)) => current == only,
+        _ => true,
+    }
+}
+
+fn resolve_path(base_dir: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }
+}
+
+async fn run_sql_hook(
+    base_dir: &Path,
+    sql: &str,
+    target_url: &str,
+    settings: &crate::settings::RunSettings,
+) -> Result<()> {
+    let path = resolve_path(base_dir, sql);
+    let script = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read hook SQL file {}", path.display()))?;
+
+    let client = crate::postgres::connect_with_retry(target_url, settings).await?;
+    client
+        .batch_execute(&script)
+        .await
+        .with_context(|| format!("Failed to execute {}", path.display()))?;
+    Ok(())
+}
+
+fn run_command_hook(
+    base_dir: &Path,
+    command: &str,
+    stage: HookStage,
+    ctx: &HookContext<'_>,
+    target_url: &str,
+) -> Result<()> {
+    let parts = crate::utils::parse_postgres_url(target_url)
+        .context("Failed to parse target URL for hook environment")?;
+    let pgpass =
+        crate::utils::PgPassFile::new(&parts).context("Failed to create .pgpass file for hook")?;
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .current_dir(base_dir)
+        .env("SEREN_HOOK_STAGE", stage.as_str())
+        .env(
+            "SEREN_SOURCE_URL",
+            crate::utils::strip_password_from_url(ctx.source_url).unwrap_or_else(|_| String::new()),
+        )
+        .env(
+            "SEREN_TARGET_URL",
+            crate::utils::strip_password_from_url(target_url).unwrap_or_else(|_| String::new()),
+        )
+        .env("PGHOST", &parts.host)
+        .env("PGPORT", parts.port.to_string())
+        .env("PGDATABASE", &parts.database)
+        .env("PGPASSFILE", pgpass.path())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    if let Some(db) = ctx.database {
+        cmd.env("SEREN_DATABASE", db);
+    }
+    if let Some(user) = &parts.user {
+        cmd.env("PGUSER", user);
+    }
+    for (env_var, value) in parts.to_pg_env_vars() {
+        cmd.env(env_var, value);
+    }
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to start hook command `{}`", command))?;
+    if !status.success() {
+        bail!("Hook command exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_names_roundtrip() {
+        for stage in HookStage::ALL {
+            assert_eq!(stage.as_str().parse::<HookStage>().unwrap(), stage);
+        }
+        assert!("post-restore".parse::<HookStage>().is_err());
+    }
+
+    #[test]
+    fn test_parse_hooks_config() {
+        let config: HooksConfig = toml::from_str(
+            r#"
+            pre-init = [{ command = "echo start" }]
+            post-data = [
+                { sql = "grants.sql", database = "app" },
+                { command = "./notify.sh", continue_on_error = true },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        config.validate().unwrap();
+        assert_eq!(config.for_stage(HookStage::PreInit).len(), 1);
+        assert_eq!(config.for_stage(HookStage::PostData).len(), 2);
+        assert!(config.for_stage(HookStage::PostSchema).is_empty());
+        assert!(config.post_data[1].continue_on_error);
+        assert!(!config.is_empty());
+    }
+
+    #[test]
+    fn test_validate_requires_exactly_one_action() {
+        let both = HooksConfig {
+            post_sync: vec![HookAction {
+                sql: Some("a.sql".to_string()),
+                command: Some("true".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(both.validate().is_err());
+
+        let neither = HooksConfig {
+            post_sync: vec![HookAction::default()],
+            ..Default::default()
+        };
+        assert!(neither.validate().is_err());
+    }
+
+    #[test]
+    fn test_applies_to_database_filter() {
+        let scoped = HookAction {
+            command: Some("true".to_string()),
+            database: Some("app".to_string()),
+            ..Default::default()
+        };
+        assert!(applies_to(&scoped, Some("app")));
+        assert!(!applies_to(&scoped, Some("analytics")));
+        assert!(applies_to(&scoped, None));
+
+        let unscoped = HookAction {
+            command: Some("true".to_string()),
+            ..Default::default()
+        };
+        assert!(applies_to(&unscoped, Some("analytics")));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let base = Path::new("/etc/replicator");
+        assert_eq!(
+            resolve_path(base, "hooks/rls.sql"),
+            PathBuf::from("/etc/replicator/hooks/rls.sql")
+        );
+        assert_eq!(
+            resolve_path(base, "/opt/fix.sql"),
+            PathBuf::from("/opt/fix.sql")
+        );
+    }
+}
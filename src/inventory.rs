@@ -0,0 +1,537 @@
+// ABOUTME: Structured inventory of any source or target: databases, schemas, tables, row estimates, sizes, keys
+// ABOUTME: Shared by the inspect and list commands and by validate's replica identity check
+
+use crate::filters::ReplicationFilter;
+use crate::SourceType;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio_postgres::Client;
+
+/// MySQL schemas that hold server metadata rather than data
+const MYSQL_SYSTEM_DATABASES: &[&str] =
+    &["mysql", "information_schema", "performance_schema", "sys"];
+
+/// MongoDB databases that hold server metadata rather than data
+const MONGODB_SYSTEM_DATABASES: &[&str] = &["admin", "config", "local"];
+
+/// Database name SQLite tables are filtered and listed under
+pub const SQLITE_DATABASE: &str = "main";
+
+/// Everything an endpoint holds, as far as a migration is concerned
+#[derive(Debug, Clone, Serialize)]
+pub struct Inventory {
+    /// `postgresql`, `sqlite`, `mysql`, or `mongodb`
+    pub source_type: &'static str,
+    pub server_version: Option<String>,
+    pub databases: Vec<DatabaseInventory>,
+}
+
+/// One database and the tables or collections in scope
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseInventory {
+    pub name: String,
+    pub size_bytes: Option<u64>,
+    /// Row counts come from a count rather than catalog statistics
+    pub exact_counts: bool,
+    pub tables: Vec<TableInventory>,
+    /// Database-wide features a migration does not carry
+    pub unsupported: Vec<String>,
+}
+
+/// One table or collection
+#[derive(Debug, Clone, Serialize)]
+pub struct TableInventory {
+    /// PostgreSQL schema; None for sources without schemas
+    pub schema: Option<String>,
+    pub name: String,
+    /// Rows or documents; None when the source cannot say
+    pub rows: Option<u64>,
+    /// Data size in bytes (PostgreSQL and MySQL include indexes)
+    pub size_bytes: Option<u64>,
+    /// Average row or document size in bytes; None when unknown or empty
+    pub avg_row_bytes: Option<u64>,
+    /// None when the source has no notion of one
+    pub has_primary_key: Option<bool>,
+    /// PostgreSQL replica identity: default, index, full, or nothing
+    pub replica_identity: Option<&'static str>,
+    /// Features of this table a migration does not carry
+    pub unsupported: Vec<String>,
+}
+
+impl TableInventory {
+    /// `schema.table`, or the bare name for sources without schemas
+    pub fn qualified_name(&self) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", schema, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Inventory every database on the endpoint that `filter` keeps
+///
+/// PostgreSQL endpoints list every non-template database; MySQL and MongoDB
+/// list the database in the URL, or every user database when the URL names
+/// none. A SQLite file is the single database `main`. Counts and sizes come
+/// from catalog statistics (exact counts for SQLite), so nothing is scanned.
+///
+/// # Arguments
+///
+/// * `url` - PostgreSQL, MySQL, or MongoDB URL, or SQLite file path
+/// * `filter` - Databases and tables to include
+///
+/// # Errors
+///
+/// Returns an error if the endpoint cannot be reached or its catalog cannot
+/// be read.
+pub async fn collect(url: &str, filter: &ReplicationFilter) -> Result<Inventory> {
+    let source_type = crate::detect_source_type(url).context("Failed to detect database type")?;
+    match source_type {
+        SourceType::PostgreSQL => collect_postgres(url, filter).await,
+        SourceType::SQLite => collect_sqlite(url, filter),
+        SourceType::MySQL => collect_mysql(url, filter).await,
+        SourceType::MongoDB => collect_mongodb(url, filter).await,
+    }
+}
+
+async fn collect_postgres(url: &str, filter: &ReplicationFilter) -> Result<Inventory> {
+    let client = crate::postgres::connect(url)
+        .await
+        .context("Failed to connect to database")?;
+    let server_version: String = client
+        .query_one("SHOW server_version", &[])
+        .await
+        .context("Failed to read server version")?
+        .get(0);
+    let mut databases = Vec::new();
+    for database in crate::migration::list_databases(&client).await? {
+        if !filter.should_replicate_database(&database.name) {
+            continue;
+        }
+        let db_url = crate::utils::replace_database_in_url(url, &database.name)?;
+        let db_client = crate::postgres::connect(&db_url)
+            .await
+            .with_context(|| format!("Failed to connect to database '{}'", database.name))?;
+        databases.push(postgres_database(&db_client, &database.name, filter).await?);
+    }
+    Ok(Inventory {
+        source_type: "postgresql",
+        server_version: Some(server_version),
+        databases,
+    })
+}
+
+/// Inventory the PostgreSQL database `client` is connected to
+///
+/// Tables without a primary key whose replica identity is `default` or
+/// `nothing` are flagged: once published, UPDATE and DELETE on them fail on
+/// the source. Unlogged tables, foreign tables, and large objects are flagged
+/// because logical replication does not carry them.
+///
+/// # Errors
+///
+/// Returns an error if the catalog cannot be read.
+pub async fn postgres_database(
+    client: &Client,
+    name: &str,
+    filter: &ReplicationFilter,
+) -> Result<DatabaseInventory> {
+    let rows = client
+        .query(
+            "SELECT n.nspname, c.relname, c.relkind::text,
+                    COALESCE(s.n_live_tup, 0),
+                    pg_total_relation_size(c.oid),
+                    pg_table_size(c.oid),
+                    EXISTS (SELECT 1 FROM pg_catalog.pg_index i
+                            WHERE i.indrelid = c.oid AND i.indisprimary),
+                    c.relreplident::text,
+                    c.relpersistence = 'u'
+             FROM pg_catalog.pg_class c
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+             LEFT JOIN pg_catalog.pg_stat_user_tables s ON s.relid = c.oid
+             WHERE c.relkind IN ('r', 'p', 'f')
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+               AND n.nspname NOT LIKE 'pg\\_toast%'
+             ORDER BY n.nspname, c.relname",
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to read tables in '{}'", name))?;
+
+    let mut tables = Vec::new();
+    for row in rows {
+        let schema: String = row.get(0);
+        let table: String = row.get(1);
+        if !filter.should_replicate_table(name, &format!("\"{}\".\"{}\"", schema, table)) {
+            continue;
+        }
+        let kind: String = row.get(2);
+        let rows = row.get::<_, i64>(3).max(0) as u64;
+        let data_bytes = row.get::<_, i64>(5).max(0) as u64;
+        let has_primary_key: bool = row.get(6);
+        let replica_identity = replica_identity_name(&row.get::<_, String>(7));
+        let unlogged: bool = row.get(8);
+        tables.push(TableInventory {
+            schema: Some(schema),
+            name: table,
+            rows: Some(rows),
+            size_bytes: Some(row.get::<_, i64>(4).max(0) as u64),
+            avg_row_bytes: (rows > 0).then(|| data_bytes / rows),
+            has_primary_key: Some(has_primary_key),
+            replica_identity: Some(replica_identity),
+            unsupported: postgres_table_issues(&kind, has_primary_key, replica_identity, unlogged),
+        });
+    }
+
+    let size: i64 = client
+        .query_one("SELECT pg_database_size(current_database())", &[])
+        .await
+        .with_context(|| format!("Failed to read the size of '{}'", name))?
+        .get(0);
+    let large_objects: i64 = client
+        .query_one(
+            "SELECT count(*) FROM pg_catalog.pg_largeobject_metadata",
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to count large objects in '{}'", name))?
+        .get(0);
+    let mut unsupported = Vec::new();
+    if large_objects > 0 {
+        unsupported.push(format!(
+            "{} large object(s): copied by init, but logical replication does not carry later changes",
+            large_objects
+        ));
+    }
+    Ok(DatabaseInventory {
+        name: name.to_string(),
+        size_bytes: Some(size.max(0) as u64),
+        exact_counts: false,
+        tables,
+        unsupported,
+    })
+}
+
+fn replica_identity_name(relreplident: &str) -> &'static str {
+    match relreplident {
+        "n" => "nothing",
+        "f" => "full",
+        "i" => "index",
+        _ => "default",
+    }
+}
+
+/// What logical replication cannot do for a table of this shape
+fn postgres_table_issues(
+    relkind: &str,
+    has_primary_key: bool,
+    replica_identity: &str,
+    unlogged: bool,
+) -> Vec<String> {
+    let mut issues = Vec::new();
+    if relkind == "f" {
+        issues
+            .push("foreign table: its rows live on a remote server and are not copied".to_string());
+        return issues;
+    }
+    if unlogged {
+        issues.push("unlogged: logical replication does not carry its changes".to_string());
+    }
+    // Partitioned parents hold no rows; their partitions are checked on their own
+    if relkind == "r" && !has_primary_key && matches!(replica_identity, "default" | "nothing") {
+        issues.push(
+            "no primary key or replica identity: UPDATE and DELETE on it fail once it is published"
+                .to_string(),
+        );
+    }
+    issues
+}
+
+fn collect_sqlite(path: &str, filter: &ReplicationFilter) -> Result<Inventory> {
+    let mut databases = Vec::new();
+    if filter.should_replicate_database(SQLITE_DATABASE) {
+        let file =
+            crate::sqlite::validate_sqlite_path(path).context("SQLite file validation failed")?;
+        let conn = crate::sqlite::open_sqlite(path).context("Failed to open SQLite database")?;
+        let mut tables = Vec::new();
+        for table in crate::sqlite::reader::list_tables(&conn)
+            .context("Failed to list tables from SQLite database")?
+        {
+            if !filter.should_replicate_table(SQLITE_DATABASE, &table) {
+                continue;
+            }
+            let rows = crate::sqlite::reader::get_table_row_count(&conn, &table)? as u64;
+            let avg_row_bytes = crate::sqlite::reader::get_average_row_size(&conn, &table)?;
+            let has_primary_key: bool = conn
+                .query_row(
+                    "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE pk > 0)",
+                    [&table],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("Failed to read the columns of table '{}'", table))?;
+            tables.push(TableInventory {
+                schema: None,
+                name: table,
+                rows: Some(rows),
+                size_bytes: avg_row_bytes.map(|avg| avg * rows),
+                avg_row_bytes,
+                has_primary_key: Some(has_primary_key),
+                replica_identity: None,
+                unsupported: Vec::new(),
+            });
+        }
+        databases.push(DatabaseInventory {
+            name: SQLITE_DATABASE.to_string(),
+            size_bytes: std::fs::metadata(&file).ok().map(|metadata| metadata.len()),
+            exact_counts: true,
+            tables,
+            unsupported: Vec::new(),
+        });
+    }
+    Ok(Inventory {
+        source_type: "sqlite",
+        server_version: Some(rusqlite::version().to_string()),
+        databases,
+    })
+}
+
+/// Name, TABLE_ROWS, AVG_ROW_LENGTH, DATA_LENGTH, INDEX_LENGTH, has primary key
+type MysqlTableRow = (
+    String,
+    Option<u64>,
+    Option<u64>,
+    Option<u64>,
+    Option<u64>,
+    bool,
+);
+
+async fn collect_mysql(url: &str, filter: &ReplicationFilter) -> Result<Inventory> {
+    use mysql_async::prelude::Queryable;
+
+    let mut conn = crate::mysql::connect_mysql(url)
+        .await
+        .context("MySQL connection failed")?;
+    let server_version: Option<String> = conn
+        .query_first("SELECT VERSION()")
+        .await
+        .context("Failed to read MySQL server version")?;
+    let names: Vec<String> = match crate::mysql::extract_database_name(url) {
+        Some(db_name) => vec![db_name],
+        None => conn
+            .query::<String, _>(
+                "SELECT SCHEMA_NAME FROM INFORMATION_SCHEMA.SCHEMATA ORDER BY SCHEMA_NAME",
+            )
+            .await
+            .context("Failed to list MySQL databases")?
+            .into_iter()
+            .filter(|name| !MYSQL_SYSTEM_DATABASES.contains(&name.to_lowercase().as_str()))
+            .collect(),
+    };
+
+    let mut databases = Vec::new();
+    for db_name in names {
+        if !filter.should_replicate_database(&db_name) {
+            continue;
+        }
+        let rows: Vec<MysqlTableRow> = conn
+            .exec(
+                "SELECT t.TABLE_NAME, t.TABLE_ROWS, t.AVG_ROW_LENGTH, t.DATA_LENGTH, t.INDEX_LENGTH,
+                        EXISTS (SELECT 1 FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS c
+                                WHERE c.TABLE_SCHEMA = t.TABLE_SCHEMA
+                                  AND c.TABLE_NAME = t.TABLE_NAME
+                                  AND c.CONSTRAINT_TYPE = 'PRIMARY KEY')
+                 FROM INFORMATION_SCHEMA.TABLES t
+                 WHERE t.TABLE_SCHEMA = ? AND t.TABLE_TYPE = 'BASE TABLE'
+                 ORDER BY t.TABLE_NAME",
+                (&db_name,),
+            )
+            .await
+            .with_context(|| format!("Failed to list tables from database '{}'", db_name))?;
+        let tables: Vec<TableInventory> = rows
+            .into_iter()
+            .filter(|(name, ..)| filter.should_replicate_table(&db_name, name))
+            .map(
+                |(name, rows, avg_row_bytes, data, index, has_primary_key)| TableInventory {
+                    schema: None,
+                    name,
+                    rows,
+                    size_bytes: data.map(|data| data + index.unwrap_or(0)),
+                    avg_row_bytes: avg_row_bytes.filter(|_| rows.unwrap_or(0) > 0),
+                    has_primary_key: Some(has_primary_key),
+                    replica_identity: None,
+                    unsupported: Vec::new(),
+                },
+            )
+            .collect();
+        databases.push(DatabaseInventory {
+            size_bytes: Some(tables.iter().filter_map(|table| table.size_bytes).sum()),
+            name: db_name,
+            exact_counts: false,
+            tables,
+            unsupported: Vec::new(),
+        });
+    }
+    Ok(Inventory {
+        source_type: "mysql",
+        server_version,
+        databases,
+    })
+}
+
+async fn collect_mongodb(url: &str, filter: &ReplicationFilter) -> Result<Inventory> {
+    use mongodb::bson::doc;
+
+    let client = crate::mongodb::connect_mongodb(url)
+        .await
+        .context("MongoDB connection failed")?;
+    let names = match crate::mongodb::extract_database_name(url)
+        .await
+        .context("Failed to parse MongoDB connection string")?
+    {
+        Some(db_name) => vec![db_name],
+        None => client
+            .list_database_names()
+            .await
+            .context("Failed to list MongoDB databases")?
+            .into_iter()
+            .filter(|name| !MONGODB_SYSTEM_DATABASES.contains(&name.as_str()))
+            .collect(),
+    };
+
+    let mut databases = Vec::new();
+    for db_name in names {
+        if !filter.should_replicate_database(&db_name) {
+            continue;
+        }
+        let db = client.database(&db_name);
+        let mut collections = crate::mongodb::reader::list_collections(&client, &db_name)
+            .await
+            .context("Failed to list collections from MongoDB database")?;
+        collections.retain(|collection| filter.should_replicate_table(&db_name, collection));
+        collections.sort();
+        let mut tables = Vec::new();
+        for collection in collections {
+            tables.push(mongodb_collection(&db, collection).await);
+        }
+        let size_bytes = db
+            .run_command(doc! { "dbStats": 1 })
+            .await
+            .ok()
+            .and_then(|stats| bson_number(&stats, "dataSize"));
+        databases.push(DatabaseInventory {
+            name: db_name,
+            size_bytes,
+            exact_counts: false,
+            tables,
+            unsupported: Vec::new(),
+        });
+    }
+    let server_version = client
+        .database("admin")
+        .run_command(doc! { "buildInfo": 1 })
+        .await
+        .ok()
+        .and_then(|info| info.get_str("version").ok().map(str::to_string));
+    Ok(Inventory {
+        source_type: "mongodb",
+        server_version,
+        databases,
+    })
+}
+
+/// Count and sizes from collStats; views and collections the user cannot
+/// stat fall back to the estimated count alone
+async fn mongodb_collection(db: &mongodb::Database, collection: String) -> TableInventory {
+    let (rows, size_bytes, avg_row_bytes) = match db
+        .run_command(mongodb::bson::doc! { "collStats": collection.as_str() })
+        .await
+    {
+        Ok(stats) => (
+            bson_number(&stats, "count"),
+            bson_number(&stats, "size"),
+            bson_number(&stats, "avgObjSize").filter(|size| *size > 0),
+        ),
+        Err(e) => {
+            tracing::debug!("collStats failed for '{}': {}", collection, e);
+            let rows = crate::mongodb::reader::get_collection_count(db, &collection)
+                .await
+                .ok()
+                .map(|count| count as u64);
+            (rows, None, None)
+        }
+    };
+    TableInventory {
+        schema: None,
+        name: collection,
+        rows,
+        size_bytes,
+        avg_row_bytes,
+        // Every document has an _id
+        has_primary_key: Some(true),
+        replica_identity: None,
+        unsupported: Vec::new(),
+    }
+}
+
+/// A non-negative number from a server reply, whichever BSON type it came in
+fn bson_number(document: &mongodb::bson::Document, key: &str) -> Option<u64> {
+    use mongodb::bson::Bson;
+
+    match document.get(key)? {
+        Bson::Int32(value) => u64::try_from(*value).ok(),
+        Bson::Int64(value) => u64::try_from(*value).ok(),
+        Bson::Double(value) if *value >= 0.0 => Some(value.round() as u64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn test_bson_number_accepts_any_numeric_type() {
+        let stats =
+            doc! { "count": 12_i32, "size": 4096_i64, "avgObjSize": 341.5, "ns": "shop.orders" };
+        assert_eq!(bson_number(&stats, "count"), Some(12));
+        assert_eq!(bson_number(&stats, "size"), Some(4096));
+        assert_eq!(bson_number(&stats, "avgObjSize"), Some(342));
+        assert_eq!(bson_number(&stats, "ns"), None);
+        assert_eq!(bson_number(&stats, "missing"), None);
+        assert_eq!(bson_number(&doc! { "count": -1_i32 }, "count"), None);
+    }
+
+    #[test]
+    fn test_postgres_table_issues() {
+        assert!(postgres_table_issues("r", true, "default", false).is_empty());
+        assert!(postgres_table_issues("r", false, "full", false).is_empty());
+        assert!(postgres_table_issues("p", false, "default", false).is_empty());
+
+        let issues = postgres_table_issues("r", false, "default", true);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].starts_with("unlogged"));
+        assert!(issues[1].starts_with("no primary key"));
+
+        let issues = postgres_table_issues("f", false, "default", false);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("foreign table"));
+    }
+
+    #[test]
+    fn test_qualified_name() {
+        let mut table = TableInventory {
+            schema: Some("sales".to_string()),
+            name: "orders".to_string(),
+            rows: None,
+            size_bytes: None,
+            avg_row_bytes: None,
+            has_primary_key: None,
+            replica_identity: None,
+            unsupported: Vec::new(),
+        };
+        assert_eq!(table.qualified_name(), "sales.orders");
+        table.schema = None;
+        assert_eq!(table.qualified_name(), "orders");
+    }
+}
@@ -0,0 +1,328 @@
+// ABOUTME: Adaptive batch sizing for JSONB inserts, bounded per source type
+// ABOUTME: Grows batches while the target keeps up and shrinks them on slow or oversized batches
+
+use crate::memory::MemoryBudget;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Parameters per JSONB row (id, data, _source_type, _row_hash, _source_updated_at)
+pub const PARAMS_PER_ROW: usize = 5;
+
+/// Most rows one INSERT can carry within PostgreSQL's 65535 bind parameter limit
+pub const MAX_ROWS_PER_STATEMENT: usize = 65535 / PARAMS_PER_ROW;
+
+/// Rows in the first batch before any latency has been observed
+const INITIAL_ROWS: usize = 1000;
+
+/// Source types that write JSONB tables
+pub const SOURCE_TYPES: [&str; 3] = ["sqlite", "mongodb", "mysql"];
+
+/// Limits the batch sizer stays within for one source type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchBounds {
+    pub min_rows: usize,
+    pub max_rows: usize,
+    /// Upper bound on the serialized size of one batch; wins over `min_rows`
+    pub max_batch_bytes: usize,
+    /// Batches slower than this shrink; batches under half of it grow
+    pub target_latency: Duration,
+}
+
+impl BatchBounds {
+    /// Defaults for a source type
+    ///
+    /// MongoDB documents can be up to 16MB each, so its batches may shrink
+    /// further than those of row-based sources.
+    pub fn for_source(source_type: &str) -> Self {
+        let min_rows = if source_type == "mongodb" { 10 } else { 100 };
+        BatchBounds {
+            min_rows,
+            max_rows: 10_000,
+            max_batch_bytes: 16 * 1024 * 1024,
+            target_latency: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// One source type's subsection of `[jsonb_batching]` in replication-config.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BatchBoundsConfig {
+    pub min_rows: Option<usize>,
+    pub max_rows: Option<usize>,
+    /// Size such as "8MB"; see `parallelism::parse_size`
+    pub max_batch_size: Option<String>,
+    pub target_latency_ms: Option<u64>,
+}
+
+/// `[jsonb_batching]` section, keyed by source type
+pub type JsonbBatchingConfig = HashMap<String, BatchBoundsConfig>;
+
+impl BatchBoundsConfig {
+    /// Overlay the fields set in this config onto existing bounds
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a size cannot be parsed or the bounds are inconsistent.
+    pub fn apply_to(&self, bounds: BatchBounds) -> Result<BatchBounds> {
+        let max_batch_bytes = match &self.max_batch_size {
+            Some(size) => crate::parallelism::parse_size(size)? as usize,
+            None => bounds.max_batch_bytes,
+        };
+        let merged = BatchBounds {
+            min_rows: self.min_rows.unwrap_or(bounds.min_rows),
+            max_rows: self.max_rows.unwrap_or(bounds.max_rows),
+            max_batch_bytes,
+            target_latency: self
+                .target_latency_ms
+                .map(Duration::from_millis)
+                .unwrap_or(bounds.target_latency),
+        };
+        if merged.min_rows == 0 || merged.min_rows > merged.max_rows {
+            bail!(
+                "min_rows ({}) must be at least 1 and no larger than max_rows ({})",
+                merged.min_rows,
+                merged.max_rows
+            );
+        }
+        if merged.max_rows > MAX_ROWS_PER_STATEMENT {
+            bail!(
+                "max_rows ({}) exceeds {} rows, the most one INSERT can carry \
+                 within PostgreSQL's bind parameter limit",
+                merged.max_rows,
+                MAX_ROWS_PER_STATEMENT
+            );
+        }
+        Ok(merged)
+    }
+}
+
+/// Resolve the `[jsonb_batching]` config section into bounds per source type
+///
+/// # Errors
+///
+/// Returns an error for unknown source types or invalid bounds.
+pub fn resolve(config: &JsonbBatchingConfig) -> Result<HashMap<String, BatchBounds>> {
+    let mut resolved = HashMap::new();
+    for (source_type, overrides) in config {
+        if !SOURCE_TYPES.contains(&source_type.as_str()) {
+            bail!(
+                "Unknown source type '{}' in [jsonb_batching]. Use one of: {}",
+                source_type,
+                SOURCE_TYPES.join(", ")
+            );
+        }
+        let bounds = overrides
+            .apply_to(BatchBounds::for_source(source_type))
+            .map_err(|e| anyhow::anyhow!("[jsonb_batching.{}]: {}", source_type, e))?;
+        resolved.insert(source_type.clone(), bounds);
+    }
+    Ok(resolved)
+}
+
+/// Serialized size of one JSONB row as sent to the target
+pub fn row_bytes(id: &str, data: &serde_json::Value) -> usize {
+    id.len() + data.to_string().len()
+}
+
+/// Picks the size of each JSONB batch from observed latency and row size
+///
+/// After each batch, [`BatchSizer::record`] halves the row count if the batch
+/// took longer than the target latency and grows it by half if it took less
+/// than half of it. The average row size caps the row count so batches stay
+/// under `max_batch_bytes`, and [`BatchSizer::next_batch`] enforces that cap
+/// exactly for rows larger than average. Under memory pressure batches shrink
+/// further (see [`crate::memory`]).
+#[derive(Debug, Clone)]
+pub struct BatchSizer {
+    bounds: BatchBounds,
+    memory: MemoryBudget,
+    rows: usize,
+    avg_row_bytes: Option<f64>,
+}
+
+impl BatchSizer {
+    /// A sizer within `bounds` that shrinks batches when `memory` runs low
+    pub fn new(bounds: BatchBounds, memory: MemoryBudget) -> Self {
+        BatchSizer {
+            bounds,
+            memory,
+            rows: INITIAL_ROWS.clamp(bounds.min_rows, bounds.max_rows),
+            avg_row_bytes: None,
+        }
+    }
+
+    /// Current target rows per batch
+    pub fn batch_rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Average serialized row size observed so far
+    pub fn avg_row_bytes(&self) -> Option<f64> {
+        self.avg_row_bytes
+    }
+
+    /// Number of leading `rows` to send next, and their serialized size
+    ///
+    /// The byte budget is also capped by what is left of the memory budget
+    /// (`--max-memory`). Always takes at least one row, even if it alone
+    /// exceeds the byte budget.
+    pub fn next_batch(&self, rows: &[(String, serde_json::Value)]) -> (usize, usize) {
+        let max_bytes = self
+            .memory
+            .batch_byte_limit()
+            .map_or(self.bounds.max_batch_bytes, |limit| {
+                limit.min(self.bounds.max_batch_bytes)
+            });
+        let mut count = 0;
+        let mut bytes = 0;
+        for (id, data) in rows.iter().take(self.rows) {
+            let size = row_bytes(id, data);
+            if count > 0 && bytes + size > max_bytes {
+                break;
+            }
+            count += 1;
+            bytes += size;
+        }
+        (count, bytes)
+    }
+
+    /// Adjust the batch size after a batch of `rows` rows and `bytes` bytes took `elapsed`
+    pub fn record(&mut self, rows: usize, bytes: usize, elapsed: Duration) {
+        if rows == 0 {
+            return;
+        }
+        let observed = bytes as f64 / rows as f64;
+        self.avg_row_bytes = Some(match self.avg_row_bytes {
+            Some(avg) => avg * 0.7 + observed * 0.3,
+            None => observed,
+        });
+
+        let mut next = if elapsed > self.bounds.target_latency {
+            self.rows / 2
+        } else if elapsed < self.bounds.target_latency / 2 {
+            self.rows + self.rows / 2 + 1
+        } else {
+            self.rows
+        };
+        next = next.clamp(self.bounds.min_rows, self.bounds.max_rows);
+
+        if let Some(avg) = self.avg_row_bytes {
+            let fits = (self.bounds.max_batch_bytes as f64 / avg.max(1.0)) as usize;
+            next = next.min(fits.max(1));
+        }
+        // Memory pressure overrides min_rows
+        self.rows = self.memory.scale_down(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bounds() -> BatchBounds {
+        BatchBounds {
+            min_rows: 10,
+            max_rows: 2000,
+            max_batch_bytes: 1024 * 1024,
+            target_latency: Duration::from_millis(500),
+        }
+    }
+
+    #[test]
+    fn test_sizer_grows_on_fast_batches_and_shrinks_on_slow() {
+        let mut sizer = BatchSizer::new(bounds(), MemoryBudget::default());
+        assert_eq!(sizer.batch_rows(), 1000);
+
+        sizer.record(1000, 100_000, Duration::from_millis(50));
+        assert_eq!(sizer.batch_rows(), 1501);
+        sizer.record(1501, 150_100, Duration::from_millis(50));
+        assert_eq!(sizer.batch_rows(), 2000);
+
+        sizer.record(2000, 200_000, Duration::from_millis(2000));
+        assert_eq!(sizer.batch_rows(), 1000);
+
+        // Within the target band the size holds steady
+        sizer.record(1000, 100_000, Duration::from_millis(400));
+        assert_eq!(sizer.batch_rows(), 1000);
+
+        for _ in 0..20 {
+            sizer.record(100, 10_000, Duration::from_secs(5));
+        }
+        assert_eq!(sizer.batch_rows(), 10);
+    }
+
+    #[test]
+    fn test_sizer_caps_rows_by_average_size() {
+        let mut sizer = BatchSizer::new(bounds(), MemoryBudget::default());
+        // 10KB rows: only ~100 fit in 1MB
+        sizer.record(1000, 10_240_000, Duration::from_millis(50));
+        assert_eq!(sizer.batch_rows(), 102);
+        assert_eq!(sizer.avg_row_bytes(), Some(10_240.0));
+    }
+
+    #[test]
+    fn test_next_batch_respects_byte_budget() {
+        let mut b = bounds();
+        b.max_batch_bytes = 100;
+        let sizer = BatchSizer::new(b, MemoryBudget::default());
+        let rows: Vec<(String, serde_json::Value)> = (0..5)
+            .map(|i| (i.to_string(), json!({"v": "x".repeat(40)})))
+            .collect();
+        let row = row_bytes(&rows[0].0, &rows[0].1);
+
+        assert_eq!(sizer.next_batch(&rows), (100 / row, (100 / row) * row));
+
+        // A single oversized row is still sent on its own
+        b.max_batch_bytes = 1;
+        assert_eq!(
+            BatchSizer::new(b, MemoryBudget::default()).next_batch(&rows),
+            (1, row)
+        );
+        assert_eq!(
+            BatchSizer::new(b, MemoryBudget::default()).next_batch(&[]),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_bounds_config_apply_to() {
+        let config = BatchBoundsConfig {
+            max_rows: Some(500),
+            max_batch_size: Some("4MB".to_string()),
+            target_latency_ms: Some(250),
+            ..Default::default()
+        };
+        let merged = config.apply_to(BatchBounds::for_source("mysql")).unwrap();
+        assert_eq!(merged.min_rows, 100);
+        assert_eq!(merged.max_rows, 500);
+        assert_eq!(merged.max_batch_bytes, 4 * 1024 * 1024);
+        assert_eq!(merged.target_latency, Duration::from_millis(250));
+
+        let too_many = BatchBoundsConfig {
+            max_rows: Some(MAX_ROWS_PER_STATEMENT + 1),
+            ..Default::default()
+        };
+        assert!(too_many
+            .apply_to(BatchBounds::for_source("sqlite"))
+            .is_err());
+
+        let inverted = BatchBoundsConfig {
+            min_rows: Some(600),
+            max_rows: Some(500),
+            ..Default::default()
+        };
+        assert!(inverted
+            .apply_to(BatchBounds::for_source("sqlite"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_resolve_rejects_unknown_source() {
+        let config: JsonbBatchingConfig =
+            [("oracle".to_string(), BatchBoundsConfig::default())].into();
+        assert!(resolve(&config).is_err());
+    }
+}
@@ -0,0 +1,339 @@
+// ABOUTME: Hash-based delta refresh of SQLite and MySQL JSONB tables (`init --refresh hash`)
+// ABOUTME: Compares per-row hashes of the source with the writer-maintained _row_hash column and rewrites only changed rows
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+use crate::jsonb::partitioning::PartitionSpec;
+use crate::jsonb::target_schema::qualified;
+use crate::jsonb::verify::document_hash;
+use crate::jsonb::JsonbOptions;
+use crate::settings::RunSettings;
+
+/// Target rows read per query when collecting hashes
+const HASH_BATCH: i64 = 10_000;
+
+/// Ids per DELETE statement
+const DELETE_BATCH: usize = 10_000;
+
+/// Rows per UPDATE statement
+const UPDATE_BATCH: usize = 1000;
+
+/// How re-running init updates an existing JSONB table (`--refresh`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RefreshStrategy {
+    /// Insert every converted row
+    #[default]
+    Full,
+    /// Rewrite only rows whose hash differs from the target's, and delete rows gone from the source
+    Hash,
+}
+
+impl RefreshStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefreshStrategy::Full => "full",
+            RefreshStrategy::Hash => "hash",
+        }
+    }
+}
+
+impl fmt::Display for RefreshStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RefreshStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(RefreshStrategy::Full),
+            "hash" => Ok(RefreshStrategy::Hash),
+            other => bail!(
+                "Unknown refresh strategy '{}'. Expected one of: full, hash",
+                other
+            ),
+        }
+    }
+}
+
+/// What a hash refresh will write
+#[derive(Debug, Default, PartialEq)]
+pub struct DeltaPlan {
+    /// Rows not yet on the target
+    pub inserts: Vec<(String, JsonValue)>,
+    /// Rows on the target whose hash changed
+    pub updates: Vec<(String, JsonValue)>,
+    /// Ids on the target no longer in the source
+    pub deletes: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Compare the converted source rows with the target's `id -> _row_hash`
+///
+/// Hashes are [`document_hash`] of the converted document, so a change in
+/// how a value is converted (e.g. a `--mysql-charset` override) also counts
+/// as a change. Target rows without a hash (written before `_row_hash`
+/// existed) count as changed, so they are rewritten once.
+pub fn plan(
+    source_rows: Vec<(String, JsonValue)>,
+    mut target: HashMap<String, Option<String>>,
+) -> DeltaPlan {
+    let mut delta = DeltaPlan::default();
+    for (id, data) in source_rows {
+        match target.remove(&id) {
+            None => delta.inserts.push((id, data)),
+            Some(Some(hash)) if hash == document_hash(&data) => delta.unchanged += 1,
+            Some(_) => delta.updates.push((id, data)),
+        }
+    }
+    delta.deletes = target.into_keys().collect();
+    delta.deletes.sort();
+    delta
+}
+
+/// `id -> _row_hash` of every row in a JSONB table, read in id order in batches
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read.
+pub async fn target_hashes(
+    client: &Client,
+    schema: &str,
+    table_name: &str,
+) -> Result<HashMap<String, Option<String>>> {
+    crate::jsonb::validate_table_name(table_name)
+        .context("Invalid table name for JSONB hash refresh")?;
+    let query = format!(
+        "SELECT id, _row_hash FROM {} WHERE id > $1 ORDER BY id LIMIT $2",
+        qualified(schema, table_name)
+    );
+    let mut hashes = HashMap::new();
+    let mut after = String::new();
+    loop {
+        let rows = client
+            .query(&query, &[&after, &HASH_BATCH])
+            .await
+            .with_context(|| format!("Failed to read row hashes from '{}'", table_name))?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.get(0);
+        let full = rows.len() as i64 == HASH_BATCH;
+        for row in rows {
+            hashes.insert(row.get(0), row.get(1));
+        }
+        if !full {
+            break;
+        }
+    }
+    Ok(hashes)
+}
+
+/// Counts from one table's hash refresh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshCounts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// Bring an existing JSONB table up to date with the converted source rows
+///
+/// New rows are inserted, changed rows are updated in place (keeping their
+/// `_migrated_at` and refreshing `_synced_at`), and rows gone from the source
+/// are deleted, in one transaction, so readers never see a half-refreshed
+/// table.
+///
+/// # Arguments
+///
+/// * `client` - Target connection
+/// * `jsonb` - Target schema and table partitioning of this run
+/// * `settings` - Memory budget and chaos faults of this run
+/// * `table_name` - JSONB table, created by [`crate::jsonb::writer::create_jsonb_table`]
+/// * `rows` - Every converted source row
+/// * `source_type` - 'sqlite' or 'mysql'
+///
+/// # Errors
+///
+/// Returns an error if the target hashes cannot be read or the rows cannot be
+/// written; the transaction is rolled back.
+pub async fn refresh_table(
+    client: &Client,
+    jsonb: &JsonbOptions,
+    settings: &RunSettings,
+    table_name: &str,
+    rows: Vec<(String, JsonValue)>,
+    source_type: &str,
+) -> Result<RefreshCounts> {
+    let schema = jsonb.target_schema.as_str();
+    let target = target_hashes(client, schema, table_name).await?;
+    let delta = plan(rows, target);
+    let counts = RefreshCounts {
+        inserted: delta.inserts.len(),
+        updated: delta.updates.len(),
+        deleted: delta.deletes.len(),
+        unchanged: delta.unchanged,
+    };
+    if counts.inserted == 0 && counts.updated == 0 && counts.deleted == 0 {
+        return Ok(counts);
+    }
+
+    client
+        .batch_execute("BEGIN")
+        .await
+        .context("Failed to start refresh transaction")?;
+    let result = async {
+        let delete_sql = format!(
+            "DELETE FROM {} WHERE id = ANY($1)",
+            qualified(schema, table_name)
+        );
+        for ids in delta.deletes.chunks(DELETE_BATCH) {
+            client
+                .execute(&delete_sql, &[&ids])
+                .await
+                .with_context(|| format!("Failed to delete removed rows from '{}'", table_name))?;
+        }
+        let sql = update_sql(schema, table_name, jsonb.partitioning_for(table_name));
+        update_rows(client, &sql, table_name, &delta.updates).await?;
+        crate::jsonb::writer::insert_jsonb_batch(
+            client,
+            jsonb,
+            settings,
+            table_name,
+            delta.inserts,
+            source_type,
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(()) => {
+            client
+                .batch_execute("COMMIT")
+                .await
+                .context("Failed to commit refresh transaction")?;
+            Ok(counts)
+        }
+        Err(e) => {
+            let _ = client.batch_execute("ROLLBACK").await;
+            Err(e)
+        }
+    }
+}
+
+/// `UPDATE ... FROM unnest(...)` rewriting changed rows and their metadata
+///
+/// Field-partitioned tables also recompute `_partition_key`; PostgreSQL
+/// moves a row whose key changed to its new partition.
+fn update_sql(schema: &str, table_name: &str, partitioning: Option<&PartitionSpec>) -> String {
+    let key = partitioning
+        .and_then(|spec| spec.key_expression("v.data"))
+        .map(|expr| format!(", _partition_key = {}", expr))
+        .unwrap_or_default();
+    format!(
+        r#"UPDATE {table} AS t SET data = v.data, _row_hash = v.hash,
+               _source_updated_at = to_timestamp(v.updated), _synced_at = NOW(){key}
+           FROM unnest($1::text[], $2::jsonb[], $3::text[], $4::float8[]) AS v(id, data, hash, updated)
+           WHERE t.id = v.id"#,
+        table = qualified(schema, table_name),
+        key = key
+    )
+}
+
+async fn update_rows(
+    client: &Client,
+    sql: &str,
+    table_name: &str,
+    rows: &[(String, JsonValue)],
+) -> Result<()> {
+    for chunk in rows.chunks(UPDATE_BATCH) {
+        let ids: Vec<&str> = chunk.iter().map(|(id, _)| id.as_str()).collect();
+        let data: Vec<&JsonValue> = chunk.iter().map(|(_, data)| data).collect();
+        let hashes: Vec<String> = chunk.iter().map(|(_, data)| document_hash(data)).collect();
+        let updated: Vec<Option<f64>> = chunk
+            .iter()
+            .map(|(_, data)| crate::jsonb::metadata::source_updated_at(data))
+            .collect();
+        client
+            .execute(sql, &[&ids, &data, &hashes, &updated])
+            .await
+            .with_context(|| format!("Failed to update changed rows in '{}'", table_name))?;
+        crate::resources::record_target_written(
+            chunk
+                .iter()
+                .map(|(_, data)| data.to_string().len() as u64)
+                .sum(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plan_writes_only_changed_rows() {
+        let same = json!({"id": 1, "name": "Alice"});
+        let source = vec![
+            ("1".to_string(), same.clone()),
+            ("2".to_string(), json!({"id": 2, "name": "Bob (renamed)"})),
+            ("3".to_string(), json!({"id": 3, "name": "Carol"})),
+            ("4".to_string(), json!({"id": 4, "name": "Dan"})),
+        ];
+        let target = HashMap::from([
+            ("1".to_string(), Some(document_hash(&same))),
+            (
+                "2".to_string(),
+                Some(document_hash(&json!({"id": 2, "name": "Bob"}))),
+            ),
+            // Written before _row_hash existed
+            ("4".to_string(), None),
+            ("9".to_string(), Some("gone".to_string())),
+        ]);
+
+        let delta = plan(source, target);
+        assert_eq!(delta.unchanged, 1);
+        let ids = |rows: &[(String, JsonValue)]| -> Vec<String> {
+            rows.iter().map(|(id, _)| id.clone()).collect()
+        };
+        assert_eq!(ids(&delta.inserts), vec!["3"]);
+        assert_eq!(ids(&delta.updates), vec!["2", "4"]);
+        assert_eq!(delta.deletes, vec!["9".to_string()]);
+    }
+
+    #[test]
+    fn test_update_sql_keeps_migrated_at() {
+        let sql = update_sql("public", "users", None);
+        assert!(sql.contains(r#"UPDATE "public"."users" AS t"#));
+        assert!(sql.contains("WHERE t.id = v.id"));
+        assert!(sql.contains("_synced_at = NOW()"));
+        assert!(!sql.contains("_migrated_at"));
+        assert!(!sql.contains("_partition_key"));
+
+        let by_tenant = PartitionSpec {
+            key: crate::jsonb::partitioning::PartitionKey::Field("tenant".to_string()),
+            partitions: 4,
+        };
+        let sql = update_sql("public", "users", Some(&by_tenant));
+        assert!(sql.contains("_partition_key = coalesce((v.data::jsonb) ->> 'tenant', '')"));
+    }
+
+    #[test]
+    fn test_refresh_strategy_parse() {
+        assert_eq!(
+            "HASH".parse::<RefreshStrategy>().unwrap(),
+            RefreshStrategy::Hash
+        );
+        assert!("timestamp".parse::<RefreshStrategy>().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+// ABOUTME: Per-row metadata the JSONB writer stores next to each document: _row_hash, _source_updated_at, _synced_at
+// ABOUTME: Lets refresh, verify, and debugging queries work from the metadata columns without rescanning the JSON payloads
+
+use serde_json::Value as JsonValue;
+
+/// Document fields read as the source row's last-update time, in order of preference
+pub const UPDATED_AT_FIELDS: [&str; 6] = [
+    "updated_at",
+    "updatedAt",
+    "modified_at",
+    "modifiedAt",
+    "last_modified",
+    "lastModified",
+];
+
+/// Epoch values above this are taken as milliseconds (year 5138 in seconds)
+const MILLIS_THRESHOLD: f64 = 1e11;
+
+/// Seconds since the epoch of 0001-01-01 and 9999-12-31T23:59:59, the range stored
+const MIN_EPOCH: f64 = -62_135_596_800.0;
+const MAX_EPOCH: f64 = 253_402_300_799.0;
+
+/// The source row's last-update time, as seconds since the epoch
+///
+/// Taken from the first [`UPDATED_AT_FIELDS`] field that holds a timestamp:
+/// an ISO 8601 string (naive times are UTC), a MySQL `{"_type": "datetime"}`
+/// object, a MongoDB `{"$date": millis}` object, or epoch seconds or
+/// milliseconds. None when the document has no such field.
+pub fn source_updated_at(data: &JsonValue) -> Option<f64> {
+    let object = data.as_object()?;
+    UPDATED_AT_FIELDS
+        .iter()
+        .filter_map(|field| object.get(*field))
+        .find_map(timestamp_value)
+}
+
+fn timestamp_value(value: &JsonValue) -> Option<f64> {
+    let epoch = match value {
+        JsonValue::String(text) => parse_timestamp(text)?,
+        JsonValue::Number(number) => {
+            let n = number.as_f64()?;
+            if n.abs() > MILLIS_THRESHOLD {
+                n / 1000.0
+            } else {
+                n
+            }
+        }
+        JsonValue::Object(map) => {
+            if let Some(millis) = map.get("$date").and_then(JsonValue::as_f64) {
+                millis / 1000.0
+            } else {
+                parse_timestamp(map.get("value")?.as_str()?)?
+            }
+        }
+        _ => return None,
+    };
+    (MIN_EPOCH..=MAX_EPOCH).contains(&epoch).then_some(epoch)
+}
+
+/// Parse `YYYY-MM-DD[( |T)HH:MM[:SS[.fff]]][Z|±HH[:MM]]` to epoch seconds
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (date, rest) = text.split_at(text.len().min(10));
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = digits(parts.next()?, 4)?;
+    let month: i64 = digits(parts.next()?, 2)?;
+    let day: i64 = digits(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut seconds = (days_from_civil(year, month, day) * 86_400) as f64;
+
+    let rest = match rest.strip_prefix([' ', 'T']) {
+        Some(time) => {
+            let (clock, zone) = match time.find(['Z', 'z', '+', '-']) {
+                Some(at) => time.split_at(at),
+                None => (time, ""),
+            };
+            let mut fields = clock.splitn(3, ':');
+            let hour: i64 = digits(fields.next()?, 2)?;
+            let minute: i64 = digits(fields.next()?, 2)?;
+            let second: f64 = match fields.next() {
+                None => 0.0,
+                Some(s) => {
+                    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+                    let whole: i64 = digits(whole, 2)?;
+                    if !fraction.is_empty() && !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    format!("{}.{}0", whole, fraction).parse().ok()?
+                }
+            };
+            if hour > 23 || minute > 59 || second >= 61.0 {
+                return None;
+            }
+            seconds += (hour * 3600 + minute * 60) as f64 + second;
+            zone
+        }
+        None => rest,
+    };
+
+    match rest {
+        "" | "Z" | "z" => {}
+        zone => {
+            let sign = match zone.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let offset = zone[1..].replace(':', "");
+            let (hours, minutes) = match offset.len() {
+                2 => (digits(&offset, 2)?, 0),
+                4 => (digits(&offset[..2], 2)?, digits(&offset[2..], 2)?),
+                _ => return None,
+            };
+            if hours > 15 || minutes > 59 {
+                return None;
+            }
+            seconds -= (sign * (hours * 3600 + minutes * 60)) as f64;
+        }
+    }
+    Some(seconds)
+}
+
+fn digits(text: &str, len: usize) -> Option<i64> {
+    (text.len() == len && text.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| text.parse().ok())
+        .flatten()
+}
+
+pub(crate) fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01"), Some(0.0));
+        assert_eq!(
+            parse_timestamp("2024-01-15 10:30:45"),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            parse_timestamp("2024-01-15T10:30:45Z"),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            parse_timestamp("2024-01-15T12:30:45+02:00"),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            parse_timestamp("2024-01-15T10:30:45.5Z"),
+            Some(1_705_314_645.5)
+        );
+        assert_eq!(parse_timestamp("2024-02-29"), Some(1_709_164_800.0));
+        // PostgreSQL would reject these, failing the whole batch
+        assert_eq!(parse_timestamp("2023-02-29"), None);
+        assert_eq!(parse_timestamp("2024-13-01"), None);
+        assert_eq!(parse_timestamp("2024-01-15 25:00"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn test_source_updated_at_formats() {
+        assert_eq!(
+            source_updated_at(&json!({"id": 1, "updated_at": "2024-01-15 10:30:45"})),
+            Some(1_705_314_645.0)
+        );
+        // MySQL DATETIME, MongoDB tagged and millisecond dates, SQLite epoch seconds
+        assert_eq!(
+            source_updated_at(
+                &json!({"updated_at": {"_type": "datetime", "value": "2024-01-15T10:30:45.000000Z"}})
+            ),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            source_updated_at(
+                &json!({"updatedAt": {"_type": "datetime", "$date": 1_705_314_645_000i64}})
+            ),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            source_updated_at(&json!({"lastModified": 1_705_314_645_000i64})),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            source_updated_at(&json!({"modified_at": 1_705_314_645})),
+            Some(1_705_314_645.0)
+        );
+        assert_eq!(
+            source_updated_at(&json!({"updated_at": "n/a", "modified_at": "2024-01-15"})),
+            Some(1_705_276_800.0)
+        );
+        assert_eq!(
+            source_updated_at(&json!({"created_at": "2024-01-15"})),
+            None
+        );
+        assert_eq!(source_updated_at(&json!([1, 2])), None);
+    }
+}
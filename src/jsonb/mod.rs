@@ -1,9 +1,83 @@
 // ABOUTME: JSONB utilities for storing non-PostgreSQL database data
-// ABOUTME: Provides schema creation and validation for SQLite, MongoDB, and MySQL data storage
+// ABOUTME: Provides schema and table creation, validation, verification, and hash-based refresh for SQLite, MongoDB, and MySQL data
 
+pub mod batching;
+pub mod collisions;
+pub mod delta;
+pub mod metadata;
+pub mod partitioning;
+pub mod reconcile;
+pub mod row_errors;
+pub mod target_schema;
+pub mod verify;
 pub mod writer;
 
 use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Per-run settings for the JSONB copies made from SQLite, MySQL, and MongoDB sources
+///
+/// Passed to `init`, `verify`, and `reconcile` so two runs in one process can
+/// write to different schemas and convert values differently.
+#[derive(Debug, Clone)]
+pub struct JsonbOptions {
+    /// Schema holding the JSONB tables (see [`target_schema::resolve`])
+    pub target_schema: String,
+    /// What to do with rows that cannot be converted (`--on-row-error`)
+    pub on_row_error: row_errors::RowErrorPolicy,
+    /// How re-running init updates existing tables (`--refresh`)
+    pub refresh: delta::RefreshStrategy,
+    /// What to do when a table name is already taken (`--collision-policy`)
+    pub collision_policy: collisions::CollisionPolicy,
+    /// Batch size bounds per source type (`[jsonb_batching]`); missing types use
+    /// [`batching::BatchBounds::for_source`]
+    pub batching: HashMap<String, batching::BatchBounds>,
+    /// Partitioning per target table (`[jsonb_partitioning]`)
+    pub partitioning: HashMap<String, partitioning::PartitionSpec>,
+    /// Actual encodings of MySQL tables, overriding their declared charsets (`--mysql-charset`)
+    pub mysql_charsets: HashMap<String, &'static encoding_rs::Encoding>,
+    /// Zones assumed for MySQL DATETIME values (`--assume-source-tz`)
+    pub source_timezones: crate::mysql::datetime::SourceTimezones,
+    /// How zero and invalid MySQL dates are stored (`--invalid-dates`)
+    pub invalid_dates: crate::mysql::datetime::InvalidDatePolicy,
+    /// How MongoDB's BSON types are written (`[bson_types]`)
+    pub bson_types: crate::mongodb::type_map::BsonTypeMap,
+    /// Where GridFS file contents are written (`--gridfs-storage`)
+    pub gridfs_storage: crate::mongodb::gridfs::GridFsStorage,
+}
+
+impl JsonbOptions {
+    /// Batch size bounds for a source type
+    pub fn batch_bounds(&self, source_type: &str) -> batching::BatchBounds {
+        self.batching
+            .get(source_type)
+            .copied()
+            .unwrap_or_else(|| batching::BatchBounds::for_source(source_type))
+    }
+
+    /// Partitioning configured for a JSONB table, if any
+    pub fn partitioning_for(&self, table_name: &str) -> Option<&partitioning::PartitionSpec> {
+        self.partitioning.get(table_name)
+    }
+}
+
+impl Default for JsonbOptions {
+    fn default() -> Self {
+        JsonbOptions {
+            target_schema: target_schema::DEFAULT_SCHEMA.to_string(),
+            on_row_error: Default::default(),
+            refresh: Default::default(),
+            collision_policy: Default::default(),
+            batching: HashMap::new(),
+            partitioning: HashMap::new(),
+            mysql_charsets: HashMap::new(),
+            source_timezones: Default::default(),
+            invalid_dates: Default::default(),
+            bson_types: Default::default(),
+            gridfs_storage: Default::default(),
+        }
+    }
+}
 
 /// Validate a table name to prevent SQL injection
 ///
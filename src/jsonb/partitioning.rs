@@ -0,0 +1,193 @@
+// ABOUTME: Hash partitioning for JSONB target tables, by document id or a tenant field
+// ABOUTME: Builds the partitioned table DDL and the partition key written with each row
+
+use crate::utils::quote_literal;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Most hash partitions one JSONB table may be split into
+pub const MAX_PARTITIONS: u32 = 1024;
+
+/// One table's subsection of `[jsonb_partitioning]` in replication-config.toml
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct JsonbPartitionConfig {
+    /// Top-level document field to partition by; the document id when unset
+    pub field: Option<String>,
+    pub partitions: u32,
+}
+
+/// `[jsonb_partitioning]` section, keyed by target table name
+pub type JsonbPartitioningConfig = HashMap<String, JsonbPartitionConfig>;
+
+/// What a partitioned JSONB table is hashed on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionKey {
+    /// The `id` column
+    Id,
+    /// A top-level document field, stored in `_partition_key`
+    Field(String),
+}
+
+/// How one JSONB table is partitioned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionSpec {
+    pub key: PartitionKey,
+    pub partitions: u32,
+}
+
+impl PartitionSpec {
+    /// Validate a config subsection
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `partitions` is outside 2..=[`MAX_PARTITIONS`] or
+    /// `field` is empty.
+    pub fn from_config(config: &JsonbPartitionConfig) -> Result<Self> {
+        if !(2..=MAX_PARTITIONS).contains(&config.partitions) {
+            bail!(
+                "partitions must be between 2 and {}, got {}",
+                MAX_PARTITIONS,
+                config.partitions
+            );
+        }
+        let key = match config.field.as_deref().map(str::trim) {
+            None => PartitionKey::Id,
+            Some("") => bail!("field cannot be empty; omit it to partition by id"),
+            Some(field) => PartitionKey::Field(field.to_string()),
+        };
+        Ok(PartitionSpec {
+            key,
+            partitions: config.partitions,
+        })
+    }
+
+    /// Extra column definition, if the key is not the id
+    pub fn key_column(&self) -> Option<&'static str> {
+        match self.key {
+            PartitionKey::Id => None,
+            PartitionKey::Field(_) => Some("_partition_key TEXT NOT NULL"),
+        }
+    }
+
+    /// Primary key columns; PostgreSQL requires them to include the partition key
+    pub fn primary_key(&self) -> &'static str {
+        match self.key {
+            PartitionKey::Id => "id",
+            PartitionKey::Field(_) => "id, _partition_key",
+        }
+    }
+
+    pub fn partition_by(&self) -> &'static str {
+        match self.key {
+            PartitionKey::Id => "PARTITION BY HASH (id)",
+            PartitionKey::Field(_) => "PARTITION BY HASH (_partition_key)",
+        }
+    }
+
+    /// Value written to `_partition_key` for a row whose document is `data_param`
+    ///
+    /// Documents without the field, or with a null, all share the `''` key.
+    pub fn key_expression(&self, data_param: &str) -> Option<String> {
+        match &self.key {
+            PartitionKey::Id => None,
+            PartitionKey::Field(field) => Some(format!(
+                "coalesce(({}::jsonb) ->> {}, '')",
+                data_param,
+                quote_literal(field)
+            )),
+        }
+    }
+
+    /// `CREATE TABLE ... PARTITION OF` for each partition of `schema.table_name`
+    ///
+    /// Partitions are created in the parent's schema.
+    pub fn partition_statements(&self, schema: &str, table_name: &str) -> Vec<String> {
+        (0..self.partitions)
+            .map(|remainder| {
+                format!(
+                    r#"CREATE TABLE IF NOT EXISTS "{schema}"."{}" PARTITION OF "{schema}"."{}" FOR VALUES WITH (MODULUS {}, REMAINDER {})"#,
+                    partition_name(table_name, remainder),
+                    table_name,
+                    self.partitions,
+                    remainder
+                )
+            })
+            .collect()
+    }
+}
+
+/// `{table}_p{remainder}`, with the table part shortened to fit in 63 bytes
+fn partition_name(table_name: &str, remainder: u32) -> String {
+    let suffix = format!("_p{}", remainder);
+    let end = table_name.len().min(63 - suffix.len());
+    format!("{}{}", &table_name[..end], suffix)
+}
+
+/// Resolve the `[jsonb_partitioning]` config section into a spec per table
+///
+/// # Errors
+///
+/// Returns an error for invalid table names or partition settings.
+pub fn resolve(config: &JsonbPartitioningConfig) -> Result<HashMap<String, PartitionSpec>> {
+    let mut resolved = HashMap::new();
+    for (table_name, table_config) in config {
+        crate::jsonb::validate_table_name(table_name)
+            .map_err(|e| anyhow::anyhow!("[jsonb_partitioning.{}]: {}", table_name, e))?;
+        let spec = PartitionSpec::from_config(table_config)
+            .map_err(|e| anyhow::anyhow!("[jsonb_partitioning.{}]: {}", table_name, e))?;
+        resolved.insert(table_name.clone(), spec);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_from_config() {
+        let by_id = PartitionSpec::from_config(&JsonbPartitionConfig {
+            field: None,
+            partitions: 4,
+        })
+        .unwrap();
+        assert_eq!(by_id.key, PartitionKey::Id);
+        assert_eq!(by_id.key_column(), None);
+        assert_eq!(by_id.key_expression("$2"), None);
+        assert_eq!(
+            by_id.partition_statements("analytics", "events")[3],
+            r#"CREATE TABLE IF NOT EXISTS "analytics"."events_p3" PARTITION OF "analytics"."events" FOR VALUES WITH (MODULUS 4, REMAINDER 3)"#
+        );
+
+        let by_tenant = PartitionSpec::from_config(&JsonbPartitionConfig {
+            field: Some("tenant_id".to_string()),
+            partitions: 8,
+        })
+        .unwrap();
+        assert_eq!(by_tenant.primary_key(), "id, _partition_key");
+        assert_eq!(
+            by_tenant.key_expression("$2").unwrap(),
+            "coalesce(($2::jsonb) ->> 'tenant_id', '')"
+        );
+
+        for partitions in [0, 1, MAX_PARTITIONS + 1] {
+            assert!(PartitionSpec::from_config(&JsonbPartitionConfig {
+                field: None,
+                partitions,
+            })
+            .is_err());
+        }
+        assert!(PartitionSpec::from_config(&JsonbPartitionConfig {
+            field: Some(" ".to_string()),
+            partitions: 4,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn partition_names_fit_identifier_limit() {
+        assert_eq!(partition_name(&"t".repeat(63), 1023).len(), 63);
+    }
+}
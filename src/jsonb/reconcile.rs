@@ -0,0 +1,189 @@
+// ABOUTME: Deletes JSONB rows whose source row or document no longer exists (`reconcile` command)
+// ABOUTME: Pages through target IDs in batches and removes those missing from the source's ID set
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::time::SystemTime;
+use tokio_postgres::Client;
+
+use crate::jsonb::target_schema::qualified;
+
+/// Target IDs read (and at most deleted) per statement
+pub const DEFAULT_BATCH_SIZE: i64 = 10_000;
+
+/// Counts from reconciling one table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReconcileCounts {
+    /// Target rows compared with the source's IDs
+    pub checked: usize,
+    /// Target rows whose ID is not on the source (deleted unless a dry run)
+    pub stale: usize,
+}
+
+/// The IDs in `batch` that are not in `source`
+pub fn stale_ids(batch: &[String], source: &HashSet<String>) -> Vec<String> {
+    batch
+        .iter()
+        .filter(|id| !source.contains(*id))
+        .cloned()
+        .collect()
+}
+
+/// Delete target rows whose ID is not in `source_ids`
+///
+/// Target IDs are read in id order, `batch_size` at a time, and each batch's
+/// stale rows are deleted before the next is read, so memory stays bounded
+/// by the source's ID set. Each batch commits on its own; an interrupted run
+/// leaves the remaining stale rows for the next one.
+///
+/// Rows with a `_synced_at` at or after `synced_before` are left alone: a
+/// concurrent init may have written them from source rows created after the
+/// source IDs were read.
+///
+/// # Arguments
+///
+/// * `client` - Target connection
+/// * `schema` - Target schema holding the table
+/// * `table_name` - JSONB table written by [`crate::jsonb::writer`]
+/// * `source_ids` - Every ID the source table currently has
+/// * `synced_before` - Target time taken before `source_ids` was read
+/// * `batch_size` - Target IDs per batch
+/// * `dry_run` - Count stale rows without deleting them
+/// * `audit` - Where the deletion is recorded
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read or a batch cannot be deleted.
+#[allow(clippy::too_many_arguments)]
+pub async fn delete_stale_rows(
+    client: &Client,
+    schema: &str,
+    table_name: &str,
+    source_ids: &HashSet<String>,
+    synced_before: SystemTime,
+    batch_size: i64,
+    dry_run: bool,
+    audit: &crate::audit::AuditSettings,
+) -> Result<ReconcileCounts> {
+    crate::jsonb::validate_table_name(table_name)
+        .context("Invalid table name for JSONB reconciliation")?;
+
+    // Tables written before _synced_at existed have no recent rows to protect
+    let guard = has_synced_at(client, schema, table_name)
+        .await?
+        .then_some(synced_before);
+    let select = format!(
+        "SELECT id FROM {} WHERE id > $1 {} ORDER BY id LIMIT $2",
+        qualified(schema, table_name),
+        if guard.is_some() {
+            "AND _synced_at < $3"
+        } else {
+            ""
+        }
+    );
+    let delete = format!(
+        "DELETE FROM {} WHERE id = ANY($1)",
+        qualified(schema, table_name)
+    );
+    let mut counts = ReconcileCounts::default();
+    let mut after = String::new();
+    let mut audited = dry_run;
+    loop {
+        let rows = match &guard {
+            Some(before) => client.query(&select, &[&after, &batch_size, before]).await,
+            None => client.query(&select, &[&after, &batch_size]).await,
+        };
+        let batch: Vec<String> = rows
+            .with_context(|| format!("Failed to read IDs from '{}'", table_name))?
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = last.clone();
+        counts.checked += batch.len();
+
+        let stale = stale_ids(&batch, source_ids);
+        counts.stale += stale.len();
+        if !stale.is_empty() && !dry_run {
+            if !audited {
+                crate::audit::record_in(
+                    audit,
+                    client,
+                    crate::audit::AuditAction::DeleteRows,
+                    &qualified(schema, table_name),
+                )
+                .await;
+                audited = true;
+            }
+            client
+                .execute(&delete, &[&stale])
+                .await
+                .with_context(|| format!("Failed to delete stale rows from '{}'", table_name))?;
+        }
+        if (batch.len() as i64) < batch_size {
+            break;
+        }
+    }
+    Ok(counts)
+}
+
+async fn has_synced_at(client: &Client, schema: &str, table_name: &str) -> Result<bool> {
+    Ok(client
+        .query_one(
+            "SELECT EXISTS (
+                 SELECT 1 FROM pg_attribute
+                 WHERE attrelid = to_regclass($1) AND attname = '_synced_at' AND NOT attisdropped
+             )",
+            &[&qualified(schema, table_name)],
+        )
+        .await
+        .with_context(|| format!("Failed to read the columns of '{}'", table_name))?
+        .get(0))
+}
+
+/// The target's current time, taken before a table's source IDs are read
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub async fn target_now(client: &Client) -> Result<SystemTime> {
+    Ok(client
+        .query_one("SELECT now()", &[])
+        .await
+        .context("Failed to read the target's clock")?
+        .get(0))
+}
+
+/// Whether the JSONB table exists on the target
+///
+/// # Errors
+///
+/// Returns an error if the catalog cannot be read.
+pub async fn table_exists(client: &Client, schema: &str, table_name: &str) -> Result<bool> {
+    Ok(client
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&qualified(schema, table_name)],
+        )
+        .await
+        .context("Failed to check for JSONB table")?
+        .get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_ids() {
+        let source: HashSet<String> = ["1", "2", "4"].iter().map(|id| id.to_string()).collect();
+        let batch: Vec<String> = ["1", "2", "3", "4", "5"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(stale_ids(&batch, &source), vec!["3", "5"]);
+        assert!(stale_ids(&[], &source).is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+// ABOUTME: Per-row handling of source values that cannot be converted to JSONB
+// ABOUTME: Skips, stubs, or fails on bad rows and records them in a quarantine table on the target
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as JsonValue;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+/// Table on the target (in the database the rows were written to) holding bad rows
+pub const QUARANTINE_TABLE: &str = "seren_replicator.row_quarantine";
+
+/// What to do with a row that cannot be converted (`--on-row-error`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RowErrorPolicy {
+    /// Leave the row out and quarantine it
+    Skip,
+    /// Write a placeholder document in its place and quarantine it
+    Stub,
+    /// Stop converting the table
+    #[default]
+    Fail,
+}
+
+impl RowErrorPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RowErrorPolicy::Skip => "skip",
+            RowErrorPolicy::Stub => "stub",
+            RowErrorPolicy::Fail => "fail",
+        }
+    }
+}
+
+impl fmt::Display for RowErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RowErrorPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" => Ok(RowErrorPolicy::Skip),
+            "stub" => Ok(RowErrorPolicy::Stub),
+            "fail" => Ok(RowErrorPolicy::Fail),
+            other => bail!(
+                "Unknown row error policy '{}'. Expected one of: skip, stub, fail",
+                other
+            ),
+        }
+    }
+}
+
+/// A source row that could not be converted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    /// 1-based position of the row in the source table
+    pub row_number: u64,
+    /// Column whose value failed, when known
+    pub column: Option<String>,
+    /// The failing value's bytes as read from the source
+    pub raw: Vec<u8>,
+    pub error: String,
+}
+
+impl RowError {
+    /// Document id used for the row's stub
+    pub fn stub_id(&self) -> String {
+        format!("_row_error_{}", self.row_number)
+    }
+
+    /// Placeholder document written under the `stub` policy
+    pub fn stub_document(&self) -> JsonValue {
+        serde_json::json!({
+            "_row_error": {
+                "row": self.row_number,
+                "column": self.column,
+                "error": self.error,
+            }
+        })
+    }
+}
+
+/// Rows converted from one table, plus the rows the policy skipped or stubbed
+#[derive(Debug, Default)]
+pub struct ConvertedRows {
+    /// `(id, document)` pairs ready for insert, including stubs
+    pub rows: Vec<(String, JsonValue)>,
+    pub errors: Vec<RowError>,
+}
+
+impl ConvertedRows {
+    /// Apply `policy` to a row that failed to convert
+    ///
+    /// # Errors
+    ///
+    /// Returns the conversion error under the `fail` policy.
+    pub fn reject(&mut self, policy: RowErrorPolicy, table: &str, error: RowError) -> Result<()> {
+        let location = match &error.column {
+            Some(column) => format!("row {} column '{}'", error.row_number, column),
+            None => format!("row {}", error.row_number),
+        };
+        match policy {
+            RowErrorPolicy::Fail => bail!(
+                "Failed to convert {} in table '{}': {}. \
+                 Use --on-row-error skip or stub to quarantine bad rows and continue.",
+                location,
+                table,
+                error.error
+            ),
+            RowErrorPolicy::Skip => {}
+            RowErrorPolicy::Stub => self.rows.push((error.stub_id(), error.stub_document())),
+        }
+        tracing::warn!(
+            "⚠ Could not convert {} in table '{}' ({}): {}",
+            location,
+            table,
+            if policy == RowErrorPolicy::Skip {
+                "skipped"
+            } else {
+                "stubbed"
+            },
+            error.error
+        );
+        self.errors.push(error);
+        Ok(())
+    }
+}
+
+/// Record rows that failed conversion in [`QUARANTINE_TABLE`]
+///
+/// Creates the table on first use.
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be created or written.
+pub async fn quarantine_rows(
+    client: &Client,
+    source_type: &str,
+    table: &str,
+    policy: RowErrorPolicy,
+    errors: &[RowError],
+) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 recorded_at timestamptz NOT NULL DEFAULT now(),
+                 source_type text NOT NULL,
+                 table_name text NOT NULL,
+                 row_number bigint NOT NULL,
+                 column_name text,
+                 raw_value bytea NOT NULL,
+                 error text NOT NULL,
+                 action text NOT NULL
+             )",
+            QUARANTINE_TABLE
+        ))
+        .await
+        .context("Failed to create the row quarantine table on target")?;
+
+    let insert = format!(
+        "INSERT INTO {} (source_type, table_name, row_number, column_name, raw_value, error, action)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        QUARANTINE_TABLE
+    );
+    for error in errors {
+        client
+            .execute(
+                &insert,
+                &[
+                    &source_type,
+                    &table,
+                    &(error.row_number as i64),
+                    &error.column,
+                    &error.raw,
+                    &error.error,
+                    &policy.as_str(),
+                ],
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to quarantine row {} of table '{}'",
+                    error.row_number, table
+                )
+            })?;
+    }
+    tracing::info!(
+        "  ✓ Quarantined {} row(s) from '{}' in {}",
+        errors.len(),
+        table,
+        QUARANTINE_TABLE
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad_row() -> RowError {
+        RowError {
+            row_number: 7,
+            column: Some("name".to_string()),
+            raw: vec![0xff, 0xfe],
+            error: "invalid UTF-8".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(
+            "Skip".parse::<RowErrorPolicy>().unwrap(),
+            RowErrorPolicy::Skip
+        );
+        assert_eq!(
+            "stub".parse::<RowErrorPolicy>().unwrap(),
+            RowErrorPolicy::Stub
+        );
+        assert!("ignore".parse::<RowErrorPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_reject_applies_policy() {
+        let mut converted = ConvertedRows::default();
+        converted
+            .reject(RowErrorPolicy::Skip, "users", bad_row())
+            .unwrap();
+        assert!(converted.rows.is_empty());
+        assert_eq!(converted.errors.len(), 1);
+
+        converted
+            .reject(RowErrorPolicy::Stub, "users", bad_row())
+            .unwrap();
+        assert_eq!(converted.rows[0].0, "_row_error_7");
+        assert_eq!(converted.rows[0].1["_row_error"]["column"], "name");
+
+        let err = converted
+            .reject(RowErrorPolicy::Fail, "users", bad_row())
+            .unwrap_err();
+        assert!(err.to_string().contains("row 7 column 'name'"));
+        assert_eq!(converted.errors.len(), 2);
+    }
+}
@@ -0,0 +1,154 @@
+// ABOUTME: PostgreSQL schema that JSONB tables are written to (`--target-schema`, `[jsonb_target_schema]`)
+// ABOUTME: Schema-qualifies every JSONB table reference so a target role's search_path cannot redirect reads or writes
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use tokio_postgres::Client;
+
+/// Schema used when neither `--target-schema` nor the config names one
+pub const DEFAULT_SCHEMA: &str = "public";
+
+/// `[jsonb_target_schema]` section: default schema per source type
+///
+/// ```toml
+/// [jsonb_target_schema]
+/// sqlite = "sqlite_import"
+/// mysql = "mysql_import"
+/// mongodb = "mongo_import"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct JsonbTargetSchemaConfig {
+    pub sqlite: Option<String>,
+    pub mysql: Option<String>,
+    pub mongodb: Option<String>,
+}
+
+impl JsonbTargetSchemaConfig {
+    /// Schema configured for `source_type`, if any
+    pub fn for_source(&self, source_type: &crate::SourceType) -> Option<&str> {
+        match source_type {
+            crate::SourceType::SQLite => self.sqlite.as_deref(),
+            crate::SourceType::MySQL => self.mysql.as_deref(),
+            crate::SourceType::MongoDB => self.mongodb.as_deref(),
+            crate::SourceType::PostgreSQL => None,
+        }
+    }
+}
+
+/// Validate a target schema name
+///
+/// Follows [`crate::jsonb::validate_table_name`], and also rejects `pg_*`
+/// names (reserved by PostgreSQL) and the replicator's own `seren_replicator`
+/// schema, which holds quarantined rows and the audit log.
+///
+/// # Errors
+///
+/// Returns an error describing why the name cannot be used.
+pub fn validate_schema_name(schema: &str) -> Result<()> {
+    crate::jsonb::validate_table_name(schema)
+        .with_context(|| format!("Invalid target schema '{}'", schema))?;
+    let lower = schema.to_lowercase();
+    if lower.starts_with("pg_") {
+        bail!(
+            "Invalid target schema '{}': names starting with 'pg_' are reserved by PostgreSQL",
+            schema
+        );
+    }
+    if lower == "seren_replicator" {
+        bail!(
+            "Invalid target schema '{}': the schema holds the replicator's own tables.\n\
+             Choose a dedicated schema such as 'mysql_import'.",
+            schema
+        );
+    }
+    Ok(())
+}
+
+/// Pick the schema from `--target-schema`, then the config's per-source default, then `public`
+///
+/// # Errors
+///
+/// Returns an error if the chosen name is invalid.
+pub fn resolve(
+    flag: Option<&str>,
+    config: Option<&JsonbTargetSchemaConfig>,
+    source_type: &crate::SourceType,
+) -> Result<String> {
+    let schema = flag
+        .or_else(|| config.and_then(|config| config.for_source(source_type)))
+        .unwrap_or(DEFAULT_SCHEMA);
+    validate_schema_name(schema)?;
+    Ok(schema.to_string())
+}
+
+/// `"schema"."table"`; both names must be validated
+///
+/// They are quoted, not escaped. Also accepted by `to_regclass`.
+pub fn qualified(schema: &str, table_name: &str) -> String {
+    format!(r#""{}"."{}""#, schema, table_name)
+}
+
+/// Create the target schema if it does not exist
+///
+/// # Errors
+///
+/// Returns an error if the schema cannot be created, e.g. because the target
+/// role lacks CREATE on the database.
+pub async fn ensure_schema(client: &Client, schema: &str) -> Result<()> {
+    client
+        .batch_execute(&format!(r#"CREATE SCHEMA IF NOT EXISTS "{}""#, schema))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to create target schema '{}'.\n\
+                 Grant CREATE on the target database, or create the schema and grant the role USAGE and CREATE on it.",
+                schema
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceType;
+
+    #[test]
+    fn test_qualified_quotes_both_parts() {
+        assert_eq!(qualified("public", "users"), r#""public"."users""#);
+        assert_eq!(
+            qualified("mysql_import", "Orders"),
+            r#""mysql_import"."Orders""#
+        );
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let config = JsonbTargetSchemaConfig {
+            mysql: Some("mysql_import".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve(Some("staging"), Some(&config), &SourceType::MySQL).unwrap(),
+            "staging"
+        );
+        assert_eq!(
+            resolve(None, Some(&config), &SourceType::MySQL).unwrap(),
+            "mysql_import"
+        );
+        assert_eq!(
+            resolve(None, Some(&config), &SourceType::SQLite).unwrap(),
+            "public"
+        );
+        assert_eq!(resolve(None, None, &SourceType::MongoDB).unwrap(), "public");
+    }
+
+    #[test]
+    fn test_validate_schema_name() {
+        assert!(validate_schema_name("mysql_import").is_ok());
+        assert!(validate_schema_name("import; DROP SCHEMA public").is_err());
+        assert!(validate_schema_name("a\"b").is_err());
+        assert!(validate_schema_name("pg_import").is_err());
+        assert!(validate_schema_name("seren_replicator").is_err());
+    }
+}
@@ -0,0 +1,519 @@
+// ABOUTME: Compares source rows/documents with their JSONB copies on the target
+// ABOUTME: Uses canonical JSON hashing so key order and formatting do not cause false mismatches
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use tokio_postgres::Client;
+
+use crate::jsonb::target_schema::qualified;
+
+/// Number of divergent IDs listed per category in the log
+pub const MAX_REPORTED_IDS: usize = 10;
+
+/// Prefix of IDs generated during conversion for rows without an `id` column
+const GENERATED_ID_PREFIX: &str = "generated_";
+
+/// A document present on both sides with different content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DivergentDocument {
+    pub id: String,
+    /// Top-level fields whose values differ (or exist on one side only)
+    pub fields: Vec<String>,
+}
+
+/// Result of comparing one source table or collection with its JSONB table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct JsonbComparison {
+    pub table: String,
+    pub source_count: usize,
+    pub target_count: usize,
+    /// IDs present on the source but not the target
+    pub missing: Vec<String>,
+    /// IDs present on the target but not the source
+    pub extra: Vec<String>,
+    /// Documents present on both sides whose content differs
+    pub different: Vec<DivergentDocument>,
+    /// Rows compared by content only, because their IDs were generated during conversion
+    pub compared_by_content: bool,
+}
+
+impl JsonbComparison {
+    /// True when counts match and no row is missing, extra, or different
+    pub fn is_valid(&self) -> bool {
+        self.source_count == self.target_count
+            && self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.different.is_empty()
+    }
+
+    /// Log the result, listing up to [`MAX_REPORTED_IDS`] IDs per category
+    pub fn log(&self) {
+        if self.is_valid() {
+            tracing::info!("  ✓ {}: Match ({} rows)", self.table, self.source_count);
+            return;
+        }
+        tracing::error!(
+            "  ✗ {}: MISMATCH: source={} rows, target={} rows",
+            self.table,
+            self.source_count,
+            self.target_count
+        );
+        let noun = if self.compared_by_content {
+            "row(s) with no matching content"
+        } else {
+            "ID(s)"
+        };
+        let different: Vec<String> = self
+            .different
+            .iter()
+            .map(|doc| format!("{} ({})", doc.id, doc.fields.join(", ")))
+            .collect();
+        for (label, ids) in [
+            ("missing on target", &self.missing),
+            ("only on target", &self.extra),
+            ("with different content", &different),
+        ] {
+            if ids.is_empty() {
+                continue;
+            }
+            tracing::error!(
+                "      {} {} {}: {}{}",
+                ids.len(),
+                noun,
+                label,
+                ids.iter()
+                    .take(MAX_REPORTED_IDS)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                if ids.len() > MAX_REPORTED_IDS {
+                    ", ..."
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+}
+
+/// Serialize a JSON value with object keys sorted at every level
+///
+/// # Examples
+///
+/// ```
+/// # use seren_replicator::jsonb::verify::canonical_json;
+/// # use serde_json::json;
+/// assert_eq!(
+///     canonical_json(&json!({"b": 1, "a": {"d": [true, null], "c": "x"}})),
+///     r#"{"a":{"c":"x","d":[true,null]},"b":1}"#
+/// );
+/// ```
+pub fn canonical_json(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Object(map) => {
+            let sorted: BTreeMap<&String, &JsonValue> = map.iter().collect();
+            out.push('{');
+            for (i, (key, value)) in sorted.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&JsonValue::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// SHA-256 of the canonical JSON form of a document
+pub fn document_hash(value: &JsonValue) -> String {
+    format!("{:x}", Sha256::digest(canonical_json(value).as_bytes()))
+}
+
+/// Read `(id, data)` from a JSONB table written by `jsonb::writer`
+///
+/// # Security
+///
+/// `table_name` is validated before use.
+pub async fn load_jsonb_rows(
+    client: &Client,
+    schema: &str,
+    table_name: &str,
+) -> Result<Vec<(String, JsonValue)>> {
+    crate::jsonb::validate_table_name(table_name)
+        .context("Invalid table name for JSONB verification")?;
+
+    let exists: bool = client
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&qualified(schema, table_name)],
+        )
+        .await
+        .context("Failed to check for JSONB table")?
+        .get(0);
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let rows = client
+        .query(
+            &format!("SELECT id, data FROM {}", qualified(schema, table_name)),
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to read JSONB table '{}'", table_name))?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Compare source documents with a JSONB table, reading its `_row_hash` column
+///
+/// When every target row has a stored hash, only `(id, _row_hash)` is read,
+/// plus the documents of rows that differ (to name the differing fields).
+/// Tables written before `_row_hash` existed are compared by reading every
+/// document, as [`compare_documents`] does.
+///
+/// # Errors
+///
+/// Returns an error if the target table cannot be read.
+pub async fn compare_with_target(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    source: &[(String, JsonValue)],
+) -> Result<JsonbComparison> {
+    let Some(target) = load_row_hashes(client, schema, table).await? else {
+        let target = load_jsonb_rows(client, schema, table).await?;
+        return Ok(compare_documents(table, source, &target));
+    };
+    let mut comparison = compare_hashes(table, source, &target);
+    if !comparison.different.is_empty() {
+        let ids: Vec<&str> = comparison
+            .different
+            .iter()
+            .map(|doc| doc.id.as_str())
+            .collect();
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT id, data FROM {} WHERE id = ANY($1)",
+                    qualified(schema, table)
+                ),
+                &[&ids],
+            )
+            .await
+            .with_context(|| format!("Failed to read differing rows from '{}'", table))?;
+        let target_docs: HashMap<String, JsonValue> =
+            rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+        fill_differing_fields(&mut comparison, source, |id| target_docs.get(id));
+    }
+    Ok(comparison)
+}
+
+/// `(id, _row_hash)` of a JSONB table; None when some row has no stored hash
+///
+/// A missing table has no rows.
+async fn load_row_hashes(
+    client: &Client,
+    schema: &str,
+    table_name: &str,
+) -> Result<Option<Vec<(String, String)>>> {
+    crate::jsonb::validate_table_name(table_name)
+        .context("Invalid table name for JSONB verification")?;
+
+    let row = client
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL, EXISTS (
+                 SELECT 1 FROM pg_attribute
+                 WHERE attrelid = to_regclass($1) AND attname = '_row_hash' AND NOT attisdropped
+             )",
+            &[&qualified(schema, table_name)],
+        )
+        .await
+        .context("Failed to check for JSONB table")?;
+    let (exists, has_hashes): (bool, bool) = (row.get(0), row.get(1));
+    if !exists {
+        return Ok(Some(Vec::new()));
+    }
+    if !has_hashes {
+        return Ok(None);
+    }
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT id, _row_hash FROM {}",
+                qualified(schema, table_name)
+            ),
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to read row hashes from '{}'", table_name))?;
+    Ok(rows
+        .iter()
+        .map(|row| Some((row.get(0), row.get::<_, Option<String>>(1)?)))
+        .collect())
+}
+
+/// Compare source documents with the target's JSONB rows
+///
+/// Rows are matched by ID and their canonical hashes compared. When the source
+/// IDs were generated during conversion (tables without an `id` column), they
+/// depend on read order, so rows are matched by content hash instead.
+pub fn compare_documents(
+    table: &str,
+    source: &[(String, JsonValue)],
+    target: &[(String, JsonValue)],
+) -> JsonbComparison {
+    let hashes: Vec<(String, String)> = target
+        .iter()
+        .map(|(id, doc)| (id.clone(), document_hash(doc)))
+        .collect();
+    let mut comparison = compare_hashes(table, source, &hashes);
+    let target_by_id: HashMap<&str, &JsonValue> =
+        target.iter().map(|(id, doc)| (id.as_str(), doc)).collect();
+    fill_differing_fields(&mut comparison, source, |id| target_by_id.get(id).copied());
+    comparison
+}
+
+/// Compare source documents with the target's `(id, hash)` pairs
+///
+/// Divergent documents are listed without their fields; see
+/// [`fill_differing_fields`].
+fn compare_hashes(
+    table: &str,
+    source: &[(String, JsonValue)],
+    target: &[(String, String)],
+) -> JsonbComparison {
+    let mut comparison = JsonbComparison {
+        table: table.to_string(),
+        source_count: source.len(),
+        target_count: target.len(),
+        ..Default::default()
+    };
+
+    let generated = source
+        .iter()
+        .map(|(id, _)| id)
+        .chain(target.iter().map(|(id, _)| id))
+        .any(|id| id.starts_with(GENERATED_ID_PREFIX));
+    if generated {
+        comparison.compared_by_content = true;
+        let mut target_hashes: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (id, hash) in target {
+            target_hashes.entry(hash).or_default().push(id);
+        }
+        for (id, doc) in source {
+            let matched = target_hashes
+                .get_mut(document_hash(doc).as_str())
+                .and_then(|ids| ids.pop());
+            if matched.is_none() {
+                comparison.missing.push(id.clone());
+            }
+        }
+        let mut extra: Vec<String> = target_hashes
+            .into_values()
+            .flatten()
+            .map(str::to_string)
+            .collect();
+        extra.sort();
+        comparison.extra = extra;
+        return comparison;
+    }
+
+    let target_by_id: HashMap<&str, &str> = target
+        .iter()
+        .map(|(id, hash)| (id.as_str(), hash.as_str()))
+        .collect();
+    let source_ids: std::collections::HashSet<&str> =
+        source.iter().map(|(id, _)| id.as_str()).collect();
+
+    for (id, doc) in source {
+        match target_by_id.get(id.as_str()) {
+            None => comparison.missing.push(id.clone()),
+            Some(hash) if document_hash(doc) != *hash => {
+                comparison.different.push(DivergentDocument {
+                    id: id.clone(),
+                    fields: Vec::new(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    comparison.extra = target
+        .iter()
+        .filter(|(id, _)| !source_ids.contains(id.as_str()))
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    comparison.missing.sort();
+    comparison.extra.sort();
+    comparison.different.sort_by(|a, b| a.id.cmp(&b.id));
+    comparison
+}
+
+/// Name the differing fields of each divergent document, given the target's documents
+fn fill_differing_fields<'a>(
+    comparison: &mut JsonbComparison,
+    source: &[(String, JsonValue)],
+    target: impl Fn(&str) -> Option<&'a JsonValue>,
+) {
+    let source_by_id: HashMap<&str, &JsonValue> =
+        source.iter().map(|(id, doc)| (id.as_str(), doc)).collect();
+    for divergent in &mut comparison.different {
+        if let (Some(source_doc), Some(target_doc)) = (
+            source_by_id.get(divergent.id.as_str()),
+            target(&divergent.id),
+        ) {
+            divergent.fields = differing_fields(source_doc, target_doc);
+        }
+    }
+}
+
+/// Top-level fields that differ between two documents, sorted
+///
+/// Returns `["<document>"]` when either side is not a JSON object.
+pub fn differing_fields(source: &JsonValue, target: &JsonValue) -> Vec<String> {
+    let (Some(source), Some(target)) = (source.as_object(), target.as_object()) else {
+        return vec!["<document>".to_string()];
+    };
+    let mut fields: Vec<String> = source
+        .keys()
+        .chain(target.keys())
+        .filter(|key| source.get(*key).map(canonical_json) != target.get(*key).map(canonical_json))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(items: &[(&str, JsonValue)]) -> Vec<(String, JsonValue)> {
+        items
+            .iter()
+            .map(|(id, doc)| (id.to_string(), doc.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_document_hash_ignores_key_order() {
+        let a: JsonValue = serde_json::from_str(r#"{"a": 1, "b": {"x": 1, "y": 2}}"#).unwrap();
+        let b: JsonValue = serde_json::from_str(r#"{"b": {"y": 2, "x": 1}, "a": 1}"#).unwrap();
+        assert_eq!(document_hash(&a), document_hash(&b));
+        assert_ne!(document_hash(&a), document_hash(&json!({"a": 2})));
+    }
+
+    #[test]
+    fn test_compare_documents_by_id() {
+        let source = rows(&[
+            ("1", json!({"id": 1, "name": "Alice"})),
+            ("2", json!({"id": 2, "name": "Bob"})),
+            ("3", json!({"id": 3, "name": "Carol"})),
+        ]);
+        let target = rows(&[
+            ("1", json!({"name": "Alice", "id": 1})),
+            ("2", json!({"id": 2, "name": "Robert"})),
+            ("4", json!({"id": 4, "name": "Dave"})),
+        ]);
+
+        let result = compare_documents("users", &source, &target);
+        assert!(!result.is_valid());
+        assert!(!result.compared_by_content);
+        assert_eq!(result.missing, vec!["3"]);
+        assert_eq!(result.extra, vec!["4"]);
+        assert_eq!(
+            result.different,
+            vec![DivergentDocument {
+                id: "2".to_string(),
+                fields: vec!["name".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compare_documents_generated_ids_by_content() {
+        // Same rows read in a different order get different generated IDs
+        let source = rows(&[
+            ("generated_1", json!({"k": "a"})),
+            ("generated_2", json!({"k": "b"})),
+        ]);
+        let target = rows(&[
+            ("generated_1", json!({"k": "b"})),
+            ("generated_2", json!({"k": "a"})),
+        ]);
+        let result = compare_documents("log", &source, &target);
+        assert!(result.compared_by_content);
+        assert!(result.is_valid());
+
+        let target = rows(&[
+            ("generated_1", json!({"k": "b"})),
+            ("generated_2", json!({"k": "c"})),
+        ]);
+        let result = compare_documents("log", &source, &target);
+        assert_eq!(result.missing, vec!["generated_1"]);
+        assert_eq!(result.extra, vec!["generated_2"]);
+    }
+
+    #[test]
+    fn test_compare_hashes_with_stored_hashes() {
+        let source = rows(&[
+            ("1", json!({"id": 1, "name": "Alice"})),
+            ("2", json!({"id": 2, "name": "Bob"})),
+        ]);
+        let target = vec![
+            (
+                "1".to_string(),
+                document_hash(&json!({"name": "Alice", "id": 1})),
+            ),
+            (
+                "2".to_string(),
+                document_hash(&json!({"id": 2, "name": "Robert"})),
+            ),
+        ];
+        let mut result = compare_hashes("users", &source, &target);
+        assert_eq!(result.different.len(), 1);
+        assert!(result.different[0].fields.is_empty());
+
+        // Only the divergent document is fetched to name its fields
+        let robert = json!({"id": 2, "name": "Robert"});
+        fill_differing_fields(&mut result, &source, |id| (id == "2").then_some(&robert));
+        assert_eq!(result.different[0].fields, vec!["name"]);
+    }
+
+    #[test]
+    fn test_differing_fields() {
+        assert_eq!(
+            differing_fields(
+                &json!({"a": 1, "b": {"x": 1, "y": 2}, "c": 3}),
+                &json!({"b": {"y": 2, "x": 1}, "c": 4, "d": null}),
+            ),
+            vec!["a", "c", "d"]
+        );
+        assert_eq!(differing_fields(&json!(1), &json!({})), vec!["<document>"]);
+    }
+}
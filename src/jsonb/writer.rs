@@ -1,7 +1,13 @@
 // ABOUTME: Write JSONB data to PostgreSQL with metadata
-// ABOUTME: Handles table creation, single row inserts, and batch inserts
+// ABOUTME: Handles table creation, single row inserts, and adaptively sized batch inserts
 
+use crate::jsonb::batching::{BatchSizer, PARAMS_PER_ROW};
+use crate::jsonb::partitioning::PartitionSpec;
+use crate::jsonb::target_schema::qualified;
+use crate::jsonb::JsonbOptions;
+use crate::settings::RunSettings;
 use anyhow::{Context, Result};
+use std::time::Instant;
 use tokio_postgres::Client;
 
 /// Create a table with JSONB schema for storing non-PostgreSQL data
@@ -11,14 +17,26 @@ use tokio_postgres::Client;
 /// - data: JSONB NOT NULL (complete document/row as JSON)
 /// - _source_type: TEXT NOT NULL ('sqlite', 'mongodb', or 'mysql')
 /// - _migrated_at: TIMESTAMP NOT NULL DEFAULT NOW()
+/// - _row_hash: TEXT (canonical SHA-256 of `data`; see [`crate::jsonb::verify::document_hash`])
+/// - _source_updated_at: TIMESTAMPTZ (the document's `updated_at`-style field, if any;
+///   see [`crate::jsonb::metadata`])
+/// - _synced_at: TIMESTAMPTZ NOT NULL DEFAULT NOW() (when the row was last written)
+///
+/// `--refresh hash` and `verify` read the metadata columns instead of the
+/// JSON payloads. Tables created before these columns existed gain them.
 ///
 /// Also creates two indexes:
 /// - GIN index on data column for efficient JSONB queries
 /// - Index on _migrated_at for temporal queries
 ///
+/// Tables listed in `[jsonb_partitioning]` are hash-partitioned instead (see
+/// [`crate::jsonb::partitioning`]). Partitioning by a document field adds a
+/// `_partition_key TEXT` column, which joins `id` in the primary key.
+///
 /// # Arguments
 ///
 /// * `client` - PostgreSQL client connection
+/// * `jsonb` - Target schema and table partitioning of this run
 /// * `table_name` - Name of the table to create (must be validated)
 /// * `source_type` - Source database type ('sqlite', 'mongodb', or 'mysql')
 ///
@@ -31,16 +49,18 @@ use tokio_postgres::Client;
 ///
 /// ```no_run
 /// # use seren_replicator::jsonb::writer::create_jsonb_table;
+/// # use seren_replicator::jsonb::JsonbOptions;
 /// # use seren_replicator::jsonb::validate_table_name;
 /// # async fn example(client: &tokio_postgres::Client) -> anyhow::Result<()> {
 /// let table_name = "users";
 /// validate_table_name(table_name)?;
-/// create_jsonb_table(client, table_name, "sqlite").await?;
+/// create_jsonb_table(client, &JsonbOptions::default(), table_name, "sqlite").await?;
 /// # Ok(())
 /// # }
 /// ```
 pub async fn create_jsonb_table(
     client: &Client,
+    jsonb: &JsonbOptions,
     table_name: &str,
     source_type: &str,
 ) -> Result<()> {
@@ -54,29 +74,94 @@ pub async fn create_jsonb_table(
         source_type
     );
 
+    let schema = jsonb.target_schema.as_str();
+    crate::jsonb::target_schema::ensure_schema(client, schema).await?;
+
     // Create table with JSONB schema
     // Note: table_name is validated above, so it's safe to use in SQL
-    let create_table_sql = format!(
-        r#"
-        CREATE TABLE IF NOT EXISTS "{}" (
-            id TEXT PRIMARY KEY,
-            data JSONB NOT NULL,
-            _source_type TEXT NOT NULL,
-            _migrated_at TIMESTAMP NOT NULL DEFAULT NOW()
-        )
-        "#,
-        table_name
-    );
+    let table = qualified(schema, table_name);
+    let partitioning = jsonb.partitioning_for(table_name);
+    let create_table_sql = match partitioning {
+        None => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id TEXT PRIMARY KEY,
+                data JSONB NOT NULL,
+                _source_type TEXT NOT NULL,
+                _migrated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+                _row_hash TEXT,
+                _source_updated_at TIMESTAMPTZ,
+                _synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            table
+        ),
+        Some(spec) => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id TEXT NOT NULL,
+                data JSONB NOT NULL,
+                _source_type TEXT NOT NULL,
+                _migrated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+                _row_hash TEXT,
+                _source_updated_at TIMESTAMPTZ,
+                _synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                {}
+                PRIMARY KEY ({})
+            ) {}
+            "#,
+            table,
+            spec.key_column()
+                .map(|column| format!("{},", column))
+                .unwrap_or_default(),
+            spec.primary_key(),
+            spec.partition_by()
+        ),
+    };
 
     client
         .execute(&create_table_sql, &[])
         .await
         .with_context(|| format!("Failed to create JSONB table '{}'", table_name))?;
+    client
+        .execute(
+            &format!(
+                r#"ALTER TABLE {}
+                   ADD COLUMN IF NOT EXISTS _row_hash TEXT,
+                   ADD COLUMN IF NOT EXISTS _source_updated_at TIMESTAMPTZ,
+                   ADD COLUMN IF NOT EXISTS _synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()"#,
+                table
+            ),
+            &[],
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to add metadata columns to JSONB table '{}'",
+                table_name
+            )
+        })?;
+
+    if let Some(spec) = partitioning {
+        for statement in spec.partition_statements(schema, table_name) {
+            client.execute(&statement, &[]).await.with_context(|| {
+                format!(
+                    "Failed to create partitions of JSONB table '{}'",
+                    table_name
+                )
+            })?;
+        }
+        tracing::info!(
+            "Partitioned JSONB table '{}' into {} hash partitions",
+            table_name,
+            spec.partitions
+        );
+    }
 
     // Create GIN index on data column for efficient JSONB queries
     let create_gin_index_sql = format!(
-        r#"CREATE INDEX IF NOT EXISTS "idx_{}_data" ON "{}" USING GIN (data)"#,
-        table_name, table_name
+        r#"CREATE INDEX IF NOT EXISTS "idx_{}_data" ON {} USING GIN (data)"#,
+        table_name, table
     );
 
     client
@@ -86,8 +171,8 @@ pub async fn create_jsonb_table(
 
     // Create index on _migrated_at for temporal queries
     let create_time_index_sql = format!(
-        r#"CREATE INDEX IF NOT EXISTS "idx_{}_migrated" ON "{}" (_migrated_at)"#,
-        table_name, table_name
+        r#"CREATE INDEX IF NOT EXISTS "idx_{}_migrated" ON {} (_migrated_at)"#,
+        table_name, table
     );
 
     client
@@ -115,6 +200,7 @@ pub async fn create_jsonb_table(
 /// # Arguments
 ///
 /// * `client` - PostgreSQL client connection
+/// * `jsonb` - Target schema and table partitioning of this run
 /// * `table_name` - Name of the table (must be validated)
 /// * `id` - Original document/row ID
 /// * `data` - Complete document/row as serde_json::Value
@@ -129,18 +215,21 @@ pub async fn create_jsonb_table(
 ///
 /// ```no_run
 /// # use seren_replicator::jsonb::writer::insert_jsonb_row;
+/// # use seren_replicator::jsonb::JsonbOptions;
 /// # use seren_replicator::jsonb::validate_table_name;
+/// # use seren_replicator::settings::RunSettings;
 /// # use serde_json::json;
 /// # async fn example(client: &tokio_postgres::Client) -> anyhow::Result<()> {
 /// let table_name = "users";
 /// validate_table_name(table_name)?;
 /// let data = json!({"name": "Alice", "age": 30});
-/// insert_jsonb_row(client, table_name, "1", data, "sqlite").await?;
+/// insert_jsonb_row(client, &JsonbOptions::default(), table_name, "1", data, "sqlite").await?;
 /// # Ok(())
 /// # }
 /// ```
 pub async fn insert_jsonb_row(
     client: &Client,
+    jsonb: &JsonbOptions,
     table_name: &str,
     id: &str,
     data: serde_json::Value,
@@ -152,13 +241,18 @@ pub async fn insert_jsonb_row(
 
     // Use parameterized query for data and metadata (safe from injection)
     // Note: table_name is validated above
+    let partitioning = jsonb.partitioning_for(table_name);
     let insert_sql = format!(
-        r#"INSERT INTO "{}" (id, data, _source_type) VALUES ($1, $2, $3)"#,
-        table_name
+        r#"INSERT INTO {} {} VALUES {}"#,
+        qualified(&jsonb.target_schema, table_name),
+        insert_columns(partitioning),
+        row_values(partitioning, 1)
     );
 
+    let hash = crate::jsonb::verify::document_hash(&data);
+    let updated_at = crate::jsonb::metadata::source_updated_at(&data);
     client
-        .execute(&insert_sql, &[&id, &data, &source_type])
+        .execute(&insert_sql, &[&id, &data, &source_type, &hash, &updated_at])
         .await
         .with_context(|| {
             format!(
@@ -178,6 +272,8 @@ pub async fn insert_jsonb_row(
 /// # Arguments
 ///
 /// * `client` - PostgreSQL client connection
+/// * `jsonb` - Target schema, partitioning, and batch bounds of this run
+/// * `settings` - Memory budget and chaos faults of this run
 /// * `table_name` - Name of the table (must be validated)
 /// * `rows` - Vector of (id, data) tuples
 /// * `source_type` - Source database type ('sqlite', 'mongodb', or 'mysql')
@@ -188,13 +284,17 @@ pub async fn insert_jsonb_row(
 ///
 /// # Performance
 ///
-/// Batches rows into groups of 1000 to avoid PostgreSQL parameter limits.
+/// Batch sizes adapt to the target's latency and the average row size within
+/// the bounds configured for `source_type` (see [`crate::jsonb::batching`]),
+/// never exceeding PostgreSQL's bind parameter limit.
 ///
 /// # Examples
 ///
 /// ```no_run
 /// # use seren_replicator::jsonb::writer::insert_jsonb_batch;
+/// # use seren_replicator::jsonb::JsonbOptions;
 /// # use seren_replicator::jsonb::validate_table_name;
+/// # use seren_replicator::settings::RunSettings;
 /// # use serde_json::json;
 /// # async fn example(client: &tokio_postgres::Client) -> anyhow::Result<()> {
 /// let table_name = "users";
@@ -203,12 +303,15 @@ pub async fn insert_jsonb_row(
 ///     ("1".to_string(), json!({"name": "Alice", "age": 30})),
 ///     ("2".to_string(), json!({"name": "Bob", "age": 25})),
 /// ];
-/// insert_jsonb_batch(client, table_name, rows, "sqlite").await?;
+/// let (jsonb, settings) = (JsonbOptions::default(), RunSettings::default());
+/// insert_jsonb_batch(client, &jsonb, &settings, table_name, rows, "sqlite").await?;
 /// # Ok(())
 /// # }
 /// ```
 pub async fn insert_jsonb_batch(
     client: &Client,
+    jsonb: &JsonbOptions,
+    settings: &RunSettings,
     table_name: &str,
     rows: Vec<(String, serde_json::Value)>,
     source_type: &str,
@@ -227,39 +330,61 @@ pub async fn insert_jsonb_batch(
         table_name
     );
 
-    // Batch inserts to avoid parameter limit (PostgreSQL limit is ~65535 parameters)
-    // With 3 parameters per row (id, data, source_type), we can do ~21000 rows
-    // Use conservative 1000 rows per batch
-    const BATCH_SIZE: usize = 1000;
+    let mut sizer = BatchSizer::new(jsonb.batch_bounds(source_type), settings.memory.clone());
+    let partitioning = jsonb.partitioning_for(table_name);
+    let mut remaining = rows.as_slice();
+    let mut batch_num = 0;
+
+    while !remaining.is_empty() {
+        let (count, bytes) = sizer.next_batch(remaining);
+        let (chunk, rest) = remaining.split_at(count);
+        remaining = rest;
+        let corrupted = settings.chaos.corrupt_next_batch().then(|| {
+            tracing::warn!(
+                "⚠ Chaos: corrupting batch {} of '{}'",
+                batch_num,
+                table_name
+            );
+            let mut chunk = chunk.to_vec();
+            chunk[0].1 = serde_json::json!({ "_chaos_corrupted": true });
+            chunk
+        });
+        let chunk = corrupted.as_deref().unwrap_or(chunk);
 
-    for (batch_num, chunk) in rows.chunks(BATCH_SIZE).enumerate() {
         // Build parameterized multi-value INSERT
-        // Format: INSERT INTO table (cols) VALUES ($1,$2,$3),($4,$5,$6),...
+        // Format: INSERT INTO table (cols) VALUES ($1,$2,...,$5),($6,$7,...,$10),...
         let mut value_placeholders = Vec::with_capacity(chunk.len());
+        let metadata: Vec<(String, Option<f64>)> = chunk
+            .iter()
+            .map(|(_, data)| {
+                (
+                    crate::jsonb::verify::document_hash(data),
+                    crate::jsonb::metadata::source_updated_at(data),
+                )
+            })
+            .collect();
         let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
-            Vec::with_capacity(chunk.len() * 3);
-
-        for (idx, (id, data)) in chunk.iter().enumerate() {
-            let param_base = idx * 3 + 1;
-            value_placeholders.push(format!(
-                "(${}, ${}, ${})",
-                param_base,
-                param_base + 1,
-                param_base + 2
-            ));
-
-            // Add parameters in order: id, data, source_type
+            Vec::with_capacity(chunk.len() * PARAMS_PER_ROW);
+
+        for (idx, ((id, data), (hash, updated_at))) in chunk.iter().zip(&metadata).enumerate() {
+            value_placeholders.push(row_values(partitioning, idx * PARAMS_PER_ROW + 1));
+
+            // Add parameters in order: id, data, source_type, row hash, source updated-at
             params.push(id);
             params.push(data);
             params.push(&source_type);
+            params.push(hash);
+            params.push(updated_at);
         }
 
         let insert_sql = format!(
-            r#"INSERT INTO "{}" (id, data, _source_type) VALUES {}"#,
-            table_name,
+            r#"INSERT INTO {} {} VALUES {}"#,
+            qualified(&jsonb.target_schema, table_name),
+            insert_columns(partitioning),
             value_placeholders.join(", ")
         );
 
+        let started = Instant::now();
         client
             .execute(&insert_sql, &params)
             .await
@@ -272,12 +397,20 @@ pub async fn insert_jsonb_batch(
                 )
             })?;
 
+        let elapsed = started.elapsed();
+        sizer.record(count, bytes, elapsed);
+        crate::resources::record_target_written(bytes as u64);
+
         tracing::debug!(
-            "Inserted batch {} ({} rows) into '{}'",
+            "Inserted batch {} ({} rows, {} bytes, {:?}) into '{}'; next batch {} rows",
             batch_num,
             chunk.len(),
-            table_name
+            bytes,
+            elapsed,
+            table_name,
+            sizer.batch_rows()
         );
+        batch_num += 1;
     }
 
     tracing::info!(
@@ -289,6 +422,38 @@ pub async fn insert_jsonb_batch(
     Ok(())
 }
 
+/// Column list for an INSERT; field-partitioned tables also take `_partition_key`
+///
+/// `_synced_at` is left to its default.
+fn insert_columns(partitioning: Option<&PartitionSpec>) -> &'static str {
+    match partitioning.and_then(|spec| spec.key_column()) {
+        Some(_) => "(id, data, _source_type, _row_hash, _source_updated_at, _partition_key)",
+        None => "(id, data, _source_type, _row_hash, _source_updated_at)",
+    }
+}
+
+/// One row's VALUES tuple, binding id, data, source type, row hash, and
+/// source updated-at (epoch seconds) from `param_base`
+///
+/// The partition key is derived from the bound document, so each row still
+/// takes [`PARAMS_PER_ROW`] parameters.
+fn row_values(partitioning: Option<&PartitionSpec>, param_base: usize) -> String {
+    let data = format!("${}", param_base + 1);
+    let key = partitioning
+        .and_then(|spec| spec.key_expression(&data))
+        .map(|expr| format!(", {}", expr))
+        .unwrap_or_default();
+    format!(
+        "(${}, {}, ${}, ${}, to_timestamp(${}){})",
+        param_base,
+        data,
+        param_base + 2,
+        param_base + 3,
+        param_base + 4,
+        key
+    )
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -297,14 +462,37 @@ mod tests {
         // (actual async test requires test database)
     }
 
+    #[test]
+    fn test_row_values_derive_partition_key() {
+        use crate::jsonb::partitioning::{JsonbPartitionConfig, PartitionSpec};
+
+        assert_eq!(
+            super::row_values(None, 6),
+            "($6, $7, $8, $9, to_timestamp($10))"
+        );
+        let spec = PartitionSpec::from_config(&JsonbPartitionConfig {
+            field: Some("tenant_id".to_string()),
+            partitions: 4,
+        })
+        .unwrap();
+        assert_eq!(
+            super::insert_columns(Some(&spec)),
+            "(id, data, _source_type, _row_hash, _source_updated_at, _partition_key)"
+        );
+        assert_eq!(
+            super::row_values(Some(&spec), 1),
+            "($1, $2, $3, $4, to_timestamp($5), coalesce(($2::jsonb) ->> 'tenant_id', ''))"
+        );
+    }
+
     #[test]
     fn test_batch_size_calculation() {
         // Verify our batch size doesn't exceed parameter limits
         // PostgreSQL parameter limit is 65535
-        // With 3 params per row (id, data, source_type) and 1000 rows per batch:
-        // 1000 * 3 = 3000 parameters per batch, which is well under the limit
+        // With 5 params per row (id, data, source_type, row hash, source updated-at)
+        // and 1000 rows per batch: 1000 * 5 = 5000 parameters per batch, well under the limit
         let batch_size = 1000_usize;
-        let params_per_row = 3_usize;
+        let params_per_row = super::PARAMS_PER_ROW;
         let total_params = batch_size * params_per_row;
         assert!(
             total_params < 65535,
@@ -1,21 +1,44 @@
 // ABOUTME: Library module for neon-seren-replicator
 // ABOUTME: Exports all core functionality for use in binary and tests
 
+pub mod audit;
+pub mod cancel;
+pub mod chaos;
 pub mod checkpoint;
 pub mod commands;
 pub mod config;
+pub mod confirm;
+pub mod deadline;
+pub mod errors;
+pub mod event_stream;
+pub mod events;
 pub mod filters;
+pub mod hooks;
 pub mod interactive;
+pub mod inventory;
+pub mod job;
 pub mod jsonb;
+pub mod memory;
 pub mod migration;
 pub mod mongodb;
 pub mod mysql;
+pub mod parallelism;
 pub mod postgres;
+pub mod protected;
 pub mod remote;
 pub mod replication;
+pub mod report;
+pub mod resources;
+pub mod retry;
+pub mod run_lock;
+pub mod settings;
 pub mod sqlite;
 pub mod table_rules;
+pub mod telemetry;
 pub mod utils;
+pub mod warnings;
+
+pub use job::{MigrationJob, MigrationJobBuilder};
 
 use anyhow::{bail, Result};
 
@@ -2,7 +2,9 @@
 // ABOUTME: Parses commands and routes to appropriate handlers
 
 use clap::{Args, Parser, Subcommand};
+use seren_replicator::checkpoint::InitScope;
 use seren_replicator::commands;
+use seren_replicator::settings::RunSettings;
 
 #[derive(Parser)]
 #[command(name = "postgres-seren-replicator")]
@@ -11,6 +13,57 @@ use seren_replicator::commands;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    retry: RetryArgs,
+    #[command(flatten)]
+    pooler: PoolerArgs,
+    /// Cap on parallel jobs for pg_dump/pg_restore and verify workers (default: CPU cores, max 8)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_total_jobs: Option<u32>,
+    /// Memory budget for buffered rows and batches, e.g. 2G; batches and parallelism shrink near it
+    #[arg(long, global = true, value_name = "SIZE")]
+    max_memory: Option<String>,
+    /// How to print a failure: text or json (with its SR-XXXX code and remediation)
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    error_format: seren_replicator::errors::ErrorFormat,
+    /// Local audit file for destructive operations (default: $SEREN_AUDIT_LOG or ~/.seren-replicator/audit.jsonl)
+    #[arg(long, global = true, value_name = "PATH")]
+    audit_log: Option<std::path::PathBuf>,
+    /// Fail the run if it logged any warnings (they are summarized at the end either way)
+    #[arg(long, global = true)]
+    strict: bool,
+    /// Write one JSON event per line (plan, table started/done, progress, warning, error) to this file
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "porcelain")]
+    events_file: Option<std::path::PathBuf>,
+    /// Stream the JSON events to stdout for wrapper UIs; logs move to stderr
+    #[arg(long, global = true)]
+    porcelain: bool,
+    /// Developer failure injection, e.g. kill-subprocess=30,drop-connection=10,corrupt-batch=3 (needs SEREN_CHAOS=1)
+    #[arg(long, global = true, hide = true, value_name = "FAULTS")]
+    chaos: Option<String>,
+}
+
+#[derive(Args, Clone, Default)]
+struct RetryArgs {
+    /// Maximum retry attempts for connections and pg_dump/psql/pg_restore (default: 3)
+    #[arg(long, global = true)]
+    max_retries: Option<u32>,
+    /// Delay in seconds before the first retry; doubles on each attempt (default: 1)
+    #[arg(long, global = true)]
+    retry_base_delay: Option<u64>,
+    /// Upper bound in seconds for any single retry delay (default: 60)
+    #[arg(long, global = true)]
+    retry_max_delay: Option<u64>,
+}
+
+#[derive(Args, Clone, Default)]
+struct PoolerArgs {
+    /// The target URL goes through a connection pooler such as PgBouncer (detected automatically for common setups)
+    #[arg(long, global = true)]
+    target_pooler: bool,
+    /// Port on the target host that bypasses the pooler; pg_restore, COPY, and subscriptions use it
+    #[arg(long, global = true, value_name = "PORT")]
+    target_direct_port: Option<u16>,
 }
 
 #[derive(Args, Clone, Default)]
@@ -24,34 +77,57 @@ struct TableRuleArgs {
     /// Time filters in the form [db.]table:column:window (e.g., db.metrics:created_at:6 months)
     #[arg(long = "time-filter")]
     time_filters: Vec<String>,
+    /// Init-time transforms in the form [db.]table:SELECT ... (repeatable); output columns are matched to the target table by name
+    #[arg(long = "transform")]
+    transforms: Vec<String>,
+    /// Range-partition a table on the target: [db.]table:range(column):daily|weekly|monthly|yearly (repeatable)
+    #[arg(long = "partition-table")]
+    partition_tables: Vec<String>,
+    /// Restore a table at init ahead of lower-priority ones: [db.]table:priority (repeatable; tables without one rank as 0)
+    #[arg(long = "table-priority", value_name = "TABLE:PRIORITY")]
+    table_priorities: Vec<String>,
+    /// Order of tables that share a priority at init: default (pg_restore's), smallest-first, or largest-first
+    #[arg(long = "copy-order", value_name = "ORDER")]
+    copy_order: Option<String>,
     /// Path to replication-config.toml describing advanced table rules
     #[arg(long = "config")]
     config_path: Option<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
+    /// Diagnose the local environment and connectivity to source and target
+    Doctor {
+        #[arg(long)]
+        source: Option<String>,
+        #[arg(long)]
+        target: Option<String>,
+    },
     /// Validate source and target databases are ready for replication
     Validate {
         #[arg(long)]
         source: String,
         #[arg(long)]
         target: String,
-        /// Include only these databases (comma-separated)
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         include_databases: Option<Vec<String>>,
-        /// Exclude these databases (comma-separated)
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         exclude_databases: Option<Vec<String>>,
-        /// Include only these tables (format: database.table, comma-separated)
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         include_tables: Option<Vec<String>>,
-        /// Exclude these tables (format: database.table, comma-separated)
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         exclude_tables: Option<Vec<String>>,
         /// Disable interactive mode (use CLI filter flags instead)
         #[arg(long)]
         no_interactive: bool,
+        /// Validate for a snapshot-only copy (init --no-sync): replication settings are only warned about
+        #[arg(long)]
+        no_sync: bool,
     },
     /// Initialize replication with snapshot copy of schema and data
     Init {
@@ -62,16 +138,16 @@ enum Commands {
         /// Skip confirmation prompt
         #[arg(short = 'y', long)]
         yes: bool,
-        /// Include only these databases (comma-separated)
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         include_databases: Option<Vec<String>>,
-        /// Exclude these databases (comma-separated)
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         exclude_databases: Option<Vec<String>>,
-        /// Include only these tables (format: database.table, comma-separated)
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         include_tables: Option<Vec<String>>,
-        /// Exclude these tables (format: database.table, comma-separated)
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         exclude_tables: Option<Vec<String>>,
         /// Disable interactive mode (use CLI filter flags instead)
@@ -82,21 +158,120 @@ enum Commands {
         /// Drop existing databases on target before copying
         #[arg(long)]
         drop_existing: bool,
-        /// Disable automatic continuous replication setup after snapshot (default: false, meaning sync IS enabled)
+        /// Target databases --drop-existing may drop without a prompt (comma-separated); required for each one unless SEREN_REQUIRE_CONFIRM=0
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "DATABASE",
+            requires = "drop_existing"
+        )]
+        confirm: Vec<String>,
+        /// Snapshot-only mode: skip publication/slot/subscription setup and mark the target copy as snapshot-only
         #[arg(long)]
         no_sync: bool,
         /// Ignore any previous checkpoint and start a fresh run
         #[arg(long)]
         no_resume: bool,
-        /// Execute replication locally instead of using SerenAI's managed service (fallback mode)
+        /// Create databases and restore schema only; copy data later with --data-only
+        #[arg(long, conflicts_with = "data_only")]
+        schema_only: bool,
+        /// Copy data into the schema staged by an earlier --schema-only run
+        #[arg(long, conflicts_with = "drop_existing")]
+        data_only: bool,
+        /// After restoring the schema, copy comments, security labels, and statistics targets it missed
+        #[arg(long, conflicts_with = "data_only")]
+        copy_annotations: bool,
+        /// On a Neon target, create a branch of its current state before --drop-existing drops anything
+        #[arg(long, requires = "drop_existing")]
+        neon_backup_branch: bool,
+        /// Before --drop-existing drops a target database, pg_dump it to this directory or s3://bucket/prefix
+        #[arg(long, value_name = "DEST", requires = "drop_existing")]
+        snapshot_before_drop: Option<String>,
+        /// Parallel pg_restore jobs for the data step (default: the --max-total-jobs budget)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        restore_jobs: Option<u32>,
+        /// Load data with session_replication_role=replica so triggers and FK checks do not fire (needs superuser)
         #[arg(long)]
-        local: bool,
-        /// API endpoint for remote execution (defaults to SerenDB's managed API)
+        no_triggers_during_load: bool,
+        /// maintenance_work_mem for restore sessions, e.g. 1GB; speeds up index builds
+        #[arg(long, value_name = "SIZE")]
+        maintenance_work_mem: Option<String>,
+        /// Apply the bulk-load settings from `tune --for-load` before the run and revert them after (needs superuser)
+        #[arg(long)]
+        tune_for_load: bool,
+        /// Read replicas of the source (comma-separated); the closest one within 64MB of replay lag serves dump and copy traffic
+        #[arg(long, value_delimiter = ',', value_name = "URL")]
+        source_candidates: Vec<String>,
+        /// Write the migration summary as a standalone HTML page to this file
+        #[arg(long, value_name = "PATH")]
+        report_html: Option<std::path::PathBuf>,
+        /// Managed provider of the target (supabase, rds, neon, seren); adapts roles, attributes, and extensions to its restrictions
+        #[arg(long, value_name = "PROFILE")]
+        target_profile: Option<String>,
+        /// Begin at this restore phase (pre-data, data, post-data, privileges, analyze), skipping earlier ones
+        #[arg(long, value_name = "PHASE")]
+        start_at_phase: Option<String>,
+        /// Stop after this restore phase; a later run continues with --start-at-phase
+        #[arg(long, value_name = "PHASE")]
+        stop_after_phase: Option<String>,
+        /// Copy tables at least this large (e.g. 200GB) in primary-key ranges alongside pg_dump
+        #[arg(long, value_name = "SIZE", conflicts_with = "schema_only")]
+        split_tables_larger_than: Option<String>,
+        /// What to do with a SQLite/MySQL row that cannot be converted to JSONB: skip, stub, or fail (default); skipped and stubbed rows are recorded in seren_replicator.row_quarantine
+        #[arg(long, value_name = "POLICY")]
+        on_row_error: Option<String>,
+        /// For MySQL sources, the charset text in a table is really stored in, overriding its declared charset (format: table=charset, comma-separated, e.g. posts=cp1251)
+        #[arg(long, value_name = "TABLE=CHARSET", value_delimiter = ',')]
+        mysql_charset: Vec<String>,
+        /// For MySQL sources, the timezone DATETIME values were written in, converted to UTC (e.g. Europe/Berlin; per column: table.column=ZONE, comma-separated); without it they are stored as naive values
+        #[arg(long, value_name = "ZONE", value_delimiter = ',')]
+        assume_source_tz: Vec<String>,
+        /// For MySQL sources, what to store for zero or impossible dates such as 0000-00-00: null, epoch, string (default; the original text as a bare string, where earlier versions stored a datetime object), or fail (the row then follows --on-row-error)
+        #[arg(long, value_name = "POLICY")]
+        invalid_dates: Option<String>,
+        /// For MongoDB sources, where GridFS file contents go: bytea (chunk rows, default) or large-object
+        #[arg(long, value_name = "STORAGE")]
+        gridfs_storage: Option<String>,
+        /// For SQLite/MySQL sources, how existing tables are updated: full (insert every row, default) or hash (rewrite only rows whose hash changed, delete removed rows)
+        #[arg(long, value_name = "STRATEGY")]
+        refresh: Option<String>,
+        /// For SQLite/MySQL/MongoDB sources, the PostgreSQL schema JSONB tables are written to, created if missing (default: [jsonb_target_schema] in --config, else public)
+        #[arg(long, value_name = "SCHEMA")]
+        target_schema: Option<String>,
+        /// For SQLite/MySQL/MongoDB sources, what to do when a converted table's name is taken on the target by another source or a non-JSONB table: error (default), suffix (write to table_<source>), or merge (share the JSONB table)
+        #[arg(long, value_name = "POLICY")]
+        collision_policy: Option<String>,
+        /// Copy only tables missing or empty on the target, leaving tables that already have rows untouched
+        #[arg(long, conflicts_with_all = ["drop_existing", "schema_only", "data_only", "refresh"])]
+        missing_only: bool,
+        /// Merge source rows into target databases that already have data: update (overwrite rows whose key matches) or skip (keep the target's row); absent tables are created
+        #[arg(long, value_name = "ON_CONFLICT", conflicts_with_all = ["drop_existing", "schema_only", "data_only", "missing_only"])]
+        merge: Option<String>,
+        /// With --merge, the columns that identify a row of a table instead of its primary key (format: [schema.]table=column[,column...]; repeatable); the target needs a unique index on them
+        #[arg(long, value_name = "TABLE=COLUMNS", requires = "merge")]
+        merge_key: Vec<String>,
+        /// With --merge, add this offset to the integer primary keys of a source database, and to the foreign keys referencing them (format: database:offset, e.g. app:1000000; comma-separated)
         #[arg(
             long,
-            default_value_t = std::env::var("SEREN_REMOTE_API")
-                .unwrap_or_else(|_| "https://79cfok1msj.execute-api.us-east-1.amazonaws.com".to_string())
+            value_name = "DB:OFFSET",
+            value_delimiter = ',',
+            requires = "merge"
         )]
+        pk_offset: Vec<String>,
+        /// With --merge, replace the UUID primary keys of these source databases with new UUIDs, rewriting the foreign keys referencing them; mappings are kept in seren_replicator.merge_key_map (comma-separated)
+        #[arg(long, value_name = "DB", value_delimiter = ',', requires = "merge")]
+        pk_regenerate_uuids: Vec<String>,
+        /// Stop starting new databases, phases, and tables before this UTC time (HH:MM for its next occurrence, or YYYY-MM-DDTHH:MM[:SS]Z); a rerun resumes where the run stopped
+        #[arg(long, value_name = "TIME")]
+        deadline: Option<String>,
+        /// Stop starting new work before this much time has passed (e.g. 6h, 90m); with --deadline, the earlier limit wins
+        #[arg(long, value_name = "DURATION")]
+        max_runtime: Option<String>,
+        /// Execute replication locally instead of using SerenAI's managed service (fallback mode)
+        #[arg(long)]
+        local: bool,
+        /// API endpoint for remote execution (defaults to SerenDB's managed API)
+        #[arg(long, default_value_t = default_remote_api())]
         remote_api: String,
         /// Maximum job duration in seconds before timeout (default: 28800 = 8 hours)
         #[arg(long, default_value_t = 28800)]
@@ -108,16 +283,16 @@ enum Commands {
         source: String,
         #[arg(long)]
         target: String,
-        /// Include only these databases (comma-separated)
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         include_databases: Option<Vec<String>>,
-        /// Exclude these databases (comma-separated)
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         exclude_databases: Option<Vec<String>>,
-        /// Include only these tables (format: database.table, comma-separated)
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         include_tables: Option<Vec<String>>,
-        /// Exclude these tables (format: database.table, comma-separated)
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         exclude_tables: Option<Vec<String>>,
         /// Disable interactive mode (use CLI filter flags instead)
@@ -128,6 +303,46 @@ enum Commands {
         /// Force recreate subscriptions even if they already exist
         #[arg(long)]
         force: bool,
+        /// Target databases whose existing subscription --force may drop and recreate (comma-separated); required for each one unless SEREN_REQUIRE_CONFIRM=0
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "DATABASE",
+            requires = "force"
+        )]
+        confirm: Vec<String>,
+        /// Replicate prepared transactions at PREPARE TRANSACTION (two_phase subscriptions, PostgreSQL 15+)
+        #[arg(long)]
+        two_phase: bool,
+        /// Create and replicate a seren_replicator.heartbeat canary table for 'status --heartbeat'
+        #[arg(long)]
+        heartbeat: bool,
+        /// Cancel a subscription's CREATE after this long waiting for its replication slot (e.g. 90s, 10m)
+        #[arg(long, value_name = "DURATION", default_value = "10m")]
+        slot_timeout: String,
+        /// Attempts at creating each subscription's replication slot before giving up
+        #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+        slot_attempts: u32,
+        /// After a timed-out slot attempt, cancel or terminate the source transactions blocking it (emergency migrations)
+        #[arg(long)]
+        cancel_blockers: bool,
+    },
+    /// Run the hooks configured for a stage (e.g. pre-cutover) against the target
+    RunHooks {
+        /// Hook stage: pre-init, post-schema, post-data, post-sync, or pre-cutover
+        #[arg(long)]
+        stage: String,
+        /// Source connection string, exposed to hooks without its password
+        #[arg(long)]
+        source: Option<String>,
+        #[arg(long)]
+        target: String,
+        /// Run once per database (comma-separated) instead of once against --target
+        #[arg(long, value_delimiter = ',')]
+        databases: Option<Vec<String>>,
+        /// Path to replication-config.toml containing the [hooks] section (and [[smoke_queries]] for pre-cutover)
+        #[arg(long = "config")]
+        config_path: String,
     },
     /// Check replication status and lag in real-time
     Status {
@@ -135,12 +350,46 @@ enum Commands {
         source: String,
         #[arg(long)]
         target: String,
-        /// Include only these databases (comma-separated)
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         include_databases: Option<Vec<String>>,
-        /// Exclude these databases (comma-separated)
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         exclude_databases: Option<Vec<String>>,
+        /// Append a lag sample per database to seren_replicator.status_history on the target
+        #[arg(long, conflicts_with = "history")]
+        record_history: bool,
+        /// Show lag trends and stalls from recorded samples over this window (e.g. 30m, 24h, 7d)
+        #[arg(long, value_name = "WINDOW")]
+        history: Option<String>,
+        /// Report whether each database has replayed past this bookmark, and how much since
+        #[arg(long, value_name = "NAME", conflicts_with = "history")]
+        since_bookmark: Option<String>,
+        /// Write a heartbeat on the source and time its arrival on the target (needs sync --heartbeat)
+        #[arg(long, conflicts_with = "history")]
+        heartbeat: bool,
+        /// Path to replication-config.toml; each [lag_thresholds] group is checked against its own limit
+        #[arg(long = "config")]
+        config_path: Option<String>,
+        /// List each subscribed table with its sync state, apply worker, last message time, and the subscription's error counts
+        #[arg(long, conflicts_with = "history")]
+        tables: bool,
+    },
+    /// Record the source's current WAL position under a label (e.g. before-deploy-1402)
+    Bookmark {
+        #[arg(long, required_unless_present = "list")]
+        source: Option<String>,
+        #[arg(long)]
+        target: String,
+        /// Bookmark label
+        #[arg(long, required_unless_present = "list", conflicts_with = "list")]
+        name: Option<String>,
+        /// Move an existing bookmark of the same name to the current position
+        #[arg(long)]
+        force: bool,
+        /// List recorded bookmarks instead of creating one
+        #[arg(long)]
+        list: bool,
     },
     /// Verify data integrity between source and target
     Verify {
@@ -148,29 +397,621 @@ enum Commands {
         source: String,
         #[arg(long)]
         target: String,
-        /// Include only these databases (comma-separated)
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         include_databases: Option<Vec<String>>,
-        /// Exclude these databases (comma-separated)
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tables: Option<Vec<String>>,
+        /// Reapply RLS settings, policies, and grants missing on the target
+        #[arg(long)]
+        restore_privileges: bool,
+        /// Skip tables with no writes since their last clean verification
+        #[arg(long)]
+        incremental: bool,
+        /// Checksum hash: md5, sha256, or hashtext (fast 64-bit, non-cryptographic)
+        #[arg(long, default_value = "md5")]
+        checksum: String,
+        /// Hash only primary key columns plus the timestamp column (fast row-presence check)
+        #[arg(long)]
+        key_columns_only: bool,
+        /// Timestamp column hashed with --key-columns-only when a table has it
+        #[arg(long, default_value = "updated_at", requires = "key_columns_only")]
+        updated_at_column: String,
+        /// For MySQL/MongoDB sources, write divergent IDs and fields to this JSON file
+        #[arg(long, value_name = "PATH")]
+        divergence_report: Option<std::path::PathBuf>,
+        /// For MySQL sources, the charset text in a table is really stored in, overriding its declared charset (format: table=charset, comma-separated, e.g. posts=cp1251)
+        #[arg(long, value_name = "TABLE=CHARSET", value_delimiter = ',')]
+        mysql_charset: Vec<String>,
+        /// For MySQL sources, the timezone DATETIME values were written in, converted to UTC (e.g. Europe/Berlin; per column: table.column=ZONE, comma-separated); without it they are stored as naive values
+        #[arg(long, value_name = "ZONE", value_delimiter = ',')]
+        assume_source_tz: Vec<String>,
+        /// For MySQL sources, what to store for zero or impossible dates such as 0000-00-00: null, epoch, string (default; the original text as a bare string, where earlier versions stored a datetime object), or fail (the row then follows --on-row-error)
+        #[arg(long, value_name = "POLICY")]
+        invalid_dates: Option<String>,
+        /// For SQLite/MySQL/MongoDB sources, the PostgreSQL schema holding the JSONB tables (default: [jsonb_target_schema] in --config, else public)
+        #[arg(long, value_name = "SCHEMA")]
+        target_schema: Option<String>,
+        /// Path to replication-config.toml; its [bson_types] mapping is applied to MongoDB documents before comparing
+        #[arg(long = "config")]
+        config_path: Option<String>,
+        /// Write the verification results as a standalone HTML page to this file
+        #[arg(long, value_name = "PATH")]
+        report_html: Option<std::path::PathBuf>,
+    },
+    /// Keep comparing recently changed rows on source and target while sync runs
+    ValidateStream {
+        #[arg(long)]
+        source: String,
+        #[arg(long)]
+        target: String,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tables: Option<Vec<String>>,
+        /// Timestamp column marking when a row last changed; tables without it are skipped
+        #[arg(long, default_value = "updated_at")]
+        updated_at_column: String,
+        /// Seconds between cycles
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+        /// Leave rows changed within this many seconds for the next cycle, giving replication time to apply them
+        #[arg(long, default_value_t = 30)]
+        grace: u64,
+        /// Most recently changed rows compared per table per cycle
+        #[arg(long, default_value_t = 100)]
+        sample_size: u32,
+        /// Stop after this many cycles and fail if divergent rows remain (default: run until Ctrl-C)
+        #[arg(long)]
+        cycles: Option<u64>,
+    },
+    /// Queue source schema changes for approval before they are applied to the target
+    DdlQueue {
+        /// Source connection string (needed with --watch)
+        #[arg(long, required_if_eq("watch", "true"))]
+        source: Option<String>,
+        /// Target connection string; its database holds the queue
+        #[arg(long)]
+        target: String,
+        /// Compare source and target schemas every --interval seconds and queue the differences
+        #[arg(long, conflicts_with_all = ["approve", "reject", "status"])]
+        watch: bool,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',', requires = "watch")]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',', requires = "watch")]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',', requires = "watch")]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',', requires = "watch")]
+        exclude_tables: Option<Vec<String>>,
+        /// Seconds between schema comparisons
+        #[arg(long, default_value_t = 60, requires = "watch")]
+        interval: u64,
+        /// Apply safe changes (nullable ADD COLUMN without a default, DROP NOT NULL) without approval
+        #[arg(long, requires = "watch")]
+        auto_apply_safe: bool,
+        /// Stop watching after this many cycles (default: run until Ctrl-C)
+        #[arg(long, requires = "watch")]
+        cycles: Option<u64>,
+        /// Apply the queued change with this id to the target
+        #[arg(long, value_name = "ID", conflicts_with_all = ["reject", "status"])]
+        approve: Option<i64>,
+        /// Reject the queued change with this id
+        #[arg(long, value_name = "ID", conflicts_with = "status")]
+        reject: Option<i64>,
+        /// When listing, show only changes with this status: pending, applied, rejected, or failed
+        #[arg(long)]
+        status: Option<String>,
+        /// Path to replication-config.toml; its ddl-pending hooks run when changes are queued
+        #[arg(long = "config")]
+        config_path: Option<String>,
+    },
+    /// Record a known, acceptable difference so verify reports it instead of failing on it
+    AcceptDiff {
+        /// Target connection string, pointing at the database that holds the table
+        #[arg(long)]
+        target: String,
+        /// Table, as schema.table (or table, in the public schema)
+        #[arg(long, required_unless_present_any = ["list", "remove"], conflicts_with_all = ["list", "remove"])]
+        table: Option<String>,
+        /// Columns allowed to differ (comma-separated); without --columns or --where, the whole table
+        #[arg(long, value_delimiter = ',', requires = "table")]
+        columns: Vec<String>,
+        /// Rows allowed to differ, as a SQL predicate (e.g. "created_at < '2020-01-01'")
+        #[arg(long = "where", value_name = "PREDICATE", requires = "table")]
+        predicate: Option<String>,
+        /// Why the difference is acceptable; shown by verify
+        #[arg(long, requires = "table")]
+        reason: Option<String>,
+        /// List the accepted differences in the target database
+        #[arg(long, conflicts_with = "remove")]
+        list: bool,
+        /// Remove the accepted difference with this id
+        #[arg(long, value_name = "ID")]
+        remove: Option<i64>,
+    },
+    /// Re-copy one table, or a slice of it, while continuous replication keeps running
+    Backfill {
+        #[arg(long)]
+        source: String,
+        #[arg(long)]
+        target: String,
+        /// Table to re-copy (format: database.table or database.schema.table)
+        #[arg(long)]
+        table: String,
+        /// Only re-copy rows matching this WHERE clause (evaluated on source and target), e.g. "id BETWEEN 1000 AND 2000"
+        #[arg(long = "where", value_name = "PREDICATE")]
+        predicate: Option<String>,
+        /// Rows per batch; the source table is write-locked while each batch is copied
+        #[arg(long, default_value_t = 1000)]
+        batch_size: usize,
+        /// Subscription replicating the table (needed when the target database has several)
+        #[arg(long)]
+        subscription: Option<String>,
+        /// Seconds each batch waits for the subscription to catch up
+        #[arg(long, default_value_t = 300)]
+        catch_up_timeout: u64,
+    },
+    /// Combine lag, verify, sequence, schema, and DDL checks into a go/no-go cutover report
+    Readiness {
+        #[arg(long)]
+        source: String,
+        #[arg(long)]
+        target: String,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tables: Option<Vec<String>>,
+        /// How much recorded lag history to consider (e.g. 30m, 24h, 7d)
+        #[arg(long, value_name = "WINDOW", default_value = "24h")]
+        history: String,
+        /// Also write the report as JSON to this file
+        #[arg(long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+        /// Path to replication-config.toml; its [[smoke_queries]] are run against the target
+        #[arg(long = "config")]
+        config_path: Option<String>,
+    },
+    /// Recommend, apply, or revert temporary target settings for a bulk load
+    Tune {
+        #[arg(long)]
+        target: String,
+        /// Check max_wal_size, checkpoint_timeout, and autovacuum against bulk-load values
+        #[arg(long, required_unless_present = "revert")]
+        for_load: bool,
+        /// Apply the recommendations with ALTER SYSTEM (needs superuser)
+        #[arg(long, requires = "for_load")]
+        apply: bool,
+        /// Restore the settings saved by an earlier --apply
+        #[arg(long, conflicts_with = "for_load")]
+        revert: bool,
+    },
+    /// Watch the source for new databases and tables matching the filter and replicate them automatically
+    Onboard {
+        #[arg(long)]
+        source: String,
+        #[arg(long)]
+        target: String,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tables: Option<Vec<String>>,
+        #[command(flatten)]
+        table_rules: TableRuleArgs,
+        /// Time between rescans of the source databases (e.g. 30s, 5m, 1h)
+        #[arg(long, default_value = "5m")]
+        interval: String,
+        /// Stop after this many rescans (default: run until Ctrl-C)
+        #[arg(long)]
+        cycles: Option<u64>,
+    },
+    /// Delete JSONB rows whose SQLite row, MySQL row, or MongoDB document was deleted on the source
+    Reconcile {
+        #[arg(long)]
+        source: String,
+        #[arg(long)]
+        target: String,
+        /// Include only these tables (format: database.table, comma-separated; SQLite tables are main.table)
+        #[arg(long, value_delimiter = ',')]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table, comma-separated; SQLite tables are main.table)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tables: Option<Vec<String>>,
+        /// Repeat the pass at this cadence (e.g. 15m, 6h, 1d); runs once when omitted
+        #[arg(long, value_name = "DURATION")]
+        interval: Option<String>,
+        /// Stop after this many passes (default with --interval: run until Ctrl-C)
+        #[arg(long, requires = "interval")]
+        cycles: Option<u64>,
+        /// Target IDs compared and deleted per batch
+        #[arg(long, value_name = "N", default_value_t = 10_000, value_parser = clap::value_parser!(i64).range(1..))]
+        batch_size: i64,
+        /// Report stale rows without deleting them
+        #[arg(long)]
+        dry_run: bool,
+        /// For SQLite/MySQL/MongoDB sources, the PostgreSQL schema holding the JSONB tables (default: [jsonb_target_schema] in --config, else public)
+        #[arg(long, value_name = "SCHEMA")]
+        target_schema: Option<String>,
+        /// Path to replication-config.toml; its [jsonb_target_schema] section sets the default schema per source type
+        #[arg(long = "config")]
+        config_path: Option<String>,
+    },
+    /// Remove replication objects left on the source by failed runs
+    Cleanup {
+        #[arg(long)]
+        source: String,
+        /// Drop sync-created publications that no subscription reads
+        #[arg(long, required = true)]
+        orphans: bool,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Leave publications younger than this alone (e.g. 30m, 2h); a running sync may not have subscribed yet
+        #[arg(long, default_value = "1h")]
+        min_age: String,
+        /// List the orphans without dropping them
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip confirmation prompt
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Tunnel PostgreSQL connections through a zstd-compressed link for slow cross-region networks
+    Relay {
+        /// Address to accept connections on (e.g. 127.0.0.1:6543)
+        #[arg(long)]
+        listen: String,
+        /// Where to forward: the server-side relay (for --side client) or PostgreSQL host:port (for --side server)
+        #[arg(long)]
+        upstream: String,
+        /// Which end of the tunnel this relay is
+        #[arg(long, value_enum)]
+        side: commands::relay::RelaySide,
+        /// zstd compression level, 1 (fastest) to 19 (smallest)
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..=19))]
+        level: i32,
+    },
+    /// Print a structured inventory of a source or target: tables, row estimates, sizes, keys, and unsupported features
+    #[command(group(clap::ArgGroup::new("endpoint").required(true).args(["source", "target"])))]
+    Inspect {
+        /// Source to inspect (PostgreSQL, MySQL, or MongoDB URL, or SQLite file)
+        #[arg(long)]
+        source: Option<String>,
+        /// PostgreSQL target to inspect
+        #[arg(long)]
+        target: Option<String>,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        include_tables: Option<Vec<String>>,
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tables: Option<Vec<String>>,
+        /// Output format: text or json (json is printed to stdout, after any log lines)
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
+        format: commands::inspect::InspectFormat,
+        /// Also write the inventory as JSON to this file
+        #[arg(long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Map the logical replication streams between source and target: publications, slots, subscriptions, and origins
+    Topology {
+        #[arg(long)]
+        source: String,
+        #[arg(long)]
+        target: String,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
         #[arg(long, value_delimiter = ',')]
         exclude_databases: Option<Vec<String>>,
-        /// Include only these tables (format: database.table, comma-separated)
+        /// Output format: text, dot (Graphviz), or mermaid (dot and mermaid are printed to stdout, after any log lines)
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
+        format: commands::topology::TopologyFormat,
+        /// Write the rendered topology to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Export a snapshot of selected source tables to Parquet files for analytics
+    Export {
+        /// PostgreSQL source URL
+        #[arg(long)]
+        source: String,
+        /// Directory to write <database>/<schema>.<table>.parquet files under
+        #[arg(long, value_name = "DIR")]
+        output_dir: std::path::PathBuf,
+        /// File format: parquet
+        #[arg(long, value_name = "FORMAT", default_value = "parquet")]
+        format: commands::export::ExportFormat,
+        /// Include only these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated; tenant_% style patterns allowed)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+        /// Include only these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         include_tables: Option<Vec<String>>,
-        /// Exclude these tables (format: database.table, comma-separated)
+        /// Exclude these tables (format: database.table or database.schema.table, comma-separated)
         #[arg(long, value_delimiter = ',')]
         exclude_tables: Option<Vec<String>>,
+        #[command(flatten)]
+        table_rules: TableRuleArgs,
+    },
+    /// Print each database's tables or collections with row/document counts and average sizes
+    List {
+        /// PostgreSQL, MySQL, or MongoDB URL, or SQLite file; MySQL/MongoDB URLs without a database list every database
+        #[arg(long)]
+        source: String,
+        /// Include only these databases (comma-separated; SQLite files are database main)
+        #[arg(long, value_delimiter = ',')]
+        include_databases: Option<Vec<String>>,
+        /// Exclude these databases (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude_databases: Option<Vec<String>>,
+    },
+    /// Show destructive operations (DROP DATABASE, TRUNCATE, DROP SUBSCRIPTION, ...) recorded by earlier runs
+    Audit {
+        #[arg(long, required_unless_present = "local")]
+        target: Option<String>,
+        /// Read the local audit file instead of the target's audit table
+        #[arg(long, conflicts_with = "target")]
+        local: bool,
+        /// Only show this action (e.g. drop_database, truncate, drop_subscription)
+        #[arg(long)]
+        action: Option<String>,
+        /// Show at most this many entries
+        #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+        limit: u32,
+    },
+    /// List and inspect jobs run by the remote execution service
+    Remote {
+        #[command(subcommand)]
+        command: RemoteCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum RemoteCommands {
+    /// Show submitted jobs, newest first
+    List {
+        /// Show at most this many jobs
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Only show jobs with this status (provisioning, running, completed, failed)
+        #[arg(long)]
+        status: Option<String>,
+        /// API endpoint for remote execution (defaults to SerenDB's managed API)
+        #[arg(long, default_value_t = default_remote_api())]
+        remote_api: String,
     },
+    /// Show a job's status, filter spec, options, and final report
+    Describe {
+        job_id: String,
+        /// API endpoint for remote execution (defaults to SerenDB's managed API)
+        #[arg(long, default_value_t = default_remote_api())]
+        remote_api: String,
+    },
+}
+
+fn default_remote_api() -> String {
+    std::env::var("SEREN_REMOTE_API")
+        .unwrap_or_else(|_| "https://79cfok1msj.execute-api.us-east-1.amazonaws.com".to_string())
+}
+
+impl Commands {
+    /// Path passed via --config, for commands that accept one
+    fn config_path(&self) -> Option<&str> {
+        match self {
+            Commands::Init { table_rules, .. }
+            | Commands::Sync { table_rules, .. }
+            | Commands::Onboard { table_rules, .. }
+            | Commands::Export { table_rules, .. } => table_rules.config_path.as_deref(),
+            Commands::RunHooks { config_path, .. } => Some(config_path.as_str()),
+            Commands::Status { config_path, .. }
+            | Commands::Verify { config_path, .. }
+            | Commands::Reconcile { config_path, .. }
+            | Commands::Readiness { config_path, .. }
+            | Commands::DdlQueue { config_path, .. } => config_path.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// File for the `--report-html` page, if requested
+    fn report_html(&self) -> Option<&std::path::Path> {
+        match self {
+            Commands::Init { report_html, .. } | Commands::Verify { report_html, .. } => {
+                report_html.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Subcommand name, as reported by telemetry
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Doctor { .. } => "doctor",
+            Commands::Validate { .. } => "validate",
+            Commands::Init { .. } => "init",
+            Commands::Sync { .. } => "sync",
+            Commands::RunHooks { .. } => "run-hooks",
+            Commands::Status { .. } => "status",
+            Commands::Bookmark { .. } => "bookmark",
+            Commands::Verify { .. } => "verify",
+            Commands::ValidateStream { .. } => "validate-stream",
+            Commands::DdlQueue { .. } => "ddl-queue",
+            Commands::AcceptDiff { .. } => "accept-diff",
+            Commands::Backfill { .. } => "backfill",
+            Commands::Readiness { .. } => "readiness",
+            Commands::Tune { .. } => "tune",
+            Commands::Onboard { .. } => "onboard",
+            Commands::Reconcile { .. } => "reconcile",
+            Commands::Cleanup { .. } => "cleanup",
+            Commands::Relay { .. } => "relay",
+            Commands::Inspect { .. } => "inspect",
+            Commands::Topology { .. } => "topology",
+            Commands::Export { .. } => "export",
+            Commands::List { .. } => "list",
+            Commands::Audit { .. } => "audit",
+            Commands::Remote { .. } => "remote",
+        }
+    }
+
+    /// Source connection string, if the command takes one
+    fn source(&self) -> Option<&str> {
+        match self {
+            Commands::Doctor { source, .. }
+            | Commands::Inspect { source, .. }
+            | Commands::RunHooks { source, .. }
+            | Commands::Bookmark { source, .. }
+            | Commands::DdlQueue { source, .. } => source.as_deref(),
+            Commands::Validate { source, .. }
+            | Commands::Init { source, .. }
+            | Commands::Sync { source, .. }
+            | Commands::Status { source, .. }
+            | Commands::Verify { source, .. }
+            | Commands::ValidateStream { source, .. }
+            | Commands::Backfill { source, .. }
+            | Commands::Readiness { source, .. }
+            | Commands::Onboard { source, .. }
+            | Commands::Reconcile { source, .. }
+            | Commands::List { source, .. }
+            | Commands::Topology { source, .. }
+            | Commands::Export { source, .. }
+            | Commands::Cleanup { source, .. } => Some(source.as_str()),
+            Commands::AcceptDiff { .. }
+            | Commands::Tune { .. }
+            | Commands::Relay { .. }
+            | Commands::Audit { .. }
+            | Commands::Remote { .. } => None,
+        }
+    }
+
+    /// Target connection string, if the command takes one
+    fn target(&self) -> Option<&str> {
+        match self {
+            Commands::Doctor { target, .. }
+            | Commands::Inspect { target, .. }
+            | Commands::Audit { target, .. } => target.as_deref(),
+            Commands::Validate { target, .. }
+            | Commands::Init { target, .. }
+            | Commands::Sync { target, .. }
+            | Commands::RunHooks { target, .. }
+            | Commands::Status { target, .. }
+            | Commands::Bookmark { target, .. }
+            | Commands::Verify { target, .. }
+            | Commands::ValidateStream { target, .. }
+            | Commands::DdlQueue { target, .. }
+            | Commands::AcceptDiff { target, .. }
+            | Commands::Backfill { target, .. }
+            | Commands::Readiness { target, .. }
+            | Commands::Tune { target, .. }
+            | Commands::Onboard { target, .. }
+            | Commands::Topology { target, .. }
+            | Commands::Reconcile { target, .. } => Some(target.as_str()),
+            Commands::Cleanup { .. }
+            | Commands::Relay { .. }
+            | Commands::List { .. }
+            | Commands::Export { .. }
+            | Commands::Remote { .. } => None,
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize logging - default to INFO level if RUST_LOG not set
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+async fn main() {
+    let cli = Cli::parse();
+    let event_stream = if cli.porcelain {
+        Some(seren_replicator::event_stream::EventStream::stdout())
+    } else {
+        match cli
+            .events_file
+            .as_deref()
+            .map(seren_replicator::event_stream::EventStream::create)
+        {
+            Some(Ok(stream)) => Some(stream),
+            Some(Err(error)) => {
+                seren_replicator::errors::report(&error, cli.error_format);
+                std::process::exit(1);
+            }
+            None => None,
+        }
+    };
+
+    // Initialize logging - default to INFO level if RUST_LOG not set. Warnings
+    // reach the end-of-run summary whatever the filter lets through.
+    // `--porcelain` keeps stdout for the event stream.
+    use tracing_subscriber::fmt::writer::BoxMakeWriter;
+    use tracing_subscriber::layer::SubscriberExt as _;
+    use tracing_subscriber::util::SubscriberInitExt as _;
+    use tracing_subscriber::Layer as _;
+    let log_writer = if cli.porcelain {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(log_writer)
+                .with_filter(
+                    tracing_subscriber::EnvFilter::try_from_default_env()
+                        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+                ),
         )
+        .with(
+            seren_replicator::warnings::WarningLayer
+                .with_filter(tracing_subscriber::filter::LevelFilter::WARN),
+        )
+        .with(event_stream.as_ref().map(|stream| {
+            stream
+                .layer()
+                .with_filter(tracing_subscriber::filter::LevelFilter::WARN)
+        }))
         .init();
 
     // Clean up stale temp directories from previous runs (older than 24 hours)
@@ -180,9 +1021,96 @@ async fn main() -> anyhow::Result<()> {
         // Don't fail startup if cleanup fails
     }
 
-    let cli = Cli::parse();
+    let error_format = cli.error_format;
+    let strict = cli.strict;
+    let telemetry_endpoint = match telemetry_endpoint(cli.command.config_path()) {
+        Ok(endpoint) => endpoint,
+        Err(error) => {
+            seren_replicator::errors::report(&error, error_format);
+            std::process::exit(1);
+        }
+    };
+    let command = cli.command.name();
+    let source = cli.command.source().map(str::to_string);
+    let started = std::time::Instant::now();
+
+    // The CLI's progress bars are an observer like any library user's
+    let progress_bars = seren_replicator::events::register(std::sync::Arc::new(
+        seren_replicator::events::ProgressBars::default(),
+    ));
+    let html_report = cli.command.report_html().map(|path| {
+        let report = std::sync::Arc::new(seren_replicator::report::HtmlReport::new(command));
+        let registration = seren_replicator::events::register(report.clone());
+        (path.to_path_buf(), report, registration)
+    });
+    let stream_registration = event_stream.as_ref().map(|stream| {
+        stream.start(command);
+        seren_replicator::events::register(std::sync::Arc::new(stream.clone()))
+    });
+    let result = run(cli).await;
+    drop(progress_bars);
+    let warnings = seren_replicator::warnings::collected();
+    if !warnings.is_empty() || strict {
+        seren_replicator::warnings::log_summary(&warnings);
+    }
+    let result = result.and_then(|()| seren_replicator::warnings::check_strict(&warnings, strict));
+    if let Some((path, report, registration)) = html_report {
+        drop(registration);
+        report.add_warnings(&warnings.messages());
+        if let Err(e) = report.write(&path, &result) {
+            tracing::warn!("⚠ {:#}", e);
+        }
+    }
+    if let Some(stream) = event_stream {
+        drop(stream_registration);
+        stream.finish(&result);
+    }
+
+    if let Some(endpoint) = telemetry_endpoint {
+        let event = seren_replicator::telemetry::TelemetryEvent::new(
+            command,
+            source.as_deref(),
+            started.elapsed(),
+            &result,
+        );
+        seren_replicator::telemetry::send(&endpoint, &event).await;
+    }
+    if let Err(error) = result {
+        seren_replicator::errors::report(&error, error_format);
+        std::process::exit(1);
+    }
+}
+
+/// Endpoint for opt-in telemetry, from `[telemetry]` and the `SEREN_TELEMETRY*` variables
+fn telemetry_endpoint(config_path: Option<&str>) -> anyhow::Result<Option<String>> {
+    use seren_replicator::telemetry::{TelemetryConfig, ENABLE_ENV, ENDPOINT_ENV};
+
+    let file = match config_path {
+        Some(path) => seren_replicator::config::load_telemetry_config_from_file(path)?,
+        None => None,
+    };
+    TelemetryConfig::resolve(
+        file,
+        std::env::var(ENABLE_ENV).ok().as_deref(),
+        std::env::var(ENDPOINT_ENV).ok().as_deref(),
+    )
+}
+
+/// Build the run's settings and dispatch the subcommand
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    seren_replicator::protected::set_protected_targets(
+        seren_replicator::protected::ProtectedTargets::load(
+            &seren_replicator::protected::protected_path(),
+        )?,
+    );
+    let mut settings = run_settings(&cli)?;
+    let jsonb = jsonb_options(&cli.command)?;
 
     match cli.command {
+        Commands::Doctor { source, target } => {
+            commands::doctor(source.as_deref(), target.as_deref()).await?;
+            Ok(())
+        }
         Commands::Validate {
             source,
             target,
@@ -191,11 +1119,13 @@ async fn main() -> anyhow::Result<()> {
             include_tables,
             exclude_tables,
             no_interactive,
+            no_sync,
         } => {
             let filter = if !no_interactive {
                 // Interactive mode (default) - prompt user to select databases and tables
                 let (filter, rules) =
-                    seren_replicator::interactive::select_databases_and_tables(&source).await?;
+                    seren_replicator::interactive::select_databases_and_tables(&source, &settings)
+                        .await?;
                 filter.with_table_rules(rules)
             } else {
                 // CLI mode - use provided filter arguments
@@ -206,108 +1136,837 @@ async fn main() -> anyhow::Result<()> {
                     exclude_tables,
                 )?
             };
-            commands::validate(&source, &target, filter).await
+            commands::validate(&source, &target, filter, !no_sync, &settings).await
+        }
+        Commands::Init {
+            source,
+            target,
+            yes,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            no_interactive,
+            table_rules,
+            drop_existing,
+            confirm,
+            no_sync,
+            no_resume,
+            schema_only,
+            data_only,
+            copy_annotations,
+            neon_backup_branch,
+            snapshot_before_drop,
+            restore_jobs,
+            no_triggers_during_load,
+            maintenance_work_mem,
+            tune_for_load,
+            source_candidates,
+            target_profile,
+            start_at_phase,
+            stop_after_phase,
+            split_tables_larger_than,
+            on_row_error,
+            mysql_charset,
+            assume_source_tz,
+            invalid_dates,
+            gridfs_storage,
+            refresh,
+            target_schema,
+            collision_policy,
+            missing_only,
+            merge,
+            merge_key,
+            pk_offset,
+            pk_regenerate_uuids,
+            deadline,
+            max_runtime,
+            local,
+            remote_api,
+            job_timeout,
+            report_html: _,
+        } => {
+            let mut options = commands::init::InitOptions {
+                jsonb,
+                confirm: seren_replicator::confirm::ConfirmSettings::from_env(confirm),
+                ..config_init_options(table_rules.config_path.as_deref())?
+            };
+            if (schema_only || data_only) && !local {
+                anyhow::bail!(
+                    "--schema-only and --data-only require --local.\n\
+                     The staged schema is tracked on this machine between the two steps."
+                );
+            }
+            if copy_annotations && !local {
+                anyhow::bail!(
+                    "--copy-annotations requires --local.\n\
+                     The managed service does not run the annotation copy step."
+                );
+            }
+            if neon_backup_branch {
+                if !local {
+                    anyhow::bail!(
+                        "--neon-backup-branch requires --local.\n\
+                         The managed service does not create Neon branches."
+                    );
+                }
+                settings.neon.backup_branch_before_drop = true;
+            }
+            if let Some(destination) = snapshot_before_drop {
+                if !local {
+                    anyhow::bail!(
+                        "--snapshot-before-drop requires --local.\n\
+                         The managed service does not keep dumps of dropped databases."
+                    );
+                }
+                options.snapshot_before_drop = Some(destination.parse()?);
+            }
+            if restore_jobs.is_some() || no_triggers_during_load || maintenance_work_mem.is_some() {
+                if !local {
+                    anyhow::bail!(
+                        "--restore-jobs, --no-triggers-during-load, and --maintenance-work-mem require --local.\n\
+                         The managed service tunes its own restores."
+                    );
+                }
+                options.restore = seren_replicator::migration::restore::RestoreTuning {
+                    jobs: restore_jobs.map(|jobs| jobs as usize),
+                    disable_triggers: no_triggers_during_load,
+                    maintenance_work_mem: maintenance_work_mem
+                        .as_deref()
+                        .map(seren_replicator::parallelism::parse_size)
+                        .transpose()?,
+                };
+            }
+            if !source_candidates.is_empty() {
+                if !local {
+                    anyhow::bail!(
+                        "--source-candidates requires --local.\n\
+                         The managed service copies from --source."
+                    );
+                }
+                options.source_candidates = source_candidates;
+            }
+            if tune_for_load && !local {
+                anyhow::bail!(
+                    "--tune-for-load requires --local.\n\
+                     The managed service does not change target settings."
+                );
+            }
+            if let Some(profile) = target_profile {
+                if !local {
+                    anyhow::bail!(
+                        "--target-profile requires --local.\n\
+                         The managed service applies its own target rules."
+                    );
+                }
+                options.target_profile = Some(profile.parse()?);
+            }
+            if start_at_phase.is_some() || stop_after_phase.is_some() {
+                if !local {
+                    anyhow::bail!(
+                        "--start-at-phase and --stop-after-phase require --local.\n\
+                         Phase progress is tracked in the checkpoint on this machine."
+                    );
+                }
+                options.phases = seren_replicator::migration::phases::PhaseRange::new(
+                    start_at_phase.as_deref().map(str::parse).transpose()?,
+                    stop_after_phase.as_deref().map(str::parse).transpose()?,
+                )?;
+            }
+            if on_row_error.is_some() && !local {
+                anyhow::bail!(
+                    "--on-row-error requires --local.\n\
+                     The managed service fails on rows it cannot convert."
+                );
+            }
+            if !mysql_charset.is_empty() && !local {
+                anyhow::bail!(
+                    "--mysql-charset requires --local.\n\
+                     The managed service converts text using the declared charsets."
+                );
+            }
+            if !assume_source_tz.is_empty() && !local {
+                anyhow::bail!(
+                    "--assume-source-tz requires --local.\n\
+                     The managed service stores MySQL DATETIME values as written."
+                );
+            }
+            if invalid_dates.is_some() && !local {
+                anyhow::bail!(
+                    "--invalid-dates requires --local.\n\
+                     The managed service stores zero and invalid MySQL dates as their original text."
+                );
+            }
+            if gridfs_storage.is_some() && !local {
+                anyhow::bail!(
+                    "--gridfs-storage requires --local.\n\
+                     The managed service stores GridFS files as bytea chunks."
+                );
+            }
+            if refresh.is_some() && !local {
+                anyhow::bail!(
+                    "--refresh requires --local.\n\
+                     The managed service copies SQLite and MySQL tables in full."
+                );
+            }
+            if target_schema.is_some() && !local {
+                anyhow::bail!(
+                    "--target-schema requires --local.\n\
+                     The managed service writes JSONB tables to the public schema."
+                );
+            }
+            if collision_policy.is_some() && !local {
+                anyhow::bail!(
+                    "--collision-policy requires --local.\n\
+                     The managed service writes converted tables under their source names."
+                );
+            }
+            if missing_only {
+                if !local {
+                    anyhow::bail!(
+                        "--missing-only requires --local.\n\
+                         The managed service copies every table in scope."
+                    );
+                }
+                options.missing_only = true;
+            }
+            if deadline.is_some() || max_runtime.is_some() {
+                if !local {
+                    anyhow::bail!(
+                        "--deadline and --max-runtime require --local.\n\
+                         The managed service runs the job to completion; use --job-timeout to bound the wait."
+                    );
+                }
+                let now = seren_replicator::deadline::now();
+                options.deadline = seren_replicator::deadline::Deadline::new(
+                    now,
+                    deadline
+                        .as_deref()
+                        .map(|value| seren_replicator::deadline::parse_deadline(value, now))
+                        .transpose()?,
+                    max_runtime
+                        .as_deref()
+                        .map(|value| {
+                            seren_replicator::replication::history::parse_window(value).map_err(
+                                |e| e.context(format!("Invalid --max-runtime '{}'", value)),
+                            )
+                        })
+                        .transpose()?,
+                );
+            }
+            if let Some(conflict) = merge {
+                if !local {
+                    anyhow::bail!(
+                        "--merge requires --local.\n\
+                         The managed service copies into empty target databases."
+                    );
+                }
+                options.merge = Some(seren_replicator::migration::merge::MergeSettings {
+                    conflict: conflict.parse()?,
+                    keys: merge_key
+                        .iter()
+                        .map(|key| key.parse())
+                        .collect::<anyhow::Result<_>>()?,
+                    pk_offsets: pk_offset
+                        .iter()
+                        .map(|offset| offset.parse())
+                        .collect::<anyhow::Result<_>>()?,
+                    regenerate_uuids: pk_regenerate_uuids,
+                });
+            }
+            if let Some(size) = split_tables_larger_than {
+                if !local {
+                    anyhow::bail!(
+                        "--split-tables-larger-than requires --local.\n\
+                         The managed service does not split tables."
+                    );
+                }
+                options.split_threshold = Some(seren_replicator::parallelism::parse_size(&size)?);
+            }
+
+            // Remote execution path (default)
+            if !local {
+                return init_remote(
+                    source,
+                    target,
+                    yes,
+                    include_databases,
+                    exclude_databases,
+                    include_tables,
+                    exclude_tables,
+                    build_table_rules(&table_rules)?,
+                    table_rules.config_path.as_deref(),
+                    drop_existing,
+                    no_sync,
+                    remote_api,
+                    job_timeout,
+                    &options.confirm,
+                    &settings,
+                )
+                .await;
+            }
+
+            // Local execution path (existing code continues below)
+            // Interactive mode is default unless --no-interactive or --yes is specified
+            // (--yes implies automation, so it disables interactive mode)
+            let filter = if !no_interactive && !yes {
+                // Interactive mode (default) - prompt user to select databases and tables
+                let (filter, rules) =
+                    seren_replicator::interactive::select_databases_and_tables(&source, &settings)
+                        .await?;
+                filter.with_table_rules(rules)
+            } else {
+                // CLI mode - use provided filter arguments
+                let filter = seren_replicator::filters::ReplicationFilter::new(
+                    include_databases,
+                    exclude_databases,
+                    include_tables,
+                    exclude_tables,
+                )?;
+                let table_rule_data = build_table_rules(&table_rules)?;
+                filter.with_table_rules(table_rule_data)
+            };
+            let enable_sync = !no_sync; // Invert the flag: by default sync is enabled
+            let scope = if schema_only {
+                InitScope::SchemaOnly
+            } else if data_only {
+                InitScope::DataOnly
+            } else {
+                InitScope::Full
+            };
+            // Held across --tune-for-load too; passed to init so it does not lock itself out
+            let run_lock = seren_replicator::run_lock::acquire(&target, "init", &settings).await?;
+            if tune_for_load {
+                // Refuse before ALTER SYSTEM, not only once init starts
+                seren_replicator::protected::check(&target, &[], "init")?;
+                commands::tune::tune_for_load(&target, true, &settings.pooler).await?;
+            }
+            let result = commands::init(
+                &source,
+                &target,
+                yes,
+                filter,
+                drop_existing,
+                enable_sync,
+                !no_resume,
+                scope,
+                copy_annotations,
+                &options,
+                Some(&run_lock),
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await;
+            if tune_for_load {
+                // Revert even when init failed, so the target is not left tuned for loading
+                if let Err(e) = commands::tune::revert_load_tuning(&target, &settings.pooler).await
+                {
+                    tracing::warn!("⚠ Failed to revert load tuning: {:#}", e);
+                    tracing::warn!(
+                        "  Revert it with: seren-replicator tune --revert --target <url>"
+                    );
+                }
+            }
+            result
+        }
+        Commands::Sync {
+            source,
+            target,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            no_interactive,
+            table_rules,
+            force,
+            confirm,
+            two_phase,
+            heartbeat,
+            slot_timeout,
+            slot_attempts,
+            cancel_blockers,
+        } => {
+            let options = commands::sync::SyncOptions {
+                slot_creation: seren_replicator::replication::slot_creation::SlotCreationOptions {
+                    timeout: seren_replicator::replication::history::parse_window(&slot_timeout)?,
+                    attempts: slot_attempts,
+                    cancel_blockers,
+                    ..Default::default()
+                },
+                confirm: seren_replicator::confirm::ConfirmSettings::from_env(confirm),
+                ..config_sync_options(table_rules.config_path.as_deref())?
+            };
+            let filter = if !no_interactive {
+                // Interactive mode (default) - prompt user to select databases and tables
+                let (filter, rules) =
+                    seren_replicator::interactive::select_databases_and_tables(&source, &settings)
+                        .await?;
+                filter.with_table_rules(rules)
+            } else {
+                // CLI mode - use provided filter arguments
+                let filter = seren_replicator::filters::ReplicationFilter::new(
+                    include_databases,
+                    exclude_databases,
+                    include_tables,
+                    exclude_tables,
+                )?;
+                let table_rule_data = build_table_rules(&table_rules)?;
+                filter.with_table_rules(table_rule_data)
+            };
+            commands::sync(
+                &source,
+                &target,
+                Some(filter),
+                None,
+                None,
+                None,
+                force,
+                two_phase,
+                heartbeat,
+                &options,
+                None,
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await
+        }
+        Commands::RunHooks {
+            stage,
+            source,
+            target,
+            databases,
+            config_path,
+        } => {
+            let stage: seren_replicator::hooks::HookStage = stage.parse()?;
+            commands::run_hooks(
+                stage,
+                source.as_deref().unwrap_or(""),
+                &target,
+                &databases.unwrap_or_default(),
+                &config_smoke_queries(Some(&config_path))?,
+                &settings,
+            )
+            .await
+        }
+        Commands::Status {
+            source,
+            target,
+            include_databases,
+            exclude_databases,
+            record_history,
+            history,
+            since_bookmark,
+            heartbeat,
+            config_path,
+            tables,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                None,
+                None,
+            )?;
+            let history_window = history
+                .as_deref()
+                .map(seren_replicator::replication::history::parse_window)
+                .transpose()?;
+            commands::status(
+                &source,
+                &target,
+                Some(filter),
+                record_history,
+                history_window,
+                since_bookmark.as_deref(),
+                heartbeat,
+                tables,
+                &config_lag_thresholds(config_path.as_deref())?,
+                &settings,
+            )
+            .await
+        }
+        Commands::Bookmark {
+            source,
+            target,
+            name,
+            force,
+            list,
+        } => {
+            if list {
+                return commands::bookmark::list_bookmarks(&target, &settings).await;
+            }
+            // clap requires both unless --list is given
+            let (Some(source), Some(name)) = (source, name) else {
+                anyhow::bail!("bookmark requires --source and --name (or --list)");
+            };
+            commands::bookmark(&source, &target, &name, force, &settings).await?;
+            Ok(())
+        }
+        Commands::Verify {
+            source,
+            target,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            restore_privileges,
+            incremental,
+            checksum,
+            key_columns_only,
+            updated_at_column,
+            divergence_report,
+            mysql_charset: _,
+            assume_source_tz: _,
+            invalid_dates: _,
+            target_schema: _,
+            config_path: _,
+            report_html: _,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                include_tables,
+                exclude_tables,
+            )?;
+            commands::verify(
+                &source,
+                &target,
+                Some(filter),
+                restore_privileges,
+                incremental,
+                &seren_replicator::migration::ChecksumOptions {
+                    algorithm: checksum.parse()?,
+                    key_columns_only,
+                    updated_at_column,
+                },
+                divergence_report.as_deref(),
+                &jsonb,
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await
+        }
+        Commands::ValidateStream {
+            source,
+            target,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            updated_at_column,
+            interval,
+            grace,
+            sample_size,
+            cycles,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                include_tables,
+                exclude_tables,
+            )?;
+            commands::validate_stream(
+                &source,
+                &target,
+                filter,
+                &commands::validate_stream::StreamOptions {
+                    updated_at_column,
+                    interval: std::time::Duration::from_secs(interval.max(1)),
+                    grace: std::time::Duration::from_secs(grace),
+                    sample_size: i64::from(sample_size.max(1)),
+                    cycles,
+                },
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await
+        }
+        Commands::DdlQueue {
+            source,
+            target,
+            watch,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            interval,
+            auto_apply_safe,
+            cycles,
+            approve,
+            reject,
+            status,
+            config_path: _,
+        } => {
+            if let Some(id) = approve {
+                return commands::ddl_queue::approve_ddl(&target, id, &settings).await;
+            }
+            if let Some(id) = reject {
+                return commands::ddl_queue::reject_ddl(&target, id, &settings).await;
+            }
+            if !watch {
+                let status = status.as_deref().map(str::parse).transpose()?;
+                return commands::ddl_queue::list_ddl_queue(&target, status, &settings).await;
+            }
+            // clap requires --source with --watch
+            let Some(source) = source else {
+                anyhow::bail!("ddl-queue --watch requires --source");
+            };
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                include_tables,
+                exclude_tables,
+            )?;
+            commands::ddl_queue::watch_ddl(
+                &source,
+                &target,
+                filter,
+                &commands::ddl_queue::WatchOptions {
+                    interval: std::time::Duration::from_secs(interval.max(1)),
+                    auto_apply_safe,
+                    cycles,
+                },
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await
+        }
+        Commands::AcceptDiff {
+            target,
+            table,
+            columns,
+            predicate,
+            reason,
+            list,
+            remove,
+        } => {
+            if list {
+                return commands::accept_diff::list_accepted_diffs(&target, &settings).await;
+            }
+            if let Some(id) = remove {
+                return commands::accept_diff::remove_accepted_diff(&target, id, &settings).await;
+            }
+            // clap requires --table unless --list or --remove is given
+            let Some(table) = table else {
+                anyhow::bail!("accept-diff requires --table (or --list or --remove)");
+            };
+            commands::accept_diff(
+                &target,
+                &table,
+                &columns,
+                predicate.as_deref(),
+                reason.as_deref(),
+                &settings,
+            )
+            .await?;
+            Ok(())
+        }
+        Commands::Backfill {
+            source,
+            target,
+            table,
+            predicate,
+            batch_size,
+            subscription,
+            catch_up_timeout,
+        } => {
+            let table = commands::backfill::BackfillTable::parse(&table)?;
+            commands::backfill(
+                &source,
+                &target,
+                &table,
+                predicate.as_deref(),
+                batch_size,
+                subscription.as_deref(),
+                catch_up_timeout,
+                None,
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await?;
+            Ok(())
+        }
+        Commands::Readiness {
+            source,
+            target,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            history,
+            output,
+            config_path,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                include_tables,
+                exclude_tables,
+            )?;
+            let window = seren_replicator::replication::history::parse_window(&history)?;
+            commands::readiness(
+                &source,
+                &target,
+                Some(filter),
+                window,
+                output.as_deref(),
+                &config_smoke_queries(config_path.as_deref())?,
+                &settings,
+            )
+            .await
+        }
+        Commands::Tune {
+            target,
+            for_load: _,
+            apply,
+            revert,
+        } => {
+            if revert {
+                return commands::tune::revert_load_tuning(&target, &settings.pooler).await;
+            }
+            commands::tune::tune_for_load(&target, apply, &settings.pooler).await?;
+            Ok(())
+        }
+        Commands::Onboard {
+            source,
+            target,
+            include_databases,
+            exclude_databases,
+            include_tables,
+            exclude_tables,
+            table_rules,
+            interval,
+            cycles,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                include_tables,
+                exclude_tables,
+            )?
+            .with_table_rules(build_table_rules(&table_rules)?);
+            commands::onboard(
+                &source,
+                &target,
+                filter,
+                &commands::onboard::OnboardOptions {
+                    interval: seren_replicator::replication::history::parse_window(&interval)?,
+                    cycles,
+                    tables: match table_rules.config_path.as_deref() {
+                        Some(path) => {
+                            seren_replicator::config::load_onboard_config_from_file(path)?
+                                .unwrap_or_default()
+                        }
+                        None => Default::default(),
+                    },
+                    init: config_init_options(table_rules.config_path.as_deref())?,
+                },
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await
+        }
+        Commands::Cleanup {
+            source,
+            orphans: _,
+            include_databases,
+            exclude_databases,
+            min_age,
+            dry_run,
+            yes,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                None,
+                None,
+            )?;
+            let min_age = seren_replicator::replication::history::parse_window(&min_age)?;
+            commands::cleanup_orphans(&source, Some(filter), min_age, dry_run, yes, &settings)
+                .await?;
+            Ok(())
         }
-        Commands::Init {
+        Commands::Reconcile {
             source,
             target,
-            yes,
-            include_databases,
-            exclude_databases,
             include_tables,
             exclude_tables,
-            no_interactive,
-            table_rules,
-            drop_existing,
-            no_sync,
-            no_resume,
-            local,
-            remote_api,
-            job_timeout,
+            interval,
+            cycles,
+            batch_size,
+            dry_run,
+            target_schema: _,
+            config_path: _,
         } => {
-            // Remote execution path (default)
-            if !local {
-                return init_remote(
-                    source,
-                    target,
-                    yes,
-                    include_databases,
-                    exclude_databases,
-                    include_tables,
-                    exclude_tables,
-                    drop_existing,
-                    no_sync,
-                    remote_api,
-                    job_timeout,
-                )
-                .await;
-            }
-
-            // Local execution path (existing code continues below)
-            // Interactive mode is default unless --no-interactive or --yes is specified
-            // (--yes implies automation, so it disables interactive mode)
-            let filter = if !no_interactive && !yes {
-                // Interactive mode (default) - prompt user to select databases and tables
-                let (filter, rules) =
-                    seren_replicator::interactive::select_databases_and_tables(&source).await?;
-                filter.with_table_rules(rules)
-            } else {
-                // CLI mode - use provided filter arguments
-                let filter = seren_replicator::filters::ReplicationFilter::new(
-                    include_databases,
-                    exclude_databases,
-                    include_tables,
-                    exclude_tables,
-                )?;
-                let table_rule_data = build_table_rules(&table_rules)?;
-                filter.with_table_rules(table_rule_data)
-            };
-            let enable_sync = !no_sync; // Invert the flag: by default sync is enabled
-            commands::init(
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                None,
+                None,
+                include_tables,
+                exclude_tables,
+            )?;
+            commands::reconcile(
                 &source,
                 &target,
-                yes,
-                filter,
-                drop_existing,
-                enable_sync,
-                !no_resume,
+                &filter,
+                &commands::reconcile::ReconcileOptions {
+                    interval: interval
+                        .as_deref()
+                        .map(seren_replicator::replication::history::parse_window)
+                        .transpose()?,
+                    cycles,
+                    batch_size,
+                    dry_run,
+                    jsonb,
+                },
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
             )
             .await
         }
-        Commands::Sync {
+        Commands::Relay {
+            listen,
+            upstream,
+            side,
+            level,
+        } => {
+            commands::relay(
+                &listen,
+                &upstream,
+                side,
+                level,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await?;
+            Ok(())
+        }
+        Commands::Inspect {
             source,
             target,
             include_databases,
             exclude_databases,
             include_tables,
             exclude_tables,
-            no_interactive,
-            table_rules,
-            force,
+            format,
+            output,
         } => {
-            let filter = if !no_interactive {
-                // Interactive mode (default) - prompt user to select databases and tables
-                let (filter, rules) =
-                    seren_replicator::interactive::select_databases_and_tables(&source).await?;
-                filter.with_table_rules(rules)
-            } else {
-                // CLI mode - use provided filter arguments
-                let filter = seren_replicator::filters::ReplicationFilter::new(
-                    include_databases,
-                    exclude_databases,
-                    include_tables,
-                    exclude_tables,
-                )?;
-                let table_rule_data = build_table_rules(&table_rules)?;
-                filter.with_table_rules(table_rule_data)
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                include_tables,
+                exclude_tables,
+            )?;
+            // clap requires exactly one of --source and --target
+            let Some(url) = source.or(target) else {
+                anyhow::bail!("inspect requires --source or --target");
             };
-            commands::sync(&source, &target, Some(filter), None, None, None, force).await
+            commands::inspect(&url, &filter, format, output.as_deref()).await
         }
-        Commands::Status {
+        Commands::Topology {
             source,
             target,
             include_databases,
             exclude_databases,
+            format,
+            output,
         } => {
             let filter = seren_replicator::filters::ReplicationFilter::new(
                 include_databases,
@@ -315,24 +1974,84 @@ async fn main() -> anyhow::Result<()> {
                 None,
                 None,
             )?;
-            commands::status(&source, &target, Some(filter)).await
+            commands::topology(
+                &source,
+                &target,
+                &filter,
+                format,
+                output.as_deref(),
+                &settings,
+            )
+            .await
         }
-        Commands::Verify {
+        Commands::Export {
             source,
-            target,
+            output_dir,
+            format,
             include_databases,
             exclude_databases,
             include_tables,
             exclude_tables,
+            table_rules,
         } => {
             let filter = seren_replicator::filters::ReplicationFilter::new(
                 include_databases,
                 exclude_databases,
                 include_tables,
                 exclude_tables,
+            )?
+            .with_table_rules(build_table_rules(&table_rules)?);
+            commands::export(
+                &source,
+                &output_dir,
+                format,
+                &filter,
+                &settings,
+                &seren_replicator::cancel::ctrl_c_token(),
+            )
+            .await
+        }
+        Commands::List {
+            source,
+            include_databases,
+            exclude_databases,
+        } => {
+            let filter = seren_replicator::filters::ReplicationFilter::new(
+                include_databases,
+                exclude_databases,
+                None,
+                None,
             )?;
-            commands::verify(&source, &target, Some(filter)).await
+            commands::list(&source, &filter).await
+        }
+        Commands::Audit {
+            target,
+            local,
+            action,
+            limit,
+        } => {
+            if local {
+                let path = cli
+                    .audit_log
+                    .unwrap_or_else(seren_replicator::audit::default_audit_file);
+                return commands::audit::audit_local(&path, action.as_deref(), limit as usize);
+            }
+            // clap requires --target unless --local is given
+            let Some(target) = target else {
+                anyhow::bail!("audit requires --target (or --local)");
+            };
+            commands::audit(&target, action.as_deref(), i64::from(limit), &settings).await
         }
+        Commands::Remote { command } => match command {
+            RemoteCommands::List {
+                limit,
+                status,
+                remote_api,
+            } => remote_list(remote_api, limit, status.as_deref(), settings.retry).await,
+            RemoteCommands::Describe { job_id, remote_api } => {
+                remote_describe(remote_api, &job_id, settings.retry).await
+            }
+        },
     }
 }
 
@@ -345,13 +2064,18 @@ async fn init_remote(
     exclude_databases: Option<Vec<String>>,
     include_tables: Option<Vec<String>>,
     exclude_tables: Option<Vec<String>>,
+    table_rules: seren_replicator::table_rules::TableRules,
+    config_path: Option<&str>,
     drop_existing: bool,
     no_sync: bool,
     remote_api: String,
     job_timeout: u64,
+    confirm: &seren_replicator::confirm::ConfirmSettings,
+    settings: &RunSettings,
 ) -> anyhow::Result<()> {
     use seren_replicator::migration;
     use seren_replicator::postgres;
+    use seren_replicator::remote::pricing::{self, RemotePricing};
     use seren_replicator::remote::{FilterSpec, JobSpec, RemoteClient};
     use std::collections::HashMap;
 
@@ -367,8 +2091,8 @@ async fn init_remote(
         exclude_tables.clone(),
     )?;
 
-    let estimated_size_bytes = {
-        let source_client = postgres::connect_with_retry(&source).await?;
+    let size_estimates = {
+        let source_client = postgres::connect_with_retry(&source, settings).await?;
         let all_databases = migration::list_databases(&source_client).await?;
 
         // Filter databases
@@ -379,38 +2103,71 @@ async fn init_remote(
 
         if databases.is_empty() {
             // No databases to replicate, use minimal size
-            0i64
+            Vec::new()
         } else {
-            // Estimate total size
-            let size_estimates = migration::estimate_database_sizes(
+            migration::estimate_database_sizes(
                 &source,
                 &source_client,
                 &databases,
                 &filter_for_sizing,
             )
-            .await?;
-
-            let total_bytes: i64 = size_estimates.iter().map(|s| s.size_bytes).sum();
-            println!(
-                "Total estimated size: {}",
-                migration::format_bytes(total_bytes)
-            );
-            total_bytes
+            .await?
         }
     };
+    let names: Vec<String> = size_estimates.iter().map(|s| s.name.clone()).collect();
+    seren_replicator::protected::check(&target, &names, "init")?;
+    if drop_existing && yes {
+        let target_client = postgres::connect_with_retry(&target, settings).await?;
+        let at_risk = seren_replicator::confirm::databases_at_risk(&target_client, &names).await?;
+        seren_replicator::confirm::check_drop(
+            seren_replicator::confirm::DropKind::Database,
+            &at_risk,
+            confirm,
+        )?;
+    }
+    let estimated_size_bytes: i64 = size_estimates.iter().map(|s| s.size_bytes).sum();
+    let copy_duration = size_estimates.iter().map(|s| s.estimated_duration).sum();
+
+    let pricing = match config_path {
+        Some(path) => match seren_replicator::config::load_remote_pricing_config_from_file(path)? {
+            Some(config) => RemotePricing::from_config(config)?,
+            None => RemotePricing::default(),
+        },
+        None => RemotePricing::default(),
+    };
+    let estimate = pricing.estimate(estimated_size_bytes, copy_duration);
+
+    if yes {
+        println!(
+            "Total estimated size: {}",
+            migration::format_bytes(estimated_size_bytes)
+        );
+        println!(
+            "Worker: {}, estimated duration: {}, approximate cost: ${:.2}",
+            estimate.worker,
+            migration::format_duration(estimate.duration),
+            estimate.cost_usd
+        );
+    } else if !pricing::confirm_remote_job(&size_estimates, &estimate)? {
+        return Err(seren_replicator::errors::ReplicatorError::Cancelled.into());
+    }
 
     // Build job specification
     let filter = if include_databases.is_none()
         && exclude_databases.is_none()
         && include_tables.is_none()
         && exclude_tables.is_none()
+        && table_rules.is_empty()
     {
         None
     } else {
-        Some(FilterSpec {
-            include_databases,
-            exclude_tables,
-        })
+        let mut filter = FilterSpec::new(include_databases, exclude_tables);
+        if !table_rules.is_empty() {
+            let fingerprint = table_rules.fingerprint();
+            println!("Table rules fingerprint: {}", fingerprint);
+            filter.table_rules_fingerprint = Some(fingerprint);
+        }
+        Some(filter)
     };
 
     let mut options = HashMap::new();
@@ -429,17 +2186,10 @@ async fn init_remote(
         serde_json::Value::Number(serde_json::Number::from(job_timeout)),
     );
 
-    let job_spec = JobSpec {
-        version: "1.0".to_string(),
-        command: "init".to_string(),
-        source_url: source,
-        target_url: target,
-        filter,
-        options,
-    };
+    let job_spec = JobSpec::new("init", source, target, filter, options);
 
     // Submit job
-    let client = RemoteClient::new(remote_api)?;
+    let client = RemoteClient::new(remote_api)?.with_retry_policy(settings.retry);
     println!("Submitting replication job...");
 
     let response = client.submit_job(&job_spec).await?;
@@ -453,12 +2203,7 @@ async fn init_remote(
             "provisioning" => println!("Status: provisioning EC2 instance..."),
             "running" => {
                 if let Some(ref progress) = status.progress {
-                    println!(
-                        "Status: running ({}/{}): {}",
-                        progress.databases_completed,
-                        progress.databases_total,
-                        progress.current_database.as_deref().unwrap_or("unknown")
-                    );
+                    println!("Status: running {}", progress.summary());
                 } else {
                     println!("Status: running...");
                 }
@@ -486,6 +2231,129 @@ async fn init_remote(
     }
 }
 
+/// Print submitted remote jobs as a table
+async fn remote_list(
+    remote_api: String,
+    limit: usize,
+    status: Option<&str>,
+    retry: seren_replicator::retry::RetryPolicy,
+) -> anyhow::Result<()> {
+    use seren_replicator::remote::{redact_url, RemoteClient};
+
+    let client = RemoteClient::new(remote_api)?.with_retry_policy(retry);
+    let jobs = client.list_jobs(limit, status).await?;
+    if jobs.is_empty() {
+        println!("No remote jobs found");
+        return Ok(());
+    }
+
+    println!(
+        "{:<38} {:<13} {:<9} {:<16} Source → Target",
+        "Job ID", "Status", "Command", "Duration"
+    );
+    println!("{}", "─".repeat(110));
+    for job in jobs {
+        let endpoint = |url: &Option<String>| {
+            url.as_deref()
+                .map(redact_url)
+                .unwrap_or_else(|| "-".to_string())
+        };
+        println!(
+            "{:<38} {:<13} {:<9} {:<16} {} → {}",
+            job.job_id,
+            job.status,
+            job.command.as_deref().unwrap_or("-"),
+            format_job_duration(job.duration_seconds),
+            endpoint(&job.source_url),
+            endpoint(&job.target_url)
+        );
+    }
+    Ok(())
+}
+
+/// Print everything recorded about one remote job
+async fn remote_describe(
+    remote_api: String,
+    job_id: &str,
+    retry: seren_replicator::retry::RetryPolicy,
+) -> anyhow::Result<()> {
+    use seren_replicator::remote::{redact_url, RemoteClient};
+
+    let client = RemoteClient::new(remote_api)?.with_retry_policy(retry);
+    let details = client.describe_job(job_id).await?;
+    let status = &details.status;
+
+    println!("Job ID:    {}", status.job_id);
+    println!("Status:    {}", status.status);
+    for (label, value) in [
+        ("Created:", &status.created_at),
+        ("Started:", &status.started_at),
+        ("Completed:", &status.completed_at),
+    ] {
+        if let Some(value) = value {
+            println!("{:<10} {}", label, value);
+        }
+    }
+    println!(
+        "Duration:  {}",
+        format_job_duration(details.duration_seconds)
+    );
+    if let Some(progress) = &status.progress {
+        println!("Progress:  {}", progress.summary());
+    }
+    if let Some(error) = &status.error {
+        println!("Error:     {}", error);
+    }
+
+    if let Some(spec) = &details.spec {
+        println!(
+            "
+Spec (version {}):",
+            spec.version
+        );
+        println!("  Command: {}", spec.command);
+        println!("  Source:  {}", redact_url(&spec.source_url));
+        println!("  Target:  {}", redact_url(&spec.target_url));
+        match &spec.filter {
+            Some(filter) => {
+                println!("  Filter (version {}):", filter.version);
+                if let Some(databases) = &filter.include_databases {
+                    println!("    Include databases: {}", databases.join(", "));
+                }
+                if let Some(tables) = &filter.exclude_tables {
+                    println!("    Exclude tables:    {}", tables.join(", "));
+                }
+                if let Some(fingerprint) = &filter.table_rules_fingerprint {
+                    println!("    Table rules:       {}", fingerprint);
+                }
+            }
+            None => println!("  Filter:  none"),
+        }
+        let mut options: Vec<_> = spec.options.iter().collect();
+        options.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, value) in options {
+            println!("  {}: {}", name, value);
+        }
+    }
+
+    if let Some(report) = &details.report {
+        println!(
+            "
+Final report:"
+        );
+        println!("{}", serde_json::to_string_pretty(report)?);
+    }
+    Ok(())
+}
+
+fn format_job_duration(seconds: Option<u64>) -> String {
+    seconds
+        .map(|secs| {
+            seren_replicator::migration::format_duration(std::time::Duration::from_secs(secs))
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
 fn build_table_rules(
     args: &TableRuleArgs,
 ) -> anyhow::Result<seren_replicator::table_rules::TableRules> {
@@ -497,5 +2365,370 @@ fn build_table_rules(
     rules.apply_schema_only_cli(&args.schema_only_tables)?;
     rules.apply_table_filter_cli(&args.table_filters)?;
     rules.apply_time_filter_cli(&args.time_filters)?;
+    rules.apply_transform_cli(&args.transforms)?;
+    rules.apply_partition_cli(&args.partition_tables)?;
+    rules.apply_priority_cli(&args.table_priorities)?;
+    if let Some(order) = &args.copy_order {
+        rules.set_copy_order(order.parse()?);
+    }
     Ok(rules)
 }
+
+/// Hooks from the `[hooks]` section of the config file, if any
+///
+/// Relative hook paths are resolved against the config file's directory.
+fn config_hooks(config_path: Option<&str>) -> anyhow::Result<seren_replicator::hooks::HookSet> {
+    let Some(path) = config_path else {
+        return Ok(Default::default());
+    };
+    if let Some(hooks) = seren_replicator::config::load_hooks_config_from_file(path)? {
+        let base_dir = std::path::Path::new(path)
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default();
+        let base_dir = if base_dir.as_os_str().is_empty() {
+            std::path::PathBuf::from(".")
+        } else {
+            base_dir
+        };
+        return seren_replicator::hooks::HookSet::new(hooks, base_dir);
+    }
+    Ok(Default::default())
+}
+
+/// Sync settings from the `[publication]` and `[subscription]` config sections
+fn config_sync_options(config_path: Option<&str>) -> anyhow::Result<commands::sync::SyncOptions> {
+    let mut options = commands::sync::SyncOptions::default();
+    let Some(path) = config_path else {
+        return Ok(options);
+    };
+    if let Some(config) = seren_replicator::config::load_publication_config_from_file(path)? {
+        options.publication =
+            seren_replicator::replication::publication::PublicationOptions::from_config(&config)
+                .map_err(|e| anyhow::anyhow!("[publication]: {:#}", e))?;
+    }
+    if let Some(config) = seren_replicator::config::load_subscription_config_from_file(path)? {
+        options.subscription =
+            seren_replicator::replication::subscription::SubscriptionOptions::from_config(&config)
+                .map_err(|e| anyhow::anyhow!("[subscription]: {:#}", e))?;
+    }
+    Ok(options)
+}
+
+/// Init settings from the config file: object policies, the `[extensions]`
+/// allowlist, `[parallelism] split_tables_larger_than`, and the sync settings
+fn config_init_options(config_path: Option<&str>) -> anyhow::Result<commands::init::InitOptions> {
+    let mut options = commands::init::InitOptions {
+        sync: config_sync_options(config_path)?,
+        ..Default::default()
+    };
+    let Some(path) = config_path else {
+        return Ok(options);
+    };
+    if let Some(config) = seren_replicator::config::load_foreign_server_config_from_file(path)? {
+        options.foreign_servers = config.policy;
+    }
+    if let Some(config) = seren_replicator::config::load_event_trigger_config_from_file(path)? {
+        options.event_triggers = config.policy;
+    }
+    if let Some(config) = seren_replicator::config::load_unsupported_object_config_from_file(path)?
+    {
+        options.unsupported_objects = config.policy;
+    }
+    if let Some(config) = seren_replicator::config::load_extension_install_config_from_file(path)? {
+        options.extensions = config;
+    }
+    if let Some(size) = seren_replicator::config::load_parallelism_config_from_file(path)?
+        .and_then(|config| config.split_tables_larger_than)
+    {
+        options.split_threshold = Some(seren_replicator::parallelism::parse_size(&size).map_err(
+            |e| anyhow::anyhow!("[parallelism] split_tables_larger_than in {}: {}", path, e),
+        )?);
+    }
+    Ok(options)
+}
+
+/// Neon target settings from the `[neon]` config section and `NEON_*` variables
+fn config_neon_settings(
+    config_path: Option<&str>,
+) -> anyhow::Result<seren_replicator::postgres::neon::NeonSettings> {
+    use seren_replicator::postgres::neon::{self, NeonSettings};
+
+    let config = match config_path {
+        Some(path) => seren_replicator::config::load_neon_config_from_file(path)?,
+        None => None,
+    };
+    Ok(NeonSettings::resolve(
+        config,
+        std::env::var(neon::API_KEY_ENV).ok(),
+        std::env::var(neon::PROJECT_ID_ENV).ok(),
+    ))
+}
+
+/// Per-group lag limits from the `[lag_thresholds]` config section
+fn config_lag_thresholds(
+    config_path: Option<&str>,
+) -> anyhow::Result<seren_replicator::replication::lag_thresholds::LagThresholds> {
+    match config_path {
+        Some(path) => match seren_replicator::config::load_lag_thresholds_config_from_file(path)? {
+            Some(config) => {
+                seren_replicator::replication::lag_thresholds::LagThresholds::from_config(&config)
+            }
+            None => Ok(Default::default()),
+        },
+        None => Ok(Default::default()),
+    }
+}
+
+/// The `[[smoke_queries]]` run by readiness and the pre-cutover stage
+fn config_smoke_queries(
+    config_path: Option<&str>,
+) -> anyhow::Result<Vec<seren_replicator::replication::smoke::SmokeQuery>> {
+    let Some(path) = config_path else {
+        return Ok(Vec::new());
+    };
+    let queries = seren_replicator::config::load_smoke_queries_from_file(path)?;
+    seren_replicator::replication::smoke::validate(&queries)?;
+    Ok(queries)
+}
+
+/// JSONB options for init, verify, and reconcile
+///
+/// The target schema comes from `--target-schema`, then the
+/// `[jsonb_target_schema]` config section. Batch bounds, partitioning, and the
+/// BSON type mapping come from their config sections, and value conversion
+/// from the command's flags.
+fn jsonb_options(command: &Commands) -> anyhow::Result<seren_replicator::jsonb::JsonbOptions> {
+    let (Commands::Init {
+        source,
+        target_schema,
+        ..
+    }
+    | Commands::Verify {
+        source,
+        target_schema,
+        ..
+    }
+    | Commands::Reconcile {
+        source,
+        target_schema,
+        ..
+    }) = command
+    else {
+        return Ok(Default::default());
+    };
+    // An undetectable source is reported by the command itself
+    let Ok(source_type) = seren_replicator::detect_source_type(source) else {
+        return Ok(Default::default());
+    };
+    if source_type == seren_replicator::SourceType::PostgreSQL {
+        if target_schema.is_some() {
+            anyhow::bail!(
+                "--target-schema applies to SQLite, MySQL, and MongoDB sources.\n\
+                 PostgreSQL sources keep their own schemas on the target."
+            );
+        }
+        return Ok(Default::default());
+    }
+    let config = match command.config_path() {
+        Some(path) => seren_replicator::config::load_jsonb_target_schema_config_from_file(path)?,
+        None => None,
+    };
+    let mut options = seren_replicator::jsonb::JsonbOptions {
+        target_schema: seren_replicator::jsonb::target_schema::resolve(
+            target_schema.as_deref(),
+            config.as_ref(),
+            &source_type,
+        )?,
+        ..Default::default()
+    };
+    if let Some(path) = command.config_path() {
+        if let Some(config) = seren_replicator::config::load_jsonb_batching_config_from_file(path)?
+        {
+            options.batching = seren_replicator::jsonb::batching::resolve(&config)?;
+        }
+        if let Some(config) =
+            seren_replicator::config::load_jsonb_partitioning_config_from_file(path)?
+        {
+            options.partitioning = seren_replicator::jsonb::partitioning::resolve(&config)?;
+        }
+        if let Some(map) = seren_replicator::config::load_bson_types_config_from_file(path)? {
+            options.bson_types = map;
+        }
+    }
+    match command {
+        Commands::Init {
+            on_row_error,
+            mysql_charset,
+            assume_source_tz,
+            invalid_dates,
+            gridfs_storage,
+            refresh,
+            collision_policy,
+            ..
+        } => {
+            if let Some(policy) = on_row_error {
+                options.on_row_error = policy.parse()?;
+            }
+            options.mysql_charsets =
+                seren_replicator::mysql::charset::parse_overrides(mysql_charset)?;
+            options.source_timezones =
+                seren_replicator::mysql::datetime::parse_timezones(assume_source_tz)?;
+            if let Some(policy) = invalid_dates {
+                options.invalid_dates = policy.parse()?;
+            }
+            if let Some(storage) = gridfs_storage {
+                options.gridfs_storage = storage.parse()?;
+            }
+            if let Some(strategy) = refresh {
+                options.refresh = strategy.parse()?;
+            }
+            if let Some(policy) = collision_policy {
+                options.collision_policy = policy.parse()?;
+            }
+        }
+        Commands::Verify {
+            mysql_charset,
+            assume_source_tz,
+            invalid_dates,
+            ..
+        } => {
+            options.mysql_charsets =
+                seren_replicator::mysql::charset::parse_overrides(mysql_charset)?;
+            options.source_timezones =
+                seren_replicator::mysql::datetime::parse_timezones(assume_source_tz)?;
+            if let Some(policy) = invalid_dates {
+                options.invalid_dates = policy.parse()?;
+            }
+        }
+        _ => {}
+    }
+    Ok(options)
+}
+
+/// Job budget from the `[parallelism]` config section and `--max-total-jobs`
+///
+/// Also returns the memory budget from `[parallelism] max_memory` and `--max-memory`.
+/// CLI flags take precedence over the config file; with neither, jobs are
+/// auto-detected and memory is unlimited.
+fn config_budgets(
+    max_total_jobs: Option<u32>,
+    max_memory: Option<&str>,
+    config_path: Option<&str>,
+) -> anyhow::Result<(
+    seren_replicator::parallelism::JobBudget,
+    seren_replicator::memory::MemoryBudget,
+)> {
+    let mut jobs = None;
+    let mut memory = None;
+    if let Some(path) = config_path {
+        if let Some(config) = seren_replicator::config::load_parallelism_config_from_file(path)? {
+            if config.max_total_jobs == Some(0) {
+                anyhow::bail!(
+                    "[parallelism] max_total_jobs in {} must be at least 1",
+                    path
+                );
+            }
+            jobs = config.max_total_jobs;
+            if let Some(size) = config.max_memory {
+                memory = Some(
+                    seren_replicator::parallelism::parse_size(&size).map_err(|e| {
+                        anyhow::anyhow!("[parallelism] max_memory in {}: {}", path, e)
+                    })?,
+                );
+            }
+        }
+    }
+    if let Some(size) = max_memory {
+        memory = Some(seren_replicator::parallelism::parse_size(size)?);
+    }
+    if let Some(bytes) = memory {
+        tracing::info!(
+            "Memory budget: {}",
+            seren_replicator::migration::estimation::format_bytes(bytes)
+        );
+    }
+    if let Some(from_cli) = max_total_jobs {
+        jobs = Some(from_cli as usize);
+    }
+    let budget = seren_replicator::parallelism::JobBudget::new(jobs);
+    if jobs.is_some() {
+        tracing::info!("Parallel job budget: {}", budget.max_total_jobs());
+    }
+    Ok((
+        budget,
+        seren_replicator::memory::MemoryBudget::new(memory.map(|bytes| bytes as usize)),
+    ))
+}
+
+/// Build the retry policy from defaults, the `[retry]` config section, and CLI flags
+///
+/// CLI flags take precedence over the config file, which takes precedence over defaults.
+fn config_retry_policy(
+    args: &RetryArgs,
+    config_path: Option<&str>,
+) -> anyhow::Result<seren_replicator::retry::RetryPolicy> {
+    use seren_replicator::retry::{RetryConfig, RetryPolicy};
+
+    let mut policy = RetryPolicy::default();
+    if let Some(path) = config_path {
+        if let Some(from_file) = seren_replicator::config::load_retry_config_from_file(path)? {
+            policy = from_file.apply_to(policy);
+        }
+    }
+    let from_cli = RetryConfig {
+        max_retries: args.max_retries,
+        base_delay_secs: args.retry_base_delay,
+        max_delay_secs: args.retry_max_delay,
+        jitter: None,
+    };
+    policy = from_cli.apply_to(policy);
+
+    if policy.max_delay < policy.base_delay {
+        anyhow::bail!(
+            "--retry-max-delay ({}s) must be greater than or equal to --retry-base-delay ({}s)",
+            policy.max_delay.as_secs(),
+            policy.base_delay.as_secs()
+        );
+    }
+
+    Ok(policy)
+}
+
+/// Settings for this run from the config file and global flags
+fn run_settings(cli: &Cli) -> anyhow::Result<RunSettings> {
+    let config_path = cli.command.config_path();
+    let (jobs, memory) =
+        config_budgets(cli.max_total_jobs, cli.max_memory.as_deref(), config_path)?;
+    let chaos = match &cli.chaos {
+        Some(spec) => {
+            use seren_replicator::chaos::{Chaos, ChaosConfig, ENABLE_ENV};
+            Chaos::new(ChaosConfig::parse(
+                spec,
+                std::env::var(ENABLE_ENV).ok().as_deref(),
+            )?)
+        }
+        None => Default::default(),
+    };
+    Ok(RunSettings {
+        retry: config_retry_policy(&cli.retry, config_path)?,
+        pooler: seren_replicator::postgres::pooler::PoolerSettings {
+            declared: cli.pooler.target_pooler,
+            direct_port: cli.pooler.target_direct_port,
+        },
+        neon: config_neon_settings(config_path)?,
+        audit: seren_replicator::audit::AuditSettings::new(
+            &std::env::args().collect::<Vec<_>>(),
+            Some(
+                cli.audit_log
+                    .clone()
+                    .unwrap_or_else(seren_replicator::audit::default_audit_file),
+            ),
+            cli.command.target(),
+        ),
+        hooks: config_hooks(config_path)?,
+        jobs,
+        memory,
+        chaos,
+    })
+}
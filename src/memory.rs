@@ -0,0 +1,224 @@
+// ABOUTME: Memory budget and accounting of one run for buffered rows and batches
+// ABOUTME: Under pressure, batch sizes and parallelism shrink instead of the process being OOM-killed
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Fraction of the budget above which batches and job counts are halved
+pub const HIGH_WATER: f64 = 0.8;
+
+/// Fraction of the budget above which work runs one job and one minimal batch at a time
+pub const CRITICAL_WATER: f64 = 0.95;
+
+/// A run's memory budget (`--max-memory`) and the data buffered against it
+///
+/// Clones share the accounting, so every task of a run draws on one budget.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBudget {
+    /// Budget in bytes (None = unlimited)
+    limit: Option<usize>,
+    usage: Arc<Usage>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    /// Bytes currently held by [`MemoryReservation`]s
+    used: AtomicUsize,
+    /// Highest value `used` has reached
+    peak: AtomicUsize,
+    /// Whether the over-budget warning has been logged
+    warned: AtomicBool,
+}
+
+impl MemoryBudget {
+    /// A budget of `limit` bytes, or unlimited
+    pub fn new(limit: Option<usize>) -> Self {
+        MemoryBudget {
+            limit,
+            usage: Arc::default(),
+        }
+    }
+
+    /// Configured memory budget, if any
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Bytes currently accounted for
+    pub fn used_bytes(&self) -> usize {
+        self.usage.used.load(Ordering::SeqCst)
+    }
+
+    /// Most bytes accounted for at once since the budget was created
+    pub fn peak_bytes(&self) -> usize {
+        self.usage.peak.load(Ordering::SeqCst)
+    }
+
+    /// Account for `bytes` of buffered data until the reservation is dropped
+    ///
+    /// Tracking never fails: the data is already in memory. Crossing the budget
+    /// logs a warning once; callers adapt through [`MemoryBudget::scale_down`]
+    /// and [`MemoryBudget::batch_byte_limit`].
+    pub fn track(&self, bytes: usize) -> MemoryReservation {
+        let used = self.usage.used.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.usage.peak.fetch_max(used, Ordering::SeqCst);
+        if let Some(limit) = self.limit {
+            if used > limit && !self.usage.warned.swap(true, Ordering::SeqCst) {
+                let message = format!(
+                    "Buffered data ({}) exceeds the memory budget ({}); reducing batch sizes and parallelism.\n\
+                     A single table or collection larger than the budget is still held in memory while it is copied.",
+                    crate::migration::estimation::format_bytes(used as i64),
+                    crate::migration::estimation::format_bytes(limit as i64)
+                );
+                tracing::warn!("⚠ {}", message);
+                crate::events::warning(&message);
+            }
+        }
+        MemoryReservation {
+            bytes,
+            usage: self.usage.clone(),
+        }
+    }
+
+    /// Log the most buffered data held at once, when a budget is set
+    pub fn log_peak(&self) {
+        if let Some(limit) = self.limit {
+            tracing::info!(
+                "  Peak buffered data: {} of {} memory budget",
+                crate::migration::estimation::format_bytes(self.peak_bytes() as i64),
+                crate::migration::estimation::format_bytes(limit as i64)
+            );
+        }
+    }
+
+    /// Fraction of the budget in use (0.0 when unlimited)
+    pub fn pressure(&self) -> f64 {
+        pressure_of(self.used_bytes(), self.limit)
+    }
+
+    /// Scale a job count or batch size down under memory pressure (at least 1)
+    pub fn scale_down(&self, count: usize) -> usize {
+        scale_for_pressure(count, self.pressure())
+    }
+
+    /// Largest batch, in bytes, that fits in what is left of the budget
+    ///
+    /// A quarter of the remaining budget, so a batch and its encoded copy fit
+    /// alongside other buffers. `None` when no budget is set.
+    pub fn batch_byte_limit(&self) -> Option<usize> {
+        self.limit
+            .map(|limit| (limit.saturating_sub(self.used_bytes()) / 4).max(1))
+    }
+}
+
+/// Memory accounted for until dropped
+#[derive(Debug)]
+pub struct MemoryReservation {
+    bytes: usize,
+    usage: Arc<Usage>,
+}
+
+impl MemoryReservation {
+    /// Bytes held
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.usage.used.fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+/// Approximate heap size of a JSON value
+///
+/// Counts string and key bytes plus a fixed overhead per value; close enough
+/// for budgeting without serializing the value.
+pub fn estimate_json_bytes(value: &serde_json::Value) -> usize {
+    const VALUE_OVERHEAD: usize = 32;
+    VALUE_OVERHEAD
+        + match value {
+            serde_json::Value::String(s) => s.len(),
+            serde_json::Value::Array(items) => items.iter().map(estimate_json_bytes).sum(),
+            serde_json::Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| key.len() + estimate_json_bytes(value))
+                .sum(),
+            _ => 0,
+        }
+}
+
+/// Approximate heap size of converted `(id, document)` rows
+pub fn estimate_rows_bytes(rows: &[(String, serde_json::Value)]) -> usize {
+    rows.iter()
+        .map(|(id, data)| id.len() + estimate_json_bytes(data))
+        .sum()
+}
+
+fn pressure_of(used: usize, limit: Option<usize>) -> f64 {
+    match limit {
+        Some(limit) if limit > 0 => used as f64 / limit as f64,
+        _ => 0.0,
+    }
+}
+
+fn scale_for_pressure(jobs: usize, pressure: f64) -> usize {
+    if pressure >= CRITICAL_WATER {
+        1
+    } else if pressure >= HIGH_WATER {
+        (jobs / 2).max(1)
+    } else {
+        jobs.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pressure_of() {
+        assert_eq!(pressure_of(500, None), 0.0);
+        assert_eq!(pressure_of(500, Some(1000)), 0.5);
+        assert_eq!(pressure_of(1500, Some(1000)), 1.5);
+    }
+
+    #[test]
+    fn test_scale_for_pressure() {
+        assert_eq!(scale_for_pressure(8, 0.0), 8);
+        assert_eq!(scale_for_pressure(8, 0.79), 8);
+        assert_eq!(scale_for_pressure(8, 0.8), 4);
+        assert_eq!(scale_for_pressure(1, 0.9), 1);
+        assert_eq!(scale_for_pressure(8, 0.95), 1);
+        assert_eq!(scale_for_pressure(8, 3.0), 1);
+    }
+
+    #[test]
+    fn test_estimate_json_bytes_grows_with_content() {
+        let small = json!({"a": 1});
+        let large = json!({"a": 1, "text": "x".repeat(10_000)});
+        assert!(estimate_json_bytes(&large) > estimate_json_bytes(&small) + 10_000);
+
+        let rows = vec![("1".to_string(), small.clone()), ("2".to_string(), small)];
+        assert_eq!(
+            estimate_rows_bytes(&rows),
+            2 * (1 + estimate_json_bytes(&json!({"a": 1})))
+        );
+    }
+
+    #[test]
+    fn test_reservation_releases_on_drop() {
+        let budget = MemoryBudget::new(Some(4 << 20));
+        let other = MemoryBudget::new(Some(4 << 20));
+        let reservation = budget.clone().track(1 << 20);
+        assert_eq!(budget.used_bytes(), 1 << 20);
+        assert_eq!(other.used_bytes(), 0);
+        assert_eq!(reservation.bytes(), 1 << 20);
+        drop(reservation);
+        assert_eq!(budget.used_bytes(), 0);
+        assert_eq!(budget.peak_bytes(), 1 << 20);
+        assert_eq!(budget.batch_byte_limit(), Some(1 << 20));
+    }
+}
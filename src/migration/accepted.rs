@@ -0,0 +1,315 @@
+// ABOUTME: Known, acceptable source/target differences recorded with `accept-diff`, stored on the target
+// ABOUTME: verify re-checks mismatched tables without the accepted columns and rows and reports them as accepted diffs
+
+use super::checksum::ChecksumExclusions;
+use crate::utils::quote_ident;
+use anyhow::{bail, Context, Result};
+use tokio_postgres::Client;
+
+/// Table in each target database holding the accepted differences for its tables
+pub const ACCEPTED_TABLE: &str = "seren_replicator.accepted_diffs";
+
+/// One accepted difference
+///
+/// With no columns and no predicate the whole table is accepted. Otherwise
+/// the listed columns and the rows matching the predicate are left out when a
+/// mismatched table is checked again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcceptedDiff {
+    pub id: i64,
+    pub schema: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    /// SQL predicate selecting the rows allowed to differ
+    pub predicate: Option<String>,
+    pub reason: Option<String>,
+    pub accepted_at: String,
+}
+
+impl AcceptedDiff {
+    /// Whether the entry accepts any difference in its table
+    pub fn whole_table(&self) -> bool {
+        self.columns.is_empty() && self.predicate.is_none()
+    }
+
+    /// Short description for logs, e.g. `columns synced_at; rows where id < 100`
+    pub fn scope(&self) -> String {
+        if self.whole_table() {
+            return "whole table".to_string();
+        }
+        let mut parts = Vec::new();
+        if !self.columns.is_empty() {
+            parts.push(format!("columns {}", self.columns.join(", ")));
+        }
+        if let Some(predicate) = &self.predicate {
+            parts.push(format!("rows where {}", predicate));
+        }
+        parts.join("; ")
+    }
+}
+
+/// How a mismatched table may still be accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Acceptance {
+    /// Any difference in the table is accepted
+    WholeTable,
+    /// The table is accepted if it matches without these columns and rows
+    Excluding(ChecksumExclusions),
+}
+
+/// Combine the entries for `schema.table` into one acceptance
+///
+/// # Returns
+///
+/// `None` if nothing is accepted for the table.
+pub fn acceptance_for(accepted: &[AcceptedDiff], schema: &str, table: &str) -> Option<Acceptance> {
+    let entries: Vec<&AcceptedDiff> = accepted
+        .iter()
+        .filter(|diff| diff.schema == schema && diff.table == table)
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    if entries.iter().any(|diff| diff.whole_table()) {
+        return Some(Acceptance::WholeTable);
+    }
+    let mut columns: Vec<String> = Vec::new();
+    let mut predicates = Vec::new();
+    for diff in entries {
+        for column in &diff.columns {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        if let Some(predicate) = &diff.predicate {
+            predicates.push(format!("({})", predicate));
+        }
+    }
+    Some(Acceptance::Excluding(ChecksumExclusions {
+        columns,
+        rows: (!predicates.is_empty()).then(|| predicates.join(" OR ")),
+    }))
+}
+
+/// Create the accepted differences schema and table on the target if they do not exist
+///
+/// # Errors
+///
+/// Returns an error if the target user cannot create the schema or table.
+pub async fn ensure_accepted_table(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 id bigserial PRIMARY KEY,
+                 schema_name text NOT NULL,
+                 table_name text NOT NULL,
+                 columns text[] NOT NULL DEFAULT '{{}}',
+                 predicate text,
+                 reason text,
+                 accepted_at timestamptz NOT NULL DEFAULT now()
+             )",
+            ACCEPTED_TABLE
+        ))
+        .await
+        .context(
+            "Failed to create the accepted differences table on the target.\n\
+             The target user needs CREATE privilege on the database in the target URL.",
+        )
+}
+
+/// Record an accepted difference for `schema.table`
+///
+/// The columns must exist on the target table and the predicate must be
+/// valid SQL for it, so a typo cannot silently accept nothing.
+///
+/// # Returns
+///
+/// The id of the new entry.
+///
+/// # Errors
+///
+/// Returns an error if the table or a column does not exist on the target,
+/// the predicate does not parse, or the entry cannot be stored.
+pub async fn add(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    columns: &[String],
+    predicate: Option<&str>,
+    reason: Option<&str>,
+) -> Result<i64> {
+    let existing: Vec<String> = client
+        .query(
+            "SELECT column_name::text FROM information_schema.columns
+             WHERE table_schema = $1 AND table_name = $2",
+            &[&schema, &table],
+        )
+        .await
+        .with_context(|| format!("Failed to read the columns of {}.{}", schema, table))?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    if existing.is_empty() {
+        bail!(
+            "Table {}.{} does not exist in the target database.\n\
+             Point --target at the database that holds the table.",
+            schema,
+            table
+        );
+    }
+    if let Some(missing) = columns.iter().find(|column| !existing.contains(column)) {
+        bail!(
+            "Column '{}' does not exist on {}.{}.\n\
+             Column names are case-sensitive; check them with \\d {}.{}.",
+            missing,
+            schema,
+            table,
+            schema,
+            table
+        );
+    }
+    if let Some(predicate) = predicate {
+        client
+            .batch_execute(&format!(
+                "EXPLAIN SELECT 1 FROM {}.{} WHERE ({})",
+                quote_ident(schema),
+                quote_ident(table),
+                predicate
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Predicate '{}' is not valid for {}.{}.\n\
+                     Pass a boolean SQL expression over the table's columns, e.g. \"id < 1000\".",
+                    predicate, schema, table
+                )
+            })?;
+    }
+
+    ensure_accepted_table(client).await?;
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO {} (schema_name, table_name, columns, predicate, reason)
+                 VALUES ($1, $2, $3, $4, $5)
+                 RETURNING id",
+                ACCEPTED_TABLE
+            ),
+            &[&schema, &table, &columns, &predicate, &reason],
+        )
+        .await
+        .context("Failed to record the accepted difference")?;
+    Ok(row.get(0))
+}
+
+/// Delete the accepted difference with `id`
+///
+/// # Returns
+///
+/// Whether an entry was deleted.
+pub async fn remove(client: &Client, id: i64) -> Result<bool> {
+    if !table_exists(client).await? {
+        return Ok(false);
+    }
+    let deleted = client
+        .execute(
+            &format!("DELETE FROM {} WHERE id = $1", ACCEPTED_TABLE),
+            &[&id],
+        )
+        .await
+        .context("Failed to delete the accepted difference")?;
+    Ok(deleted > 0)
+}
+
+/// Load the accepted differences recorded in this database, oldest first
+///
+/// # Returns
+///
+/// An empty list if the table does not exist yet.
+pub async fn load(client: &Client) -> Result<Vec<AcceptedDiff>> {
+    if !table_exists(client).await? {
+        return Ok(Vec::new());
+    }
+    let rows = client
+        .query(
+            &format!(
+                "SELECT id, schema_name, table_name, columns, predicate, reason,
+                        to_char(accepted_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                 FROM {}
+                 ORDER BY id",
+                ACCEPTED_TABLE
+            ),
+            &[],
+        )
+        .await
+        .context("Failed to read accepted differences")?;
+    Ok(rows
+        .iter()
+        .map(|row| AcceptedDiff {
+            id: row.get(0),
+            schema: row.get(1),
+            table: row.get(2),
+            columns: row.get(3),
+            predicate: row.get(4),
+            reason: row.get(5),
+            accepted_at: row.get(6),
+        })
+        .collect())
+}
+
+async fn table_exists(client: &Client) -> Result<bool> {
+    Ok(client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&ACCEPTED_TABLE])
+        .await
+        .context("Failed to check for the accepted differences table")?
+        .get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(table: &str, columns: &[&str], predicate: Option<&str>) -> AcceptedDiff {
+        AcceptedDiff {
+            id: 1,
+            schema: "public".to_string(),
+            table: table.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            predicate: predicate.map(str::to_string),
+            reason: None,
+            accepted_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_acceptance_for() {
+        let accepted = vec![
+            diff("events", &["synced_at"], None),
+            diff("events", &["synced_at", "etag"], Some("id < 100")),
+            diff("events", &[], Some("source = 'legacy'")),
+            diff("audit", &[], None),
+        ];
+        assert_eq!(acceptance_for(&accepted, "public", "users"), None);
+        assert_eq!(
+            acceptance_for(&accepted, "public", "audit"),
+            Some(Acceptance::WholeTable)
+        );
+        assert_eq!(
+            acceptance_for(&accepted, "public", "events"),
+            Some(Acceptance::Excluding(ChecksumExclusions {
+                columns: vec!["synced_at".to_string(), "etag".to_string()],
+                rows: Some("(id < 100) OR (source = 'legacy')".to_string()),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_scope() {
+        assert_eq!(diff("t", &[], None).scope(), "whole table");
+        assert_eq!(
+            diff("t", &["a", "b"], Some("id < 5")).scope(),
+            "columns a, b; rows where id < 5"
+        );
+    }
+}
@@ -1,7 +1,10 @@
 // ABOUTME: Data validation utilities using checksums
-// ABOUTME: Computes and compares table checksums for data integrity verification
+// ABOUTME: Computes server-side table checksums (md5, sha256, hashtext) and compares source and target
 
+use crate::utils::quote_ident;
 use anyhow::{Context, Result};
+use std::fmt;
+use std::str::FromStr;
 use tokio_postgres::Client;
 
 /// Result of a checksum comparison between source and target tables
@@ -23,19 +26,166 @@ impl ChecksumResult {
     }
 }
 
-/// Compute checksum for a table
+/// Hash function used for table checksums
+///
+/// All hashing happens on the server. PostgreSQL has no built-in xxhash; for a
+/// fast non-cryptographic hash use `hashtext`, which is the 64-bit hash
+/// PostgreSQL itself uses for hash partitioning and is stable across platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Md5,
+    /// Requires PostgreSQL 11+
+    Sha256,
+    /// `hashtextextended`; fastest, requires PostgreSQL 11+
+    Hashtext,
+}
+
+impl ChecksumAlgorithm {
+    pub const ALL: [ChecksumAlgorithm; 3] = [
+        ChecksumAlgorithm::Md5,
+        ChecksumAlgorithm::Sha256,
+        ChecksumAlgorithm::Hashtext,
+    ];
+
+    /// Name accepted by `verify --checksum`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "md5",
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Hashtext => "hashtext",
+        }
+    }
+
+    /// SQL expression hashing a text expression to a hex string
+    fn hash_sql(&self, expr: &str) -> String {
+        match self {
+            ChecksumAlgorithm::Md5 => format!("md5({})", expr),
+            ChecksumAlgorithm::Sha256 => {
+                format!("encode(sha256(convert_to({}, 'UTF8')), 'hex')", expr)
+            }
+            ChecksumAlgorithm::Hashtext => {
+                format!("lpad(to_hex(hashtextextended({}, 0)), 16, '0')", expr)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ChecksumAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.as_str() == s.to_ascii_lowercase())
+            .with_context(|| {
+                format!(
+                    "Unknown checksum algorithm '{}'. Expected one of: md5, sha256, hashtext",
+                    s
+                )
+            })
+    }
+}
+
+/// How table checksums are computed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumOptions {
+    pub algorithm: ChecksumAlgorithm,
+    /// Hash only the primary key and `updated_at_column` instead of every column
+    ///
+    /// Detects missing, extra, and stale rows much faster on wide tables, but not
+    /// changes that leave the timestamp column untouched.
+    pub key_columns_only: bool,
+    /// Timestamp column included in key-only mode when the table has it
+    pub updated_at_column: String,
+}
+
+impl Default for ChecksumOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: ChecksumAlgorithm::Md5,
+            key_columns_only: false,
+            updated_at_column: "updated_at".to_string(),
+        }
+    }
+}
+
+/// Columns and rows left out of a checksum, for differences the user has accepted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChecksumExclusions {
+    /// Columns not hashed
+    pub columns: Vec<String>,
+    /// SQL predicate; matching rows are neither hashed nor counted
+    pub rows: Option<String>,
+}
+
+/// Compute checksum for a table with the default options (MD5 over all columns)
 ///
-/// This generates an MD5 checksum of all data in the table by:
-/// 1. Querying all columns in the table
-/// 2. Concatenating all column values for each row
-/// 3. Ordering by all columns for deterministic results
-/// 4. Computing MD5 hash of the aggregated data
+/// See [`compute_table_checksum_with_options`].
 pub async fn compute_table_checksum(
     client: &Client,
     schema: &str,
     table: &str,
 ) -> Result<(String, i64)> {
-    tracing::debug!("Computing checksum for {}.{}", schema, table);
+    compute_table_checksum_with_options(client, schema, table, &ChecksumOptions::default()).await
+}
+
+/// Compute checksum for a table
+///
+/// The checksum is computed entirely on the server:
+/// 1. Each row's selected columns are concatenated (NULLs as empty strings) and hashed
+/// 2. Row hashes are aggregated in a deterministic order (primary key when the
+///    table has one, otherwise all selected columns)
+/// 3. The aggregate is hashed again with the same algorithm
+///
+/// Only one short hash per row is aggregated, rather than the full row text.
+///
+/// # Errors
+///
+/// Returns an error if the table has no columns or the query fails (for example,
+/// `sha256`/`hashtext` on PostgreSQL older than 11).
+pub async fn compute_table_checksum_with_options(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    options: &ChecksumOptions,
+) -> Result<(String, i64)> {
+    compute_table_checksum_excluding(
+        client,
+        schema,
+        table,
+        options,
+        &ChecksumExclusions::default(),
+    )
+    .await
+}
+
+/// Compute checksum for a table, leaving out the excluded columns and rows
+///
+/// See [`compute_table_checksum_with_options`].
+///
+/// # Errors
+///
+/// Also returns an error if every hashed column is excluded.
+pub async fn compute_table_checksum_excluding(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    options: &ChecksumOptions,
+    exclusions: &ChecksumExclusions,
+) -> Result<(String, i64)> {
+    tracing::debug!(
+        "Computing {} checksum for {}.{}",
+        options.algorithm,
+        schema,
+        table
+    );
 
     // Get all columns for the table
     let column_query = "
@@ -59,30 +209,54 @@ pub async fn compute_table_checksum(
         .map(|row| row.get::<_, String>(0))
         .collect();
 
-    // Build COALESCE expressions to handle NULLs
-    let coalesce_exprs: Vec<String> = columns
+    let key_query = "
+        SELECT a.attname::text
+        FROM pg_index i
+        JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+        WHERE i.indrelid = (quote_ident($1) || '.' || quote_ident($2))::regclass
+          AND i.indisprimary
+        ORDER BY array_position(i.indkey, a.attnum)
+    ";
+    let primary_key: Vec<String> = client
+        .query(key_query, &[&schema, &table])
+        .await
+        .context(format!(
+            "Failed to get primary key for {}.{}",
+            schema, table
+        ))?
         .iter()
-        .map(|col| format!("COALESCE(\"{}\"::text, '')", col))
+        .map(|row| row.get(0))
         .collect();
 
-    let concat_expr = coalesce_exprs.join(" || '|' || ");
-
-    // Build ORDER BY clause using all columns
-    let order_by: Vec<String> = columns.iter().map(|col| format!("\"{}\"", col)).collect();
-    let order_by_clause = order_by.join(", ");
+    let mut hashed = hashed_columns(&columns, &primary_key, options);
+    hashed.retain(|column| !exclusions.columns.contains(column));
+    if hashed.is_empty() {
+        anyhow::bail!(
+            "Every compared column of {}.{} is excluded; accept the whole table instead",
+            schema,
+            table
+        );
+    }
+    if options.key_columns_only && primary_key.is_empty() {
+        tracing::warn!(
+            "  ⚠ {}.{} has no primary key; hashing all columns",
+            schema,
+            table
+        );
+    }
+    let order_by = if primary_key.is_empty() {
+        &hashed
+    } else {
+        &primary_key
+    };
 
-    // Compute checksum: MD5 of all concatenated rows, ordered deterministically
-    let checksum_query = format!(
-        "SELECT
-            md5(string_agg(row_data, '' ORDER BY row_num)) as checksum,
-            COUNT(*) as row_count
-        FROM (
-            SELECT
-                {} as row_data,
-                ROW_NUMBER() OVER (ORDER BY {}) as row_num
-            FROM \"{}\".\"{}\"
-        ) t",
-        concat_expr, order_by_clause, schema, table
+    let checksum_query = checksum_sql(
+        schema,
+        table,
+        &hashed,
+        order_by,
+        options.algorithm,
+        exclusions.rows.as_deref(),
     );
 
     let result = client
@@ -110,18 +284,102 @@ pub async fn compute_table_checksum(
     Ok((checksum, row_count))
 }
 
+/// Columns to hash: all of them, or the primary key plus the timestamp column
+///
+/// Falls back to all columns when key-only mode is requested but the table has
+/// no primary key.
+fn hashed_columns(
+    columns: &[String],
+    primary_key: &[String],
+    options: &ChecksumOptions,
+) -> Vec<String> {
+    if !options.key_columns_only || primary_key.is_empty() {
+        return columns.to_vec();
+    }
+    let mut selected = primary_key.to_vec();
+    if columns.contains(&options.updated_at_column)
+        && !selected.contains(&options.updated_at_column)
+    {
+        selected.push(options.updated_at_column.clone());
+    }
+    selected
+}
+
+/// Build the server-side checksum query
+fn checksum_sql(
+    schema: &str,
+    table: &str,
+    columns: &[String],
+    order_by: &[String],
+    algorithm: ChecksumAlgorithm,
+    excluded_rows: Option<&str>,
+) -> String {
+    let row_expr = columns
+        .iter()
+        .map(|col| format!("COALESCE({}::text, '')", quote_ident(col)))
+        .collect::<Vec<_>>()
+        .join(" || '|' || ");
+    let order_by = order_by
+        .iter()
+        .map(|col| quote_ident(col))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let where_clause = excluded_rows
+        .map(|predicate| format!(" WHERE NOT COALESCE(({}), false)", predicate))
+        .unwrap_or_default();
+
+    format!(
+        "SELECT
+            {} as checksum,
+            COUNT(*) as row_count
+        FROM {}.{}{}",
+        algorithm.hash_sql(&format!(
+            "string_agg({}, '' ORDER BY {})",
+            algorithm.hash_sql(&format!("({})", row_expr)),
+            order_by
+        )),
+        quote_ident(schema),
+        quote_ident(table),
+        where_clause,
+    )
+}
+
 /// Compare a table between source and target databases
 pub async fn compare_tables(
     source_client: &Client,
     target_client: &Client,
     schema: &str,
     table: &str,
+    options: &ChecksumOptions,
+) -> Result<ChecksumResult> {
+    compare_tables_excluding(
+        source_client,
+        target_client,
+        schema,
+        table,
+        options,
+        &ChecksumExclusions::default(),
+    )
+    .await
+}
+
+/// Compare a table between source and target, leaving out the excluded columns and rows
+pub async fn compare_tables_excluding(
+    source_client: &Client,
+    target_client: &Client,
+    schema: &str,
+    table: &str,
+    options: &ChecksumOptions,
+    exclusions: &ChecksumExclusions,
 ) -> Result<ChecksumResult> {
     tracing::info!("Comparing table {}.{}", schema, table);
 
     // Compute checksums in parallel
-    let source_future = compute_table_checksum(source_client, schema, table);
-    let target_future = compute_table_checksum(target_client, schema, table);
+    let source_future =
+        compute_table_checksum_excluding(source_client, schema, table, options, exclusions);
+    let target_future =
+        compute_table_checksum_excluding(target_client, schema, table, options, exclusions);
 
     let (source_result, target_result) = tokio::try_join!(source_future, target_future)?;
 
@@ -206,8 +464,14 @@ mod tests {
         let target_client = connect(&target_url).await.unwrap();
 
         // Compare a system table that should exist on both
-        let result =
-            compare_tables(&source_client, &target_client, "pg_catalog", "pg_database").await;
+        let result = compare_tables(
+            &source_client,
+            &target_client,
+            "pg_catalog",
+            "pg_database",
+            &ChecksumOptions::default(),
+        )
+        .await;
 
         match &result {
             Ok(comparison) => {
@@ -249,4 +513,73 @@ mod tests {
         assert_eq!(rows1, rows2);
         println!("✓ Checksum is deterministic: {}", checksum1);
     }
+
+    #[test]
+    fn test_checksum_algorithm_parse() {
+        assert_eq!(
+            "sha256".parse::<ChecksumAlgorithm>().unwrap(),
+            ChecksumAlgorithm::Sha256
+        );
+        assert_eq!(
+            "MD5".parse::<ChecksumAlgorithm>().unwrap(),
+            ChecksumAlgorithm::Md5
+        );
+        assert!("xxhash".parse::<ChecksumAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn test_hashed_columns_key_only() {
+        let columns: Vec<String> = ["id", "tenant", "payload", "updated_at"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let key = vec!["tenant".to_string(), "id".to_string()];
+        let mut options = ChecksumOptions::default();
+
+        assert_eq!(hashed_columns(&columns, &key, &options), columns);
+
+        options.key_columns_only = true;
+        assert_eq!(
+            hashed_columns(&columns, &key, &options),
+            vec!["tenant", "id", "updated_at"]
+        );
+
+        // No timestamp column: key only; no primary key: all columns
+        options.updated_at_column = "modified".to_string();
+        assert_eq!(
+            hashed_columns(&columns, &key, &options),
+            vec!["tenant", "id"]
+        );
+        assert_eq!(hashed_columns(&columns, &[], &options), columns);
+    }
+
+    #[test]
+    fn test_checksum_sql_is_server_side_and_ordered() {
+        let sql = checksum_sql(
+            "public",
+            "Orders",
+            &["id".to_string(), "total".to_string()],
+            &["id".to_string()],
+            ChecksumAlgorithm::Sha256,
+            None,
+        );
+        assert!(sql.contains("string_agg(encode(sha256(convert_to((COALESCE(\"id\"::text, '') || '|' || COALESCE(\"total\"::text, '')), 'UTF8')), 'hex'), '' ORDER BY \"id\")"));
+        assert!(sql.contains("FROM \"public\".\"Orders\""));
+        assert!(!sql.contains("WHERE"));
+    }
+
+    #[test]
+    fn test_checksum_sql_excludes_accepted_rows() {
+        let sql = checksum_sql(
+            "public",
+            "orders",
+            &["id".to_string()],
+            &["id".to_string()],
+            ChecksumAlgorithm::Md5,
+            Some("(id < 100) OR (legacy)"),
+        );
+        assert!(sql.ends_with(
+            "FROM \"public\".\"orders\" WHERE NOT COALESCE(((id < 100) OR (legacy)), false)"
+        ));
+    }
 }
@@ -0,0 +1,206 @@
+// ABOUTME: Schedules the tables of the init data restore by table-rule priority, then by size
+// ABOUTME: Reads the dump's table of contents so business-critical tables are restored first
+
+use super::manifest::Manifest;
+use crate::table_rules::{CopyOrder, TableRules};
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+/// One line of `pg_restore --list`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    /// The line as `pg_restore --use-list` expects it back
+    pub line: String,
+    pub dump_id: u32,
+    /// Schema and table of a `TABLE DATA` entry
+    pub table: Option<(String, String)>,
+}
+
+/// Parse the output of `pg_restore --list`, skipping its comment lines
+pub fn parse_toc(listing: &str) -> Vec<TocEntry> {
+    listing
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with(';'))
+        .filter_map(|line| {
+            let (id, rest) = line.split_once(';')?;
+            let dump_id = id.trim().parse().ok()?;
+            // "<catalog oid> <object oid> TABLE DATA <schema> <table> <owner>"
+            let description = rest.trim().splitn(3, ' ').nth(2)?;
+            let table = description.strip_prefix("TABLE DATA ").and_then(|names| {
+                // Names with spaces are ambiguous here; those tables keep the default slot
+                match names.split(' ').collect::<Vec<_>>()[..] {
+                    [schema, table, _owner] => Some((schema.to_string(), table.to_string())),
+                    _ => None,
+                }
+            });
+            Some(TocEntry {
+                line: line.to_string(),
+                dump_id,
+                table,
+            })
+        })
+        .collect()
+}
+
+/// A table of the data restore with its place in the schedule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledTable {
+    pub entry: TocEntry,
+    pub schema: String,
+    pub table: String,
+    pub priority: i64,
+    /// Size of the table's data files in the dump
+    pub dumped_bytes: u64,
+}
+
+/// Order the dump's tables for restore
+///
+/// Higher priorities come first; tables of equal priority follow the copy
+/// order, keeping the dump's order for [`CopyOrder::Default`].
+///
+/// # Returns
+///
+/// The tables in restore order, and the remaining entries (sequence values,
+/// large objects, entries whose name could not be read) to restore after them.
+pub fn schedule(
+    entries: Vec<TocEntry>,
+    database: &str,
+    rules: &TableRules,
+    manifest: &Manifest,
+) -> (Vec<ScheduledTable>, Vec<TocEntry>) {
+    let mut tables = Vec::new();
+    let mut rest = Vec::new();
+    for entry in entries {
+        let Some((schema, table)) = entry.table.clone() else {
+            rest.push(entry);
+            continue;
+        };
+        tables.push(ScheduledTable {
+            priority: rules.priority(database, &schema, &table),
+            dumped_bytes: dumped_bytes(manifest, entry.dump_id),
+            entry,
+            schema,
+            table,
+        });
+    }
+    let order = rules.copy_order();
+    tables.sort_by(|a, b| {
+        b.priority.cmp(&a.priority).then(match order {
+            CopyOrder::Default => std::cmp::Ordering::Equal,
+            CopyOrder::SmallestFirst => a.dumped_bytes.cmp(&b.dumped_bytes),
+            CopyOrder::LargestFirst => b.dumped_bytes.cmp(&a.dumped_bytes),
+        })
+    });
+    (tables, rest)
+}
+
+/// Size of a TOC entry's data file (`<id>.dat`, `<id>.dat.gz`, ...)
+fn dumped_bytes(manifest: &Manifest, dump_id: u32) -> u64 {
+    let prefix = format!("{}.dat", dump_id);
+    manifest
+        .files
+        .iter()
+        .filter(|file| file.path == prefix || file.path.starts_with(&format!("{}.", prefix)))
+        .map(|file| file.size)
+        .sum()
+}
+
+/// The table of contents of a directory-format dump (`pg_restore --list`)
+///
+/// # Errors
+///
+/// Returns an error if pg_restore cannot be run or cannot read the dump.
+pub fn list_contents(input_path: &str) -> Result<String> {
+    let output = Command::new("pg_restore")
+        .arg("--list")
+        .arg("--format=directory")
+        .arg(input_path)
+        .output()
+        .context("Failed to execute pg_restore --list. Is PostgreSQL client installed?")?;
+    if !output.status.success() {
+        bail!(
+            "pg_restore --list failed for {}: {}\n\
+             The dump may be incomplete; re-run init to dump the database again.",
+            input_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::manifest::ManifestEntry;
+    use crate::table_rules::QualifiedTable;
+
+    const LISTING: &str = "\
+;
+; Archive created at 2026-03-01 02:30:00 UTC
+;
+3401; 0 16390 TABLE DATA public orders postgres
+3402; 0 16395 TABLE DATA public audit_log postgres
+3403; 0 16399 TABLE DATA billing invoices postgres
+3404; 0 0 SEQUENCE SET public orders_id_seq postgres
+";
+
+    fn manifest() -> Manifest {
+        let file = |path: &str, size| ManifestEntry {
+            path: path.to_string(),
+            size,
+            sha256: String::new(),
+        };
+        Manifest {
+            version: 1,
+            files: vec![
+                file("3401.dat.gz", 500),
+                file("3402.dat.gz", 9_000),
+                file("3403.dat.gz", 20),
+                file("toc.dat", 4_000),
+            ],
+        }
+    }
+
+    fn names(tables: &[ScheduledTable]) -> Vec<&str> {
+        tables.iter().map(|t| t.table.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_toc_reads_table_data_entries() {
+        let entries = parse_toc(LISTING);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].dump_id, 3401);
+        assert_eq!(
+            entries[2].table,
+            Some(("billing".to_string(), "invoices".to_string()))
+        );
+        assert_eq!(entries[3].table, None);
+        assert_eq!(
+            entries[3].line,
+            "3404; 0 0 SEQUENCE SET public orders_id_seq postgres"
+        );
+    }
+
+    #[test]
+    fn test_schedule_by_priority_then_copy_order() {
+        let mut rules = TableRules::default();
+        let (tables, rest) = schedule(parse_toc(LISTING), "app", &rules, &manifest());
+        assert_eq!(names(&tables), vec!["orders", "audit_log", "invoices"]);
+        assert_eq!(rest.len(), 1);
+
+        rules.set_copy_order(CopyOrder::SmallestFirst);
+        let (tables, _) = schedule(parse_toc(LISTING), "app", &rules, &manifest());
+        assert_eq!(names(&tables), vec!["invoices", "orders", "audit_log"]);
+        assert_eq!(tables[2].dumped_bytes, 9_000);
+
+        rules
+            .add_priority(QualifiedTable::parse("app.public.audit_log").unwrap(), 5)
+            .unwrap();
+        rules
+            .add_priority(QualifiedTable::parse("billing.invoices").unwrap(), -1)
+            .unwrap();
+        rules.set_copy_order(CopyOrder::LargestFirst);
+        let (tables, _) = schedule(parse_toc(LISTING), "app", &rules, &manifest());
+        assert_eq!(names(&tables), vec!["audit_log", "orders", "invoices"]);
+    }
+}
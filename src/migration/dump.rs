@@ -1,14 +1,33 @@
 // ABOUTME: Wrapper for pg_dump command to export database objects
 // ABOUTME: Handles global objects, schema, and data export
 
+use crate::cancel::CancellationToken;
 use crate::filters::ReplicationFilter;
+use crate::settings::RunSettings;
+use crate::utils::{quote_ident, quote_literal, quote_qualified};
 use anyhow::{Context, Result};
 use std::collections::BTreeSet;
 use std::process::{Command, Stdio};
-use std::time::Duration;
 
 /// Dump global objects (roles, tablespaces) using pg_dumpall
-pub async fn dump_globals(source_url: &str, output_path: &str) -> Result<()> {
+///
+/// Without `pg_dumpall` in PATH, the globals are rebuilt from catalog queries
+/// over a native connection instead (see [`synthesize_globals`]).
+pub async fn dump_globals(
+    source_url: &str,
+    output_path: &str,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    if which::which("pg_dumpall").is_err() {
+        tracing::warn!("⚠ pg_dumpall not found; rebuilding global objects from catalog queries");
+        return crate::cancel::run(
+            cancel,
+            synthesize_globals(source_url, output_path, settings),
+        )
+        .await;
+    }
+
     tracing::info!("Dumping global objects to {}", output_path);
 
     // Parse URL and create .pgpass file for secure authentication
@@ -53,7 +72,7 @@ pub async fn dump_globals(source_url: &str, output_path: &str) -> Result<()> {
                 cmd.env(env_var, value);
             }
 
-            cmd.status().context(
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
                 "Failed to execute pg_dumpall. Is PostgreSQL client installed?\n\
                  Install with:\n\
                  - Ubuntu/Debian: sudo apt-get install postgresql-client\n\
@@ -61,8 +80,7 @@ pub async fn dump_globals(source_url: &str, output_path: &str) -> Result<()> {
                  - RHEL/CentOS: sudo yum install postgresql",
             )
         },
-        3,                      // Max 3 retries
-        Duration::from_secs(1), // Start with 1 second delay
+        &settings.retry,
         "pg_dumpall (dump globals)",
     )
     .context(
@@ -70,7 +88,8 @@ pub async fn dump_globals(source_url: &str, output_path: &str) -> Result<()> {
          \n\
          Common causes:\n\
          - Connection authentication failed\n\
-         - User lacks sufficient privileges (need SUPERUSER or pg_read_all_settings role)\n\
+         - User lacks sufficient privileges (need SUPERUSER or pg_read_all_settings role;\n\
+           on RDS/Aurora, use the master user)\n\
          - Network connectivity issues\n\
          - Invalid connection string\n\
          - Connection timeout or network issues",
@@ -80,12 +99,253 @@ pub async fn dump_globals(source_url: &str, output_path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Write a globals script equivalent to `pg_dumpall --globals-only --no-role-passwords`
+/// from catalog queries, for hosts without `pg_dumpall`
+///
+/// Covers roles with their attributes, comments, and settings; role
+/// memberships; and tablespaces. The statements use the same shape as
+/// pg_dumpall's, so the RDS and target-profile rewrites apply unchanged.
+///
+/// Fidelity limits compared with pg_dumpall: security labels, per-database
+/// role settings, membership `INHERIT`/`SET` options (PostgreSQL 16+), and
+/// tablespace options and grants are not reproduced. Role passwords are never
+/// copied either way.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be reached or its catalogs read, or
+/// the script cannot be written.
+pub async fn synthesize_globals(
+    source_url: &str,
+    output_path: &str,
+    settings: &RunSettings,
+) -> Result<()> {
+    tracing::info!(
+        "Rebuilding global objects from source catalogs into {}",
+        output_path
+    );
+    let client = crate::postgres::connect_with_retry(source_url, settings)
+        .await
+        .context("Failed to connect to source database to read roles and tablespaces")?;
+
+    let mut script = String::from(
+        "--\n-- Global objects rebuilt from catalog queries (pg_dumpall was not available)\n--\n\n",
+    );
+
+    let roles = client
+        .query(
+            "SELECT rolname::text, rolsuper, rolinherit, rolcreaterole, rolcreatedb,
+                    rolcanlogin, rolreplication, rolbypassrls, rolconnlimit,
+                    rolvaliduntil::text, shobj_description(oid, 'pg_authid'),
+                    COALESCE(rolconfig, '{}')
+             FROM pg_roles
+             WHERE rolname !~ '^pg_'
+             ORDER BY 1",
+            &[],
+        )
+        .await
+        .context("Failed to read roles from pg_roles")?;
+    for row in &roles {
+        let name: String = row.get(0);
+        let role = GlobalRole {
+            name: name.clone(),
+            superuser: row.get(1),
+            inherit: row.get(2),
+            create_role: row.get(3),
+            create_db: row.get(4),
+            login: row.get(5),
+            replication: row.get(6),
+            bypass_rls: row.get(7),
+            connection_limit: row.get(8),
+            valid_until: row.get(9),
+        };
+        script.push_str(&role.to_sql());
+        if let Some(comment) = row.get::<_, Option<String>>(10) {
+            script.push_str(&format!(
+                "COMMENT ON ROLE {} IS {};\n",
+                quote_ident(&name),
+                quote_literal(&comment)
+            ));
+        }
+        for setting in row.get::<_, Vec<String>>(11) {
+            if let Some(sql) = role_setting_sql(&name, &setting) {
+                script.push_str(&sql);
+            }
+        }
+    }
+    script.push('\n');
+
+    let memberships = client
+        .query(
+            "SELECT r.rolname::text, m.rolname::text, a.admin_option, g.rolname::text
+             FROM pg_auth_members a
+             JOIN pg_roles r ON r.oid = a.roleid
+             JOIN pg_roles m ON m.oid = a.member
+             LEFT JOIN pg_roles g ON g.oid = a.grantor
+             WHERE m.rolname !~ '^pg_'
+             ORDER BY 1, 2",
+            &[],
+        )
+        .await
+        .context("Failed to read role memberships from pg_auth_members")?;
+    for row in &memberships {
+        let mut grant = format!(
+            "GRANT {} TO {}",
+            quote_ident(row.get(0)),
+            quote_ident(row.get(1))
+        );
+        if row.get::<_, bool>(2) {
+            grant.push_str(" WITH ADMIN OPTION");
+        }
+        if let Some(grantor) = row.get::<_, Option<&str>>(3) {
+            grant.push_str(&format!(" GRANTED BY {}", quote_ident(grantor)));
+        }
+        script.push_str(&grant);
+        script.push_str(";\n");
+    }
+    script.push('\n');
+
+    let tablespaces = client
+        .query(
+            "SELECT spcname::text, pg_get_userbyid(spcowner)::text, pg_tablespace_location(oid)
+             FROM pg_tablespace
+             WHERE spcname !~ '^pg_'
+             ORDER BY 1",
+            &[],
+        )
+        .await
+        .context("Failed to read tablespaces from pg_tablespace")?;
+    for row in &tablespaces {
+        script.push_str(&format!(
+            "CREATE TABLESPACE {} OWNER {} LOCATION {};\n",
+            quote_ident(row.get(0)),
+            quote_ident(row.get(1)),
+            quote_literal(row.get(2))
+        ));
+    }
+
+    std::fs::write(output_path, script)
+        .with_context(|| format!("Failed to write globals script {}", output_path))?;
+    tracing::info!(
+        "✓ Rebuilt {} role(s), {} membership(s), and {} tablespace(s) from catalogs",
+        roles.len(),
+        memberships.len(),
+        tablespaces.len()
+    );
+    tracing::warn!(
+        "⚠ Security labels, per-database role settings, and tablespace options are not included without pg_dumpall"
+    );
+    Ok(())
+}
+
+/// A role's attributes as read from `pg_roles`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GlobalRole {
+    name: String,
+    superuser: bool,
+    inherit: bool,
+    create_role: bool,
+    create_db: bool,
+    login: bool,
+    replication: bool,
+    bypass_rls: bool,
+    connection_limit: i32,
+    valid_until: Option<String>,
+}
+
+impl GlobalRole {
+    /// `CREATE ROLE` and `ALTER ROLE ... WITH`, as pg_dumpall writes them
+    fn to_sql(&self) -> String {
+        let flag = |on: bool, name: &str| {
+            if on {
+                name.to_string()
+            } else {
+                format!("NO{}", name)
+            }
+        };
+        let mut attributes = [
+            flag(self.superuser, "SUPERUSER"),
+            flag(self.inherit, "INHERIT"),
+            flag(self.create_role, "CREATEROLE"),
+            flag(self.create_db, "CREATEDB"),
+            flag(self.login, "LOGIN"),
+            flag(self.replication, "REPLICATION"),
+            flag(self.bypass_rls, "BYPASSRLS"),
+        ]
+        .join(" ");
+        if self.connection_limit != -1 {
+            attributes.push_str(&format!(" CONNECTION LIMIT {}", self.connection_limit));
+        }
+        if let Some(valid_until) = &self.valid_until {
+            attributes.push_str(&format!(" VALID UNTIL {}", quote_literal(valid_until)));
+        }
+        let name = quote_ident(&self.name);
+        format!(
+            "CREATE ROLE {};\nALTER ROLE {} WITH {};\n",
+            name, name, attributes
+        )
+    }
+}
+
+/// Settings whose value is a list of separately quoted items
+const LIST_SETTINGS: [&str; 5] = [
+    "search_path",
+    "temp_tablespaces",
+    "local_preload_libraries",
+    "session_preload_libraries",
+    "shared_preload_libraries",
+];
+
+/// `ALTER ROLE ... SET` for one `rolconfig` entry such as `search_path=app, public`
+fn role_setting_sql(role: &str, setting: &str) -> Option<String> {
+    let (name, value) = setting.split_once('=')?;
+    let value = if LIST_SETTINGS.contains(&name) {
+        value
+            .split(',')
+            .map(|item| {
+                let item = item.trim();
+                let item = match item.strip_prefix('"').and_then(|i| i.strip_suffix('"')) {
+                    Some(quoted) => quoted.replace("\"\"", "\""),
+                    None => item.to_string(),
+                };
+                quote_literal(&item)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        quote_literal(value)
+    };
+    Some(format!(
+        "ALTER ROLE {} SET {} TO {};\n",
+        quote_ident(role),
+        name,
+        value
+    ))
+}
+
+/// Part of the schema `dump_schema` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaSection {
+    /// Everything (`--schema-only`)
+    All,
+    /// Definitions needed before data loads: tables, types, functions, views
+    PreData,
+    /// Indexes, constraints, triggers, and rules, created after data loads
+    ///
+    /// Dumped with `--clean --if-exists`, so each object is dropped if present
+    /// before it is created and a partly applied restore can be run again.
+    PostData,
+}
+
 /// Dump schema (DDL) for a specific database
 pub async fn dump_schema(
     source_url: &str,
     database: &str,
     output_path: &str,
     filter: &ReplicationFilter,
+    section: SchemaSection,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     tracing::info!(
         "Dumping schema for database '{}' to {}",
@@ -110,11 +370,24 @@ pub async fn dump_schema(
     crate::utils::retry_subprocess_with_backoff(
         || {
             let mut cmd = Command::new("pg_dump");
-            cmd.arg("--schema-only")
-                .arg("--no-owner") // Don't include ownership commands
+            cmd.arg("--no-owner") // Don't include ownership commands
                 .arg("--no-privileges") // We'll handle privileges separately
                 .arg("--verbose"); // Show progress
 
+            match section {
+                SchemaSection::All => {
+                    cmd.arg("--schema-only");
+                }
+                SchemaSection::PreData => {
+                    cmd.arg("--section=pre-data");
+                }
+                SchemaSection::PostData => {
+                    cmd.arg("--section=post-data")
+                        .arg("--clean")
+                        .arg("--if-exists");
+                }
+            }
+
             // Add table filtering if specified
             // Only exclude explicit exclude_tables from schema dump (NOT schema_only or predicate tables)
             if let Some(ref exclude) = exclude_tables {
@@ -160,7 +433,7 @@ pub async fn dump_schema(
                 cmd.env(env_var, value);
             }
 
-            cmd.status().context(
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
                 "Failed to execute pg_dump. Is PostgreSQL client installed?\n\
                  Install with:\n\
                  - Ubuntu/Debian: sudo apt-get install postgresql-client\n\
@@ -168,8 +441,7 @@ pub async fn dump_schema(
                  - RHEL/CentOS: sudo yum install postgresql",
             )
         },
-        3,                      // Max 3 retries
-        Duration::from_secs(1), // Start with 1 second delay
+        &settings.retry,
         "pg_dump (dump schema)",
     )
     .with_context(|| {
@@ -198,17 +470,24 @@ pub async fn dump_schema(
 /// - Large object (blob) support
 /// - Directory output for efficient parallel restore
 ///
-/// The number of parallel jobs is automatically determined based on available CPU cores.
+/// The number of parallel jobs comes from the global job budget
+/// (`--max-total-jobs`), defaulting to the available CPU cores capped at 8.
+///
+/// `split_tables` lists quoted `"schema"."table"` names whose data is copied
+/// separately in key ranges (see `migration::split`) and is left out of the dump.
+///
+/// A manifest with every file's size and SHA-256 is written next to the
+/// directory (see [`super::manifest`]) so `restore_data` can check it first.
 pub async fn dump_data(
     source_url: &str,
     database: &str,
     output_path: &str,
     filter: &ReplicationFilter,
+    split_tables: &[String],
+    settings: &RunSettings,
+    cancel: &CancellationToken,
 ) -> Result<()> {
-    // Determine optimal number of parallel jobs (number of CPUs, capped at 8)
-    let num_cpus = std::thread::available_parallelism()
-        .map(|n| n.get().min(8))
-        .unwrap_or(4);
+    let num_cpus = settings.jobs.dump_restore_jobs(&settings.memory);
 
     tracing::info!(
         "Dumping data for database '{}' to {} (parallel={}, compression=9, format=directory)",
@@ -227,7 +506,12 @@ pub async fn dump_data(
     let output_path_owned = output_path.to_string();
 
     // Collect filter options
-    let exclude_tables = get_data_excluded_tables_for_db(filter, database);
+    let mut exclude_tables = get_data_excluded_tables_for_db(filter, database);
+    if !split_tables.is_empty() {
+        exclude_tables
+            .get_or_insert_with(Vec::new)
+            .extend(split_tables.iter().cloned());
+    }
     let include_tables = get_included_tables_for_db(filter, database);
 
     // Wrap subprocess execution with retry logic
@@ -287,7 +571,7 @@ pub async fn dump_data(
                 cmd.env(env_var, value);
             }
 
-            cmd.status().context(
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
                 "Failed to execute pg_dump. Is PostgreSQL client installed?\n\
                  Install with:\n\
                  - Ubuntu/Debian: sudo apt-get install postgresql-client\n\
@@ -295,8 +579,7 @@ pub async fn dump_data(
                  - RHEL/CentOS: sudo yum install postgresql",
             )
         },
-        3,                      // Max 3 retries
-        Duration::from_secs(1), // Start with 1 second delay
+        &settings.retry,
         "pg_dump (dump data)",
     )
     .with_context(|| {
@@ -319,6 +602,8 @@ pub async fn dump_data(
         "✓ Data dumped successfully using {} parallel jobs",
         num_cpus
     );
+    let manifest = super::manifest::write_manifest(std::path::Path::new(output_path))?;
+    crate::resources::record_source_read(manifest.total_bytes());
     Ok(())
 }
 
@@ -332,16 +617,10 @@ fn get_schema_excluded_tables_for_db(
 ) -> Option<Vec<String>> {
     let mut tables = BTreeSet::new();
 
-    // Handle explicit exclude_tables (format: "database.table")
+    // Handle explicit exclude_tables ("database.table" or "database.schema.table")
     // These tables are completely excluded (no schema, no data)
-    if let Some(explicit) = filter.exclude_tables() {
-        for full_name in explicit {
-            let parts: Vec<&str> = full_name.split('.').collect();
-            if parts.len() == 2 && parts[0] == db_name {
-                // Format as "public"."table" for consistency
-                tables.insert(format!("\"public\".\"{}\"", parts[1]));
-            }
-        }
+    for (schema, table) in filter.excluded_tables_for_db(db_name) {
+        tables.insert(quote_qualified(&schema, &table));
     }
 
     if tables.is_empty() {
@@ -352,8 +631,8 @@ fn get_schema_excluded_tables_for_db(
 }
 
 /// Extract table names to exclude from DATA dumps (--exclude-table-data flag)
-/// Excludes explicit excludes, schema_only tables, and predicate tables
-/// (predicate tables will be copied separately with filtering)
+/// Excludes explicit excludes, schema_only tables, predicate tables, and transform
+/// tables (the last two are copied separately with filtering)
 /// Returns schema-qualified names in format: "schema"."table"
 fn get_data_excluded_tables_for_db(
     filter: &ReplicationFilter,
@@ -361,16 +640,9 @@ fn get_data_excluded_tables_for_db(
 ) -> Option<Vec<String>> {
     let mut tables = BTreeSet::new();
 
-    // Handle explicit exclude_tables (format: "database.table")
-    // Default to public schema for backward compatibility
-    if let Some(explicit) = filter.exclude_tables() {
-        for full_name in explicit {
-            let parts: Vec<&str> = full_name.split('.').collect();
-            if parts.len() == 2 && parts[0] == db_name {
-                // Format as "public"."table" for consistency
-                tables.insert(format!("\"public\".\"{}\"", parts[1]));
-            }
-        }
+    // Handle explicit exclude_tables ("database.table" defaults to public)
+    for (schema, table) in filter.excluded_tables_for_db(db_name) {
+        tables.insert(quote_qualified(&schema, &table));
     }
 
     // schema_only_tables and predicate_tables already return schema-qualified names
@@ -382,6 +654,11 @@ fn get_data_excluded_tables_for_db(
         tables.insert(table);
     }
 
+    // Transform tables are copied through their SELECT by migration::filtered
+    for (table, _) in filter.transform_tables(db_name) {
+        tables.insert(table);
+    }
+
     if tables.is_empty() {
         None
     } else {
@@ -389,21 +666,63 @@ fn get_data_excluded_tables_for_db(
     }
 }
 
+/// Header of one entry in a plain-format dump: `-- Name: x; Type: y; Schema: z; Owner: w`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct DumpEntryHeader<'a> {
+    pub name: &'a str,
+    pub object_type: &'a str,
+    pub schema: &'a str,
+}
+
+fn parse_entry_header(line: &str) -> Option<DumpEntryHeader<'_>> {
+    let rest = line.strip_prefix("-- Name: ")?;
+    let (name, rest) = rest.split_once("; Type: ")?;
+    let (object_type, rest) = rest.split_once("; Schema: ")?;
+    let schema = rest.split_once(';').map_or(rest, |(schema, _)| schema);
+    Some(DumpEntryHeader {
+        name,
+        object_type,
+        schema,
+    })
+}
+
+/// Split a plain-format `pg_dump` file into its entries
+///
+/// Each entry starts at the `--` line above a `-- Name:` header and runs until the
+/// next one. Text before the first entry (SET statements) has no header. Joining
+/// the returned slices reproduces the input exactly.
+pub(crate) fn dump_entries(sql: &str) -> Vec<(Option<DumpEntryHeader<'_>>, &str)> {
+    let mut starts = vec![0];
+    let mut offset = 0;
+    let mut previous: Option<(usize, &str)> = None;
+    for line in sql.split_inclusive('\n') {
+        if let Some((prev_offset, prev_line)) = previous {
+            if prev_line.trim_end() == "--" && line.starts_with("-- Name: ") && prev_offset > 0 {
+                starts.push(prev_offset);
+            }
+        }
+        previous = Some((offset, line));
+        offset += line.len();
+    }
+    starts.push(sql.len());
+    starts.dedup();
+    starts
+        .windows(2)
+        .map(|w| {
+            let section = &sql[w[0]..w[1]];
+            let header = section.lines().nth(1).and_then(parse_entry_header);
+            (header, section)
+        })
+        .collect()
+}
+
 /// Extract table names for a specific database from include_tables filter
 /// Returns schema-qualified names in format: "schema"."table"
 fn get_included_tables_for_db(filter: &ReplicationFilter, db_name: &str) -> Option<Vec<String>> {
-    filter.include_tables().map(|tables| {
+    filter.included_tables_for_db(db_name).map(|tables| {
         tables
             .iter()
-            .filter_map(|full_name| {
-                let parts: Vec<&str> = full_name.split('.').collect();
-                if parts.len() == 2 && parts[0] == db_name {
-                    // Format as "public"."table" for consistency
-                    Some(format!("\"public\".\"{}\"", parts[1]))
-                } else {
-                    None
-                }
-            })
+            .map(|(schema, table)| quote_qualified(schema, table))
             .collect()
     })
 }
@@ -413,6 +732,50 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_global_role_sql_matches_pg_dumpall() {
+        let role = GlobalRole {
+            name: "app".to_string(),
+            superuser: false,
+            inherit: true,
+            create_role: false,
+            create_db: false,
+            login: true,
+            replication: false,
+            bypass_rls: false,
+            connection_limit: 10,
+            valid_until: Some("2030-01-01 00:00:00+00".to_string()),
+        };
+        assert_eq!(
+            role.to_sql(),
+            "CREATE ROLE \"app\";\n\
+             ALTER ROLE \"app\" WITH NOSUPERUSER INHERIT NOCREATEROLE NOCREATEDB LOGIN NOREPLICATION NOBYPASSRLS \
+             CONNECTION LIMIT 10 VALID UNTIL '2030-01-01 00:00:00+00';\n"
+        );
+        // The RDS rewrite still recognizes synthesized statements
+        let (_, removed) = crate::postgres::flavor::strip_rds_internal_roles(
+            &GlobalRole {
+                name: "rdsadmin".to_string(),
+                ..role
+            }
+            .to_sql(),
+        );
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn test_role_setting_sql() {
+        assert_eq!(
+            role_setting_sql("app", "statement_timeout=30s").as_deref(),
+            Some("ALTER ROLE \"app\" SET statement_timeout TO '30s';\n")
+        );
+        assert_eq!(
+            role_setting_sql("app", "search_path=\"$user\", app, \"My Schema\"").as_deref(),
+            Some("ALTER ROLE \"app\" SET search_path TO '$user', 'app', 'My Schema';\n")
+        );
+        assert_eq!(role_setting_sql("app", "garbage"), None);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_dump_globals() {
@@ -420,7 +783,13 @@ mod tests {
         let dir = tempdir().unwrap();
         let output = dir.path().join("globals.sql");
 
-        let result = dump_globals(&url, output.to_str().unwrap()).await;
+        let result = dump_globals(
+            &url,
+            output.to_str().unwrap(),
+            &Default::default(),
+            &CancellationToken::new(),
+        )
+        .await;
 
         assert!(result.is_ok());
         assert!(output.exists());
@@ -441,7 +810,16 @@ mod tests {
         let db = url.split('/').next_back().unwrap_or("postgres");
 
         let filter = crate::filters::ReplicationFilter::empty();
-        let result = dump_schema(&url, db, output.to_str().unwrap(), &filter).await;
+        let result = dump_schema(
+            &url,
+            db,
+            output.to_str().unwrap(),
+            &filter,
+            SchemaSection::All,
+            &Default::default(),
+            &CancellationToken::new(),
+        )
+        .await;
 
         assert!(result.is_ok());
         assert!(output.exists());
@@ -553,4 +931,31 @@ mod tests {
         let tables = get_included_tables_for_db(&filter, "db1");
         assert!(tables.is_none());
     }
+
+    #[test]
+    fn test_table_args_quote_mixed_case_dotted_and_reserved_names() {
+        let filter = crate::filters::ReplicationFilter::new(
+            None,
+            None,
+            Some(vec![
+                "db1.Orders".to_string(),
+                r#"db1."order.items""#.to_string(),
+                "db1.Sales.user".to_string(),
+                r#"db1.public."odd""name""#.to_string(),
+            ]),
+            None,
+        )
+        .unwrap();
+
+        let tables = get_included_tables_for_db(&filter, "db1").unwrap();
+        assert_eq!(
+            tables,
+            vec![
+                r#""public"."Orders""#,
+                r#""public"."order.items""#,
+                r#""Sales"."user""#,
+                r#""public"."odd""name""#,
+            ]
+        );
+    }
 }
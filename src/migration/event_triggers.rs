@@ -0,0 +1,413 @@
+// ABOUTME: Detection of event triggers and deferral of their creation until cutover
+// ABOUTME: Records on the target whether triggers were restored, deferred, or recreated
+
+use super::dump::dump_entries;
+use crate::replication::mode::{database_settings, lookup_setting};
+use crate::utils::{quote_ident, quote_literal};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt;
+use tokio_postgres::Client;
+
+/// Database-level setting that records what init did with the source's event triggers
+pub const EVENT_TRIGGERS_SETTING: &str = "seren_replicator.event_triggers";
+
+/// Database-level setting holding the script that recreates deferred event triggers
+pub const DEFERRED_SCRIPT_SETTING: &str = "seren_replicator.deferred_event_triggers";
+
+/// What to do with event triggers found in the source schema
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventTriggerPolicy {
+    /// Restore event triggers with the schema
+    #[default]
+    Keep,
+    /// Leave event triggers out of the schema restore and create them at cutover
+    /// (`run-hooks --stage pre-cutover`)
+    Defer,
+}
+
+/// `[event_triggers]` section of replication-config.toml
+///
+/// ```toml
+/// [event_triggers]
+/// policy = "defer"
+/// ```
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct EventTriggerConfig {
+    #[serde(default)]
+    pub policy: EventTriggerPolicy,
+}
+
+/// What happened to a database's event triggers, as recorded on the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTriggerDecision {
+    /// Restored with the schema during init
+    Restored,
+    /// Left out of the schema restore; waiting for the pre-cutover stage
+    Deferred,
+    /// Created on the target by the pre-cutover stage
+    Recreated,
+}
+
+impl EventTriggerDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventTriggerDecision::Restored => "restored",
+            EventTriggerDecision::Deferred => "deferred",
+            EventTriggerDecision::Recreated => "recreated",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "restored" => Some(EventTriggerDecision::Restored),
+            "deferred" => Some(EventTriggerDecision::Deferred),
+            "recreated" => Some(EventTriggerDecision::Recreated),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EventTriggerDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An event trigger and the function it executes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTrigger {
+    pub name: String,
+    /// Event name (ddl_command_start, ddl_command_end, sql_drop, table_rewrite, ...)
+    pub event: String,
+    /// Quoted, schema-qualified function name
+    pub function: String,
+    /// Command tags from `WHEN TAG IN (...)`, empty for all commands
+    pub tags: Vec<String>,
+    /// `pg_event_trigger.evtenabled`: O (origin), R (replica), A (always), D (disabled)
+    pub enabled: String,
+}
+
+impl EventTrigger {
+    /// Statements that recreate the trigger, including its firing mode
+    pub fn create_sql(&self) -> String {
+        let mut sql = format!(
+            "CREATE EVENT TRIGGER {} ON {}",
+            quote_ident(&self.name),
+            self.event
+        );
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|t| quote_literal(t)).collect();
+            sql.push_str(&format!(" WHEN TAG IN ({})", tags.join(", ")));
+        }
+        sql.push_str(&format!(" EXECUTE FUNCTION {}();", self.function));
+
+        let mode = match self.enabled.as_str() {
+            "D" => Some("DISABLE"),
+            "R" => Some("ENABLE REPLICA"),
+            "A" => Some("ENABLE ALWAYS"),
+            _ => None,
+        };
+        if let Some(mode) = mode {
+            sql.push_str(&format!(
+                "\nALTER EVENT TRIGGER {} {};",
+                quote_ident(&self.name),
+                mode
+            ));
+        }
+        sql
+    }
+
+    fn describe_enabled(&self) -> &'static str {
+        match self.enabled.as_str() {
+            "D" => "disabled",
+            "R" => "replica",
+            "A" => "always",
+            _ => "origin",
+        }
+    }
+}
+
+/// Read the event triggers of a database, skipping those created by extensions
+///
+/// # Errors
+///
+/// Returns an error if the catalog query fails.
+pub async fn collect_event_triggers(client: &Client) -> Result<Vec<EventTrigger>> {
+    let rows = client
+        .query(
+            "SELECT e.evtname::text, e.evtevent::text, n.nspname::text, p.proname::text,
+                    COALESCE(e.evttags, '{}'), e.evtenabled::text
+             FROM pg_event_trigger e
+             JOIN pg_proc p ON p.oid = e.evtfoid
+             JOIN pg_namespace n ON n.oid = p.pronamespace
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM pg_depend d
+                 WHERE d.classid = 'pg_event_trigger'::regclass
+                   AND d.objid = e.oid AND d.deptype = 'e')
+             ORDER BY 1",
+            &[],
+        )
+        .await
+        .context("Failed to query event triggers")?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let schema: String = row.get(2);
+            let function: String = row.get(3);
+            EventTrigger {
+                name: row.get(0),
+                event: row.get(1),
+                function: format!("{}.{}", quote_ident(&schema), quote_ident(&function)),
+                tags: row.get(4),
+                enabled: row.get(5),
+            }
+        })
+        .collect())
+}
+
+/// Script that recreates all given triggers
+pub fn recreate_script(triggers: &[EventTrigger]) -> String {
+    triggers
+        .iter()
+        .map(EventTrigger::create_sql)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Log the event triggers found in a database and what will happen to them
+pub fn log_report(database: &str, triggers: &[EventTrigger], policy: EventTriggerPolicy) {
+    tracing::info!(
+        "ℹ Database '{}' has {} event trigger(s):",
+        database,
+        triggers.len()
+    );
+    for trigger in triggers {
+        tracing::info!(
+            "  {} on {} -> {}() [{}]",
+            trigger.name,
+            trigger.event,
+            trigger.function,
+            trigger.describe_enabled()
+        );
+    }
+    match policy {
+        EventTriggerPolicy::Keep => tracing::info!("  Restoring them with the schema"),
+        EventTriggerPolicy::Defer => {
+            tracing::info!("  Deferring them until cutover (run-hooks --stage pre-cutover)")
+        }
+    }
+}
+
+/// Remove `EVENT TRIGGER` entries (and their comments) from a plain-format schema dump
+///
+/// # Returns
+///
+/// The rewritten SQL and the number of entries removed.
+pub fn strip_event_triggers(sql: &str) -> (String, usize) {
+    let mut output = String::with_capacity(sql.len());
+    let mut removed = 0;
+    for (header, section) in dump_entries(sql) {
+        let is_event_trigger = header.is_some_and(|h| {
+            h.object_type == "EVENT TRIGGER" || h.name.starts_with("EVENT TRIGGER ")
+        });
+        if is_event_trigger {
+            removed += 1;
+        } else {
+            output.push_str(section);
+        }
+    }
+    (output, removed)
+}
+
+/// Record on the target what init did with a database's event triggers
+///
+/// With `Deferred`, `script` is stored so the pre-cutover stage can recreate the
+/// triggers from any machine. Other decisions clear a previously stored script.
+///
+/// # Arguments
+///
+/// * `client` - Client connected to the target cluster (any database)
+/// * `db_name` - Name of the target database
+/// * `decision` - What happened to the triggers
+/// * `script` - Recreate script (deferred triggers only)
+///
+/// # Errors
+///
+/// Returns an error if the database name is invalid or the settings cannot be written.
+pub async fn record_decision(
+    client: &Client,
+    db_name: &str,
+    decision: EventTriggerDecision,
+    script: Option<&str>,
+) -> Result<()> {
+    crate::utils::validate_postgres_identifier(db_name)
+        .with_context(|| format!("Invalid database name: '{}'", db_name))?;
+
+    let db = quote_ident(db_name);
+    let script_sql = match script {
+        Some(script) => format!(
+            "ALTER DATABASE {} SET {} = {}",
+            db,
+            DEFERRED_SCRIPT_SETTING,
+            quote_literal(script)
+        ),
+        None => format!("ALTER DATABASE {} RESET {}", db, DEFERRED_SCRIPT_SETTING),
+    };
+    client
+        .batch_execute(&format!(
+            "ALTER DATABASE {} SET {} = '{}'; {}",
+            db, EVENT_TRIGGERS_SETTING, decision, script_sql
+        ))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to record event trigger decision for database '{}'",
+                db_name
+            )
+        })?;
+    Ok(())
+}
+
+/// Read the event trigger decision recorded for a target database
+///
+/// # Returns
+///
+/// `None` if init found no event triggers (or ran before this was recorded),
+/// otherwise the decision and the stored recreate script, if any.
+pub async fn get_decision(
+    client: &Client,
+    db_name: &str,
+) -> Result<Option<(EventTriggerDecision, Option<String>)>> {
+    let setconfig = database_settings(client, db_name).await?;
+    Ok(parse_decision(&setconfig))
+}
+
+fn parse_decision(setconfig: &[String]) -> Option<(EventTriggerDecision, Option<String>)> {
+    let decision = lookup_setting(setconfig, EVENT_TRIGGERS_SETTING)
+        .as_deref()
+        .and_then(EventTriggerDecision::parse)?;
+    Some((decision, lookup_setting(setconfig, DEFERRED_SCRIPT_SETTING)))
+}
+
+/// Create the event triggers deferred by init for one database
+///
+/// # Arguments
+///
+/// * `cluster_client` - Client connected to the target cluster (any database)
+/// * `db_client` - Client connected to the target database itself
+/// * `db_name` - Name of the target database
+///
+/// # Returns
+///
+/// `true` if deferred triggers were created, `false` if none were waiting.
+///
+/// # Errors
+///
+/// Returns an error if the script fails; the decision stays `deferred` so the
+/// stage can be run again.
+pub async fn recreate_deferred(
+    cluster_client: &Client,
+    db_client: &Client,
+    db_name: &str,
+) -> Result<bool> {
+    let Some((EventTriggerDecision::Deferred, Some(script))) =
+        get_decision(cluster_client, db_name).await?
+    else {
+        return Ok(false);
+    };
+
+    db_client.batch_execute(&script).await.with_context(|| {
+        format!(
+            "Failed to recreate deferred event triggers in '{}'.\n\
+             Creating event triggers requires superuser on the target.",
+            db_name
+        )
+    })?;
+    record_decision(
+        cluster_client,
+        db_name,
+        EventTriggerDecision::Recreated,
+        None,
+    )
+    .await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(enabled: &str, tags: &[&str]) -> EventTrigger {
+        EventTrigger {
+            name: "audit_ddl".to_string(),
+            event: "ddl_command_end".to_string(),
+            function: "\"public\".\"log_ddl\"".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            enabled: enabled.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_sql() {
+        assert_eq!(
+            trigger("O", &[]).create_sql(),
+            "CREATE EVENT TRIGGER \"audit_ddl\" ON ddl_command_end \
+             EXECUTE FUNCTION \"public\".\"log_ddl\"();"
+        );
+        assert_eq!(
+            trigger("A", &["CREATE TABLE", "ALTER TABLE"]).create_sql(),
+            "CREATE EVENT TRIGGER \"audit_ddl\" ON ddl_command_end \
+             WHEN TAG IN ('CREATE TABLE', 'ALTER TABLE') \
+             EXECUTE FUNCTION \"public\".\"log_ddl\"();\n\
+             ALTER EVENT TRIGGER \"audit_ddl\" ENABLE ALWAYS;"
+        );
+    }
+
+    #[test]
+    fn test_strip_event_triggers() {
+        let dump = "\
+SET lock_timeout = 0;
+
+--
+-- Name: t; Type: TABLE; Schema: public; Owner: -
+--
+
+CREATE TABLE public.t (id integer);
+
+
+--
+-- Name: audit_ddl; Type: EVENT TRIGGER; Schema: -; Owner: -
+--
+
+CREATE EVENT TRIGGER audit_ddl ON ddl_command_end
+   EXECUTE FUNCTION public.log_ddl();
+
+
+--
+-- Name: EVENT TRIGGER audit_ddl; Type: COMMENT; Schema: -; Owner: -
+--
+
+COMMENT ON EVENT TRIGGER audit_ddl IS 'audit';
+";
+        let (sql, removed) = strip_event_triggers(dump);
+        assert_eq!(removed, 2);
+        assert!(sql.contains("CREATE TABLE public.t"));
+        assert!(!sql.contains("EVENT TRIGGER"));
+    }
+
+    #[test]
+    fn test_parse_decision() {
+        assert_eq!(parse_decision(&[]), None);
+        let config = vec![
+            "seren_replicator.event_triggers=deferred".to_string(),
+            "seren_replicator.deferred_event_triggers=CREATE EVENT TRIGGER x ON sql_drop EXECUTE FUNCTION f();".to_string(),
+        ];
+        let (decision, script) = parse_decision(&config).unwrap();
+        assert_eq!(decision, EventTriggerDecision::Deferred);
+        assert!(script.unwrap().starts_with("CREATE EVENT TRIGGER x"));
+    }
+}
@@ -1,25 +1,25 @@
 // ABOUTME: Handles filtered table replication using COPY streaming
 // ABOUTME: Applies table-level predicates and time filters during init snapshots
 
+use crate::cancel::{self, CancellationToken};
 use crate::postgres;
+use crate::settings::RunSettings;
 use anyhow::{bail, Context, Result};
 use futures::{pin_mut, SinkExt, StreamExt};
 use std::collections::BTreeSet;
 use tokio_postgres::Client;
 
 /// Parse schema-qualified table name into (schema, table)
-/// Expects format: "schema"."table"
+/// Expects format: "schema"."table" (quotes keep dots and case literal)
 fn parse_schema_table(qualified: &str) -> Result<(String, String)> {
-    // Remove quotes and split on .
-    let parts: Vec<&str> = qualified.split('.').map(|s| s.trim_matches('"')).collect();
+    let parts = crate::utils::split_qualified_name(qualified)?;
 
-    if parts.len() == 2 {
-        Ok((parts[0].to_string(), parts[1].to_string()))
-    } else {
-        bail!(
+    match parts.as_slice() {
+        [schema, table] => Ok((schema.name.clone(), table.name.clone())),
+        _ => bail!(
             "Expected schema-qualified table name (\"schema\".\"table\"), got: {}",
             qualified
-        );
+        ),
     }
 }
 
@@ -63,19 +63,93 @@ async fn get_cascade_targets(
     Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
 }
 
+/// Quoted output column names of a transform query, in order
+///
+/// # Errors
+///
+/// Returns an error if the query does not parse on the source or repeats a
+/// column name.
+async fn transform_columns(client: &Client, table: &str, select: &str) -> Result<Vec<String>> {
+    let statement = client
+        .prepare(select)
+        .await
+        .with_context(|| format!("Transform query for '{}' is not valid on the source", table))?;
+    let mut seen = BTreeSet::new();
+    let mut columns = Vec::new();
+    for column in statement.columns() {
+        if !seen.insert(column.name()) {
+            bail!(
+                "Transform query for '{}' returns column '{}' more than once; \
+                 alias each output column to a distinct target column",
+                table,
+                column.name()
+            );
+        }
+        columns.push(crate::utils::quote_ident(column.name()));
+    }
+    if columns.is_empty() {
+        bail!("Transform query for '{}' returns no columns", table);
+    }
+    Ok(columns)
+}
+
+/// How one table is copied by [`copy_rule_tables`]
+enum CopySource<'a> {
+    /// `SELECT * FROM table WHERE predicate`, copied in binary format
+    Predicate(&'a str),
+    /// A user-supplied `SELECT`, copied in text format so expressions may change
+    /// column types; output columns are matched to the target table by name
+    Transform(&'a str),
+}
+
+/// Copy tables with WHERE-clause predicates, as (`"schema"."table"`, predicate)
 pub async fn copy_filtered_tables(
     source_url: &str,
     target_url: &str,
     tables: &[(String, String)],
+    settings: &RunSettings,
+    cancel: &CancellationToken,
 ) -> Result<()> {
+    copy_rule_tables(source_url, target_url, tables, &[], settings, cancel).await
+}
+
+/// Copy predicate-filtered and transformed tables into truncated target tables
+///
+/// # Arguments
+///
+/// * `predicates` - (`"schema"."table"`, WHERE predicate) pairs
+/// * `transforms` - (`"schema"."table"`, SELECT query) pairs; the query runs on
+///   the source and its output columns must name target columns
+///
+/// # Errors
+///
+/// Returns an error if truncating would cascade into a table that is not being
+/// copied, or if a query or COPY fails.
+pub async fn copy_rule_tables(
+    source_url: &str,
+    target_url: &str,
+    predicates: &[(String, String)],
+    transforms: &[(String, String)],
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let tables: Vec<(&String, CopySource)> = predicates
+        .iter()
+        .map(|(table, predicate)| (table, CopySource::Predicate(predicate)))
+        .chain(
+            transforms
+                .iter()
+                .map(|(table, select)| (table, CopySource::Transform(select))),
+        )
+        .collect();
     if tables.is_empty() {
         return Ok(());
     }
 
-    let source_client = postgres::connect_with_retry(source_url)
+    let source_client = postgres::connect_with_retry(source_url, settings)
         .await
         .context("Failed to connect to source database for filtered copy")?;
-    let target_client = postgres::connect_with_retry(target_url)
+    let target_client = postgres::connect_with_retry(target_url, settings)
         .await
         .context("Failed to connect to target database for filtered copy")?;
 
@@ -86,14 +160,14 @@ pub async fn copy_filtered_tables(
     );
 
     let mut all_cascade_targets = BTreeSet::new();
-    let table_names: BTreeSet<String> = tables.iter().map(|(t, _)| t.clone()).collect();
+    let table_names: BTreeSet<String> = tables.iter().map(|(t, _)| (*t).clone()).collect();
 
-    for (table, _) in tables {
+    for (table, _) in &tables {
         let (schema, table_name) = parse_schema_table(table)?;
         let targets = get_cascade_targets(&target_client, &schema, &table_name).await?;
 
         for (target_schema, target_table) in targets {
-            let qualified = format!("\"{}\".\"{}\"", target_schema, target_table);
+            let qualified = crate::utils::quote_qualified(&target_schema, &target_table);
             all_cascade_targets.insert((target_schema, target_table, qualified));
         }
     }
@@ -125,33 +199,59 @@ pub async fn copy_filtered_tables(
     }
 
     // Step 4: Proceed with TRUNCATE CASCADE and filtered copy
-    for (table, predicate) in tables {
-        tracing::info!(
-            "  Applying filtered copy for table '{}' with predicate: {}",
-            table,
-            predicate
-        );
+    for (table, source) in &tables {
+        cancel::check(cancel)?;
+        match source {
+            CopySource::Predicate(predicate) => tracing::info!(
+                "  Applying filtered copy for table '{}' with predicate: {}",
+                table,
+                predicate
+            ),
+            CopySource::Transform(select) => tracing::info!(
+                "  Applying transformed copy for table '{}': {}",
+                table,
+                select
+            ),
+        }
 
         // Table is already schema-qualified and quoted (e.g., "public"."table")
         let quoted_table = table;
 
         // Use TRUNCATE CASCADE to handle FK dependencies
+        crate::audit::record_in(
+            &settings.audit,
+            &target_client,
+            crate::audit::AuditAction::Truncate,
+            &format!("{} CASCADE", quoted_table),
+        )
+        .await;
         let truncate_sql = format!("TRUNCATE TABLE {} CASCADE", quoted_table);
         target_client
             .execute(&truncate_sql, &[])
             .await
             .with_context(|| format!("Failed to truncate target table '{}'", table))?;
 
-        let copy_out_sql = format!(
-            "COPY (SELECT * FROM {} WHERE {}) TO STDOUT BINARY",
-            quoted_table, predicate
-        );
+        let (copy_out_sql, copy_in_sql) = match source {
+            CopySource::Predicate(predicate) => (
+                format!(
+                    "COPY (SELECT * FROM {} WHERE {}) TO STDOUT BINARY",
+                    quoted_table, predicate
+                ),
+                format!("COPY {} FROM STDIN BINARY", quoted_table),
+            ),
+            CopySource::Transform(select) => {
+                let columns = transform_columns(&source_client, table, select).await?;
+                (
+                    format!("COPY ({}) TO STDOUT", select),
+                    format!("COPY {} ({}) FROM STDIN", quoted_table, columns.join(", ")),
+                )
+            }
+        };
         let reader = source_client
             .copy_out(&copy_out_sql)
             .await
             .with_context(|| format!("Failed to copy data from source table '{}'", table))?;
 
-        let copy_in_sql = format!("COPY {} FROM STDIN BINARY", quoted_table);
         let writer = target_client
             .copy_in(&copy_in_sql)
             .await
@@ -160,13 +260,27 @@ pub async fn copy_filtered_tables(
         pin_mut!(reader);
         pin_mut!(writer);
 
-        while let Some(chunk) = reader.next().await {
-            let data = chunk?;
-            writer.as_mut().send(data).await?;
-        }
-
-        writer.finish().await?;
-        tracing::info!("  ✓ Filtered copy complete for '{}'", table);
+        // Cancelling drops the sink unfinished, which aborts the COPY on the target
+        let started = std::time::Instant::now();
+        let copied = cancel::run(cancel, async move {
+            let mut copied = 0;
+            while let Some(chunk) = reader.next().await {
+                let data = chunk?;
+                let bytes = data.len() as u64;
+                crate::resources::record_source_read(bytes);
+                writer.as_mut().send(data).await?;
+                crate::resources::record_target_written(bytes);
+                copied += bytes;
+            }
+            writer.finish().await?;
+            Ok(copied)
+        })
+        .await?;
+        tracing::info!(
+            "  ✓ Filtered copy complete for '{}': {}",
+            table,
+            crate::resources::describe_transfer(copied, started.elapsed())
+        );
     }
 
     Ok(())
@@ -200,6 +314,15 @@ mod tests {
         assert_eq!(result.unwrap(), ("public".to_string(), "users".to_string()));
     }
 
+    #[test]
+    fn test_parse_schema_table_keeps_quoted_names() {
+        let result = parse_schema_table(r#""Sales"."order.items""#).unwrap();
+        assert_eq!(result, ("Sales".to_string(), "order.items".to_string()));
+
+        let result = parse_schema_table(r#""public"."odd""name""#).unwrap();
+        assert_eq!(result, ("public".to_string(), r#"odd"name"#.to_string()));
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_cascade_targets_detected() {
@@ -294,7 +417,14 @@ mod tests {
             "amount > 0".to_string(),
         )];
 
-        let result = copy_filtered_tables(&source_url, &target_url, &tables).await;
+        let result = copy_filtered_tables(
+            &source_url,
+            &target_url,
+            &tables,
+            &Default::default(),
+            &CancellationToken::new(),
+        )
+        .await;
 
         // Should fail with safety check error
         assert!(result.is_err());
@@ -384,7 +514,14 @@ mod tests {
             ),
         ];
 
-        let result = copy_filtered_tables(&source_url, &target_url, &tables).await;
+        let result = copy_filtered_tables(
+            &source_url,
+            &target_url,
+            &tables,
+            &Default::default(),
+            &CancellationToken::new(),
+        )
+        .await;
 
         // Should succeed since all FK-related tables are included
         assert!(
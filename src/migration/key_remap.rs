@@ -0,0 +1,395 @@
+// ABOUTME: Primary key remapping for merge mode (--pk-offset, --pk-regenerate-uuids)
+// ABOUTME: Rewrites staged keys and the foreign keys pointing at them, recording UUID mappings on the target
+
+use crate::migration::missing::Table;
+use crate::utils::{quote_ident, quote_qualified};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio_postgres::{Client, GenericClient};
+
+/// Old-to-new UUID mappings, so re-runs and later tables reuse the same keys
+pub const KEY_MAP_TABLE: &str = "seren_replicator.merge_key_map";
+
+/// Offset added to one source database's integer keys (`--pk-offset db:N`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PkOffset {
+    pub database: String,
+    pub offset: i64,
+}
+
+impl FromStr for PkOffset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s
+            .rsplit_once(':')
+            .filter(|(database, _)| !database.is_empty())
+            .and_then(|(database, offset)| {
+                offset.trim().parse::<i64>().ok().map(|offset| PkOffset {
+                    database: database.trim().to_string(),
+                    offset,
+                })
+            });
+        match parsed {
+            Some(offset) => Ok(offset),
+            None => bail!(
+                "Invalid --pk-offset '{}'.\n\
+                 Expected database:offset, e.g. app:1000000",
+                s
+            ),
+        }
+    }
+}
+
+/// How one source database's keys are rewritten
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyRemap {
+    /// Added to integer primary keys and the foreign keys referencing them
+    pub offset: Option<i64>,
+    /// Replace UUID primary keys with new random UUIDs
+    pub regenerate_uuids: bool,
+}
+
+impl KeyRemap {
+    pub fn is_empty(&self) -> bool {
+        self.offset.is_none() && !self.regenerate_uuids
+    }
+
+    /// Whether keys of this kind are rewritten
+    pub fn applies_to(&self, kind: KeyKind) -> bool {
+        match kind {
+            KeyKind::Integer => self.offset.is_some(),
+            KeyKind::Uuid => self.regenerate_uuids,
+        }
+    }
+}
+
+/// Primary key types that can be remapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Integer,
+    Uuid,
+}
+
+impl KeyKind {
+    fn from_type(type_name: &str) -> Option<Self> {
+        match type_name {
+            "int2" | "int4" | "int8" => Some(KeyKind::Integer),
+            "uuid" => Some(KeyKind::Uuid),
+            _ => None,
+        }
+    }
+}
+
+/// A foreign key on the target; `columns` is set for single-column keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub child: Table,
+    pub parent: Table,
+    /// (referencing column, referenced column)
+    pub columns: Option<(String, String)>,
+}
+
+/// Foreign keys on the target
+///
+/// # Errors
+///
+/// Returns an error if the catalog cannot be read.
+pub async fn foreign_keys(client: &Client) -> Result<Vec<ForeignKey>> {
+    let rows = client
+        .query(
+            "SELECT cn.nspname::text, c.relname::text, pn.nspname::text, p.relname::text,
+                    CASE WHEN array_length(con.conkey, 1) = 1 THEN
+                        (SELECT attname::text FROM pg_attribute
+                         WHERE attrelid = con.conrelid AND attnum = con.conkey[1]) END,
+                    CASE WHEN array_length(con.confkey, 1) = 1 THEN
+                        (SELECT attname::text FROM pg_attribute
+                         WHERE attrelid = con.confrelid AND attnum = con.confkey[1]) END
+             FROM pg_constraint con
+             JOIN pg_class c ON c.oid = con.conrelid
+             JOIN pg_namespace cn ON cn.oid = c.relnamespace
+             JOIN pg_class p ON p.oid = con.confrelid
+             JOIN pg_namespace pn ON pn.oid = p.relnamespace
+             WHERE con.contype = 'f'",
+            &[],
+        )
+        .await
+        .context("Failed to read foreign keys on the target")?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let child_column: Option<String> = row.get(4);
+            let parent_column: Option<String> = row.get(5);
+            ForeignKey {
+                child: (row.get(0), row.get(1)),
+                parent: (row.get(2), row.get(3)),
+                columns: child_column.zip(parent_column),
+            }
+        })
+        .collect())
+}
+
+/// The single-column primary key of a target table, if its type can be remapped
+///
+/// # Errors
+///
+/// Returns an error if the catalog cannot be read.
+pub async fn remappable_key(
+    client: &Client,
+    schema: &str,
+    table: &str,
+) -> Result<Option<(String, KeyKind)>> {
+    let relation = quote_qualified(schema, table);
+    let rows = client
+        .query(
+            "SELECT a.attname::text, t.typname::text FROM pg_index i
+             JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+             JOIN pg_type t ON t.oid = a.atttypid
+             WHERE i.indrelid = $1::text::regclass AND i.indisprimary",
+            &[&relation],
+        )
+        .await
+        .with_context(|| format!("Failed to get primary key of {}", relation))?;
+    let [row] = rows.as_slice() else {
+        return Ok(None);
+    };
+    let type_name: String = row.get(1);
+    Ok(KeyKind::from_type(&type_name).map(|kind| (row.get(0), kind)))
+}
+
+/// The key columns of one table rewritten while it is staged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableRemap {
+    /// The table's own primary key
+    pub key: Option<(String, KeyKind)>,
+    /// Foreign key columns, with the table whose remapped key they reference
+    pub references: Vec<(String, Table, KeyKind)>,
+}
+
+impl TableRemap {
+    /// Plan the rewrite of `table` from the remapped keys of the merged tables
+    ///
+    /// A primary key that is also a remapped foreign key follows its parent.
+    pub fn new(
+        table: &Table,
+        remapped: &HashMap<Table, (String, KeyKind)>,
+        foreign_keys: &[ForeignKey],
+    ) -> Self {
+        let references: Vec<(String, Table, KeyKind)> = foreign_keys
+            .iter()
+            .filter(|fk| &fk.child == table)
+            .filter_map(|fk| {
+                let (column, parent_column) = fk.columns.as_ref()?;
+                let (key, kind) = remapped.get(&fk.parent)?;
+                (key == parent_column).then(|| (column.clone(), fk.parent.clone(), *kind))
+            })
+            .collect();
+        let key = remapped
+            .get(table)
+            .filter(|(key, _)| !references.iter().any(|(column, _, _)| column == key))
+            .cloned();
+        TableRemap { key, references }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.key.is_none() && self.references.is_empty()
+    }
+}
+
+/// Create the UUID mapping table if it does not exist
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be created.
+pub async fn ensure_key_map(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 database text NOT NULL,
+                 schema_name text NOT NULL,
+                 table_name text NOT NULL,
+                 old_key text NOT NULL,
+                 new_key text NOT NULL,
+                 mapped_at timestamptz NOT NULL DEFAULT now(),
+                 PRIMARY KEY (database, schema_name, table_name, old_key)
+             )",
+            KEY_MAP_TABLE
+        ))
+        .await
+        .context("Failed to create the merge key map on target")
+}
+
+/// Rewrite the keys of the rows in `staging` for `table` of source `database`
+///
+/// New UUIDs are recorded in [`KEY_MAP_TABLE`] before use, so the same source
+/// row gets the same key on every run and in every referencing table.
+///
+/// # Errors
+///
+/// Returns an error if a rewrite fails.
+pub async fn apply(
+    client: &impl GenericClient,
+    staging: &str,
+    database: &str,
+    table: &Table,
+    plan: &TableRemap,
+    remap: KeyRemap,
+) -> Result<()> {
+    let qualified = quote_qualified(&table.0, &table.1);
+    let mut offset_columns = Vec::new();
+    if let Some((key, kind)) = &plan.key {
+        match kind {
+            KeyKind::Integer => offset_columns.push(key.clone()),
+            KeyKind::Uuid => {
+                let key = quote_ident(key);
+                client
+                    .execute(
+                        &format!(
+                            "INSERT INTO {} (database, schema_name, table_name, old_key, new_key)
+                             SELECT DISTINCT $1, $2, $3, {}::text, gen_random_uuid()::text
+                             FROM {} WHERE {} IS NOT NULL
+                             ON CONFLICT DO NOTHING",
+                            KEY_MAP_TABLE, key, staging, key
+                        ),
+                        &[&database, &table.0, &table.1],
+                    )
+                    .await
+                    .with_context(|| format!("Failed to map UUID keys of {}", qualified))?;
+                map_uuid_column(client, staging, database, &key, table).await?;
+            }
+        }
+    }
+    for (column, parent, kind) in &plan.references {
+        match kind {
+            KeyKind::Integer => offset_columns.push(column.clone()),
+            KeyKind::Uuid => {
+                map_uuid_column(client, staging, database, &quote_ident(column), parent).await?
+            }
+        }
+    }
+    if let (Some(offset), false) = (remap.offset, offset_columns.is_empty()) {
+        let assignments = offset_columns
+            .iter()
+            .map(|column| format!("{0} = {0} + $1::bigint", quote_ident(column)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        client
+            .execute(
+                &format!("UPDATE {} SET {}", staging, assignments),
+                &[&offset],
+            )
+            .await
+            .with_context(|| format!("Failed to offset keys of {}", qualified))?;
+    }
+    Ok(())
+}
+
+/// Replace the UUIDs in `column` with their mapping for `parent`'s key
+async fn map_uuid_column(
+    client: &impl GenericClient,
+    staging: &str,
+    database: &str,
+    column: &str,
+    parent: &Table,
+) -> Result<()> {
+    client
+        .execute(
+            &format!(
+                "UPDATE {staging} s SET {column} = m.new_key::uuid FROM {KEY_MAP_TABLE} m
+                 WHERE m.database = $1 AND m.schema_name = $2 AND m.table_name = $3
+                   AND m.old_key = s.{column}::text"
+            ),
+            &[&database, &parent.0, &parent.1],
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to rewrite {} with the UUID keys of {}.{}",
+                column, parent.0, parent.1
+            )
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str) -> Table {
+        ("public".to_string(), name.to_string())
+    }
+
+    fn fk(child: &str, parent: &str, columns: Option<(&str, &str)>) -> ForeignKey {
+        ForeignKey {
+            child: table(child),
+            parent: table(parent),
+            columns: columns.map(|(c, p)| (c.to_string(), p.to_string())),
+        }
+    }
+
+    #[test]
+    fn test_pk_offset_parse() {
+        assert_eq!(
+            "app:1000000".parse::<PkOffset>().unwrap(),
+            PkOffset {
+                database: "app".to_string(),
+                offset: 1_000_000
+            }
+        );
+        assert_eq!("a:b:-5".parse::<PkOffset>().unwrap().database, "a:b");
+        assert!("app".parse::<PkOffset>().is_err());
+        assert!(":5".parse::<PkOffset>().is_err());
+        assert!("app:lots".parse::<PkOffset>().is_err());
+    }
+
+    #[test]
+    fn test_key_remap_applies_to_enabled_kinds() {
+        let remap = KeyRemap {
+            offset: Some(10),
+            regenerate_uuids: false,
+        };
+        assert!(remap.applies_to(KeyKind::Integer));
+        assert!(!remap.applies_to(KeyKind::Uuid));
+        assert!(KeyRemap::default().is_empty());
+    }
+
+    #[test]
+    fn test_table_remap_follows_foreign_keys() {
+        let remapped = HashMap::from([
+            (table("customers"), ("id".to_string(), KeyKind::Integer)),
+            (table("orders"), ("id".to_string(), KeyKind::Uuid)),
+            (
+                table("profiles"),
+                ("customer_id".to_string(), KeyKind::Integer),
+            ),
+        ]);
+        let foreign_keys = vec![
+            fk("orders", "customers", Some(("customer_id", "id"))),
+            fk("orders", "regions", Some(("region_id", "id"))),
+            fk("orders", "customers", None),
+            fk("profiles", "customers", Some(("customer_id", "id"))),
+            fk("items", "orders", Some(("order_ref", "code"))),
+        ];
+
+        let orders = TableRemap::new(&table("orders"), &remapped, &foreign_keys);
+        assert_eq!(orders.key, Some(("id".to_string(), KeyKind::Uuid)));
+        assert_eq!(
+            orders.references,
+            vec![(
+                "customer_id".to_string(),
+                table("customers"),
+                KeyKind::Integer
+            )]
+        );
+
+        // A key that is itself a remapped foreign key is rewritten once, as a reference
+        let profiles = TableRemap::new(&table("profiles"), &remapped, &foreign_keys);
+        assert_eq!(profiles.key, None);
+        assert_eq!(profiles.references.len(), 1);
+
+        // References to columns other than the remapped key are left alone
+        assert!(TableRemap::new(&table("items"), &remapped, &foreign_keys).is_empty());
+    }
+}
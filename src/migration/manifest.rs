@@ -0,0 +1,220 @@
+// ABOUTME: manifest.json for dump directories: every file's size and SHA-256, written after pg_dump
+// ABOUTME: Checked before pg_restore so a truncated or corrupted dump fails fast with the file named
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Manifest format version, bumped on incompatible changes
+const MANIFEST_VERSION: u32 = 1;
+
+/// One file in a dump directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the dump directory, with `/` separators
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// The files of a dump directory when it was written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Combined size of the dump's files
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Where the manifest for `dump_dir` is written: `<dump_dir>.manifest.json`
+///
+/// It sits next to the directory rather than inside it, so the dump itself
+/// stays exactly as pg_dump wrote it.
+pub fn manifest_path(dump_dir: &Path) -> PathBuf {
+    let mut name = dump_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".manifest.json");
+    dump_dir.with_file_name(name)
+}
+
+/// Hash every file in `dump_dir` and write the manifest next to it
+///
+/// # Returns
+///
+/// The manifest that was written.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or the manifest cannot be written.
+pub fn write_manifest(dump_dir: &Path) -> Result<Manifest> {
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        files: scan(dump_dir)?,
+    };
+    let path = manifest_path(dump_dir);
+    let json = serde_json::to_string_pretty(&manifest).context("Failed to encode dump manifest")?;
+    fs::write(&path, json)
+        .with_context(|| format!("Failed to write dump manifest {}", path.display()))?;
+    tracing::info!(
+        "✓ Wrote dump manifest: {} file(s), {}",
+        manifest.files.len(),
+        super::format_bytes(manifest.total_bytes() as i64)
+    );
+    Ok(manifest)
+}
+
+/// Check `dump_dir` against its manifest before it is restored
+///
+/// Files added since the manifest was written are ignored.
+///
+/// # Returns
+///
+/// The manifest the dump matched.
+///
+/// # Errors
+///
+/// Returns an error naming every missing, truncated, or corrupted file, or if
+/// the manifest is missing or unreadable.
+pub fn verify_manifest(dump_dir: &Path) -> Result<Manifest> {
+    let path = manifest_path(dump_dir);
+    let raw = fs::read_to_string(&path).with_context(|| {
+        format!(
+            "Dump manifest {} is missing or unreadable.\n\
+             The dump may be incomplete; re-run init to dump the database again.",
+            path.display()
+        )
+    })?;
+    let manifest: Manifest = serde_json::from_str(&raw)
+        .with_context(|| format!("Dump manifest {} is not valid JSON", path.display()))?;
+    if manifest.version != MANIFEST_VERSION {
+        bail!(
+            "Dump manifest {} has version {}, expected {}.\n\
+             Re-run init with this version of seren-replicator to dump the database again.",
+            path.display(),
+            manifest.version,
+            MANIFEST_VERSION
+        );
+    }
+
+    let problems = compare(&manifest.files, &scan(dump_dir)?);
+    if !problems.is_empty() {
+        bail!(
+            "Dump {} does not match its manifest:\n  {}\n\
+             The dump was truncated or corrupted after it was written (disk full, or \
+             files changed or removed). Free disk space if needed and re-run init to dump \
+             the database again.",
+            dump_dir.display(),
+            problems.join("\n  ")
+        );
+    }
+    tracing::info!(
+        "✓ Dump verified against its manifest ({} file(s))",
+        manifest.files.len()
+    );
+    Ok(manifest)
+}
+
+/// Describe how `actual` differs from `expected`, one line per file
+fn compare(expected: &[ManifestEntry], actual: &[ManifestEntry]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for entry in expected {
+        match actual.iter().find(|a| a.path == entry.path) {
+            None => problems.push(format!("{}: missing", entry.path)),
+            Some(found) if found.size != entry.size => problems.push(format!(
+                "{}: {} bytes, expected {} (truncated or overwritten)",
+                entry.path, found.size, entry.size
+            )),
+            Some(found) if found.sha256 != entry.sha256 => {
+                problems.push(format!("{}: SHA-256 mismatch (corrupted)", entry.path))
+            }
+            Some(_) => {}
+        }
+    }
+    problems
+}
+
+/// Size and SHA-256 of every file under `dir`, sorted by path
+fn scan(dir: &Path) -> Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let listing = fs::read_dir(&current)
+            .with_context(|| format!("Failed to list dump directory {}", current.display()))?;
+        for item in listing {
+            let item = item
+                .with_context(|| format!("Failed to list dump directory {}", current.display()))?;
+            let path = item.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let relative = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let mut file = fs::File::open(&path)
+                .with_context(|| format!("Failed to open dump file {}", path.display()))?;
+            let mut hasher = Sha256::new();
+            let size = io::copy(&mut file, &mut hasher)
+                .with_context(|| format!("Failed to read dump file {}", path.display()))?;
+            entries.push(ManifestEntry {
+                path: relative,
+                size,
+                sha256: format!("{:x}", hasher.finalize()),
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_detects_truncation_and_corruption() {
+        let dir = tempdir().unwrap();
+        let dump = dir.path().join("app_data.dump");
+        fs::create_dir_all(dump.join("blobs")).unwrap();
+        fs::write(dump.join("toc.dat"), b"table of contents").unwrap();
+        fs::write(dump.join("3001.dat.gz"), b"0123456789").unwrap();
+        fs::write(dump.join("blobs").join("1.dat"), b"blob").unwrap();
+
+        let manifest = write_manifest(&dump).unwrap();
+        assert_eq!(
+            manifest_path(&dump),
+            dir.path().join("app_data.dump.manifest.json")
+        );
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["3001.dat.gz", "blobs/1.dat", "toc.dat"]);
+        verify_manifest(&dump).unwrap();
+
+        fs::write(dump.join("3001.dat.gz"), b"01234").unwrap();
+        fs::write(dump.join("toc.dat"), b"table of CONTENTS").unwrap();
+        fs::remove_file(dump.join("blobs").join("1.dat")).unwrap();
+        let message = format!("{:#}", verify_manifest(&dump).unwrap_err());
+        assert!(message.contains("3001.dat.gz: 5 bytes, expected 10"));
+        assert!(message.contains("toc.dat: SHA-256 mismatch"));
+        assert!(message.contains("blobs/1.dat: missing"));
+    }
+
+    #[test]
+    fn test_missing_manifest_fails() {
+        let dir = tempdir().unwrap();
+        let dump = dir.path().join("app_data.dump");
+        fs::create_dir_all(&dump).unwrap();
+        assert!(verify_manifest(&dump).is_err());
+    }
+}
@@ -0,0 +1,704 @@
+// ABOUTME: Merge mode (`init --merge`): loads source rows into staging tables and upserts them into populated targets
+// ABOUTME: Rows are matched on the target's primary key or a --merge-key mapping rule with INSERT ... ON CONFLICT
+
+use crate::cancel::{self, CancellationToken};
+use crate::migration::key_remap::{self, KeyRemap, PkOffset, TableRemap};
+use crate::migration::missing::Table;
+use crate::postgres;
+use crate::settings::RunSettings;
+use crate::utils::{quote_ident, quote_qualified};
+use anyhow::{bail, Context, Result};
+use futures::{pin_mut, SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+/// Staging table each source table is loaded into, dropped at commit
+const STAGING_TABLE: &str = "seren_merge_staging";
+
+/// What happens to a target row whose key matches a source row (`--merge`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeConflict {
+    /// Overwrite the target row with the source row
+    #[default]
+    Update,
+    /// Keep the target row
+    Skip,
+}
+
+impl MergeConflict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeConflict::Update => "update",
+            MergeConflict::Skip => "skip",
+        }
+    }
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MergeConflict {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "update" => Ok(MergeConflict::Update),
+            "skip" => Ok(MergeConflict::Skip),
+            other => bail!(
+                "Unknown merge conflict action '{}'. Expected one of: update, skip",
+                other
+            ),
+        }
+    }
+}
+
+/// Columns that identify the same row on both sides of one table (`--merge-key`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeKey {
+    pub schema: String,
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+impl FromStr for MergeKey {
+    type Err = anyhow::Error;
+
+    /// Parse `[schema.]table=column[,column...]`; the schema defaults to `public`
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((table, columns)) = s.split_once('=') else {
+            bail!(
+                "Invalid merge key '{}'.\n\
+                 Expected [schema.]table=column[,column...], e.g. sales.orders=region,order_no",
+                s
+            );
+        };
+        let parts = crate::utils::split_qualified_name(table)?;
+        let (schema, table) = match parts.as_slice() {
+            [table] => ("public".to_string(), table.name.clone()),
+            [schema, table] => (schema.name.clone(), table.name.clone()),
+            _ => bail!(
+                "Invalid merge key table '{}'. Expected table or schema.table",
+                table
+            ),
+        };
+        let columns: Vec<String> = columns
+            .split(',')
+            .map(|column| column.trim().to_string())
+            .collect();
+        if columns.iter().any(String::is_empty) {
+            bail!("Merge key '{}' has an empty column name", s);
+        }
+        Ok(MergeKey {
+            schema,
+            table,
+            columns,
+        })
+    }
+}
+
+/// Merge mode settings of an init run (`--merge`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeSettings {
+    pub conflict: MergeConflict,
+    /// Key columns for tables not matched on their primary key
+    pub keys: Vec<MergeKey>,
+    /// Offsets added to the integer keys of source databases
+    pub pk_offsets: Vec<PkOffset>,
+    /// Source databases whose UUID keys are replaced
+    pub regenerate_uuids: Vec<String>,
+}
+
+impl MergeSettings {
+    /// How the keys of source `database` are rewritten
+    pub fn remap_for(&self, database: &str) -> KeyRemap {
+        KeyRemap {
+            offset: self
+                .pk_offsets
+                .iter()
+                .find(|offset| offset.database == database)
+                .map(|offset| offset.offset),
+            regenerate_uuids: self.regenerate_uuids.iter().any(|name| name == database),
+        }
+    }
+
+    fn key_for(&self, schema: &str, table: &str) -> Option<&MergeKey> {
+        self.keys
+            .iter()
+            .find(|key| key.schema == schema && key.table == table)
+    }
+}
+
+/// Rows written by merging one table
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeCounts {
+    pub staged: u64,
+    pub inserted: u64,
+    pub updated: u64,
+}
+
+impl MergeCounts {
+    /// Source rows whose target row was left as it was
+    pub fn kept(&self) -> u64 {
+        self.staged.saturating_sub(self.inserted + self.updated)
+    }
+}
+
+/// Merge `tables` of one database into their existing target tables
+///
+/// Each table is loaded into a temporary staging table and upserted into the
+/// target table in one transaction, so a failed table leaves the target as it
+/// was. Owned sequences are then advanced past the merged values. Tables are
+/// merged in foreign-key order, referenced tables first, so keys remapped for
+/// `database` (see [`key_remap`]) are known before the rows referencing them.
+///
+/// # Arguments
+///
+/// * `source_db_url` - Source database URL
+/// * `target_db_url` - Target database URL
+/// * `database` - Source database name, which selects the key remapping rules
+/// * `tables` - Tables present on both sides
+/// * `merge` - Conflict action and key mapping rules
+/// * `settings` - Retry policy and chaos faults of the run
+/// * `cancel` - Cancels the copy in progress
+///
+/// # Errors
+///
+/// Returns an error if a table has no usable key, its columns differ from the
+/// target's, or the copy or upsert fails.
+pub async fn merge_tables(
+    source_db_url: &str,
+    target_db_url: &str,
+    database: &str,
+    tables: &[Table],
+    merge: &MergeSettings,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let source = postgres::connect_with_retry(source_db_url, settings).await?;
+    let mut target = postgres::connect_with_retry(target_db_url, settings).await?;
+
+    let foreign_keys = key_remap::foreign_keys(&target).await?;
+    let edges: Vec<(Table, Table)> = foreign_keys
+        .iter()
+        .map(|fk| (fk.child.clone(), fk.parent.clone()))
+        .collect();
+    let tables = parents_first(tables, &edges);
+
+    // Check every table before writing to any of them
+    let mut shapes = Vec::with_capacity(tables.len());
+    for (schema, table) in &tables {
+        shapes.push(merge_shape(&source, &target, schema, table, merge).await?);
+    }
+
+    let remap = merge.remap_for(database);
+    let mut remapped = HashMap::new();
+    if !remap.is_empty() {
+        for (schema, table) in &tables {
+            if let Some((key, kind)) = key_remap::remappable_key(&target, schema, table).await? {
+                if remap.applies_to(kind) {
+                    remapped.insert((schema.clone(), table.clone()), (key, kind));
+                }
+            }
+        }
+        if remap.regenerate_uuids {
+            key_remap::ensure_key_map(&target).await?;
+        }
+        if let Some(offset) = remap.offset {
+            tracing::info!(
+                "  Integer keys from '{}' are offset by {}",
+                database,
+                offset
+            );
+        }
+        if remap.regenerate_uuids {
+            tracing::info!(
+                "  UUID keys from '{}' are regenerated (mappings in {})",
+                database,
+                key_remap::KEY_MAP_TABLE
+            );
+        }
+    }
+
+    for (table, shape) in tables.iter().zip(&shapes) {
+        cancel::check(cancel)?;
+        let (schema, table) = (&table.0, &table.1);
+        let remap_plan =
+            TableRemap::new(&(schema.clone(), table.clone()), &remapped, &foreign_keys);
+        let counts = merge_table(
+            &source,
+            &mut target,
+            database,
+            schema,
+            table,
+            shape,
+            remap,
+            &remap_plan,
+            merge,
+            cancel,
+        )
+        .await?;
+        tracing::info!(
+            "  ✓ Merged {}.{}: {} inserted, {} updated, {} kept",
+            schema,
+            table,
+            counts.inserted,
+            counts.updated,
+            counts.kept()
+        );
+    }
+    Ok(())
+}
+
+/// Order `tables` so referenced tables are merged before the tables referencing them
+///
+/// Tables in a reference cycle keep their relative order at the end.
+fn parents_first(tables: &[Table], foreign_keys: &[(Table, Table)]) -> Vec<Table> {
+    let mut pending: Vec<Table> = tables.to_vec();
+    let mut ordered = Vec::with_capacity(tables.len());
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|table| {
+            !foreign_keys.iter().any(|(child, parent)| {
+                child == table && parent != table && pending.contains(parent)
+            })
+        });
+        match ready {
+            Some(index) => ordered.push(pending.remove(index)),
+            None => {
+                ordered.append(&mut pending);
+            }
+        }
+    }
+    ordered
+}
+
+/// Columns copied into a table and the ones its rows are matched on
+struct MergeShape {
+    columns: Vec<String>,
+    keys: Vec<String>,
+}
+
+async fn merge_shape(
+    source: &Client,
+    target: &Client,
+    schema: &str,
+    table: &str,
+    settings: &MergeSettings,
+) -> Result<MergeShape> {
+    let columns = merge_columns(source, target, schema, table).await?;
+    let keys = conflict_columns(target, schema, table, settings).await?;
+    if let Some(key) = keys.iter().find(|key| !columns.contains(key)) {
+        bail!(
+            "Merge key column '{}' of {}.{} is not copied from the source.\n\
+             Use --merge-key with columns both sides have.",
+            key,
+            schema,
+            table
+        );
+    }
+    Ok(MergeShape { columns, keys })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn merge_table(
+    source: &Client,
+    target: &mut Client,
+    database: &str,
+    schema: &str,
+    table: &str,
+    shape: &MergeShape,
+    remap: KeyRemap,
+    remap_plan: &TableRemap,
+    settings: &MergeSettings,
+    cancel: &CancellationToken,
+) -> Result<MergeCounts> {
+    let qualified = quote_qualified(schema, table);
+    let MergeShape { columns, keys } = shape;
+    let column_list = columns
+        .iter()
+        .map(|column| quote_ident(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let transaction = target
+        .transaction()
+        .await
+        .context("Failed to start merge transaction")?;
+    transaction
+        .batch_execute(&format!(
+            "CREATE TEMP TABLE {} ON COMMIT DROP AS SELECT {} FROM {} WITH NO DATA",
+            STAGING_TABLE, column_list, qualified
+        ))
+        .await
+        .with_context(|| format!("Failed to create staging table for {}", qualified))?;
+
+    let reader = source
+        .copy_out(&format!(
+            "COPY (SELECT {} FROM {}) TO STDOUT",
+            column_list, qualified
+        ))
+        .await
+        .with_context(|| format!("Failed to copy {} from source", qualified))?;
+    let writer = transaction
+        .copy_in(&format!(
+            "COPY {} ({}) FROM STDIN",
+            STAGING_TABLE, column_list
+        ))
+        .await
+        .with_context(|| format!("Failed to stage {} on target", qualified))?;
+    pin_mut!(reader);
+    pin_mut!(writer);
+    // Cancelling drops the sink unfinished, which aborts the COPY and the transaction
+    let staged = cancel::run(cancel, async move {
+        while let Some(chunk) = reader.next().await {
+            let data = chunk?;
+            let bytes = data.len() as u64;
+            crate::resources::record_source_read(bytes);
+            writer.as_mut().send(data).await?;
+            crate::resources::record_target_written(bytes);
+        }
+        Ok(writer.finish().await?)
+    })
+    .await
+    .with_context(|| format!("Failed to stage {}", qualified))?;
+
+    if !remap_plan.is_empty() {
+        key_remap::apply(
+            &transaction,
+            STAGING_TABLE,
+            database,
+            &(schema.to_string(), table.to_string()),
+            remap_plan,
+            remap,
+        )
+        .await?;
+    }
+
+    let row = transaction
+        .query_one(
+            &upsert_sql(&qualified, columns, keys, settings.conflict),
+            &[],
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to merge into {}.\n\
+                 If several source rows share a merge key, pick a key that is unique on the source.",
+                qualified
+            )
+        })?;
+    advance_sequences(&transaction, &qualified, columns).await?;
+    transaction
+        .commit()
+        .await
+        .with_context(|| format!("Failed to commit merge into {}", qualified))?;
+
+    Ok(MergeCounts {
+        staged,
+        inserted: row.get::<_, i64>(0) as u64,
+        updated: row.get::<_, i64>(1) as u64,
+    })
+}
+
+/// The source's stored columns, all of which the target must also store
+async fn merge_columns(
+    source: &Client,
+    target: &Client,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>> {
+    let source_columns = stored_columns(source, schema, table).await?;
+    let target_columns = stored_columns(target, schema, table).await?;
+    if let Some(column) = source_columns
+        .iter()
+        .find(|column| !target_columns.contains(column))
+    {
+        bail!(
+            "Column '{}' of {}.{} is missing (or generated) on the target.\n\
+             Add it to the target table before merging, or exclude the table.",
+            column,
+            schema,
+            table
+        );
+    }
+    Ok(source_columns)
+}
+
+async fn stored_columns(client: &Client, schema: &str, table: &str) -> Result<Vec<String>> {
+    let relation = quote_qualified(schema, table);
+    let rows = client
+        .query(
+            "SELECT attname::text
+             FROM pg_attribute
+             WHERE attrelid = $1::text::regclass
+               AND attnum > 0 AND NOT attisdropped AND attgenerated = ''
+             ORDER BY attnum",
+            &[&relation],
+        )
+        .await
+        .with_context(|| format!("Failed to get columns of {}", relation))?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// The `ON CONFLICT` columns: the mapping rule's, else the target's primary key
+async fn conflict_columns(
+    target: &Client,
+    schema: &str,
+    table: &str,
+    settings: &MergeSettings,
+) -> Result<Vec<String>> {
+    let qualified = quote_qualified(schema, table);
+    if let Some(key) = settings.key_for(schema, table) {
+        // Sorted by bytes on both sides: COLLATE "C" matches Rust's ordering
+        let mut sorted = key.columns.clone();
+        sorted.sort();
+        let unique: bool = target
+            .query_one(
+                "SELECT EXISTS (
+                     SELECT 1 FROM pg_index i
+                     WHERE i.indrelid = $1::text::regclass AND i.indisunique
+                       AND i.indpred IS NULL AND i.indexprs IS NULL
+                       AND (SELECT array_agg(a.attname::text ORDER BY a.attname::text COLLATE \"C\")
+                            FROM pg_attribute a
+                            WHERE a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)) = $2
+                 )",
+                &[&qualified, &sorted],
+            )
+            .await
+            .with_context(|| format!("Failed to read unique indexes of {}", qualified))?
+            .get(0);
+        if !unique {
+            bail!(
+                "The merge key ({}) of {} has no unique index on the target.\n\
+                 Create one first, e.g. CREATE UNIQUE INDEX ON {} ({});",
+                key.columns.join(", "),
+                qualified,
+                qualified,
+                key.columns
+                    .iter()
+                    .map(|column| quote_ident(column))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        return Ok(key.columns.clone());
+    }
+
+    let rows = target
+        .query(
+            "SELECT a.attname::text FROM pg_index i
+             JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+             WHERE i.indrelid = $1::text::regclass AND i.indisprimary
+             ORDER BY array_position(i.indkey, a.attnum)",
+            &[&qualified],
+        )
+        .await
+        .with_context(|| format!("Failed to get primary key of {}", qualified))?;
+    if rows.is_empty() {
+        bail!(
+            "{} has no primary key on the target, so merge cannot match its rows.\n\
+             Map it to unique columns with --merge-key {}.{}=column[,column...], or exclude the table.",
+            qualified,
+            schema,
+            table
+        );
+    }
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Upsert the staging table into `qualified`, returning (inserted, updated)
+///
+/// Updates skip rows that are already identical, so those count as kept.
+fn upsert_sql(
+    qualified: &str,
+    columns: &[String],
+    keys: &[String],
+    conflict: MergeConflict,
+) -> String {
+    let column_list = columns
+        .iter()
+        .map(|column| quote_ident(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let key_list = keys
+        .iter()
+        .map(|column| quote_ident(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let updated: Vec<String> = columns
+        .iter()
+        .filter(|column| !keys.contains(column))
+        .map(|column| quote_ident(column))
+        .collect();
+    let action = if conflict == MergeConflict::Skip || updated.is_empty() {
+        "DO NOTHING".to_string()
+    } else {
+        format!(
+            "DO UPDATE SET {} WHERE ({}) IS DISTINCT FROM ({})",
+            updated
+                .iter()
+                .map(|column| format!("{} = EXCLUDED.{}", column, column))
+                .collect::<Vec<_>>()
+                .join(", "),
+            updated
+                .iter()
+                .map(|column| format!("t.{}", column))
+                .collect::<Vec<_>>()
+                .join(", "),
+            updated
+                .iter()
+                .map(|column| format!("EXCLUDED.{}", column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    format!(
+        "WITH merged AS (\
+         INSERT INTO {qualified} AS t ({column_list}) OVERRIDING SYSTEM VALUE \
+         SELECT {column_list} FROM {STAGING_TABLE} \
+         ON CONFLICT ({key_list}) {action} \
+         RETURNING (xmax = 0) AS inserted) \
+         SELECT count(*) FILTER (WHERE inserted), count(*) FILTER (WHERE NOT inserted) FROM merged"
+    )
+}
+
+/// Move owned sequences past the largest merged value, so new target rows do not collide
+async fn advance_sequences(
+    client: &tokio_postgres::Transaction<'_>,
+    qualified: &str,
+    columns: &[String],
+) -> Result<()> {
+    for column in columns {
+        let sequence: Option<String> = client
+            .query_one(
+                "SELECT pg_get_serial_sequence($1, $2)",
+                &[&qualified, column],
+            )
+            .await
+            .with_context(|| format!("Failed to look up the sequence of {}", qualified))?
+            .get(0);
+        let Some(sequence) = sequence else {
+            continue;
+        };
+        client
+            .execute(
+                &format!(
+                    "SELECT setval($1::text::regclass, m.max_value) \
+                     FROM (SELECT max({})::bigint AS max_value FROM {}) m, {} s \
+                     WHERE m.max_value > s.last_value",
+                    quote_ident(column),
+                    qualified,
+                    sequence
+                ),
+                &[&sequence],
+            )
+            .await
+            .with_context(|| format!("Failed to advance sequence {}", sequence))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_conflict_parse() {
+        assert_eq!(
+            "update".parse::<MergeConflict>().unwrap(),
+            MergeConflict::Update
+        );
+        assert_eq!(
+            " SKIP ".parse::<MergeConflict>().unwrap(),
+            MergeConflict::Skip
+        );
+        assert!("replace".parse::<MergeConflict>().is_err());
+    }
+
+    #[test]
+    fn test_merge_key_parse() {
+        let key: MergeKey = "orders=order_no".parse().unwrap();
+        assert_eq!(key.schema, "public");
+        assert_eq!(key.table, "orders");
+        assert_eq!(key.columns, vec!["order_no"]);
+
+        let key: MergeKey = "\"Sales\".orders=region, order_no".parse().unwrap();
+        assert_eq!(key.schema, "Sales");
+        assert_eq!(key.columns, vec!["region", "order_no"]);
+
+        assert!("orders".parse::<MergeKey>().is_err());
+        assert!("orders=a,,b".parse::<MergeKey>().is_err());
+        assert!("a.b.c=id".parse::<MergeKey>().is_err());
+    }
+
+    #[test]
+    fn test_upsert_sql_update_skips_identical_rows() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let sql = upsert_sql(
+            "\"public\".\"users\"",
+            &columns,
+            &["id".to_string()],
+            MergeConflict::Update,
+        );
+        assert!(sql.contains("ON CONFLICT (\"id\") DO UPDATE SET \"name\" = EXCLUDED.\"name\""));
+        assert!(sql.contains("WHERE (t.\"name\") IS DISTINCT FROM (EXCLUDED.\"name\")"));
+        assert!(sql.contains("OVERRIDING SYSTEM VALUE"));
+    }
+
+    #[test]
+    fn test_upsert_sql_does_nothing_for_skip_or_key_only_tables() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let keys = vec!["id".to_string()];
+        let sql = upsert_sql("\"public\".\"users\"", &columns, &keys, MergeConflict::Skip);
+        assert!(sql.contains("ON CONFLICT (\"id\") DO NOTHING"));
+
+        let sql = upsert_sql("\"public\".\"tags\"", &keys, &keys, MergeConflict::Update);
+        assert!(sql.contains("ON CONFLICT (\"id\") DO NOTHING"));
+    }
+
+    #[test]
+    fn test_parents_first_orders_by_foreign_keys() {
+        let table = |name: &str| ("public".to_string(), name.to_string());
+        let tables = vec![
+            table("items"),
+            table("orders"),
+            table("customers"),
+            table("tags"),
+        ];
+        let foreign_keys = vec![
+            (table("items"), table("orders")),
+            (table("orders"), table("customers")),
+            (table("tags"), table("tags")),
+            (table("orders"), table("regions")),
+        ];
+        assert_eq!(
+            parents_first(&tables, &foreign_keys),
+            vec![
+                table("customers"),
+                table("orders"),
+                table("items"),
+                table("tags")
+            ]
+        );
+
+        let cycle = vec![(table("a"), table("b")), (table("b"), table("a"))];
+        assert_eq!(
+            parents_first(&[table("a"), table("b"), table("c")], &cycle),
+            vec![table("c"), table("a"), table("b")]
+        );
+    }
+
+    #[test]
+    fn test_merge_counts_kept() {
+        let counts = MergeCounts {
+            staged: 10,
+            inserted: 4,
+            updated: 3,
+        };
+        assert_eq!(counts.kept(), 3);
+    }
+}
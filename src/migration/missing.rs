@@ -0,0 +1,240 @@
+// ABOUTME: Differential init (`init --missing-only`): copies only tables absent or empty on the target
+// ABOUTME: Compares source and target inventories per database and leaves populated target tables untouched
+
+use crate::filters::ReplicationFilter;
+use crate::inventory;
+use crate::utils::{quote_ident, quote_qualified};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use tokio_postgres::Client;
+
+/// A `(schema, table)` pair
+pub type Table = (String, String);
+
+/// What a `--missing-only` run does with each source table of one database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MissingPlan {
+    /// Absent on the target: schema and data are copied
+    pub create: Vec<Table>,
+    /// Present but empty on the target: only data is copied
+    pub fill: Vec<Table>,
+    /// Present with rows on the target: left untouched
+    pub populated: Vec<Table>,
+}
+
+impl MissingPlan {
+    /// Sort source tables by what the target already has
+    ///
+    /// `target` maps each table on the target to whether it holds any rows.
+    pub fn new(source: &[Table], target: &HashMap<Table, bool>) -> Self {
+        let mut plan = Self::default();
+        for table in source {
+            match target.get(table) {
+                None => plan.create.push(table.clone()),
+                Some(false) => plan.fill.push(table.clone()),
+                Some(true) => plan.populated.push(table.clone()),
+            }
+        }
+        plan
+    }
+
+    /// Tables whose data is copied: the created ones and the empty ones
+    pub fn data_tables(&self) -> Vec<Table> {
+        self.create.iter().chain(&self.fill).cloned().collect()
+    }
+
+    /// Nothing to copy
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.fill.is_empty()
+    }
+
+    /// Log the plan for `database`
+    pub fn log(&self, database: &str) {
+        tracing::info!(
+            "  --missing-only: {} table(s) to create, {} empty table(s) to fill, {} populated table(s) left untouched in '{}'",
+            self.create.len(),
+            self.fill.len(),
+            self.populated.len(),
+            database
+        );
+        for (schema, table) in &self.populated {
+            tracing::info!("    ↷ {}.{} already has rows", schema, table);
+        }
+    }
+}
+
+/// Reject settings a `--missing-only` or `--merge` run cannot honour
+///
+/// Table rules (WHERE filters, transforms, schema-only tables, partitioning)
+/// apply to whole-database copies; a differential copy moves whole tables.
+///
+/// # Errors
+///
+/// Returns an error if `filter` carries table rules.
+pub fn check_filter(filter: &ReplicationFilter, flag: &str) -> Result<()> {
+    if !filter.table_rules().is_empty() {
+        bail!(
+            "{} copies whole tables and cannot apply table rules (filters, transforms, schema-only tables, partitioning).\n\
+             Remove them from the command line and --config, or copy those tables with a separate init run.",
+            flag
+        );
+    }
+    Ok(())
+}
+
+/// Plan a `--missing-only` copy of one database
+///
+/// The source tables come from the [`inventory`] (subject to `filter`); each
+/// one the target also has is probed for a single row, so a table counts as
+/// empty only when it really is.
+///
+/// # Errors
+///
+/// Returns an error if either catalog cannot be read.
+pub async fn plan_database(
+    source: &Client,
+    target: &Client,
+    database: &str,
+    filter: &ReplicationFilter,
+) -> Result<MissingPlan> {
+    let source_tables: Vec<Table> = inventory::postgres_database(source, database, filter)
+        .await?
+        .tables
+        .into_iter()
+        .filter_map(|table| table.schema.map(|schema| (schema, table.name)))
+        .collect();
+    let target_inventory =
+        inventory::postgres_database(target, database, &ReplicationFilter::empty()).await?;
+
+    let mut target_tables = HashMap::new();
+    for table in target_inventory.tables {
+        let Some(schema) = table.schema else {
+            continue;
+        };
+        let key = (schema, table.name);
+        if !source_tables.contains(&key) {
+            continue;
+        }
+        let has_rows: bool = target
+            .query_one(
+                &format!(
+                    "SELECT EXISTS (SELECT 1 FROM {})",
+                    quote_qualified(&key.0, &key.1)
+                ),
+                &[],
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to check whether {}.{} is empty on the target",
+                    key.0, key.1
+                )
+            })?
+            .get(0);
+        target_tables.insert(key, has_rows);
+    }
+    Ok(MissingPlan::new(&source_tables, &target_tables))
+}
+
+/// `filter` narrowed to `tables` of `database`
+///
+/// # Errors
+///
+/// Returns an error if the narrowed filter is invalid.
+pub fn restrict(
+    filter: &ReplicationFilter,
+    database: &str,
+    tables: &[Table],
+) -> Result<ReplicationFilter> {
+    let entries = tables
+        .iter()
+        .map(|(schema, table)| {
+            format!(
+                "{}.{}",
+                quote_ident(database),
+                quote_qualified(schema, table)
+            )
+        })
+        .collect();
+    Ok(ReplicationFilter::new(None, None, Some(entries), None)?
+        .with_table_rules(filter.table_rules().clone())
+        .for_database(database))
+}
+
+/// Whether a JSONB table exists on the target and holds at least one row
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read.
+pub async fn jsonb_table_populated(
+    client: &Client,
+    schema: &str,
+    table_name: &str,
+) -> Result<bool> {
+    crate::jsonb::validate_table_name(table_name)
+        .context("Invalid table name for --missing-only check")?;
+    let qualified = crate::jsonb::target_schema::qualified(schema, table_name);
+    let exists: bool = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&qualified])
+        .await
+        .context("Failed to check for JSONB table")?
+        .get(0);
+    if !exists {
+        return Ok(false);
+    }
+    Ok(client
+        .query_one(&format!("SELECT EXISTS (SELECT 1 FROM {})", qualified), &[])
+        .await
+        .with_context(|| format!("Failed to check whether '{}' is empty", table_name))?
+        .get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(schema: &str, name: &str) -> Table {
+        (schema.to_string(), name.to_string())
+    }
+
+    #[test]
+    fn test_plan_sorts_tables_by_target_state() {
+        let source = vec![
+            table("public", "users"),
+            table("public", "orders"),
+            table("sales", "invoices"),
+        ];
+        let target = HashMap::from([
+            (table("public", "users"), true),
+            (table("public", "orders"), false),
+            (table("public", "legacy"), true),
+        ]);
+        let plan = MissingPlan::new(&source, &target);
+        assert_eq!(plan.create, vec![table("sales", "invoices")]);
+        assert_eq!(plan.fill, vec![table("public", "orders")]);
+        assert_eq!(plan.populated, vec![table("public", "users")]);
+        assert_eq!(
+            plan.data_tables(),
+            vec![table("sales", "invoices"), table("public", "orders")]
+        );
+        assert!(!plan.is_empty());
+
+        let plan = MissingPlan::new(&[table("public", "users")], &target);
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_restrict_limits_filter_to_listed_tables() {
+        let filter = restrict(
+            &ReplicationFilter::empty(),
+            "app",
+            &[table("public", "orders"), table("Sales", "my.table")],
+        )
+        .unwrap();
+        assert!(filter.should_replicate_database("app"));
+        assert!(!filter.should_replicate_database("other"));
+        assert!(filter.should_replicate_schema_table("app", "public", "orders"));
+        assert!(filter.should_replicate_schema_table("app", "Sales", "my.table"));
+        assert!(!filter.should_replicate_schema_table("app", "public", "users"));
+    }
+}
@@ -1,18 +1,43 @@
 // ABOUTME: Migration utilities module
 // ABOUTME: Handles schema introspection, dump/restore, and data migration
 
+pub mod accepted;
+pub mod annotations;
 pub mod checksum;
+pub mod copy_order;
 pub mod dump;
 pub mod estimation;
+pub mod event_triggers;
 pub mod filtered;
+pub mod foreign_servers;
+pub mod key_remap;
+pub mod manifest;
+pub mod merge;
+pub mod missing;
+pub mod parquet;
+pub mod partitioning;
+pub mod phases;
 pub mod restore;
+pub mod safeguard;
 pub mod schema;
+pub mod security;
+pub mod split;
+pub mod unsupported;
+pub mod upgrade;
+pub mod watermarks;
 
-pub use checksum::{compare_tables, compute_table_checksum, ChecksumResult};
-pub use dump::{dump_data, dump_globals, dump_schema};
+pub use checksum::{
+    compare_tables, compute_table_checksum, compute_table_checksum_with_options, ChecksumAlgorithm,
+    ChecksumOptions, ChecksumResult,
+};
+pub use dump::{dump_data, dump_globals, dump_schema, SchemaSection};
 pub use estimation::{estimate_database_sizes, format_bytes, format_duration, DatabaseSizeInfo};
 pub use filtered::copy_filtered_tables;
 pub use restore::{restore_data, restore_globals, restore_schema};
 pub use schema::{
     get_table_columns, list_databases, list_tables, ColumnInfo, DatabaseInfo, TableInfo,
 };
+pub use security::{
+    apply_restore_statements, collect_security_inventory, diff_security, restore_statements,
+    SecurityDiff, SecurityInventory,
+};
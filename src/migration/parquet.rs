@@ -0,0 +1,648 @@
+// ABOUTME: Snapshot export to Parquet: streams a table's COPY output into a typed Parquet file
+// ABOUTME: Maps PostgreSQL column types to Arrow types; anything without an exact mapping is kept as text
+
+use crate::cancel::{self, CancellationToken};
+use anyhow::{bail, Context, Result};
+use arrow_array::builder::{
+    BinaryBuilder, BooleanBuilder, Date32Builder, Float32Builder, Float64Builder, Int16Builder,
+    Int32Builder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use futures::{pin_mut, StreamExt};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_postgres::types::Type;
+use tokio_postgres::Client;
+
+/// Rows buffered before they are handed to the Parquet writer
+const BATCH_ROWS: usize = 65_536;
+
+/// Session settings that make COPY's text output parseable
+///
+/// Run once on the export connection, before [`export_table`].
+pub const SESSION_SETTINGS: &str = "SET DateStyle = 'ISO, YMD'; SET TimeZone = 'UTC'; \
+     SET IntervalStyle = 'iso_8601'; SET extra_float_digits = 3; SET bytea_output = 'hex'";
+
+/// How one exported column is stored in Parquet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Boolean,
+    Int16,
+    Int32,
+    Int64,
+    Float32,
+    Float64,
+    Date,
+    Timestamp,
+    TimestampTz,
+    Binary,
+    /// Every other type (numeric, uuid, json, arrays, ...) in its text form,
+    /// which keeps numeric precision and round-trips into any engine
+    Text,
+}
+
+impl ColumnKind {
+    /// Storage for a PostgreSQL column type
+    pub fn from_pg_type(ty: &Type) -> Self {
+        match *ty {
+            Type::BOOL => ColumnKind::Boolean,
+            Type::INT2 => ColumnKind::Int16,
+            Type::INT4 => ColumnKind::Int32,
+            Type::INT8 | Type::OID => ColumnKind::Int64,
+            Type::FLOAT4 => ColumnKind::Float32,
+            Type::FLOAT8 => ColumnKind::Float64,
+            Type::DATE => ColumnKind::Date,
+            Type::TIMESTAMP => ColumnKind::Timestamp,
+            Type::TIMESTAMPTZ => ColumnKind::TimestampTz,
+            Type::BYTEA => ColumnKind::Binary,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnKind::Boolean => DataType::Boolean,
+            ColumnKind::Int16 => DataType::Int16,
+            ColumnKind::Int32 => DataType::Int32,
+            ColumnKind::Int64 => DataType::Int64,
+            ColumnKind::Float32 => DataType::Float32,
+            ColumnKind::Float64 => DataType::Float64,
+            ColumnKind::Date => DataType::Date32,
+            ColumnKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            ColumnKind::TimestampTz => {
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+            }
+            ColumnKind::Binary => DataType::Binary,
+            ColumnKind::Text => DataType::Utf8,
+        }
+    }
+}
+
+/// One column of an export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportColumn {
+    pub name: String,
+    pub kind: ColumnKind,
+}
+
+/// Output columns of `select`, as the source describes them
+///
+/// # Errors
+///
+/// Returns an error if the query does not parse on the source or repeats a
+/// column name.
+pub async fn query_columns(client: &Client, select: &str) -> Result<Vec<ExportColumn>> {
+    let statement = client
+        .prepare(select)
+        .await
+        .with_context(|| format!("Export query is not valid on the source: {}", select))?;
+    let mut columns: Vec<ExportColumn> = Vec::new();
+    for column in statement.columns() {
+        if columns.iter().any(|seen| seen.name == column.name()) {
+            bail!(
+                "Export query returns column '{}' more than once.\n\
+                 Alias each output column of the transform to a distinct name.",
+                column.name()
+            );
+        }
+        columns.push(ExportColumn {
+            name: column.name().to_string(),
+            kind: ColumnKind::from_pg_type(column.type_()),
+        });
+    }
+    Ok(columns)
+}
+
+/// Stream `select` from the source into a Parquet file at `path`
+///
+/// The query runs as `COPY (select) TO STDOUT` in text format on `client`,
+/// which must have [`SESSION_SETTINGS`] applied. Rows are written in
+/// Snappy-compressed row groups; the file appears at `path` only once it is
+/// complete, so a failed or cancelled export never leaves a truncated file
+/// behind.
+///
+/// # Arguments
+///
+/// * `client` - Source connection, usually inside a read-only snapshot
+/// * `select` - Query whose rows are exported
+/// * `columns` - Its output columns, from [`query_columns`]
+/// * `path` - Parquet file to write
+/// * `cancel` - Stops the export between chunks
+///
+/// # Errors
+///
+/// Returns an error if the COPY fails, a value cannot be converted, or the
+/// file cannot be written.
+pub async fn export_table(
+    client: &Client,
+    select: &str,
+    columns: &[ExportColumn],
+    path: &Path,
+    cancel: &CancellationToken,
+) -> Result<u64> {
+    let schema = Arc::new(Schema::new(
+        columns
+            .iter()
+            .map(|column| Field::new(&column.name, column.kind.data_type(), true))
+            .collect::<Vec<_>>(),
+    ));
+    let partial = path.with_extension("parquet.partial");
+    let file = std::fs::File::create(&partial)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+        .context("Failed to start Parquet file")?;
+
+    let reader = client
+        .copy_out(&format!("COPY ({}) TO STDOUT", select))
+        .await
+        .with_context(|| format!("Failed to copy from source: {}", select))?;
+    pin_mut!(reader);
+
+    let result = cancel::run(cancel, async {
+        let mut batch = Batch::new(columns);
+        let mut pending: Vec<u8> = Vec::new();
+        let mut rows = 0u64;
+        while let Some(chunk) = reader.next().await {
+            let data = chunk?;
+            crate::resources::record_source_read(data.len() as u64);
+            pending.extend_from_slice(&data);
+            // Text COPY escapes newlines inside values, so each raw newline ends a row
+            let mut start = 0;
+            while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+                let line = &pending[start..start + end];
+                batch.push(&split_copy_line(line)?)?;
+                rows += 1;
+                start += end + 1;
+                if batch.len() >= BATCH_ROWS {
+                    writer.write(&batch.finish(&schema)?)?;
+                }
+            }
+            pending.drain(..start);
+        }
+        if !pending.is_empty() {
+            bail!("COPY output ended in the middle of a row");
+        }
+        if batch.len() > 0 {
+            writer.write(&batch.finish(&schema)?)?;
+        }
+        for (column, count) in columns.iter().zip(&batch.infinite) {
+            if *count > 0 {
+                tracing::warn!(
+                    "  ⚠ {}: {} infinite value(s) in column '{}' written as NULL; Parquet dates and timestamps have no infinity",
+                    path.display(),
+                    count,
+                    column.name
+                );
+            }
+        }
+        Ok(rows)
+    })
+    .await;
+
+    let rows = match result {
+        Ok(rows) => rows,
+        Err(e) => {
+            drop(writer);
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    writer.close().context("Failed to finish Parquet file")?;
+    let written = std::fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+    crate::resources::record_target_written(written);
+    std::fs::rename(&partial, path)
+        .with_context(|| format!("Failed to move the export into place at {}", path.display()))?;
+    Ok(rows)
+}
+
+/// Split one row of text-format COPY output into its values (`None` for NULL)
+///
+/// # Errors
+///
+/// Returns an error if a value is not valid UTF-8.
+pub fn split_copy_line(line: &[u8]) -> Result<Vec<Option<String>>> {
+    let mut values = Vec::new();
+    for field in line.split(|&b| b == b'\t') {
+        if field == b"\\N" {
+            values.push(None);
+            continue;
+        }
+        let mut value = Vec::with_capacity(field.len());
+        let mut i = 0;
+        while i < field.len() {
+            if field[i] != b'\\' || i + 1 == field.len() {
+                value.push(field[i]);
+                i += 1;
+                continue;
+            }
+            i += 1;
+            match field[i] {
+                b'b' => value.push(0x08),
+                b'f' => value.push(0x0c),
+                b'n' => value.push(b'\n'),
+                b'r' => value.push(b'\r'),
+                b't' => value.push(b'\t'),
+                b'v' => value.push(0x0b),
+                b'0'..=b'7' => {
+                    let digits = field[i..]
+                        .iter()
+                        .take(3)
+                        .take_while(|b| (b'0'..=b'7').contains(*b))
+                        .count();
+                    let octal = std::str::from_utf8(&field[i..i + digits]).unwrap_or("0");
+                    value.push(u8::from_str_radix(octal, 8).unwrap_or(0));
+                    i += digits - 1;
+                }
+                b'x' if field.get(i + 1).is_some_and(u8::is_ascii_hexdigit) => {
+                    let digits = field[i + 1..]
+                        .iter()
+                        .take(2)
+                        .take_while(|b| b.is_ascii_hexdigit())
+                        .count();
+                    let hex = std::str::from_utf8(&field[i + 1..i + 1 + digits]).unwrap_or("0");
+                    value.push(u8::from_str_radix(hex, 16).unwrap_or(0));
+                    i += digits;
+                }
+                other => value.push(other),
+            }
+            i += 1;
+        }
+        values.push(Some(
+            String::from_utf8(value).context("COPY output is not valid UTF-8")?,
+        ));
+    }
+    Ok(values)
+}
+
+/// Rows being collected into the next record batch
+struct Batch<'a> {
+    columns: &'a [ExportColumn],
+    builders: Vec<ColumnBuilder>,
+    rows: usize,
+    /// Per column, `infinity`/`-infinity` values written as NULL
+    infinite: Vec<u64>,
+}
+
+enum ColumnBuilder {
+    Boolean(BooleanBuilder),
+    Int16(Int16Builder),
+    Int32(Int32Builder),
+    Int64(Int64Builder),
+    Float32(Float32Builder),
+    Float64(Float64Builder),
+    Date(Date32Builder),
+    Timestamp(TimestampMicrosecondBuilder),
+    TimestampTz(TimestampMicrosecondBuilder),
+    Binary(BinaryBuilder),
+    Text(StringBuilder),
+}
+
+impl<'a> Batch<'a> {
+    fn new(columns: &'a [ExportColumn]) -> Self {
+        Batch {
+            columns,
+            builders: columns.iter().map(|c| ColumnBuilder::new(c.kind)).collect(),
+            rows: 0,
+            infinite: vec![0; columns.len()],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.rows
+    }
+
+    fn push(&mut self, values: &[Option<String>]) -> Result<()> {
+        if values.len() != self.columns.len() {
+            bail!(
+                "COPY row has {} values but the export has {} columns",
+                values.len(),
+                self.columns.len()
+            );
+        }
+        for (((builder, column), value), infinite) in self
+            .builders
+            .iter_mut()
+            .zip(self.columns)
+            .zip(values)
+            .zip(&mut self.infinite)
+        {
+            let stored = builder.append(value.as_deref()).with_context(|| {
+                format!(
+                    "Column '{}' has a value Parquet cannot store: {}",
+                    column.name,
+                    value.as_deref().unwrap_or_default()
+                )
+            })?;
+            if !stored {
+                *infinite += 1;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Take the collected rows as a record batch, leaving the batch empty
+    fn finish(&mut self, schema: &Arc<Schema>) -> Result<RecordBatch> {
+        let arrays: Vec<ArrayRef> = self.builders.iter_mut().map(|b| b.finish()).collect();
+        self.rows = 0;
+        RecordBatch::try_new(schema.clone(), arrays).context("Failed to build record batch")
+    }
+}
+
+impl ColumnBuilder {
+    fn new(kind: ColumnKind) -> Self {
+        match kind {
+            ColumnKind::Boolean => ColumnBuilder::Boolean(BooleanBuilder::new()),
+            ColumnKind::Int16 => ColumnBuilder::Int16(Int16Builder::new()),
+            ColumnKind::Int32 => ColumnBuilder::Int32(Int32Builder::new()),
+            ColumnKind::Int64 => ColumnBuilder::Int64(Int64Builder::new()),
+            ColumnKind::Float32 => ColumnBuilder::Float32(Float32Builder::new()),
+            ColumnKind::Float64 => ColumnBuilder::Float64(Float64Builder::new()),
+            ColumnKind::Date => ColumnBuilder::Date(Date32Builder::new()),
+            ColumnKind::Timestamp => ColumnBuilder::Timestamp(TimestampMicrosecondBuilder::new()),
+            ColumnKind::TimestampTz => {
+                ColumnBuilder::TimestampTz(TimestampMicrosecondBuilder::new().with_timezone("UTC"))
+            }
+            ColumnKind::Binary => ColumnBuilder::Binary(BinaryBuilder::new()),
+            ColumnKind::Text => ColumnBuilder::Text(StringBuilder::new()),
+        }
+    }
+
+    /// Append a value; `false` when an infinite date or timestamp was written as NULL
+    fn append(&mut self, value: Option<&str>) -> Result<bool> {
+        match self {
+            ColumnBuilder::Boolean(b) => b.append_option(value.map(|v| v == "t")),
+            ColumnBuilder::Int16(b) => b.append_option(value.map(str::parse).transpose()?),
+            ColumnBuilder::Int32(b) => b.append_option(value.map(str::parse).transpose()?),
+            ColumnBuilder::Int64(b) => b.append_option(value.map(str::parse).transpose()?),
+            ColumnBuilder::Float32(b) => b.append_option(value.map(str::parse).transpose()?),
+            ColumnBuilder::Float64(b) => b.append_option(value.map(str::parse).transpose()?),
+            ColumnBuilder::Date(b) => {
+                let days = value.map(parse_date).transpose()?;
+                b.append_option(days.flatten());
+                return Ok(value.is_none() || days.flatten().is_some());
+            }
+            ColumnBuilder::Timestamp(b) | ColumnBuilder::TimestampTz(b) => {
+                let micros = value.map(parse_timestamp).transpose()?;
+                b.append_option(micros.flatten());
+                return Ok(value.is_none() || micros.flatten().is_some());
+            }
+            ColumnBuilder::Binary(b) => b.append_option(value.map(parse_bytea).transpose()?),
+            ColumnBuilder::Text(b) => b.append_option(value),
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Boolean(b) => Arc::new(b.finish()),
+            ColumnBuilder::Int16(b) => Arc::new(b.finish()),
+            ColumnBuilder::Int32(b) => Arc::new(b.finish()),
+            ColumnBuilder::Int64(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float32(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float64(b) => Arc::new(b.finish()),
+            ColumnBuilder::Date(b) => Arc::new(b.finish()),
+            ColumnBuilder::Timestamp(b) => Arc::new(b.finish()),
+            ColumnBuilder::TimestampTz(b) => Arc::new(b.finish().with_timezone("UTC")),
+            ColumnBuilder::Binary(b) => Arc::new(b.finish()),
+            ColumnBuilder::Text(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Days since 1970-01-01 of an ISO date (`YYYY-MM-DD`, or `YYYY-MM-DD BC`)
+///
+/// `infinity` and `-infinity` have no Parquet equivalent and give `None`.
+fn parse_date(value: &str) -> Result<Option<i32>> {
+    if is_infinite(value) {
+        return Ok(None);
+    }
+    let (date, bc) = strip_bc(value);
+    Ok(Some(i32::try_from(civil_days(date, bc)?)?))
+}
+
+/// Microseconds since the Unix epoch of an ISO timestamp, with an optional
+/// `+HH[:MM[:SS]]` offset and ` BC` suffix as `timestamptz` prints them
+///
+/// `infinity` and `-infinity` give `None`.
+fn parse_timestamp(value: &str) -> Result<Option<i64>> {
+    if is_infinite(value) {
+        return Ok(None);
+    }
+    let (value, bc) = strip_bc(value);
+    let (date, time) = value.split_once(' ').context("not an ISO timestamp")?;
+    let days = civil_days(date, bc)?;
+    let offset_at = time.find(['+', '-']);
+    let (clock, offset) = match offset_at {
+        Some(at) => (&time[..at], Some(&time[at..])),
+        None => (time, None),
+    };
+    let mut hms = clock.splitn(3, ':');
+    let (Some(hours), Some(minutes), Some(seconds)) = (hms.next(), hms.next(), hms.next()) else {
+        bail!("not an ISO timestamp");
+    };
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let micros = format!("{:0<6}", fraction)[..6].parse::<i64>()?;
+    let mut total = ((days * 24 + hours.parse::<i64>()?) * 60 + minutes.parse::<i64>()?) * 60
+        + whole.parse::<i64>()?;
+    if let Some(offset) = offset {
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let mut offset_seconds = 0;
+        for (part, scale) in offset[1..].split(':').zip([3600, 60, 1]) {
+            offset_seconds += part.parse::<i64>()? * scale;
+        }
+        total -= sign * offset_seconds;
+    }
+    Ok(Some(total * 1_000_000 + micros))
+}
+
+fn is_infinite(value: &str) -> bool {
+    matches!(value, "infinity" | "-infinity")
+}
+
+/// The value without PostgreSQL's ` BC` suffix, and whether it had one
+fn strip_bc(value: &str) -> (&str, bool) {
+    match value.strip_suffix(" BC") {
+        Some(value) => (value, true),
+        None => (value, false),
+    }
+}
+
+/// Days since 1970-01-01 of `YYYY-MM-DD`; BC years count back from 1 BC (year 0)
+fn civil_days(date: &str, bc: bool) -> Result<i64> {
+    let mut parts = date.splitn(3, '-');
+    let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next()) else {
+        bail!("not an ISO date");
+    };
+    let (year, month, day): (i64, i64, i64) = (year.parse()?, month.parse()?, day.parse()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        bail!("not an ISO date");
+    }
+    let year = if bc { 1 - year } else { year };
+    Ok(crate::jsonb::metadata::days_from_civil(year, month, day))
+}
+
+/// Bytes of a hex-format bytea value (`\x0a1b...`)
+fn parse_bytea(value: &str) -> Result<Vec<u8>> {
+    let hex = value
+        .strip_prefix("\\x")
+        .context("bytea value is not in hex format")?;
+    if hex.len() % 2 != 0 {
+        bail!("bytea value has an odd number of hex digits");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&hex[i..i + 2], 16)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_copy_line_unescapes_values() {
+        let values = split_copy_line(b"1\t\\N\ta\\tb\\nc\t\\\\x01\t\\101\\x42").unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Some("1".to_string()),
+                None,
+                Some("a\tb\nc".to_string()),
+                Some("\\x01".to_string()),
+                Some("AB".to_string()),
+            ]
+        );
+        assert_eq!(split_copy_line(b"").unwrap(), vec![Some(String::new())]);
+    }
+
+    #[test]
+    fn test_parse_dates_and_timestamps() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), Some(0));
+        assert_eq!(parse_date("2000-03-01").unwrap(), Some(11_017));
+        assert_eq!(parse_date("1969-12-31").unwrap(), Some(-1));
+        assert!(parse_date("1970-13-01").is_err());
+
+        assert_eq!(
+            parse_timestamp("1970-01-01 00:00:01").unwrap(),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-01 00:00:00.25").unwrap(),
+            Some(250_000)
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-01 02:00:00+02").unwrap(),
+            parse_timestamp("1970-01-01 00:00:00").unwrap()
+        );
+        assert_eq!(
+            parse_timestamp("1969-12-31 19:30:00-04:30").unwrap(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_infinity_has_no_value() {
+        assert_eq!(parse_date("infinity").unwrap(), None);
+        assert_eq!(parse_timestamp("infinity").unwrap(), None);
+    }
+
+    #[test]
+    fn test_negative_infinity_has_no_value() {
+        assert_eq!(parse_date("-infinity").unwrap(), None);
+        assert_eq!(parse_timestamp("-infinity").unwrap(), None);
+    }
+
+    #[test]
+    fn test_bc_dates_count_back_from_year_zero() {
+        // PostgreSQL: SELECT '0044-03-15 BC'::date - '1970-01-01'::date
+        assert_eq!(parse_date("0044-03-15 BC").unwrap(), Some(-735_160));
+        // 1 BC is the year before 0001-01-01, and was a leap year
+        assert_eq!(
+            parse_date("0001-12-31 BC").unwrap().unwrap() + 1,
+            parse_date("0001-01-01").unwrap().unwrap()
+        );
+        assert_eq!(
+            parse_date("0001-01-01 BC").unwrap().unwrap() + 366,
+            parse_date("0001-01-01").unwrap().unwrap()
+        );
+        assert_eq!(
+            parse_timestamp("0044-03-15 12:00:00+00 BC").unwrap(),
+            Some(
+                i64::from(parse_date("0044-03-15 BC").unwrap().unwrap()) * 86_400_000_000
+                    + 43_200_000_000
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_bytea_hex() {
+        assert_eq!(parse_bytea("\\x00ff10").unwrap(), vec![0x00, 0xff, 0x10]);
+        assert!(parse_bytea("\\x0").is_err());
+        assert!(parse_bytea("abc").is_err());
+    }
+
+    #[test]
+    fn test_batch_builds_typed_columns() {
+        let columns = vec![
+            ExportColumn {
+                name: "id".to_string(),
+                kind: ColumnKind::from_pg_type(&Type::INT8),
+            },
+            ExportColumn {
+                name: "price".to_string(),
+                kind: ColumnKind::from_pg_type(&Type::NUMERIC),
+            },
+            ExportColumn {
+                name: "active".to_string(),
+                kind: ColumnKind::from_pg_type(&Type::BOOL),
+            },
+        ];
+        assert_eq!(columns[1].kind, ColumnKind::Text);
+        let schema = Arc::new(Schema::new(
+            columns
+                .iter()
+                .map(|c| Field::new(&c.name, c.kind.data_type(), true))
+                .collect::<Vec<_>>(),
+        ));
+        let mut batch = Batch::new(&columns);
+        batch
+            .push(&[Some("7".into()), Some("1.50".into()), Some("t".into())])
+            .unwrap();
+        batch.push(&[Some("8".into()), None, None]).unwrap();
+        assert!(batch.push(&[Some("x".into()), None, None]).is_err());
+        let record = batch.finish(&schema).unwrap();
+        assert_eq!(record.num_rows(), 2);
+        assert_eq!(record.column(1).null_count(), 1);
+        assert_eq!(batch.len(), 0);
+    }
+
+    #[test]
+    fn test_batch_writes_infinity_as_null() {
+        let columns = vec![ExportColumn {
+            name: "valid_until".to_string(),
+            kind: ColumnKind::from_pg_type(&Type::TIMESTAMPTZ),
+        }];
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "valid_until",
+            columns[0].kind.data_type(),
+            true,
+        )]));
+        let mut batch = Batch::new(&columns);
+        batch.push(&[Some("infinity".into())]).unwrap();
+        batch
+            .push(&[Some("2024-01-15 10:30:00+00".into())])
+            .unwrap();
+        batch.push(&[None]).unwrap();
+        assert_eq!(batch.infinite, vec![1]);
+        let record = batch.finish(&schema).unwrap();
+        assert_eq!(record.column(0).null_count(), 2);
+    }
+}
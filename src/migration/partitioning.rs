@@ -0,0 +1,447 @@
+// ABOUTME: Turns selected tables into range-partitioned tables on the target
+// ABOUTME: Rewrites the schema dump, then creates partitions from the source's data range
+
+use super::dump::dump_entries;
+use crate::table_rules::PartitionRule;
+use crate::utils::{quote_ident, quote_qualified};
+use anyhow::{bail, Context, Result};
+use tokio_postgres::Client;
+
+/// More partitions than this means the interval is too fine for the data
+pub const MAX_PARTITIONS: usize = 1000;
+
+const PARTITIONABLE_TYPES: &[&str] = &[
+    "date",
+    "timestamp without time zone",
+    "timestamp with time zone",
+];
+
+/// A table to partition, with the names as pg_dump writes them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionTarget {
+    pub schema: String,
+    pub table: String,
+    pub rule: PartitionRule,
+    /// `schema.table`, quoted only where needed (pg_dump's form)
+    pub dump_name: String,
+    /// The partition column, quoted only where needed
+    pub dump_column: String,
+}
+
+/// Check each rule against the source catalog and resolve its dump names
+///
+/// # Errors
+///
+/// Returns an error if a table or column does not exist, the column is not a
+/// `date` or `timestamp`, or the table is already partitioned or inherits.
+pub async fn resolve_targets(
+    source: &Client,
+    rules: Vec<(String, String, PartitionRule)>,
+) -> Result<Vec<PartitionTarget>> {
+    let mut targets = Vec::with_capacity(rules.len());
+    for (schema, table, rule) in rules {
+        let row = source
+            .query_opt(
+                "SELECT quote_ident(n.nspname) || '.' || quote_ident(c.relname), \
+                        quote_ident(a.attname), \
+                        format_type(a.atttypid, NULL), \
+                        c.relkind::text, \
+                        EXISTS (SELECT 1 FROM pg_inherits i WHERE i.inhrelid = c.oid) \
+                 FROM pg_class c \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 LEFT JOIN pg_attribute a ON a.attrelid = c.oid \
+                      AND a.attname = $3 AND NOT a.attisdropped \
+                 WHERE n.nspname = $1 AND c.relname = $2",
+                &[&schema, &table, &rule.column],
+            )
+            .await
+            .with_context(|| format!("Failed to look up {}.{} on source", schema, table))?;
+        let Some(row) = row else {
+            bail!(
+                "Partition rule for {}.{}: table not found on source",
+                schema,
+                table
+            );
+        };
+        let dump_name: String = row.get(0);
+        let dump_column: Option<String> = row.get(1);
+        let column_type: Option<String> = row.get(2);
+        let relkind: String = row.get(3);
+        let inherits: bool = row.get(4);
+
+        let (Some(dump_column), Some(column_type)) = (dump_column, column_type) else {
+            bail!(
+                "Partition rule for {}.{}: column '{}' not found",
+                schema,
+                table,
+                rule.column
+            );
+        };
+        if !PARTITIONABLE_TYPES.contains(&column_type.as_str()) {
+            bail!(
+                "Partition rule for {}.{}: column '{}' is {}, expected date or timestamp",
+                schema,
+                table,
+                rule.column,
+                column_type
+            );
+        }
+        if relkind != "r" || inherits {
+            bail!(
+                "Partition rule for {}.{}: only plain tables can be partitioned on load \
+                 (this one is already partitioned, inherits, or is not a table)",
+                schema,
+                table
+            );
+        }
+        targets.push(PartitionTarget {
+            schema,
+            table,
+            rule,
+            dump_name,
+            dump_column,
+        });
+    }
+    Ok(targets)
+}
+
+/// Drop `ONLY` in front of a partitioned table so ALTERs reach its partitions
+fn strip_only(section: &str, dump_name: &str) -> String {
+    section
+        .replace(
+            &format!(" ONLY {};", dump_name),
+            &format!(" {};", dump_name),
+        )
+        .replace(
+            &format!(" ONLY {}\n", dump_name),
+            &format!(" {}\n", dump_name),
+        )
+        .replace(
+            &format!(" ONLY {} ", dump_name),
+            &format!(" {} ", dump_name),
+        )
+}
+
+/// Rewrite a pre-data dump so each target table is created `PARTITION BY RANGE`
+///
+/// # Errors
+///
+/// Returns an error if a table's `CREATE TABLE` cannot be found in the dump.
+pub fn rewrite_pre_data(sql: &str, targets: &[PartitionTarget]) -> Result<String> {
+    let mut output = String::with_capacity(sql.len());
+    let mut converted = vec![false; targets.len()];
+    for (header, section) in dump_entries(sql) {
+        let mut section = section.to_string();
+        for (index, target) in targets.iter().enumerate() {
+            let is_table = header.as_ref().is_some_and(|h| {
+                h.object_type == "TABLE" && h.schema == target.schema && h.name == target.table
+            });
+            let create = format!("CREATE TABLE {} (", target.dump_name);
+            if let Some(start) = section.find(&create).filter(|_| is_table) {
+                let Some(end) = section[start..].find("\n);") else {
+                    bail!(
+                        "Cannot partition {}.{}: unterminated CREATE TABLE in schema dump",
+                        target.schema,
+                        target.table
+                    );
+                };
+                let end = start + end;
+                section.replace_range(
+                    end..end + 3,
+                    &format!("\n)\nPARTITION BY RANGE ({});", target.dump_column),
+                );
+                converted[index] = true;
+            }
+            section = strip_only(&section, &target.dump_name);
+        }
+        output.push_str(&section);
+    }
+    if let Some(index) = converted.iter().position(|done| !done) {
+        bail!(
+            "Cannot partition {}.{}: table not found in schema dump",
+            targets[index].schema,
+            targets[index].table
+        );
+    }
+    Ok(output)
+}
+
+/// Add the partition column to a PRIMARY KEY or UNIQUE column list
+fn extend_key(section: &str, column: &str) -> Option<String> {
+    let keyword = ["PRIMARY KEY", "UNIQUE"]
+        .iter()
+        .find_map(|keyword| section.find(&format!(" {} ", keyword)))?;
+    let open = keyword + section[keyword..].find('(')?;
+    let close = open + section[open..].find(')')?;
+    let columns: Vec<&str> = section[open + 1..close].split(',').map(str::trim).collect();
+    if columns.contains(&column) {
+        return None;
+    }
+    let mut rewritten = section.to_string();
+    rewritten.insert_str(close, &format!(", {}", column));
+    Some(rewritten)
+}
+
+/// Rewrite a post-data dump for tables that were partitioned in pre-data
+///
+/// PRIMARY KEY and UNIQUE constraints gain the partition column, which
+/// PostgreSQL requires on partitioned tables. Foreign keys that reference a
+/// partitioned table are left out, because a key on the parent no longer
+/// matches the referenced columns.
+///
+/// # Returns
+///
+/// The rewritten SQL and one note per changed or removed constraint.
+pub fn rewrite_post_data(sql: &str, targets: &[PartitionTarget]) -> (String, Vec<String>) {
+    let mut output = String::with_capacity(sql.len());
+    let mut notes = Vec::new();
+    'entries: for (header, section) in dump_entries(sql) {
+        let mut section = section.to_string();
+        for target in targets {
+            let Some(header) = header.as_ref() else {
+                continue;
+            };
+            let on_table = header.schema == target.schema
+                && header
+                    .name
+                    .strip_prefix(target.table.as_str())
+                    .is_some_and(|rest| rest.starts_with(' '));
+            match header.object_type {
+                "CONSTRAINT" if on_table => {
+                    if let Some(rewritten) = extend_key(&section, &target.dump_column) {
+                        notes.push(format!(
+                            "{}: added '{}' to constraint {}",
+                            target.dump_name, target.rule.column, header.name
+                        ));
+                        section = rewritten;
+                    }
+                }
+                "FK CONSTRAINT"
+                    if section.contains(&format!("REFERENCES {}(", target.dump_name)) =>
+                {
+                    notes.push(format!(
+                        "{}: dropped foreign key {}.{} that references it",
+                        target.dump_name, header.schema, header.name
+                    ));
+                    continue 'entries;
+                }
+                _ => {}
+            }
+            section = strip_only(&section, &target.dump_name);
+        }
+        output.push_str(&section);
+    }
+    (output, notes)
+}
+
+/// Create the range partitions and a DEFAULT partition for one table
+///
+/// Bounds run from the source's oldest row through one interval past the
+/// later of its newest row and now. Rows outside that range land in the
+/// DEFAULT partition.
+///
+/// # Returns
+///
+/// The number of partitions created, including the default.
+///
+/// # Errors
+///
+/// Returns an error if the range would need more than [`MAX_PARTITIONS`]
+/// partitions or a partition cannot be created.
+pub async fn create_partitions(
+    source: &Client,
+    target: &Client,
+    partition: &PartitionTarget,
+) -> Result<usize> {
+    let interval = partition.rule.interval;
+    let query = format!(
+        "WITH bounds AS ( \
+             SELECT date_trunc($1, coalesce(min({col})::timestamp, now()::timestamp)) AS lo, \
+                    date_trunc($1, greatest(max({col})::timestamp, now()::timestamp)) AS hi \
+             FROM {table}) \
+         SELECT to_char(g, $3), \
+                to_char(g, 'YYYY-MM-DD HH24:MI:SS'), \
+                to_char(g + $2::text::interval, 'YYYY-MM-DD HH24:MI:SS') \
+         FROM bounds, generate_series(bounds.lo, bounds.hi + $2::text::interval, $2::text::interval) g",
+        col = quote_ident(&partition.rule.column),
+        table = quote_qualified(&partition.schema, &partition.table),
+    );
+    let rows = source
+        .query(
+            &query,
+            &[
+                &interval.date_trunc_unit(),
+                &interval.interval(),
+                &interval.name_format(),
+            ],
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to compute partition bounds for {}.{}",
+                partition.schema, partition.table
+            )
+        })?;
+    if rows.len() > MAX_PARTITIONS {
+        bail!(
+            "Partitioning {}.{} {} would create {} partitions (limit {}). \
+             Use a coarser interval.",
+            partition.schema,
+            partition.table,
+            interval,
+            rows.len(),
+            MAX_PARTITIONS
+        );
+    }
+
+    let parent = quote_qualified(&partition.schema, &partition.table);
+    let mut statements = Vec::with_capacity(rows.len() + 1);
+    for row in &rows {
+        let suffix: String = row.get(0);
+        let from: String = row.get(1);
+        let to: String = row.get(2);
+        let name = partition_name(&partition.table, &format!("_p{}", suffix));
+        statements.push(format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ('{}') TO ('{}');",
+            quote_qualified(&partition.schema, &name),
+            parent,
+            from,
+            to
+        ));
+    }
+    statements.push(format!(
+        "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} DEFAULT;",
+        quote_qualified(
+            &partition.schema,
+            &partition_name(&partition.table, "_default")
+        ),
+        parent
+    ));
+    target
+        .batch_execute(&statements.join("\n"))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to create partitions for {}.{} on target",
+                partition.schema, partition.table
+            )
+        })?;
+    Ok(statements.len())
+}
+
+/// `{table}{suffix}`, with the table part shortened to fit in 63 bytes
+fn partition_name(table: &str, suffix: &str) -> String {
+    let mut end = table.len().min(63 - suffix.len());
+    while !table.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &table[..end], suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table_rules::PartitionInterval;
+
+    fn entry(name: &str, object_type: &str, schema: &str, body: &str) -> String {
+        format!(
+            "--\n-- Name: {}; Type: {}; Schema: {}; Owner: -\n--\n\n{}\n\n",
+            name, object_type, schema, body
+        )
+    }
+
+    fn events() -> PartitionTarget {
+        PartitionTarget {
+            schema: "public".to_string(),
+            table: "events".to_string(),
+            rule: PartitionRule {
+                column: "created_at".to_string(),
+                interval: PartitionInterval::Monthly,
+            },
+            dump_name: "public.events".to_string(),
+            dump_column: "created_at".to_string(),
+        }
+    }
+
+    #[test]
+    fn pre_data_creates_partitioned_table() {
+        let sql = [
+            entry(
+                "events",
+                "TABLE",
+                "public",
+                "CREATE TABLE public.events (\n    id bigint NOT NULL,\n    created_at timestamp with time zone\n);",
+            ),
+            entry(
+                "events id",
+                "DEFAULT",
+                "public",
+                "ALTER TABLE ONLY public.events ALTER COLUMN id SET DEFAULT nextval('public.events_id_seq'::regclass);",
+            ),
+            entry(
+                "users",
+                "TABLE",
+                "public",
+                "CREATE TABLE public.users (\n    id bigint NOT NULL\n);",
+            ),
+        ]
+        .concat();
+        let rewritten = rewrite_pre_data(&sql, &[events()]).unwrap();
+        assert!(rewritten
+            .contains("created_at timestamp with time zone\n)\nPARTITION BY RANGE (created_at);"));
+        assert!(rewritten.contains("ALTER TABLE public.events ALTER COLUMN id"));
+        assert!(rewritten.contains("CREATE TABLE public.users (\n    id bigint NOT NULL\n);"));
+
+        let mut missing = events();
+        missing.table = "audit".to_string();
+        assert!(rewrite_pre_data(&sql, &[missing]).is_err());
+    }
+
+    #[test]
+    fn post_data_extends_keys_and_drops_referencing_fks() {
+        let sql = [
+            entry(
+                "events events_pkey",
+                "CONSTRAINT",
+                "public",
+                "ALTER TABLE ONLY public.events\n    ADD CONSTRAINT events_pkey PRIMARY KEY (id);",
+            ),
+            entry(
+                "events events_uuid_key",
+                "CONSTRAINT",
+                "public",
+                "ALTER TABLE ONLY public.events\n    ADD CONSTRAINT events_uuid_key UNIQUE (uuid, created_at);",
+            ),
+            entry(
+                "events_archive events_archive_pkey",
+                "CONSTRAINT",
+                "public",
+                "ALTER TABLE ONLY public.events_archive\n    ADD CONSTRAINT events_archive_pkey PRIMARY KEY (id);",
+            ),
+            entry(
+                "comments comments_event_id_fkey",
+                "FK CONSTRAINT",
+                "public",
+                "ALTER TABLE ONLY public.comments\n    ADD CONSTRAINT comments_event_id_fkey FOREIGN KEY (event_id) REFERENCES public.events(id);",
+            ),
+        ]
+        .concat();
+        let (rewritten, notes) = rewrite_post_data(&sql, &[events()]);
+        assert!(rewritten.contains(
+            "ALTER TABLE public.events\n    ADD CONSTRAINT events_pkey PRIMARY KEY (id, created_at);"
+        ));
+        assert!(rewritten.contains("UNIQUE (uuid, created_at);"));
+        assert!(rewritten.contains(
+            "ALTER TABLE ONLY public.events_archive\n    ADD CONSTRAINT events_archive_pkey PRIMARY KEY (id);"
+        ));
+        assert!(!rewritten.contains("comments_event_id_fkey"));
+        assert_eq!(notes.len(), 2);
+    }
+
+    #[test]
+    fn partition_names_fit_identifier_limit() {
+        assert_eq!(partition_name("events", "_p202401"), "events_p202401");
+        let long = "e".repeat(70);
+        assert_eq!(partition_name(&long, "_default").len(), 63);
+    }
+}
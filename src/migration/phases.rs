@@ -0,0 +1,174 @@
+// ABOUTME: Ordered restore phases (pre-data, data, post-data, privileges, analyze) for init
+// ABOUTME: Selects which phases a run performs via --start-at-phase/--stop-after-phase
+
+use crate::checkpoint::InitScope;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// One step of restoring a database, in execution order
+///
+/// Each phase is recorded in the init checkpoint when it starts and when it
+/// completes, so an interrupted run resumes at the phase that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestorePhase {
+    /// Create the database and restore tables, types, functions, and other
+    /// definitions (`pg_dump --section=pre-data`)
+    PreData,
+    /// Copy table contents
+    Data,
+    /// Create indexes, constraints, triggers, and rules (`pg_dump --section=post-data`)
+    PostData,
+    /// Reapply row-level security and grants left out by `--no-privileges`
+    Privileges,
+    /// Refresh planner statistics with `ANALYZE`
+    Analyze,
+}
+
+impl RestorePhase {
+    pub const ALL: [RestorePhase; 5] = [
+        RestorePhase::PreData,
+        RestorePhase::Data,
+        RestorePhase::PostData,
+        RestorePhase::Privileges,
+        RestorePhase::Analyze,
+    ];
+
+    /// Name accepted by `--start-at-phase` and `--stop-after-phase`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RestorePhase::PreData => "pre-data",
+            RestorePhase::Data => "data",
+            RestorePhase::PostData => "post-data",
+            RestorePhase::Privileges => "privileges",
+            RestorePhase::Analyze => "analyze",
+        }
+    }
+
+    /// Phases an init scope covers, in order
+    ///
+    /// `--schema-only` restores both schema sections so the staged schema is
+    /// complete; `--data-only` does the rest.
+    pub fn for_scope(scope: InitScope) -> &'static [RestorePhase] {
+        match scope {
+            InitScope::Full => &RestorePhase::ALL,
+            InitScope::SchemaOnly => &[RestorePhase::PreData, RestorePhase::PostData],
+            InitScope::DataOnly => &[
+                RestorePhase::Data,
+                RestorePhase::Privileges,
+                RestorePhase::Analyze,
+            ],
+        }
+    }
+}
+
+impl fmt::Display for RestorePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RestorePhase {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        RestorePhase::ALL
+            .into_iter()
+            .find(|phase| phase.as_str() == s.to_ascii_lowercase())
+            .with_context(|| {
+                format!(
+                    "Unknown restore phase '{}'. Expected one of: pre-data, data, post-data, privileges, analyze",
+                    s
+                )
+            })
+    }
+}
+
+/// Inclusive range of phases a run performs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseRange {
+    pub start: RestorePhase,
+    pub stop: RestorePhase,
+}
+
+impl Default for PhaseRange {
+    fn default() -> Self {
+        Self {
+            start: RestorePhase::PreData,
+            stop: RestorePhase::Analyze,
+        }
+    }
+}
+
+impl PhaseRange {
+    /// Build a range from `--start-at-phase` and `--stop-after-phase`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `start` comes after `stop`.
+    pub fn new(start: Option<RestorePhase>, stop: Option<RestorePhase>) -> Result<Self> {
+        let range = Self {
+            start: start.unwrap_or(RestorePhase::PreData),
+            stop: stop.unwrap_or(RestorePhase::Analyze),
+        };
+        if range.start > range.stop {
+            bail!(
+                "--start-at-phase {} comes after --stop-after-phase {}.\n\
+                 Phases run in order: pre-data, data, post-data, privileges, analyze.",
+                range.start,
+                range.stop
+            );
+        }
+        Ok(range)
+    }
+
+    pub fn includes(&self, phase: RestorePhase) -> bool {
+        self.start <= phase && phase <= self.stop
+    }
+
+    /// The first phase of `scope` this range leaves for a later run, if any
+    pub fn next_after(&self, scope: InitScope) -> Option<RestorePhase> {
+        RestorePhase::for_scope(scope)
+            .iter()
+            .copied()
+            .find(|phase| *phase > self.stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_phase() {
+        assert_eq!(
+            "post-data".parse::<RestorePhase>().unwrap(),
+            RestorePhase::PostData
+        );
+        assert_eq!(
+            "ANALYZE".parse::<RestorePhase>().unwrap(),
+            RestorePhase::Analyze
+        );
+        assert!("indexes".parse::<RestorePhase>().is_err());
+    }
+
+    #[test]
+    fn test_phase_range() {
+        let range =
+            PhaseRange::new(Some(RestorePhase::Data), Some(RestorePhase::PostData)).unwrap();
+        assert!(!range.includes(RestorePhase::PreData));
+        assert!(range.includes(RestorePhase::Data));
+        assert!(range.includes(RestorePhase::PostData));
+        assert!(!range.includes(RestorePhase::Analyze));
+        assert_eq!(
+            range.next_after(InitScope::Full),
+            Some(RestorePhase::Privileges)
+        );
+        assert_eq!(range.next_after(InitScope::SchemaOnly), None);
+
+        assert!(PhaseRange::new(Some(RestorePhase::Analyze), Some(RestorePhase::Data)).is_err());
+        assert_eq!(PhaseRange::new(None, None).unwrap(), PhaseRange::default());
+    }
+}
@@ -1,12 +1,69 @@
 // ABOUTME: Wrapper for psql and pg_restore to import database objects
 // ABOUTME: Restores global objects, schema, and data to target
 
+use crate::cancel::CancellationToken;
+use crate::events::{self, Phase, TableEvent, TableOutcome};
+use crate::settings::RunSettings;
+use crate::table_rules::TableRules;
 use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Parallelism and session settings for restoring data
+///
+/// `synchronous_commit` is always off in restore sessions: a crash mid-load
+/// means re-running the restore anyway, so waiting for WAL flushes only
+/// slows it down.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreTuning {
+    /// pg_restore `--jobs`; `None` uses the run's job budget
+    pub jobs: Option<usize>,
+    /// Load with `session_replication_role = replica`, so user triggers and
+    /// foreign-key checks do not fire (needs superuser, or SET privilege on PG15+)
+    pub disable_triggers: bool,
+    /// `maintenance_work_mem` in bytes for restore sessions, which speeds up
+    /// the index builds of the post-data step
+    pub maintenance_work_mem: Option<i64>,
+}
+
+impl RestoreTuning {
+    /// Number of pg_restore jobs for the data step
+    pub fn jobs(&self, settings: &RunSettings) -> usize {
+        self.jobs
+            .unwrap_or_else(|| settings.jobs.dump_restore_jobs(&settings.memory))
+    }
+
+    /// `PGOPTIONS` for the pg_restore sessions loading data
+    pub fn data_pg_options(&self) -> String {
+        let mut options = vec!["-c synchronous_commit=off".to_string()];
+        if self.disable_triggers {
+            options.push("-c session_replication_role=replica".to_string());
+        }
+        options.extend(self.maintenance_work_mem_option());
+        options.join(" ")
+    }
+
+    /// `PGOPTIONS` for psql schema restores, if any tuning applies to them
+    pub fn schema_pg_options(&self) -> Option<String> {
+        self.maintenance_work_mem_option()
+    }
+
+    fn maintenance_work_mem_option(&self) -> Option<String> {
+        self.maintenance_work_mem
+            .map(|bytes| format!("-c maintenance_work_mem={}kB", (bytes / 1024).max(1024)))
+    }
+}
 
 /// Restore global objects using psql
-pub async fn restore_globals(target_url: &str, input_path: &str) -> Result<()> {
+pub async fn restore_globals(
+    target_url: &str,
+    input_path: &str,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
     tracing::info!("Restoring global objects from {}", input_path);
 
     // Parse URL and create .pgpass file for secure authentication
@@ -51,7 +108,7 @@ pub async fn restore_globals(target_url: &str, input_path: &str) -> Result<()> {
                 cmd.env(env_var, value);
             }
 
-            cmd.status().context(
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
                 "Failed to execute psql. Is PostgreSQL client installed?\n\
                  Install with:\n\
                  - Ubuntu/Debian: sudo apt-get install postgresql-client\n\
@@ -59,8 +116,7 @@ pub async fn restore_globals(target_url: &str, input_path: &str) -> Result<()> {
                  - RHEL/CentOS: sudo yum install postgresql",
             )
         },
-        3,                      // Max 3 retries
-        Duration::from_secs(1), // Start with 1 second delay
+        &settings.retry,
         "psql (restore globals)",
     );
 
@@ -78,8 +134,14 @@ pub async fn restore_globals(target_url: &str, input_path: &str) -> Result<()> {
     }
 }
 
-/// Restore schema using psql
-pub async fn restore_schema(target_url: &str, input_path: &str) -> Result<()> {
+/// Restore schema using psql, with the `maintenance_work_mem` of `tuning`
+pub async fn restore_schema(
+    target_url: &str,
+    input_path: &str,
+    tuning: &RestoreTuning,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
     tracing::info!("Restoring schema from {}", input_path);
 
     // Parse URL and create .pgpass file for secure authentication
@@ -90,6 +152,7 @@ pub async fn restore_schema(target_url: &str, input_path: &str) -> Result<()> {
 
     let env_vars = parts.to_pg_env_vars();
     let input_path_owned = input_path.to_string();
+    let pg_options = tuning.schema_pg_options();
 
     // Wrap subprocess execution with retry logic
     crate::utils::retry_subprocess_with_backoff(
@@ -119,12 +182,16 @@ pub async fn restore_schema(target_url: &str, input_path: &str) -> Result<()> {
                 cmd.env(env_var, value);
             }
 
+            if let Some(options) = &pg_options {
+                cmd.env("PGOPTIONS", options);
+            }
+
             // Apply TCP keepalive parameters to prevent idle connection timeouts
             for (env_var, value) in crate::utils::get_keepalive_env_vars() {
                 cmd.env(env_var, value);
             }
 
-            cmd.status().context(
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
                 "Failed to execute psql. Is PostgreSQL client installed?\n\
                  Install with:\n\
                  - Ubuntu/Debian: sudo apt-get install postgresql-client\n\
@@ -132,8 +199,7 @@ pub async fn restore_schema(target_url: &str, input_path: &str) -> Result<()> {
                  - RHEL/CentOS: sudo yum install postgresql",
             )
         },
-        3,                      // Max 3 retries
-        Duration::from_secs(1), // Start with 1 second delay
+        &settings.retry,
         "psql (restore schema)",
     )
     .context(
@@ -159,19 +225,215 @@ pub async fn restore_schema(target_url: &str, input_path: &str) -> Result<()> {
 /// - Automatic decompression of compressed dump files
 /// - Optimized for directory format dumps created by dump_data()
 ///
-/// The number of parallel jobs is automatically determined based on available CPU cores.
-pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
-    // Determine optimal number of parallel jobs (number of CPUs, capped at 8)
-    let num_cpus = std::thread::available_parallelism()
-        .map(|n| n.get().min(8))
-        .unwrap_or(4);
+/// The number of parallel jobs comes from `tuning` (`--restore-jobs`),
+/// or else the run's job budget (`--max-total-jobs`), defaulting to the
+/// available CPU cores capped at 8. The sessions run with the tuning's
+/// `PGOPTIONS`.
+///
+/// The dump is first checked against the manifest `dump_data` wrote, so a
+/// truncated or corrupted dump fails before pg_restore starts.
+pub async fn restore_data(
+    target_url: &str,
+    input_path: &str,
+    tuning: &RestoreTuning,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let manifest = super::manifest::verify_manifest(std::path::Path::new(input_path))?;
+
+    let num_cpus = tuning.jobs(settings);
+    let pg_options = tuning.data_pg_options();
 
     tracing::info!(
-        "Restoring data from {} (parallel={}, format=directory)",
+        "Restoring data from {} (parallel={}, format=directory, {})",
+        input_path,
+        num_cpus,
+        pg_options
+    );
+
+    run_pg_restore_data(
+        target_url,
         input_path,
+        num_cpus,
+        None,
+        &pg_options,
+        settings,
+        cancel,
+    )?;
+
+    tracing::info!(
+        "✓ Data restored successfully using {} parallel jobs",
         num_cpus
     );
+    crate::resources::record_target_written(manifest.total_bytes());
+    Ok(())
+}
 
+/// Restore data table by table, in table-rule priority order
+///
+/// Used instead of [`restore_data`] when the table rules give the database
+/// priorities or a `--copy-order`. pg_restore schedules its parallel jobs by
+/// size, so each table gets its own pg_restore run instead, started in
+/// [`super::copy_order::schedule`] order by as many workers as
+/// [`restore_data`] would use jobs. Sequence values and large objects are
+/// restored after the tables.
+///
+/// # Errors
+///
+/// Returns an error if the dump does not match its manifest, its table of
+/// contents cannot be read, or a table fails to restore.
+pub async fn restore_data_in_order(
+    target_url: &str,
+    input_path: &str,
+    database: &str,
+    rules: &TableRules,
+    tuning: &RestoreTuning,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    use super::copy_order;
+
+    let manifest = super::manifest::verify_manifest(std::path::Path::new(input_path))?;
+    let (tables, rest) = copy_order::schedule(
+        copy_order::parse_toc(&copy_order::list_contents(input_path)?),
+        database,
+        rules,
+        &manifest,
+    );
+
+    let workers = tuning.jobs(settings).clamp(1, tables.len().max(1));
+    let pg_options = tuning.data_pg_options();
+    tracing::info!(
+        "Restoring data from {} table by table ({} table(s), {} order, {} worker(s), {})",
+        input_path,
+        tables.len(),
+        rules.copy_order(),
+        workers,
+        pg_options
+    );
+    for table in tables.iter().filter(|table| table.priority != 0) {
+        tracing::info!(
+            "  Priority {}: {}.{}",
+            table.priority,
+            table.schema,
+            table.table
+        );
+    }
+
+    // pg_restore --use-list files live next to the dump, which stays as pg_dump wrote it
+    let lists_dir = PathBuf::from(format!("{}.lists", input_path));
+    std::fs::create_dir_all(&lists_dir)
+        .with_context(|| format!("Failed to create {}", lists_dir.display()))?;
+    let write_list = |name: String, lines: Vec<&str>| -> Result<PathBuf> {
+        let path = lists_dir.join(name);
+        std::fs::write(&path, lines.join("\n") + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    };
+
+    let queue = Mutex::new(tables.into_iter().collect::<VecDeque<_>>());
+    let failed = AtomicBool::new(false);
+    let first_error = Mutex::new(None);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if failed.load(Ordering::Relaxed) {
+                    return;
+                }
+                let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+                let Some(table) = next else {
+                    return;
+                };
+                let event = TableEvent::new(Phase::Copy, database, &table.schema, &table.table);
+                events::table_started(&event);
+                let result = write_list(
+                    format!("{}.list", table.entry.dump_id),
+                    vec![table.entry.line.as_str()],
+                )
+                .and_then(|list| {
+                    run_pg_restore_data(
+                        target_url,
+                        input_path,
+                        1,
+                        Some(&list),
+                        &pg_options,
+                        settings,
+                        cancel,
+                    )
+                })
+                .with_context(|| format!("Failed to restore {}.{}", table.schema, table.table));
+                match result {
+                    Ok(()) => {
+                        events::table_completed(&event, &TableOutcome::Success { rows: None });
+                        if table.priority != 0 {
+                            tracing::info!(
+                                "  ✓ {}.{} restored (priority {})",
+                                table.schema,
+                                table.table,
+                                table.priority
+                            );
+                        } else {
+                            tracing::debug!("  ✓ {}.{} restored", table.schema, table.table);
+                        }
+                    }
+                    Err(e) => {
+                        events::table_completed(
+                            &event,
+                            &TableOutcome::Failed {
+                                error: format!("{:#}", e),
+                            },
+                        );
+                        failed.store(true, Ordering::Relaxed);
+                        first_error
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .get_or_insert(e);
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    if let Some(e) = first_error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+        return Err(e);
+    }
+
+    if !rest.is_empty() {
+        let list = write_list(
+            "remaining.list".to_string(),
+            rest.iter().map(|entry| entry.line.as_str()).collect(),
+        )?;
+        run_pg_restore_data(
+            target_url,
+            input_path,
+            1,
+            Some(&list),
+            &pg_options,
+            settings,
+            cancel,
+        )?;
+    }
+
+    tracing::info!(
+        "✓ Data restored successfully table by table using {} worker(s)",
+        workers
+    );
+    crate::resources::record_target_written(manifest.total_bytes());
+    Ok(())
+}
+
+/// Run one `pg_restore --data-only` of `input_path`, retrying on failure
+///
+/// With `list_file`, only the entries listed in it are restored.
+fn run_pg_restore_data(
+    target_url: &str,
+    input_path: &str,
+    jobs: usize,
+    list_file: Option<&std::path::Path>,
+    pg_options: &str,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
     // Parse URL and create .pgpass file for secure authentication
     let parts = crate::utils::parse_postgres_url(target_url)
         .with_context(|| format!("Failed to parse target URL: {}", target_url))?;
@@ -179,7 +441,6 @@ pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
         .context("Failed to create .pgpass file for authentication")?;
 
     let env_vars = parts.to_pg_env_vars();
-    let input_path_owned = input_path.to_string();
 
     // Wrap subprocess execution with retry logic
     crate::utils::retry_subprocess_with_backoff(
@@ -187,7 +448,7 @@ pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
             let mut cmd = Command::new("pg_restore");
             cmd.arg("--data-only")
                 .arg("--no-owner")
-                .arg(format!("--jobs={}", num_cpus)) // Parallel restore jobs
+                .arg(format!("--jobs={}", jobs)) // Parallel restore jobs
                 .arg("--host")
                 .arg(&parts.host)
                 .arg("--port")
@@ -196,11 +457,15 @@ pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
                 .arg(&parts.database)
                 .arg("--format=directory") // Directory format
                 .arg("--verbose") // Show progress
-                .arg(&input_path_owned)
+                .arg(input_path)
                 .env("PGPASSFILE", pgpass.path())
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit());
 
+            if let Some(list) = list_file {
+                cmd.arg("--use-list").arg(list);
+            }
+
             // Add username if specified
             if let Some(user) = &parts.user {
                 cmd.arg("--username").arg(user);
@@ -211,12 +476,14 @@ pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
                 cmd.env(env_var, value);
             }
 
+            cmd.env("PGOPTIONS", pg_options);
+
             // Apply TCP keepalive parameters to prevent idle connection timeouts
             for (env_var, value) in crate::utils::get_keepalive_env_vars() {
                 cmd.env(env_var, value);
             }
 
-            cmd.status().context(
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
                 "Failed to execute pg_restore. Is PostgreSQL client installed?\n\
                  Install with:\n\
                  - Ubuntu/Debian: sudo apt-get install postgresql-client\n\
@@ -224,8 +491,7 @@ pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
                  - RHEL/CentOS: sudo yum install postgresql",
             )
         },
-        3,                      // Max 3 retries
-        Duration::from_secs(1), // Start with 1 second delay
+        &settings.retry,
         "pg_restore (restore data)",
     )
     .context(
@@ -240,11 +506,6 @@ pub async fn restore_data(target_url: &str, input_path: &str) -> Result<()> {
          - Input directory is not a valid pg_dump directory format\n\
          - Connection timeout or network issues",
     )?;
-
-    tracing::info!(
-        "✓ Data restored successfully using {} parallel jobs",
-        num_cpus
-    );
     Ok(())
 }
 
@@ -254,6 +515,28 @@ mod tests {
     use crate::migration::dump;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_restore_tuning_pg_options() {
+        let tuning = RestoreTuning::default();
+        assert_eq!(tuning.data_pg_options(), "-c synchronous_commit=off");
+        assert_eq!(tuning.schema_pg_options(), None);
+
+        let tuning = RestoreTuning {
+            jobs: Some(12),
+            disable_triggers: true,
+            maintenance_work_mem: Some(2 * 1024 * 1024 * 1024),
+        };
+        assert_eq!(tuning.jobs(&RunSettings::default()), 12);
+        assert_eq!(
+            tuning.data_pg_options(),
+            "-c synchronous_commit=off -c session_replication_role=replica -c maintenance_work_mem=2097152kB"
+        );
+        assert_eq!(
+            tuning.schema_pg_options().as_deref(),
+            Some("-c maintenance_work_mem=2097152kB")
+        );
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_restore_globals() {
@@ -264,12 +547,23 @@ mod tests {
         let dump_file = dir.path().join("globals.sql");
 
         // Dump from source
-        dump::dump_globals(&source_url, dump_file.to_str().unwrap())
-            .await
-            .unwrap();
+        dump::dump_globals(
+            &source_url,
+            dump_file.to_str().unwrap(),
+            &Default::default(),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
 
         // Restore to target
-        let result = restore_globals(&target_url, dump_file.to_str().unwrap()).await;
+        let result = restore_globals(
+            &target_url,
+            dump_file.to_str().unwrap(),
+            &Default::default(),
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_ok());
     }
 }
@@ -0,0 +1,234 @@
+// ABOUTME: Safeguard dumps of target databases taken right before --drop-existing drops them
+// ABOUTME: Written with pg_dump to a local directory or uploaded to S3 with the aws CLI
+
+use crate::cancel::CancellationToken;
+use crate::settings::RunSettings;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where safeguard dumps are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotDestination {
+    /// A local directory, created if missing
+    Directory(PathBuf),
+    /// `s3://bucket/prefix`; uploaded with `aws s3 cp`
+    S3 { bucket: String, prefix: String },
+}
+
+impl FromStr for SnapshotDestination {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            bail!("Snapshot destination cannot be empty; pass a directory or s3://bucket/prefix");
+        }
+        let Some(rest) = s.strip_prefix("s3://") else {
+            return Ok(SnapshotDestination::Directory(PathBuf::from(s)));
+        };
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            bail!(
+                "Invalid S3 destination '{}': expected s3://bucket or s3://bucket/prefix",
+                s
+            );
+        }
+        Ok(SnapshotDestination::S3 {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+impl SnapshotDestination {
+    /// Where the dump named `file_name` ends up, for logs and the returned location
+    pub fn location(&self, file_name: &str) -> String {
+        match self {
+            SnapshotDestination::Directory(dir) => dir.join(file_name).display().to_string(),
+            SnapshotDestination::S3 { bucket, prefix } if prefix.is_empty() => {
+                format!("s3://{}/{}", bucket, file_name)
+            }
+            SnapshotDestination::S3 { bucket, prefix } => {
+                format!("s3://{}/{}/{}", bucket, prefix, file_name)
+            }
+        }
+    }
+}
+
+/// Dump a target database, schema and data, before it is dropped
+///
+/// The dump uses pg_dump's custom format, so it can be restored with
+/// `pg_restore --create --dbname=postgres <file>`.
+///
+/// # Arguments
+///
+/// * `destination` - Where the dump goes (`--snapshot-before-drop`)
+/// * `target_url` - PostgreSQL connection string for the target server
+/// * `database` - Database about to be dropped
+/// * `cancel` - Stops pg_dump and the upload when cancelled
+///
+/// # Returns
+///
+/// Where the dump was written.
+///
+/// # Errors
+///
+/// Returns an error if pg_dump or the upload fails. Nothing has been dropped
+/// at that point.
+pub async fn snapshot_before_drop(
+    destination: &SnapshotDestination,
+    target_url: &str,
+    database: &str,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let file_name = format!("{}-before-drop-{}.dump", database, stamp);
+    let location = destination.location(&file_name);
+    let db_url = crate::utils::replace_database_in_url(target_url, database)?;
+
+    tracing::info!(
+        "  Saving a safeguard dump of '{}' to {}...",
+        database,
+        location
+    );
+    match destination {
+        SnapshotDestination::Directory(dir) => {
+            std::fs::create_dir_all(dir).with_context(|| {
+                format!("Failed to create snapshot directory {}", dir.display())
+            })?;
+            pg_dump_custom(&db_url, &dir.join(&file_name), settings, cancel)?;
+        }
+        SnapshotDestination::S3 { .. } => {
+            let temp_dir = crate::utils::create_managed_temp_dir()
+                .context("Failed to create temp directory for the safeguard dump")?;
+            let local = temp_dir.join(&file_name);
+            let result = pg_dump_custom(&db_url, &local, settings, cancel)
+                .and_then(|()| upload_to_s3(&local, &location, settings, cancel));
+            let _ = crate::utils::remove_managed_temp_dir(&temp_dir);
+            result?;
+        }
+    }
+    tracing::info!("  ✓ Safeguard dump of '{}' saved to {}", database, location);
+    Ok(location)
+}
+
+fn pg_dump_custom(
+    db_url: &str,
+    output: &Path,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let parts = crate::utils::parse_postgres_url(db_url)
+        .context("Failed to parse target URL for the safeguard dump")?;
+    let pgpass = crate::utils::PgPassFile::new(&parts)
+        .context("Failed to create .pgpass file for authentication")?;
+    let env_vars = parts.to_pg_env_vars();
+
+    crate::utils::retry_subprocess_with_backoff(
+        || {
+            let mut cmd = Command::new("pg_dump");
+            cmd.arg("--format=custom")
+                .arg("--blobs")
+                .arg("--host")
+                .arg(&parts.host)
+                .arg("--port")
+                .arg(parts.port.to_string())
+                .arg("--dbname")
+                .arg(&parts.database)
+                .arg(format!("--file={}", output.display()))
+                .env("PGPASSFILE", pgpass.path())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit());
+            if let Some(user) = &parts.user {
+                cmd.arg("--username").arg(user);
+            }
+            for (env_var, value) in &env_vars {
+                cmd.env(env_var, value);
+            }
+            for (env_var, value) in crate::utils::get_keepalive_env_vars() {
+                cmd.env(env_var, value);
+            }
+            crate::cancel::status(&mut cmd, cancel, &settings.chaos)
+                .context("Failed to execute pg_dump. Is PostgreSQL client installed?")
+        },
+        &settings.retry,
+        "pg_dump (safeguard dump)",
+    )
+    .with_context(|| {
+        format!(
+            "Safeguard dump of '{}' failed, so nothing was dropped.\n\
+             Fix the error above, or re-run without --snapshot-before-drop.",
+            parts.database
+        )
+    })
+}
+
+fn upload_to_s3(
+    local: &Path,
+    location: &str,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let mut cmd = Command::new("aws");
+    cmd.arg("s3")
+        .arg("cp")
+        .arg("--only-show-errors")
+        .arg(local)
+        .arg(location)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    let status = crate::cancel::status(&mut cmd, cancel, &settings.chaos).context(
+        "Failed to execute the aws CLI for the S3 upload.\n\
+         Install it (https://aws.amazon.com/cli/) or snapshot to a local directory instead.",
+    )?;
+    if !status.success() {
+        bail!(
+            "Uploading the safeguard dump to {} failed ({}), so nothing was dropped.\n\
+             Check the AWS credentials and that the bucket accepts writes.",
+            location,
+            status
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_destination() {
+        assert_eq!(
+            "/var/backups/seren".parse::<SnapshotDestination>().unwrap(),
+            SnapshotDestination::Directory(PathBuf::from("/var/backups/seren"))
+        );
+        assert_eq!(
+            "s3://backups/seren/prod/"
+                .parse::<SnapshotDestination>()
+                .unwrap(),
+            SnapshotDestination::S3 {
+                bucket: "backups".to_string(),
+                prefix: "seren/prod".to_string(),
+            }
+        );
+        assert!("s3://".parse::<SnapshotDestination>().is_err());
+        assert!("".parse::<SnapshotDestination>().is_err());
+    }
+
+    #[test]
+    fn test_location() {
+        let s3: SnapshotDestination = "s3://backups".parse().unwrap();
+        assert_eq!(s3.location("app.dump"), "s3://backups/app.dump");
+        let s3: SnapshotDestination = "s3://backups/x".parse().unwrap();
+        assert_eq!(s3.location("app.dump"), "s3://backups/x/app.dump");
+        let dir = SnapshotDestination::Directory(PathBuf::from("snapshots"));
+        assert_eq!(dir.location("app.dump"), "snapshots/app.dump");
+    }
+}
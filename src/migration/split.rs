@@ -0,0 +1,453 @@
+// ABOUTME: Plans and copies huge tables in primary-key ranges alongside pg_dump
+// ABOUTME: Avoids one multi-terabyte table serializing the per-table parallel dump
+
+use crate::cancel::{self, CancellationToken};
+use crate::filters::ReplicationFilter;
+use crate::postgres;
+use crate::settings::RunSettings;
+use crate::utils::{quote_ident, quote_qualified};
+use anyhow::{Context, Result};
+use futures::future::try_join_all;
+use futures::{pin_mut, SinkExt, StreamExt};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio_postgres::Client;
+
+/// Ranges created per copy worker, so uneven key distributions still balance
+const RANGES_PER_WORKER: usize = 4;
+
+/// Half-open primary-key range `[lower, upper)`; `None` means unbounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRange {
+    pub lower: Option<i64>,
+    pub upper: Option<i64>,
+}
+
+impl KeyRange {
+    /// SQL predicate selecting this range of `column`
+    fn predicate(&self, column: &str) -> String {
+        let column = quote_ident(column);
+        match (self.lower, self.upper) {
+            (Some(lower), Some(upper)) => {
+                format!("{} >= {} AND {} < {}", column, lower, column, upper)
+            }
+            (Some(lower), None) => format!("{} >= {}", column, lower),
+            (None, Some(upper)) => format!("{} < {}", column, upper),
+            (None, None) => "true".to_string(),
+        }
+    }
+}
+
+/// A table that will be copied in key ranges instead of by pg_dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTable {
+    pub schema: String,
+    pub table: String,
+    pub key_column: String,
+    pub size_bytes: i64,
+    pub ranges: Vec<KeyRange>,
+}
+
+impl SplitTable {
+    /// Quoted `"schema"."table"` name, as passed to pg_dump `--exclude-table-data`
+    pub fn qualified_name(&self) -> String {
+        quote_qualified(&self.schema, &self.table)
+    }
+}
+
+/// A table over the threshold that cannot be split, with the reason
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsplittableTable {
+    pub schema: String,
+    pub table: String,
+    pub size_bytes: i64,
+    pub reason: String,
+}
+
+/// Which huge tables to split and how
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SplitPlan {
+    pub tables: Vec<SplitTable>,
+    pub skipped: Vec<UnsplittableTable>,
+}
+
+impl SplitPlan {
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Names to exclude from the pg_dump data step
+    pub fn excluded_tables(&self) -> Vec<String> {
+        self.tables.iter().map(SplitTable::qualified_name).collect()
+    }
+
+    fn log(&self) {
+        for table in &self.tables {
+            tracing::info!(
+                "  Splitting {}.{} ({}) into {} range(s) on '{}'",
+                table.schema,
+                table.table,
+                super::format_bytes(table.size_bytes),
+                table.ranges.len(),
+                table.key_column
+            );
+        }
+        for table in &self.skipped {
+            let message = format!(
+                "Not splitting {}.{} ({}): {}; pg_dump will copy it in one job",
+                table.schema,
+                table.table,
+                super::format_bytes(table.size_bytes),
+                table.reason
+            );
+            tracing::warn!("  ⚠ {}", message);
+            crate::events::warning(&message);
+        }
+    }
+}
+
+/// Choose which tables of one database to copy in key ranges
+///
+/// A table qualifies when its total size is at least `threshold_bytes`, it has
+/// a single-column integer primary key, it has no foreign keys in either
+/// direction (range copies run while pg_restore fills the other tables, so
+/// neither side's rows can be relied on to exist yet), and its data is not
+/// already excluded or filtered by the replication rules.
+///
+/// # Arguments
+///
+/// * `client` - Connection to the source database
+/// * `filter` - Replication filter for table selection
+/// * `db_name` - Database being copied
+/// * `threshold_bytes` - Minimum total table size to split
+/// * `workers` - Copy workers that will process the ranges
+pub async fn plan_splits(
+    client: &Client,
+    filter: &ReplicationFilter,
+    db_name: &str,
+    threshold_bytes: i64,
+    workers: usize,
+) -> Result<SplitPlan> {
+    let rows = client
+        .query(
+            "SELECT n.nspname::text, c.relname::text, pg_total_relation_size(c.oid),
+                    (SELECT array_agg(a.attname::text || ':' || format_type(a.atttypid, NULL))
+                     FROM pg_index i
+                     JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+                     WHERE i.indrelid = c.oid AND i.indisprimary),
+                    EXISTS (SELECT 1 FROM pg_constraint f
+                            WHERE f.contype = 'f' AND (f.conrelid = c.oid OR f.confrelid = c.oid))
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE c.relkind = 'r'
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+               AND n.nspname NOT LIKE 'pg_toast%'
+               AND pg_total_relation_size(c.oid) >= $1
+             ORDER BY pg_total_relation_size(c.oid) DESC",
+            &[&threshold_bytes],
+        )
+        .await
+        .context("Failed to find large tables for splitting")?;
+
+    let mut excluded = filter.predicate_tables(db_name);
+    excluded.extend(filter.transform_tables(db_name));
+    let schema_only = filter.schema_only_tables(db_name);
+
+    let mut plan = SplitPlan::default();
+    for row in &rows {
+        let schema: String = row.get(0);
+        let table: String = row.get(1);
+        let size_bytes: i64 = row.get(2);
+        let key: Option<Vec<String>> = row.get(3);
+        let has_foreign_keys: bool = row.get(4);
+
+        if !filter.should_replicate_schema_table(db_name, &schema, &table) {
+            continue;
+        }
+        let qualified = quote_qualified(&schema, &table);
+        let skip = |reason: &str| UnsplittableTable {
+            schema: schema.clone(),
+            table: table.clone(),
+            size_bytes,
+            reason: reason.to_string(),
+        };
+        if schema_only.contains(&qualified) || excluded.iter().any(|(t, _)| *t == qualified) {
+            continue;
+        }
+        if has_foreign_keys {
+            plan.skipped
+                .push(skip("it has foreign keys to or from other tables"));
+            continue;
+        }
+        let Some(key_column) = integer_key_column(key.as_deref().unwrap_or_default()) else {
+            plan.skipped
+                .push(skip("it has no single-column integer primary key"));
+            continue;
+        };
+
+        let bounds: (Option<i64>, Option<i64>) = {
+            let sql = format!(
+                "SELECT min({col})::bigint, max({col})::bigint FROM {}",
+                qualified,
+                col = quote_ident(&key_column)
+            );
+            let row = client
+                .query_one(&sql, &[])
+                .await
+                .with_context(|| format!("Failed to read key range of {}", qualified))?;
+            (row.get(0), row.get(1))
+        };
+        let (Some(min), Some(max)) = bounds else {
+            continue; // Empty table
+        };
+
+        plan.tables.push(SplitTable {
+            schema,
+            table,
+            key_column,
+            size_bytes,
+            ranges: split_ranges(min, max, workers.max(1) * RANGES_PER_WORKER),
+        });
+    }
+    Ok(plan)
+}
+
+/// Name of the primary key column if the key is a single integer column
+///
+/// `key` holds `name:type` entries as returned by the planner query.
+fn integer_key_column(key: &[String]) -> Option<String> {
+    let [entry] = key else {
+        return None;
+    };
+    let (name, data_type) = entry.rsplit_once(':')?;
+    matches!(data_type, "smallint" | "integer" | "bigint").then(|| name.to_string())
+}
+
+/// Split `[min, max]` into at most `count` contiguous ranges covering all keys
+///
+/// The first range is unbounded below and the last unbounded above, so rows
+/// inserted outside the sampled bounds are still copied.
+pub fn split_ranges(min: i64, max: i64, count: usize) -> Vec<KeyRange> {
+    let span = max as i128 - min as i128 + 1;
+    let count = (count.max(1) as i128).min(span.max(1));
+    let step = (span + count - 1) / count;
+
+    let mut ranges = Vec::new();
+    let mut lower: Option<i64> = None;
+    for i in 1..count {
+        let upper = (min as i128 + step * i) as i64;
+        ranges.push(KeyRange {
+            lower,
+            upper: Some(upper),
+        });
+        lower = Some(upper);
+    }
+    ranges.push(KeyRange { lower, upper: None });
+    ranges
+}
+
+/// Copy every range of the planned tables from source to target
+///
+/// Each of `workers` tasks opens its own connection pair and takes ranges from
+/// a shared queue, streaming them with binary COPY. Target tables must exist
+/// and be empty.
+///
+/// # Errors
+///
+/// Returns an error if a connection or any range copy fails.
+pub async fn copy_split_tables(
+    source_url: &str,
+    target_url: &str,
+    plan: &SplitPlan,
+    workers: usize,
+    settings: &RunSettings,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let queue: VecDeque<(&SplitTable, KeyRange)> = plan
+        .tables
+        .iter()
+        .flat_map(|table| table.ranges.iter().map(move |range| (table, *range)))
+        .collect();
+    let workers = workers.clamp(1, queue.len().max(1));
+    tracing::info!(
+        "  Copying {} range(s) of {} split table(s) with {} worker(s)...",
+        queue.len(),
+        plan.tables.len(),
+        workers
+    );
+
+    let queue = Mutex::new(queue);
+    try_join_all((0..workers).map(|_| async {
+        let source = postgres::connect_with_retry(source_url, settings)
+            .await
+            .context("Failed to connect to source database for split copy")?;
+        let target = postgres::connect_with_retry(target_url, settings)
+            .await
+            .context("Failed to connect to target database for split copy")?;
+        loop {
+            let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+            let Some((table, range)) = next else {
+                return Ok::<_, anyhow::Error>(());
+            };
+            // Dropping an unfinished range aborts its COPY on the target
+            cancel::run(cancel, copy_range(&source, &target, table, range)).await?;
+        }
+    }))
+    .await?;
+
+    for table in &plan.tables {
+        tracing::info!(
+            "  ✓ Split copy complete for {}.{}",
+            table.schema,
+            table.table
+        );
+    }
+    Ok(())
+}
+
+async fn copy_range(
+    source: &Client,
+    target: &Client,
+    table: &SplitTable,
+    range: KeyRange,
+) -> Result<()> {
+    let qualified = table.qualified_name();
+    let predicate = range.predicate(&table.key_column);
+    let reader = source
+        .copy_out(&format!(
+            "COPY (SELECT * FROM {} WHERE {}) TO STDOUT BINARY",
+            qualified, predicate
+        ))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to copy {} where {} from source",
+                qualified, predicate
+            )
+        })?;
+    let writer = target
+        .copy_in(&format!("COPY {} FROM STDIN BINARY", qualified))
+        .await
+        .with_context(|| format!("Failed to copy {} into target", qualified))?;
+
+    pin_mut!(reader);
+    pin_mut!(writer);
+    let started = std::time::Instant::now();
+    let mut copied = 0;
+    while let Some(chunk) = reader.next().await {
+        let data = chunk?;
+        let bytes = data.len() as u64;
+        crate::resources::record_source_read(bytes);
+        writer.as_mut().send(data).await?;
+        crate::resources::record_target_written(bytes);
+        copied += bytes;
+    }
+    writer.finish().await?;
+    tracing::debug!(
+        "Copied {} where {}: {}",
+        qualified,
+        predicate,
+        crate::resources::describe_transfer(copied, started.elapsed())
+    );
+    Ok(())
+}
+
+/// Plan splits for one database and log the plan
+///
+/// Tables of at least `threshold` bytes are split; returns an empty plan when
+/// `threshold` is `None`.
+pub async fn plan_for_database(
+    source_url: &str,
+    filter: &ReplicationFilter,
+    db_name: &str,
+    threshold: Option<i64>,
+    workers: usize,
+    settings: &RunSettings,
+) -> Result<SplitPlan> {
+    let Some(threshold) = threshold else {
+        return Ok(SplitPlan::default());
+    };
+    let client = postgres::connect_with_retry(source_url, settings).await?;
+    let plan = plan_splits(&client, filter, db_name, threshold, workers).await?;
+    plan.log();
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_ranges_cover_all_keys() {
+        let ranges = split_ranges(1, 100, 4);
+        assert_eq!(
+            ranges,
+            vec![
+                KeyRange {
+                    lower: None,
+                    upper: Some(26)
+                },
+                KeyRange {
+                    lower: Some(26),
+                    upper: Some(51)
+                },
+                KeyRange {
+                    lower: Some(51),
+                    upper: Some(76)
+                },
+                KeyRange {
+                    lower: Some(76),
+                    upper: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_ranges_small_and_extreme_spans() {
+        // Fewer keys than requested ranges
+        assert_eq!(split_ranges(5, 6, 8).len(), 2);
+        assert_eq!(
+            split_ranges(7, 7, 8),
+            vec![KeyRange {
+                lower: None,
+                upper: None
+            }]
+        );
+        // Full i64 range must not overflow
+        let ranges = split_ranges(i64::MIN, i64::MAX, 4);
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[1].lower, Some(i64::MIN / 2));
+    }
+
+    #[test]
+    fn test_integer_key_column() {
+        assert_eq!(
+            integer_key_column(&["id:bigint".to_string()]),
+            Some("id".to_string())
+        );
+        assert_eq!(integer_key_column(&["id:uuid".to_string()]), None);
+        assert_eq!(
+            integer_key_column(&["a:integer".to_string(), "b:integer".to_string()]),
+            None
+        );
+        assert_eq!(integer_key_column(&[]), None);
+    }
+
+    #[test]
+    fn test_range_predicate() {
+        let range = KeyRange {
+            lower: Some(10),
+            upper: Some(20),
+        };
+        assert_eq!(range.predicate("Id"), "\"Id\" >= 10 AND \"Id\" < 20");
+        assert_eq!(
+            KeyRange {
+                lower: None,
+                upper: Some(5)
+            }
+            .predicate("id"),
+            "\"id\" < 5"
+        );
+    }
+}
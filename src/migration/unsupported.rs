@@ -0,0 +1,321 @@
+// ABOUTME: Detects schema dump entries known to fail or misbehave on the target
+// ABOUTME: Strips them before restore or stops early with a categorized report
+
+use super::dump::{dump_entries, DumpEntryHeader};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use tokio_postgres::Client;
+
+/// What to do with unsupported objects found in a schema dump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnsupportedObjectPolicy {
+    /// Leave the objects out of the restore and report them
+    #[default]
+    Strip,
+    /// Stop before restoring and report the objects
+    Fail,
+}
+
+impl fmt::Display for UnsupportedObjectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UnsupportedObjectPolicy::Strip => "strip",
+            UnsupportedObjectPolicy::Fail => "fail",
+        })
+    }
+}
+
+/// `[unsupported_objects]` section of replication-config.toml
+///
+/// ```toml
+/// [unsupported_objects]
+/// policy = "fail"
+/// ```
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct UnsupportedObjectConfig {
+    #[serde(default)]
+    pub policy: UnsupportedObjectPolicy,
+}
+
+/// Why an object was flagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnsupportedCategory {
+    /// An extension the target does not offer, such as a custom C extension
+    UnavailableExtension,
+    /// A publication; sync creates the publications replication needs
+    Publication,
+    /// A subscription, which would connect the target to the source's upstream
+    Subscription,
+    /// An unlogged table: restored and copied by init, but never sent by
+    /// logical replication (reported only, never stripped)
+    UnloggedTable,
+}
+
+impl UnsupportedCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnsupportedCategory::UnavailableExtension => "extensions not available on the target",
+            UnsupportedCategory::Publication => "publications",
+            UnsupportedCategory::Subscription => "subscriptions",
+            UnsupportedCategory::UnloggedTable => "unlogged tables (not replicated by sync)",
+        }
+    }
+
+    /// Whether the object is removed from the dump (or stops a `fail` run)
+    pub fn blocks_restore(&self) -> bool {
+        !matches!(self, UnsupportedCategory::UnloggedTable)
+    }
+}
+
+/// One flagged dump entry
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnsupportedObject {
+    pub category: UnsupportedCategory,
+    /// Schema-qualified where the dump gives a schema
+    pub name: String,
+}
+
+/// Extensions the target can install (`pg_available_extensions`)
+pub async fn available_extensions(client: &Client) -> Result<BTreeSet<String>> {
+    let rows = client
+        .query("SELECT name FROM pg_available_extensions", &[])
+        .await
+        .context("Failed to list available extensions on target")?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Classify one dump entry, if it is unsupported
+fn classify(
+    header: &DumpEntryHeader<'_>,
+    section: &str,
+    available: &BTreeSet<String>,
+) -> Option<UnsupportedCategory> {
+    match header.object_type {
+        "EXTENSION" if !available.contains(header.name) => {
+            Some(UnsupportedCategory::UnavailableExtension)
+        }
+        // Comments on a stripped extension would fail too
+        "COMMENT" => header
+            .name
+            .strip_prefix("EXTENSION ")
+            .filter(|name| !available.contains(*name))
+            .map(|_| UnsupportedCategory::UnavailableExtension),
+        "PUBLICATION" | "PUBLICATION TABLE" | "PUBLICATION TABLES IN SCHEMA" => {
+            Some(UnsupportedCategory::Publication)
+        }
+        "SUBSCRIPTION" | "SUBSCRIPTION TABLE" => Some(UnsupportedCategory::Subscription),
+        "TABLE" if section.contains("CREATE UNLOGGED TABLE ") => {
+            Some(UnsupportedCategory::UnloggedTable)
+        }
+        _ => None,
+    }
+}
+
+fn entry_name(header: &DumpEntryHeader<'_>) -> String {
+    if header.schema == "-" {
+        header.name.to_string()
+    } else {
+        format!("{}.{}", header.schema, header.name)
+    }
+}
+
+/// Find unsupported objects in a plain-format schema dump
+///
+/// # Arguments
+///
+/// * `sql` - Schema dump contents
+/// * `available` - Extensions the target offers (see [`available_extensions`])
+pub fn scan_schema(sql: &str, available: &BTreeSet<String>) -> Vec<UnsupportedObject> {
+    let mut found: Vec<UnsupportedObject> = dump_entries(sql)
+        .into_iter()
+        .filter_map(|(header, section)| {
+            let header = header?;
+            // The extension's own entry is reported; its comment just goes with it
+            if header.object_type == "COMMENT" {
+                return None;
+            }
+            classify(&header, section, available).map(|category| UnsupportedObject {
+                category,
+                name: entry_name(&header),
+            })
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Remove the entries that block a restore from a schema dump
+///
+/// Unlogged tables are kept.
+///
+/// # Returns
+///
+/// The rewritten SQL and the number of dump entries removed.
+pub fn strip_unsupported(sql: &str, available: &BTreeSet<String>) -> (String, usize) {
+    let mut output = String::with_capacity(sql.len());
+    let mut removed = 0;
+    for (header, section) in dump_entries(sql) {
+        let blocked = header
+            .as_ref()
+            .and_then(|header| classify(header, section, available))
+            .is_some_and(|category| category.blocks_restore());
+        if blocked {
+            removed += 1;
+        } else {
+            output.push_str(section);
+        }
+    }
+    (output, removed)
+}
+
+/// One line per category, e.g. `publications: public.pub_all`
+pub fn summarize(objects: &[UnsupportedObject]) -> Vec<String> {
+    let categories: BTreeSet<UnsupportedCategory> = objects.iter().map(|o| o.category).collect();
+    categories
+        .into_iter()
+        .map(|category| {
+            let names: Vec<&str> = objects
+                .iter()
+                .filter(|o| o.category == category)
+                .map(|o| o.name.as_str())
+                .collect();
+            format!("{}: {}", category.label(), names.join(", "))
+        })
+        .collect()
+}
+
+/// Log the categorized report for a database
+pub fn log_report(database: &str, objects: &[UnsupportedObject], policy: UnsupportedObjectPolicy) {
+    tracing::warn!(
+        "⚠ Database '{}' has {} object(s) that need attention on the target (policy: {})",
+        database,
+        objects.len(),
+        policy
+    );
+    for line in summarize(objects) {
+        tracing::warn!("  {}", line);
+    }
+    if objects.iter().any(|o| o.category.blocks_restore()) {
+        match policy {
+            UnsupportedObjectPolicy::Strip => {
+                tracing::warn!("  Left out of the restore; recreate them on the target if needed.")
+            }
+            UnsupportedObjectPolicy::Fail => {
+                tracing::warn!(
+                    "  Stopping before restore ([unsupported_objects] policy = \"fail\")."
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, object_type: &str, schema: &str, body: &str) -> String {
+        format!(
+            "--\n-- Name: {}; Type: {}; Schema: {}; Owner: -\n--\n\n{}\n\n",
+            name, object_type, schema, body
+        )
+    }
+
+    fn sample_dump() -> String {
+        [
+            "SET statement_timeout = 0;\n\n".to_string(),
+            entry(
+                "acme_hash",
+                "EXTENSION",
+                "-",
+                "CREATE EXTENSION IF NOT EXISTS acme_hash WITH SCHEMA public;",
+            ),
+            entry(
+                "EXTENSION acme_hash",
+                "COMMENT",
+                "-",
+                "COMMENT ON EXTENSION acme_hash IS 'hashes';",
+            ),
+            entry(
+                "hstore",
+                "EXTENSION",
+                "-",
+                "CREATE EXTENSION IF NOT EXISTS hstore WITH SCHEMA public;",
+            ),
+            entry("users", "TABLE", "public", "CREATE TABLE public.users (id int);"),
+            entry(
+                "scratch",
+                "TABLE",
+                "public",
+                "CREATE UNLOGGED TABLE public.scratch (id int);",
+            ),
+            entry(
+                "pub_all",
+                "PUBLICATION",
+                "-",
+                "CREATE PUBLICATION pub_all FOR ALL TABLES;",
+            ),
+            entry(
+                "sub_up",
+                "SUBSCRIPTION",
+                "-",
+                "CREATE SUBSCRIPTION sub_up CONNECTION 'host=up' PUBLICATION p WITH (connect = false);",
+            ),
+        ]
+        .concat()
+    }
+
+    fn available() -> BTreeSet<String> {
+        ["hstore", "plpgsql"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn scan_categorizes_objects() {
+        let found = scan_schema(&sample_dump(), &available());
+        assert_eq!(
+            found,
+            vec![
+                UnsupportedObject {
+                    category: UnsupportedCategory::UnavailableExtension,
+                    name: "acme_hash".to_string(),
+                },
+                UnsupportedObject {
+                    category: UnsupportedCategory::Publication,
+                    name: "pub_all".to_string(),
+                },
+                UnsupportedObject {
+                    category: UnsupportedCategory::Subscription,
+                    name: "sub_up".to_string(),
+                },
+                UnsupportedObject {
+                    category: UnsupportedCategory::UnloggedTable,
+                    name: "public.scratch".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            summarize(&found)[0],
+            "extensions not available on the target: acme_hash"
+        );
+    }
+
+    #[test]
+    fn strip_keeps_supported_and_unlogged_entries() {
+        let (sql, removed) = strip_unsupported(&sample_dump(), &available());
+        assert_eq!(removed, 4);
+        assert!(sql.starts_with("SET statement_timeout = 0;"));
+        assert!(!sql.contains("acme_hash"));
+        assert!(!sql.contains("PUBLICATION"));
+        assert!(!sql.contains("SUBSCRIPTION"));
+        assert!(sql.contains("CREATE EXTENSION IF NOT EXISTS hstore"));
+        assert!(sql.contains("CREATE TABLE public.users"));
+        assert!(sql.contains("CREATE UNLOGGED TABLE public.scratch"));
+    }
+}
@@ -0,0 +1,186 @@
+// ABOUTME: Compatibility matrix for replicating to a newer PostgreSQL major version
+// ABOUTME: Finds source objects that rely on types, operators, and extensions removed or changed on the target
+
+use anyhow::{Context, Result};
+use tokio_postgres::Client;
+
+/// A change in a PostgreSQL major version that breaks objects copied from older sources
+struct UpgradeRule {
+    /// First major version with the change
+    since: u32,
+    /// What the objects found rely on
+    what: &'static str,
+    /// Run in each source database; one object name per row
+    query: &'static str,
+    remediation: &'static str,
+}
+
+/// Checks applied when `since` falls in (source major, target major], oldest first
+///
+/// Queries only run against sources older than `since`, so they may use
+/// catalog columns later releases removed (e.g. `relhasoids`).
+const RULES: &[UpgradeRule] = &[
+    UpgradeRule {
+        since: 11,
+        what: "extension chkpass (removed in PostgreSQL 11)",
+        query: "SELECT extname::text FROM pg_extension WHERE extname = 'chkpass'",
+        remediation: "Move the data to pgcrypto's crypt() and drop the extension on the source",
+    },
+    UpgradeRule {
+        since: 12,
+        what: "table WITH OIDS (removed in PostgreSQL 12)",
+        query: "SELECT format('%I.%I', n.nspname, c.relname) \
+                FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                WHERE c.relhasoids AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast') \
+                ORDER BY 1",
+        remediation: "Run ALTER TABLE ... SET WITHOUT OIDS on the source; add an identity column if the oid values are used",
+    },
+    UpgradeRule {
+        since: 12,
+        what: "column of type abstime, reltime, or tinterval (removed in PostgreSQL 12)",
+        query: "SELECT format('%I.%I.%I', n.nspname, c.relname, a.attname) \
+                FROM pg_attribute a \
+                JOIN pg_class c ON c.oid = a.attrelid \
+                JOIN pg_namespace n ON n.oid = c.relnamespace \
+                JOIN pg_type t ON t.oid = a.atttypid \
+                WHERE t.typname IN ('abstime', 'reltime', 'tinterval') \
+                  AND a.attnum > 0 AND NOT a.attisdropped \
+                  AND c.relkind IN ('r', 'p', 'v', 'm') \
+                  AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast') \
+                ORDER BY 1",
+        remediation: "Convert the columns to timestamptz or interval on the source before migrating",
+    },
+    UpgradeRule {
+        since: 14,
+        what: "user-defined postfix operator (removed in PostgreSQL 14)",
+        query: "SELECT format('%I.%s', n.nspname, o.oprname) \
+                FROM pg_operator o JOIN pg_namespace n ON n.oid = o.oprnamespace \
+                WHERE o.oprkind = 'r' \
+                  AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast') \
+                ORDER BY 1",
+        remediation: "Replace the operator with a function or prefix operator and update the queries that use it",
+    },
+    UpgradeRule {
+        since: 14,
+        what: "aggregate built on array_append, array_cat, and similar (their signatures use anycompatiblearray since PostgreSQL 14)",
+        query: "SELECT a.aggfnoid::regprocedure::text \
+                FROM pg_aggregate a \
+                JOIN pg_proc p ON p.oid = a.aggfnoid \
+                JOIN pg_namespace n ON n.oid = p.pronamespace \
+                WHERE a.aggtransfn::regproc::text IN ('array_append', 'array_prepend', 'array_cat', \
+                      'array_position', 'array_positions', 'array_remove', 'array_replace', 'width_bucket') \
+                  AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast') \
+                ORDER BY 1",
+        remediation: "Drop the aggregate before the schema is restored and recreate it on the target with anycompatible/anycompatiblearray argument types",
+    },
+    UpgradeRule {
+        since: 15,
+        what: "PL/Python 2 (removed in PostgreSQL 15)",
+        query: "SELECT extname::text FROM pg_extension WHERE extname IN ('plpythonu', 'plpython2u')",
+        remediation: "Port the functions to plpython3u on the source",
+    },
+    UpgradeRule {
+        since: 17,
+        what: "extension adminpack or old_snapshot (removed in PostgreSQL 17)",
+        query: "SELECT extname::text FROM pg_extension WHERE extname IN ('adminpack', 'old_snapshot')",
+        remediation: "Drop the extension on the source (DROP EXTENSION ...) before migrating; the target cannot install it",
+    },
+];
+
+/// Behavior changes that need no per-object check but should be known before an upgrade
+const NOTES: &[(u32, &str)] = &[
+    (
+        14,
+        "password_encryption defaults to scram-sha-256; roles with md5 passwords keep working, but clients older than libpq 10 cannot log in with new passwords",
+    ),
+    (
+        15,
+        "PUBLIC no longer has CREATE on schema public; grant it to the roles that create objects there (GRANT CREATE ON SCHEMA public TO <role>)",
+    ),
+    (
+        16,
+        "CREATEROLE only lets a role administer the roles it created; grant ADMIN OPTION explicitly to roles that manage other existing roles",
+    ),
+];
+
+/// A source object that will not survive a major-version upgrade unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeFinding {
+    pub database: String,
+    pub object: String,
+    pub what: &'static str,
+    pub remediation: &'static str,
+}
+
+/// Whether a change first made in `since` lies between the two versions
+fn crosses(since: u32, source_major: u32, target_major: u32) -> bool {
+    source_major < since && since <= target_major
+}
+
+/// Behavior changes between the two major versions, oldest first
+pub fn notes(source_major: u32, target_major: u32) -> Vec<&'static str> {
+    NOTES
+        .iter()
+        .filter(|(since, _)| crosses(*since, source_major, target_major))
+        .map(|(_, note)| *note)
+        .collect()
+}
+
+/// Find objects in one source database that break between the two major versions
+///
+/// # Arguments
+///
+/// * `client` - Connection to the source database
+/// * `database` - Its name, for the findings
+/// * `source_major` / `target_major` - Major versions, e.g. 12 and 17
+///
+/// # Errors
+///
+/// Returns an error if a catalog query fails.
+pub async fn check_database(
+    client: &Client,
+    database: &str,
+    source_major: u32,
+    target_major: u32,
+) -> Result<Vec<UpgradeFinding>> {
+    let mut findings = Vec::new();
+    for rule in RULES
+        .iter()
+        .filter(|rule| crosses(rule.since, source_major, target_major))
+    {
+        let rows = client
+            .query(rule.query, &[])
+            .await
+            .with_context(|| format!("Failed to check '{}' for {}", database, rule.what))?;
+        findings.extend(rows.iter().map(|row| UpgradeFinding {
+            database: database.to_string(),
+            object: row.get(0),
+            what: rule.what,
+            remediation: rule.remediation,
+        }));
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_apply_only_between_versions() {
+        let applicable = |source, target| -> Vec<u32> {
+            RULES
+                .iter()
+                .filter(|rule| crosses(rule.since, source, target))
+                .map(|rule| rule.since)
+                .collect()
+        };
+        assert!(applicable(16, 16).is_empty());
+        assert_eq!(applicable(12, 17), vec![14, 14, 15, 17]);
+        assert_eq!(applicable(10, 12), vec![11, 12, 12]);
+        assert_eq!(notes(13, 15).len(), 2);
+        assert!(notes(15, 17)
+            .iter()
+            .all(|note| !note.contains("schema public")));
+    }
+}
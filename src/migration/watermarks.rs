@@ -0,0 +1,295 @@
+// ABOUTME: Per-table verification watermarks stored on the target
+// ABOUTME: Lets `verify --incremental` skip tables unchanged since their last clean verification
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use tokio_postgres::Client;
+
+/// Table in each target database holding the last clean verification per table
+pub const WATERMARK_TABLE: &str = "seren_replicator.verify_watermarks";
+
+/// Change state of one table on one side, read from the statistics collector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableActivity {
+    /// Cumulative inserted + updated + deleted tuples (`pg_stat_user_tables`)
+    pub changes: i64,
+    /// Current relfilenode; changes on TRUNCATE, VACUUM FULL, and CLUSTER
+    pub filenode: i64,
+}
+
+/// Activity on both sides at the time a table was last verified clean
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableWatermark {
+    pub source: TableActivity,
+    pub target: TableActivity,
+}
+
+/// Source position captured when a verify run starts, stored for auditing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub lsn: String,
+    /// Oldest transaction still running on the source
+    pub xmin: i64,
+}
+
+pub type Watermarks = HashMap<(String, String), TableWatermark>;
+pub type Activity = HashMap<(String, String), TableActivity>;
+
+/// Create the watermark schema and table on the target if they do not exist
+///
+/// # Errors
+///
+/// Returns an error if the target user cannot create the schema or table.
+pub async fn ensure_watermark_table(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 schema_name text NOT NULL,
+                 table_name text NOT NULL,
+                 verified_at timestamptz NOT NULL DEFAULT now(),
+                 source_lsn pg_lsn,
+                 source_xmin bigint,
+                 source_changes bigint NOT NULL,
+                 source_filenode bigint NOT NULL,
+                 target_changes bigint NOT NULL,
+                 target_filenode bigint NOT NULL,
+                 PRIMARY KEY (schema_name, table_name)
+             )",
+            WATERMARK_TABLE
+        ))
+        .await
+        .context(
+            "Failed to create the verify watermark table on the target.\n\
+             The target user needs CREATE privilege on each replicated database.",
+        )
+}
+
+/// Load stored watermarks, keyed by (schema, table)
+///
+/// # Returns
+///
+/// An empty map if the watermark table does not exist yet.
+pub async fn load_watermarks(client: &Client) -> Result<Watermarks> {
+    let exists: bool = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&WATERMARK_TABLE])
+        .await
+        .context("Failed to check for the verify watermark table")?
+        .get(0);
+    if !exists {
+        return Ok(Watermarks::new());
+    }
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT schema_name, table_name, source_changes, source_filenode,
+                        target_changes, target_filenode
+                 FROM {}",
+                WATERMARK_TABLE
+            ),
+            &[],
+        )
+        .await
+        .context("Failed to read verify watermarks")?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            (
+                (row.get(0), row.get(1)),
+                TableWatermark {
+                    source: TableActivity {
+                        changes: row.get(2),
+                        filenode: row.get(3),
+                    },
+                    target: TableActivity {
+                        changes: row.get(4),
+                        filenode: row.get(5),
+                    },
+                },
+            )
+        })
+        .collect())
+}
+
+/// Read current change counters for every user table, keyed by (schema, table)
+///
+/// Statistics are flushed by backends within about a second of commit, so this
+/// is read before any table is checked: a write that lands during verification
+/// shows up as a change on the next run rather than being missed.
+pub async fn table_activity(client: &Client) -> Result<Activity> {
+    let rows = client
+        .query(
+            "SELECT schemaname::text, relname::text,
+                    n_tup_ins + n_tup_upd + n_tup_del,
+                    pg_relation_filenode(relid)::bigint
+             FROM pg_stat_user_tables",
+            &[],
+        )
+        .await
+        .context("Failed to read table statistics")?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let filenode: Option<i64> = row.get(3);
+            Some((
+                (row.get(0), row.get(1)),
+                TableActivity {
+                    changes: row.get(2),
+                    filenode: filenode?,
+                },
+            ))
+        })
+        .collect())
+}
+
+/// Read the source's current WAL position and oldest running transaction
+pub async fn source_position(client: &Client) -> Result<SourcePosition> {
+    let row = client
+        .query_one(
+            "SELECT (CASE WHEN pg_is_in_recovery() THEN pg_last_wal_replay_lsn()
+                          ELSE pg_current_wal_lsn() END)::text,
+                    txid_snapshot_xmin(txid_current_snapshot())",
+            &[],
+        )
+        .await
+        .context("Failed to read source WAL position")?;
+    Ok(SourcePosition {
+        lsn: row.get(0),
+        xmin: row.get(1),
+    })
+}
+
+/// Current activity for a table on both sides, if statistics exist on both
+pub fn current_watermark(
+    source: &Activity,
+    target: &Activity,
+    schema: &str,
+    table: &str,
+) -> Option<TableWatermark> {
+    let key = (schema.to_string(), table.to_string());
+    Some(TableWatermark {
+        source: *source.get(&key)?,
+        target: *target.get(&key)?,
+    })
+}
+
+/// Whether a table can be skipped: it has a stored clean watermark and neither
+/// side has changed since
+pub fn is_unchanged(stored: Option<&TableWatermark>, current: Option<&TableWatermark>) -> bool {
+    matches!((stored, current), (Some(stored), Some(current)) if stored == current)
+}
+
+/// Record a clean verification for a table
+pub async fn store_watermark(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    watermark: &TableWatermark,
+    position: &SourcePosition,
+) -> Result<()> {
+    client
+        .execute(
+            &format!(
+                "INSERT INTO {} (schema_name, table_name, verified_at, source_lsn, source_xmin,
+                                 source_changes, source_filenode, target_changes, target_filenode)
+                 VALUES ($1, $2, now(), $3::text::pg_lsn, $4, $5, $6, $7, $8)
+                 ON CONFLICT (schema_name, table_name) DO UPDATE SET
+                     verified_at = EXCLUDED.verified_at,
+                     source_lsn = EXCLUDED.source_lsn,
+                     source_xmin = EXCLUDED.source_xmin,
+                     source_changes = EXCLUDED.source_changes,
+                     source_filenode = EXCLUDED.source_filenode,
+                     target_changes = EXCLUDED.target_changes,
+                     target_filenode = EXCLUDED.target_filenode",
+                WATERMARK_TABLE
+            ),
+            &[
+                &schema,
+                &table,
+                &position.lsn,
+                &position.xmin,
+                &watermark.source.changes,
+                &watermark.source.filenode,
+                &watermark.target.changes,
+                &watermark.target.filenode,
+            ],
+        )
+        .await
+        .with_context(|| format!("Failed to store verify watermark for {}.{}", schema, table))?;
+    Ok(())
+}
+
+/// Forget a table's watermark so the next incremental run checks it again
+pub async fn clear_watermark(client: &Client, schema: &str, table: &str) -> Result<()> {
+    client
+        .execute(
+            &format!(
+                "DELETE FROM {} WHERE schema_name = $1 AND table_name = $2",
+                WATERMARK_TABLE
+            ),
+            &[&schema, &table],
+        )
+        .await
+        .with_context(|| format!("Failed to clear verify watermark for {}.{}", schema, table))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watermark(source_changes: i64, target_changes: i64) -> TableWatermark {
+        TableWatermark {
+            source: TableActivity {
+                changes: source_changes,
+                filenode: 16384,
+            },
+            target: TableActivity {
+                changes: target_changes,
+                filenode: 24576,
+            },
+        }
+    }
+
+    #[test]
+    fn test_is_unchanged() {
+        let stored = watermark(100, 100);
+        assert!(is_unchanged(Some(&stored), Some(&watermark(100, 100))));
+
+        // New writes on either side
+        assert!(!is_unchanged(Some(&stored), Some(&watermark(101, 100))));
+        assert!(!is_unchanged(Some(&stored), Some(&watermark(100, 101))));
+
+        // Truncated (new relfilenode, counters unchanged)
+        let mut truncated = stored;
+        truncated.source.filenode = 16500;
+        assert!(!is_unchanged(Some(&stored), Some(&truncated)));
+
+        // Never verified, or statistics unavailable
+        assert!(!is_unchanged(None, Some(&stored)));
+        assert!(!is_unchanged(Some(&stored), None));
+    }
+
+    #[test]
+    fn test_current_watermark_requires_both_sides() {
+        let key = ("public".to_string(), "users".to_string());
+        let activity = TableActivity {
+            changes: 5,
+            filenode: 1,
+        };
+        let source: Activity = [(key.clone(), activity)].into_iter().collect();
+        let target = Activity::new();
+
+        assert!(current_watermark(&source, &target, "public", "users").is_none());
+        assert_eq!(
+            current_watermark(&source, &source, "public", "users"),
+            Some(TableWatermark {
+                source: activity,
+                target: activity
+            })
+        );
+    }
+}
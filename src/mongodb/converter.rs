@@ -1,14 +1,21 @@
 // ABOUTME: MongoDB BSON to JSONB type conversion for PostgreSQL storage
 // ABOUTME: Handles all BSON types with lossless conversion and special type encoding
 
+use crate::mongodb::type_map::{
+    exact_json_number, BinaryMapping, BsonTypeMap, DateTimeMapping, DecimalMapping,
+    ObjectIdMapping, RegexMapping,
+};
 use anyhow::{Context, Result};
 use bson::{Bson, Document};
 use mongodb::Database;
 use serde_json::Value as JsonValue;
+use std::collections::HashSet;
 
 /// Convert a BSON value to JSON
 ///
-/// Maps BSON types to JSON types:
+/// Maps BSON types to JSON types, using the default `[bson_types]` mapping
+/// for ObjectId, Decimal128, DateTime, Binary, and Regex (shown below); see
+/// [`bson_to_json_with`] for a configured one:
 /// - Int32/Int64 → number
 /// - Double → number
 /// - String → string
@@ -38,6 +45,17 @@ use serde_json::Value as JsonValue;
 /// assert_eq!(json, serde_json::json!(42));
 /// ```
 pub fn bson_to_json(value: &Bson) -> Result<JsonValue> {
+    bson_to_json_with(value, &BsonTypeMap::default())
+}
+
+/// Convert a BSON value to JSON using an explicit type mapping
+///
+/// See [`crate::mongodb::type_map`] for the representations each mapping selects.
+///
+/// # Errors
+///
+/// Returns an error if a finite double cannot be represented as a JSON number.
+pub fn bson_to_json_with(value: &Bson, map: &BsonTypeMap) -> Result<JsonValue> {
     match value {
         Bson::Double(f) => {
             // Handle non-finite numbers
@@ -52,13 +70,14 @@ pub fn bson_to_json(value: &Bson) -> Result<JsonValue> {
         }
         Bson::String(s) => Ok(JsonValue::String(s.clone())),
         Bson::Array(arr) => {
-            let json_arr: Result<Vec<JsonValue>> = arr.iter().map(bson_to_json).collect();
+            let json_arr: Result<Vec<JsonValue>> =
+                arr.iter().map(|v| bson_to_json_with(v, map)).collect();
             Ok(JsonValue::Array(json_arr?))
         }
         Bson::Document(doc) => {
             let json_obj: Result<serde_json::Map<String, JsonValue>> = doc
                 .iter()
-                .map(|(k, v)| bson_to_json(v).map(|json_v| (k.clone(), json_v)))
+                .map(|(k, v)| bson_to_json_with(v, map).map(|json_v| (k.clone(), json_v)))
                 .collect();
             Ok(JsonValue::Object(json_obj?))
         }
@@ -66,39 +85,56 @@ pub fn bson_to_json(value: &Bson) -> Result<JsonValue> {
         Bson::Null => Ok(JsonValue::Null),
         Bson::Int32(i) => Ok(JsonValue::Number((*i).into())),
         Bson::Int64(i) => Ok(JsonValue::Number((*i).into())),
-        Bson::ObjectId(oid) => {
+        Bson::ObjectId(oid) => match map.objectid {
             // Store ObjectId as object with $oid field for type preservation
-            Ok(serde_json::json!({
+            ObjectIdMapping::Tagged => Ok(serde_json::json!({
                 "_type": "objectid",
                 "$oid": oid.to_hex()
-            }))
-        }
-        Bson::DateTime(dt) => {
+            })),
+            ObjectIdMapping::Text => Ok(JsonValue::String(oid.to_hex())),
+        },
+        Bson::DateTime(dt) => match map.datetime {
             // Store DateTime as object with $date field
             // Using milliseconds since epoch for precision
-            Ok(serde_json::json!({
+            DateTimeMapping::Tagged => Ok(serde_json::json!({
                 "_type": "datetime",
                 "$date": dt.timestamp_millis()
-            }))
-        }
+            })),
+            DateTimeMapping::Iso8601 => Ok(match dt.try_to_rfc3339_string() {
+                Ok(text) => JsonValue::String(text),
+                // Outside the years RFC 3339 can express
+                Err(_) => serde_json::json!({
+                    "_type": "datetime",
+                    "$date": dt.timestamp_millis()
+                }),
+            }),
+            DateTimeMapping::Millis => Ok(JsonValue::Number(dt.timestamp_millis().into())),
+        },
         Bson::Binary(bin) => {
             // Encode binary as base64 in object
             let encoded =
                 base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &bin.bytes);
-            Ok(serde_json::json!({
-                "_type": "binary",
-                "subtype": u8::from(bin.subtype),
-                "data": encoded
-            }))
+            match map.binary {
+                BinaryMapping::Tagged => Ok(serde_json::json!({
+                    "_type": "binary",
+                    "subtype": u8::from(bin.subtype),
+                    "data": encoded
+                })),
+                BinaryMapping::Base64 => Ok(JsonValue::String(encoded)),
+            }
         }
-        Bson::RegularExpression(regex) => {
+        Bson::RegularExpression(regex) => match map.regex {
             // Store regex as object with pattern and options
-            Ok(serde_json::json!({
+            RegexMapping::Tagged => Ok(serde_json::json!({
                 "_type": "regex",
                 "pattern": regex.pattern,
                 "options": regex.options
-            }))
-        }
+            })),
+            RegexMapping::Text => Ok(JsonValue::String(format!(
+                "/{}/{}",
+                regex.pattern, regex.options
+            ))),
+        },
         Bson::Timestamp(ts) => {
             // Store timestamp as object
             Ok(serde_json::json!({
@@ -109,7 +145,17 @@ pub fn bson_to_json(value: &Bson) -> Result<JsonValue> {
         }
         Bson::Decimal128(dec) => {
             // Store Decimal128 as string to preserve precision
-            Ok(JsonValue::String(dec.to_string()))
+            let text = dec.to_string();
+            match map.decimal128 {
+                DecimalMapping::Text => Ok(JsonValue::String(text)),
+                DecimalMapping::Tagged => Ok(serde_json::json!({
+                    "_type": "decimal128",
+                    "$numberDecimal": text
+                })),
+                DecimalMapping::Number => Ok(exact_json_number(&text)
+                    .map(JsonValue::Number)
+                    .unwrap_or(JsonValue::String(text))),
+            }
         }
         Bson::Undefined => {
             // Treat undefined as null
@@ -161,10 +207,19 @@ pub fn bson_to_json(value: &Bson) -> Result<JsonValue> {
 /// assert_eq!(json["age"], 30);
 /// ```
 pub fn document_to_json(document: &Document) -> Result<JsonValue> {
+    document_to_json_with(document, &BsonTypeMap::default())
+}
+
+/// Convert a BSON document to a JSON object using an explicit type mapping
+///
+/// # Errors
+///
+/// Returns an error if any field fails to convert.
+pub fn document_to_json_with(document: &Document, map: &BsonTypeMap) -> Result<JsonValue> {
     let mut json_obj = serde_json::Map::new();
 
     for (key, value) in document.iter() {
-        let json_value = bson_to_json(value)
+        let json_value = bson_to_json_with(value, map)
             .with_context(|| format!("Failed to convert field '{}' to JSON", key))?;
         json_obj.insert(key.clone(), json_value);
     }
@@ -172,6 +227,60 @@ pub fn document_to_json(document: &Document) -> Result<JsonValue> {
     Ok(JsonValue::Object(json_obj))
 }
 
+/// The JSONB row ID for a document's `_id`; None for unsupported types
+fn document_id(id: &Bson) -> Option<String> {
+    match id {
+        Bson::ObjectId(oid) => Some(oid.to_hex()),
+        Bson::String(s) => Some(s.clone()),
+        Bson::Int32(i) => Some(i.to_string()),
+        Bson::Int64(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+/// Read the JSONB row IDs of a collection, fetching only each document's `_id`
+///
+/// The IDs are the ones [`convert_collection_to_jsonb`] assigns, so they can
+/// be compared with the `id` column of the collection's JSONB copy.
+///
+/// # Returns
+///
+/// None when some `_id` has an unsupported type, since those documents are
+/// identified by position.
+///
+/// # Errors
+///
+/// Returns an error if the collection cannot be read.
+pub async fn read_collection_ids(
+    database: &Database,
+    collection_name: &str,
+) -> Result<Option<HashSet<String>>> {
+    crate::jsonb::validate_table_name(collection_name)
+        .context("Invalid collection name for ID reading")?;
+    let mut cursor = database
+        .collection::<Document>(collection_name)
+        .find(Document::new())
+        .projection(bson::doc! { "_id": 1 })
+        .await
+        .with_context(|| format!("Failed to query collection '{}'", collection_name))?;
+
+    use futures::stream::StreamExt;
+    let mut ids = HashSet::new();
+    while let Some(result) = cursor.next().await {
+        let document = result.with_context(|| {
+            format!(
+                "Failed to read an _id from collection '{}'",
+                collection_name
+            )
+        })?;
+        match document.get("_id").and_then(document_id) {
+            Some(id) => ids.insert(id),
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(ids))
+}
+
 /// Convert an entire MongoDB collection to JSONB format
 ///
 /// Reads all documents from a collection and converts them to JSONB.
@@ -187,6 +296,7 @@ pub fn document_to_json(document: &Document) -> Result<JsonValue> {
 ///
 /// * `database` - MongoDB database reference
 /// * `collection_name` - Collection name (must be validated)
+/// * `map` - BSON type mapping of this run
 ///
 /// # Returns
 ///
@@ -200,13 +310,14 @@ pub fn document_to_json(document: &Document) -> Result<JsonValue> {
 ///
 /// ```no_run
 /// # use seren_replicator::mongodb::{connect_mongodb, converter::convert_collection_to_jsonb};
+/// # use seren_replicator::mongodb::type_map::BsonTypeMap;
 /// # use seren_replicator::jsonb::validate_table_name;
 /// # async fn example() -> anyhow::Result<()> {
 /// let client = connect_mongodb("mongodb://localhost:27017/mydb").await?;
 /// let db = client.database("mydb");
 /// let collection = "users";
 /// validate_table_name(collection)?;
-/// let rows = convert_collection_to_jsonb(&db, collection).await?;
+/// let rows = convert_collection_to_jsonb(&db, collection, &BsonTypeMap::default()).await?;
 /// println!("Converted {} documents to JSONB", rows.len());
 /// # Ok(())
 /// # }
@@ -214,6 +325,7 @@ pub fn document_to_json(document: &Document) -> Result<JsonValue> {
 pub async fn convert_collection_to_jsonb(
     database: &Database,
     collection_name: &str,
+    map: &BsonTypeMap,
 ) -> Result<Vec<(String, JsonValue)>> {
     // Validate collection name
     crate::jsonb::validate_table_name(collection_name)
@@ -235,12 +347,9 @@ pub async fn convert_collection_to_jsonb(
         // Extract or generate ID
         let id = if let Some(id_value) = document.get("_id") {
             // Use _id field from document
-            match id_value {
-                Bson::ObjectId(oid) => oid.to_hex(),
-                Bson::String(s) => s.clone(),
-                Bson::Int32(i) => i.to_string(),
-                Bson::Int64(i) => i.to_string(),
-                _ => {
+            match document_id(id_value) {
+                Some(id) => id,
+                None => {
                     tracing::warn!(
                         "Document {} in collection '{}' has unsupported _id type, using doc number",
                         doc_num + 1,
@@ -260,7 +369,7 @@ pub async fn convert_collection_to_jsonb(
         };
 
         // Convert document to JSON
-        let json_data = document_to_json(&document).with_context(|| {
+        let json_data = document_to_json_with(&document, map).with_context(|| {
             format!(
                 "Failed to convert document {} in collection '{}' to JSON",
                 doc_num + 1,
@@ -390,4 +499,52 @@ mod tests {
         assert_eq!(json["tags"][0], "admin");
         assert_eq!(json["tags"][1], "user");
     }
+
+    #[test]
+    fn test_convert_with_type_map() {
+        use crate::mongodb::type_map::*;
+        let oid = ObjectId::new();
+        let map = BsonTypeMap {
+            objectid: ObjectIdMapping::Text,
+            decimal128: DecimalMapping::Number,
+            datetime: DateTimeMapping::Iso8601,
+            binary: BinaryMapping::Base64,
+            regex: RegexMapping::Text,
+        };
+
+        assert_eq!(
+            bson_to_json_with(&Bson::ObjectId(oid), &map).unwrap(),
+            oid.to_hex()
+        );
+        let price: bson::Decimal128 = "19.99".parse().unwrap();
+        assert_eq!(
+            bson_to_json_with(&Bson::Decimal128(price), &map).unwrap(),
+            serde_json::json!(19.99)
+        );
+        let precise: bson::Decimal128 = "1234567890.12345678901".parse().unwrap();
+        assert_eq!(
+            bson_to_json_with(&Bson::Decimal128(precise), &map).unwrap(),
+            "1234567890.12345678901"
+        );
+        let date = bson::DateTime::from_millis(1_678_886_400_000);
+        assert_eq!(
+            bson_to_json_with(&Bson::DateTime(date), &map).unwrap(),
+            "2023-03-15T13:20:00Z"
+        );
+        let regex = Bson::RegularExpression(bson::Regex {
+            pattern: "^a".to_string(),
+            options: "i".to_string(),
+        });
+        assert_eq!(bson_to_json_with(&regex, &map).unwrap(), "/^a/i");
+
+        let tagged = bson_to_json_with(
+            &Bson::Decimal128(price),
+            &BsonTypeMap {
+                decimal128: DecimalMapping::Tagged,
+                ..BsonTypeMap::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(tagged["$numberDecimal"], "19.99");
+    }
 }
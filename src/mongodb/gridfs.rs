@@ -0,0 +1,431 @@
+// ABOUTME: Detects GridFS buckets in MongoDB sources and migrates their files to PostgreSQL
+// ABOUTME: Writes a metadata table plus bytea chunk rows or large objects per bucket
+
+use crate::audit::AuditSettings;
+use crate::cancel::{self, CancellationToken};
+use anyhow::{bail, Context, Result};
+use bson::{doc, Bson, Document};
+use futures::stream::StreamExt;
+use mongodb::Database;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+/// Where GridFS file contents are written on the target (`--gridfs-storage`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GridFsStorage {
+    /// One bytea row per chunk in `{bucket}_chunks`
+    #[default]
+    Bytea,
+    /// One large object per file, referenced by `{bucket}_files.content_oid`
+    LargeObject,
+}
+
+impl GridFsStorage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GridFsStorage::Bytea => "bytea",
+            GridFsStorage::LargeObject => "large-object",
+        }
+    }
+}
+
+impl fmt::Display for GridFsStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GridFsStorage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bytea" => Ok(GridFsStorage::Bytea),
+            "large-object" | "large_object" | "lo" => Ok(GridFsStorage::LargeObject),
+            other => bail!(
+                "Unknown GridFS storage '{}'. Expected one of: bytea, large-object",
+                other
+            ),
+        }
+    }
+}
+
+/// A GridFS bucket: the `{name}.files` and `{name}.chunks` collection pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridFsBucket {
+    pub name: String,
+}
+
+impl GridFsBucket {
+    pub fn files_collection(&self) -> String {
+        format!("{}.files", self.name)
+    }
+
+    pub fn chunks_collection(&self) -> String {
+        format!("{}.chunks", self.name)
+    }
+
+    /// Target metadata table, e.g. `fs_files`
+    pub fn files_table(&self) -> String {
+        format!("{}_files", self.name)
+    }
+
+    /// Target chunk table for bytea storage, e.g. `fs_chunks`
+    pub fn chunks_table(&self) -> String {
+        format!("{}_chunks", self.name)
+    }
+}
+
+/// Split collections into GridFS buckets and the remaining ordinary collections
+///
+/// A bucket is recognised when both `{name}.files` and `{name}.chunks` exist.
+pub fn detect_buckets(collections: &[String]) -> (Vec<GridFsBucket>, Vec<String>) {
+    let mut buckets: Vec<GridFsBucket> = collections
+        .iter()
+        .filter_map(|name| name.strip_suffix(".files"))
+        .filter(|bucket| collections.contains(&format!("{}.chunks", bucket)))
+        .map(|bucket| GridFsBucket {
+            name: bucket.to_string(),
+        })
+        .collect();
+    buckets.sort_by(|a, b| a.name.cmp(&b.name));
+    let remaining = collections
+        .iter()
+        .filter(|name| {
+            !buckets
+                .iter()
+                .any(|b| **name == b.files_collection() || **name == b.chunks_collection())
+        })
+        .cloned()
+        .collect();
+    (buckets, remaining)
+}
+
+/// Target id for a GridFS file `_id`
+fn file_id(id: &Bson) -> Result<String> {
+    Ok(match id {
+        Bson::ObjectId(oid) => oid.to_hex(),
+        Bson::String(s) => s.clone(),
+        Bson::Int32(i) => i.to_string(),
+        Bson::Int64(i) => i.to_string(),
+        other => crate::mongodb::converter::bson_to_json(other)?.to_string(),
+    })
+}
+
+fn integer_field(document: &Document, field: &str) -> Option<i64> {
+    match document.get(field)? {
+        Bson::Int32(i) => Some(i64::from(*i)),
+        Bson::Int64(i) => Some(*i),
+        Bson::Double(d) if d.fract() == 0.0 => Some(*d as i64),
+        _ => None,
+    }
+}
+
+/// Totals for one migrated bucket
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BucketSummary {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Recreate the target tables for a bucket, unlinking large objects from a previous run
+async fn prepare_tables(
+    client: &Client,
+    schema: &str,
+    bucket: &GridFsBucket,
+    storage: GridFsStorage,
+    audit: &AuditSettings,
+) -> Result<()> {
+    crate::jsonb::target_schema::ensure_schema(client, schema).await?;
+    let files = crate::jsonb::target_schema::qualified(schema, &bucket.files_table());
+    let chunks = crate::jsonb::target_schema::qualified(schema, &bucket.chunks_table());
+    let has_previous = client
+        .query_opt(
+            "SELECT 1 FROM information_schema.columns \
+             WHERE table_schema = $1 AND table_name = $2 AND column_name = 'content_oid'",
+            &[&schema, &bucket.files_table()],
+        )
+        .await
+        .context("Failed to check for an existing GridFS metadata table")?
+        .is_some();
+    if has_previous {
+        crate::audit::record_in(
+            audit,
+            client,
+            crate::audit::AuditAction::UnlinkLargeObjects,
+            &format!("content_oid of {}", files),
+        )
+        .await;
+        client
+            .batch_execute(&format!(
+                "SELECT lo_unlink(content_oid) FROM {} WHERE content_oid IS NOT NULL",
+                files
+            ))
+            .await
+            .with_context(|| format!("Failed to unlink large objects of '{}'", bucket.name))?;
+    }
+
+    for table in [&chunks, &files] {
+        let exists: bool = client
+            .query_one("SELECT to_regclass($1) IS NOT NULL", &[table])
+            .await
+            .context("Failed to check for an existing GridFS table")?
+            .get(0);
+        if exists {
+            crate::audit::record_in(audit, client, crate::audit::AuditAction::DropTable, table)
+                .await;
+        }
+    }
+
+    let content = match storage {
+        GridFsStorage::Bytea => "",
+        GridFsStorage::LargeObject => ",\n                content_oid OID",
+    };
+    let mut ddl = format!(
+        "DROP TABLE IF EXISTS {chunks};
+         DROP TABLE IF EXISTS {files};
+         CREATE TABLE {files} (
+             id TEXT PRIMARY KEY,
+             filename TEXT,
+             length BIGINT NOT NULL,
+             chunk_size INTEGER NOT NULL,
+             upload_date TIMESTAMPTZ,
+             metadata JSONB NOT NULL{content},
+             _source_type TEXT NOT NULL DEFAULT 'mongodb',
+             _migrated_at TIMESTAMP NOT NULL DEFAULT NOW()
+         );",
+        chunks = chunks,
+        files = files,
+        content = content
+    );
+    if storage == GridFsStorage::Bytea {
+        ddl.push_str(&format!(
+            "CREATE TABLE {chunks} (
+                 file_id TEXT NOT NULL REFERENCES {files} (id) ON DELETE CASCADE,
+                 n INTEGER NOT NULL,
+                 data BYTEA NOT NULL,
+                 PRIMARY KEY (file_id, n)
+             );",
+            chunks = chunks,
+            files = files
+        ));
+    }
+    client.batch_execute(&ddl).await.with_context(|| {
+        format!(
+            "Failed to create tables for GridFS bucket '{}'",
+            bucket.name
+        )
+    })
+}
+
+/// Migrate every file of a GridFS bucket
+///
+/// Writes one row per file to `{bucket}_files` (filename, length, chunk size,
+/// upload date, and the whole files document as JSONB). Contents go to
+/// `{bucket}_chunks` as bytea rows, or into one large object per file,
+/// depending on the run's GridFS storage. Chunks are streamed, so no file is held in memory.
+///
+/// # Errors
+///
+/// Returns an error if a file's chunks are missing or out of order, or a read
+/// or write fails.
+pub async fn migrate_bucket(
+    database: &Database,
+    client: &Client,
+    jsonb: &crate::jsonb::JsonbOptions,
+    bucket: &GridFsBucket,
+    audit: &AuditSettings,
+    cancel: &CancellationToken,
+) -> Result<BucketSummary> {
+    let schema = jsonb.target_schema.as_str();
+    let storage = jsonb.gridfs_storage;
+    crate::jsonb::validate_table_name(&bucket.files_table())
+        .with_context(|| format!("Invalid GridFS bucket name '{}'", bucket.name))?;
+    crate::jsonb::validate_table_name(&bucket.chunks_table())
+        .with_context(|| format!("Invalid GridFS bucket name '{}'", bucket.name))?;
+
+    prepare_tables(client, schema, bucket, storage, audit).await?;
+
+    let files_table = crate::jsonb::target_schema::qualified(schema, &bucket.files_table());
+    let insert_file = format!(
+        "INSERT INTO {} (id, filename, length, chunk_size, upload_date, metadata) \
+         VALUES ($1, $2, $3, $4, $5::text::timestamptz, $6)",
+        files_table
+    );
+    let insert_chunk = format!(
+        "INSERT INTO {} (file_id, n, data) VALUES ($1, $2, $3)",
+        crate::jsonb::target_schema::qualified(schema, &bucket.chunks_table())
+    );
+    let set_oid = format!(
+        "UPDATE {} SET content_oid = lo_create(0) WHERE id = $1 RETURNING content_oid",
+        files_table
+    );
+
+    let files = database.collection::<Document>(&bucket.files_collection());
+    let chunks = database.collection::<Document>(&bucket.chunks_collection());
+    let mut cursor = files
+        .find(doc! {})
+        .await
+        .with_context(|| format!("Failed to query '{}'", bucket.files_collection()))?;
+
+    let mut summary = BucketSummary::default();
+    while let Some(file) = cursor.next().await {
+        cancel::check(cancel)?;
+        let file =
+            file.with_context(|| format!("Failed to read '{}'", bucket.files_collection()))?;
+        let Some(id_value) = file.get("_id") else {
+            bail!("A document in '{}' has no _id", bucket.files_collection());
+        };
+        let id = file_id(id_value)?;
+        let length = integer_field(&file, "length")
+            .with_context(|| format!("GridFS file '{}' has no valid length", id))?;
+        let chunk_size = integer_field(&file, "chunkSize")
+            .filter(|size| *size > 0 && *size <= i64::from(i32::MAX))
+            .with_context(|| format!("GridFS file '{}' has no valid chunkSize", id))?;
+        let filename = file.get_str("filename").ok().map(str::to_string);
+        let upload_date = file
+            .get_datetime("uploadDate")
+            .ok()
+            .and_then(|date| date.try_to_rfc3339_string().ok());
+        let metadata = crate::mongodb::converter::document_to_json_with(&file, &jsonb.bson_types)?;
+
+        client
+            .execute(
+                &insert_file,
+                &[
+                    &id,
+                    &filename,
+                    &length,
+                    &(chunk_size as i32),
+                    &upload_date,
+                    &metadata,
+                ],
+            )
+            .await
+            .with_context(|| format!("Failed to record GridFS file '{}'", id))?;
+
+        let oid: Option<u32> = match storage {
+            GridFsStorage::Bytea => None,
+            GridFsStorage::LargeObject => Some(
+                client
+                    .query_one(&set_oid, &[&id])
+                    .await
+                    .with_context(|| format!("Failed to create large object for '{}'", id))?
+                    .get(0),
+            ),
+        };
+
+        let expected_chunks = (length + chunk_size - 1) / chunk_size;
+        let mut chunk_cursor = chunks
+            .find(doc! { "files_id": id_value.clone() })
+            .sort(doc! { "n": 1 })
+            .await
+            .with_context(|| format!("Failed to query chunks of GridFS file '{}'", id))?;
+        let mut next_n = 0i64;
+        let mut written = 0i64;
+        while let Some(chunk) = chunk_cursor.next().await {
+            let chunk =
+                chunk.with_context(|| format!("Failed to read a chunk of GridFS file '{}'", id))?;
+            let n = integer_field(&chunk, "n")
+                .with_context(|| format!("A chunk of GridFS file '{}' has no n", id))?;
+            if n != next_n {
+                bail!(
+                    "GridFS file '{}' in bucket '{}' is missing chunk {} (found chunk {}). \
+                     Repair or remove the file in MongoDB before migrating.",
+                    id,
+                    bucket.name,
+                    next_n,
+                    n
+                );
+            }
+            let data = match chunk.get("data") {
+                Some(Bson::Binary(binary)) => &binary.bytes,
+                _ => bail!("Chunk {} of GridFS file '{}' has no binary data", n, id),
+            };
+            match oid {
+                None => {
+                    client
+                        .execute(&insert_chunk, &[&id, &(n as i32), data])
+                        .await
+                        .with_context(|| format!("Failed to write chunk {} of '{}'", n, id))?;
+                }
+                Some(oid) => {
+                    client
+                        .execute(
+                            "SELECT lo_put($1, $2, $3)",
+                            &[&oid, &(n * chunk_size), data],
+                        )
+                        .await
+                        .with_context(|| format!("Failed to write chunk {} of '{}'", n, id))?;
+                }
+            }
+            written += data.len() as i64;
+            next_n += 1;
+        }
+        if next_n != expected_chunks || written != length {
+            bail!(
+                "GridFS file '{}' in bucket '{}' has {} chunk(s) totalling {} bytes, \
+                 but its length of {} bytes needs {} chunk(s). \
+                 Repair or remove the file in MongoDB before migrating.",
+                id,
+                bucket.name,
+                next_n,
+                written,
+                length,
+                expected_chunks
+            );
+        }
+
+        summary.files += 1;
+        summary.bytes += length as u64;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_buckets() {
+        let collections: Vec<String> = ["users", "fs.files", "fs.chunks", "avatars.files"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (buckets, remaining) = detect_buckets(&collections);
+        assert_eq!(
+            buckets,
+            vec![GridFsBucket {
+                name: "fs".to_string()
+            }]
+        );
+        assert_eq!(buckets[0].files_table(), "fs_files");
+        // A lone .files collection is not a bucket
+        assert_eq!(remaining, vec!["users", "avatars.files"]);
+    }
+
+    #[test]
+    fn test_parse_storage() {
+        assert_eq!(
+            "large-object".parse::<GridFsStorage>().unwrap(),
+            GridFsStorage::LargeObject
+        );
+        assert_eq!(
+            "BYTEA".parse::<GridFsStorage>().unwrap(),
+            GridFsStorage::Bytea
+        );
+        assert!("blob".parse::<GridFsStorage>().is_err());
+    }
+
+    #[test]
+    fn test_file_id() {
+        let oid = bson::oid::ObjectId::parse_str("507f1f77bcf86cd799439011").unwrap();
+        assert_eq!(
+            file_id(&Bson::ObjectId(oid)).unwrap(),
+            "507f1f77bcf86cd799439011"
+        );
+        assert_eq!(file_id(&Bson::Int32(7)).unwrap(), "7");
+    }
+}
@@ -2,7 +2,9 @@
 // ABOUTME: Provides secure connection validation and read-only database access
 
 pub mod converter;
+pub mod gridfs;
 pub mod reader;
+pub mod type_map;
 
 use anyhow::{bail, Context, Result};
 use mongodb::{options::ClientOptions, Client};
@@ -0,0 +1,158 @@
+// ABOUTME: Configurable mapping of BSON types (ObjectId, Decimal128, DateTime, Binary, Regex) into JSONB
+// ABOUTME: Parsed from the [bson_types] config section; defaults keep the tagged JSONB structures
+
+use serde::Deserialize;
+
+/// How ObjectIds are written
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectIdMapping {
+    /// `{"_type": "objectid", "$oid": "<hex>"}`
+    #[default]
+    Tagged,
+    /// The hex string, matching the text `id` column
+    Text,
+}
+
+/// How Decimal128 values are written
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalMapping {
+    /// The decimal as a string, e.g. `"123.45"`
+    #[default]
+    Text,
+    /// `{"_type": "decimal128", "$numberDecimal": "123.45"}`
+    Tagged,
+    /// A JSON number when it keeps every digit, the string otherwise
+    Number,
+}
+
+/// How DateTimes are written
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DateTimeMapping {
+    /// `{"_type": "datetime", "$date": <milliseconds since epoch>}`
+    #[default]
+    Tagged,
+    /// An RFC 3339 string, e.g. `"2023-03-15T13:20:00Z"`
+    Iso8601,
+    /// Milliseconds since the epoch as a number
+    Millis,
+}
+
+/// How Binary values are written
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryMapping {
+    /// `{"_type": "binary", "subtype": 0, "data": "<base64>"}`
+    #[default]
+    Tagged,
+    /// The base64 string alone (the subtype is dropped)
+    Base64,
+}
+
+/// How regular expressions are written
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegexMapping {
+    /// `{"_type": "regex", "pattern": "^a", "options": "i"}`
+    #[default]
+    Tagged,
+    /// `"/^a/i"`
+    Text,
+}
+
+/// `[bson_types]` section of replication-config.toml
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BsonTypeMap {
+    pub objectid: ObjectIdMapping,
+    pub decimal128: DecimalMapping,
+    pub datetime: DateTimeMapping,
+    pub binary: BinaryMapping,
+    pub regex: RegexMapping,
+}
+
+/// Sign, significant digits, and exponent of a decimal string, with zeros trimmed
+///
+/// `"1.50"` and `"15e-1"` both give `(false, "15", -1)`. Returns `None` for
+/// anything that is not a finite decimal.
+fn canonical_decimal(s: &str) -> Option<(bool, String, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(pos) => (&s[..pos], s[pos + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty()
+        || !int_part
+            .chars()
+            .chain(frac_part.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let digits = format!("{}{}", int_part, frac_part);
+    let mut exponent = exponent - frac_part.len() as i64;
+    let trimmed = digits.trim_start_matches('0');
+    if trimmed.is_empty() {
+        return Some((false, "0".to_string(), 0));
+    }
+    let without_trailing = trimmed.trim_end_matches('0');
+    exponent += (trimmed.len() - without_trailing.len()) as i64;
+    Some((negative, without_trailing.to_string(), exponent))
+}
+
+/// `decimal` as a JSON number, if the number is written back as the same decimal value
+///
+/// JSONB stores numbers as `numeric`, so this holds for values such as `0.1`
+/// that are inexact as an f64 but print the same, and fails for values with
+/// more significant digits than an f64 carries.
+pub fn exact_json_number(decimal: &str) -> Option<serde_json::Number> {
+    let expected = canonical_decimal(decimal)?;
+    let value: f64 = decimal.parse().ok()?;
+    if !value.is_finite() {
+        return None;
+    }
+    // Rust prints the shortest string that parses back to the same f64
+    if canonical_decimal(&format!("{:e}", value))? != expected {
+        return None;
+    }
+    serde_json::Number::from_f64(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type_map() {
+        let map: BsonTypeMap =
+            toml::from_str("objectid = \"text\"\ndecimal128 = \"number\"\ndatetime = \"iso8601\"")
+                .unwrap();
+        assert_eq!(map.objectid, ObjectIdMapping::Text);
+        assert_eq!(map.decimal128, DecimalMapping::Number);
+        assert_eq!(map.datetime, DateTimeMapping::Iso8601);
+        assert_eq!(map.binary, BinaryMapping::Tagged);
+        assert!(toml::from_str::<BsonTypeMap>("uuid = \"text\"").is_err());
+        assert!(toml::from_str::<BsonTypeMap>("objectid = \"hex\"").is_err());
+    }
+
+    #[test]
+    fn test_exact_json_number() {
+        assert_eq!(exact_json_number("1.50").unwrap().as_f64(), Some(1.5));
+        assert_eq!(
+            exact_json_number("-2.5E+3").unwrap().as_f64(),
+            Some(-2500.0)
+        );
+        assert_eq!(exact_json_number("0.000").unwrap().as_f64(), Some(0.0));
+        // More digits than an f64 carries
+        assert!(exact_json_number("12345678901234567890.123").is_none());
+        assert!(exact_json_number("0.1000000000000000000001").is_none());
+        assert!(exact_json_number("NaN").is_none());
+        assert!(exact_json_number("Infinity").is_none());
+    }
+}
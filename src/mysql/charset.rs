@@ -0,0 +1,316 @@
+// ABOUTME: MySQL character set detection and transcoding of legacy-encoded text to UTF-8
+// ABOUTME: Maps column and connection charsets (latin1, cp1251, ...) to decoders, with per-table overrides
+
+use anyhow::{bail, Context, Result};
+use encoding_rs::Encoding;
+use mysql_async::{prelude::*, Conn};
+use std::collections::HashMap;
+
+/// Decoder for a MySQL character set name, or a WHATWG encoding label
+///
+/// MySQL's `latin1` is really cp1252, and decodes as windows-1252.
+pub fn encoding_for_charset(name: &str) -> Option<&'static Encoding> {
+    let name = name.trim().to_ascii_lowercase();
+    let encoding = match name.as_str() {
+        "utf8" | "utf8mb3" | "utf8mb4" | "ascii" => encoding_rs::UTF_8,
+        "latin1" => encoding_rs::WINDOWS_1252,
+        "latin2" => encoding_rs::ISO_8859_2,
+        "latin7" => encoding_rs::ISO_8859_13,
+        "cp1250" => encoding_rs::WINDOWS_1250,
+        "cp1251" => encoding_rs::WINDOWS_1251,
+        "cp1256" => encoding_rs::WINDOWS_1256,
+        "cp1257" => encoding_rs::WINDOWS_1257,
+        "cp866" => encoding_rs::IBM866,
+        "koi8r" => encoding_rs::KOI8_R,
+        "koi8u" => encoding_rs::KOI8_U,
+        "greek" => encoding_rs::ISO_8859_7,
+        "hebrew" => encoding_rs::ISO_8859_8,
+        "tis620" => encoding_rs::WINDOWS_874,
+        "sjis" | "cp932" => encoding_rs::SHIFT_JIS,
+        "ujis" | "eucjpms" => encoding_rs::EUC_JP,
+        "gbk" | "gb2312" => encoding_rs::GBK,
+        "gb18030" => encoding_rs::GB18030,
+        "big5" => encoding_rs::BIG5,
+        "euckr" => encoding_rs::EUC_KR,
+        other => return Encoding::for_label(other.as_bytes()),
+    };
+    Some(encoding)
+}
+
+fn is_utf8(encoding: &'static Encoding) -> bool {
+    encoding == encoding_rs::UTF_8
+}
+
+/// Parse `--mysql-charset` values of the form `TABLE=CHARSET`
+///
+/// # Errors
+///
+/// Returns an error for a malformed entry, an invalid table name, or a
+/// charset with no decoder.
+pub fn parse_overrides(values: &[String]) -> Result<HashMap<String, &'static Encoding>> {
+    let mut overrides = HashMap::new();
+    for value in values {
+        let Some((table, charset)) = value.split_once('=') else {
+            bail!(
+                "Invalid --mysql-charset '{}'. Expected TABLE=CHARSET, e.g. posts=cp1251",
+                value
+            );
+        };
+        let table = table.trim();
+        crate::jsonb::validate_table_name(table)
+            .with_context(|| format!("Invalid table in --mysql-charset '{}'", value))?;
+        let Some(encoding) = encoding_for_charset(charset) else {
+            bail!(
+                "Unsupported charset '{}' in --mysql-charset '{}'. \
+                 Use a MySQL charset name such as latin1, cp1251, koi8r, sjis, or gbk",
+                charset.trim(),
+                value
+            );
+        };
+        overrides.insert(table.to_string(), encoding);
+    }
+    Ok(overrides)
+}
+
+/// Character set of each column of a table, in ordinal order (`None` for non-text columns)
+///
+/// # Errors
+///
+/// Returns an error if INFORMATION_SCHEMA cannot be queried.
+pub async fn get_column_charsets(
+    conn: &mut Conn,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<Option<String>>> {
+    let query = r#"
+        SELECT CHARACTER_SET_NAME
+        FROM INFORMATION_SCHEMA.COLUMNS
+        WHERE TABLE_SCHEMA = ?
+        AND TABLE_NAME = ?
+        ORDER BY ORDINAL_POSITION
+    "#;
+    conn.exec(query, (db_name, table_name))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to get column charsets for table '{}.{}'",
+                db_name, table_name
+            )
+        })
+}
+
+/// The connection's `character_set_results` (`None` when results are sent unconverted)
+///
+/// # Errors
+///
+/// Returns an error if the variable cannot be read.
+pub async fn results_charset(conn: &mut Conn) -> Result<Option<String>> {
+    let charset: Option<Option<String>> = conn
+        .query_first("SELECT @@character_set_results")
+        .await
+        .context("Failed to read character_set_results from MySQL")?;
+    Ok(charset
+        .flatten()
+        .filter(|name| !name.eq_ignore_ascii_case("binary")))
+}
+
+/// Have the server send stored bytes unconverted, returning the setting to restore
+///
+/// # Errors
+///
+/// Returns an error if the session variable cannot be read or set.
+pub async fn use_raw_results(conn: &mut Conn) -> Result<Option<String>> {
+    let previous = results_charset(conn).await?;
+    conn.query_drop("SET character_set_results = NULL")
+        .await
+        .context("Failed to disable result charset conversion on MySQL")?;
+    Ok(previous)
+}
+
+/// Restore `character_set_results` after [`use_raw_results`]
+///
+/// # Errors
+///
+/// Returns an error if the session variable cannot be set.
+pub async fn restore_results(conn: &mut Conn, previous: Option<String>) -> Result<()> {
+    let statement = match previous {
+        Some(charset)
+            if charset
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+        {
+            format!("SET character_set_results = {}", charset)
+        }
+        Some(charset) => bail!("Unexpected character_set_results '{}'", charset),
+        None => "SET character_set_results = NULL".to_string(),
+    };
+    conn.query_drop(statement)
+        .await
+        .context("Failed to restore character_set_results on MySQL")
+}
+
+/// How the text columns of one table must be decoded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDecoding {
+    /// Decoder per column in ordinal order; `None` where bytes are already UTF-8 or not text
+    pub columns: Vec<Option<&'static Encoding>>,
+    /// Whether the table must be read with [`use_raw_results`]
+    pub raw_results: bool,
+}
+
+impl TableDecoding {
+    /// Decide how to decode a table's text columns
+    ///
+    /// * With an override, the stored bytes are read unconverted and every
+    ///   text column is decoded with the override, whatever its declared charset.
+    /// * Otherwise the server converts each column to `results_charset`, so only
+    ///   a non-UTF-8 results charset needs decoding; with no results charset
+    ///   each column is decoded from its own declared charset.
+    pub fn plan(
+        column_charsets: &[Option<String>],
+        results_charset: Option<&str>,
+        table_override: Option<&'static Encoding>,
+    ) -> Self {
+        let decode = |encoding: Option<&'static Encoding>| encoding.filter(|e| !is_utf8(e));
+        if let Some(encoding) = table_override {
+            return TableDecoding {
+                columns: column_charsets
+                    .iter()
+                    .map(|charset| charset.as_ref().and_then(|_| decode(Some(encoding))))
+                    .collect(),
+                raw_results: true,
+            };
+        }
+        let results = results_charset.map(encoding_for_charset);
+        TableDecoding {
+            columns: column_charsets
+                .iter()
+                .map(|charset| {
+                    let charset = charset.as_deref()?;
+                    match results {
+                        Some(results) => decode(results),
+                        None => decode(encoding_for_charset(charset)),
+                    }
+                })
+                .collect(),
+            raw_results: false,
+        }
+    }
+
+    pub fn encoding(&self, idx: usize) -> Option<&'static Encoding> {
+        self.columns.get(idx).copied().flatten()
+    }
+}
+
+/// Decode `bytes` stored in `encoding`
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid in `encoding`.
+pub fn decode(bytes: &[u8], encoding: &'static Encoding) -> Result<String> {
+    match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+        Some(text) => Ok(text.into_owned()),
+        None => bail!("value is not valid {}", encoding.name()),
+    }
+}
+
+/// Log declared legacy charsets, so mislabeled columns can be overridden
+pub fn log_legacy_columns(table_name: &str, column_names: &[String], charsets: &[Option<String>]) {
+    let legacy: Vec<String> = column_names
+        .iter()
+        .zip(charsets)
+        .filter_map(|(column, charset)| {
+            let charset = charset.as_deref()?;
+            match encoding_for_charset(charset) {
+                Some(encoding) if is_utf8(encoding) => None,
+                _ => Some(format!("{} ({})", column, charset)),
+            }
+        })
+        .collect();
+    if !legacy.is_empty() {
+        tracing::info!(
+            "  Table '{}' has non-UTF-8 columns, transcoded to UTF-8: {}. \
+             If text looks garbled on the target, the declared charset is wrong; \
+             re-run with --mysql-charset {}=<actual charset>",
+            table_name,
+            legacy.join(", "),
+            table_name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charsets(names: &[Option<&str>]) -> Vec<Option<String>> {
+        names.iter().map(|n| n.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn test_encoding_for_charset() {
+        assert_eq!(
+            encoding_for_charset("latin1"),
+            Some(encoding_rs::WINDOWS_1252)
+        );
+        assert_eq!(
+            encoding_for_charset("CP1251"),
+            Some(encoding_rs::WINDOWS_1251)
+        );
+        assert_eq!(
+            encoding_for_charset("windows-1251"),
+            Some(encoding_rs::WINDOWS_1251)
+        );
+        assert_eq!(encoding_for_charset("utf8mb4"), Some(encoding_rs::UTF_8));
+        assert_eq!(encoding_for_charset("swe7"), None);
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        let overrides = parse_overrides(&["posts=cp1251".to_string()]).unwrap();
+        assert_eq!(overrides["posts"], encoding_rs::WINDOWS_1251);
+        assert!(parse_overrides(&["posts".to_string()]).is_err());
+        assert!(parse_overrides(&["posts=swe7".to_string()]).is_err());
+        assert!(parse_overrides(&["posts;drop=latin1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_plan_decoding() {
+        let columns = charsets(&[None, Some("latin1"), Some("utf8mb4")]);
+
+        // Server converts to utf8mb4: nothing to do
+        let plan = TableDecoding::plan(&columns, Some("utf8mb4"), None);
+        assert_eq!(plan.columns, vec![None, None, None]);
+        assert!(!plan.raw_results);
+
+        // Server converts to cp1251: every text column arrives in cp1251
+        let plan = TableDecoding::plan(&columns, Some("cp1251"), None);
+        assert_eq!(plan.encoding(1), Some(encoding_rs::WINDOWS_1251));
+        assert_eq!(plan.encoding(2), Some(encoding_rs::WINDOWS_1251));
+        assert_eq!(plan.encoding(0), None);
+
+        // Unconverted results: each column in its declared charset
+        let plan = TableDecoding::plan(&columns, None, None);
+        assert_eq!(
+            plan.columns,
+            vec![None, Some(encoding_rs::WINDOWS_1252), None]
+        );
+
+        // Override: latin1-labelled cp1251 text is read raw
+        let plan = TableDecoding::plan(&columns, Some("utf8mb4"), Some(encoding_rs::WINDOWS_1251));
+        assert!(plan.raw_results);
+        assert_eq!(plan.encoding(1), Some(encoding_rs::WINDOWS_1251));
+        assert_eq!(plan.encoding(0), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        let privet = [0xCF, 0xF0, 0xE8, 0xE2, 0xE5, 0xF2];
+        assert_eq!(
+            decode(&privet, encoding_rs::WINDOWS_1251).unwrap(),
+            "Привет"
+        );
+        assert_eq!(decode(&[0xE9], encoding_rs::WINDOWS_1252).unwrap(), "é");
+        assert!(decode(&[0x81], encoding_rs::SHIFT_JIS).is_err());
+    }
+}
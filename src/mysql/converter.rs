@@ -1,9 +1,15 @@
 // ABOUTME: MySQL to JSONB type conversion with lossless data preservation
 // ABOUTME: Handles all MySQL data types including dates, decimals, and binary data
 
+use crate::jsonb::row_errors::{ConvertedRows, RowError};
+use crate::jsonb::JsonbOptions;
+use crate::mysql::charset::{self, TableDecoding};
+use crate::mysql::datetime::{self, DatetimeColumn, InvalidDatePolicy};
 use anyhow::{Context, Result};
+use encoding_rs::Encoding;
 use mysql_async::{prelude::*, Row, Value};
 use serde_json::Value as JsonValue;
+use std::collections::HashSet;
 
 /// Convert a MySQL Value to JSON Value
 ///
@@ -76,6 +82,10 @@ pub fn mysql_value_to_json(value: &Value) -> Result<JsonValue> {
             }
         }
 
+        Value::Date(..) if datetime::is_invalid_date(value) => {
+            datetime::invalid_date_to_json(value, None, InvalidDatePolicy::default())
+        }
+
         Value::Date(year, month, day, hour, minute, second, micro) => {
             // Format as ISO 8601 datetime string
             let datetime_str = format!(
@@ -128,6 +138,24 @@ pub fn mysql_value_to_json(value: &Value) -> Result<JsonValue> {
 /// # }
 /// ```
 pub fn mysql_row_to_json(row: &Row, column_names: &[String]) -> Result<JsonValue> {
+    row_to_json(
+        row,
+        column_names,
+        &TableDecoding::default(),
+        &[],
+        &[],
+        InvalidDatePolicy::default(),
+    )
+}
+
+fn row_to_json(
+    row: &Row,
+    column_names: &[String],
+    decoding: &TableDecoding,
+    spatial: &[bool],
+    datetimes: &[Option<DatetimeColumn>],
+    invalid_dates: InvalidDatePolicy,
+) -> Result<JsonValue> {
     let mut obj = serde_json::Map::new();
 
     for (idx, col_name) in column_names.iter().enumerate() {
@@ -137,8 +165,14 @@ pub fn mysql_row_to_json(row: &Row, column_names: &[String]) -> Result<JsonValue
             .ok_or_else(|| anyhow::anyhow!("Failed to get column {} at index {}", col_name, idx))?;
 
         // Convert to JSON
-        let json_val = mysql_value_to_json(&value)
-            .with_context(|| format!("Failed to convert column '{}' to JSON", col_name))?;
+        let json_val = column_to_json(
+            &value,
+            decoding.encoding(idx),
+            is_spatial(spatial, idx),
+            datetime_column(datetimes, idx),
+            invalid_dates,
+        )
+        .with_context(|| format!("Failed to convert column '{}' to JSON", col_name))?;
 
         obj.insert(col_name.clone(), json_val);
     }
@@ -146,6 +180,43 @@ pub fn mysql_row_to_json(row: &Row, column_names: &[String]) -> Result<JsonValue
     Ok(JsonValue::Object(obj))
 }
 
+fn is_spatial(spatial: &[bool], idx: usize) -> bool {
+    spatial.get(idx).copied().unwrap_or(false)
+}
+
+fn datetime_column(datetimes: &[Option<DatetimeColumn>], idx: usize) -> Option<&DatetimeColumn> {
+    datetimes.get(idx).and_then(Option::as_ref)
+}
+
+/// Convert a column value, transcoding text stored in a legacy charset to UTF-8,
+/// decoding spatial values to WKT/GeoJSON, applying `--assume-source-tz`
+/// to DATETIME values, and `--invalid-dates` to zero and impossible dates
+fn column_to_json(
+    value: &Value,
+    encoding: Option<&'static Encoding>,
+    spatial: bool,
+    datetime: Option<&DatetimeColumn>,
+    invalid_dates: InvalidDatePolicy,
+) -> Result<JsonValue> {
+    if datetime::is_invalid_date(value) {
+        return datetime::invalid_date_to_json(value, datetime, invalid_dates);
+    }
+    if let (Value::Date(year, month, day, hour, minute, second, micro), Some(column)) =
+        (value, datetime)
+    {
+        return datetime::datetime_to_json(
+            column, *year, *month, *day, *hour, *minute, *second, *micro,
+        );
+    }
+    match (value, encoding) {
+        (Value::Bytes(bytes), _) if spatial => crate::mysql::spatial::geometry_to_json(bytes),
+        (Value::Bytes(bytes), Some(encoding)) => {
+            Ok(JsonValue::String(charset::decode(bytes, encoding)?))
+        }
+        _ => mysql_value_to_json(value),
+    }
+}
+
 /// Get column names for a MySQL table
 ///
 /// Queries INFORMATION_SCHEMA to get all column names for a table.
@@ -199,29 +270,95 @@ pub async fn get_column_names(
     Ok(columns)
 }
 
+/// Columns used as the JSONB row ID, in order of preference
+const ID_COLUMNS: [&str; 3] = ["id", "Id", "ID"];
+
+/// The JSONB row ID for an ID column's converted value
+fn json_id(value: &JsonValue) -> String {
+    value.to_string().trim_matches('"').to_string()
+}
+
+/// Read the JSONB row IDs of a table without converting its rows
+///
+/// The IDs are the ones [`convert_table_to_jsonb`] assigns, so they can be
+/// compared with the `id` column of the table's JSONB copy.
+///
+/// # Returns
+///
+/// None when the table has no `id` column (its IDs are generated from row
+/// positions) or an ID holds non-ASCII bytes, whose transcoding depends on
+/// the column charset.
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read.
+pub async fn read_table_ids(
+    conn: &mut mysql_async::Conn,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Option<HashSet<String>>> {
+    crate::jsonb::validate_table_name(table_name).context("Invalid table name for ID reading")?;
+    let column_names = get_column_names(conn, db_name, table_name).await?;
+    let Some(id_column) = ID_COLUMNS
+        .iter()
+        .find(|column| column_names.iter().any(|name| name == *column))
+    else {
+        return Ok(None);
+    };
+    let values: Vec<Value> = conn
+        .query(format!(
+            "SELECT `{}` FROM `{}`.`{}`",
+            id_column, db_name, table_name
+        ))
+        .await
+        .with_context(|| format!("Failed to read IDs from table '{}'", table_name))?;
+    let mut ids = HashSet::with_capacity(values.len());
+    for value in &values {
+        if matches!(value, Value::Bytes(bytes) if !bytes.is_ascii()) {
+            return Ok(None);
+        }
+        ids.insert(json_id(&mysql_value_to_json(value)?));
+    }
+    Ok(Some(ids))
+}
+
 /// Convert an entire MySQL table to JSONB format
 ///
 /// Reads all rows from the table and converts them to (id, jsonb_data) tuples.
-/// The ID is extracted from a primary key or auto-generated.
+/// The ID is extracted from a primary key or auto-generated. Text in legacy
+/// charsets (latin1, cp1251, ...) is transcoded to UTF-8, using the table's
+/// `--mysql-charset` override when set. Spatial values become
+/// `{"_type": "geometry", "srid", "wkt", "geojson"}` objects. Zero and impossible dates follow
+/// `--invalid-dates`, with a per-table count logged. Rows that fail to convert are handled
+/// by the `--on-row-error` policy.
 ///
 /// # Arguments
 ///
 /// * `conn` - MySQL connection
 /// * `db_name` - Database name
 /// * `table_name` - Table name
+/// * `jsonb` - Charset overrides, assumed zones, and date and row error policies of this run
 ///
 /// # Returns
 ///
-/// Vector of (id, json_data) tuples ready for PostgreSQL JSONB storage
+/// (id, json_data) tuples ready for PostgreSQL JSONB storage, plus the rows
+/// that were skipped or stubbed
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read, or a row cannot be converted
+/// under the `fail` policy.
 ///
 /// # Examples
 ///
 /// ```no_run
+/// # use seren_replicator::jsonb::JsonbOptions;
 /// # use seren_replicator::mysql::{connect_mysql, converter::convert_table_to_jsonb};
 /// # async fn example() -> anyhow::Result<()> {
 /// let mut conn = connect_mysql("mysql://localhost:3306/mydb").await?;
-/// let rows = convert_table_to_jsonb(&mut conn, "mydb", "users").await?;
-/// println!("Converted {} rows", rows.len());
+/// let converted =
+///     convert_table_to_jsonb(&mut conn, "mydb", "users", &JsonbOptions::default()).await?;
+/// println!("Converted {} rows", converted.rows.len());
 /// # Ok(())
 /// # }
 /// ```
@@ -229,7 +366,8 @@ pub async fn convert_table_to_jsonb(
     conn: &mut mysql_async::Conn,
     db_name: &str,
     table_name: &str,
-) -> Result<Vec<(String, JsonValue)>> {
+    jsonb: &JsonbOptions,
+) -> Result<ConvertedRows> {
     // Validate table name
     crate::jsonb::validate_table_name(table_name)
         .context("Invalid table name for JSONB conversion")?;
@@ -245,47 +383,142 @@ pub async fn convert_table_to_jsonb(
 
     if column_names.is_empty() {
         tracing::warn!("Table '{}.{}' has no columns", db_name, table_name);
-        return Ok(vec![]);
+        return Ok(ConvertedRows::default());
     }
 
-    // Read all rows
-    let rows = crate::mysql::reader::read_table_data(conn, db_name, table_name).await?;
+    // Work out how text columns must be transcoded to UTF-8
+    let column_charsets = charset::get_column_charsets(conn, db_name, table_name).await?;
+    let table_override = jsonb.mysql_charsets.get(table_name).copied();
+    if table_override.is_none() {
+        charset::log_legacy_columns(table_name, &column_names, &column_charsets);
+    }
+    let results_charset = charset::results_charset(conn).await?;
+    let decoding =
+        TableDecoding::plan(&column_charsets, results_charset.as_deref(), table_override);
+    let spatial = crate::mysql::spatial::get_spatial_flags(conn, db_name, table_name).await?;
+    let datetimes = datetime::plan(
+        table_name,
+        &column_names,
+        &datetime::get_datetime_flags(conn, db_name, table_name).await?,
+        &jsonb.source_timezones,
+    );
 
-    let mut result = Vec::with_capacity(rows.len());
+    // Read all rows, unconverted by the server when the declared charsets are overridden
+    let rows = if decoding.raw_results {
+        let previous = charset::use_raw_results(conn).await?;
+        let rows = crate::mysql::reader::read_table_data(conn, db_name, table_name).await;
+        charset::restore_results(conn, previous).await?;
+        rows?
+    } else {
+        crate::mysql::reader::read_table_data(conn, db_name, table_name).await?
+    };
+
+    let mut converted = ConvertedRows {
+        rows: Vec::with_capacity(rows.len()),
+        errors: Vec::new(),
+    };
     let mut id_counter = 1u64;
+    let mut invalid_dates = 0usize;
 
-    for row in rows {
+    for (row_num, row) in rows.into_iter().enumerate() {
         // Convert row to JSON
-        let json_data = mysql_row_to_json(&row, &column_names)
-            .with_context(|| format!("Failed to convert row in table '{}'", table_name))?;
+        let json_data = match row_to_json(
+            &row,
+            &column_names,
+            &decoding,
+            &spatial,
+            &datetimes,
+            jsonb.invalid_dates,
+        ) {
+            Ok(json_data) => json_data,
+            Err(e) => {
+                let (column, raw) = failing_value(
+                    &row,
+                    &column_names,
+                    &decoding,
+                    &spatial,
+                    &datetimes,
+                    jsonb.invalid_dates,
+                );
+                let error = RowError {
+                    row_number: row_num as u64 + 1,
+                    column,
+                    raw,
+                    error: format!("{:#}", e),
+                };
+                converted.reject(jsonb.on_row_error, table_name, error)?;
+                continue;
+            }
+        };
 
         // Try to extract ID from common ID column names
-        let id = if let Some(id_val) = json_data.get("id") {
-            // Use 'id' column if exists
-            id_val.to_string().trim_matches('"').to_string()
-        } else if let Some(id_val) = json_data.get("Id") {
-            // Case insensitive check
-            id_val.to_string().trim_matches('"').to_string()
-        } else if let Some(id_val) = json_data.get("ID") {
-            id_val.to_string().trim_matches('"').to_string()
-        } else {
-            // Generate sequential ID
-            let generated_id = format!("generated_{}", id_counter);
-            id_counter += 1;
-            generated_id
+        let id = match ID_COLUMNS.iter().find_map(|column| json_data.get(*column)) {
+            Some(id_val) => json_id(id_val),
+            None => {
+                // Generate sequential ID
+                let generated_id = format!("generated_{}", id_counter);
+                id_counter += 1;
+                generated_id
+            }
         };
 
-        result.push((id, json_data));
+        invalid_dates += (0..column_names.len())
+            .filter(|idx| row.as_ref(*idx).is_some_and(datetime::is_invalid_date))
+            .count();
+        converted.rows.push((id, json_data));
+    }
+
+    if invalid_dates > 0 {
+        let message = format!(
+            "Table '{}.{}': stored {} zero or invalid date value(s) as {} (--invalid-dates)",
+            db_name, table_name, invalid_dates, jsonb.invalid_dates
+        );
+        tracing::warn!("⚠ {}", message);
+        crate::events::warning(&message);
     }
 
     tracing::info!(
         "Converted {} rows from table '{}.{}'",
-        result.len(),
+        converted.rows.len(),
         db_name,
         table_name
     );
 
-    Ok(result)
+    Ok(converted)
+}
+
+/// First column of `row` that cannot be converted, with its raw bytes
+fn failing_value(
+    row: &Row,
+    column_names: &[String],
+    decoding: &TableDecoding,
+    spatial: &[bool],
+    datetimes: &[Option<DatetimeColumn>],
+    invalid_dates: InvalidDatePolicy,
+) -> (Option<String>, Vec<u8>) {
+    for (idx, col_name) in column_names.iter().enumerate() {
+        match row.as_ref(idx) {
+            None => return (Some(col_name.clone()), Vec::new()),
+            Some(value)
+                if column_to_json(
+                    value,
+                    decoding.encoding(idx),
+                    is_spatial(spatial, idx),
+                    datetime_column(datetimes, idx),
+                    invalid_dates,
+                )
+                .is_err() =>
+            {
+                let raw = match value {
+                    Value::Bytes(bytes) => bytes.clone(),
+                    other => other.as_sql(true).into_bytes(),
+                };
+                return (Some(col_name.clone()), raw);
+            }
+            Some(_) => {}
+        }
+    }
+    (None, Vec::new())
 }
 
 #[cfg(test)]
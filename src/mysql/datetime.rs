@@ -0,0 +1,452 @@
+// ABOUTME: MySQL DATETIME conversion - true UTC instants under --assume-source-tz, distinct naive values otherwise
+// ABOUTME: Zero and impossible dates (0000-00-00) follow the --invalid-dates policy: null, epoch, string, or fail
+
+use anyhow::{bail, Context, Result};
+use jiff::civil::DateTime;
+use jiff::tz::TimeZone;
+use mysql_async::{prelude::*, Conn, Value};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Zones assumed for DATETIME values (`--assume-source-tz`)
+#[derive(Debug, Clone, Default)]
+pub struct SourceTimezones {
+    /// Zone for every DATETIME column without an override
+    pub default: Option<TimeZone>,
+    /// Per-column zones, keyed by `table.column`
+    pub columns: HashMap<String, TimeZone>,
+}
+
+impl SourceTimezones {
+    /// The zone assumed for `table.column`, if any
+    pub fn zone_for(&self, table: &str, column: &str) -> Option<&TimeZone> {
+        self.columns
+            .get(&format!("{}.{}", table, column))
+            .or(self.default.as_ref())
+    }
+}
+
+/// Parse `--assume-source-tz` values: `ZONE` for every DATETIME column, or
+/// `TABLE.COLUMN=ZONE` for one column
+///
+/// Zones are IANA names such as `Europe/Berlin` or `UTC`.
+///
+/// # Errors
+///
+/// Returns an error for an unknown zone, a malformed column, or two default zones.
+pub fn parse_timezones(values: &[String]) -> Result<SourceTimezones> {
+    let mut timezones = SourceTimezones::default();
+    for value in values {
+        let value = value.trim();
+        match value.split_once('=') {
+            Some((column, zone)) => {
+                let column = column.trim();
+                let Some((table, name)) = column.split_once('.') else {
+                    bail!(
+                        "Invalid --assume-source-tz '{}'. Expected TABLE.COLUMN=ZONE, e.g. orders.created_at=UTC",
+                        value
+                    );
+                };
+                crate::jsonb::validate_table_name(table)
+                    .with_context(|| format!("Invalid table in --assume-source-tz '{}'", value))?;
+                if name.is_empty() {
+                    bail!("Missing column in --assume-source-tz '{}'", value);
+                }
+                timezones
+                    .columns
+                    .insert(column.to_string(), lookup_zone(zone.trim(), value)?);
+            }
+            None => {
+                if timezones.default.is_some() {
+                    bail!(
+                        "--assume-source-tz takes one default zone; use TABLE.COLUMN=ZONE for '{}'",
+                        value
+                    );
+                }
+                timezones.default = Some(lookup_zone(value, value)?);
+            }
+        }
+    }
+    Ok(timezones)
+}
+
+fn lookup_zone(name: &str, value: &str) -> Result<TimeZone> {
+    TimeZone::get(name).map_err(|_| {
+        anyhow::anyhow!(
+            "Unknown timezone '{}' in --assume-source-tz '{}'. Use an IANA name such as Europe/Berlin or UTC",
+            name,
+            value
+        )
+    })
+}
+
+/// How each DATETIME column of `table` is converted under `timezones`, in
+/// ordinal order (`None` for other columns)
+pub fn plan(
+    table: &str,
+    column_names: &[String],
+    datetime: &[bool],
+    timezones: &SourceTimezones,
+) -> Vec<Option<DatetimeColumn>> {
+    column_names
+        .iter()
+        .zip(datetime)
+        .map(|(column, is_datetime)| {
+            is_datetime.then(|| match timezones.zone_for(table, column) {
+                Some(zone) => DatetimeColumn::Zoned(zone.clone()),
+                None => DatetimeColumn::Naive,
+            })
+        })
+        .collect()
+}
+
+/// How one DATETIME column is stored
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatetimeColumn {
+    /// No zone assumed: `{"_type": "naive_datetime", "value": "YYYY-MM-DDTHH:MM:SS.ffffff"}`
+    Naive,
+    /// Values are wall-clock times in this zone, stored as UTC instants:
+    /// `{"_type": "datetime", "value": "YYYY-MM-DDTHH:MM:SS.ffffffZ"}`
+    Zoned(TimeZone),
+}
+
+/// Which columns of a table are DATETIME, in ordinal order
+///
+/// # Errors
+///
+/// Returns an error if INFORMATION_SCHEMA cannot be queried.
+pub async fn get_datetime_flags(
+    conn: &mut Conn,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<bool>> {
+    let query = r#"
+        SELECT DATA_TYPE
+        FROM INFORMATION_SCHEMA.COLUMNS
+        WHERE TABLE_SCHEMA = ?
+        AND TABLE_NAME = ?
+        ORDER BY ORDINAL_POSITION
+    "#;
+    let data_types: Vec<String> =
+        conn.exec(query, (db_name, table_name))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to get column types for table '{}.{}'",
+                    db_name, table_name
+                )
+            })?;
+    Ok(data_types
+        .iter()
+        .map(|data_type| data_type.eq_ignore_ascii_case("datetime"))
+        .collect())
+}
+
+/// Convert the parts of a DATETIME value as `column` stores it
+///
+/// Wall-clock times skipped by a DST change are read with the offset in force
+/// before it (02:30 becomes 03:30), and repeated ones as their first
+/// occurrence, as PostgreSQL does for `timestamptz` input.
+///
+/// # Errors
+///
+/// Returns an error if a zoned value is not a valid date and time.
+#[allow(clippy::too_many_arguments)]
+pub fn datetime_to_json(
+    column: &DatetimeColumn,
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    micro: u32,
+) -> Result<JsonValue> {
+    let zone = match column {
+        DatetimeColumn::Naive => {
+            return Ok(serde_json::json!({
+                "_type": "naive_datetime",
+                "value": format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}",
+                    year, month, day, hour, minute, second, micro
+                )
+            }));
+        }
+        DatetimeColumn::Zoned(zone) => zone,
+    };
+    let invalid = || {
+        format!(
+            "Invalid DATETIME {:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+            year, month, day, hour, minute, second, micro
+        )
+    };
+    let local = DateTime::new(
+        i16::try_from(year).with_context(invalid)?,
+        month as i8,
+        day as i8,
+        hour as i8,
+        minute as i8,
+        second as i8,
+        i32::try_from(u64::from(micro) * 1000).with_context(invalid)?,
+    )
+    .with_context(invalid)?;
+    let utc = local
+        .to_zoned(zone.clone())
+        .with_context(invalid)?
+        .timestamp()
+        .to_zoned(TimeZone::UTC)
+        .datetime();
+    Ok(serde_json::json!({
+        "_type": "datetime",
+        "value": format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            utc.year(),
+            utc.month(),
+            utc.day(),
+            utc.hour(),
+            utc.minute(),
+            utc.second(),
+            utc.subsec_nanosecond() / 1000
+        )
+    }))
+}
+
+/// What to store for a zero or impossible date such as `0000-00-00` (`--invalid-dates`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidDatePolicy {
+    /// JSON null
+    Null,
+    /// The Unix epoch, in the column's usual shape
+    Epoch,
+    /// The value as MySQL prints it, as a plain string
+    #[default]
+    String,
+    /// Reject the row, leaving it to `--on-row-error`
+    Fail,
+}
+
+impl InvalidDatePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvalidDatePolicy::Null => "null",
+            InvalidDatePolicy::Epoch => "epoch",
+            InvalidDatePolicy::String => "string",
+            InvalidDatePolicy::Fail => "fail",
+        }
+    }
+}
+
+impl fmt::Display for InvalidDatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for InvalidDatePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "null" => Ok(InvalidDatePolicy::Null),
+            "epoch" => Ok(InvalidDatePolicy::Epoch),
+            "string" => Ok(InvalidDatePolicy::String),
+            "fail" => Ok(InvalidDatePolicy::Fail),
+            other => bail!(
+                "Unknown invalid date policy '{}'. Expected one of: null, epoch, string, fail",
+                other
+            ),
+        }
+    }
+}
+
+/// Whether `value` is a DATE, DATETIME, or TIMESTAMP with a zero or impossible
+/// date part, as MySQL stores without `NO_ZERO_DATE` or with `ALLOW_INVALID_DATES`
+pub fn is_invalid_date(value: &Value) -> bool {
+    match value {
+        Value::Date(year, month, day, ..) => {
+            !(1..=12).contains(month)
+                || *day == 0
+                || i64::from(*day)
+                    > crate::jsonb::metadata::days_in_month(i64::from(*year), i64::from(*month))
+        }
+        _ => false,
+    }
+}
+
+/// Convert an invalid date under `policy`
+///
+/// `column` is how the value's DATETIME column is stored; other date columns
+/// pass `None` and keep the `{"_type": "datetime"}` shape.
+///
+/// # Errors
+///
+/// Returns an error under the `fail` policy.
+pub fn invalid_date_to_json(
+    value: &Value,
+    column: Option<&DatetimeColumn>,
+    policy: InvalidDatePolicy,
+) -> Result<JsonValue> {
+    let Value::Date(year, month, day, hour, minute, second, micro) = value else {
+        bail!("Not a date value");
+    };
+    match policy {
+        InvalidDatePolicy::Null => Ok(JsonValue::Null),
+        InvalidDatePolicy::Epoch => match column {
+            Some(DatetimeColumn::Naive) => {
+                datetime_to_json(&DatetimeColumn::Naive, 1970, 1, 1, 0, 0, 0, 0)
+            }
+            _ => Ok(serde_json::json!({
+                "_type": "datetime",
+                "value": "1970-01-01T00:00:00.000000Z"
+            })),
+        },
+        InvalidDatePolicy::String => Ok(JsonValue::String(mysql_text(
+            *year, *month, *day, *hour, *minute, *second, *micro,
+        ))),
+        InvalidDatePolicy::Fail => bail!(
+            "Invalid date '{}'. Use --invalid-dates null, epoch, or string to convert such values",
+            mysql_text(*year, *month, *day, *hour, *minute, *second, *micro)
+        ),
+    }
+}
+
+/// A date as MySQL prints it: `YYYY-MM-DD`, with the time when it is not midnight
+fn mysql_text(
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    micro: u32,
+) -> String {
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    match (hour, minute, second, micro) {
+        (0, 0, 0, 0) => date,
+        (_, _, _, 0) => format!("{} {:02}:{:02}:{:02}", date, hour, minute, second),
+        _ => format!(
+            "{} {:02}:{:02}:{:02}.{:06}",
+            date, hour, minute, second, micro
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> DatetimeColumn {
+        DatetimeColumn::Zoned(TimeZone::get("Europe/Berlin").unwrap())
+    }
+
+    #[test]
+    fn test_parse_timezones() {
+        let timezones = parse_timezones(&[
+            "Europe/Berlin".to_string(),
+            "orders.created_at=UTC".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            timezones.zone_for("orders", "created_at"),
+            Some(&TimeZone::UTC)
+        );
+        assert_eq!(
+            timezones
+                .zone_for("orders", "shipped_at")
+                .and_then(|z| z.iana_name()),
+            Some("Europe/Berlin")
+        );
+        for bad in [
+            vec!["Mars/Olympus".to_string()],
+            vec!["created_at=UTC".to_string()],
+            vec!["UTC".to_string(), "Europe/Berlin".to_string()],
+            vec!["orders.created_at=Nowhere".to_string()],
+        ] {
+            assert!(parse_timezones(&bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_naive_value_has_no_zone() {
+        let json =
+            datetime_to_json(&DatetimeColumn::Naive, 2024, 1, 15, 10, 30, 45, 123456).unwrap();
+        assert_eq!(json["_type"], "naive_datetime");
+        assert_eq!(json["value"], "2024-01-15T10:30:45.123456");
+    }
+
+    #[test]
+    fn test_zoned_value_converts_to_utc() {
+        // CET (+01:00) in winter, CEST (+02:00) in summer
+        let winter = datetime_to_json(&berlin(), 2024, 1, 15, 10, 30, 45, 123456).unwrap();
+        assert_eq!(winter["_type"], "datetime");
+        assert_eq!(winter["value"], "2024-01-15T09:30:45.123456Z");
+        let summer = datetime_to_json(&berlin(), 2024, 7, 1, 0, 15, 0, 0).unwrap();
+        assert_eq!(summer["value"], "2024-06-30T22:15:00.000000Z");
+        // 02:30 does not exist on 2024-03-31 in Berlin; it reads as 03:30 CEST
+        let gap = datetime_to_json(&berlin(), 2024, 3, 31, 2, 30, 0, 0).unwrap();
+        assert_eq!(gap["value"], "2024-03-31T01:30:00.000000Z");
+        // 02:30 happens twice on 2024-10-27; the first (CEST) one is taken
+        let fold = datetime_to_json(&berlin(), 2024, 10, 27, 2, 30, 0, 0).unwrap();
+        assert_eq!(fold["value"], "2024-10-27T00:30:00.000000Z");
+        assert!(datetime_to_json(&berlin(), 2024, 2, 30, 0, 0, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_plan_only_covers_datetime_columns() {
+        let columns = vec!["id".to_string(), "created_at".to_string()];
+        let naive = plan(
+            "plan_test",
+            &columns,
+            &[false, true],
+            &SourceTimezones::default(),
+        );
+        assert_eq!(naive, vec![None, Some(DatetimeColumn::Naive)]);
+
+        let timezones =
+            parse_timezones(&["plan_test.created_at=Europe/Berlin".to_string()]).unwrap();
+        let zoned = plan("plan_test", &columns, &[false, true], &timezones);
+        assert_eq!(zoned, vec![None, Some(berlin())]);
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        let zero = Value::Date(0, 0, 0, 0, 0, 0, 0);
+        assert!(is_invalid_date(&zero));
+        assert!(is_invalid_date(&Value::Date(2024, 2, 30, 0, 0, 0, 0)));
+        assert!(is_invalid_date(&Value::Date(2024, 5, 0, 0, 0, 0, 0)));
+        assert!(!is_invalid_date(&Value::Date(2024, 2, 29, 0, 0, 0, 0)));
+        assert!(!is_invalid_date(&Value::Int(0)));
+
+        let policy = |p: &str| p.parse::<InvalidDatePolicy>().unwrap();
+        assert_eq!(
+            invalid_date_to_json(&zero, None, policy("null")).unwrap(),
+            JsonValue::Null
+        );
+        assert_eq!(
+            invalid_date_to_json(&zero, None, policy("string")).unwrap(),
+            "0000-00-00"
+        );
+        assert_eq!(
+            invalid_date_to_json(
+                &Value::Date(2024, 2, 30, 8, 5, 0, 0),
+                None,
+                policy("STRING")
+            )
+            .unwrap(),
+            "2024-02-30 08:05:00"
+        );
+        assert_eq!(
+            invalid_date_to_json(&zero, None, policy("epoch")).unwrap()["value"],
+            "1970-01-01T00:00:00.000000Z"
+        );
+        assert_eq!(
+            invalid_date_to_json(&zero, Some(&DatetimeColumn::Naive), policy("epoch")).unwrap(),
+            serde_json::json!({"_type": "naive_datetime", "value": "1970-01-01T00:00:00.000000"})
+        );
+        let err = invalid_date_to_json(&zero, Some(&berlin()), policy("fail")).unwrap_err();
+        assert!(err.to_string().contains("'0000-00-00'"));
+        assert!("zero".parse::<InvalidDatePolicy>().is_err());
+    }
+}
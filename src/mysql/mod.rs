@@ -1,8 +1,12 @@
 // ABOUTME: MySQL database reading utilities for replication to PostgreSQL
 // ABOUTME: Provides secure connection validation and read-only database access
 
+pub mod charset;
 pub mod converter;
+pub mod datetime;
 pub mod reader;
+pub mod schema;
+pub mod spatial;
 
 use anyhow::{bail, Context, Result};
 use mysql_async::{Conn, Opts};
@@ -0,0 +1,225 @@
+// ABOUTME: Records MySQL ENUM and SET column definitions in a companion _schema JSONB table
+// ABOUTME: Lets target-side validation of the opaque string values be reconstructed after migration
+
+use anyhow::{bail, Context, Result};
+use mysql_async::{prelude::*, Conn};
+use serde_json::Value as JsonValue;
+use tokio_postgres::Client;
+
+/// JSONB table on the target holding one schema document per MySQL table
+pub const SCHEMA_TABLE: &str = "_schema";
+
+/// Kind of a MySQL column with a fixed list of allowed values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumKind {
+    /// Exactly one of the values
+    Enum,
+    /// Any combination of the values, stored as a comma-separated string
+    Set,
+}
+
+impl EnumKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnumKind::Enum => "enum",
+            EnumKind::Set => "set",
+        }
+    }
+}
+
+/// An ENUM or SET column and its allowed values, in definition order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumColumn {
+    pub name: String,
+    pub kind: EnumKind,
+    pub values: Vec<String>,
+    pub nullable: bool,
+}
+
+/// Parse an INFORMATION_SCHEMA `COLUMN_TYPE` such as `enum('a','it''s')`
+///
+/// Returns `None` for other column types.
+///
+/// # Errors
+///
+/// Returns an error if the value list is malformed.
+pub fn parse_column_type(column_type: &str) -> Result<Option<(EnumKind, Vec<String>)>> {
+    let lower = column_type.to_ascii_lowercase();
+    let (kind, rest) = if lower.starts_with("enum(") {
+        (EnumKind::Enum, &column_type[5..])
+    } else if lower.starts_with("set(") {
+        (EnumKind::Set, &column_type[4..])
+    } else {
+        return Ok(None);
+    };
+    let Some(list) = rest.strip_suffix(')') else {
+        bail!("Malformed column type '{}'", column_type);
+    };
+
+    let mut values = Vec::new();
+    let mut chars = list.chars().peekable();
+    loop {
+        if chars.next() != Some('\'') {
+            bail!("Malformed column type '{}'", column_type);
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                Some('\'') if chars.peek() == Some(&'\'') => {
+                    chars.next();
+                    value.push('\'');
+                }
+                Some('\'') => break,
+                Some(c) => value.push(c),
+                None => bail!("Malformed column type '{}'", column_type),
+            }
+        }
+        values.push(value);
+        match chars.next() {
+            Some(',') => continue,
+            None => break,
+            Some(_) => bail!("Malformed column type '{}'", column_type),
+        }
+    }
+    Ok(Some((kind, values)))
+}
+
+/// ENUM and SET columns of a MySQL table, in ordinal order
+///
+/// # Errors
+///
+/// Returns an error if INFORMATION_SCHEMA cannot be queried or a column type
+/// cannot be parsed.
+pub async fn get_enum_columns(
+    conn: &mut Conn,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<EnumColumn>> {
+    let query = r#"
+        SELECT COLUMN_NAME, COLUMN_TYPE, IS_NULLABLE
+        FROM INFORMATION_SCHEMA.COLUMNS
+        WHERE TABLE_SCHEMA = ?
+        AND TABLE_NAME = ?
+        AND DATA_TYPE IN ('enum', 'set')
+        ORDER BY ORDINAL_POSITION
+    "#;
+    let rows: Vec<(String, String, String)> = conn
+        .exec(query, (db_name, table_name))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to get ENUM/SET columns for table '{}.{}'",
+                db_name, table_name
+            )
+        })?;
+
+    let mut columns = Vec::with_capacity(rows.len());
+    for (name, column_type, is_nullable) in rows {
+        if let Some((kind, values)) = parse_column_type(&column_type)
+            .with_context(|| format!("Column '{}' of table '{}'", name, table_name))?
+        {
+            columns.push(EnumColumn {
+                name,
+                kind,
+                values,
+                nullable: is_nullable.eq_ignore_ascii_case("YES"),
+            });
+        }
+    }
+    Ok(columns)
+}
+
+/// The `_schema` document for one table
+pub fn schema_document(db_name: &str, table_name: &str, columns: &[EnumColumn]) -> JsonValue {
+    let columns: serde_json::Map<String, JsonValue> = columns
+        .iter()
+        .map(|column| {
+            (
+                column.name.clone(),
+                serde_json::json!({
+                    "type": column.kind.as_str(),
+                    "values": column.values,
+                    "nullable": column.nullable,
+                }),
+            )
+        })
+        .collect();
+    serde_json::json!({
+        "database": db_name,
+        "table": table_name,
+        "columns": columns,
+    })
+}
+
+/// Write (or replace) a table's document in [`SCHEMA_TABLE`]
+///
+/// # Errors
+///
+/// Returns an error if the `_schema` table cannot be created or written.
+pub async fn write_schema_document(
+    client: &Client,
+    jsonb: &crate::jsonb::JsonbOptions,
+    table_name: &str,
+    document: JsonValue,
+) -> Result<()> {
+    crate::jsonb::writer::create_jsonb_table(client, jsonb, SCHEMA_TABLE, "mysql")
+        .await
+        .context("Failed to create the _schema table on target")?;
+    client
+        .execute(
+            &format!(
+                r#"INSERT INTO {} (id, data, _source_type) VALUES ($1, $2, 'mysql')
+                   ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data, _migrated_at = NOW(), _synced_at = NOW()"#,
+                crate::jsonb::target_schema::qualified(&jsonb.target_schema, SCHEMA_TABLE)
+            ),
+            &[&table_name, &document],
+        )
+        .await
+        .with_context(|| format!("Failed to record ENUM/SET metadata for '{}'", table_name))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_column_type() {
+        assert_eq!(
+            parse_column_type("enum('new','paid','it''s, done')").unwrap(),
+            Some((
+                EnumKind::Enum,
+                vec![
+                    "new".to_string(),
+                    "paid".to_string(),
+                    "it's, done".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_column_type("set('a','b')").unwrap(),
+            Some((EnumKind::Set, vec!["a".to_string(), "b".to_string()]))
+        );
+        assert_eq!(parse_column_type("enum('')").unwrap().unwrap().1, vec![""]);
+        assert_eq!(parse_column_type("varchar(20)").unwrap(), None);
+        assert!(parse_column_type("enum('a',b)").is_err());
+        assert!(parse_column_type("enum('a'").is_err());
+    }
+
+    #[test]
+    fn test_schema_document() {
+        let columns = vec![EnumColumn {
+            name: "status".to_string(),
+            kind: EnumKind::Enum,
+            values: vec!["new".to_string(), "paid".to_string()],
+            nullable: false,
+        }];
+        let document = schema_document("shop", "orders", &columns);
+        assert_eq!(document["table"], "orders");
+        assert_eq!(document["columns"]["status"]["type"], "enum");
+        assert_eq!(
+            document["columns"]["status"]["values"],
+            serde_json::json!(["new", "paid"])
+        );
+    }
+}
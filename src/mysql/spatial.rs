@@ -0,0 +1,472 @@
+// ABOUTME: MySQL spatial value conversion (internal SRID + WKB format) to WKT and GeoJSON
+// ABOUTME: Also adds PostGIS geometry columns generated from the JSONB documents when available
+
+use anyhow::{bail, Context, Result};
+use mysql_async::{prelude::*, Conn};
+use serde_json::Value as JsonValue;
+use tokio_postgres::Client;
+
+/// INFORMATION_SCHEMA `DATA_TYPE`s of MySQL spatial columns
+pub const SPATIAL_TYPES: &[&str] = &[
+    "geometry",
+    "point",
+    "linestring",
+    "polygon",
+    "multipoint",
+    "multilinestring",
+    "multipolygon",
+    "geometrycollection",
+    "geomcollection",
+];
+
+type Coord = (f64, f64);
+
+/// A decoded MySQL geometry value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Point(Coord),
+    LineString(Vec<Coord>),
+    Polygon(Vec<Vec<Coord>>),
+    MultiPoint(Vec<Coord>),
+    MultiLineString(Vec<Vec<Coord>>),
+    MultiPolygon(Vec<Vec<Vec<Coord>>>),
+    GeometryCollection(Vec<Geometry>),
+}
+
+struct WkbReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WkbReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let Some(slice) = self.bytes.get(self.pos..self.pos + len) else {
+            bail!("geometry value is truncated at byte {}", self.pos);
+        };
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u32(&mut self, little_endian: bool) -> Result<u32> {
+        let bytes: [u8; 4] = self.take(4)?.try_into()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// An element count, checked against the bytes left so corrupt input cannot over-allocate
+    fn count(&mut self, little_endian: bool, min_element_bytes: usize) -> Result<usize> {
+        let count = self.u32(little_endian)? as usize;
+        if count.saturating_mul(min_element_bytes) > self.bytes.len() - self.pos {
+            bail!(
+                "geometry value declares {} elements but is too short",
+                count
+            );
+        }
+        Ok(count)
+    }
+
+    fn coord(&mut self, little_endian: bool) -> Result<Coord> {
+        let mut read = || -> Result<f64> {
+            let bytes: [u8; 8] = self.take(8)?.try_into()?;
+            Ok(if little_endian {
+                f64::from_le_bytes(bytes)
+            } else {
+                f64::from_be_bytes(bytes)
+            })
+        };
+        Ok((read()?, read()?))
+    }
+
+    fn coords(&mut self, little_endian: bool) -> Result<Vec<Coord>> {
+        let count = self.count(little_endian, 16)?;
+        (0..count).map(|_| self.coord(little_endian)).collect()
+    }
+
+    fn rings(&mut self, little_endian: bool) -> Result<Vec<Vec<Coord>>> {
+        let count = self.count(little_endian, 4)?;
+        (0..count).map(|_| self.coords(little_endian)).collect()
+    }
+
+    fn members(&mut self, little_endian: bool, expected: &str) -> Result<Vec<Geometry>> {
+        let count = self.count(little_endian, 5)?;
+        (0..count)
+            .map(|_| self.geometry())
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("invalid {} member", expected))
+    }
+
+    fn geometry(&mut self) -> Result<Geometry> {
+        let little_endian = match self.take(1)?[0] {
+            0 => false,
+            1 => true,
+            other => bail!("invalid WKB byte order {}", other),
+        };
+        let geometry_type = self.u32(little_endian)?;
+        Ok(match geometry_type {
+            1 => Geometry::Point(self.coord(little_endian)?),
+            2 => Geometry::LineString(self.coords(little_endian)?),
+            3 => Geometry::Polygon(self.rings(little_endian)?),
+            4 => Geometry::MultiPoint(
+                self.members(little_endian, "MULTIPOINT")?
+                    .into_iter()
+                    .map(|member| match member {
+                        Geometry::Point(coord) => Ok(coord),
+                        _ => bail!("MULTIPOINT member is not a POINT"),
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            5 => Geometry::MultiLineString(
+                self.members(little_endian, "MULTILINESTRING")?
+                    .into_iter()
+                    .map(|member| match member {
+                        Geometry::LineString(coords) => Ok(coords),
+                        _ => bail!("MULTILINESTRING member is not a LINESTRING"),
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            6 => Geometry::MultiPolygon(
+                self.members(little_endian, "MULTIPOLYGON")?
+                    .into_iter()
+                    .map(|member| match member {
+                        Geometry::Polygon(rings) => Ok(rings),
+                        _ => bail!("MULTIPOLYGON member is not a POLYGON"),
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            7 => Geometry::GeometryCollection(self.members(little_endian, "GEOMETRYCOLLECTION")?),
+            other => bail!("unsupported WKB geometry type {}", other),
+        })
+    }
+}
+
+/// Decode a value in MySQL's internal geometry format: a 4-byte little-endian SRID, then WKB
+///
+/// # Errors
+///
+/// Returns an error if the value is truncated, has trailing bytes, or uses a
+/// geometry type MySQL does not produce.
+pub fn parse_mysql_geometry(bytes: &[u8]) -> Result<(u32, Geometry)> {
+    let mut reader = WkbReader { bytes, pos: 0 };
+    let srid = reader.u32(true)?;
+    let geometry = reader.geometry()?;
+    if reader.pos != bytes.len() {
+        bail!(
+            "geometry value has {} unexpected trailing bytes",
+            bytes.len() - reader.pos
+        );
+    }
+    Ok((srid, geometry))
+}
+
+fn wkt_coords(coords: &[Coord]) -> String {
+    coords
+        .iter()
+        .map(|(x, y)| format!("{} {}", x, y))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn wkt_rings(rings: &[Vec<Coord>]) -> String {
+    rings
+        .iter()
+        .map(|ring| format!("({})", wkt_coords(ring)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn wkt_list(tag: &str, items: Vec<String>) -> String {
+    if items.is_empty() {
+        format!("{} EMPTY", tag)
+    } else {
+        format!("{}({})", tag, items.join(","))
+    }
+}
+
+impl Geometry {
+    /// Well-known text, e.g. `POINT(1 2)`
+    pub fn to_wkt(&self) -> String {
+        match self {
+            Geometry::Point((x, y)) => format!("POINT({} {})", x, y),
+            Geometry::LineString(coords) => wkt_list(
+                "LINESTRING",
+                coords.iter().map(|(x, y)| format!("{} {}", x, y)).collect(),
+            ),
+            Geometry::Polygon(rings) => wkt_list(
+                "POLYGON",
+                rings
+                    .iter()
+                    .map(|ring| format!("({})", wkt_coords(ring)))
+                    .collect(),
+            ),
+            Geometry::MultiPoint(coords) => wkt_list(
+                "MULTIPOINT",
+                coords
+                    .iter()
+                    .map(|(x, y)| format!("({} {})", x, y))
+                    .collect(),
+            ),
+            Geometry::MultiLineString(lines) => wkt_list(
+                "MULTILINESTRING",
+                lines
+                    .iter()
+                    .map(|line| format!("({})", wkt_coords(line)))
+                    .collect(),
+            ),
+            Geometry::MultiPolygon(polygons) => wkt_list(
+                "MULTIPOLYGON",
+                polygons
+                    .iter()
+                    .map(|rings| format!("({})", wkt_rings(rings)))
+                    .collect(),
+            ),
+            Geometry::GeometryCollection(members) => wkt_list(
+                "GEOMETRYCOLLECTION",
+                members.iter().map(Geometry::to_wkt).collect(),
+            ),
+        }
+    }
+
+    /// GeoJSON geometry object
+    pub fn to_geojson(&self) -> JsonValue {
+        fn point(coord: &Coord) -> JsonValue {
+            serde_json::json!([coord.0, coord.1])
+        }
+        fn line(coords: &[Coord]) -> JsonValue {
+            JsonValue::Array(coords.iter().map(point).collect())
+        }
+        fn rings(rings: &[Vec<Coord>]) -> JsonValue {
+            JsonValue::Array(rings.iter().map(|ring| line(ring)).collect())
+        }
+        match self {
+            Geometry::Point(coord) => {
+                serde_json::json!({"type": "Point", "coordinates": point(coord)})
+            }
+            Geometry::LineString(coords) => {
+                serde_json::json!({"type": "LineString", "coordinates": line(coords)})
+            }
+            Geometry::Polygon(polygon) => {
+                serde_json::json!({"type": "Polygon", "coordinates": rings(polygon)})
+            }
+            Geometry::MultiPoint(coords) => {
+                serde_json::json!({"type": "MultiPoint", "coordinates": line(coords)})
+            }
+            Geometry::MultiLineString(lines) => {
+                serde_json::json!({"type": "MultiLineString", "coordinates": rings(lines)})
+            }
+            Geometry::MultiPolygon(polygons) => serde_json::json!({
+                "type": "MultiPolygon",
+                "coordinates": polygons.iter().map(|polygon| rings(polygon)).collect::<Vec<_>>()
+            }),
+            Geometry::GeometryCollection(members) => serde_json::json!({
+                "type": "GeometryCollection",
+                "geometries": members.iter().map(Geometry::to_geojson).collect::<Vec<_>>()
+            }),
+        }
+    }
+}
+
+/// Convert a MySQL geometry value to its JSONB form
+///
+/// Format: `{"_type": "geometry", "srid": 4326, "wkt": "POINT(1 2)", "geojson": {...}}`.
+/// Coordinates keep the order MySQL stored them in.
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be decoded.
+pub fn geometry_to_json(bytes: &[u8]) -> Result<JsonValue> {
+    let (srid, geometry) = parse_mysql_geometry(bytes)?;
+    Ok(serde_json::json!({
+        "_type": "geometry",
+        "srid": srid,
+        "wkt": geometry.to_wkt(),
+        "geojson": geometry.to_geojson(),
+    }))
+}
+
+/// Which columns of a table are spatial, in ordinal order
+///
+/// # Errors
+///
+/// Returns an error if INFORMATION_SCHEMA cannot be queried.
+pub async fn get_spatial_flags(
+    conn: &mut Conn,
+    db_name: &str,
+    table_name: &str,
+) -> Result<Vec<bool>> {
+    let query = r#"
+        SELECT DATA_TYPE
+        FROM INFORMATION_SCHEMA.COLUMNS
+        WHERE TABLE_SCHEMA = ?
+        AND TABLE_NAME = ?
+        ORDER BY ORDINAL_POSITION
+    "#;
+    let data_types: Vec<String> =
+        conn.exec(query, (db_name, table_name))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to get column types for table '{}.{}'",
+                    db_name, table_name
+                )
+            })?;
+    Ok(data_types
+        .iter()
+        .map(|data_type| SPATIAL_TYPES.contains(&data_type.to_ascii_lowercase().as_str()))
+        .collect())
+}
+
+/// `{base}{suffix}`, with `base` shortened to fit in 63 bytes
+fn identifier_with_suffix(base: &str, suffix: &str) -> String {
+    let mut end = base.len().min(63 - suffix.len());
+    while !base.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &base[..end], suffix)
+}
+
+/// Name of the PostGIS column generated for spatial column `column`
+pub fn geometry_column_name(column: &str) -> String {
+    identifier_with_suffix(column, "_geom")
+}
+
+/// Add a generated PostGIS `geometry` column and GiST index per spatial column
+///
+/// Each `{column}_geom` column is computed from the document's GeoJSON and
+/// SRID, so it stays in step with `data`. Does nothing when the target does
+/// not have the `postgis` extension.
+///
+/// # Errors
+///
+/// Returns an error if the columns or indexes cannot be created.
+pub async fn add_postgis_columns(
+    client: &Client,
+    schema: &str,
+    table_name: &str,
+    columns: &[String],
+) -> Result<()> {
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let has_postgis = client
+        .query_opt("SELECT 1 FROM pg_extension WHERE extname = 'postgis'", &[])
+        .await
+        .context("Failed to check for PostGIS on target")?
+        .is_some();
+    if !has_postgis {
+        tracing::info!(
+            "  Target has no PostGIS; spatial columns of '{}' are kept as WKT/GeoJSON in data only",
+            table_name
+        );
+        return Ok(());
+    }
+
+    let table = crate::jsonb::target_schema::qualified(schema, table_name);
+    for column in columns {
+        let geom_column = geometry_column_name(column);
+        let key = crate::utils::quote_literal(column);
+        client
+            .batch_execute(&format!(
+                "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {geom} geometry \
+                 GENERATED ALWAYS AS (ST_SetSRID(ST_GeomFromGeoJSON(data -> {key} -> 'geojson'), \
+                 coalesce((data -> {key} ->> 'srid')::int, 0))) STORED;
+                 CREATE INDEX IF NOT EXISTS {index} ON {table} USING GIST ({geom});",
+                table = table,
+                geom = crate::utils::quote_ident(&geom_column),
+                key = key,
+                index = crate::utils::quote_ident(&identifier_with_suffix(
+                    &format!("{}_{}", table_name, column),
+                    "_geom_idx"
+                )),
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to add PostGIS column '{}' to '{}'",
+                    geom_column, table_name
+                )
+            })?;
+        tracing::info!(
+            "  ✓ Added PostGIS column '{}' to '{}'",
+            geom_column,
+            table_name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_wkb(srid: u32, x: f64, y: f64) -> Vec<u8> {
+        let mut bytes = srid.to_le_bytes().to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&x.to_le_bytes());
+        bytes.extend_from_slice(&y.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_point_to_json() {
+        let json = geometry_to_json(&point_wkb(4326, -73.5, 40.25)).unwrap();
+        assert_eq!(json["_type"], "geometry");
+        assert_eq!(json["srid"], 4326);
+        assert_eq!(json["wkt"], "POINT(-73.5 40.25)");
+        assert_eq!(
+            json["geojson"],
+            serde_json::json!({"type": "Point", "coordinates": [-73.5, 40.25]})
+        );
+    }
+
+    #[test]
+    fn test_multipolygon_and_collection() {
+        // MULTIPOLYGON with one triangle, big-endian
+        let mut wkb = 0u32.to_le_bytes().to_vec();
+        wkb.push(0);
+        wkb.extend_from_slice(&6u32.to_be_bytes());
+        wkb.extend_from_slice(&1u32.to_be_bytes());
+        wkb.push(0);
+        wkb.extend_from_slice(&3u32.to_be_bytes());
+        wkb.extend_from_slice(&1u32.to_be_bytes());
+        wkb.extend_from_slice(&4u32.to_be_bytes());
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.0, 0.0)] {
+            wkb.extend_from_slice(&f64::to_be_bytes(x));
+            wkb.extend_from_slice(&f64::to_be_bytes(y));
+        }
+        let (srid, geometry) = parse_mysql_geometry(&wkb).unwrap();
+        assert_eq!(srid, 0);
+        assert_eq!(geometry.to_wkt(), "MULTIPOLYGON(((0 0,1 0,0 1,0 0)))");
+        assert_eq!(geometry.to_geojson()["coordinates"][0][0][2][1], 1.0);
+
+        assert_eq!(
+            Geometry::GeometryCollection(vec![]).to_wkt(),
+            "GEOMETRYCOLLECTION EMPTY"
+        );
+    }
+
+    #[test]
+    fn test_invalid_geometry() {
+        let wkb = point_wkb(0, 1.0, 2.0);
+        assert!(parse_mysql_geometry(&wkb[..wkb.len() - 1]).is_err());
+        let mut trailing = wkb.clone();
+        trailing.push(0);
+        assert!(parse_mysql_geometry(&trailing).is_err());
+
+        // A huge declared count must not allocate
+        let mut line = 0u32.to_le_bytes().to_vec();
+        line.push(1);
+        line.extend_from_slice(&2u32.to_le_bytes());
+        line.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_mysql_geometry(&line).is_err());
+    }
+
+    #[test]
+    fn test_geometry_column_name() {
+        assert_eq!(geometry_column_name("location"), "location_geom");
+        assert_eq!(geometry_column_name(&"c".repeat(80)).len(), 63);
+    }
+}
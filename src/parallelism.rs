@@ -0,0 +1,228 @@
+// ABOUTME: Budget of one run for parallel work against source and target
+// ABOUTME: Bounds pg_dump/pg_restore jobs, verify workers, and split-table copy workers; shrinks under memory pressure
+
+use crate::memory::MemoryBudget;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Job count used when the number of cores cannot be detected
+const FALLBACK_JOBS: usize = 4;
+
+/// Upper bound for the auto-detected job count
+const AUTO_JOBS_CAP: usize = 8;
+
+/// Concurrent table checks used by verify when the budget allows
+pub const VERIFY_CONCURRENCY: usize = 4;
+
+/// `[parallelism]` section of replication-config.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ParallelismConfig {
+    pub max_total_jobs: Option<usize>,
+    /// Copy tables at least this large (e.g. "200GB") in key ranges; see `migration::split`
+    pub split_tables_larger_than: Option<String>,
+    /// Memory budget (e.g. "2G") for buffered rows and batches; see `crate::memory`
+    pub max_memory: Option<String>,
+}
+
+/// A run's budget for parallel work (`--max-total-jobs`)
+///
+/// Clones share the reserved jobs, so work running alongside pg_dump in one
+/// run is taken out of that run's pg_dump/pg_restore share.
+#[derive(Debug, Clone, Default)]
+pub struct JobBudget {
+    /// Explicit cap (None = auto-detect)
+    max_total_jobs: Option<usize>,
+    /// Jobs currently held by [`JobReservation`]s
+    reserved: Arc<AtomicUsize>,
+}
+
+impl JobBudget {
+    /// A budget of `max_total_jobs`, or the detected core count when `None`
+    pub fn new(max_total_jobs: Option<usize>) -> Self {
+        JobBudget {
+            max_total_jobs,
+            reserved: Arc::default(),
+        }
+    }
+
+    /// Maximum number of jobs any single phase may run at once
+    ///
+    /// Phases run one after another and verify divides the budget among the
+    /// databases it checks at once, so this is also the most concurrent work the
+    /// tool puts on the source or target at any time. Filtered
+    /// table copies and JSONB writers copy one table at a time, so they always fit.
+    /// Without an explicit cap it is the number of CPU cores, capped at 8.
+    pub fn max_total_jobs(&self) -> usize {
+        resolve_jobs(self.max_total_jobs, detected_cores())
+    }
+
+    /// Number of parallel jobs to pass to pg_dump/pg_restore `--jobs`
+    ///
+    /// Jobs reserved for work running alongside pg_dump (split-table copies) are
+    /// subtracted, leaving at least one. Halved under memory pressure.
+    pub fn dump_restore_jobs(&self, memory: &MemoryBudget) -> usize {
+        memory.scale_down(
+            self.max_total_jobs()
+                .saturating_sub(self.reserved.load(Ordering::SeqCst))
+                .max(1),
+        )
+    }
+
+    /// Reserve up to `wanted` jobs for work that runs concurrently with pg_dump
+    ///
+    /// At most half of the budget (and at least one job) is handed out, so pg_dump
+    /// keeps the rest.
+    pub fn reserve_jobs(&self, wanted: usize) -> JobReservation {
+        let jobs = wanted.clamp(1, (self.max_total_jobs() / 2).max(1));
+        self.reserved.fetch_add(jobs, Ordering::SeqCst);
+        JobReservation {
+            jobs,
+            reserved: self.reserved.clone(),
+        }
+    }
+
+    /// Number of tables verify checks concurrently, reduced under memory pressure
+    pub fn verify_concurrency(&self, memory: &MemoryBudget) -> usize {
+        memory.scale_down(self.max_total_jobs().min(VERIFY_CONCURRENCY))
+    }
+
+    /// Number of databases verify checks concurrently
+    ///
+    /// Each database uses [`JobBudget::verify_concurrency`] connection pairs, so
+    /// databases times tables in flight stays within
+    /// [`JobBudget::max_total_jobs`]. At least one.
+    pub fn verify_database_concurrency(&self, memory: &MemoryBudget) -> usize {
+        databases_within_budget(
+            memory.scale_down(self.max_total_jobs()),
+            self.verify_concurrency(memory),
+        )
+    }
+}
+
+/// Jobs set aside from the budget until dropped
+#[derive(Debug)]
+pub struct JobReservation {
+    jobs: usize,
+    reserved: Arc<AtomicUsize>,
+}
+
+impl JobReservation {
+    /// Number of jobs held
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+}
+
+impl Drop for JobReservation {
+    fn drop(&mut self) {
+        self.reserved.fetch_sub(self.jobs, Ordering::SeqCst);
+    }
+}
+
+/// Parse a size such as `500MB`, `200GB`, `2TB`, or `2G` (binary units) into bytes
+///
+/// # Examples
+///
+/// ```
+/// # use seren_replicator::parallelism::parse_size;
+/// assert_eq!(parse_size("2GB").unwrap(), 2 * 1024 * 1024 * 1024);
+/// assert_eq!(parse_size("1.5 TB").unwrap(), 1649267441664);
+/// assert!(parse_size("lots").is_err());
+/// ```
+pub fn parse_size(size: &str) -> Result<i64> {
+    let trimmed = size.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid size '{}'. Use e.g. 500MB, 200GB, or 2TB", size))?;
+    let multiplier: f64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1024.0,
+        "M" | "MB" => 1024.0 * 1024.0,
+        "G" | "GB" => 1024.0 * 1024.0 * 1024.0,
+        "T" | "TB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        other => bail!(
+            "Unknown size unit '{}' in '{}'. Use B, KB, MB, GB, or TB",
+            other,
+            size
+        ),
+    };
+    let bytes = number * multiplier;
+    if bytes < 1.0 {
+        bail!("Size '{}' must be at least 1 byte", size);
+    }
+    Ok(bytes as i64)
+}
+
+fn databases_within_budget(total_jobs: usize, per_database: usize) -> usize {
+    (total_jobs / per_database.max(1)).max(1)
+}
+
+fn detected_cores() -> Option<usize> {
+    std::thread::available_parallelism().ok().map(|n| n.get())
+}
+
+fn resolve_jobs(configured: Option<usize>, cores: Option<usize>) -> usize {
+    match configured {
+        Some(jobs) => jobs.max(1),
+        None => cores.map_or(FALLBACK_JOBS, |n| n.min(AUTO_JOBS_CAP)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_jobs_auto_detect() {
+        assert_eq!(resolve_jobs(None, Some(2)), 2);
+        assert_eq!(resolve_jobs(None, Some(64)), AUTO_JOBS_CAP);
+        assert_eq!(resolve_jobs(None, None), FALLBACK_JOBS);
+    }
+
+    #[test]
+    fn test_resolve_jobs_explicit_cap() {
+        // An explicit cap can go below or above the auto-detected value
+        assert_eq!(resolve_jobs(Some(1), Some(16)), 1);
+        assert_eq!(resolve_jobs(Some(16), Some(4)), 16);
+        assert_eq!(resolve_jobs(Some(0), Some(4)), 1);
+    }
+
+    #[test]
+    fn test_databases_within_budget() {
+        assert_eq!(databases_within_budget(16, 4), 4);
+        assert_eq!(databases_within_budget(8, 3), 2);
+        // A budget smaller than one database's workers still verifies one
+        assert_eq!(databases_within_budget(2, 4), 1);
+        assert_eq!(databases_within_budget(1, 1), 1);
+    }
+
+    #[test]
+    fn test_reservations_are_per_budget() {
+        let budget = JobBudget::new(Some(8));
+        let other = JobBudget::new(Some(8));
+        let memory = MemoryBudget::default();
+        let reservation = budget.reserve_jobs(3);
+        assert_eq!(reservation.jobs(), 3);
+        assert_eq!(budget.clone().dump_restore_jobs(&memory), 5);
+        assert_eq!(other.dump_restore_jobs(&memory), 8);
+        drop(reservation);
+        assert_eq!(budget.dump_restore_jobs(&memory), 8);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("100mb").unwrap(), 100 * 1024 * 1024);
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size(" 2 TB ").unwrap(), 2 * 1024_i64.pow(4));
+        assert!(parse_size("0GB").is_err());
+        assert!(parse_size("10PB").is_err());
+        assert!(parse_size("GB").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 // ABOUTME: Extension compatibility checking for PostgreSQL databases
-// ABOUTME: Validates that target has all required extensions and they are properly configured
+// ABOUTME: Validates target extensions and installs allowlisted ones in dependency order before schema restore
 
+use super::profile::TargetProfile;
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use tokio_postgres::Client;
 
 #[derive(Debug, Clone)]
@@ -94,10 +97,208 @@ pub fn requires_preload(extension_name: &str) -> bool {
     PRELOAD_REQUIRED_EXTENSIONS.contains(&extension_name)
 }
 
+/// `[extensions]` section of replication-config.toml
+///
+/// ```toml
+/// [extensions]
+/// install = ["postgis", "vector"]  # or ["*"] for every source extension
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ExtensionInstallConfig {
+    /// Source extensions init may create on the target before restoring the schema
+    #[serde(default)]
+    pub install: Vec<String>,
+}
+
+impl ExtensionInstallConfig {
+    pub fn allows(&self, name: &str) -> bool {
+        self.install
+            .iter()
+            .any(|entry| entry == "*" || entry == name)
+    }
+}
+
+/// Order extensions so each comes after the extensions it requires
+///
+/// `requires` maps an extension to the extensions its control file lists.
+/// Requirements outside `names` are left to `CREATE EXTENSION ... CASCADE`.
+/// Extensions caught in a cycle keep their input order at the end.
+pub fn install_order(names: &[String], requires: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let wanted: HashSet<&str> = names.iter().map(String::as_str).collect();
+    let mut ordered: Vec<String> = Vec::new();
+    let mut pending: Vec<&String> = names.iter().collect();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|name| {
+            let ready = requires
+                .get(name.as_str())
+                .map(|deps| {
+                    deps.iter()
+                        .filter(|dep| wanted.contains(dep.as_str()))
+                        .all(|dep| ordered.contains(dep))
+                })
+                .unwrap_or(true);
+            if ready {
+                ordered.push((*name).clone());
+            }
+            !ready
+        });
+        if pending.len() == before {
+            ordered.extend(pending.drain(..).cloned());
+        }
+    }
+    ordered
+}
+
+/// Create allowlisted source extensions the target offers but has not installed
+///
+/// Runs before the schema restore so the dump's `CREATE EXTENSION IF NOT EXISTS`
+/// finds them in place. Extensions whose schema does not exist on the target
+/// yet, that the target does not offer, or that the `--target-profile` rules
+/// out, are left to the schema restore. Failures are logged, not returned.
+///
+/// # Returns
+///
+/// The extensions that were created.
+///
+/// # Errors
+///
+/// Returns an error if the catalog queries fail.
+pub async fn install_missing_extensions(
+    source_client: &Client,
+    target_client: &Client,
+    config: &ExtensionInstallConfig,
+    profile: Option<TargetProfile>,
+) -> Result<Vec<String>> {
+    if config.install.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let source_rows = source_client
+        .query(
+            "SELECT e.extname, n.nspname FROM pg_extension e
+             JOIN pg_namespace n ON n.oid = e.extnamespace
+             WHERE e.extname != 'plpgsql' ORDER BY e.extname",
+            &[],
+        )
+        .await
+        .context("Failed to query source extensions")?;
+    let installed: HashSet<String> = get_installed_extensions(target_client)
+        .await?
+        .into_iter()
+        .map(|ext| ext.name)
+        .collect();
+    let available: HashMap<String, Vec<String>> = target_client
+        .query(
+            "SELECT v.name, coalesce(v.requires::text[], '{}')
+             FROM pg_available_extension_versions v
+             JOIN pg_available_extensions a ON a.name = v.name AND a.default_version = v.version",
+            &[],
+        )
+        .await
+        .context("Failed to query available extensions on target")?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+    let schemas: HashSet<String> = target_client
+        .query("SELECT nspname FROM pg_namespace", &[])
+        .await
+        .context("Failed to query target schemas")?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let mut schema_of = HashMap::new();
+    for row in &source_rows {
+        let (name, schema): (String, String) = (row.get(0), row.get(1));
+        if !config.allows(&name) || installed.contains(&name) {
+            continue;
+        }
+        if profile.is_some_and(|profile| !profile.allows_extension(&name)) {
+            continue;
+        }
+        if !available.contains_key(&name) {
+            tracing::warn!("  ⚠ Extension '{}' is not available on the target", name);
+            continue;
+        }
+        if !schemas.contains(&schema) {
+            tracing::info!(
+                "  Extension '{}' lives in schema '{}', which the schema restore creates; leaving it to the restore",
+                name,
+                schema
+            );
+            continue;
+        }
+        schema_of.insert(name, schema);
+    }
+
+    let names: Vec<String> = source_rows
+        .iter()
+        .map(|row| row.get::<_, String>(0))
+        .filter(|name| schema_of.contains_key(name))
+        .collect();
+    let mut created = Vec::new();
+    for name in install_order(&names, &available) {
+        let sql = format!(
+            "CREATE EXTENSION IF NOT EXISTS {} WITH SCHEMA {} CASCADE",
+            crate::utils::quote_ident(&name),
+            crate::utils::quote_ident(&schema_of[&name])
+        );
+        match target_client.batch_execute(&sql).await {
+            Ok(()) => created.push(name),
+            Err(e) => tracing::warn!("  ⚠ Could not create extension '{}': {}", name, e),
+        }
+    }
+    Ok(created)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_install_order_puts_requirements_first() {
+        let names: Vec<String> = ["earthdistance", "postgis_topology", "cube", "postgis"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let requires = HashMap::from([
+            ("earthdistance".to_string(), vec!["cube".to_string()]),
+            ("postgis_topology".to_string(), vec!["postgis".to_string()]),
+            ("cube".to_string(), vec![]),
+            ("postgis".to_string(), vec![]),
+        ]);
+        assert_eq!(
+            install_order(&names, &requires),
+            vec!["cube", "postgis", "earthdistance", "postgis_topology"]
+        );
+    }
+
+    #[test]
+    fn test_install_order_ignores_unlisted_requirements_and_cycles() {
+        let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let requires = HashMap::from([
+            ("a".to_string(), vec!["b".to_string()]),
+            ("b".to_string(), vec!["a".to_string()]),
+            ("c".to_string(), vec!["plpython3u".to_string()]),
+        ]);
+        assert_eq!(install_order(&names, &requires), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_install_config_allows() {
+        let config = ExtensionInstallConfig {
+            install: vec!["postgis".to_string()],
+        };
+        assert!(config.allows("postgis"));
+        assert!(!config.allows("vector"));
+        assert!(ExtensionInstallConfig {
+            install: vec!["*".to_string()],
+        }
+        .allows("vector"));
+    }
+
     #[test]
     fn test_requires_preload() {
         assert!(requires_preload("timescaledb"));
@@ -0,0 +1,317 @@
+// ABOUTME: Detects managed PostgreSQL flavors (AWS RDS, Aurora) and applies their replication rules
+// ABOUTME: Checks rds.logical_replication and rds_replication membership, and strips RDS-internal roles from globals
+
+use crate::errors::ReplicatorError;
+use anyhow::{Context, Result};
+use std::path::Path;
+use tokio_postgres::Client;
+
+/// Kind of PostgreSQL server, as far as replication setup is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerFlavor {
+    /// Self-hosted or a provider without special rules
+    Generic,
+    /// Amazon RDS for PostgreSQL
+    Rds,
+    /// Amazon Aurora PostgreSQL-Compatible Edition
+    Aurora,
+}
+
+impl ServerFlavor {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ServerFlavor::Generic => "PostgreSQL",
+            ServerFlavor::Rds => "AWS RDS for PostgreSQL",
+            ServerFlavor::Aurora => "AWS Aurora PostgreSQL",
+        }
+    }
+
+    /// RDS and Aurora share the rds_* roles and parameter-group settings
+    pub fn is_rds(&self) -> bool {
+        matches!(self, ServerFlavor::Rds | ServerFlavor::Aurora)
+    }
+}
+
+/// Identify the server from the roles and functions RDS and Aurora install
+///
+/// # Errors
+///
+/// Returns an error if the catalog query fails.
+pub async fn detect_flavor(client: &Client) -> Result<ServerFlavor> {
+    let row = client
+        .query_one(
+            "SELECT
+                EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'rds_superuser'),
+                EXISTS (SELECT 1 FROM pg_proc WHERE proname = 'aurora_version')",
+            &[],
+        )
+        .await
+        .context("Failed to detect server flavor")?;
+    let (rds_roles, aurora): (bool, bool) = (row.get(0), row.get(1));
+    Ok(match (rds_roles, aurora) {
+        (_, true) => ServerFlavor::Aurora,
+        (true, false) => ServerFlavor::Rds,
+        _ => ServerFlavor::Generic,
+    })
+}
+
+/// Replication readiness of an RDS or Aurora source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdsReplicationCheck {
+    pub user: String,
+    /// `rds.logical_replication` is on in the parameter group
+    pub logical_replication: bool,
+    /// The user is a member of `rds_replication` (directly or through another role)
+    pub has_rds_replication: bool,
+    /// The user can grant `rds_replication`, e.g. the master user via `rds_superuser`
+    pub can_grant_rds_replication: bool,
+}
+
+/// Read the RDS settings and role memberships that logical replication needs
+///
+/// # Errors
+///
+/// Returns an error if the queries fail.
+pub async fn check_rds_replication(client: &Client) -> Result<RdsReplicationCheck> {
+    let row = client
+        .query_one(
+            "SELECT
+                current_user::text,
+                coalesce(current_setting('rds.logical_replication', true), 'off'),
+                pg_has_role(current_user, 'rds_replication', 'MEMBER'),
+                pg_has_role(current_user, 'rds_superuser', 'MEMBER')",
+            &[],
+        )
+        .await
+        .context("Failed to read RDS replication settings")?;
+    let setting: String = row.get(1);
+    Ok(RdsReplicationCheck {
+        user: row.get(0),
+        logical_replication: matches!(setting.to_ascii_lowercase().as_str(), "on" | "1"),
+        has_rds_replication: row.get(2),
+        can_grant_rds_replication: row.get(3),
+    })
+}
+
+impl RdsReplicationCheck {
+    /// Fail with provider-specific guidance if replication cannot work
+    ///
+    /// A missing `rds_replication` grant is tolerated when `allow_grant` is set
+    /// and the user could grant it to itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SR-0026` if `rds.logical_replication` is off, or `SR-0027` if
+    /// the user lacks `rds_replication`.
+    pub fn require(&self, allow_grant: bool) -> Result<()> {
+        if !self.logical_replication {
+            return Err(ReplicatorError::RdsLogicalReplicationDisabled.into());
+        }
+        let can_replicate =
+            self.has_rds_replication || (allow_grant && self.can_grant_rds_replication);
+        if !can_replicate {
+            return Err(ReplicatorError::RdsReplicationRoleMissing {
+                user: self.user.clone(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Make sure the connected RDS user can replicate, granting `rds_replication` if possible
+///
+/// Does nothing on other flavors.
+///
+/// # Errors
+///
+/// Returns an error if the checks fail or the grant is refused.
+pub async fn ensure_rds_replication(client: &Client) -> Result<ServerFlavor> {
+    let flavor = detect_flavor(client).await?;
+    if !flavor.is_rds() {
+        return Ok(flavor);
+    }
+    let check = check_rds_replication(client).await?;
+    check.require(true)?;
+    if !check.has_rds_replication {
+        client
+            .batch_execute(&format!(
+                "GRANT rds_replication TO {}",
+                crate::utils::quote_ident(&check.user)
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to grant rds_replication to '{}'. Run as the master user: GRANT rds_replication TO {};",
+                    check.user, check.user
+                )
+            })?;
+        tracing::info!("✓ Granted rds_replication to '{}'", check.user);
+    }
+    Ok(flavor)
+}
+
+/// Roles RDS creates for its own use; they do not exist (and must not be created) elsewhere
+pub fn is_rds_internal_role(role: &str) -> bool {
+    let role = role.trim_matches('"');
+    role.starts_with("rds_")
+        || matches!(
+            role,
+            "rdsadmin" | "rdsrepladmin" | "rdstopmgr" | "rdsproxyadmin"
+        )
+}
+
+/// Remove RDS-internal roles from a `pg_dumpall --globals-only` script
+///
+/// See [`strip_internal_roles`]. Returns how many statements were removed.
+pub fn strip_rds_internal_roles(script: &str) -> (String, usize) {
+    strip_internal_roles(script, is_rds_internal_role)
+}
+
+/// Remove provider-internal roles from a `pg_dumpall --globals-only` script
+///
+/// Drops `CREATE ROLE`/`ALTER ROLE`/`COMMENT ON ROLE` for roles matching
+/// `is_internal` and grants of or to them, and strips `GRANTED BY <internal>`
+/// from the rest so the grants restore on a target without those roles.
+/// Returns how many statements were removed.
+pub fn strip_internal_roles(script: &str, is_internal: impl Fn(&str) -> bool) -> (String, usize) {
+    let mut removed = 0;
+    let mut kept = Vec::new();
+    for line in script.lines() {
+        let words: Vec<&str> = line
+            .trim_end_matches(';')
+            .split_whitespace()
+            .map(|word| word.trim_end_matches(';'))
+            .collect();
+        let role_statement = match words.as_slice() {
+            ["CREATE", "ROLE", role, ..] | ["ALTER", "ROLE", role, ..] => is_internal(role),
+            ["COMMENT", "ON", "ROLE", role, ..] => is_internal(role),
+            ["GRANT", rest @ ..] => {
+                let to = rest.iter().position(|word| *word == "TO");
+                let by = rest.iter().position(|word| *word == "GRANTED");
+                let granted = &rest[..to.unwrap_or(rest.len())];
+                let grantees =
+                    &rest[to.map(|i| i + 1).unwrap_or(rest.len())..by.unwrap_or(rest.len())];
+                granted
+                    .iter()
+                    .chain(grantees)
+                    .any(|role| is_internal(role.trim_end_matches(',')))
+            }
+            _ => false,
+        };
+        if role_statement {
+            removed += 1;
+            continue;
+        }
+        match line.find(" GRANTED BY ") {
+            Some(at) if is_internal(line[at + 12..].trim_end_matches(';').trim()) => {
+                kept.push(format!("{};", &line[..at]));
+            }
+            _ => kept.push(line.to_string()),
+        }
+    }
+    let mut cleaned = kept.join("\n");
+    if script.ends_with('\n') {
+        cleaned.push('\n');
+    }
+    (cleaned, removed)
+}
+
+/// Apply [`strip_rds_internal_roles`] to a globals file in place
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or written.
+pub fn strip_rds_globals_file(path: &Path) -> Result<()> {
+    let script = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read globals dump {}", path.display()))?;
+    let (cleaned, removed) = strip_rds_internal_roles(&script);
+    std::fs::write(path, cleaned)
+        .with_context(|| format!("Failed to write globals dump {}", path.display()))?;
+    if removed > 0 {
+        tracing::info!(
+            "✓ Skipped {} statement(s) for RDS-internal roles (rdsadmin, rds_*)",
+            removed
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_rds_internal_role() {
+        assert!(is_rds_internal_role("rdsadmin"));
+        assert!(is_rds_internal_role("rds_superuser"));
+        assert!(is_rds_internal_role("\"rds_replication\""));
+        assert!(!is_rds_internal_role("app"));
+        assert!(!is_rds_internal_role("rdsuser_app"));
+    }
+
+    #[test]
+    fn test_strip_rds_internal_roles() {
+        let script = "\
+CREATE ROLE rdsadmin;
+ALTER ROLE rdsadmin WITH SUPERUSER INHERIT CREATEROLE CREATEDB LOGIN REPLICATION BYPASSRLS;
+CREATE ROLE app;
+ALTER ROLE app WITH NOSUPERUSER INHERIT NOCREATEROLE NOCREATEDB LOGIN NOREPLICATION NOBYPASSRLS;
+CREATE ROLE rds_superuser;
+GRANT rds_superuser TO app GRANTED BY rdsadmin;
+GRANT readers TO app GRANTED BY rdsadmin;
+GRANT readers TO reporting GRANTED BY app;
+";
+        let (cleaned, removed) = strip_rds_internal_roles(script);
+        assert_eq!(removed, 4);
+        assert_eq!(
+            cleaned,
+            "\
+CREATE ROLE app;
+ALTER ROLE app WITH NOSUPERUSER INHERIT NOCREATEROLE NOCREATEDB LOGIN NOREPLICATION NOBYPASSRLS;
+GRANT readers TO app;
+GRANT readers TO reporting GRANTED BY app;
+"
+        );
+    }
+
+    #[test]
+    fn test_rds_check_requires_parameter_and_role() {
+        let ready = RdsReplicationCheck {
+            user: "app".to_string(),
+            logical_replication: true,
+            has_rds_replication: true,
+            can_grant_rds_replication: false,
+        };
+        assert!(ready.require(false).is_ok());
+
+        let disabled = RdsReplicationCheck {
+            logical_replication: false,
+            ..ready.clone()
+        };
+        assert!(matches!(
+            crate::errors::find(&disabled.require(true).unwrap_err()),
+            Some(ReplicatorError::RdsLogicalReplicationDisabled)
+        ));
+
+        let grantable = RdsReplicationCheck {
+            has_rds_replication: false,
+            can_grant_rds_replication: true,
+            ..ready.clone()
+        };
+        assert!(grantable.require(true).is_ok());
+        assert!(matches!(
+            crate::errors::find(&grantable.require(false).unwrap_err()),
+            Some(ReplicatorError::RdsReplicationRoleMissing { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_detect_flavor() {
+        let url = std::env::var("TEST_SOURCE_URL").unwrap();
+        let client = crate::postgres::connect(&url).await.unwrap();
+        let flavor = detect_flavor(&client).await.unwrap();
+        println!("Detected {}", flavor.label());
+    }
+}
@@ -1,15 +1,27 @@
 // ABOUTME: PostgreSQL utilities module
-// ABOUTME: Exports connection management and common database operations
+// ABOUTME: Exports connection management, pooler routing, RDS/Aurora and Neon support, and common database operations
 
+pub mod activity;
 pub mod connection;
 pub mod extensions;
+pub mod flavor;
+pub mod neon;
+pub mod pooler;
 pub mod privileges;
+pub mod profile;
+pub mod source_selection;
+pub mod tuning;
 
+pub use activity::{
+    get_bloated_tables, get_long_running_transactions, get_prepared_transactions, BloatedTable,
+    LongTransaction, PreparedTransaction,
+};
 pub use connection::{add_keepalive_params, connect, connect_with_retry};
 pub use extensions::{
     get_available_extensions, get_installed_extensions, get_preloaded_libraries, requires_preload,
     AvailableExtension, Extension,
 };
 pub use privileges::{
-    check_source_privileges, check_target_privileges, check_wal_level, PrivilegeCheck,
+    check_source_privileges, check_target_privileges, check_wal_level, get_replication_settings,
+    PrivilegeCheck, ReplicationSettings,
 };
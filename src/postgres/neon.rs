@@ -0,0 +1,432 @@
+// ABOUTME: Target adapter for Neon serverless endpoints: compute heartbeat, endpoint/branch IDs, backup branches
+// ABOUTME: Keeps the compute awake through long restores and can branch the target before --drop-existing
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Environment variable holding a Neon API key, needed for branch IDs and backup branches
+pub const API_KEY_ENV: &str = "NEON_API_KEY";
+
+/// Environment variable holding the Neon project ID of the target
+pub const PROJECT_ID_ENV: &str = "NEON_PROJECT_ID";
+
+const DEFAULT_API_BASE_URL: &str = "https://console.neon.tech/api/v2";
+
+/// Neon suspends idle computes after 5 minutes by default
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `[neon]` section of replication-config.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NeonConfig {
+    /// Seconds between heartbeat queries during init; 0 disables the heartbeat
+    pub heartbeat_secs: Option<u64>,
+    /// Branch the target before `--drop-existing` drops anything
+    pub backup_branch_before_drop: Option<bool>,
+    /// Project containing the target endpoint (or set `NEON_PROJECT_ID`)
+    pub project_id: Option<String>,
+}
+
+/// Neon behavior of a run, from `[neon]`, the environment, and CLI flags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NeonSettings {
+    pub heartbeat_interval: Option<Duration>,
+    pub backup_branch_before_drop: bool,
+    pub project_id: Option<String>,
+    pub api_key: Option<String>,
+    pub api_base_url: String,
+}
+
+impl Default for NeonSettings {
+    fn default() -> Self {
+        NeonSettings {
+            heartbeat_interval: Some(DEFAULT_HEARTBEAT_INTERVAL),
+            backup_branch_before_drop: false,
+            project_id: None,
+            api_key: None,
+            api_base_url: DEFAULT_API_BASE_URL.to_string(),
+        }
+    }
+}
+
+impl NeonSettings {
+    /// Merge the config section with `NEON_API_KEY` and `NEON_PROJECT_ID`
+    ///
+    /// The environment wins over the config file for the project ID.
+    pub fn resolve(
+        config: Option<NeonConfig>,
+        api_key: Option<String>,
+        project_id: Option<String>,
+    ) -> Self {
+        let config = config.unwrap_or_default();
+        let defaults = NeonSettings::default();
+        NeonSettings {
+            heartbeat_interval: match config.heartbeat_secs {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => defaults.heartbeat_interval,
+            },
+            backup_branch_before_drop: config.backup_branch_before_drop.unwrap_or(false),
+            project_id: project_id.filter(|id| !id.is_empty()).or(config.project_id),
+            api_key: api_key.filter(|key| !key.is_empty()),
+            api_base_url: defaults.api_base_url,
+        }
+    }
+}
+
+/// A Neon compute endpoint recognized from its host name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NeonEndpoint {
+    /// Endpoint ID, e.g. `ep-cool-darkness-123456`
+    pub endpoint_id: String,
+    /// Whether the URL goes through Neon's connection pooler
+    pub pooled: bool,
+}
+
+/// Recognize `ep-<name>[-pooler].<region>.neon.tech` hosts
+pub fn parse_endpoint(url: &str) -> Option<NeonEndpoint> {
+    let parts = crate::utils::parse_postgres_url(url).ok()?;
+    let host = parts.host.to_ascii_lowercase();
+    if !host.ends_with(".neon.tech") {
+        return None;
+    }
+    let label = host.split('.').next()?;
+    if !label.starts_with("ep-") {
+        return None;
+    }
+    let (endpoint_id, pooled) = match label.strip_suffix("-pooler") {
+        Some(id) => (id, true),
+        None => (label, false),
+    };
+    Some(NeonEndpoint {
+        endpoint_id: endpoint_id.to_string(),
+        pooled,
+    })
+}
+
+/// Runs `SELECT 1` on the target until dropped, so the compute is not suspended
+///
+/// Neon suspends a compute with no activity, which can happen between
+/// pg_restore runs or while a large source table is being dumped. A
+/// suspended compute cold-starts on the next connection and drops open
+/// sessions, so the heartbeat keeps one connection busy at a low rate.
+#[must_use = "the heartbeat stops when dropped"]
+pub struct Heartbeat {
+    task: JoinHandle<()>,
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Start a heartbeat if the target is a Neon endpoint and heartbeats are enabled
+pub fn start_heartbeat(target_url: &str, settings: &NeonSettings) -> Option<Heartbeat> {
+    let endpoint = parse_endpoint(target_url)?;
+    let interval = settings.heartbeat_interval?;
+    tracing::info!(
+        "Target is Neon endpoint {}; sending a heartbeat every {}s to prevent autosuspend",
+        endpoint.endpoint_id,
+        interval.as_secs()
+    );
+    let url = target_url.to_string();
+    let task = tokio::spawn(async move {
+        let mut client = None;
+        loop {
+            tokio::time::sleep(interval).await;
+            if client.is_none() {
+                client = super::connect(&url).await.ok();
+            }
+            let Some(connected) = &client else {
+                tracing::debug!("Neon heartbeat could not connect; retrying next interval");
+                continue;
+            };
+            if let Err(e) = connected.simple_query("SELECT 1").await {
+                tracing::debug!("Neon heartbeat failed: {}", e);
+                client = None;
+            }
+        }
+    });
+    Some(Heartbeat { task })
+}
+
+#[derive(Deserialize)]
+struct EndpointResponse {
+    endpoint: EndpointInfo,
+}
+
+#[derive(Deserialize)]
+struct EndpointInfo {
+    branch_id: String,
+}
+
+#[derive(Deserialize)]
+struct BranchResponse {
+    branch: BranchInfo,
+}
+
+#[derive(Deserialize)]
+struct BranchInfo {
+    id: String,
+}
+
+/// Minimal client for the Neon management API
+pub struct NeonApi {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    project_id: String,
+}
+
+impl NeonApi {
+    /// Client for the configured project, or `None` without an API key and project ID
+    pub fn from_settings(settings: &NeonSettings) -> Result<Option<Self>> {
+        let (Some(api_key), Some(project_id)) = (&settings.api_key, &settings.project_id) else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .timeout(API_TIMEOUT)
+            .build()
+            .context("Failed to create Neon API client")?;
+        Ok(Some(NeonApi {
+            client,
+            base_url: settings.api_base_url.clone(),
+            api_key: api_key.clone(),
+            project_id: project_id.clone(),
+        }))
+    }
+
+    /// Branch an endpoint serves
+    pub async fn endpoint_branch(&self, endpoint_id: &str) -> Result<String> {
+        let url = format!(
+            "{}/projects/{}/endpoints/{}",
+            self.base_url, self.project_id, endpoint_id
+        );
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.api_key)
+            .send()
+            .await
+            .context("Failed to reach the Neon API")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!(
+                "Neon API returned {} for endpoint {}: {}. Check {} and {}",
+                status,
+                endpoint_id,
+                body,
+                API_KEY_ENV,
+                PROJECT_ID_ENV
+            );
+        }
+        let endpoint: EndpointResponse = response
+            .json()
+            .await
+            .context("Failed to parse Neon endpoint response")?;
+        Ok(endpoint.endpoint.branch_id)
+    }
+
+    /// Create a branch of `parent_id` at its current state and return its ID
+    pub async fn create_branch(&self, parent_id: &str, name: &str) -> Result<String> {
+        let url = format!("{}/projects/{}/branches", self.base_url, self.project_id);
+        let body = serde_json::json!({ "branch": { "parent_id": parent_id, "name": name } });
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach the Neon API")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!(
+                "Neon API returned {} creating branch '{}': {}",
+                status,
+                name,
+                body
+            );
+        }
+        let branch: BranchResponse = response
+            .json()
+            .await
+            .context("Failed to parse Neon branch response")?;
+        Ok(branch.branch.id)
+    }
+}
+
+/// One-line description of a Neon target for status output, e.g.
+/// `Neon endpoint ep-cool-123456 (pooled), branch br-wispy-789`
+///
+/// The branch is shown when `NEON_API_KEY` and a project ID are configured.
+pub async fn describe_target(target_url: &str, settings: &NeonSettings) -> Option<String> {
+    let endpoint = parse_endpoint(target_url)?;
+    let mut description = format!("Neon endpoint {}", endpoint.endpoint_id);
+    if endpoint.pooled {
+        description.push_str(" (pooled)");
+    }
+    match NeonApi::from_settings(settings) {
+        Ok(Some(api)) => match api.endpoint_branch(&endpoint.endpoint_id).await {
+            Ok(branch) => description.push_str(&format!(", branch {}", branch)),
+            Err(e) => tracing::debug!("Could not look up Neon branch: {}", e),
+        },
+        Ok(None) => description.push_str(&format!(
+            " (set {} and {} to show its branch)",
+            API_KEY_ENV, PROJECT_ID_ENV
+        )),
+        Err(e) => tracing::debug!("Could not create Neon API client: {}", e),
+    }
+    Some(description)
+}
+
+/// Branch the target's current state before a destructive run, if enabled
+///
+/// Returns the new branch ID, or `None` when backup branches are disabled.
+///
+/// # Errors
+///
+/// Returns an error if backup branches are enabled but the target is not a
+/// Neon endpoint, the API key or project ID is missing, or the API call fails.
+/// Nothing has been dropped at that point.
+pub async fn backup_branch_before_drop(
+    target_url: &str,
+    settings: &NeonSettings,
+) -> Result<Option<String>> {
+    if !settings.backup_branch_before_drop {
+        return Ok(None);
+    }
+    let Some(endpoint) = parse_endpoint(target_url) else {
+        bail!("--neon-backup-branch requires a Neon target (an ep-*.neon.tech host)");
+    };
+    let Some(api) = NeonApi::from_settings(settings)? else {
+        bail!(
+            "--neon-backup-branch needs {} and {} (or [neon] project_id) to create the branch",
+            API_KEY_ENV,
+            PROJECT_ID_ENV
+        );
+    };
+
+    let parent = api.endpoint_branch(&endpoint.endpoint_id).await?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let name = format!("seren-before-drop-{}", stamp);
+    let branch = api.create_branch(&parent, &name).await?;
+    tracing::info!(
+        "✓ Created Neon branch '{}' ({}) from {} before dropping existing databases",
+        name,
+        branch,
+        parent
+    );
+    Ok(Some(branch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            parse_endpoint("postgresql://u:p@ep-cool-darkness-123456.us-east-2.aws.neon.tech/db"),
+            Some(NeonEndpoint {
+                endpoint_id: "ep-cool-darkness-123456".to_string(),
+                pooled: false,
+            })
+        );
+        assert_eq!(
+            parse_endpoint(
+                "postgresql://u:p@ep-cool-darkness-123456-pooler.us-east-2.aws.neon.tech/db"
+            ),
+            Some(NeonEndpoint {
+                endpoint_id: "ep-cool-darkness-123456".to_string(),
+                pooled: true,
+            })
+        );
+        assert_eq!(parse_endpoint("postgresql://u:p@db.example.com/db"), None);
+        assert_eq!(parse_endpoint("postgresql://u:p@neon.tech/db"), None);
+    }
+
+    #[test]
+    fn test_resolve_settings() {
+        let settings = NeonSettings::resolve(None, None, None);
+        assert_eq!(settings, NeonSettings::default());
+
+        let settings = NeonSettings::resolve(
+            Some(NeonConfig {
+                heartbeat_secs: Some(0),
+                backup_branch_before_drop: Some(true),
+                project_id: Some("from-config".to_string()),
+            }),
+            Some("key".to_string()),
+            Some("from-env".to_string()),
+        );
+        assert_eq!(settings.heartbeat_interval, None);
+        assert!(settings.backup_branch_before_drop);
+        assert_eq!(settings.project_id.as_deref(), Some("from-env"));
+        assert_eq!(settings.api_key.as_deref(), Some("key"));
+    }
+
+    /// Serve canned HTTP responses, one per connection, in order
+    async fn serve(responses: Vec<String>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn json_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn test_api_reads_branch_and_creates_backup() {
+        let base = serve(vec![
+            json_response(r#"{"endpoint":{"id":"ep-a","branch_id":"br-main"}}"#),
+            json_response(r#"{"branch":{"id":"br-backup","name":"b"},"operations":[]}"#),
+        ])
+        .await;
+        let settings = NeonSettings {
+            api_key: Some("key".to_string()),
+            project_id: Some("proj".to_string()),
+            api_base_url: base,
+            ..NeonSettings::default()
+        };
+        let api = NeonApi::from_settings(&settings).unwrap().unwrap();
+        assert_eq!(api.endpoint_branch("ep-a").await.unwrap(), "br-main");
+        assert_eq!(
+            api.create_branch("br-main", "backup").await.unwrap(),
+            "br-backup"
+        );
+    }
+
+    #[test]
+    fn test_api_requires_key_and_project() {
+        let settings = NeonSettings {
+            api_key: Some("key".to_string()),
+            ..NeonSettings::default()
+        };
+        assert!(NeonApi::from_settings(&settings).unwrap().is_none());
+    }
+}
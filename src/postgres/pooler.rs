@@ -0,0 +1,345 @@
+// ABOUTME: Detects connection poolers (PgBouncer, Supavisor, Neon's pooler) in front of the target
+// ABOUTME: Routes session-required operations (pg_restore, COPY, subscriptions) to a direct connection
+
+use crate::errors::ReplicatorError;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Default ports of PgBouncer (6432) and Supabase's Supavisor transaction pooler (6543)
+const POOLER_PORTS: &[u16] = &[6432, 6543];
+
+/// Work done against the target, grouped by whether it needs a dedicated session
+///
+/// Transaction-mode poolers hand each transaction to whichever server
+/// connection is free, so session state (`SET`, temp tables, advisory locks,
+/// prepared statements on PgBouncer before 1.21) does not survive between
+/// statements, and `COPY` and `CREATE SUBSCRIPTION` fail or hang.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetOperation {
+    /// pg_restore of globals, schema, or data
+    Restore,
+    /// COPY streams and batched inserts from init and JSONB sources
+    Copy,
+    /// CREATE/ALTER/DROP SUBSCRIPTION from sync
+    Subscription,
+    /// Read-mostly checks: verify, status, readiness
+    Query,
+}
+
+impl TargetOperation {
+    /// Whether this operation cannot run through a transaction-mode pooler
+    pub fn requires_session(&self) -> bool {
+        !matches!(self, TargetOperation::Query)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetOperation::Restore => "pg_restore",
+            TargetOperation::Copy => "COPY",
+            TargetOperation::Subscription => "subscription management",
+            TargetOperation::Query => "verification queries",
+        }
+    }
+}
+
+/// Pooler flags from the CLI (`--target-pooler`, `--target-direct-port`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolerSettings {
+    /// The target URL is known to go through a pooler
+    pub declared: bool,
+    /// Port on the target host that reaches PostgreSQL directly
+    pub direct_port: Option<u16>,
+}
+
+/// URLs known to reach PostgreSQL directly, so routing the same target twice
+/// neither probes again nor rewrites a URL that was already rewritten
+static DIRECT_URLS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Why the target is treated as pooled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolerEvidence {
+    /// `--target-pooler` was passed
+    Declared,
+    /// The URL uses a well-known pooler port
+    PoolerPort(u16),
+    /// The host name marks a pooler endpoint, e.g. Neon's `-pooler` hosts
+    PoolerHost(String),
+    /// Consecutive statements ran on different server backends
+    BackendChanged,
+}
+
+impl fmt::Display for PoolerEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolerEvidence::Declared => write!(f, "declared with --target-pooler"),
+            PoolerEvidence::PoolerPort(port) => write!(f, "port {} is a pooler port", port),
+            PoolerEvidence::PoolerHost(host) => write!(f, "host {} is a pooler endpoint", host),
+            PoolerEvidence::BackendChanged => {
+                write!(f, "consecutive statements ran on different server backends")
+            }
+        }
+    }
+}
+
+/// Pooler evidence visible in the URL and flags alone, without connecting
+///
+/// A URL already on the configured direct port is never treated as pooled.
+///
+/// # Errors
+///
+/// Returns an error if the URL cannot be parsed.
+pub fn url_evidence(url: &str, settings: &PoolerSettings) -> Result<Option<PoolerEvidence>> {
+    let parts = crate::utils::parse_postgres_url(url)?;
+    if settings.direct_port == Some(parts.port) {
+        return Ok(None);
+    }
+    if settings.declared {
+        return Ok(Some(PoolerEvidence::Declared));
+    }
+    if POOLER_PORTS.contains(&parts.port) {
+        return Ok(Some(PoolerEvidence::PoolerPort(parts.port)));
+    }
+    if parts.host.contains("-pooler.") || parts.host.starts_with("pooler.") {
+        return Ok(Some(PoolerEvidence::PoolerHost(parts.host)));
+    }
+    Ok(None)
+}
+
+/// Connect and check whether consecutive statements share a backend
+///
+/// Uses the simple query protocol, which works through any pooler mode. A
+/// single client is often handed the same server connection, so a clean
+/// probe does not rule a pooler out; pass `--target-pooler` when unsure.
+/// Returns `None` if the connection fails, leaving the caller to report it.
+pub async fn probe(url: &str) -> Option<PoolerEvidence> {
+    let client = match super::connect(url).await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::debug!("Skipping pooler probe: {}", e);
+            return None;
+        }
+    };
+    let mut pids = HashSet::new();
+    for _ in 0..3 {
+        let messages = client.simple_query("SELECT pg_backend_pid()").await.ok()?;
+        let pid = messages.iter().find_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })?;
+        pids.insert(pid);
+    }
+    (pids.len() > 1).then_some(PoolerEvidence::BackendChanged)
+}
+
+/// URL that bypasses the pooler: the direct port, or Neon's host without `-pooler`
+fn direct_url(url: &str, settings: &PoolerSettings) -> Result<Option<String>> {
+    let parts = crate::utils::parse_postgres_url(url)?;
+    let authority = if let Some(port) = settings.direct_port {
+        format!("{}:{}", parts.host, port)
+    } else if parts.host.contains("-pooler.") {
+        format!("{}:{}", parts.host.replacen("-pooler.", ".", 1), parts.port)
+    } else {
+        return Ok(None);
+    };
+
+    // Host and port sit between the userinfo and the database path
+    let Some(scheme_end) = url.find("://").map(|i| i + 3) else {
+        return Ok(None);
+    };
+    let rest = &url[scheme_end..];
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let start = rest[..end].rfind('@').map(|i| i + 1).unwrap_or(0);
+    Ok(Some(format!(
+        "{}{}{}",
+        &url[..scheme_end + start],
+        authority,
+        &rest[end..]
+    )))
+}
+
+/// Target URL to use for `operation`
+///
+/// When the target is behind a pooler, session-required operations are
+/// routed to the direct port (`--target-direct-port`) or, for Neon, the
+/// non-pooler host. Query operations use the direct URL when one is known
+/// and otherwise run through the pooler with a warning.
+///
+/// # Errors
+///
+/// Returns [`ReplicatorError::PoolerTarget`] (`SR-0025`) if a session-required
+/// operation targets a pooler and no direct connection is configured.
+pub async fn route_target(
+    url: &str,
+    operation: TargetOperation,
+    settings: &PoolerSettings,
+) -> Result<String> {
+    if is_routed(url) || crate::detect_source_type(url).ok() != Some(crate::SourceType::PostgreSQL)
+    {
+        return Ok(url.to_string());
+    }
+    let evidence = match url_evidence(url, settings)? {
+        Some(evidence) => Some(evidence),
+        None => probe(url).await,
+    };
+    let Some(evidence) = evidence else {
+        mark_routed(url);
+        return Ok(url.to_string());
+    };
+
+    match direct_url(url, settings)? {
+        Some(direct) => {
+            tracing::info!(
+                "✓ Target is behind a connection pooler ({}); running {} on a direct connection",
+                evidence,
+                operation.as_str()
+            );
+            mark_routed(&direct);
+            Ok(direct)
+        }
+        None if !operation.requires_session() => {
+            tracing::warn!(
+                "⚠ Target is behind a connection pooler ({}); running {} through it. \
+                 Pass --target-direct-port if queries fail on prepared statements",
+                evidence,
+                operation.as_str()
+            );
+            Ok(url.to_string())
+        }
+        None => Err(ReplicatorError::PoolerTarget {
+            operation: operation.as_str().to_string(),
+            evidence: evidence.to_string(),
+        }
+        .into()),
+    }
+}
+
+fn is_routed(url: &str) -> bool {
+    DIRECT_URLS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|urls| urls.contains(url))
+}
+
+fn mark_routed(url: &str) {
+    DIRECT_URLS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashSet::new)
+        .insert(url.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_evidence() {
+        let none = PoolerSettings::default();
+        assert_eq!(
+            url_evidence("postgresql://u:p@db.example.com:6432/app", &none).unwrap(),
+            Some(PoolerEvidence::PoolerPort(6432))
+        );
+        assert_eq!(
+            url_evidence(
+                "postgresql://u:p@ep-cool-1234-pooler.us-east-2.aws.neon.tech:5432/app",
+                &none
+            )
+            .unwrap(),
+            Some(PoolerEvidence::PoolerHost(
+                "ep-cool-1234-pooler.us-east-2.aws.neon.tech".to_string()
+            ))
+        );
+        assert_eq!(
+            url_evidence("postgresql://u:p@db.example.com:5432/app", &none).unwrap(),
+            None
+        );
+
+        let declared = PoolerSettings {
+            declared: true,
+            direct_port: Some(5432),
+        };
+        assert_eq!(
+            url_evidence("postgresql://u:p@db.example.com:6000/app", &declared).unwrap(),
+            Some(PoolerEvidence::Declared)
+        );
+        assert_eq!(
+            url_evidence("postgresql://u:p@db.example.com:5432/app", &declared).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_direct_url() {
+        let port = PoolerSettings {
+            declared: false,
+            direct_port: Some(5432),
+        };
+        assert_eq!(
+            direct_url(
+                "postgresql://u:p@db.example.com:6543/app?sslmode=require",
+                &port
+            )
+            .unwrap()
+            .as_deref(),
+            Some("postgresql://u:p@db.example.com:5432/app?sslmode=require")
+        );
+        assert_eq!(
+            direct_url("postgresql://u:p@db.example.com/app", &port)
+                .unwrap()
+                .as_deref(),
+            Some("postgresql://u:p@db.example.com:5432/app")
+        );
+        assert_eq!(
+            direct_url(
+                "postgresql://u:p@ep-cool-1234-pooler.us-east-2.aws.neon.tech:5432/app",
+                &PoolerSettings::default()
+            )
+            .unwrap()
+            .as_deref(),
+            Some("postgresql://u:p@ep-cool-1234.us-east-2.aws.neon.tech:5432/app")
+        );
+        assert_eq!(
+            direct_url(
+                "postgresql://u:p@db.example.com:6432/app",
+                &PoolerSettings::default()
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_operation_without_direct_route_fails() {
+        let err = route_target(
+            "postgresql://u:p@pooled.invalid:6432/app",
+            TargetOperation::Copy,
+            &PoolerSettings::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            crate::errors::find(&err),
+            Some(ReplicatorError::PoolerTarget { .. })
+        ));
+
+        let url = route_target(
+            "postgresql://u:p@pooled.invalid:6432/app",
+            TargetOperation::Query,
+            &PoolerSettings::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(url, "postgresql://u:p@pooled.invalid:6432/app");
+    }
+
+    #[test]
+    fn test_only_queries_tolerate_poolers() {
+        assert!(TargetOperation::Restore.requires_session());
+        assert!(TargetOperation::Copy.requires_session());
+        assert!(TargetOperation::Subscription.requires_session());
+        assert!(!TargetOperation::Query.requires_session());
+    }
+}
@@ -0,0 +1,451 @@
+// ABOUTME: Target profile presets (supabase, rds, neon, seren) for managed PostgreSQL providers
+// ABOUTME: Rewrites dumped globals and schema to drop reserved roles, superuser attributes, and unsupported extensions
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Managed provider the target runs on, selected with `init --target-profile`
+///
+/// None of these providers hand out a real superuser, so pieces of a
+/// `pg_dumpall`/`pg_dump` script that need one fail the restore: provider-owned
+/// roles, `SUPERUSER`/`REPLICATION`/`BYPASSRLS` attributes, tablespaces, and
+/// extensions the provider does not ship.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetProfile {
+    Supabase,
+    Rds,
+    Neon,
+    Seren,
+}
+
+/// Contrib extensions every supported provider ships
+const CORE_EXTENSIONS: &[&str] = &[
+    "amcheck",
+    "bloom",
+    "btree_gin",
+    "btree_gist",
+    "citext",
+    "cube",
+    "dblink",
+    "dict_int",
+    "earthdistance",
+    "fuzzystrmatch",
+    "hstore",
+    "intarray",
+    "isn",
+    "lo",
+    "ltree",
+    "pg_buffercache",
+    "pg_prewarm",
+    "pg_stat_statements",
+    "pg_trgm",
+    "pgcrypto",
+    "pgrowlocks",
+    "pgstattuple",
+    "plpgsql",
+    "postgres_fdw",
+    "seg",
+    "tablefunc",
+    "tcn",
+    "tsm_system_rows",
+    "tsm_system_time",
+    "unaccent",
+    "uuid-ossp",
+];
+
+const SUPABASE_EXTENSIONS: &[&str] = &[
+    "address_standardizer",
+    "http",
+    "hypopg",
+    "index_advisor",
+    "moddatetime",
+    "pg_cron",
+    "pg_graphql",
+    "pg_hashids",
+    "pg_jsonschema",
+    "pg_net",
+    "pg_repack",
+    "pgaudit",
+    "pgjwt",
+    "pgroonga",
+    "pgrouting",
+    "pgsodium",
+    "pgtap",
+    "plpgsql_check",
+    "plv8",
+    "postgis",
+    "postgis_raster",
+    "postgis_topology",
+    "rum",
+    "supabase_vault",
+    "vector",
+    "wrappers",
+];
+
+const RDS_EXTENSIONS: &[&str] = &[
+    "address_standardizer",
+    "address_standardizer_data_us",
+    "aws_commons",
+    "aws_lambda",
+    "aws_s3",
+    "h3",
+    "h3_postgis",
+    "hll",
+    "hypopg",
+    "ip4r",
+    "orafce",
+    "pg_bigm",
+    "pg_cron",
+    "pg_hint_plan",
+    "pg_partman",
+    "pg_repack",
+    "pg_similarity",
+    "pgaudit",
+    "pglogical",
+    "pgrouting",
+    "pgtap",
+    "plperl",
+    "plv8",
+    "postgis",
+    "postgis_raster",
+    "postgis_topology",
+    "prefix",
+    "rdkit",
+    "vector",
+];
+
+const NEON_EXTENSIONS: &[&str] = &[
+    "address_standardizer",
+    "address_standardizer_data_us",
+    "h3",
+    "h3_postgis",
+    "hll",
+    "hypopg",
+    "ip4r",
+    "neon",
+    "neon_utils",
+    "pg_cron",
+    "pg_graphql",
+    "pg_hashids",
+    "pg_hint_plan",
+    "pg_jsonschema",
+    "pg_partman",
+    "pg_repack",
+    "pg_roaringbitmap",
+    "pg_session_jwt",
+    "pg_tiktoken",
+    "pg_uuidv7",
+    "pgjwt",
+    "pgrouting",
+    "pgtap",
+    "pgx_ulid",
+    "plpgsql_check",
+    "plv8",
+    "postgis",
+    "postgis_raster",
+    "postgis_topology",
+    "prefix",
+    "rum",
+    "semver",
+    "timescaledb",
+    "vector",
+];
+
+/// Roles Supabase creates for its API, auth, and storage services
+const SUPABASE_ROLES: &[&str] = &[
+    "anon",
+    "authenticated",
+    "authenticator",
+    "dashboard_user",
+    "pgbouncer",
+    "pgsodium_keyholder",
+    "pgsodium_keyiduser",
+    "pgsodium_keymaker",
+    "postgres",
+    "service_role",
+];
+
+/// Role attributes only a superuser can hand out
+const SUPERUSER_ATTRIBUTES: &[&str] = &["SUPERUSER", "REPLICATION", "BYPASSRLS"];
+
+impl TargetProfile {
+    pub const ALL: [TargetProfile; 4] = [
+        TargetProfile::Supabase,
+        TargetProfile::Rds,
+        TargetProfile::Neon,
+        TargetProfile::Seren,
+    ];
+
+    /// Name accepted by `--target-profile`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetProfile::Supabase => "supabase",
+            TargetProfile::Rds => "rds",
+            TargetProfile::Neon => "neon",
+            TargetProfile::Seren => "seren",
+        }
+    }
+
+    /// Whether the provider creates and owns this role itself
+    pub fn is_reserved_role(&self, role: &str) -> bool {
+        let role = role.trim_matches('"');
+        match self {
+            TargetProfile::Supabase => {
+                role.starts_with("supabase_") || SUPABASE_ROLES.contains(&role)
+            }
+            TargetProfile::Rds => super::flavor::is_rds_internal_role(role),
+            TargetProfile::Neon | TargetProfile::Seren => {
+                matches!(role, "cloud_admin" | "neon_superuser")
+            }
+        }
+    }
+
+    /// Whether the provider lets non-superusers create this extension
+    pub fn allows_extension(&self, name: &str) -> bool {
+        let name = name.trim_matches('"');
+        let provider = match self {
+            TargetProfile::Supabase => SUPABASE_EXTENSIONS,
+            TargetProfile::Rds => RDS_EXTENSIONS,
+            TargetProfile::Neon | TargetProfile::Seren => NEON_EXTENSIONS,
+        };
+        CORE_EXTENSIONS.contains(&name) || provider.contains(&name)
+    }
+
+    /// Adapt a `pg_dumpall --globals-only` script to the provider
+    ///
+    /// Removes reserved roles (see [`super::flavor::strip_internal_roles`]) and
+    /// tablespace statements, and drops superuser-only role attributes.
+    pub fn rewrite_globals(&self, script: &str) -> (String, GlobalsRewrite) {
+        let (script, roles_removed) =
+            super::flavor::strip_internal_roles(script, |role| self.is_reserved_role(role));
+        let mut report = GlobalsRewrite {
+            roles_removed,
+            ..Default::default()
+        };
+        let mut kept = Vec::new();
+        for line in script.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["CREATE" | "ALTER", "TABLESPACE", ..] | ["COMMENT", "ON", "TABLESPACE", ..] => {
+                    report.tablespaces_removed += 1;
+                }
+                ["CREATE" | "ALTER", "ROLE", ..] => {
+                    let before = words.len();
+                    let rewritten: Vec<&str> = words
+                        .iter()
+                        .copied()
+                        .filter(|word| !SUPERUSER_ATTRIBUTES.contains(&word.trim_end_matches(';')))
+                        .collect();
+                    if rewritten.len() == before {
+                        kept.push(line.to_string());
+                    } else {
+                        report.attributes_dropped += before - rewritten.len();
+                        let mut statement = rewritten.join(" ");
+                        if !statement.ends_with(';') {
+                            statement.push(';');
+                        }
+                        kept.push(statement);
+                    }
+                }
+                _ => kept.push(line.to_string()),
+            }
+        }
+        let mut cleaned = kept.join("\n");
+        if script.ends_with('\n') {
+            cleaned.push('\n');
+        }
+        (cleaned, report)
+    }
+
+    /// Remove `CREATE EXTENSION` and `COMMENT ON EXTENSION` for extensions the
+    /// provider does not offer
+    ///
+    /// Returns the rewritten script and the names of the removed extensions.
+    pub fn rewrite_schema(&self, sql: &str) -> (String, Vec<String>) {
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+        for line in sql.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let extension = match words.as_slice() {
+                ["CREATE", "EXTENSION", "IF", "NOT", "EXISTS", name, ..]
+                | ["CREATE", "EXTENSION", name, ..] => Some((*name, true)),
+                ["COMMENT", "ON", "EXTENSION", name, ..] => Some((*name, false)),
+                _ => None,
+            };
+            match extension {
+                Some((name, create)) if !self.allows_extension(name.trim_end_matches(';')) => {
+                    if create {
+                        removed.push(name.trim_end_matches(';').trim_matches('"').to_string());
+                    }
+                }
+                _ => kept.push(line),
+            }
+        }
+        let mut cleaned = kept.join("\n");
+        if sql.ends_with('\n') {
+            cleaned.push('\n');
+        }
+        (cleaned, removed)
+    }
+
+    /// Apply [`TargetProfile::rewrite_globals`] to a globals file in place
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or written.
+    pub fn prepare_globals_file(&self, path: &Path) -> Result<()> {
+        let script = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read globals dump {}", path.display()))?;
+        let (cleaned, report) = self.rewrite_globals(&script);
+        std::fs::write(path, cleaned)
+            .with_context(|| format!("Failed to write globals dump {}", path.display()))?;
+        if report.roles_removed > 0 {
+            tracing::info!(
+                "✓ Skipped {} statement(s) for roles reserved by {}",
+                report.roles_removed,
+                self
+            );
+        }
+        if report.attributes_dropped > 0 {
+            tracing::info!(
+                "✓ Dropped {} SUPERUSER/REPLICATION/BYPASSRLS attribute(s) that {} does not allow",
+                report.attributes_dropped,
+                self
+            );
+        }
+        if report.tablespaces_removed > 0 {
+            tracing::warn!(
+                "⚠ Skipped {} tablespace statement(s); {} places all data in its default tablespace",
+                report.tablespaces_removed,
+                self
+            );
+        }
+        Ok(())
+    }
+
+    /// Apply [`TargetProfile::rewrite_schema`] to a schema file in place
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or written.
+    pub fn prepare_schema_file(&self, path: &Path) -> Result<()> {
+        let sql = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read schema dump {}", path.display()))?;
+        let (cleaned, removed) = self.rewrite_schema(&sql);
+        if removed.is_empty() {
+            return Ok(());
+        }
+        std::fs::write(path, cleaned)
+            .with_context(|| format!("Failed to write schema dump {}", path.display()))?;
+        tracing::warn!(
+            "  ⚠ Skipped extension(s) not available on {}: {}",
+            self,
+            removed.join(", ")
+        );
+        tracing::warn!("    Objects that depend on them will fail to restore");
+        Ok(())
+    }
+}
+
+impl fmt::Display for TargetProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TargetProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        TargetProfile::ALL
+            .into_iter()
+            .find(|profile| profile.as_str() == s.to_ascii_lowercase())
+            .with_context(|| {
+                format!(
+                    "Unknown target profile '{}'. Expected one of: supabase, rds, neon, seren",
+                    s
+                )
+            })
+    }
+}
+
+/// What [`TargetProfile::rewrite_globals`] changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GlobalsRewrite {
+    pub roles_removed: usize,
+    pub attributes_dropped: usize,
+    pub tablespaces_removed: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target_profile() {
+        assert_eq!(
+            "Supabase".parse::<TargetProfile>().unwrap(),
+            TargetProfile::Supabase
+        );
+        assert_eq!("rds".parse::<TargetProfile>().unwrap(), TargetProfile::Rds);
+        assert!("heroku".parse::<TargetProfile>().is_err());
+    }
+
+    #[test]
+    fn test_rewrite_globals_for_supabase() {
+        let script = "\
+CREATE ROLE anon;
+ALTER ROLE anon WITH NOSUPERUSER NOINHERIT NOCREATEROLE NOCREATEDB NOLOGIN NOREPLICATION NOBYPASSRLS;
+CREATE ROLE admin;
+ALTER ROLE admin WITH SUPERUSER INHERIT CREATEROLE CREATEDB LOGIN REPLICATION BYPASSRLS;
+CREATE TABLESPACE fast OWNER admin LOCATION '/mnt/fast';
+GRANT readers TO admin GRANTED BY supabase_admin;
+";
+        let (cleaned, report) = TargetProfile::Supabase.rewrite_globals(script);
+        assert_eq!(
+            report,
+            GlobalsRewrite {
+                roles_removed: 2,
+                attributes_dropped: 3,
+                tablespaces_removed: 1,
+            }
+        );
+        assert_eq!(
+            cleaned,
+            "\
+CREATE ROLE admin;
+ALTER ROLE admin WITH INHERIT CREATEROLE CREATEDB LOGIN;
+GRANT readers TO admin;
+"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_schema_drops_unavailable_extensions() {
+        let sql = "\
+CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\" WITH SCHEMA public;
+COMMENT ON EXTENSION \"uuid-ossp\" IS 'generate universally unique identifiers (UUIDs)';
+CREATE EXTENSION IF NOT EXISTS pg_bigm WITH SCHEMA public;
+COMMENT ON EXTENSION pg_bigm IS 'text similarity measurement and index searching based on bigrams';
+CREATE TABLE public.docs (id uuid DEFAULT public.uuid_generate_v4());
+";
+        let (cleaned, removed) = TargetProfile::Neon.rewrite_schema(sql);
+        assert_eq!(removed, vec!["pg_bigm".to_string()]);
+        assert!(cleaned.contains("\"uuid-ossp\""));
+        assert!(!cleaned.contains("pg_bigm"));
+        assert!(cleaned.ends_with("uuid_generate_v4());\n"));
+
+        let (_, removed) = TargetProfile::Rds.rewrite_schema(sql);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn test_reserved_roles() {
+        assert!(TargetProfile::Rds.is_reserved_role("rdsadmin"));
+        assert!(TargetProfile::Supabase.is_reserved_role("\"supabase_auth_admin\""));
+        assert!(TargetProfile::Neon.is_reserved_role("neon_superuser"));
+        assert!(!TargetProfile::Seren.is_reserved_role("app"));
+    }
+}
@@ -0,0 +1,252 @@
+// ABOUTME: Picks the closest healthy read replica among --source-candidates for dump and copy traffic
+// ABOUTME: Measures round-trip latency and replay lag; the primary --source keeps slots and publications
+
+use crate::settings::RunSettings;
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+use tokio_postgres::Client;
+
+/// Replicas further behind the primary than this are not used for copying
+pub const DEFAULT_MAX_LAG_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Round trips timed per candidate; the median is used
+const LATENCY_SAMPLES: usize = 5;
+
+/// What was measured for one candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateMeasurement {
+    pub url: String,
+    /// Median round-trip time of `SELECT 1`
+    pub latency: Option<Duration>,
+    /// WAL bytes the candidate has yet to replay; 0 for the primary
+    pub lag_bytes: Option<u64>,
+    /// Why the candidate cannot be used, if it cannot
+    pub rejected: Option<String>,
+}
+
+/// Index of the lowest-latency usable candidate within `max_lag_bytes`
+pub fn choose(measurements: &[CandidateMeasurement], max_lag_bytes: u64) -> Option<usize> {
+    measurements
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.rejected.is_none())
+        .filter(|(_, m)| m.lag_bytes.is_some_and(|lag| lag <= max_lag_bytes))
+        .filter_map(|(i, m)| m.latency.map(|latency| (i, latency)))
+        .min_by_key(|(_, latency)| *latency)
+        .map(|(i, _)| i)
+}
+
+/// Choose where init dumps and copies from: the primary or one of `candidates`
+///
+/// Each candidate must be reachable, belong to the same cluster as the
+/// primary (same system identifier, when the user may read it), and be at
+/// most [`DEFAULT_MAX_LAG_BYTES`] behind. Of those, the one with the lowest
+/// round-trip time wins. The primary is always a candidate.
+///
+/// # Returns
+///
+/// The URL to copy from; `primary_url` when no replicas are configured or none is better.
+///
+/// # Errors
+///
+/// Returns an error only if the primary itself cannot be measured.
+pub async fn select_copy_source(
+    primary_url: &str,
+    candidates: &[String],
+    settings: &RunSettings,
+) -> Result<String> {
+    if candidates.is_empty() {
+        return Ok(primary_url.to_string());
+    }
+
+    tracing::info!(
+        "Measuring the source and {} replica candidate(s)...",
+        candidates.len()
+    );
+    let primary = super::connect_with_retry(primary_url, settings)
+        .await
+        .context("Failed to connect to the primary source")?;
+    let primary_lsn: String = primary
+        .query_one("SELECT pg_current_wal_lsn()::text", &[])
+        .await
+        .context("Failed to read the primary's WAL position")?
+        .get(0);
+    let primary_lsn = crate::replication::history::parse_lsn(&primary_lsn).unwrap_or(0);
+    let primary_id = system_identifier(&primary).await;
+
+    let mut measurements = vec![CandidateMeasurement {
+        url: primary_url.to_string(),
+        latency: Some(median_latency(&primary).await?),
+        lag_bytes: Some(0),
+        rejected: None,
+    }];
+    for url in candidates {
+        measurements.push(measure_replica(url, primary_lsn, primary_id.as_deref()).await);
+    }
+
+    tracing::info!(
+        "{:<48} {:>10} {:>12}  Status",
+        "Candidate",
+        "Latency",
+        "Lag"
+    );
+    for (idx, m) in measurements.iter().enumerate() {
+        let name = if idx == 0 {
+            format!("{} (primary)", crate::remote::redact_url(&m.url))
+        } else {
+            crate::remote::redact_url(&m.url)
+        };
+        let status = match (&m.rejected, m.lag_bytes) {
+            (Some(reason), _) => format!("✗ {}", reason),
+            (None, Some(lag)) if lag > DEFAULT_MAX_LAG_BYTES => "✗ too far behind".to_string(),
+            _ => "✓".to_string(),
+        };
+        tracing::info!(
+            "{:<48} {:>10} {:>12}  {}",
+            name,
+            m.latency
+                .map(|l| format!("{:.1}ms", l.as_secs_f64() * 1000.0))
+                .unwrap_or_else(|| "-".to_string()),
+            m.lag_bytes
+                .map(|lag| crate::migration::format_bytes(lag as i64))
+                .unwrap_or_else(|| "-".to_string()),
+            status
+        );
+    }
+
+    let chosen = choose(&measurements, DEFAULT_MAX_LAG_BYTES).unwrap_or(0);
+    if chosen == 0 {
+        tracing::info!("✓ Copying from the primary source");
+    } else {
+        tracing::info!(
+            "✓ Copying from replica {}; replication slots stay on the primary",
+            crate::remote::redact_url(&measurements[chosen].url)
+        );
+        tracing::info!(
+            "  Long dumps on a standby can be cancelled by recovery conflicts; \
+             enable hot_standby_feedback on it if that happens"
+        );
+    }
+    Ok(measurements.swap_remove(chosen).url)
+}
+
+async fn measure_replica(
+    url: &str,
+    primary_lsn: u64,
+    primary_id: Option<&str>,
+) -> CandidateMeasurement {
+    let mut measurement = CandidateMeasurement {
+        url: url.to_string(),
+        latency: None,
+        lag_bytes: None,
+        rejected: None,
+    };
+    let client = match super::connect(url).await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::debug!(
+                "Candidate {} unreachable: {:#}",
+                crate::remote::redact_url(url),
+                e
+            );
+            measurement.rejected = Some("unreachable".to_string());
+            return measurement;
+        }
+    };
+    if let (Some(primary_id), Some(id)) = (primary_id, system_identifier(&client).await) {
+        if primary_id != id {
+            measurement.rejected = Some("not a replica of the source".to_string());
+            return measurement;
+        }
+    }
+    let lag = client
+        .query_one(
+            "SELECT CASE WHEN pg_is_in_recovery()
+                         THEN pg_last_wal_replay_lsn()::text
+                         ELSE pg_current_wal_lsn()::text END",
+            &[],
+        )
+        .await
+        .ok()
+        .and_then(|row| row.get::<_, Option<String>>(0))
+        .and_then(|lsn| crate::replication::history::parse_lsn(&lsn))
+        .map(|replayed| primary_lsn.saturating_sub(replayed));
+    match lag {
+        Some(lag) => measurement.lag_bytes = Some(lag),
+        None => {
+            measurement.rejected = Some("replay position unknown".to_string());
+            return measurement;
+        }
+    }
+    match median_latency(&client).await {
+        Ok(latency) => measurement.latency = Some(latency),
+        Err(_) => measurement.rejected = Some("query failed".to_string()),
+    }
+    measurement
+}
+
+async fn median_latency(client: &Client) -> Result<Duration> {
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    for _ in 0..LATENCY_SAMPLES {
+        let started = Instant::now();
+        client
+            .simple_query("SELECT 1")
+            .await
+            .context("Failed to time a round trip")?;
+        samples.push(started.elapsed());
+    }
+    samples.sort();
+    Ok(samples[samples.len() / 2])
+}
+
+/// The cluster's system identifier, if the user may read pg_control_system()
+async fn system_identifier(client: &Client) -> Option<String> {
+    client
+        .query_one(
+            "SELECT system_identifier::text FROM pg_control_system()",
+            &[],
+        )
+        .await
+        .ok()
+        .map(|row| row.get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        latency_ms: Option<u64>,
+        lag: Option<u64>,
+        rejected: bool,
+    ) -> CandidateMeasurement {
+        CandidateMeasurement {
+            url: String::new(),
+            latency: latency_ms.map(Duration::from_millis),
+            lag_bytes: lag,
+            rejected: rejected.then(|| "unreachable".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_choose_lowest_latency_within_lag() {
+        let measurements = vec![
+            candidate(Some(80), Some(0), false),
+            candidate(Some(5), Some(1024), false),
+            candidate(Some(2), Some(DEFAULT_MAX_LAG_BYTES + 1), false),
+            candidate(Some(1), Some(0), true),
+        ];
+        assert_eq!(choose(&measurements, DEFAULT_MAX_LAG_BYTES), Some(1));
+    }
+
+    #[test]
+    fn test_choose_falls_back_to_primary() {
+        let measurements = vec![
+            candidate(Some(80), Some(0), false),
+            candidate(None, None, true),
+            candidate(Some(5), Some(DEFAULT_MAX_LAG_BYTES * 2), false),
+        ];
+        assert_eq!(choose(&measurements, DEFAULT_MAX_LAG_BYTES), Some(0));
+        assert_eq!(choose(&[], DEFAULT_MAX_LAG_BYTES), None);
+    }
+}
@@ -0,0 +1,273 @@
+// ABOUTME: Temporary bulk-load settings for the target (max_wal_size, checkpoint_timeout, autovacuum)
+// ABOUTME: Recommends them, applies them with ALTER SYSTEM, and reverts to the saved previous values
+
+use anyhow::{Context, Result};
+use tokio_postgres::Client;
+
+/// Table on the target (in [`TUNING_DATABASE`]) remembering the values to revert to
+pub const TUNING_TABLE: &str = "seren_replicator.load_tuning";
+
+/// Database holding [`TUNING_TABLE`], whatever database the target URL names
+///
+/// ALTER SYSTEM changes the whole cluster, so apply and revert must find the
+/// saved values even when they are run with URLs for different databases.
+pub const TUNING_DATABASE: &str = "postgres";
+
+/// Smallest max_wal_size (MB) worth loading with: fewer forced checkpoints during COPY
+const LOAD_MAX_WAL_SIZE_MB: i64 = 16 * 1024;
+
+/// Smallest checkpoint_timeout (seconds) worth loading with
+const LOAD_CHECKPOINT_TIMEOUT_SECS: i64 = 30 * 60;
+
+/// A setting whose current value is worse for bulk loading than the recommendation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadSetting {
+    pub name: &'static str,
+    /// Current value, as `SHOW` prints it
+    pub current: String,
+    /// Value to use during the load, as accepted by `ALTER SYSTEM SET`
+    pub recommended: String,
+    pub reason: &'static str,
+}
+
+/// Current values of the tuned settings, in pg_settings' base units
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentSettings {
+    /// MB
+    pub max_wal_size: i64,
+    pub max_wal_size_display: String,
+    /// Seconds
+    pub checkpoint_timeout: i64,
+    pub checkpoint_timeout_display: String,
+    pub autovacuum: bool,
+}
+
+/// Settings to change for a bulk load, given the current ones
+///
+/// Settings already at least as good as the recommendation are left out.
+pub fn recommend_for_load(current: &CurrentSettings) -> Vec<LoadSetting> {
+    let mut settings = Vec::new();
+    if current.max_wal_size < LOAD_MAX_WAL_SIZE_MB {
+        settings.push(LoadSetting {
+            name: "max_wal_size",
+            current: current.max_wal_size_display.clone(),
+            recommended: format!("{}GB", LOAD_MAX_WAL_SIZE_MB / 1024),
+            reason: "fewer WAL-triggered checkpoints while tables are copied",
+        });
+    }
+    if current.checkpoint_timeout < LOAD_CHECKPOINT_TIMEOUT_SECS {
+        settings.push(LoadSetting {
+            name: "checkpoint_timeout",
+            current: current.checkpoint_timeout_display.clone(),
+            recommended: format!("{}min", LOAD_CHECKPOINT_TIMEOUT_SECS / 60),
+            reason: "fewer timed checkpoints while tables are copied",
+        });
+    }
+    if current.autovacuum {
+        settings.push(LoadSetting {
+            name: "autovacuum",
+            current: "on".to_string(),
+            recommended: "off".to_string(),
+            reason:
+                "no vacuum or analyze workers competing with the load; init analyzes afterwards",
+        });
+    }
+    settings
+}
+
+/// Read the current values of the tuned settings
+///
+/// # Errors
+///
+/// Returns an error if pg_settings cannot be read.
+pub async fn current_settings(client: &Client) -> Result<CurrentSettings> {
+    let row = client
+        .query_one(
+            "SELECT
+                 (SELECT setting::bigint FROM pg_settings WHERE name = 'max_wal_size'),
+                 current_setting('max_wal_size'),
+                 (SELECT setting::bigint FROM pg_settings WHERE name = 'checkpoint_timeout'),
+                 current_setting('checkpoint_timeout'),
+                 current_setting('autovacuum') = 'on'",
+            &[],
+        )
+        .await
+        .context("Failed to read target settings")?;
+    Ok(CurrentSettings {
+        max_wal_size: row.get(0),
+        max_wal_size_display: row.get(1),
+        checkpoint_timeout: row.get(2),
+        checkpoint_timeout_display: row.get(3),
+        autovacuum: row.get(4),
+    })
+}
+
+/// Apply `settings` with ALTER SYSTEM and reload the configuration
+///
+/// The value each setting had in postgresql.auto.conf beforehand is saved in
+/// [`TUNING_TABLE`] first. Applying twice keeps the original values.
+///
+/// # Errors
+///
+/// Returns an error if the user may not run ALTER SYSTEM (superuser, or the
+/// ALTER SYSTEM privilege on PostgreSQL 15+). Managed services such as RDS
+/// never allow it; use their parameter groups instead.
+pub async fn apply(client: &Client, settings: &[LoadSetting]) -> Result<()> {
+    ensure_tuning_table(client).await?;
+    for setting in settings {
+        client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (name, previous)
+                     SELECT $1::text,
+                            (SELECT setting FROM pg_file_settings
+                             WHERE name = $1::text AND applied
+                               AND sourcefile LIKE '%postgresql.auto.conf')
+                     ON CONFLICT (name) DO NOTHING",
+                    TUNING_TABLE
+                ),
+                &[&setting.name],
+            )
+            .await
+            .with_context(|| format!("Failed to save the current value of {}", setting.name))?;
+        client
+            .batch_execute(&format!(
+                "ALTER SYSTEM SET {} = {}",
+                setting.name,
+                crate::utils::quote_literal(&setting.recommended)
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to run ALTER SYSTEM SET {} = '{}'.\n\
+                     ALTER SYSTEM needs a superuser (or the ALTER SYSTEM privilege on PostgreSQL 15+).\n\
+                     On managed services, change the setting in the provider's parameter group instead.",
+                    setting.name, setting.recommended
+                )
+            })?;
+    }
+    client
+        .batch_execute("SELECT pg_reload_conf()")
+        .await
+        .context("Failed to reload the target configuration")?;
+    Ok(())
+}
+
+/// Restore the settings saved by [`apply`] and reload the configuration
+///
+/// # Returns
+///
+/// The names of the settings reverted; empty if nothing was applied.
+///
+/// # Errors
+///
+/// Returns an error if ALTER SYSTEM is not permitted.
+pub async fn revert(client: &Client) -> Result<Vec<String>> {
+    let exists: bool = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&TUNING_TABLE])
+        .await
+        .context("Failed to check for the load tuning table")?
+        .get(0);
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let rows = client
+        .query(
+            &format!("SELECT name, previous FROM {} ORDER BY name", TUNING_TABLE),
+            &[],
+        )
+        .await
+        .context("Failed to read saved settings")?;
+    let mut reverted = Vec::new();
+    for row in &rows {
+        let name: String = row.get(0);
+        let previous: Option<String> = row.get(1);
+        let statement = match &previous {
+            Some(value) => format!(
+                "ALTER SYSTEM SET {} = {}",
+                crate::utils::quote_ident(&name),
+                crate::utils::quote_literal(value)
+            ),
+            None => format!("ALTER SYSTEM RESET {}", crate::utils::quote_ident(&name)),
+        };
+        client
+            .batch_execute(&statement)
+            .await
+            .with_context(|| format!("Failed to revert {}", name))?;
+        client
+            .execute(
+                &format!("DELETE FROM {} WHERE name = $1", TUNING_TABLE),
+                &[&name],
+            )
+            .await
+            .with_context(|| format!("Failed to clear the saved value of {}", name))?;
+        reverted.push(name);
+    }
+    client
+        .batch_execute("SELECT pg_reload_conf()")
+        .await
+        .context("Failed to reload the target configuration")?;
+    Ok(reverted)
+}
+
+async fn ensure_tuning_table(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 name text PRIMARY KEY,
+                 previous text,
+                 applied_at timestamptz NOT NULL DEFAULT now()
+             )",
+            TUNING_TABLE
+        ))
+        .await
+        .context(
+            "Failed to create the load tuning table on the target.\n\
+             The target user needs CREATE privilege on the 'postgres' database.",
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> CurrentSettings {
+        CurrentSettings {
+            max_wal_size: 1024,
+            max_wal_size_display: "1GB".to_string(),
+            checkpoint_timeout: 300,
+            checkpoint_timeout_display: "5min".to_string(),
+            autovacuum: true,
+        }
+    }
+
+    #[test]
+    fn test_recommend_for_load_defaults() {
+        let settings = recommend_for_load(&defaults());
+        let pairs: Vec<_> = settings
+            .iter()
+            .map(|s| (s.name, s.current.as_str(), s.recommended.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("max_wal_size", "1GB", "16GB"),
+                ("checkpoint_timeout", "5min", "30min"),
+                ("autovacuum", "on", "off"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recommend_for_load_keeps_larger_values() {
+        let current = CurrentSettings {
+            max_wal_size: 64 * 1024,
+            max_wal_size_display: "64GB".to_string(),
+            checkpoint_timeout: 3600,
+            checkpoint_timeout_display: "1h".to_string(),
+            autovacuum: false,
+        };
+        assert!(recommend_for_load(&current).is_empty());
+    }
+}
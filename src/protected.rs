@@ -0,0 +1,231 @@
+// ABOUTME: Operator-maintained list of protected hosts and databases (/etc/seren-replicator/protected.toml)
+// ABOUTME: Destructive commands refuse to run against them, whatever flags are passed
+
+use crate::errors::ReplicatorError;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Where the protected list is read from unless `SEREN_PROTECTED_CONFIG` is set
+pub const DEFAULT_PROTECTED_PATH: &str = "/etc/seren-replicator/protected.toml";
+
+/// Environment variable overriding [`DEFAULT_PROTECTED_PATH`]
+pub const PROTECTED_PATH_ENV: &str = "SEREN_PROTECTED_CONFIG";
+
+/// Hosts and databases no destructive command may touch
+///
+/// ```toml
+/// hosts = ["prod-db.internal", "*.prod.example.com"]
+/// databases = ["billing", "ledger"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProtectedTargets {
+    /// Hostnames, matched case-insensitively; `*.example.com` matches any subdomain
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Database names, matched exactly on any host
+    #[serde(default)]
+    pub databases: Vec<String>,
+    /// File the list was read from, for error messages
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl ProtectedTargets {
+    /// Read the protected list from `path`
+    ///
+    /// # Returns
+    ///
+    /// `None` if the file does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed, so a
+    /// broken list never silently disables protection.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read protected target list {}", path.display())
+                })
+            }
+        };
+        let mut targets: ProtectedTargets = toml::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse protected target list {}.\n\
+                 Expected top-level 'hosts' and 'databases' arrays of strings.",
+                path.display()
+            )
+        })?;
+        targets.path = path.to_path_buf();
+        Ok(Some(targets))
+    }
+
+    /// Whether `host` is on the list
+    pub fn protects_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.hosts.iter().any(|pattern| {
+            let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(suffix) => host
+                    .strip_suffix(suffix)
+                    .is_some_and(|prefix| prefix.ends_with('.') && prefix.len() > 1),
+                None => host == pattern,
+            }
+        })
+    }
+
+    /// Whether `database` is on the list
+    pub fn protects_database(&self, database: &str) -> bool {
+        self.databases.iter().any(|name| name == database)
+    }
+
+    /// Refuse `operation` if the URL's host or database, or any of `databases`, is protected
+    ///
+    /// # Errors
+    ///
+    /// Returns `SR-0037` naming the protected host or database, or an error if
+    /// the URL cannot be parsed.
+    pub fn check(&self, target_url: &str, databases: &[String], operation: &str) -> Result<()> {
+        let parts = crate::utils::parse_postgres_url(target_url)
+            .context("Failed to parse target URL for the protected target check")?;
+        let protected = if self.protects_host(&parts.host) {
+            Some(format!("host '{}'", parts.host))
+        } else {
+            std::iter::once(&parts.database)
+                .chain(databases)
+                .find(|name| self.protects_database(name))
+                .map(|name| format!("database '{}'", name))
+        };
+        match protected {
+            Some(target) => Err(ReplicatorError::ProtectedTarget {
+                operation: operation.to_string(),
+                target,
+                list: self.path.display().to_string(),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `None` until the list is set or first loaded; then the list, or `None` when there is no file
+static SETTINGS: RwLock<Option<Option<ProtectedTargets>>> = RwLock::new(None);
+
+/// Set the process-wide protected list (`None` protects nothing)
+pub fn set_protected_targets(targets: Option<ProtectedTargets>) {
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = Some(targets);
+}
+
+/// Path of the protected list: `SEREN_PROTECTED_CONFIG`, or [`DEFAULT_PROTECTED_PATH`]
+pub fn protected_path() -> PathBuf {
+    std::env::var_os(PROTECTED_PATH_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_PROTECTED_PATH))
+}
+
+/// Refuse `operation` against a protected target, using the process-wide list
+///
+/// The list is read from [`protected_path`] on first use when it has not
+/// been set, so library callers are protected the same way as the CLI.
+///
+/// # Errors
+///
+/// See [`ProtectedTargets::check`]. Also returns an error if the list has to
+/// be loaded and cannot be read or parsed.
+pub fn check(target_url: &str, databases: &[String], operation: &str) -> Result<()> {
+    let loaded = SETTINGS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let targets = match loaded {
+        Some(targets) => targets,
+        None => {
+            let targets = ProtectedTargets::load(&protected_path())?;
+            set_protected_targets(targets.clone());
+            targets
+        }
+    };
+    match targets {
+        Some(targets) => targets.check(target_url, databases, operation),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets() -> ProtectedTargets {
+        ProtectedTargets {
+            hosts: vec![
+                "prod-db.internal".to_string(),
+                "*.prod.example.com".to_string(),
+            ],
+            databases: vec!["billing".to_string()],
+            path: PathBuf::from("protected.toml"),
+        }
+    }
+
+    #[test]
+    fn test_protects_host() {
+        let targets = targets();
+        assert!(targets.protects_host("prod-db.internal"));
+        assert!(targets.protects_host("PROD-DB.internal."));
+        assert!(targets.protects_host("db1.prod.example.com"));
+        assert!(!targets.protects_host("prod.example.com"));
+        assert!(!targets.protects_host("db1.notprod.example.com"));
+        assert!(!targets.protects_host("staging-db.internal"));
+    }
+
+    #[test]
+    fn test_check() {
+        let targets = targets();
+        assert!(targets
+            .check(
+                "postgresql://u:p@staging/postgres",
+                &["app".to_string()],
+                "init"
+            )
+            .is_ok());
+
+        let err = targets
+            .check(
+                "postgresql://u:p@db1.prod.example.com/postgres",
+                &[],
+                "init",
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("host 'db1.prod.example.com'"));
+
+        let err = targets
+            .check("postgresql://u:p@staging/billing", &[], "backfill")
+            .unwrap_err();
+        assert!(err.to_string().contains("database 'billing'"));
+
+        let err = targets
+            .check(
+                "postgresql://u:p@staging/postgres",
+                &["app".to_string(), "billing".to_string()],
+                "init",
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("database 'billing'"));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("protected.toml");
+        assert_eq!(ProtectedTargets::load(&path).unwrap(), None);
+
+        std::fs::write(&path, "hosts = [\"prod-db\"]\n").unwrap();
+        let loaded = ProtectedTargets::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.hosts, vec!["prod-db".to_string()]);
+        assert!(loaded.databases.is_empty());
+
+        std::fs::write(&path, "host = \"prod-db\"\n").unwrap();
+        assert!(ProtectedTargets::load(&path).is_err());
+    }
+}
@@ -1,38 +1,81 @@
 // ABOUTME: HTTP client for communicating with remote execution API
-// ABOUTME: Handles job submission, status polling, and error handling
+// ABOUTME: Handles job submission, listing, adaptive status polling, rate limits, and retries with jitter
 
 use anyhow::{Context, Result};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use std::time::Duration;
 
-use super::models::{JobResponse, JobSpec, JobStatus};
+use super::models::{
+    JobDetails, JobList, JobResponse, JobSpec, JobStatus, JobSummary, WorkerCapabilities,
+};
+use crate::errors::ReplicatorError;
+use crate::retry::RetryPolicy;
+
+/// Limit for a whole request, including reading the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Limit for establishing the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wait between polls while the job is changing
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Longest wait between polls of a job whose status has stopped changing
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Longest server-requested `Retry-After` honored
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
 
 pub struct RemoteClient {
     client: Client,
     api_base_url: String,
+    retry: RetryPolicy,
 }
 
 impl RemoteClient {
     pub fn new(api_base_url: String) -> Result<Self> {
         let client = Client::builder()
-            .timeout(Duration::from_secs(30))
+            .timeout(REQUEST_TIMEOUT)
+            .connect_timeout(CONNECT_TIMEOUT)
             .build()
             .context("Failed to create HTTP client")?;
 
         Ok(Self {
             client,
             api_base_url,
+            retry: RetryPolicy::DEFAULT,
         })
     }
 
+    /// Retry rate-limited and failed requests per `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Validate the spec, check the fleet can run its version, and submit it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spec is invalid, the fleet advertises only
+    /// incompatible versions (`SR-0060`), or the submission fails.
     pub async fn submit_job(&self, spec: &JobSpec) -> Result<JobResponse> {
+        spec.validate().context("Invalid remote job spec")?;
+        if let Some(capabilities) = self.worker_capabilities().await? {
+            if !capabilities.supports(&spec.version) {
+                return Err(ReplicatorError::RemoteSpecUnsupported {
+                    spec_version: spec.version.clone(),
+                    supported: capabilities.spec_versions.join(", "),
+                }
+                .into());
+            }
+        }
+
         let url = format!("{}/jobs", self.api_base_url);
 
+        // Only retried when the service cannot have created the job
         let response = self
-            .client
-            .post(&url)
-            .json(spec)
-            .send()
+            .send_with_retry(|| self.client.post(&url).json(spec), false, "Job submission")
             .await
             .context("Failed to submit job to remote service. If the service is unavailable, you can use --local to run replication on your machine instead")?;
 
@@ -50,12 +93,48 @@ impl RemoteClient {
         Ok(job_response)
     }
 
+    /// Spec versions the worker fleet advertises
+    ///
+    /// Returns `None` when the service predates version advertisement (404),
+    /// in which case the job is submitted and the worker validates it.
+    pub async fn worker_capabilities(&self) -> Result<Option<WorkerCapabilities>> {
+        let url = format!("{}/capabilities", self.api_base_url);
+
+        let response = self
+            .send_with_retry(|| self.client.get(&url), true, "Capabilities request")
+            .await
+            .context("Failed to read supported job spec versions from remote service")?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            tracing::debug!("Remote service does not advertise job spec versions");
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!(
+                "Failed to read supported job spec versions {}: {}. The remote service may be experiencing issues",
+                status,
+                body
+            );
+        }
+
+        let capabilities: WorkerCapabilities = response
+            .json()
+            .await
+            .context("Failed to parse remote service capabilities")?;
+        Ok(Some(capabilities))
+    }
+
     pub async fn get_job_status(&self, job_id: &str) -> Result<JobStatus> {
         let url = format!("{}/jobs/{}", self.api_base_url, job_id);
 
-        let response = self.client.get(&url).send().await.context(
-            "Failed to get job status from remote service. The remote service may be unavailable",
-        )?;
+        let response = self
+            .send_with_retry(|| self.client.get(&url), true, "Job status request")
+            .await
+            .context(
+                "Failed to get job status from remote service. The remote service may be unavailable",
+            )?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -75,32 +154,318 @@ impl RemoteClient {
         Ok(job_status)
     }
 
+    /// Jobs submitted with this API, newest first
+    ///
+    /// `status` narrows the list to one status, e.g. "running".
+    pub async fn list_jobs(&self, limit: usize, status: Option<&str>) -> Result<Vec<JobSummary>> {
+        let url = format!("{}/jobs", self.api_base_url);
+        let mut query = vec![("limit", limit.to_string())];
+        if let Some(status) = status {
+            query.push(("status", status.to_string()));
+        }
+
+        let response = self
+            .send_with_retry(
+                || self.client.get(&url).query(&query),
+                true,
+                "Job list request",
+            )
+            .await
+            .context(
+                "Failed to list jobs from remote service. The remote service may be unavailable",
+            )?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!(
+                "Failed to list jobs {}: {}. The remote service may be experiencing issues",
+                status,
+                body
+            );
+        }
+
+        let list: JobList = response.json().await.context("Failed to parse job list")?;
+        Ok(list.jobs)
+    }
+
+    /// Full record of one job, including its spec and final report
+    pub async fn describe_job(&self, job_id: &str) -> Result<JobDetails> {
+        let url = format!("{}/jobs/{}", self.api_base_url, job_id);
+
+        let response = self
+            .send_with_retry(
+                || self.client.get(&url).query(&[("detail", "full")]),
+                true,
+                "Job details request",
+            )
+            .await
+            .context(
+                "Failed to get job details from remote service. The remote service may be unavailable",
+            )?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!(
+                "No remote job with ID '{}'. Run `remote list` to see submitted jobs",
+                job_id
+            );
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!(
+                "Failed to get job details {}: {}. The remote service may be experiencing issues",
+                status,
+                body
+            );
+        }
+
+        let details: JobDetails = response
+            .json()
+            .await
+            .context("Failed to parse job details")?;
+        Ok(details)
+    }
+
+    /// Poll a job until it completes or fails
+    ///
+    /// Polls every 2 seconds while the status or progress is changing and
+    /// backs off to 30 seconds while it is not. `callback` runs for the first
+    /// status and then only when something changed.
     pub async fn poll_until_complete(
         &self,
         job_id: &str,
         callback: impl Fn(&JobStatus),
     ) -> Result<JobStatus> {
+        let mut previous: Option<JobStatus> = None;
+        let mut interval = MIN_POLL_INTERVAL;
         loop {
             let status = self.get_job_status(job_id).await?;
-            callback(&status);
+            let changed = previous.as_ref() != Some(&status);
+            if changed {
+                callback(&status);
+            }
 
             match status.status.as_str() {
                 "completed" | "failed" => return Ok(status),
                 _ => {
-                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    interval = next_poll_interval(interval, changed);
+                    tokio::time::sleep(interval).await;
                 }
             }
+            previous = Some(status);
+        }
+    }
+
+    /// Send a request, retrying rate limits and transient failures
+    ///
+    /// 429 and 503 responses wait for `Retry-After` when the server sends one
+    /// and otherwise back off per the client's retry policy, with jitter.
+    /// Connection failures are always retried since nothing reached the
+    /// server; timeouts and other 5xx responses only when `idempotent`.
+    /// The last response is returned as-is once retries run out.
+    async fn send_with_retry(
+        &self,
+        request: impl Fn() -> RequestBuilder,
+        idempotent: bool,
+        operation: &str,
+    ) -> Result<Response> {
+        let policy = self.retry;
+        let mut attempt = 0;
+        loop {
+            let retries_left = attempt < policy.max_retries;
+            let delay = match request().send().await {
+                Ok(response) if retries_left && is_throttled(response.status()) => {
+                    let delay =
+                        retry_after(&response).unwrap_or_else(|| policy.delay_for_attempt(attempt));
+                    tracing::warn!(
+                        "⚠ {} was rate limited ({}), retrying in {:?}...",
+                        operation,
+                        response.status(),
+                        delay
+                    );
+                    delay
+                }
+                Ok(response)
+                    if retries_left && idempotent && response.status().is_server_error() =>
+                {
+                    let delay = policy.delay_for_attempt(attempt);
+                    tracing::warn!(
+                        "⚠ {} failed with {}, retrying in {:?}...",
+                        operation,
+                        response.status(),
+                        delay
+                    );
+                    delay
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if retries_left && (e.is_connect() || (idempotent && e.is_timeout())) => {
+                    let delay = policy.delay_for_attempt(attempt);
+                    tracing::warn!(
+                        "⚠ {} failed (attempt {}/{}): {}, retrying in {:?}...",
+                        operation,
+                        attempt + 1,
+                        policy.max_retries + 1,
+                        e,
+                        delay
+                    );
+                    delay
+                }
+                Err(e) => return Err(e.into()),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 }
 
+fn is_throttled(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+/// Delay requested by a `Retry-After` header given in seconds
+///
+/// HTTP-date values are ignored in favor of the normal backoff.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    parse_retry_after(value.to_str().ok()?)
+}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let seconds: u64 = value.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+/// Reset to the shortest interval after a change, otherwise grow by half up to the cap
+fn next_poll_interval(current: Duration, changed: bool) -> Duration {
+    if changed {
+        MIN_POLL_INTERVAL
+    } else {
+        (current + current / 2).min(MAX_POLL_INTERVAL)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     #[test]
     fn test_client_creation() {
         let client = RemoteClient::new("https://api.example.com".to_string());
         assert!(client.is_ok());
     }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("7"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("86400"), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn test_next_poll_interval() {
+        let mut interval = MIN_POLL_INTERVAL;
+        for _ in 0..20 {
+            interval = next_poll_interval(interval, false);
+        }
+        assert_eq!(interval, MAX_POLL_INTERVAL);
+        assert_eq!(next_poll_interval(interval, true), MIN_POLL_INTERVAL);
+        assert_eq!(
+            next_poll_interval(MIN_POLL_INTERVAL, false),
+            Duration::from_secs(3)
+        );
+    }
+
+    /// Serve canned HTTP responses, one per connection, in order
+    async fn serve(responses: Vec<String>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_status_retries_after_rate_limit() {
+        let body = r#"{"job_id":"j1","status":"running","progress":{"current_database":"app","databases_completed":1,"databases_total":3,"current_table":"public.orders"}}"#;
+        let url = serve(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        ])
+        .await;
+
+        let client = RemoteClient::new(url).unwrap();
+        let status = client.get_job_status("j1").await.unwrap();
+        assert_eq!(status.status, "running");
+        let progress = status.progress.unwrap();
+        assert_eq!(progress.current_table.as_deref(), Some("public.orders"));
+        assert_eq!(progress.tables_total, None);
+    }
+
+    #[tokio::test]
+    async fn test_submit_rejects_unsupported_spec_version() {
+        let body = r#"{"spec_versions":["2.0"]}"#;
+        let url = serve(vec![format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )])
+        .await;
+
+        let client = RemoteClient::new(url).unwrap();
+        let spec = JobSpec::new(
+            "init",
+            "postgresql://u@source/db".to_string(),
+            "postgresql://u@target/db".to_string(),
+            None,
+            Default::default(),
+        );
+        let err = client.submit_job(&spec).await.unwrap_err();
+        assert!(matches!(
+            crate::errors::find(&err),
+            Some(ReplicatorError::RemoteSpecUnsupported { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs() {
+        let body = r#"{"jobs":[{"job_id":"j2","status":"running","command":"init","duration_seconds":30},{"job_id":"j1","status":"completed"}]}"#;
+        let url = serve(vec![format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )])
+        .await;
+
+        let client = RemoteClient::new(url).unwrap();
+        let jobs = client.list_jobs(20, None).await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].command.as_deref(), Some("init"));
+        assert_eq!(jobs[1].duration_seconds, None);
+    }
+
+    #[tokio::test]
+    async fn test_describe_missing_job() {
+        let url = serve(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let client = RemoteClient::new(url).unwrap();
+        let err = client.describe_job("nope").await.unwrap_err();
+        assert!(err.to_string().contains("No remote job with ID 'nope'"));
+    }
 }
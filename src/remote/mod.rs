@@ -1,8 +1,12 @@
 // ABOUTME: Remote execution module for running replication jobs on AWS
-// ABOUTME: Handles job submission, status polling, and log retrieval
+// ABOUTME: Handles job submission, status polling, cost estimates, and log retrieval
 
 pub mod client;
 pub mod models;
+pub mod pricing;
 
 pub use client::RemoteClient;
-pub use models::{FilterSpec, JobResponse, JobSpec, JobStatus};
+pub use models::{
+    redact_url, FilterSpec, JobDetails, JobResponse, JobSpec, JobStatus, JobSummary,
+    WorkerCapabilities, JOB_SPEC_VERSION,
+};
@@ -0,0 +1,227 @@
+// ABOUTME: Worker size, duration, and approximate cost of a remote job, shown before submission
+// ABOUTME: Pricing tiers come from [remote_pricing] in the config file or built-in defaults
+
+use crate::migration::{self, DatabaseSizeInfo};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Time to provision a worker before the copy starts
+const PROVISIONING_TIME: Duration = Duration::from_secs(5 * 60);
+
+/// `[remote_pricing]` section of replication-config.toml
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemotePricingConfig {
+    pub tiers: Vec<PricingTierConfig>,
+}
+
+/// One worker size and its price
+#[derive(Debug, Clone, Deserialize)]
+pub struct PricingTierConfig {
+    /// Largest source size this worker is used for, e.g. "100GB"; omit for the largest worker
+    pub max_size: Option<String>,
+    pub worker: String,
+    pub hourly_usd: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PricingTier {
+    max_bytes: Option<i64>,
+    worker: String,
+    hourly_usd: f64,
+}
+
+/// Worker tiers used to estimate what a remote job costs
+///
+/// The defaults approximate on-demand compute prices for the managed
+/// service's workers; override them under `[remote_pricing]` to match your
+/// contract. Estimates never affect which worker the service picks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemotePricing {
+    tiers: Vec<PricingTier>,
+}
+
+impl Default for RemotePricing {
+    fn default() -> Self {
+        const GB: i64 = 1024 * 1024 * 1024;
+        let tier = |max_bytes, worker: &str, hourly_usd| PricingTier {
+            max_bytes,
+            worker: worker.to_string(),
+            hourly_usd,
+        };
+        RemotePricing {
+            tiers: vec![
+                tier(Some(100 * GB), "c6i.xlarge (4 vCPU, 8 GB)", 0.17),
+                tier(Some(1024 * GB), "c6i.2xlarge (8 vCPU, 16 GB)", 0.34),
+                tier(None, "c6i.4xlarge (16 vCPU, 32 GB)", 0.68),
+            ],
+        }
+    }
+}
+
+impl RemotePricing {
+    /// Build tiers from the config section, ordered from smallest to largest
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no tiers, a size does not parse, or a
+    /// price is negative.
+    pub fn from_config(config: RemotePricingConfig) -> Result<Self> {
+        if config.tiers.is_empty() {
+            bail!("[remote_pricing] needs at least one entry in `tiers`");
+        }
+        let mut tiers = config
+            .tiers
+            .into_iter()
+            .map(|tier| {
+                if tier.hourly_usd < 0.0 {
+                    bail!(
+                        "[remote_pricing] hourly_usd for '{}' cannot be negative",
+                        tier.worker
+                    );
+                }
+                let max_bytes = tier
+                    .max_size
+                    .as_deref()
+                    .map(crate::parallelism::parse_size)
+                    .transpose()
+                    .with_context(|| format!("Invalid max_size for worker '{}'", tier.worker))?;
+                Ok(PricingTier {
+                    max_bytes,
+                    worker: tier.worker,
+                    hourly_usd: tier.hourly_usd,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tiers.sort_by_key(|tier| tier.max_bytes.unwrap_or(i64::MAX));
+        Ok(RemotePricing { tiers })
+    }
+
+    /// Worker, total duration, and cost for copying `size_bytes`
+    ///
+    /// `copy_duration` comes from the size estimate; provisioning time is added.
+    /// Sources larger than every tier use the largest worker.
+    pub fn estimate(&self, size_bytes: i64, copy_duration: Duration) -> RemoteEstimate {
+        let tier = self
+            .tiers
+            .iter()
+            .find(|tier| tier.max_bytes.is_none_or(|max| size_bytes <= max))
+            .or(self.tiers.last())
+            .expect("pricing has at least one tier");
+        let duration = copy_duration + PROVISIONING_TIME;
+        RemoteEstimate {
+            worker: tier.worker.clone(),
+            duration,
+            cost_usd: duration.as_secs_f64() / 3600.0 * tier.hourly_usd,
+        }
+    }
+}
+
+/// What a remote job is expected to use
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEstimate {
+    pub worker: String,
+    pub duration: Duration,
+    pub cost_usd: f64,
+}
+
+/// Print per-database sizes and the remote estimate, then ask to proceed
+///
+/// Mirrors the confirmation `init --local` shows, with worker and cost lines.
+pub fn confirm_remote_job(sizes: &[DatabaseSizeInfo], estimate: &RemoteEstimate) -> Result<bool> {
+    let total_bytes: i64 = sizes.iter().map(|s| s.size_bytes).sum();
+
+    println!();
+    println!("{:<20} {:<12} {:<15}", "Database", "Size", "Est. Time");
+    println!("{}", "─".repeat(50));
+    for size in sizes {
+        println!(
+            "{:<20} {:<12} {:<15}",
+            size.name,
+            size.size_human,
+            migration::format_duration(size.estimated_duration)
+        );
+    }
+    println!("{}", "─".repeat(50));
+    println!("Total: {}", migration::format_bytes(total_bytes));
+    println!("Worker: {}", estimate.worker);
+    println!(
+        "Estimated duration: {} (including provisioning)",
+        migration::format_duration(estimate.duration)
+    );
+    println!("Approximate cost: ${:.2}", estimate.cost_usd);
+    println!();
+
+    print!("Submit remote replication job? [y/N]: ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read user input")?;
+
+    Ok(input.trim().to_lowercase() == "y")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: i64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_default_tiers_pick_worker_by_size() {
+        let pricing = RemotePricing::default();
+        let hour = Duration::from_secs(3600) - PROVISIONING_TIME;
+
+        let small = pricing.estimate(10 * GB, hour);
+        assert!(small.worker.starts_with("c6i.xlarge"));
+        assert_eq!(small.duration, Duration::from_secs(3600));
+        assert!((small.cost_usd - 0.17).abs() < 1e-9);
+
+        assert!(pricing
+            .estimate(500 * GB, hour)
+            .worker
+            .starts_with("c6i.2xlarge"));
+        assert!(pricing
+            .estimate(5000 * GB, hour)
+            .worker
+            .starts_with("c6i.4xlarge"));
+    }
+
+    #[test]
+    fn test_from_config_sorts_tiers_and_falls_back_to_largest() {
+        let config: RemotePricingConfig = toml::from_str(
+            r#"
+            [[tiers]]
+            max_size = "1TB"
+            worker = "large"
+            hourly_usd = 2.0
+
+            [[tiers]]
+            max_size = "50GB"
+            worker = "small"
+            hourly_usd = 1.0
+            "#,
+        )
+        .unwrap();
+        let pricing = RemotePricing::from_config(config).unwrap();
+        assert_eq!(pricing.estimate(GB, Duration::ZERO).worker, "small");
+        assert_eq!(pricing.estimate(100 * GB, Duration::ZERO).worker, "large");
+        assert_eq!(pricing.estimate(4096 * GB, Duration::ZERO).worker, "large");
+    }
+
+    #[test]
+    fn test_from_config_rejects_bad_tiers() {
+        assert!(RemotePricing::from_config(RemotePricingConfig { tiers: vec![] }).is_err());
+        let negative = RemotePricingConfig {
+            tiers: vec![PricingTierConfig {
+                max_size: None,
+                worker: "w".to_string(),
+                hourly_usd: -1.0,
+            }],
+        };
+        assert!(RemotePricing::from_config(negative).is_err());
+    }
+}
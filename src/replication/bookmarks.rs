@@ -0,0 +1,219 @@
+// ABOUTME: Named source WAL positions stored on the target, e.g. "before-deploy-1402"
+// ABOUTME: Backs the bookmark command and `status --since-bookmark`
+
+use anyhow::{bail, Context, Result};
+use tokio_postgres::Client;
+
+/// Table on the target (in the database of the target URL) holding bookmarks
+pub const BOOKMARK_TABLE: &str = "seren_replicator.bookmarks";
+
+/// A labelled source LSN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub name: String,
+    pub lsn: String,
+    /// Human-readable UTC timestamp
+    pub created_at: String,
+}
+
+/// Where a subscription's replay position stands relative to a bookmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookmarkProgress {
+    /// Every write before the bookmark is on the target; `since_bytes` of WAL
+    /// after it has been replayed too
+    Reached { since_bytes: u64 },
+    /// `remaining_bytes` of WAL before the bookmark still has to be replayed
+    Behind { remaining_bytes: u64 },
+}
+
+impl BookmarkProgress {
+    /// Compare a replay LSN with a bookmark LSN
+    ///
+    /// Returns `None` if either LSN cannot be parsed.
+    pub fn new(bookmark_lsn: &str, replay_lsn: &str) -> Option<Self> {
+        let bookmark = super::history::parse_lsn(bookmark_lsn)?;
+        let replay = super::history::parse_lsn(replay_lsn)?;
+        Some(if replay >= bookmark {
+            BookmarkProgress::Reached {
+                since_bytes: replay - bookmark,
+            }
+        } else {
+            BookmarkProgress::Behind {
+                remaining_bytes: bookmark - replay,
+            }
+        })
+    }
+}
+
+/// Check a bookmark name: non-empty, at most 63 characters, no control characters
+///
+/// # Errors
+///
+/// Returns an error describing the problem.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!("Bookmark name cannot be empty");
+    }
+    if name.chars().count() > 63 {
+        bail!("Bookmark name '{}' is longer than 63 characters", name);
+    }
+    if name.chars().any(char::is_control) {
+        bail!("Bookmark name {:?} contains control characters", name);
+    }
+    Ok(())
+}
+
+/// Create the bookmark schema and table on the target if they do not exist
+///
+/// # Errors
+///
+/// Returns an error if the target user cannot create the schema or table.
+pub async fn ensure_bookmark_table(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 name text PRIMARY KEY,
+                 lsn pg_lsn NOT NULL,
+                 created_at timestamptz NOT NULL DEFAULT now()
+             )",
+            BOOKMARK_TABLE
+        ))
+        .await
+        .context(
+            "Failed to create the bookmark table on the target.\n\
+             The target user needs CREATE privilege on the database in the target URL.",
+        )
+}
+
+/// Store a bookmark, replacing one of the same name only when `replace` is set
+///
+/// # Errors
+///
+/// Returns an error if the name is taken and `replace` is not set, or the
+/// write fails.
+pub async fn save_bookmark(client: &Client, name: &str, lsn: &str, replace: bool) -> Result<()> {
+    let conflict = if replace {
+        "ON CONFLICT (name) DO UPDATE SET lsn = EXCLUDED.lsn, created_at = now()"
+    } else {
+        "ON CONFLICT (name) DO NOTHING"
+    };
+    let written = client
+        .execute(
+            &format!(
+                "INSERT INTO {} (name, lsn) VALUES ($1, $2::text::pg_lsn) {}",
+                BOOKMARK_TABLE, conflict
+            ),
+            &[&name, &lsn],
+        )
+        .await
+        .with_context(|| format!("Failed to save bookmark '{}'", name))?;
+    if written == 0 {
+        bail!(
+            "Bookmark '{}' already exists.\n\
+             Choose another name, or pass --force to move it to the current position.",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Load all bookmarks, oldest first
+///
+/// # Returns
+///
+/// An empty list if the bookmark table does not exist yet.
+pub async fn list_bookmarks(client: &Client) -> Result<Vec<Bookmark>> {
+    let exists: bool = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&BOOKMARK_TABLE])
+        .await
+        .context("Failed to check for the bookmark table")?
+        .get(0);
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT name, lsn::text,
+                        to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                 FROM {}
+                 ORDER BY created_at, name",
+                BOOKMARK_TABLE
+            ),
+            &[],
+        )
+        .await
+        .context("Failed to read bookmarks")?;
+    Ok(rows
+        .iter()
+        .map(|row| Bookmark {
+            name: row.get(0),
+            lsn: row.get(1),
+            created_at: row.get(2),
+        })
+        .collect())
+}
+
+/// Load one bookmark by name
+///
+/// # Errors
+///
+/// Returns an error, listing the known names, if there is no such bookmark.
+pub async fn get_bookmark(client: &Client, name: &str) -> Result<Bookmark> {
+    let bookmarks = list_bookmarks(client).await?;
+    if let Some(bookmark) = bookmarks.iter().find(|b| b.name == name) {
+        return Ok(bookmark.clone());
+    }
+    if bookmarks.is_empty() {
+        bail!(
+            "Bookmark '{}' not found: no bookmarks are recorded on the target.\n\
+             Create one with 'bookmark --name {}'.",
+            name,
+            name
+        );
+    }
+    bail!(
+        "Bookmark '{}' not found. Known bookmarks: {}",
+        name,
+        bookmarks
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmark_progress() {
+        assert_eq!(
+            BookmarkProgress::new("0/3000000", "0/3000100"),
+            Some(BookmarkProgress::Reached { since_bytes: 0x100 })
+        );
+        assert_eq!(
+            BookmarkProgress::new("0/3000000", "0/3000000"),
+            Some(BookmarkProgress::Reached { since_bytes: 0 })
+        );
+        assert_eq!(
+            BookmarkProgress::new("1/0", "0/FFFFFF00"),
+            Some(BookmarkProgress::Behind {
+                remaining_bytes: 0x100
+            })
+        );
+        assert_eq!(BookmarkProgress::new("0/10", "bogus"), None);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("before-deploy-1402").is_ok());
+        assert!(validate_name("deploy 14:02").is_ok());
+        assert!(validate_name("  ").is_err());
+        assert!(validate_name("a\nb").is_err());
+        assert!(validate_name(&"x".repeat(64)).is_err());
+    }
+}
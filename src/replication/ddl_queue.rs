@@ -0,0 +1,582 @@
+// ABOUTME: Queue of source schema changes awaiting operator approval before they reach the target
+// ABOUTME: Compares table columns on both sides, generates the target DDL, and tracks each change's status
+
+use crate::utils::quote_ident;
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+/// Table on the target (in the database of the target URL) holding queued DDL
+pub const DDL_QUEUE_TABLE: &str = "seren_replicator.ddl_queue";
+
+/// A column of a user table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDef {
+    pub name: String,
+    /// As printed by `format_type`, e.g. `character varying(40)`
+    pub data_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    pub primary_key: bool,
+}
+
+/// Columns of every user table, keyed by (schema, table), in column order
+pub type SchemaSnapshot = BTreeMap<(String, String), Vec<ColumnDef>>;
+
+/// Kind of schema change found on the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdlKind {
+    CreateTable,
+    AddColumn,
+    AlterColumnType,
+    SetNotNull,
+    DropNotNull,
+    DropColumn,
+}
+
+impl DdlKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DdlKind::CreateTable => "create_table",
+            DdlKind::AddColumn => "add_column",
+            DdlKind::AlterColumnType => "alter_column_type",
+            DdlKind::SetNotNull => "set_not_null",
+            DdlKind::DropNotNull => "drop_not_null",
+            DdlKind::DropColumn => "drop_column",
+        }
+    }
+}
+
+/// A source schema change and the statement that brings the target in line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdlChange {
+    pub kind: DdlKind,
+    /// `schema.table` or `schema.table.column`
+    pub object: String,
+    pub statement: String,
+    /// Applied without approval under `--auto-apply-safe`: cannot fail on
+    /// existing rows, lose data, or block replication
+    pub safe: bool,
+}
+
+/// Lifecycle of a queued change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdlStatus {
+    Pending,
+    Applied,
+    Rejected,
+    Failed,
+}
+
+impl DdlStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DdlStatus::Pending => "pending",
+            DdlStatus::Applied => "applied",
+            DdlStatus::Rejected => "rejected",
+            DdlStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for DdlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DdlStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [
+            DdlStatus::Pending,
+            DdlStatus::Applied,
+            DdlStatus::Rejected,
+            DdlStatus::Failed,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == s)
+        .with_context(|| {
+            format!(
+                "Unknown DDL status '{}'. Expected one of: pending, applied, rejected, failed",
+                s
+            )
+        })
+    }
+}
+
+/// A change stored in [`DDL_QUEUE_TABLE`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedDdl {
+    pub id: i64,
+    pub database: String,
+    pub kind: String,
+    pub object: String,
+    pub statement: String,
+    pub safe: bool,
+    pub status: DdlStatus,
+    pub detected_at: String,
+    pub error: Option<String>,
+}
+
+/// Read the columns of every user table in the connected database
+///
+/// # Errors
+///
+/// Returns an error if the catalogs cannot be read.
+pub async fn snapshot(client: &Client) -> Result<SchemaSnapshot> {
+    let rows = client
+        .query(
+            "SELECT n.nspname::text, c.relname::text, a.attname::text,
+                    format_type(a.atttypid, a.atttypmod), a.attnotnull,
+                    pg_get_expr(d.adbin, d.adrelid),
+                    COALESCE(a.attnum = ANY(pk.conkey), false)
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+             LEFT JOIN pg_attrdef d ON d.adrelid = c.oid AND d.adnum = a.attnum
+             LEFT JOIN pg_constraint pk ON pk.conrelid = c.oid AND pk.contype = 'p'
+             WHERE c.relkind IN ('r', 'p') AND NOT c.relispartition
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast', 'seren_replicator')
+               AND n.nspname NOT LIKE 'pg_temp%'
+             ORDER BY 1, 2, a.attnum",
+            &[],
+        )
+        .await
+        .context("Failed to read table columns")?;
+    let mut snapshot = SchemaSnapshot::new();
+    for row in &rows {
+        snapshot
+            .entry((row.get(0), row.get(1)))
+            .or_default()
+            .push(ColumnDef {
+                name: row.get(2),
+                data_type: row.get(3),
+                not_null: row.get(4),
+                default: row.get(5),
+                primary_key: row.get(6),
+            });
+    }
+    Ok(snapshot)
+}
+
+/// Changes that bring `target` in line with `source`
+///
+/// Tables only on the target are left alone; dropping tables is never queued.
+pub fn diff(source: &SchemaSnapshot, target: &SchemaSnapshot) -> Vec<DdlChange> {
+    let mut changes = Vec::new();
+    for ((schema, table), source_columns) in source {
+        let qualified = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let object = format!("{}.{}", schema, table);
+        let Some(target_columns) = target.get(&(schema.clone(), table.clone())) else {
+            changes.push(DdlChange {
+                kind: DdlKind::CreateTable,
+                object,
+                statement: create_table_sql(&qualified, source_columns),
+                safe: false,
+            });
+            continue;
+        };
+
+        for column in source_columns {
+            let object = format!("{}.{}.{}", schema, table, column.name);
+            let name = quote_ident(&column.name);
+            let Some(existing) = target_columns.iter().find(|c| c.name == column.name) else {
+                changes.push(DdlChange {
+                    kind: DdlKind::AddColumn,
+                    object,
+                    statement: format!(
+                        "ALTER TABLE {} ADD COLUMN {}",
+                        qualified,
+                        column_sql(column)
+                    ),
+                    // Rows already on the target get NULL and nothing is rewritten
+                    safe: !column.not_null && column.default.is_none(),
+                });
+                continue;
+            };
+            if existing.data_type != column.data_type {
+                changes.push(DdlChange {
+                    kind: DdlKind::AlterColumnType,
+                    object: object.clone(),
+                    statement: format!(
+                        "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}::{}",
+                        qualified, name, column.data_type, name, column.data_type
+                    ),
+                    safe: false,
+                });
+            }
+            if column.not_null && !existing.not_null {
+                changes.push(DdlChange {
+                    kind: DdlKind::SetNotNull,
+                    object: object.clone(),
+                    statement: format!(
+                        "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL",
+                        qualified, name
+                    ),
+                    safe: false,
+                });
+            } else if !column.not_null && existing.not_null && !existing.primary_key {
+                changes.push(DdlChange {
+                    kind: DdlKind::DropNotNull,
+                    object,
+                    statement: format!(
+                        "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL",
+                        qualified, name
+                    ),
+                    safe: true,
+                });
+            }
+        }
+
+        for column in target_columns {
+            if !source_columns.iter().any(|c| c.name == column.name) {
+                changes.push(DdlChange {
+                    kind: DdlKind::DropColumn,
+                    object: format!("{}.{}.{}", schema, table, column.name),
+                    statement: format!(
+                        "ALTER TABLE {} DROP COLUMN {}",
+                        qualified,
+                        quote_ident(&column.name)
+                    ),
+                    safe: false,
+                });
+            }
+        }
+    }
+    changes
+}
+
+fn column_sql(column: &ColumnDef) -> String {
+    let mut sql = format!("{} {}", quote_ident(&column.name), column.data_type);
+    if let Some(default) = &column.default {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    if column.not_null {
+        sql.push_str(" NOT NULL");
+    }
+    sql
+}
+
+fn create_table_sql(qualified: &str, columns: &[ColumnDef]) -> String {
+    let mut lines: Vec<String> = columns.iter().map(column_sql).collect();
+    let key: Vec<String> = columns
+        .iter()
+        .filter(|c| c.primary_key)
+        .map(|c| quote_ident(&c.name))
+        .collect();
+    if !key.is_empty() {
+        lines.push(format!("PRIMARY KEY ({})", key.join(", ")));
+    }
+    format!(
+        "CREATE TABLE {} (\n    {}\n)",
+        qualified,
+        lines.join(",\n    ")
+    )
+}
+
+/// Create the queue schema and table on the target if they do not exist
+///
+/// # Errors
+///
+/// Returns an error if the target user cannot create the schema or table.
+pub async fn ensure_queue_table(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {} (
+                 id bigserial PRIMARY KEY,
+                 database text NOT NULL,
+                 kind text NOT NULL,
+                 object text NOT NULL,
+                 statement text NOT NULL,
+                 safe boolean NOT NULL,
+                 status text NOT NULL DEFAULT 'pending',
+                 detected_at timestamptz NOT NULL DEFAULT now(),
+                 decided_at timestamptz,
+                 error text
+             )",
+            DDL_QUEUE_TABLE
+        ))
+        .await
+        .context(
+            "Failed to create the DDL queue table on the target.\n\
+             The target user needs CREATE privilege on the database in the target URL.",
+        )
+}
+
+/// Queue a change unless the same statement is already queued and not applied
+///
+/// A rejected change stays rejected: it is not queued again while the
+/// difference persists.
+///
+/// # Returns
+///
+/// The new entry's id, or `None` if it was already queued.
+pub async fn enqueue(client: &Client, database: &str, change: &DdlChange) -> Result<Option<i64>> {
+    let row = client
+        .query_opt(
+            &format!(
+                "INSERT INTO {0} (database, kind, object, statement, safe)
+                 SELECT $1::text, $2::text, $3::text, $4::text, $5
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM {0}
+                     WHERE database = $1::text AND statement = $4::text AND status <> 'applied'
+                 )
+                 RETURNING id",
+                DDL_QUEUE_TABLE
+            ),
+            &[
+                &database,
+                &change.kind.as_str(),
+                &change.object,
+                &change.statement,
+                &change.safe,
+            ],
+        )
+        .await
+        .with_context(|| format!("Failed to queue DDL for {}", change.object))?;
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Load queued changes, oldest first, optionally only those with `status`
+///
+/// # Returns
+///
+/// An empty list if the queue table does not exist yet.
+pub async fn list(client: &Client, status: Option<DdlStatus>) -> Result<Vec<QueuedDdl>> {
+    let exists: bool = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&DDL_QUEUE_TABLE])
+        .await
+        .context("Failed to check for the DDL queue table")?
+        .get(0);
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let status = status.map(|s| s.as_str());
+    let rows = client
+        .query(
+            &format!(
+                "SELECT id, database, kind, object, statement, safe, status,
+                        to_char(detected_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'), error
+                 FROM {}
+                 WHERE $1::text IS NULL OR status = $1::text
+                 ORDER BY id",
+                DDL_QUEUE_TABLE
+            ),
+            &[&status],
+        )
+        .await
+        .context("Failed to read the DDL queue")?;
+    rows.iter()
+        .map(|row| {
+            Ok(QueuedDdl {
+                id: row.get(0),
+                database: row.get(1),
+                kind: row.get(2),
+                object: row.get(3),
+                statement: row.get(4),
+                safe: row.get(5),
+                status: row.get::<_, String>(6).parse()?,
+                detected_at: row.get(7),
+                error: row.get(8),
+            })
+        })
+        .collect()
+}
+
+/// Load one queued change
+///
+/// # Errors
+///
+/// Returns an error if no change has this id.
+pub async fn get(client: &Client, id: i64) -> Result<QueuedDdl> {
+    match list(client, None).await?.into_iter().find(|e| e.id == id) {
+        Some(entry) => Ok(entry),
+        None => bail!(
+            "No queued DDL change #{}.\n\
+             List them with: seren-replicator ddl-queue --target <url>",
+            id
+        ),
+    }
+}
+
+/// Record the outcome of a decision on a queued change
+pub async fn set_status(
+    client: &Client,
+    id: i64,
+    status: DdlStatus,
+    error: Option<&str>,
+) -> Result<()> {
+    client
+        .execute(
+            &format!(
+                "UPDATE {} SET status = $2, error = $3, decided_at = now() WHERE id = $1",
+                DDL_QUEUE_TABLE
+            ),
+            &[&id, &status.as_str(), &error],
+        )
+        .await
+        .with_context(|| format!("Failed to update queued DDL #{}", id))?;
+    Ok(())
+}
+
+/// Run a change's statement on the database it belongs to
+///
+/// New tables are picked up by every subscription in that database with
+/// `ALTER SUBSCRIPTION ... REFRESH PUBLICATION`, which also copies their rows.
+///
+/// # Errors
+///
+/// Returns an error if the statement fails; nothing is changed in that case.
+pub async fn apply(
+    client: &Client,
+    kind: &str,
+    object: &str,
+    statement: &str,
+    audit: &crate::audit::AuditSettings,
+) -> Result<()> {
+    if kind == DdlKind::DropColumn.as_str() {
+        crate::audit::record_in(audit, client, crate::audit::AuditAction::DropColumn, object).await;
+    }
+    client
+        .batch_execute(statement)
+        .await
+        .with_context(|| format!("Failed to apply DDL for {}", object))?;
+    if kind == DdlKind::CreateTable.as_str() {
+        let subscriptions: Vec<String> = client
+            .query(
+                "SELECT subname::text FROM pg_subscription
+                 WHERE subdbid = (SELECT oid FROM pg_database WHERE datname = current_database())",
+                &[],
+            )
+            .await
+            .context("Failed to list subscriptions")?
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        for subscription in subscriptions {
+            client
+                .batch_execute(&format!(
+                    "ALTER SUBSCRIPTION {} REFRESH PUBLICATION",
+                    quote_ident(&subscription)
+                ))
+                .await
+                .with_context(|| {
+                    format!(
+                        "Created {}, but refreshing subscription '{}' failed.\n\
+                         If the source publication does not include the table, add it there first, \
+                         then run ALTER SUBSCRIPTION {} REFRESH PUBLICATION.",
+                        object,
+                        subscription,
+                        quote_ident(&subscription)
+                    )
+                })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, not_null: bool) -> ColumnDef {
+        ColumnDef {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            not_null,
+            default: None,
+            primary_key: false,
+        }
+    }
+
+    fn snapshot(tables: &[(&str, Vec<ColumnDef>)]) -> SchemaSnapshot {
+        tables
+            .iter()
+            .map(|(name, columns)| (("public".to_string(), name.to_string()), columns.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_columns() {
+        let mut id = column("id", "bigint", true);
+        id.primary_key = true;
+        let mut status = column("status", "text", true);
+        status.default = Some("'new'::text".to_string());
+        let source = snapshot(&[(
+            "orders",
+            vec![
+                id.clone(),
+                column("note", "text", false),
+                status,
+                column("total", "numeric(12,2)", false),
+                column("ref", "text", false),
+            ],
+        )]);
+        let target = snapshot(&[(
+            "orders",
+            vec![
+                id,
+                column("total", "integer", false),
+                column("ref", "text", true),
+                column("legacy", "text", false),
+            ],
+        )]);
+
+        let changes: Vec<_> = diff(&source, &target)
+            .into_iter()
+            .map(|c| (c.kind, c.statement, c.safe))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    DdlKind::AddColumn,
+                    "ALTER TABLE \"public\".\"orders\" ADD COLUMN \"note\" text".to_string(),
+                    true
+                ),
+                (
+                    DdlKind::AddColumn,
+                    "ALTER TABLE \"public\".\"orders\" ADD COLUMN \"status\" text DEFAULT 'new'::text NOT NULL".to_string(),
+                    false
+                ),
+                (
+                    DdlKind::AlterColumnType,
+                    "ALTER TABLE \"public\".\"orders\" ALTER COLUMN \"total\" TYPE numeric(12,2) USING \"total\"::numeric(12,2)".to_string(),
+                    false
+                ),
+                (
+                    DdlKind::DropNotNull,
+                    "ALTER TABLE \"public\".\"orders\" ALTER COLUMN \"ref\" DROP NOT NULL".to_string(),
+                    true
+                ),
+                (
+                    DdlKind::DropColumn,
+                    "ALTER TABLE \"public\".\"orders\" DROP COLUMN \"legacy\"".to_string(),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_new_table() {
+        let mut id = column("id", "integer", true);
+        id.primary_key = true;
+        let source = snapshot(&[("events", vec![id, column("body", "jsonb", false)])]);
+        let changes = diff(&source, &SchemaSnapshot::new());
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, DdlKind::CreateTable);
+        assert!(!changes[0].safe);
+        assert_eq!(
+            changes[0].statement,
+            "CREATE TABLE \"public\".\"events\" (\n    \"id\" integer NOT NULL,\n    \"body\" jsonb,\n    PRIMARY KEY (\"id\")\n)"
+        );
+        // Tables only on the target are never dropped
+        assert!(diff(&SchemaSnapshot::new(), &source).is_empty());
+    }
+}
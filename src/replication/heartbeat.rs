@@ -0,0 +1,205 @@
+// ABOUTME: Heartbeat canary: a one-row tool-owned table written on the source and read back on the target
+// ABOUTME: Measures true end-to-end replication latency for `status --heartbeat`
+
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+use tokio_postgres::Client;
+
+/// Schema of the heartbeat table, in every replicated database
+pub const HEARTBEAT_SCHEMA: &str = "seren_replicator";
+
+/// Name of the heartbeat table
+pub const HEARTBEAT_TABLE: &str = "heartbeat";
+
+/// How often the target is polled for a beat
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether a table is the heartbeat table (skipped by verify, always published)
+pub fn is_heartbeat_table(schema: &str, table: &str) -> bool {
+    schema == HEARTBEAT_SCHEMA && table == HEARTBEAT_TABLE
+}
+
+fn qualified() -> String {
+    crate::utils::quote_qualified(HEARTBEAT_SCHEMA, HEARTBEAT_TABLE)
+}
+
+/// Create the heartbeat table if it does not exist
+///
+/// On the source (`seed`), its single row is inserted too. On the target the
+/// table is left empty so the subscription's initial copy can fill it.
+///
+/// # Errors
+///
+/// Returns an error if the user cannot create the schema or table.
+pub async fn ensure_heartbeat_table(client: &Client, seed: bool) -> Result<()> {
+    let mut sql = format!(
+        "CREATE SCHEMA IF NOT EXISTS {schema};
+         CREATE TABLE IF NOT EXISTS {table} (
+             id integer PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+             seq bigint NOT NULL,
+             written_at timestamptz NOT NULL
+         )",
+        schema = crate::utils::quote_ident(HEARTBEAT_SCHEMA),
+        table = qualified()
+    );
+    if seed {
+        sql.push_str(&format!(
+            ";\nINSERT INTO {} (id, seq, written_at) VALUES (1, 0, now()) ON CONFLICT (id) DO NOTHING",
+            qualified()
+        ));
+    }
+    client.batch_execute(&sql).await.with_context(|| {
+        format!(
+            "Failed to create the heartbeat table {}.\n\
+             The user needs CREATE privilege on the database.",
+            qualified()
+        )
+    })
+}
+
+/// Add the heartbeat table to the first of `publications` unless one already publishes it
+///
+/// # Returns
+///
+/// `true` if the table was added.
+///
+/// # Errors
+///
+/// Returns an error if the publication cannot be inspected or altered.
+pub async fn ensure_published(client: &Client, publications: &[String]) -> Result<bool> {
+    let Some(first) = publications.first() else {
+        return Ok(false);
+    };
+    let published: bool = client
+        .query_one(
+            "SELECT EXISTS (
+                 SELECT 1 FROM pg_publication_tables
+                 WHERE pubname = ANY($1) AND schemaname = $2 AND tablename = $3
+             )",
+            &[&publications, &HEARTBEAT_SCHEMA, &HEARTBEAT_TABLE],
+        )
+        .await
+        .context("Failed to check whether the heartbeat table is published")?
+        .get(0);
+    if published {
+        return Ok(false);
+    }
+    client
+        .batch_execute(&format!(
+            "ALTER PUBLICATION {} ADD TABLE {}",
+            crate::utils::quote_ident(first),
+            qualified()
+        ))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to add the heartbeat table to publication '{}'",
+                first
+            )
+        })?;
+    Ok(true)
+}
+
+/// Refresh the subscription if it does not replicate the heartbeat table yet
+///
+/// Only the heartbeat table is new to the subscription here, so the refresh
+/// copies nothing else.
+///
+/// # Errors
+///
+/// Returns an error if the subscription cannot be inspected or refreshed.
+pub async fn ensure_subscribed(client: &Client, subscription: &str) -> Result<()> {
+    let subscribed: bool = client
+        .query_one(
+            "SELECT EXISTS (
+                 SELECT 1
+                 FROM pg_subscription_rel r
+                 JOIN pg_subscription s ON s.oid = r.srsubid
+                 WHERE s.subname = $1 AND r.srrelid = to_regclass($2)
+             )",
+            &[&subscription, &qualified()],
+        )
+        .await
+        .context("Failed to check whether the subscription replicates the heartbeat table")?
+        .get(0);
+    if subscribed {
+        return Ok(());
+    }
+    client
+        .batch_execute(&format!(
+            "ALTER SUBSCRIPTION {} REFRESH PUBLICATION",
+            crate::utils::quote_ident(subscription)
+        ))
+        .await
+        .with_context(|| format!("Failed to refresh subscription '{}'", subscription))
+}
+
+/// Whether the heartbeat table exists in the connected database
+pub async fn heartbeat_exists(client: &Client) -> Result<bool> {
+    Ok(client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&qualified()])
+        .await
+        .context("Failed to check for the heartbeat table")?
+        .get(0))
+}
+
+/// Write a beat on the source and wait for it to appear on the target
+///
+/// Both timestamps come from this process's clock, so the result is not
+/// affected by clock skew between the servers.
+///
+/// # Returns
+///
+/// The time from the source commit until the beat was visible on the target,
+/// or `None` if it did not arrive within `timeout`.
+///
+/// # Errors
+///
+/// Returns an error if the beat cannot be written or the target cannot be read.
+pub async fn measure_latency(
+    source: &Client,
+    target: &Client,
+    timeout: Duration,
+) -> Result<Option<Duration>> {
+    let seq: i64 = source
+        .query_one(
+            &format!(
+                "UPDATE {} SET seq = seq + 1, written_at = clock_timestamp() WHERE id = 1 RETURNING seq",
+                qualified()
+            ),
+            &[],
+        )
+        .await
+        .context("Failed to write a heartbeat on the source; run 'sync --heartbeat' to set it up")?
+        .get(0);
+    let committed = Instant::now();
+
+    let query = format!("SELECT coalesce(max(seq), -1) FROM {}", qualified());
+    loop {
+        let arrived: i64 = target
+            .query_one(&query, &[])
+            .await
+            .context("Failed to read the heartbeat on the target")?
+            .get(0);
+        let elapsed = committed.elapsed();
+        if arrived >= seq {
+            return Ok(Some(elapsed));
+        }
+        if elapsed >= timeout {
+            return Ok(None);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_heartbeat_table() {
+        assert!(is_heartbeat_table("seren_replicator", "heartbeat"));
+        assert!(!is_heartbeat_table("public", "heartbeat"));
+        assert!(!is_heartbeat_table("seren_replicator", "status_history"));
+    }
+}
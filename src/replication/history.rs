@@ -0,0 +1,418 @@
+// ABOUTME: Persists replication lag samples on the target and summarizes lag trends
+// ABOUTME: Backs `status --record-history` and `status --history <window>`
+
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// Table on the target (in the database of the target URL) holding lag samples
+pub const HISTORY_TABLE: &str = "seren_replicator.status_history";
+
+/// A sample counts as part of a stall when the replay LSN has not moved for at
+/// least this long while the subscriber is behind
+pub const STALL_MIN_SECS: f64 = 300.0;
+
+/// One lag measurement for a database
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusSample {
+    pub database: String,
+    /// Bytes of WAL between the source's current LSN and the subscriber's replay LSN
+    pub lag_bytes: Option<i64>,
+    /// Replay lag reported by pg_stat_replication
+    pub lag_seconds: Option<f64>,
+    pub replay_lsn: Option<String>,
+    /// End-to-end latency of a heartbeat write, when `status --heartbeat` measured one
+    pub heartbeat_seconds: Option<f64>,
+}
+
+/// A stored sample with its timestamp
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryRow {
+    /// Seconds since the Unix epoch
+    pub sampled_at_epoch: f64,
+    /// Human-readable UTC timestamp
+    pub sampled_at: String,
+    pub sample: StatusSample,
+}
+
+/// A period during which replication made no progress
+#[derive(Debug, Clone, PartialEq)]
+pub struct StallPeriod {
+    pub start: String,
+    pub end: String,
+    pub duration_secs: f64,
+    pub lag_bytes: Option<i64>,
+}
+
+/// Direction of lag over the window, from the first to the last sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagTrend {
+    Shrinking,
+    Steady,
+    Growing,
+}
+
+/// Lag statistics for one database over the requested window
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySummary {
+    pub database: String,
+    pub samples: usize,
+    pub first_at: String,
+    pub last_at: String,
+    pub latest_lag_bytes: Option<i64>,
+    pub max_lag_bytes: Option<i64>,
+    pub latest_lag_seconds: Option<f64>,
+    pub max_lag_seconds: Option<f64>,
+    pub latest_heartbeat_seconds: Option<f64>,
+    pub max_heartbeat_seconds: Option<f64>,
+    pub trend: LagTrend,
+    pub stalls: Vec<StallPeriod>,
+    /// Lag in bytes per sample, oldest first (for the sparkline)
+    pub lag_series: Vec<i64>,
+}
+
+/// Create the history schema and table on the target if they do not exist
+///
+/// # Errors
+///
+/// Returns an error if the target user cannot create the schema or table.
+pub async fn ensure_history_table(client: &Client) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS seren_replicator;
+             CREATE TABLE IF NOT EXISTS {table} (
+                 sampled_at timestamptz NOT NULL DEFAULT now(),
+                 database_name text NOT NULL,
+                 lag_bytes bigint,
+                 lag_seconds double precision,
+                 replay_lsn pg_lsn
+             );
+             ALTER TABLE {table} ADD COLUMN IF NOT EXISTS heartbeat_seconds double precision;
+             CREATE INDEX IF NOT EXISTS status_history_db_time_idx
+                 ON {table} (database_name, sampled_at)",
+            table = HISTORY_TABLE
+        ))
+        .await
+        .context(
+            "Failed to create the status history table on the target.\n\
+             The target user needs CREATE privilege on the database in the target URL.",
+        )
+}
+
+/// Append one sample to the history table
+pub async fn record_sample(client: &Client, sample: &StatusSample) -> Result<()> {
+    client
+        .execute(
+            &format!(
+                "INSERT INTO {} (database_name, lag_bytes, lag_seconds, replay_lsn, heartbeat_seconds)
+                 VALUES ($1, $2, $3, $4::text::pg_lsn, $5)",
+                HISTORY_TABLE
+            ),
+            &[
+                &sample.database,
+                &sample.lag_bytes,
+                &sample.lag_seconds,
+                &sample.replay_lsn,
+                &sample.heartbeat_seconds,
+            ],
+        )
+        .await
+        .with_context(|| format!("Failed to record status sample for '{}'", sample.database))?;
+    Ok(())
+}
+
+/// Load samples newer than `window`, oldest first
+///
+/// # Returns
+///
+/// An empty list if the history table does not exist yet.
+pub async fn load_history(client: &Client, window: Duration) -> Result<Vec<HistoryRow>> {
+    let exists: bool = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&HISTORY_TABLE])
+        .await
+        .context("Failed to check for the status history table")?
+        .get(0);
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT extract(epoch FROM sampled_at)::float8,
+                        to_char(sampled_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+                        database_name, lag_bytes, lag_seconds, replay_lsn::text,
+                        heartbeat_seconds
+                 FROM {}
+                 WHERE sampled_at >= now() - make_interval(secs => $1)
+                 ORDER BY database_name, sampled_at",
+                HISTORY_TABLE
+            ),
+            &[&(window.as_secs() as f64)],
+        )
+        .await
+        .context("Failed to read status history")?;
+
+    Ok(rows
+        .iter()
+        .map(|row| HistoryRow {
+            sampled_at_epoch: row.get(0),
+            sampled_at: row.get(1),
+            sample: StatusSample {
+                database: row.get(2),
+                lag_bytes: row.get(3),
+                lag_seconds: row.get(4),
+                replay_lsn: row.get(5),
+                heartbeat_seconds: row.get(6),
+            },
+        })
+        .collect())
+}
+
+/// Summarize the samples of one database (rows must be oldest first)
+pub fn summarize(database: &str, rows: &[HistoryRow]) -> Option<HistorySummary> {
+    let first = rows.first()?;
+    let last = rows.last()?;
+
+    let lag_series: Vec<i64> = rows.iter().filter_map(|r| r.sample.lag_bytes).collect();
+    let max_of = |values: &mut dyn Iterator<Item = f64>| {
+        values.fold(None, |max: Option<f64>, v| {
+            Some(max.map_or(v, |m| m.max(v)))
+        })
+    };
+    let max_lag_seconds = max_of(&mut rows.iter().filter_map(|r| r.sample.lag_seconds));
+    let max_heartbeat_seconds = max_of(&mut rows.iter().filter_map(|r| r.sample.heartbeat_seconds));
+
+    Some(HistorySummary {
+        database: database.to_string(),
+        samples: rows.len(),
+        first_at: first.sampled_at.clone(),
+        last_at: last.sampled_at.clone(),
+        latest_lag_bytes: last.sample.lag_bytes,
+        max_lag_bytes: lag_series.iter().copied().max(),
+        latest_lag_seconds: last.sample.lag_seconds,
+        max_lag_seconds,
+        latest_heartbeat_seconds: rows.iter().rev().find_map(|r| r.sample.heartbeat_seconds),
+        max_heartbeat_seconds,
+        trend: lag_trend(&lag_series),
+        stalls: find_stalls(rows),
+        lag_series,
+    })
+}
+
+/// Compare the first and last thirds of the series to classify the trend
+fn lag_trend(series: &[i64]) -> LagTrend {
+    if series.len() < 2 {
+        return LagTrend::Steady;
+    }
+    let third = (series.len() / 3).max(1);
+    let avg = |values: &[i64]| values.iter().sum::<i64>() as f64 / values.len() as f64;
+    let early = avg(&series[..third]);
+    let late = avg(&series[series.len() - third..]);
+
+    // Ignore changes below 10% (or 1 MB) to avoid reporting noise as a trend
+    let tolerance = (early * 0.1).max(1_048_576.0);
+    if late > early + tolerance {
+        LagTrend::Growing
+    } else if late < early - tolerance {
+        LagTrend::Shrinking
+    } else {
+        LagTrend::Steady
+    }
+}
+
+/// Find runs of samples where the replay LSN stayed put while lag was non-zero
+fn find_stalls(rows: &[HistoryRow]) -> Vec<StallPeriod> {
+    let mut stalls = Vec::new();
+    let mut run_start = 0;
+
+    for idx in 1..=rows.len() {
+        let continues = idx < rows.len() && {
+            let prev = &rows[idx - 1].sample;
+            let cur = &rows[idx].sample;
+            cur.replay_lsn.is_some()
+                && cur.replay_lsn == prev.replay_lsn
+                && cur.lag_bytes.unwrap_or(0) > 0
+        };
+        if continues {
+            continue;
+        }
+
+        let start = &rows[run_start];
+        let end = &rows[idx - 1];
+        let duration_secs = end.sampled_at_epoch - start.sampled_at_epoch;
+        if idx - 1 > run_start && duration_secs >= STALL_MIN_SECS {
+            stalls.push(StallPeriod {
+                start: start.sampled_at.clone(),
+                end: end.sampled_at.clone(),
+                duration_secs,
+                lag_bytes: end.sample.lag_bytes,
+            });
+        }
+        run_start = idx;
+    }
+    stalls
+}
+
+/// Render a series as a one-line sparkline, downsampled to at most `width` points
+pub fn sparkline(series: &[i64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if series.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let bucket = series.len().div_ceil(width);
+    let points: Vec<i64> = series
+        .chunks(bucket)
+        .map(|chunk| chunk.iter().copied().max().unwrap_or(0))
+        .collect();
+    let max = points.iter().copied().max().unwrap_or(0).max(1);
+    points
+        .iter()
+        .map(|&v| BARS[((v.max(0) as f64 / max as f64) * 7.0).round() as usize])
+        .collect()
+}
+
+/// Parse a PostgreSQL LSN ("16/B374D848") into a byte position
+pub fn parse_lsn(lsn: &str) -> Option<u64> {
+    let (high, low) = lsn.split_once('/')?;
+    let high = u64::from_str_radix(high, 16).ok()?;
+    let low = u64::from_str_radix(low, 16).ok()?;
+    Some((high << 32) | low)
+}
+
+/// Parse a history window such as `30m`, `24h`, or `7d`
+///
+/// # Errors
+///
+/// Returns an error for an unknown unit or a zero/invalid amount.
+pub fn parse_window(window: &str) -> Result<Duration> {
+    let window = window.trim();
+    let split = window
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(window.len());
+    let (amount, unit) = window.split_at(split);
+    let amount: u64 = match amount.parse() {
+        Ok(amount) if amount > 0 => amount,
+        _ => bail!(
+            "Invalid history window '{}'. Use a number followed by s, m, h, or d (e.g. 24h)",
+            window
+        ),
+    };
+    let seconds = match unit {
+        "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        "d" => amount * 86_400,
+        _ => bail!(
+            "Invalid history window unit in '{}'. Use s, m, h, or d (e.g. 24h)",
+            window
+        ),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(epoch: f64, lag_bytes: i64, lsn: &str) -> HistoryRow {
+        HistoryRow {
+            sampled_at_epoch: epoch,
+            sampled_at: format!("t{}", epoch),
+            sample: StatusSample {
+                database: "app".to_string(),
+                lag_bytes: Some(lag_bytes),
+                lag_seconds: Some(lag_bytes as f64 / 1000.0),
+                replay_lsn: Some(lsn.to_string()),
+                heartbeat_seconds: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("24h").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_window("30m").unwrap(), Duration::from_secs(1_800));
+        assert_eq!(parse_window("7d").unwrap(), Duration::from_secs(604_800));
+        assert!(parse_window("0h").is_err());
+        assert!(parse_window("h").is_err());
+        assert!(parse_window("5w").is_err());
+    }
+
+    #[test]
+    fn test_parse_lsn() {
+        assert_eq!(parse_lsn("0/10"), Some(16));
+        assert_eq!(parse_lsn("1/0"), Some(1 << 32));
+        assert_eq!(parse_lsn("garbage"), None);
+    }
+
+    #[test]
+    fn test_find_stalls() {
+        let rows = vec![
+            row(0.0, 10, "0/100"),
+            row(300.0, 2_000_000, "0/200"),
+            row(600.0, 4_000_000, "0/200"),
+            row(900.0, 6_000_000, "0/200"),
+            row(1200.0, 0, "0/900"),
+        ];
+        let stalls = find_stalls(&rows);
+        assert_eq!(stalls.len(), 1);
+        assert_eq!(stalls[0].start, "t300");
+        assert_eq!(stalls[0].end, "t900");
+        assert_eq!(stalls[0].duration_secs, 600.0);
+    }
+
+    #[test]
+    fn test_summary_trend() {
+        let growing: Vec<HistoryRow> = (0..6)
+            .map(|i| row(i as f64 * 60.0, i * 10_000_000, &format!("0/{:X}", i + 1)))
+            .collect();
+        let summary = summarize("app", &growing).unwrap();
+        assert_eq!(summary.trend, LagTrend::Growing);
+        assert_eq!(summary.max_lag_bytes, Some(50_000_000));
+        assert!(summary.stalls.is_empty());
+        assert_eq!(summary.max_heartbeat_seconds, None);
+
+        let mut beats = growing.clone();
+        beats[1].sample.heartbeat_seconds = Some(2.5);
+        beats[4].sample.heartbeat_seconds = Some(0.4);
+        let summary = summarize("app", &beats).unwrap();
+        assert_eq!(summary.latest_heartbeat_seconds, Some(0.4));
+        assert_eq!(summary.max_heartbeat_seconds, Some(2.5));
+
+        assert_eq!(lag_trend(&[5_000_000, 5_100_000]), LagTrend::Steady);
+        assert_eq!(lag_trend(&[50_000_000, 0]), LagTrend::Shrinking);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 7, 14], 10), "▁▅█");
+        assert_eq!(sparkline(&[1, 2, 3, 4], 2).chars().count(), 2);
+        assert_eq!(sparkline(&[], 10), "");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_record_and_load_history() {
+        let url = std::env::var("TEST_TARGET_URL").unwrap();
+        let client = crate::postgres::connect(&url).await.unwrap();
+        ensure_history_table(&client).await.unwrap();
+        record_sample(
+            &client,
+            &StatusSample {
+                database: "history_test".to_string(),
+                lag_bytes: Some(42),
+                lag_seconds: Some(0.5),
+                replay_lsn: Some("0/16B3748".to_string()),
+                heartbeat_seconds: Some(0.25),
+            },
+        )
+        .await
+        .unwrap();
+        let rows = load_history(&client, Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert!(rows.iter().any(|r| r.sample.database == "history_test"));
+    }
+}
@@ -0,0 +1,269 @@
+// ABOUTME: Acceptable replication lag per group of databases, from the [lag_thresholds] config section
+// ABOUTME: status evaluates each group against its own limit (e.g. payments 10s, analytics 1h)
+
+use super::history::parse_window;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Group name used for databases covered only by `default`
+pub const DEFAULT_GROUP: &str = "default";
+
+/// `[lag_thresholds]` section of replication-config.toml
+///
+/// ```toml
+/// [lag_thresholds]
+/// default = "5m"
+///
+/// [[lag_thresholds.groups]]
+/// name = "payments"
+/// databases = ["payments", "billing"]
+/// max_lag = "10s"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LagThresholdsConfig {
+    /// Limit for databases outside every group; unset leaves them unchecked
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<LagGroupConfig>,
+}
+
+/// Databases sharing one lag limit
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LagGroupConfig {
+    pub name: String,
+    pub databases: Vec<String>,
+    /// e.g. `10s`, `5m`, `1h`
+    pub max_lag: String,
+}
+
+/// A validated lag group
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LagGroup {
+    name: String,
+    databases: Vec<String>,
+    max_lag: Duration,
+}
+
+/// Validated `[lag_thresholds]` settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LagThresholds {
+    default: Option<Duration>,
+    groups: Vec<LagGroup>,
+}
+
+/// How one group fared
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupLag {
+    pub name: String,
+    pub max_lag: Duration,
+    /// Databases checked, with their replay lag in seconds (`None` if unknown)
+    pub databases: Vec<(String, Option<f64>)>,
+}
+
+impl GroupLag {
+    /// Databases over the limit; a database whose lag is unknown counts as over
+    pub fn exceeded(&self) -> Vec<&str> {
+        let limit = self.max_lag.as_secs_f64();
+        self.databases
+            .iter()
+            .filter(|(_, lag)| lag.is_none_or(|lag| lag > limit))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Highest known lag in the group, in seconds
+    pub fn worst(&self) -> Option<f64> {
+        self.databases
+            .iter()
+            .filter_map(|(_, lag)| *lag)
+            .fold(None, |worst, lag| {
+                Some(worst.map_or(lag, |w: f64| w.max(lag)))
+            })
+    }
+}
+
+impl LagThresholds {
+    /// Validate a `[lag_thresholds]` config section
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an invalid duration, a duplicate or reserved group
+    /// name, an empty group, or a database listed in more than one group.
+    pub fn from_config(config: &LagThresholdsConfig) -> Result<Self> {
+        let default = config
+            .default
+            .as_deref()
+            .map(|value| parse_limit(value).context("Invalid [lag_thresholds] default"))
+            .transpose()?;
+        let mut groups: Vec<LagGroup> = Vec::with_capacity(config.groups.len());
+        for group in &config.groups {
+            if group.name == DEFAULT_GROUP {
+                bail!(
+                    "Lag group name '{}' is reserved; set the [lag_thresholds] default instead",
+                    DEFAULT_GROUP
+                );
+            }
+            if groups.iter().any(|g| g.name == group.name) {
+                bail!("Lag group '{}' is defined twice", group.name);
+            }
+            if group.databases.is_empty() {
+                bail!("Lag group '{}' lists no databases", group.name);
+            }
+            for database in &group.databases {
+                if let Some(other) = groups.iter().find(|g| g.databases.contains(database)) {
+                    bail!(
+                        "Database '{}' is listed in lag groups '{}' and '{}'",
+                        database,
+                        other.name,
+                        group.name
+                    );
+                }
+            }
+            let max_lag = parse_limit(&group.max_lag)
+                .with_context(|| format!("Invalid max_lag for lag group '{}'", group.name))?;
+            groups.push(LagGroup {
+                name: group.name.clone(),
+                databases: group.databases.clone(),
+                max_lag,
+            });
+        }
+        Ok(LagThresholds { default, groups })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.groups.is_empty()
+    }
+
+    /// The group and limit that apply to a database, if any
+    pub fn threshold_for(&self, database: &str) -> Option<(&str, Duration)> {
+        self.groups
+            .iter()
+            .find(|g| g.databases.iter().any(|d| d == database))
+            .map(|g| (g.name.as_str(), g.max_lag))
+            .or_else(|| self.default.map(|limit| (DEFAULT_GROUP, limit)))
+    }
+
+    /// Sort measured lags (seconds, `None` if unknown) into their groups
+    ///
+    /// Groups come back in config order, then the default group. Groups with
+    /// no measured database and databases with no limit are left out.
+    pub fn evaluate(&self, lags: &[(String, Option<f64>)]) -> Vec<GroupLag> {
+        let mut names: Vec<&str> = self.groups.iter().map(|g| g.name.as_str()).collect();
+        names.push(DEFAULT_GROUP);
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let mut max_lag = None;
+                let databases: Vec<(String, Option<f64>)> = lags
+                    .iter()
+                    .filter(|(database, _)| match self.threshold_for(database) {
+                        Some((group, limit)) if group == name => {
+                            max_lag = Some(limit);
+                            true
+                        }
+                        _ => false,
+                    })
+                    .cloned()
+                    .collect();
+                max_lag.map(|max_lag| GroupLag {
+                    name: name.to_string(),
+                    max_lag,
+                    databases,
+                })
+            })
+            .collect()
+    }
+}
+
+fn parse_limit(value: &str) -> Result<Duration> {
+    parse_window(value)
+        .with_context(|| format!("'{}' is not a lag limit such as 10s, 5m, or 1h", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(name: &str, databases: &[&str], max_lag: &str) -> LagGroupConfig {
+        LagGroupConfig {
+            name: name.to_string(),
+            databases: databases.iter().map(|d| d.to_string()).collect(),
+            max_lag: max_lag.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_groups_separately() {
+        let thresholds = LagThresholds::from_config(&LagThresholdsConfig {
+            default: Some("5m".to_string()),
+            groups: vec![
+                group("payments", &["payments", "billing"], "10s"),
+                group("analytics", &["analytics"], "1h"),
+            ],
+        })
+        .unwrap();
+        assert_eq!(
+            thresholds.threshold_for("billing"),
+            Some(("payments", Duration::from_secs(10)))
+        );
+        assert_eq!(
+            thresholds.threshold_for("app"),
+            Some((DEFAULT_GROUP, Duration::from_secs(300)))
+        );
+
+        let lags = vec![
+            ("payments".to_string(), Some(3.0)),
+            ("billing".to_string(), Some(42.0)),
+            ("analytics".to_string(), Some(1800.0)),
+            ("app".to_string(), None),
+        ];
+        let groups = thresholds.evaluate(&lags);
+        let names: Vec<&str> = groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["payments", "analytics", DEFAULT_GROUP]);
+        assert_eq!(groups[0].exceeded(), vec!["billing"]);
+        assert_eq!(groups[0].worst(), Some(42.0));
+        assert!(groups[1].exceeded().is_empty());
+        // Unknown lag cannot be shown to be within the limit
+        assert_eq!(groups[2].exceeded(), vec!["app"]);
+        assert_eq!(groups[2].worst(), None);
+    }
+
+    #[test]
+    fn test_without_default_ungrouped_databases_are_unchecked() {
+        let thresholds = LagThresholds::from_config(&LagThresholdsConfig {
+            default: None,
+            groups: vec![group("payments", &["payments"], "10s")],
+        })
+        .unwrap();
+        assert_eq!(thresholds.threshold_for("app"), None);
+        let groups = thresholds.evaluate(&[("app".to_string(), Some(9999.0))]);
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn test_from_config_rejects_invalid_groups() {
+        let config = |groups| LagThresholdsConfig {
+            default: None,
+            groups,
+        };
+        assert!(LagThresholds::from_config(&config(vec![group("a", &["x"], "10 s")])).is_err());
+        assert!(LagThresholds::from_config(&config(vec![group("a", &[], "10s")])).is_err());
+        assert!(
+            LagThresholds::from_config(&config(vec![group("default", &["x"], "10s")])).is_err()
+        );
+        assert!(LagThresholds::from_config(&config(vec![
+            group("a", &["x"], "10s"),
+            group("a", &["y"], "10s")
+        ]))
+        .is_err());
+        assert!(LagThresholds::from_config(&config(vec![
+            group("a", &["x"], "10s"),
+            group("b", &["x"], "1h")
+        ]))
+        .is_err());
+    }
+}
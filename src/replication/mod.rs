@@ -1,16 +1,28 @@
 // ABOUTME: Replication utilities module
 // ABOUTME: Handles PostgreSQL logical replication setup and monitoring
 
+pub mod bookmarks;
+pub mod ddl_queue;
+pub mod heartbeat;
+pub mod history;
+pub mod lag_thresholds;
+pub mod mode;
 pub mod monitor;
+pub mod new_tables;
+pub mod orphans;
 pub mod publication;
+pub mod slot_creation;
+pub mod smoke;
 pub mod subscription;
+pub mod topology;
 
+pub use mode::{clear_snapshot_only, get_replication_mode, mark_snapshot_only, ReplicationMode};
 pub use monitor::{
     get_replication_lag, get_subscription_status, is_replication_caught_up, SourceReplicationStats,
     SubscriptionStats,
 };
 pub use publication::{create_publication, drop_publication, list_publications};
 pub use subscription::{
-    create_subscription, detect_subscription_state, drop_subscription, list_subscriptions,
-    wait_for_sync, SubscriptionState,
+    apply_streaming_option, create_subscription, detect_subscription_state, drop_subscription,
+    list_subscriptions, wait_for_sync, SubscriptionState,
 };
@@ -0,0 +1,308 @@
+// ABOUTME: Records on the target how each replicated database was set up
+// ABOUTME: Marks snapshot-only copies and the last verify outcome for status and readiness
+
+use anyhow::{Context, Result};
+use tokio_postgres::Client;
+
+/// Database-level setting that records the replication mode of a target database
+pub const REPLICATION_MODE_SETTING: &str = "seren_replicator.replication_mode";
+
+/// Database-level setting that records when the snapshot-only copy completed
+pub const SNAPSHOT_AT_SETTING: &str = "seren_replicator.snapshot_completed_at";
+
+/// Value of [`REPLICATION_MODE_SETTING`] for copies without continuous replication
+pub const SNAPSHOT_ONLY: &str = "snapshot-only";
+
+/// Database-level setting that records the outcome of the last `verify` (passed/failed)
+pub const LAST_VERIFY_RESULT_SETTING: &str = "seren_replicator.last_verify_result";
+
+/// Database-level setting that records when the last `verify` finished
+pub const LAST_VERIFY_AT_SETTING: &str = "seren_replicator.last_verify_at";
+
+/// Database-level setting with a short description of the last `verify` findings
+pub const LAST_VERIFY_DETAILS_SETTING: &str = "seren_replicator.last_verify_details";
+
+/// Outcome of the last `verify` run for a target database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyRecord {
+    pub passed: bool,
+    /// Timestamp (UTC) when verify finished
+    pub verified_at: String,
+    /// Summary such as "0 table mismatch(es), 0 policy/privilege difference(s)"
+    pub details: String,
+}
+
+/// How a target database was populated, as recorded by init
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationMode {
+    /// No marker recorded (continuous replication expected, or copied by an older version)
+    Unmarked,
+    /// init ran with --no-sync; no publication, slot, or subscription exists
+    SnapshotOnly {
+        /// Timestamp (UTC) when the snapshot finished, if recorded
+        completed_at: Option<String>,
+    },
+}
+
+/// Mark a target database as a snapshot-only copy
+///
+/// Stores the marker as database-level settings (`ALTER DATABASE ... SET`) so no
+/// tables or comments are added to the user's schema.
+///
+/// # Arguments
+///
+/// * `client` - Client connected to the target cluster (any database)
+/// * `db_name` - Name of the target database to mark
+///
+/// # Errors
+///
+/// Returns an error if the database name is invalid or the settings cannot be written.
+pub async fn mark_snapshot_only(client: &Client, db_name: &str) -> Result<()> {
+    crate::utils::validate_postgres_identifier(db_name)
+        .with_context(|| format!("Invalid database name: '{}'", db_name))?;
+
+    let completed_at = utc_now(client).await?;
+
+    let db = crate::utils::quote_ident(db_name);
+    client
+        .batch_execute(&format!(
+            "ALTER DATABASE {db} SET {mode} = '{value}'; \
+             ALTER DATABASE {db} SET {at} = '{completed_at}'",
+            db = db,
+            mode = REPLICATION_MODE_SETTING,
+            value = SNAPSHOT_ONLY,
+            at = SNAPSHOT_AT_SETTING,
+            completed_at = completed_at,
+        ))
+        .await
+        .with_context(|| format!("Failed to mark database '{}' as snapshot-only", db_name))?;
+
+    Ok(())
+}
+
+/// Remove the snapshot-only marker from a target database
+///
+/// Called once continuous replication is set up for the database.
+///
+/// # Errors
+///
+/// Returns an error if the database name is invalid or the settings cannot be reset.
+pub async fn clear_snapshot_only(client: &Client, db_name: &str) -> Result<()> {
+    crate::utils::validate_postgres_identifier(db_name)
+        .with_context(|| format!("Invalid database name: '{}'", db_name))?;
+
+    let db = crate::utils::quote_ident(db_name);
+    client
+        .batch_execute(&format!(
+            "ALTER DATABASE {db} RESET {mode}; ALTER DATABASE {db} RESET {at}",
+            db = db,
+            mode = REPLICATION_MODE_SETTING,
+            at = SNAPSHOT_AT_SETTING,
+        ))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to clear snapshot-only marker on database '{}'",
+                db_name
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Record the outcome of a `verify` run on a target database
+///
+/// # Arguments
+///
+/// * `client` - Client connected to the target cluster (any database)
+/// * `db_name` - Name of the verified target database
+/// * `passed` - Whether all checks passed
+/// * `details` - Short summary of the findings
+///
+/// # Errors
+///
+/// Returns an error if the database name is invalid or the settings cannot be written.
+pub async fn record_verify_result(
+    client: &Client,
+    db_name: &str,
+    passed: bool,
+    details: &str,
+) -> Result<()> {
+    crate::utils::validate_postgres_identifier(db_name)
+        .with_context(|| format!("Invalid database name: '{}'", db_name))?;
+
+    let db = crate::utils::quote_ident(db_name);
+    client
+        .batch_execute(&format!(
+            "ALTER DATABASE {db} SET {result} = '{value}'; \
+             ALTER DATABASE {db} SET {details_setting} = {details}; \
+             ALTER DATABASE {db} SET {at} = {now}",
+            db = db,
+            result = LAST_VERIFY_RESULT_SETTING,
+            value = if passed { "passed" } else { "failed" },
+            details_setting = LAST_VERIFY_DETAILS_SETTING,
+            details = crate::utils::quote_literal(details),
+            at = LAST_VERIFY_AT_SETTING,
+            now = crate::utils::quote_literal(&utc_now(client).await?),
+        ))
+        .await
+        .with_context(|| format!("Failed to record verify result for database '{}'", db_name))?;
+
+    Ok(())
+}
+
+/// Read the outcome of the last `verify` recorded for a target database
+///
+/// # Returns
+///
+/// `None` if verify has not recorded a result for the database.
+pub async fn get_verify_result(client: &Client, db_name: &str) -> Result<Option<VerifyRecord>> {
+    let setconfig = database_settings(client, db_name).await?;
+    Ok(parse_verify_record(&setconfig))
+}
+
+fn parse_verify_record(setconfig: &[String]) -> Option<VerifyRecord> {
+    let passed = match lookup_setting(setconfig, LAST_VERIFY_RESULT_SETTING)?.as_str() {
+        "passed" => true,
+        "failed" => false,
+        _ => return None,
+    };
+    Some(VerifyRecord {
+        passed,
+        verified_at: lookup_setting(setconfig, LAST_VERIFY_AT_SETTING).unwrap_or_default(),
+        details: lookup_setting(setconfig, LAST_VERIFY_DETAILS_SETTING).unwrap_or_default(),
+    })
+}
+
+/// Current time on the server, formatted as an ISO 8601 UTC timestamp
+async fn utc_now(client: &Client) -> Result<String> {
+    let row = client
+        .query_one(
+            "SELECT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"')",
+            &[],
+        )
+        .await
+        .context("Failed to read current time from target")?;
+    Ok(row.get(0))
+}
+
+/// Read the replication mode recorded for a target database
+///
+/// # Arguments
+///
+/// * `client` - Client connected to the target cluster (any database)
+/// * `db_name` - Name of the target database to inspect
+///
+/// # Returns
+///
+/// `ReplicationMode::Unmarked` if the database does not exist or has no marker.
+pub async fn get_replication_mode(client: &Client, db_name: &str) -> Result<ReplicationMode> {
+    let setconfig = database_settings(client, db_name).await?;
+    Ok(parse_replication_mode(&setconfig))
+}
+
+/// Read the database-level settings (`ALTER DATABASE ... SET`) of a target database
+///
+/// Returns the raw `pg_db_role_setting.setconfig` entries ("name=value"), or an
+/// empty list if the database does not exist or has no settings.
+pub(crate) async fn database_settings(client: &Client, db_name: &str) -> Result<Vec<String>> {
+    let rows = client
+        .query(
+            "SELECT s.setconfig
+             FROM pg_db_role_setting s
+             JOIN pg_database d ON d.oid = s.setdatabase
+             WHERE d.datname = $1 AND s.setrole = 0",
+            &[&db_name],
+        )
+        .await
+        .with_context(|| format!("Failed to read settings for database '{}'", db_name))?;
+
+    Ok(rows
+        .first()
+        .and_then(|row| row.get::<_, Option<Vec<String>>>(0))
+        .unwrap_or_default())
+}
+
+/// Find the value of one setting in `pg_db_role_setting.setconfig` entries
+pub(crate) fn lookup_setting(setconfig: &[String], name: &str) -> Option<String> {
+    setconfig.iter().find_map(|entry| {
+        entry
+            .split_once('=')
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    })
+}
+
+/// Interpret `pg_db_role_setting.setconfig` entries ("name=value")
+fn parse_replication_mode(setconfig: &[String]) -> ReplicationMode {
+    match lookup_setting(setconfig, REPLICATION_MODE_SETTING).as_deref() {
+        Some(SNAPSHOT_ONLY) => ReplicationMode::SnapshotOnly {
+            completed_at: lookup_setting(setconfig, SNAPSHOT_AT_SETTING),
+        },
+        _ => ReplicationMode::Unmarked,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_replication_mode() {
+        assert_eq!(parse_replication_mode(&[]), ReplicationMode::Unmarked);
+
+        let config = vec![
+            "search_path=app".to_string(),
+            "seren_replicator.replication_mode=snapshot-only".to_string(),
+            "seren_replicator.snapshot_completed_at=2025-01-02T03:04:05Z".to_string(),
+        ];
+        assert_eq!(
+            parse_replication_mode(&config),
+            ReplicationMode::SnapshotOnly {
+                completed_at: Some("2025-01-02T03:04:05Z".to_string())
+            }
+        );
+
+        let other = vec!["seren_replicator.replication_mode=continuous".to_string()];
+        assert_eq!(parse_replication_mode(&other), ReplicationMode::Unmarked);
+    }
+
+    #[test]
+    fn test_parse_verify_record() {
+        assert_eq!(parse_verify_record(&[]), None);
+
+        let config = vec![
+            "seren_replicator.last_verify_result=failed".to_string(),
+            "seren_replicator.last_verify_at=2025-01-02T03:04:05Z".to_string(),
+            "seren_replicator.last_verify_details=2 table mismatch(es)".to_string(),
+        ];
+        assert_eq!(
+            parse_verify_record(&config),
+            Some(VerifyRecord {
+                passed: false,
+                verified_at: "2025-01-02T03:04:05Z".to_string(),
+                details: "2 table mismatch(es)".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_mark_and_clear_snapshot_only() {
+        let url = std::env::var("TEST_TARGET_URL").unwrap();
+        let client = crate::postgres::connect(&url).await.unwrap();
+        let db = "postgres";
+
+        mark_snapshot_only(&client, db).await.unwrap();
+        assert!(matches!(
+            get_replication_mode(&client, db).await.unwrap(),
+            ReplicationMode::SnapshotOnly { .. }
+        ));
+
+        clear_snapshot_only(&client, db).await.unwrap();
+        assert_eq!(
+            get_replication_mode(&client, db).await.unwrap(),
+            ReplicationMode::Unmarked
+        );
+    }
+}
@@ -28,6 +28,132 @@ pub struct SubscriptionStats {
     pub state: String,
 }
 
+/// Sync state of one table in a subscription, with the worker applying it
+#[derive(Debug, Clone)]
+pub struct TableSyncStats {
+    /// `schema.table`, quoted where needed
+    pub table: String,
+    /// `pg_subscription_rel.srsubstate` code (see [`describe_table_state`])
+    pub state: String,
+    /// LSN the table finished its initial sync at, once known
+    pub sync_lsn: Option<String>,
+    /// Tablesync worker while copying, the leader apply worker once ready
+    pub worker_pid: Option<i32>,
+    /// When that worker last heard from the source (UTC)
+    pub last_message_at: Option<String>,
+}
+
+/// Error counters for a subscription since its statistics were reset (PostgreSQL 15+)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionErrorCounts {
+    pub apply_errors: i64,
+    pub sync_errors: i64,
+    /// When the counters were last reset (UTC), if ever
+    pub reset_at: Option<String>,
+}
+
+/// Human-readable name for a `pg_subscription_rel.srsubstate` code
+pub fn describe_table_state(state: &str) -> &str {
+    match state {
+        "i" => "initializing",
+        "d" => "copying",
+        "f" => "copied",
+        "s" => "syncing",
+        "r" => "streaming",
+        other => other,
+    }
+}
+
+/// Get the per-table sync state of a subscription from the target database
+///
+/// Run against the subscription's own database: `pg_subscription_rel` only
+/// lists the tables of subscriptions in the connected database. Tables still
+/// copying are matched to their tablesync worker in `pg_stat_subscription`;
+/// ready tables to the leader apply worker.
+pub async fn get_table_sync_status(
+    client: &Client,
+    subscription_name: &str,
+) -> Result<Vec<TableSyncStats>> {
+    // leader_pid (PostgreSQL 16+) tells parallel apply workers apart from the
+    // leader; read through to_jsonb so older servers, without it, still work
+    let rows = client
+        .query(
+            "SELECT format('%I.%I', n.nspname, c.relname),
+                sr.srsubstate::text,
+                sr.srsublsn::text,
+                COALESCE(w.pid, leader.pid),
+                to_char(COALESCE(w.last_msg_receipt_time, leader.last_msg_receipt_time)
+                        AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+            FROM pg_subscription_rel sr
+            JOIN pg_subscription s ON s.oid = sr.srsubid
+            JOIN pg_class c ON c.oid = sr.srrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_stat_subscription w
+                ON w.subid = s.oid AND w.relid = sr.srrelid
+            LEFT JOIN LATERAL (
+                SELECT l.pid, l.last_msg_receipt_time
+                FROM pg_stat_subscription l
+                WHERE l.subid = s.oid
+                  AND l.relid IS NULL
+                  AND l.pid IS NOT NULL
+                  AND (to_jsonb(l) ->> 'leader_pid') IS NULL
+                LIMIT 1
+            ) leader ON sr.srsubstate = 'r'
+            WHERE s.subname = $1
+            ORDER BY 1",
+            &[&subscription_name],
+        )
+        .await
+        .context("Failed to query per-table subscription state")?;
+
+    Ok(rows
+        .iter()
+        .map(|row| TableSyncStats {
+            table: row.get(0),
+            state: row.get(1),
+            sync_lsn: row.get(2),
+            worker_pid: row.get(3),
+            last_message_at: row.get(4),
+        })
+        .collect())
+}
+
+/// Get a subscription's apply and sync error counters from the target database
+///
+/// Returns `None` before PostgreSQL 15, which has no `pg_stat_subscription_stats`,
+/// or if the subscription does not exist.
+pub async fn get_subscription_error_counts(
+    client: &Client,
+    subscription_name: &str,
+) -> Result<Option<SubscriptionErrorCounts>> {
+    let available: bool = client
+        .query_one(
+            "SELECT to_regclass('pg_catalog.pg_stat_subscription_stats') IS NOT NULL",
+            &[],
+        )
+        .await
+        .context("Failed to check for pg_stat_subscription_stats")?
+        .get(0);
+    if !available {
+        return Ok(None);
+    }
+    let row = client
+        .query_opt(
+            "SELECT apply_error_count, sync_error_count,
+                to_char(stats_reset AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+            FROM pg_stat_subscription_stats
+            WHERE subname = $1",
+            &[&subscription_name],
+        )
+        .await
+        .context("Failed to query subscription error counts")?;
+    Ok(row.map(|row| SubscriptionErrorCounts {
+        apply_errors: row.get(0),
+        sync_errors: row.get(1),
+        reset_at: row.get(2),
+    }))
+}
+
 /// Get replication statistics from the source database
 /// Queries pg_stat_replication to see what's being replicated to subscribers
 pub async fn get_replication_lag(
@@ -169,6 +295,13 @@ mod tests {
     use super::*;
     use crate::postgres::connect;
 
+    #[test]
+    fn test_describe_table_state() {
+        assert_eq!(describe_table_state("d"), "copying");
+        assert_eq!(describe_table_state("r"), "streaming");
+        assert_eq!(describe_table_state("x"), "x");
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_get_replication_lag() {
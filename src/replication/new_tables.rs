@@ -0,0 +1,317 @@
+// ABOUTME: Finds source tables created after a database was subscribed and brings them into replication
+// ABOUTME: Creates the target table, adds it to the publication, and refreshes the subscription to copy its rows
+
+use crate::filters::ReplicationFilter;
+use crate::replication::ddl_queue::{self, DdlKind};
+use crate::replication::orphans::SUBSCRIPTION_PREFIX;
+use crate::replication::publication::PublicationOptions;
+use crate::table_rules::TableRuleKind;
+use crate::utils::{quote_ident, quote_qualified};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use tokio_postgres::Client;
+
+/// `[onboard]` section of replication-config.toml
+///
+/// ```toml
+/// [onboard]
+/// new_tables = true
+/// require_table_approval = true
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct OnboardConfig {
+    /// Replicate tables created on the source in databases already replicating
+    #[serde(default = "default_new_tables")]
+    pub new_tables: bool,
+    /// Queue each new table's CREATE TABLE in the DDL queue and wait for
+    /// `ddl-queue --approve` before replicating it
+    #[serde(default)]
+    pub require_table_approval: bool,
+}
+
+fn default_new_tables() -> bool {
+    true
+}
+
+impl Default for OnboardConfig {
+    fn default() -> Self {
+        Self {
+            new_tables: true,
+            require_table_approval: false,
+        }
+    }
+}
+
+/// A source table matching the filter that the subscription does not replicate yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewTable {
+    pub schema: String,
+    pub table: String,
+    /// `ALTER PUBLICATION ... ADD TABLE` target; None when the publication covers all tables
+    pub publication: Option<String>,
+    /// Table and optional row filter, as written in the publication
+    pub clause: String,
+}
+
+impl NewTable {
+    /// `schema.table`, as used for objects in the DDL queue
+    pub fn object(&self) -> String {
+        format!("{}.{}", self.schema, self.table)
+    }
+}
+
+/// The sync-created subscription of one target database and its publications
+#[derive(Debug, Clone)]
+pub struct SubscriptionTables {
+    pub subscription: String,
+    /// Publication names, ungrouped first (as sync creates them)
+    pub publications: Vec<String>,
+    /// Tables the subscription already replicates, as (schema, table)
+    pub tables: BTreeSet<(String, String)>,
+}
+
+/// The sync-created subscription in the database `target` is connected to
+///
+/// # Returns
+///
+/// None when the database has no such subscription (e.g. a snapshot-only copy).
+///
+/// # Errors
+///
+/// Returns an error if the subscription catalogs cannot be read.
+pub async fn subscription_tables(target: &Client) -> Result<Option<SubscriptionTables>> {
+    let Some(row) = target
+        .query_opt(
+            "SELECT oid, subname::text, subpublications::text[] FROM pg_subscription \
+             WHERE subdbid = (SELECT oid FROM pg_database WHERE datname = current_database()) \
+               AND subname LIKE $1::text || '%' \
+             ORDER BY subname LIMIT 1",
+            &[&SUBSCRIPTION_PREFIX.replace('_', "\\_")],
+        )
+        .await
+        .context("Failed to read pg_subscription on target")?
+    else {
+        return Ok(None);
+    };
+    let oid: u32 = row.get(0);
+    let tables = target
+        .query(
+            "SELECT n.nspname::text, c.relname::text \
+             FROM pg_subscription_rel sr \
+             JOIN pg_class c ON c.oid = sr.srrelid \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE sr.srsubid = $1",
+            &[&oid],
+        )
+        .await
+        .context("Failed to read pg_subscription_rel on target")?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+    Ok(Some(SubscriptionTables {
+        subscription: row.get(1),
+        publications: row.get(2),
+        tables,
+    }))
+}
+
+/// The publication a table belongs in: its `[publication]` group's, else the ungrouped one
+fn publication_for(
+    database: &str,
+    schema: &str,
+    table: &str,
+    publications: &[String],
+    options: &PublicationOptions,
+) -> Option<String> {
+    let base = publications.first()?;
+    let grouped = options
+        .group_for(database, schema, table)
+        .map(|group| format!("{}_{}", base, group))
+        .filter(|name| publications.contains(name));
+    Some(grouped.unwrap_or_else(|| base.clone()))
+}
+
+/// Source tables matching the filter that `subscribed` does not replicate yet
+///
+/// Schema-only and transformed tables are never replicated, so they are
+/// skipped, as when the publication was created. Each table goes in the
+/// publication of its group in `publication`.
+///
+/// # Errors
+///
+/// Returns an error if the source catalogs cannot be read.
+pub async fn find_new_tables(
+    source: &Client,
+    database: &str,
+    filter: &ReplicationFilter,
+    subscribed: &SubscriptionTables,
+    publication: &PublicationOptions,
+) -> Result<Vec<NewTable>> {
+    let all_tables: bool = source
+        .query_one(
+            "SELECT COALESCE(bool_or(puballtables), false) FROM pg_publication WHERE pubname = ANY($1)",
+            &[&subscribed.publications],
+        )
+        .await
+        .context("Failed to read pg_publication on source")?
+        .get(0);
+
+    let mut found = Vec::new();
+    for table in crate::migration::list_tables(source).await? {
+        let key = (table.schema.clone(), table.name.clone());
+        if subscribed.tables.contains(&key)
+            || crate::replication::heartbeat::is_heartbeat_table(&table.schema, &table.name)
+            || !filter.should_replicate_schema_table(database, &table.schema, &table.name)
+        {
+            continue;
+        }
+        let qualified = quote_qualified(&table.schema, &table.name);
+        let clause = match filter
+            .table_rules()
+            .rule_for_table(database, &table.schema, &table.name)
+        {
+            Some(TableRuleKind::SchemaOnly) | Some(TableRuleKind::Transform(_)) => continue,
+            Some(TableRuleKind::Predicate(predicate)) => {
+                format!("{} WHERE ({})", qualified, predicate)
+            }
+            None => qualified,
+        };
+        found.push(NewTable {
+            publication: if all_tables {
+                None
+            } else {
+                publication_for(
+                    database,
+                    &table.schema,
+                    &table.name,
+                    &subscribed.publications,
+                    publication,
+                )
+            },
+            schema: table.schema,
+            table: table.name,
+            clause,
+        });
+    }
+    Ok(found)
+}
+
+/// `CREATE TABLE` for a new source table, with its columns and primary key
+///
+/// # Errors
+///
+/// Returns an error if the source catalogs cannot be read or the table is gone.
+pub async fn create_table_statement(source: &Client, table: &NewTable) -> Result<String> {
+    let snapshot = ddl_queue::snapshot(source).await?;
+    let key = (table.schema.clone(), table.table.clone());
+    let columns = snapshot
+        .get(&key)
+        .with_context(|| format!("Table {} no longer exists on the source", table.object()))?;
+    let source_only = std::iter::once((key, columns.clone())).collect();
+    ddl_queue::diff(&source_only, &Default::default())
+        .into_iter()
+        .find(|change| change.kind == DdlKind::CreateTable)
+        .map(|change| change.statement)
+        .with_context(|| format!("Could not build CREATE TABLE for {}", table.object()))
+}
+
+/// Whether the table exists in the database `target` is connected to
+///
+/// # Errors
+///
+/// Returns an error if the catalog cannot be read.
+pub async fn target_has_table(target: &Client, table: &NewTable) -> Result<bool> {
+    let row = target
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&quote_qualified(&table.schema, &table.table)],
+        )
+        .await
+        .context("Failed to look up table on target")?;
+    Ok(row.get(0))
+}
+
+/// Publish the table on the source and refresh the subscription, which copies its rows
+///
+/// The target table must exist first: the subscription stops with an error
+/// if it receives changes for a table it cannot find.
+///
+/// # Errors
+///
+/// Returns an error if the publication cannot be altered or the subscription
+/// cannot be refreshed.
+pub async fn add_to_replication(
+    source: &Client,
+    target: &Client,
+    subscription: &str,
+    table: &NewTable,
+) -> Result<()> {
+    if let Some(publication) = &table.publication {
+        source
+            .batch_execute(&format!(
+                "ALTER PUBLICATION {} ADD TABLE {}",
+                quote_ident(publication),
+                table.clause
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to add {} to publication '{}' on the source.\n\
+                     The source user must own the publication; row filters need PostgreSQL 15+.",
+                    table.object(),
+                    publication
+                )
+            })?;
+    }
+    target
+        .batch_execute(&format!(
+            "ALTER SUBSCRIPTION {} REFRESH PUBLICATION WITH (copy_data = true)",
+            quote_ident(subscription)
+        ))
+        .await
+        .with_context(|| {
+            format!(
+                "Published {}, but refreshing subscription '{}' failed.\n\
+                 Run ALTER SUBSCRIPTION {} REFRESH PUBLICATION on the target to start copying it.",
+                table.object(),
+                subscription,
+                quote_ident(subscription)
+            )
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_onboard_config_defaults() {
+        let config: OnboardConfig = toml::from_str("require_table_approval = true").unwrap();
+        assert!(config.new_tables);
+        assert!(config.require_table_approval);
+        assert_eq!(
+            publication_for(
+                "app",
+                "public",
+                "events",
+                &["seren_migration_pub_app".to_string()],
+                &PublicationOptions::default()
+            )
+            .as_deref(),
+            Some("seren_migration_pub_app")
+        );
+        assert_eq!(
+            publication_for(
+                "app",
+                "public",
+                "events",
+                &[],
+                &PublicationOptions::default()
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,197 @@
+// ABOUTME: Finds publications left on the source by failed runs, with no subscription reading them
+// ABOUTME: Publications are stamped with their creation time so cleanup --orphans spares those a running sync just made
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// Name prefix of the publications sync creates (`seren_migration_pub`, `seren_migration_pub_<db>`, ...)
+pub const PUBLICATION_PREFIX: &str = "seren_migration_pub";
+
+/// Name prefix of the subscriptions sync creates; each one's slot on the source has the same name
+pub const SUBSCRIPTION_PREFIX: &str = "seren_migration_sub";
+
+/// Publications younger than this may belong to a sync that has not created its subscription yet
+pub const DEFAULT_MIN_AGE: Duration = Duration::from_secs(3600);
+
+/// Start of the comment recorded on each publication when it is created
+const CREATED_COMMENT: &str = "seren-replicator: created";
+
+/// A tool-created publication with no replication slot of a tool-created subscription in its database
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrphanPublication {
+    pub database: String,
+    pub publication: String,
+    /// Seconds since creation; None for publications made before creation stamps were recorded
+    pub age_secs: Option<f64>,
+}
+
+impl OrphanPublication {
+    /// One-line description for logs
+    pub fn describe(&self) -> String {
+        let created = match self.age_secs {
+            Some(secs) => format!(
+                "created {} ago",
+                crate::migration::format_duration(Duration::from_secs_f64(secs.max(0.0)))
+            ),
+            None => "creation time not recorded".to_string(),
+        };
+        format!("{}.{} ({})", self.database, self.publication, created)
+    }
+}
+
+/// Record when a publication was created, as its comment
+///
+/// Failing to stamp only loses the age check in `cleanup --orphans`, so it
+/// is logged and otherwise ignored.
+pub(crate) async fn stamp_created(client: &Client, publication: &str) {
+    let result = async {
+        let row = client
+            .query_one(
+                "SELECT format('COMMENT ON PUBLICATION %I IS %L', $1::text, \
+                        $2::text || ' ' || to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') || ' UTC')",
+                &[&publication, &CREATED_COMMENT],
+            )
+            .await?;
+        let statement: String = row.get(0);
+        client.batch_execute(&statement).await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::debug!(
+            "Could not record the creation time of publication '{}': {}",
+            publication,
+            e
+        );
+    }
+}
+
+/// Whether a publication found in the catalog counts as an orphan
+fn is_orphan(name: &str, age_secs: Option<f64>, has_subscriber: bool, min_age: Duration) -> bool {
+    name.starts_with(PUBLICATION_PREFIX)
+        && !has_subscriber
+        && age_secs.is_none_or(|secs| secs >= min_age.as_secs_f64())
+}
+
+/// Orphaned publications in the database `client` is connected to
+///
+/// A publication is an orphan when its name has the tool's prefix, no logical
+/// slot named like a tool-created subscription exists in the same database,
+/// and it was created at least `min_age` ago (publications without a
+/// creation stamp predate stamping and are always old enough).
+///
+/// # Errors
+///
+/// Returns an error if the catalog queries fail.
+pub async fn find_in_database(
+    client: &Client,
+    database: &str,
+    min_age: Duration,
+) -> Result<Vec<OrphanPublication>> {
+    let rows = client
+        .query(
+            r"SELECT p.pubname::text,
+                     EXTRACT(EPOCH FROM now() - (
+                         (regexp_match(d.description, '^seren-replicator: created (\d{4}-\d\d-\d\d \d\d:\d\d:\d\d) UTC$'))[1]::timestamp
+                         AT TIME ZONE 'UTC'))::float8,
+                     EXISTS (SELECT 1 FROM pg_replication_slots s
+                             WHERE s.database = current_database()
+                               AND s.slot_type = 'logical'
+                               AND s.slot_name LIKE $1::text || '%')
+              FROM pg_publication p
+              LEFT JOIN pg_description d
+                ON d.objoid = p.oid AND d.classoid = 'pg_publication'::regclass
+              WHERE p.pubname LIKE $2::text || '%'
+              ORDER BY 1",
+            &[
+                &SUBSCRIPTION_PREFIX.replace('_', "\\_"),
+                &PUBLICATION_PREFIX.replace('_', "\\_"),
+            ],
+        )
+        .await
+        .with_context(|| format!("Failed to look for orphaned publications in '{}'", database))?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let name: String = row.get(0);
+            let age_secs: Option<f64> = row.get(1);
+            let has_subscriber: bool = row.get(2);
+            is_orphan(&name, age_secs, has_subscriber, min_age).then(|| OrphanPublication {
+                database: database.to_string(),
+                publication: name,
+                age_secs,
+            })
+        })
+        .collect())
+}
+
+/// Orphaned publications in each of the given source databases
+///
+/// # Errors
+///
+/// Returns an error if a database cannot be reached or queried.
+pub async fn find_orphans(
+    source_url: &str,
+    databases: &[String],
+    min_age: Duration,
+) -> Result<Vec<OrphanPublication>> {
+    let mut orphans = Vec::new();
+    for database in databases {
+        let url = crate::utils::replace_database_in_url(source_url, database)?;
+        let client = crate::postgres::connect(&url)
+            .await
+            .with_context(|| format!("Failed to connect to source database '{}'", database))?;
+        orphans.extend(find_in_database(&client, database, min_age).await?);
+    }
+    Ok(orphans)
+}
+
+/// Orphaned publications in every database of the source cluster
+///
+/// # Errors
+///
+/// Returns an error if the databases cannot be listed or queried.
+pub async fn find_all_orphans(
+    source_url: &str,
+    min_age: Duration,
+) -> Result<Vec<OrphanPublication>> {
+    let client = crate::postgres::connect(source_url)
+        .await
+        .context("Failed to connect to source database")?;
+    let databases: Vec<String> = crate::migration::list_databases(&client)
+        .await?
+        .into_iter()
+        .map(|db| db.name)
+        .collect();
+    find_orphans(source_url, &databases, min_age).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_orphan() {
+        let hour = DEFAULT_MIN_AGE;
+        assert!(is_orphan("seren_migration_pub", Some(7200.0), false, hour));
+        assert!(is_orphan("seren_migration_pub_app", None, false, hour));
+        // A sync may still be creating its subscription
+        assert!(!is_orphan("seren_migration_pub", Some(60.0), false, hour));
+        assert!(!is_orphan("seren_migration_pub", Some(7200.0), true, hour));
+        assert!(!is_orphan("debezium_pub", None, false, hour));
+    }
+
+    #[test]
+    fn test_describe_orphan() {
+        let orphan = OrphanPublication {
+            database: "app".to_string(),
+            publication: "seren_migration_pub_app".to_string(),
+            age_secs: None,
+        };
+        assert_eq!(
+            orphan.describe(),
+            "app.seren_migration_pub_app (creation time not recorded)"
+        );
+    }
+}
@@ -1,16 +1,179 @@
 // ABOUTME: Publication management for logical replication on source database
 // ABOUTME: Creates and manages PostgreSQL publications for table replication
 
+use crate::audit::AuditSettings;
+use crate::errors::ReplicatorError;
 use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use tokio_postgres::Client;
 
 use crate::filters::ReplicationFilter;
-use crate::table_rules::TableRuleKind;
+use crate::table_rules::{QualifiedTable, TableRuleKind};
 
-/// Create a publication for tables with optional filtering
+/// Operations a publication can send, in PostgreSQL's order
+pub const PUBLISH_OPERATIONS: [&str; 4] = ["insert", "update", "delete", "truncate"];
+
+/// `[publication]` section of replication-config.toml
+///
+/// ```toml
+/// [publication]
+/// publish = "insert,update,delete"
 ///
-/// When table filters are specified, creates a publication for only the filtered tables.
-/// Without filters, creates a publication for all tables.
+/// [[publication.groups]]
+/// name = "append_only"
+/// tables = ["events", "audit.log"]
+/// publish = "insert"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PublicationConfig {
+    /// Operations for tables outside every group; PostgreSQL's default (all) when unset
+    #[serde(default)]
+    pub publish: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<PublicationGroupConfig>,
+}
+
+/// Tables published together with their own `publish` operations
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PublicationGroupConfig {
+    pub name: String,
+    /// `table`, `schema.table`, or `database.schema.table`
+    pub tables: Vec<String>,
+    pub publish: String,
+}
+
+/// A validated publication group
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PublicationGroup {
+    name: String,
+    tables: Vec<QualifiedTable>,
+    publish: String,
+}
+
+impl PublicationGroup {
+    fn contains(&self, db_name: &str, schema: &str, table: &str) -> bool {
+        self.tables.iter().any(|t| {
+            t.database.as_deref().is_none_or(|db| db == db_name)
+                && t.schema == schema
+                && t.table == table
+        })
+    }
+}
+
+/// Validated `[publication]` settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublicationOptions {
+    publish: Option<String>,
+    groups: Vec<PublicationGroup>,
+}
+
+impl PublicationOptions {
+    /// Validate a `[publication]` config section
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for unknown operations, invalid or duplicate group
+    /// names, empty groups, or a table listed in more than one group.
+    pub fn from_config(config: &PublicationConfig) -> Result<Self> {
+        let publish = config.publish.as_deref().map(parse_publish).transpose()?;
+        let mut groups: Vec<PublicationGroup> = Vec::with_capacity(config.groups.len());
+        for group in &config.groups {
+            crate::utils::validate_postgres_identifier(&group.name)
+                .with_context(|| format!("Invalid publication group name '{}'", group.name))?;
+            if groups.iter().any(|g| g.name == group.name) {
+                bail!("Publication group '{}' is defined twice", group.name);
+            }
+            if group.tables.is_empty() {
+                bail!("Publication group '{}' lists no tables", group.name);
+            }
+            let tables = group
+                .tables
+                .iter()
+                .map(|spec| QualifiedTable::parse(spec))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("Invalid table in publication group '{}'", group.name))?;
+            for table in &tables {
+                if let Some(other) = groups.iter().find(|g| g.tables.iter().any(|t| t == table)) {
+                    bail!(
+                        "Table '{}.{}' is listed in publication groups '{}' and '{}'",
+                        table.schema,
+                        table.table,
+                        other.name,
+                        group.name
+                    );
+                }
+            }
+            let publish = parse_publish(&group.publish)
+                .with_context(|| format!("Invalid publish for group '{}'", group.name))?;
+            groups.push(PublicationGroup {
+                name: group.name.clone(),
+                tables,
+                publish,
+            });
+        }
+        Ok(PublicationOptions { publish, groups })
+    }
+
+    /// Name of the group a table is published in, if any
+    pub fn group_for(&self, db_name: &str, schema: &str, table: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|g| g.contains(db_name, schema, table))
+            .map(|g| g.name.as_str())
+    }
+}
+
+/// Normalize a `publish` list such as `"insert, update"`
+///
+/// # Errors
+///
+/// Returns an error if the list is empty or names an unknown operation.
+pub fn parse_publish(value: &str) -> Result<String> {
+    let mut operations = Vec::new();
+    for operation in value.split(',').map(|op| op.trim().to_ascii_lowercase()) {
+        if operation.is_empty() {
+            continue;
+        }
+        if !PUBLISH_OPERATIONS.contains(&operation.as_str()) {
+            bail!(
+                "Unknown publish operation '{}'. Use any of: {}",
+                operation,
+                PUBLISH_OPERATIONS.join(", ")
+            );
+        }
+        operations.push(operation);
+    }
+    if operations.is_empty() {
+        bail!(
+            "publish must list at least one of: {}",
+            PUBLISH_OPERATIONS.join(", ")
+        );
+    }
+    Ok(PUBLISH_OPERATIONS
+        .iter()
+        .filter(|op| operations.iter().any(|o| o == *op))
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+/// One publication to create: its name, `publish` setting, and table clauses
+#[derive(Debug, Default)]
+struct PlannedPublication {
+    publish: Option<String>,
+    clauses: Vec<String>,
+    has_predicates: bool,
+}
+
+/// Create the publications for a database with optional filtering
+///
+/// When table filters are specified, publishes only the filtered tables.
+/// Without filters or publication groups, creates one publication for all tables.
+/// Each group in the `[publication]` config gets its own publication, named
+/// `{publication_name}_{group}`, with the group's `publish` operations.
 ///
 /// # Arguments
 ///
@@ -18,16 +181,19 @@ use crate::table_rules::TableRuleKind;
 /// * `db_name` - Name of the database (for filtering context)
 /// * `publication_name` - Name of the publication to create
 /// * `filter` - Replication filter for table inclusion/exclusion
+/// * `options` - `[publication]` operations and groups
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` if publication is created or already exists
+/// The names of the publications that hold tables, for the subscription.
+/// Publications that already exist are left as they are.
 pub async fn create_publication(
     client: &Client,
     db_name: &str,
     publication_name: &str,
     filter: &ReplicationFilter,
-) -> Result<()> {
+    options: &PublicationOptions,
+) -> Result<Vec<String>> {
     // Validate publication name to prevent SQL injection
     crate::utils::validate_postgres_identifier(publication_name).with_context(|| {
         format!(
@@ -38,43 +204,73 @@ pub async fn create_publication(
 
     tracing::info!("Creating publication '{}'...", publication_name);
 
-    if filter.is_empty() {
-        let query = format!("CREATE PUBLICATION \"{}\" FOR ALL TABLES", publication_name);
-        return execute_publication_query(client, publication_name, &query).await;
+    if filter.is_empty() && options.groups.is_empty() {
+        let query = format!(
+            "CREATE PUBLICATION \"{}\" FOR ALL TABLES{}",
+            publication_name,
+            with_publish(options.publish.as_deref())
+        );
+        warn_default_truncate(publication_name, options.publish.as_deref(), None);
+        execute_publication_query(client, publication_name, &query).await?;
+        return Ok(vec![publication_name.to_string()]);
     }
 
     let tables = crate::migration::list_tables(client).await?;
 
-    let mut plain_tables = Vec::new();
-    let mut predicate_tables = Vec::new();
+    // Ungrouped tables first, then one publication per group
+    let mut planned: BTreeMap<String, PlannedPublication> = BTreeMap::new();
+    let mut order = vec![publication_name.to_string()];
+    planned.insert(
+        publication_name.to_string(),
+        PlannedPublication {
+            publish: options.publish.clone(),
+            ..Default::default()
+        },
+    );
+    for group in &options.groups {
+        let name = format!("{}_{}", publication_name, group.name);
+        crate::utils::validate_postgres_identifier(&name).with_context(|| {
+            format!(
+                "Publication name '{}' for group '{}' is not a valid identifier",
+                name, group.name
+            )
+        })?;
+        order.push(name.clone());
+        planned.insert(
+            name,
+            PlannedPublication {
+                publish: Some(group.publish.clone()),
+                ..Default::default()
+            },
+        );
+    }
 
     for table in tables {
-        // Build "schema.table" identifier for include/exclude logic
-        let table_identifier = if table.schema == "public" {
-            table.name.clone()
-        } else {
-            format!("{}.{}", table.schema, table.name)
-        };
-
-        if !filter.should_replicate_table(db_name, &table_identifier) {
+        // The heartbeat canary is only there to be replicated
+        if crate::replication::heartbeat::is_heartbeat_table(&table.schema, &table.name) {
+            let publication = planned
+                .get_mut(publication_name)
+                .expect("the ungrouped publication is planned");
+            publication
+                .clauses
+                .push(crate::utils::quote_qualified(&table.schema, &table.name));
+            continue;
+        }
+        if !filter.should_replicate_schema_table(db_name, &table.schema, &table.name) {
             continue;
         }
 
-        // Validate schema/table names
-        crate::utils::validate_postgres_identifier(&table.schema).with_context(|| {
-            format!(
-                "Invalid schema name '{}' for table '{}': must be a valid PostgreSQL identifier",
-                table.schema, table.name
-            )
-        })?;
-        crate::utils::validate_postgres_identifier(&table.name).with_context(|| {
-            format!(
-                "Invalid table name '{}' in schema '{}': must be a valid PostgreSQL identifier",
-                table.name, table.schema
-            )
-        })?;
-
-        let fq_table = format!("\"{}\".\"{}\"", table.schema, table.name);
+        // Catalog names may hold any character; quoting keeps them literal
+        let fq_table = crate::utils::quote_qualified(&table.schema, &table.name);
+        let target = options
+            .groups
+            .iter()
+            .find(|g| g.contains(db_name, &table.schema, &table.name))
+            .map(|g| format!("{}_{}", publication_name, g.name))
+            .unwrap_or_else(|| publication_name.to_string());
+        let publication = planned
+            .get_mut(&target)
+            .expect("every group has a planned publication");
 
         match filter
             .table_rules()
@@ -83,50 +279,87 @@ pub async fn create_publication(
             Some(TableRuleKind::SchemaOnly) => {
                 tracing::debug!(
                     "Excluding table '{}' from publication (schema-only)",
-                    table_identifier
+                    fq_table
+                );
+            }
+            Some(TableRuleKind::Transform(_)) => {
+                // Logical replication would send the untransformed rows
+                tracing::warn!(
+                    "⚠ Excluding table '{}' from publication (copied through a transform at init; changes are not replicated)",
+                    fq_table
                 );
             }
             Some(TableRuleKind::Predicate(pred)) => {
-                predicate_tables.push((fq_table, pred));
+                publication
+                    .clauses
+                    .push(format!("{} WHERE ({})", fq_table, pred));
+                publication.has_predicates = true;
             }
             None => {
-                plain_tables.push(fq_table);
+                publication.clauses.push(fq_table);
             }
         }
     }
 
-    if plain_tables.is_empty() && predicate_tables.is_empty() {
-        bail!(
-            "No tables available for publication '{}' after applying filters and schema-only rules",
-            publication_name
-        );
+    order.retain(|name| !planned[name].clauses.is_empty());
+    if order.is_empty() {
+        return Err(ReplicatorError::NoPublishableTables {
+            publication: publication_name.to_string(),
+        }
+        .into());
     }
 
-    let has_predicates = !predicate_tables.is_empty();
+    let has_predicates = order.iter().any(|name| planned[name].has_predicates);
     let server_version = get_server_version(client).await?;
     if has_predicates && server_version < 150000 {
-        bail!(
-            "Table-level predicates require PostgreSQL 15+. Detected server version {}.\n\
-             Upgrade the source database or remove --table-filter/--time-filter for logical replication.",
-            server_version
+        return Err(ReplicatorError::PredicatesRequirePg15 { server_version }.into());
+    }
+
+    for name in &order {
+        let publication = &planned[name];
+        warn_default_truncate(
+            name,
+            publication.publish.as_deref(),
+            Some(publication.clauses.len()),
+        );
+        let query = format!(
+            "CREATE PUBLICATION \"{}\" FOR TABLE {}{}",
+            name,
+            publication.clauses.join(", "),
+            with_publish(publication.publish.as_deref())
         );
+        execute_publication_query(client, name, &query).await?;
     }
 
-    let mut clauses = Vec::new();
-    clauses.extend(plain_tables);
-    clauses.extend(
-        predicate_tables
-            .iter()
-            .map(|(table, predicate)| format!("{} WHERE ({})", table, predicate)),
-    );
+    Ok(order)
+}
 
-    let query = format!(
-        "CREATE PUBLICATION \"{}\" FOR TABLE {}",
+fn with_publish(publish: Option<&str>) -> String {
+    publish
+        .map(|operations| format!(" WITH (publish = '{}')", operations))
+        .unwrap_or_default()
+}
+
+/// Point out that TRUNCATE is replicated when nobody chose to replicate it
+///
+/// A source-side `TRUNCATE`, such as a nightly staging-table reset, empties
+/// the same tables on the target.
+fn warn_default_truncate(publication_name: &str, publish: Option<&str>, tables: Option<usize>) {
+    if publish.is_some() {
+        return;
+    }
+    let scope = match tables {
+        Some(count) => format!("its {} table(s)", count),
+        None => "every table".to_string(),
+    };
+    tracing::warn!(
+        "⚠ Publication '{}' replicates TRUNCATE (PostgreSQL's default): a TRUNCATE on the source empties {} on the target",
         publication_name,
-        clauses.join(", ")
+        scope
+    );
+    tracing::warn!(
+        "  Set publish = \"insert,update,delete\" under [publication] in --config to ignore TRUNCATE"
     );
-
-    execute_publication_query(client, publication_name, &query).await
 }
 
 async fn execute_publication_query(
@@ -136,6 +369,7 @@ async fn execute_publication_query(
 ) -> Result<()> {
     match client.execute(query, &[]).await {
         Ok(_) => {
+            super::orphans::stamp_created(client, publication_name).await;
             tracing::info!("✓ Publication '{}' created successfully", publication_name);
             Ok(())
         }
@@ -180,7 +414,8 @@ async fn execute_publication_query(
     }
 }
 
-async fn get_server_version(client: &Client) -> Result<i32> {
+/// The server's `server_version_num`, e.g. 150004
+pub(crate) async fn get_server_version(client: &Client) -> Result<i32> {
     let row = client
         .query_one("SHOW server_version_num", &[])
         .await
@@ -206,8 +441,12 @@ pub async fn list_publications(client: &Client) -> Result<Vec<String>> {
     Ok(publications)
 }
 
-/// Drop a publication
-pub async fn drop_publication(client: &Client, publication_name: &str) -> Result<()> {
+/// Drop a publication, recording it in the `audit` trail
+pub async fn drop_publication(
+    client: &Client,
+    publication_name: &str,
+    audit: &AuditSettings,
+) -> Result<()> {
     // Validate publication name to prevent SQL injection
     crate::utils::validate_postgres_identifier(publication_name).with_context(|| {
         format!(
@@ -218,6 +457,13 @@ pub async fn drop_publication(client: &Client, publication_name: &str) -> Result
 
     tracing::info!("Dropping publication '{}'...", publication_name);
 
+    crate::audit::record_in(
+        audit,
+        client,
+        crate::audit::AuditAction::DropPublication,
+        publication_name,
+    )
+    .await;
     let query = format!("DROP PUBLICATION IF EXISTS \"{}\"", publication_name);
 
     client
@@ -232,6 +478,46 @@ pub async fn drop_publication(client: &Client, publication_name: &str) -> Result
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_parse_publish_normalizes() {
+        assert_eq!(
+            parse_publish("Delete, insert,UPDATE").unwrap(),
+            "insert, update, delete"
+        );
+        assert!(parse_publish("insert,upsert").is_err());
+        assert!(parse_publish(" , ").is_err());
+    }
+
+    #[test]
+    fn test_publication_options_from_config() {
+        let group = |name: &str, tables: &[&str]| PublicationGroupConfig {
+            name: name.to_string(),
+            tables: tables.iter().map(|t| t.to_string()).collect(),
+            publish: "insert".to_string(),
+        };
+        let options = PublicationOptions::from_config(&PublicationConfig {
+            publish: Some("insert,update,delete".to_string()),
+            groups: vec![group("append_only", &["events", "mydb.audit.log"])],
+        })
+        .unwrap();
+        assert_eq!(options.publish.as_deref(), Some("insert, update, delete"));
+        let append_only = &options.groups[0];
+        assert!(append_only.contains("anydb", "public", "events"));
+        assert!(append_only.contains("mydb", "audit", "log"));
+        assert!(!append_only.contains("otherdb", "audit", "log"));
+
+        assert!(PublicationOptions::from_config(&PublicationConfig {
+            publish: None,
+            groups: vec![group("a", &["events"]), group("b", &["public.events"])],
+        })
+        .is_err());
+        assert!(PublicationOptions::from_config(&PublicationConfig {
+            publish: None,
+            groups: vec![group("a", &[])],
+        })
+        .is_err());
+    }
     use crate::postgres::connect;
 
     #[tokio::test]
@@ -245,10 +531,17 @@ mod tests {
         let filter = ReplicationFilter::empty();
 
         // Clean up if exists
-        let _ = drop_publication(&client, pub_name).await;
+        let _ = drop_publication(&client, pub_name, &AuditSettings::default()).await;
 
         // Create publication
-        let result = create_publication(&client, db_name, pub_name, &filter).await;
+        let result = create_publication(
+            &client,
+            db_name,
+            pub_name,
+            &filter,
+            &PublicationOptions::default(),
+        )
+        .await;
         match &result {
             Ok(_) => println!("✓ Publication created successfully"),
             Err(e) => {
@@ -268,7 +561,9 @@ mod tests {
         assert!(pubs.contains(&pub_name.to_string()));
 
         // Clean up
-        drop_publication(&client, pub_name).await.unwrap();
+        drop_publication(&client, pub_name, &AuditSettings::default())
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -282,12 +577,18 @@ mod tests {
         let filter = ReplicationFilter::empty();
 
         // Create publication
-        create_publication(&client, db_name, pub_name, &filter)
-            .await
-            .unwrap();
+        create_publication(
+            &client,
+            db_name,
+            pub_name,
+            &filter,
+            &PublicationOptions::default(),
+        )
+        .await
+        .unwrap();
 
         // Drop it
-        let result = drop_publication(&client, pub_name).await;
+        let result = drop_publication(&client, pub_name, &AuditSettings::default()).await;
         assert!(result.is_ok());
 
         // Verify it's gone
@@ -0,0 +1,156 @@
+// ABOUTME: Safety timeout, retries, and diagnostics for creating a subscription's replication slot
+// ABOUTME: Reports the source transactions a slot is waiting on and can stop them (--cancel-blockers)
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// How long to wait for a slot, how often, and whether to stop what blocks it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotCreationOptions {
+    /// Time allowed for one CREATE SUBSCRIPTION before it is cancelled
+    pub timeout: Duration,
+    /// CREATE SUBSCRIPTION attempts before giving up
+    pub attempts: u32,
+    /// Time between reports of what the slot is waiting on
+    pub diagnostic_interval: Duration,
+    /// After a timed-out attempt, cancel or terminate the blocking source sessions
+    pub cancel_blockers: bool,
+}
+
+impl Default for SlotCreationOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(600),
+            attempts: 3,
+            diagnostic_interval: Duration::from_secs(30),
+            cancel_blockers: false,
+        }
+    }
+}
+
+/// A source transaction that started before slot creation and still holds an xid or snapshot
+///
+/// Creating a logical slot waits for every such transaction, in any database
+/// of the source cluster, to finish before it has a consistent starting point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blocker {
+    pub pid: i32,
+    pub user: String,
+    pub database: String,
+    pub state: String,
+    /// Seconds since the transaction started
+    pub xact_age_secs: f64,
+    /// Current or last query, whitespace collapsed and truncated
+    pub query: String,
+}
+
+impl Blocker {
+    /// One-line description for logs and errors
+    pub fn describe(&self) -> String {
+        format!(
+            "pid {} ({}@{}, {}, transaction open {}): {}",
+            self.pid,
+            self.user,
+            self.database,
+            self.state,
+            crate::migration::format_duration(Duration::from_secs_f64(self.xact_age_secs.max(0.0))),
+            self.query
+        )
+    }
+}
+
+/// Source transactions older than `waiting_for` that can hold up slot creation, oldest first
+///
+/// # Errors
+///
+/// Returns an error if pg_stat_activity cannot be read.
+pub async fn find_blockers(source: &Client, waiting_for: Duration) -> Result<Vec<Blocker>> {
+    let rows = source
+        .query(
+            "SELECT pid, COALESCE(usename::text, ''), COALESCE(datname::text, ''), \
+                    COALESCE(state, ''), \
+                    EXTRACT(EPOCH FROM now() - xact_start)::float8, \
+                    left(regexp_replace(COALESCE(query, ''), '\\s+', ' ', 'g'), 200) \
+             FROM pg_stat_activity \
+             WHERE pid <> pg_backend_pid() \
+               AND backend_type = 'client backend' \
+               AND xact_start IS NOT NULL \
+               AND xact_start < now() - make_interval(secs => $1) \
+               AND (backend_xid IS NOT NULL OR backend_xmin IS NOT NULL) \
+             ORDER BY xact_start \
+             LIMIT 10",
+            &[&waiting_for.as_secs_f64()],
+        )
+        .await
+        .context("Failed to read pg_stat_activity on the source")?;
+    Ok(rows
+        .iter()
+        .map(|row| Blocker {
+            pid: row.get(0),
+            user: row.get(1),
+            database: row.get(2),
+            state: row.get(3),
+            xact_age_secs: row.get(4),
+            query: row.get(5),
+        })
+        .collect())
+}
+
+/// Stop the blocking sessions: running queries are cancelled, idle transactions terminated
+///
+/// Cancelling a query aborts its transaction, which is enough for the slot;
+/// a session idle in transaction has no query to cancel, so it is terminated.
+///
+/// # Returns
+///
+/// The number of sessions signalled.
+///
+/// # Errors
+///
+/// Returns an error if the source user may not signal the sessions (needs
+/// superuser or pg_signal_backend).
+pub async fn cancel_blockers(source: &Client, blockers: &[Blocker]) -> Result<usize> {
+    let mut signalled = 0;
+    for blocker in blockers {
+        let function = if blocker.state == "active" {
+            "pg_cancel_backend"
+        } else {
+            "pg_terminate_backend"
+        };
+        let row = source
+            .query_one(&format!("SELECT {}($1)", function), &[&blocker.pid])
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to run {} on pid {}; the source user needs superuser or pg_signal_backend",
+                    function, blocker.pid
+                )
+            })?;
+        if row.get::<_, bool>(0) {
+            signalled += 1;
+            tracing::warn!("  ⚠ {} pid {}", function, blocker.pid);
+        }
+    }
+    Ok(signalled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_blocker() {
+        let blocker = Blocker {
+            pid: 4242,
+            user: "etl".to_string(),
+            database: "analytics".to_string(),
+            state: "idle in transaction".to_string(),
+            xact_age_secs: 5400.0,
+            query: "SELECT * FROM events".to_string(),
+        };
+        let description = blocker.describe();
+        assert!(description.starts_with("pid 4242 (etl@analytics, idle in transaction"));
+        assert!(description.ends_with("): SELECT * FROM events"));
+    }
+}
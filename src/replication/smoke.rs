@@ -0,0 +1,235 @@
+// ABOUTME: Smoke queries from the [[smoke_queries]] config section, run read-only against the target
+// ABOUTME: readiness and the pre-cutover stage check application-critical invariants before traffic moves
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+/// How long one smoke query may run before it counts as failed
+const STATEMENT_TIMEOUT: &str = "60s";
+
+/// One `[[smoke_queries]]` entry of replication-config.toml
+///
+/// ```toml
+/// [[smoke_queries]]
+/// name = "admin account"
+/// databases = ["app"]  # omit to run in every database
+/// sql = "SELECT id FROM users WHERE email = 'admin@example.com'"
+/// min_rows = 1         # default 1
+/// expect = "1"         # optional: first column of the first row, as text
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SmokeQuery {
+    pub name: String,
+    #[serde(default)]
+    pub databases: Vec<String>,
+    pub sql: String,
+    #[serde(default)]
+    pub min_rows: Option<u64>,
+    #[serde(default)]
+    pub expect: Option<String>,
+}
+
+impl SmokeQuery {
+    /// Whether the query runs in `database`
+    pub fn applies_to(&self, database: &str) -> bool {
+        self.databases.is_empty() || self.databases.iter().any(|d| d == database)
+    }
+
+    /// Judge the query's result: its row count and the first column of its
+    /// first row (`None` when there are no rows, `Some(None)` for NULL)
+    ///
+    /// Returns a description of the result and whether it passed.
+    pub fn evaluate(&self, rows: u64, first: Option<Option<&str>>) -> (bool, String) {
+        let min_rows = self.min_rows.unwrap_or(1);
+        if rows < min_rows {
+            return (
+                false,
+                format!("returned {} row(s), expected at least {}", rows, min_rows),
+            );
+        }
+        if let Some(expected) = &self.expect {
+            let actual = first.flatten();
+            if actual != Some(expected.as_str()) {
+                return (
+                    false,
+                    format!(
+                        "returned {}, expected '{}'",
+                        actual.map_or("NULL".to_string(), |v| format!("'{}'", v)),
+                        expected
+                    ),
+                );
+            }
+            return (true, format!("returned '{}'", expected));
+        }
+        (true, format!("returned {} row(s)", rows))
+    }
+}
+
+/// Reject smoke queries that cannot be run as written
+///
+/// # Errors
+///
+/// Returns an error for an unnamed, duplicate, or empty query.
+pub fn validate(queries: &[SmokeQuery]) -> Result<()> {
+    for (i, query) in queries.iter().enumerate() {
+        if query.name.trim().is_empty() {
+            bail!("Smoke query #{} has no name", i + 1);
+        }
+        if query.sql.trim().is_empty() {
+            bail!("Smoke query '{}' has no sql", query.name);
+        }
+        if queries[..i].iter().any(|q| q.name == query.name) {
+            bail!("Smoke query '{}' is defined twice", query.name);
+        }
+    }
+    Ok(())
+}
+
+/// Result of one smoke query in one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmokeResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Run every smoke query that applies to `database` on `client`
+///
+/// Each query must be a single statement and runs in its own read-only
+/// transaction with a statement timeout, and is rolled back, so a smoke
+/// query can never change the target. A query that fails (a missing table, a timeout) is a failed
+/// check, not an error.
+///
+/// # Errors
+///
+/// Returns an error if the read-only transaction cannot be opened.
+pub async fn run(
+    client: &Client,
+    database: &str,
+    queries: &[SmokeQuery],
+) -> Result<Vec<SmokeResult>> {
+    let mut results = Vec::new();
+    for query in queries.iter().filter(|q| q.applies_to(database)) {
+        client
+            .batch_execute(&format!(
+                "BEGIN READ ONLY; SET LOCAL statement_timeout = '{}'",
+                STATEMENT_TIMEOUT
+            ))
+            .await?;
+        // Preparing uses the extended protocol, which refuses more than one
+        // statement, so `COMMIT; DELETE ...` cannot escape the transaction
+        let outcome = match client.prepare(&query.sql).await {
+            Ok(_) => client.simple_query(&query.sql).await,
+            Err(e) => Err(e),
+        };
+        client.batch_execute("ROLLBACK").await?;
+
+        let (passed, detail) = match outcome {
+            Ok(messages) => {
+                let rows: Vec<_> = messages
+                    .iter()
+                    .filter_map(|m| match m {
+                        SimpleQueryMessage::Row(row) => Some(row),
+                        _ => None,
+                    })
+                    .collect();
+                let first = rows.first().map(|row| row.try_get(0).ok().flatten());
+                query.evaluate(rows.len() as u64, first)
+            }
+            Err(e) => (
+                false,
+                format!(
+                    "failed: {}",
+                    e.as_db_error()
+                        .map_or_else(|| e.to_string(), |db| db.message().to_string())
+                ),
+            ),
+        };
+        results.push(SmokeResult {
+            name: query.name.clone(),
+            passed,
+            detail,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(min_rows: Option<u64>, expect: Option<&str>) -> SmokeQuery {
+        SmokeQuery {
+            name: "admin".to_string(),
+            databases: vec!["app".to_string()],
+            sql: "SELECT 1".to_string(),
+            min_rows,
+            expect: expect.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_row_count_and_expected_value() {
+        assert!(query(None, None).evaluate(1, Some(Some("1"))).0);
+        assert_eq!(
+            query(None, None).evaluate(0, None),
+            (false, "returned 0 row(s), expected at least 1".to_string())
+        );
+        assert!(query(Some(0), None).evaluate(0, None).0);
+        assert!(query(None, Some("42")).evaluate(1, Some(Some("42"))).0);
+        assert_eq!(
+            query(None, Some("42")).evaluate(1, Some(Some("41"))),
+            (false, "returned '41', expected '42'".to_string())
+        );
+        assert_eq!(
+            query(None, Some("42")).evaluate(1, Some(None)),
+            (false, "returned NULL, expected '42'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_applies_to_and_validate() {
+        let scoped = query(None, None);
+        assert!(scoped.applies_to("app"));
+        assert!(!scoped.applies_to("billing"));
+        let everywhere = SmokeQuery {
+            databases: Vec::new(),
+            ..scoped.clone()
+        };
+        assert!(everywhere.applies_to("billing"));
+
+        assert!(validate(std::slice::from_ref(&scoped)).is_ok());
+        assert!(validate(&[scoped.clone(), scoped.clone()]).is_err());
+        let empty = SmokeQuery {
+            sql: " ".to_string(),
+            ..scoped
+        };
+        assert!(validate(&[empty]).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_run_refuses_multiple_statements() {
+        let url = std::env::var("TEST_TARGET_URL").unwrap();
+        let client = crate::postgres::connect(&url).await.unwrap();
+        let escape = SmokeQuery {
+            name: "escape".to_string(),
+            databases: Vec::new(),
+            sql: "COMMIT; CREATE TABLE smoke_escaped (id int)".to_string(),
+            min_rows: Some(0),
+            expect: None,
+        };
+
+        let results = run(&client, "app", &[escape]).await.unwrap();
+        assert!(!results[0].passed);
+        assert!(results[0].detail.contains("multiple commands"));
+        let escaped = client
+            .query_opt("SELECT to_regclass('smoke_escaped')::text", &[])
+            .await
+            .unwrap()
+            .and_then(|row| row.get::<_, Option<String>>(0));
+        assert_eq!(escaped, None);
+    }
+}
@@ -1,16 +1,242 @@
 // ABOUTME: Subscription management for logical replication on target database
 // ABOUTME: Creates and manages PostgreSQL subscriptions to receive replicated data
 
-use anyhow::{Context, Result};
-use std::time::Duration;
+use super::slot_creation::{self, Blocker, SlotCreationOptions};
+use crate::audit::AuditSettings;
+use crate::errors::ReplicatorError;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
 use tokio_postgres::Client;
 
-/// Create a subscription to a publication on the source database
+/// How often wait_for_sync polls subscription state on the target
+const SYNC_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Consecutive failed polls before wait_for_sync backs off
+const SYNC_POLL_FAILURE_THRESHOLD: u32 = 5;
+
+/// Poll interval while the target is failing status checks
+const SYNC_POLL_OPEN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `[subscription]` section of replication-config.toml
+///
+/// ```toml
+/// [subscription]
+/// streaming = "parallel"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SubscriptionConfig {
+    /// How in-progress transactions reach the target: off, on, or parallel
+    #[serde(default)]
+    pub streaming: Option<String>,
+}
+
+/// The subscription `streaming` option
+///
+/// - `Off`: a transaction is sent only after it commits on the source
+/// - `On` (PostgreSQL 14+): large transactions are streamed while they run and
+///   spooled to disk on the target, then applied at commit
+/// - `Parallel` (PostgreSQL 16+): streamed transactions are applied by
+///   parallel apply workers as they arrive, so one large transaction does not
+///   hold up everything behind it on the single leader apply worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamingMode {
+    Off,
+    On,
+    Parallel,
+}
+
+impl StreamingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamingMode::Off => "off",
+            StreamingMode::On => "on",
+            StreamingMode::Parallel => "parallel",
+        }
+    }
+
+    /// The closest mode a target of this `server_version_num` supports
+    pub fn supported_on(self, server_version: i32) -> StreamingMode {
+        match self {
+            StreamingMode::Parallel if server_version >= 160000 => StreamingMode::Parallel,
+            StreamingMode::Parallel | StreamingMode::On if server_version >= 140000 => {
+                StreamingMode::On
+            }
+            _ => StreamingMode::Off,
+        }
+    }
+
+    /// Read `pg_subscription.substream` as text: a boolean before PostgreSQL 16, then `f`/`t`/`p`
+    fn from_catalog(value: &str) -> Option<StreamingMode> {
+        match value {
+            "f" | "false" => Some(StreamingMode::Off),
+            "t" | "true" => Some(StreamingMode::On),
+            "p" => Some(StreamingMode::Parallel),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for StreamingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" | "false" => Ok(StreamingMode::Off),
+            "on" | "true" => Ok(StreamingMode::On),
+            "parallel" => Ok(StreamingMode::Parallel),
+            _ => bail!(
+                "Unknown subscription streaming mode '{}'. Expected one of: off, on, parallel",
+                s
+            ),
+        }
+    }
+}
+
+/// Validated `[subscription]` settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionOptions {
+    /// `None` leaves PostgreSQL's default (off before 18, parallel from 18)
+    pub streaming: Option<StreamingMode>,
+}
+
+impl SubscriptionOptions {
+    /// Validate a `[subscription]` config section
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an unknown streaming mode.
+    pub fn from_config(config: &SubscriptionConfig) -> Result<Self> {
+        Ok(SubscriptionOptions {
+            streaming: config.streaming.as_deref().map(str::parse).transpose()?,
+        })
+    }
+}
+
+/// The configured streaming mode, lowered to what the target supports
+///
+/// # Errors
+///
+/// Returns an error if the target's version cannot be read.
+async fn effective_streaming(
+    client: &Client,
+    options: &SubscriptionOptions,
+) -> Result<Option<StreamingMode>> {
+    let Some(configured) = options.streaming else {
+        return Ok(None);
+    };
+    let server_version = super::publication::get_server_version(client).await?;
+    let mode = configured.supported_on(server_version);
+    if mode != configured {
+        tracing::warn!(
+            "⚠ Target PostgreSQL {} does not support streaming = {}; using streaming = {}",
+            server_version / 10000,
+            configured.as_str(),
+            mode.as_str()
+        );
+    }
+    if mode == StreamingMode::Parallel {
+        let workers = client
+            .query_one("SHOW max_parallel_apply_workers_per_subscription", &[])
+            .await
+            .context("Failed to read max_parallel_apply_workers_per_subscription on the target")?
+            .get::<_, String>(0);
+        tracing::info!(
+            "  Parallel apply: up to {} worker(s) per subscription (max_parallel_apply_workers_per_subscription)",
+            workers
+        );
+    }
+    // Before PostgreSQL 14 the option does not exist, and off is the behavior
+    if mode == StreamingMode::Off && server_version < 140000 {
+        return Ok(None);
+    }
+    Ok(Some(mode))
+}
+
+/// `WITH (...)` clause for CREATE SUBSCRIPTION, or empty when nothing is set
+fn with_clause(two_phase: bool, streaming: Option<StreamingMode>) -> String {
+    let mut options = Vec::new();
+    if two_phase {
+        options.push("two_phase = true".to_string());
+    }
+    if let Some(mode) = streaming {
+        options.push(format!("streaming = {}", mode.as_str()));
+    }
+    if options.is_empty() {
+        String::new()
+    } else {
+        format!(" WITH ({})", options.join(", "))
+    }
+}
+
+/// Bring an existing subscription's `streaming` option in line with `[subscription]`
+///
+/// Unlike most subscription options, streaming can be changed in place with
+/// ALTER SUBSCRIPTION; the apply worker restarts but nothing is recopied.
+///
+/// # Errors
+///
+/// Returns an error if the subscription cannot be read or altered.
+pub async fn apply_streaming_option(
+    client: &Client,
+    subscription_name: &str,
+    options: &SubscriptionOptions,
+) -> Result<()> {
+    let Some(mode) = effective_streaming(client, options).await? else {
+        return Ok(());
+    };
+    let row = client
+        .query_opt(
+            "SELECT substream::text FROM pg_subscription WHERE subname = $1",
+            &[&subscription_name],
+        )
+        .await
+        .with_context(|| format!("Failed to look up subscription '{}'", subscription_name))?;
+    let Some(row) = row else {
+        return Ok(());
+    };
+    if StreamingMode::from_catalog(row.get::<_, &str>(0)) == Some(mode) {
+        return Ok(());
+    }
+    client
+        .batch_execute(&format!(
+            "ALTER SUBSCRIPTION \"{}\" SET (streaming = {})",
+            subscription_name,
+            mode.as_str()
+        ))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to set streaming = {} on subscription '{}'",
+                mode.as_str(),
+                subscription_name
+            )
+        })?;
+    tracing::info!(
+        "✓ Subscription '{}' now uses streaming = {}",
+        subscription_name,
+        mode.as_str()
+    );
+    Ok(())
+}
+
+/// Create a subscription to one or more publications on the source database
+///
+/// With `two_phase`, prepared transactions are replicated at `PREPARE
+/// TRANSACTION` instead of at `COMMIT PREPARED` (PostgreSQL 15+ on both ends).
+/// The `streaming` option comes from `options` (the `[subscription]` config
+/// section); creating the slot is bounded by `slot_creation`.
 pub async fn create_subscription(
     client: &Client,
     subscription_name: &str,
     source_connection_string: &str,
-    publication_name: &str,
+    publication_names: &[String],
+    two_phase: bool,
+    options: &SubscriptionOptions,
+    slot_creation: &SlotCreationOptions,
 ) -> Result<()> {
     // Validate subscription name to prevent SQL injection
     crate::utils::validate_postgres_identifier(subscription_name).with_context(|| {
@@ -20,13 +246,20 @@ pub async fn create_subscription(
         )
     })?;
 
-    // Validate publication name to prevent SQL injection
-    crate::utils::validate_postgres_identifier(publication_name).with_context(|| {
-        format!(
-            "Invalid publication name '{}': must be a valid PostgreSQL identifier",
-            publication_name
-        )
-    })?;
+    // Validate publication names to prevent SQL injection
+    for publication_name in publication_names {
+        crate::utils::validate_postgres_identifier(publication_name).with_context(|| {
+            format!(
+                "Invalid publication name '{}': must be a valid PostgreSQL identifier",
+                publication_name
+            )
+        })?;
+    }
+    let publication_list = publication_names
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<_>>()
+        .join(", ");
 
     tracing::info!("Creating subscription '{}'...", subscription_name);
 
@@ -50,12 +283,24 @@ pub async fn create_subscription(
         "  To avoid storing passwords, configure .pgpass on the target PostgreSQL server"
     );
 
+    let streaming = effective_streaming(client, options).await?;
     let query = format!(
-        "CREATE SUBSCRIPTION \"{}\" CONNECTION '{}' PUBLICATION \"{}\"",
-        subscription_name, source_connection_string, publication_name
+        "CREATE SUBSCRIPTION \"{}\" CONNECTION '{}' PUBLICATION {}{}",
+        subscription_name,
+        source_connection_string,
+        publication_list,
+        with_clause(two_phase, streaming)
     );
 
-    match client.execute(&query, &[]).await {
+    match execute_with_slot_timeout(
+        client,
+        &query,
+        subscription_name,
+        source_connection_string,
+        slot_creation,
+    )
+    .await?
+    {
         Ok(_) => {
             tracing::info!(
                 "✓ Subscription '{}' created successfully",
@@ -71,49 +316,32 @@ pub async fn create_subscription(
                 Ok(())
             } else if err_str.contains("permission denied") || err_str.contains("must be superuser")
             {
-                anyhow::bail!(
-                    "Permission denied: Cannot create subscription '{}'.\n\
-                     Only superusers can create subscriptions in PostgreSQL.\n\
-                     Contact your database administrator to:\n\
-                     1. Grant superuser: ALTER ROLE <user> WITH SUPERUSER;\n\
-                     2. Or create the subscription on your behalf\n\
-                     Error: {}",
-                    subscription_name,
-                    err_str
-                )
+                Err(ReplicatorError::SubscriptionPermissionDenied {
+                    subscription: subscription_name.to_string(),
+                    detail: err_str,
+                }
+                .into())
             } else if err_str.contains("publication") && err_str.contains("does not exist") {
-                anyhow::bail!(
-                    "Publication does not exist: Cannot create subscription '{}'.\n\
-                     The publication '{}' was not found on the source database.\n\
-                     Make sure the publication exists before creating the subscription.\n\
-                     Error: {}",
-                    subscription_name,
-                    publication_name,
-                    err_str
-                )
+                Err(ReplicatorError::PublicationMissing {
+                    subscription: subscription_name.to_string(),
+                    publication: publication_names.join(", "),
+                    detail: err_str,
+                }
+                .into())
             } else if err_str.contains("could not connect to the publisher")
                 || err_str.contains("connection")
             {
-                anyhow::bail!(
-                    "Connection failed: Cannot connect to source database for subscription '{}'.\n\
-                     Please verify:\n\
-                     - The source database is accessible from the target\n\
-                     - The connection string is correct\n\
-                     - Firewall rules allow connections\n\
-                     - The source user has REPLICATION privilege\n\
-                     Error: {}",
-                    subscription_name,
-                    err_str
-                )
+                Err(ReplicatorError::PublisherUnreachable {
+                    subscription: subscription_name.to_string(),
+                    detail: err_str,
+                }
+                .into())
             } else if err_str.contains("replication slot") {
-                anyhow::bail!(
-                    "Replication slot error: Cannot create subscription '{}'.\n\
-                     The source database may have reached the maximum number of replication slots.\n\
-                     Check 'max_replication_slots' on the source database.\n\
-                     Error: {}",
-                    subscription_name,
-                    err_str
-                )
+                Err(ReplicatorError::ReplicationSlotsExhausted {
+                    subscription: subscription_name.to_string(),
+                    detail: err_str,
+                }
+                .into())
             } else {
                 anyhow::bail!(
                     "Failed to create subscription '{}': {}\n\
@@ -131,6 +359,152 @@ pub async fn create_subscription(
     }
 }
 
+/// Run CREATE SUBSCRIPTION under the slot creation timeout, retrying attempts that time out
+///
+/// Creating the slot on the source waits for every transaction already
+/// running there to finish. While it waits, the blocking transactions are
+/// reported every diagnostic interval; with `cancel_blockers` they are stopped
+/// after a timed-out attempt so the next attempt can succeed.
+///
+/// # Returns
+///
+/// The result of the first attempt that did not time out.
+///
+/// # Errors
+///
+/// Returns [`ReplicatorError::SlotCreationTimedOut`] once every attempt timed
+/// out, or an error if the timeout cannot be set or blockers cannot be stopped.
+async fn execute_with_slot_timeout(
+    client: &Client,
+    query: &str,
+    subscription_name: &str,
+    source_connection_string: &str,
+    options: &SlotCreationOptions,
+) -> Result<std::result::Result<u64, tokio_postgres::Error>> {
+    let attempts = options.attempts.max(1);
+    let mut source: Option<Client> = None;
+    let mut blockers = Vec::new();
+
+    for attempt in 1..=attempts {
+        client
+            .batch_execute(&format!(
+                "SET statement_timeout = {}",
+                options.timeout.as_millis()
+            ))
+            .await
+            .context("Failed to set the slot creation timeout on the target")?;
+        let started = Instant::now();
+        let result = {
+            let execute = client.execute(query, &[]);
+            tokio::pin!(execute);
+            let mut ticker = tokio::time::interval_at(
+                tokio::time::Instant::now() + options.diagnostic_interval,
+                options.diagnostic_interval,
+            );
+            loop {
+                tokio::select! {
+                    result = &mut execute => break result,
+                    _ = ticker.tick() => {
+                        report_blockers(&mut source, source_connection_string, started.elapsed()).await;
+                    }
+                }
+            }
+        };
+        client
+            .batch_execute("RESET statement_timeout")
+            .await
+            .context("Failed to reset statement_timeout on the target")?;
+
+        match result {
+            Err(e) if e.code() == Some(&SqlState::QUERY_CANCELED) => {
+                tracing::warn!(
+                    "⚠ Attempt {}/{}: replication slot for '{}' was not created within {}",
+                    attempt,
+                    attempts,
+                    subscription_name,
+                    crate::migration::format_duration(options.timeout)
+                );
+                blockers =
+                    report_blockers(&mut source, source_connection_string, started.elapsed()).await;
+                if options.cancel_blockers && attempt < attempts && !blockers.is_empty() {
+                    if let Some(source) = &source {
+                        tracing::warn!(
+                            "⚠ --cancel-blockers: stopping {} blocking source session(s)",
+                            blockers.len()
+                        );
+                        slot_creation::cancel_blockers(source, &blockers).await?;
+                    }
+                }
+            }
+            other => return Ok(other),
+        }
+    }
+
+    Err(ReplicatorError::SlotCreationTimedOut {
+        subscription: subscription_name.to_string(),
+        attempts,
+        timeout: crate::migration::format_duration(options.timeout),
+        blockers: if blockers.is_empty() {
+            "no long-running transaction found in pg_stat_activity (check pg_prepared_xacts)"
+                .to_string()
+        } else {
+            blockers
+                .iter()
+                .map(|b| b.describe())
+                .collect::<Vec<_>>()
+                .join("; ")
+        },
+    }
+    .into())
+}
+
+/// Log the source transactions slot creation is waiting on
+///
+/// Connects to the source on first use. Diagnostics are best effort: a
+/// failure is logged at debug level and reported as no blockers.
+async fn report_blockers(
+    source: &mut Option<Client>,
+    source_connection_string: &str,
+    waiting_for: Duration,
+) -> Vec<Blocker> {
+    if source.is_none() {
+        match crate::postgres::connect(source_connection_string).await {
+            Ok(client) => *source = Some(client),
+            Err(e) => {
+                tracing::debug!("Cannot connect to the source for slot diagnostics: {:#}", e);
+                return Vec::new();
+            }
+        }
+    }
+    let Some(client) = source.as_ref() else {
+        return Vec::new();
+    };
+    match slot_creation::find_blockers(client, waiting_for).await {
+        Ok(blockers) => {
+            if blockers.is_empty() {
+                tracing::info!(
+                    "  Still creating the replication slot after {}; no long-running source transactions found",
+                    crate::migration::format_duration(waiting_for)
+                );
+            } else {
+                tracing::warn!(
+                    "  ⚠ Replication slot waiting {} on {} source transaction(s):",
+                    crate::migration::format_duration(waiting_for),
+                    blockers.len()
+                );
+                for blocker in &blockers {
+                    tracing::warn!("    {}", blocker.describe());
+                }
+            }
+            blockers
+        }
+        Err(e) => {
+            tracing::debug!("Slot diagnostics failed: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
 /// List all subscriptions in the database
 pub async fn list_subscriptions(client: &Client) -> Result<Vec<String>> {
     let rows = client
@@ -143,8 +517,12 @@ pub async fn list_subscriptions(client: &Client) -> Result<Vec<String>> {
     Ok(subscriptions)
 }
 
-/// Drop a subscription
-pub async fn drop_subscription(client: &Client, subscription_name: &str) -> Result<()> {
+/// Drop a subscription, recording it in the `audit` trail
+pub async fn drop_subscription(
+    client: &Client,
+    subscription_name: &str,
+    audit: &AuditSettings,
+) -> Result<()> {
     // Validate subscription name to prevent SQL injection
     crate::utils::validate_postgres_identifier(subscription_name).with_context(|| {
         format!(
@@ -155,6 +533,35 @@ pub async fn drop_subscription(client: &Client, subscription_name: &str) -> Resu
 
     tracing::info!("Dropping subscription '{}'...", subscription_name);
 
+    // Dropping the subscription also drops its replication slot on the source
+    let slot = client
+        .query_opt(
+            "SELECT subslotname::text FROM pg_subscription WHERE subname = $1",
+            &[&subscription_name],
+        )
+        .await
+        .context(format!(
+            "Failed to look up subscription '{}'",
+            subscription_name
+        ))?;
+    if let Some(row) = slot {
+        crate::audit::record_in(
+            audit,
+            client,
+            crate::audit::AuditAction::DropSubscription,
+            subscription_name,
+        )
+        .await;
+        if let Some(slot) = row.get::<_, Option<String>>(0) {
+            crate::audit::record_in(
+                audit,
+                client,
+                crate::audit::AuditAction::DropReplicationSlot,
+                &format!("{} (on the source)", slot),
+            )
+            .await;
+        }
+    }
     let query = format!("DROP SUBSCRIPTION IF EXISTS \"{}\"", subscription_name);
 
     client.execute(&query, &[]).await.context(format!(
@@ -217,6 +624,10 @@ pub async fn detect_subscription_state(
 
 /// Wait for subscription to complete initial sync and enter streaming state
 /// Returns when subscription reaches 'r' (ready/streaming) state
+///
+/// Transient status-query failures are tolerated. After several consecutive
+/// failures a circuit breaker opens, an alert is logged, and polling backs off to a
+/// long interval until the target answers again or the timeout expires.
 pub async fn wait_for_sync(
     client: &Client,
     subscription_name: &str,
@@ -230,19 +641,62 @@ pub async fn wait_for_sync(
     let start = std::time::Instant::now();
     let timeout = Duration::from_secs(timeout_secs);
 
+    let mut breaker =
+        crate::retry::CircuitBreaker::new(SYNC_POLL_FAILURE_THRESHOLD, SYNC_POLL_OPEN_INTERVAL);
+
     loop {
-        let row = client
+        let poll = client
             .query_one(
                 "SELECT srsubstate FROM pg_stat_subscription WHERE subname = $1",
                 &[&subscription_name],
             )
-            .await
-            .context(format!(
-                "Failed to query subscription status for '{}'",
-                subscription_name
-            ))?;
-
-        let state: String = row.get(0);
+            .await;
+
+        let state: String = match poll {
+            Ok(row) => {
+                if breaker.record_success() {
+                    tracing::info!(
+                        "✓ Target is reachable again, resuming sync monitoring for '{}'",
+                        subscription_name
+                    );
+                }
+                row.get(0)
+            }
+            Err(e) if client.is_closed() => {
+                return Err(e).context(format!(
+                    "Lost connection to target while waiting for subscription '{}' to sync",
+                    subscription_name
+                ));
+            }
+            Err(e) => {
+                if breaker.record_failure() {
+                    tracing::error!(
+                        "✗ Target failed {} consecutive status checks for subscription '{}': {}",
+                        breaker.consecutive_failures(),
+                        subscription_name,
+                        e
+                    );
+                    tracing::error!(
+                        "  Backing off to one probe every {}s until the target recovers",
+                        SYNC_POLL_OPEN_INTERVAL.as_secs()
+                    );
+                } else {
+                    tracing::warn!(
+                        "⚠ Failed to query subscription status for '{}': {}",
+                        subscription_name,
+                        e
+                    );
+                }
+                if start.elapsed() > timeout {
+                    return Err(e).context(format!(
+                        "Failed to query subscription status for '{}'",
+                        subscription_name
+                    ));
+                }
+                tokio::time::sleep(breaker.next_delay(SYNC_POLL_INTERVAL)).await;
+                continue;
+            }
+        };
 
         match state.as_str() {
             "r" => {
@@ -290,7 +744,7 @@ pub async fn wait_for_sync(
             );
         }
 
-        tokio::time::sleep(Duration::from_secs(2)).await;
+        tokio::time::sleep(SYNC_POLL_INTERVAL).await;
     }
 }
 
@@ -299,6 +753,42 @@ mod tests {
     use super::*;
     use crate::postgres::connect;
 
+    #[test]
+    fn test_streaming_mode_falls_back_on_older_targets() {
+        assert_eq!(
+            StreamingMode::Parallel.supported_on(160002),
+            StreamingMode::Parallel
+        );
+        assert_eq!(
+            StreamingMode::Parallel.supported_on(150006),
+            StreamingMode::On
+        );
+        assert_eq!(
+            StreamingMode::Parallel.supported_on(130014),
+            StreamingMode::Off
+        );
+        assert_eq!(StreamingMode::On.supported_on(170000), StreamingMode::On);
+        assert_eq!(StreamingMode::from_catalog("true"), Some(StreamingMode::On));
+        assert_eq!(
+            StreamingMode::from_catalog("p"),
+            Some(StreamingMode::Parallel)
+        );
+        assert!("sometimes".parse::<StreamingMode>().is_err());
+    }
+
+    #[test]
+    fn test_with_clause() {
+        assert_eq!(with_clause(false, None), "");
+        assert_eq!(
+            with_clause(true, Some(StreamingMode::Parallel)),
+            " WITH (two_phase = true, streaming = parallel)"
+        );
+        assert_eq!(
+            with_clause(false, Some(StreamingMode::Off)),
+            " WITH (streaming = off)"
+        );
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_create_and_list_subscriptions() {
@@ -315,15 +805,30 @@ mod tests {
         let filter = crate::filters::ReplicationFilter::empty();
 
         // Create publication on source
-        crate::replication::create_publication(&source_client, db_name, pub_name, &filter)
-            .await
-            .unwrap();
+        crate::replication::create_publication(
+            &source_client,
+            db_name,
+            pub_name,
+            &filter,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
 
         // Clean up subscription if exists
-        let _ = drop_subscription(&target_client, sub_name).await;
+        let _ = drop_subscription(&target_client, sub_name, &AuditSettings::default()).await;
 
         // Create subscription on target
-        let result = create_subscription(&target_client, sub_name, &source_url, pub_name).await;
+        let result = create_subscription(
+            &target_client,
+            sub_name,
+            &source_url,
+            &[pub_name.to_string()],
+            false,
+            &SubscriptionOptions::default(),
+            &SlotCreationOptions::default(),
+        )
+        .await;
         match &result {
             Ok(_) => println!("✓ Subscription created successfully"),
             Err(e) => {
@@ -343,8 +848,10 @@ mod tests {
         assert!(subs.contains(&sub_name.to_string()));
 
         // Clean up
-        drop_subscription(&target_client, sub_name).await.unwrap();
-        crate::replication::drop_publication(&source_client, pub_name)
+        drop_subscription(&target_client, sub_name, &AuditSettings::default())
+            .await
+            .unwrap();
+        crate::replication::drop_publication(&source_client, pub_name, &AuditSettings::default())
             .await
             .unwrap();
     }
@@ -364,17 +871,31 @@ mod tests {
         let filter = crate::filters::ReplicationFilter::empty();
 
         // Create publication on source
-        crate::replication::create_publication(&source_client, db_name, pub_name, &filter)
-            .await
-            .unwrap();
+        crate::replication::create_publication(
+            &source_client,
+            db_name,
+            pub_name,
+            &filter,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
 
         // Create subscription on target
-        create_subscription(&target_client, sub_name, &source_url, pub_name)
-            .await
-            .unwrap();
+        create_subscription(
+            &target_client,
+            sub_name,
+            &source_url,
+            &[pub_name.to_string()],
+            false,
+            &SubscriptionOptions::default(),
+            &SlotCreationOptions::default(),
+        )
+        .await
+        .unwrap();
 
         // Drop it
-        let result = drop_subscription(&target_client, sub_name).await;
+        let result = drop_subscription(&target_client, sub_name, &AuditSettings::default()).await;
         assert!(result.is_ok());
 
         // Verify it's gone
@@ -382,7 +903,7 @@ mod tests {
         assert!(!subs.contains(&sub_name.to_string()));
 
         // Clean up publication
-        crate::replication::drop_publication(&source_client, pub_name)
+        crate::replication::drop_publication(&source_client, pub_name, &AuditSettings::default())
             .await
             .unwrap();
     }
@@ -402,25 +923,41 @@ mod tests {
         let filter = crate::filters::ReplicationFilter::empty();
 
         // Create publication on source
-        crate::replication::create_publication(&source_client, db_name, pub_name, &filter)
-            .await
-            .unwrap();
+        crate::replication::create_publication(
+            &source_client,
+            db_name,
+            pub_name,
+            &filter,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
 
         // Clean up subscription if exists
-        let _ = drop_subscription(&target_client, sub_name).await;
+        let _ = drop_subscription(&target_client, sub_name, &AuditSettings::default()).await;
 
         // Create subscription on target
-        create_subscription(&target_client, sub_name, &source_url, pub_name)
-            .await
-            .unwrap();
+        create_subscription(
+            &target_client,
+            sub_name,
+            &source_url,
+            &[pub_name.to_string()],
+            false,
+            &SubscriptionOptions::default(),
+            &SlotCreationOptions::default(),
+        )
+        .await
+        .unwrap();
 
         // Wait for sync (30 second timeout)
         let result = wait_for_sync(&target_client, sub_name, 30).await;
         assert!(result.is_ok(), "Failed to wait for sync: {:?}", result);
 
         // Clean up
-        drop_subscription(&target_client, sub_name).await.unwrap();
-        crate::replication::drop_publication(&source_client, pub_name)
+        drop_subscription(&target_client, sub_name, &AuditSettings::default())
+            .await
+            .unwrap();
+        crate::replication::drop_publication(&source_client, pub_name, &AuditSettings::default())
             .await
             .unwrap();
     }
@@ -0,0 +1,758 @@
+// ABOUTME: Reads publications, slots, subscriptions, and origins from PostgreSQL clusters and links them
+// ABOUTME: Renders the logical replication topology as text, Graphviz dot, or Mermaid
+
+use crate::filters::ReplicationFilter;
+use crate::postgres::connect_with_retry;
+use crate::settings::RunSettings;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use tokio_postgres::Client;
+
+/// A publication in one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publication {
+    pub database: String,
+    pub name: String,
+    pub all_tables: bool,
+    pub tables: i64,
+}
+
+/// A replication slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    pub name: String,
+    /// None for physical slots
+    pub database: Option<String>,
+    pub plugin: Option<String>,
+    pub slot_type: String,
+    pub active: bool,
+    /// WAL the slot holds back; None on standbys
+    pub retained_bytes: Option<i64>,
+}
+
+/// A subscription in one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub database: String,
+    pub name: String,
+    pub enabled: bool,
+    pub publications: Vec<String>,
+    /// None when created with `slot_name = NONE`
+    pub slot: Option<String>,
+    /// Last source position applied, from the subscription's replication origin
+    pub remote_lsn: Option<String>,
+}
+
+/// A replication origin no subscription owns (e.g. one used by an external tool)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub name: String,
+    pub remote_lsn: Option<String>,
+}
+
+/// The replication objects of one cluster
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClusterTopology {
+    /// Role in the command line, e.g. `source` or `target`
+    pub label: String,
+    /// host:port
+    pub endpoint: String,
+    pub publications: Vec<Publication>,
+    pub slots: Vec<Slot>,
+    pub subscriptions: Vec<Subscription>,
+    pub origins: Vec<Origin>,
+}
+
+/// Index of an object: (cluster, object within the cluster)
+pub type NodeRef = (usize, usize);
+
+/// One subscription and what it reads from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stream {
+    pub subscription: NodeRef,
+    /// The slot the subscription reads, if found on another cluster
+    pub slot: Option<NodeRef>,
+    /// The subscription's publications in the slot's database
+    pub publications: Vec<NodeRef>,
+}
+
+/// Several clusters and the streams between them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Topology {
+    pub clusters: Vec<ClusterTopology>,
+}
+
+/// Read the replication objects of one cluster
+///
+/// Slots, subscriptions, and origins are cluster-wide; publications are read
+/// from each database `filter` selects. Replication origin progress needs
+/// superuser (or `pg_read_all_stats` on newer servers); without it, positions
+/// are left out.
+///
+/// # Errors
+///
+/// Returns an error if the cluster or one of its databases cannot be queried.
+pub async fn collect(
+    label: &str,
+    url: &str,
+    filter: &ReplicationFilter,
+    settings: &RunSettings,
+) -> Result<ClusterTopology> {
+    let parts = crate::utils::parse_postgres_url(url)?;
+    let client = connect_with_retry(url, settings)
+        .await
+        .with_context(|| format!("Failed to connect to {}", label))?;
+
+    let databases: Vec<String> = crate::migration::list_databases(&client)
+        .await
+        .with_context(|| format!("Failed to list databases on {}", label))?
+        .into_iter()
+        .map(|db| db.name)
+        .filter(|name| filter.should_replicate_database(name))
+        .collect();
+
+    let mut publications = Vec::new();
+    for database in &databases {
+        let db_url = crate::utils::replace_database_in_url(url, database)?;
+        let db_client = connect_with_retry(&db_url, settings)
+            .await
+            .with_context(|| {
+                format!("Failed to connect to database '{}' on {}", database, label)
+            })?;
+        publications.extend(database_publications(&db_client, database).await?);
+    }
+
+    let slots = slots(&client)
+        .await?
+        .into_iter()
+        .filter(|slot| {
+            slot.database
+                .as_deref()
+                .is_none_or(|database| databases.iter().any(|name| name == database))
+        })
+        .collect();
+
+    let mut origins = origins(&client).await.unwrap_or_else(|e| {
+        tracing::warn!(
+            "⚠ Could not read replication origin progress on {}: {:#}",
+            label,
+            e
+        );
+        Vec::new()
+    });
+    let mut subscriptions = Vec::new();
+    for (oid, mut subscription) in self::subscriptions(&client).await? {
+        let origin = format!("pg_{}", oid);
+        if let Some(index) = origins.iter().position(|o| o.name == origin) {
+            subscription.remote_lsn = origins.remove(index).remote_lsn;
+        }
+        if databases.contains(&subscription.database) {
+            subscriptions.push(subscription);
+        }
+    }
+
+    Ok(ClusterTopology {
+        label: label.to_string(),
+        endpoint: format!("{}:{}", parts.host, parts.port),
+        publications,
+        slots,
+        subscriptions,
+        origins,
+    })
+}
+
+async fn database_publications(client: &Client, database: &str) -> Result<Vec<Publication>> {
+    let rows = client
+        .query(
+            "SELECT p.pubname::text, p.puballtables,
+                    (SELECT count(*) FROM pg_publication_tables t WHERE t.pubname = p.pubname)
+             FROM pg_publication p ORDER BY p.pubname",
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to read publications in '{}'", database))?;
+    Ok(rows
+        .iter()
+        .map(|row| Publication {
+            database: database.to_string(),
+            name: row.get(0),
+            all_tables: row.get(1),
+            tables: row.get(2),
+        })
+        .collect())
+}
+
+async fn slots(client: &Client) -> Result<Vec<Slot>> {
+    let rows = client
+        .query(
+            "SELECT slot_name::text, database::text, plugin::text, slot_type, active,
+                    CASE WHEN pg_is_in_recovery() THEN NULL
+                         ELSE pg_wal_lsn_diff(pg_current_wal_lsn(), restart_lsn)::bigint END
+             FROM pg_replication_slots ORDER BY slot_name",
+            &[],
+        )
+        .await
+        .context("Failed to read replication slots")?;
+    Ok(rows
+        .iter()
+        .map(|row| Slot {
+            name: row.get(0),
+            database: row.get(1),
+            plugin: row.get(2),
+            slot_type: row.get(3),
+            active: row.get(4),
+            retained_bytes: row.get(5),
+        })
+        .collect())
+}
+
+async fn subscriptions(client: &Client) -> Result<Vec<(u32, Subscription)>> {
+    let rows = client
+        .query(
+            "SELECT s.oid, d.datname::text, s.subname::text, s.subenabled,
+                    s.subpublications::text[], s.subslotname::text
+             FROM pg_subscription s JOIN pg_database d ON d.oid = s.subdbid
+             ORDER BY d.datname, s.subname",
+            &[],
+        )
+        .await
+        .context("Failed to read subscriptions")?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            (
+                row.get(0),
+                Subscription {
+                    database: row.get(1),
+                    name: row.get(2),
+                    enabled: row.get(3),
+                    publications: row.get(4),
+                    slot: row.get(5),
+                    remote_lsn: None,
+                },
+            )
+        })
+        .collect())
+}
+
+async fn origins(client: &Client) -> Result<Vec<Origin>> {
+    let rows = client
+        .query(
+            "SELECT o.roname::text, s.remote_lsn::text
+             FROM pg_replication_origin o
+             LEFT JOIN pg_replication_origin_status s ON s.local_id = o.roident
+             ORDER BY o.roname",
+            &[],
+        )
+        .await
+        .context("Failed to read replication origins")?;
+    Ok(rows
+        .iter()
+        .map(|row| Origin {
+            name: row.get(0),
+            remote_lsn: row.get(1),
+        })
+        .collect())
+}
+
+impl Topology {
+    /// Link every subscription to the slot it reads on another cluster, and
+    /// to its publications in that slot's database
+    pub fn streams(&self) -> Vec<Stream> {
+        let mut streams = Vec::new();
+        for (cluster_index, cluster) in self.clusters.iter().enumerate() {
+            for (index, subscription) in cluster.subscriptions.iter().enumerate() {
+                let slot = subscription.slot.as_deref().and_then(|name| {
+                    self.clusters
+                        .iter()
+                        .enumerate()
+                        .filter(|(other, _)| *other != cluster_index)
+                        .find_map(|(other, upstream)| {
+                            upstream
+                                .slots
+                                .iter()
+                                .position(|slot| slot.name == name)
+                                .map(|slot| (other, slot))
+                        })
+                });
+                let publications = slot
+                    .map(|(other, slot)| {
+                        let upstream = &self.clusters[other];
+                        let database = upstream.slots[slot].database.as_deref();
+                        upstream
+                            .publications
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, publication)| {
+                                Some(publication.database.as_str()) == database
+                                    && subscription.publications.contains(&publication.name)
+                            })
+                            .map(|(index, _)| (other, index))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                streams.push(Stream {
+                    subscription: (cluster_index, index),
+                    slot,
+                    publications,
+                });
+            }
+        }
+        streams
+    }
+
+    /// Objects that look left behind: logical slots no subscription here
+    /// reads, and subscriptions whose slot is on none of these clusters
+    pub fn warnings(&self) -> Vec<String> {
+        let streams = self.streams();
+        let mut warnings = Vec::new();
+        for (cluster_index, cluster) in self.clusters.iter().enumerate() {
+            for (index, slot) in cluster.slots.iter().enumerate() {
+                let read = streams
+                    .iter()
+                    .any(|stream| stream.slot == Some((cluster_index, index)));
+                if slot.slot_type == "logical" && !read {
+                    warnings.push(format!(
+                        "Slot '{}' on {} has no subscriber on these clusters{}",
+                        slot.name,
+                        cluster.label,
+                        if slot.active {
+                            " (a client outside them is reading it)"
+                        } else {
+                            " and is inactive: it holds WAL until dropped"
+                        }
+                    ));
+                }
+            }
+        }
+        for stream in &streams {
+            let (cluster_index, index) = stream.subscription;
+            let cluster = &self.clusters[cluster_index];
+            let subscription = &cluster.subscriptions[index];
+            if let (None, Some(slot)) = (stream.slot, &subscription.slot) {
+                warnings.push(format!(
+                    "Subscription '{}' in {}.{} reads slot '{}', which is on none of these clusters",
+                    subscription.name, cluster.label, subscription.database, slot
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Plain-text listing of every cluster, then one line per stream
+    pub fn render_text(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for cluster in &self.clusters {
+            lines.push(format!("{} ({})", cluster.label, cluster.endpoint));
+            for publication in &cluster.publications {
+                lines.push(format!("  publication  {}", publication_label(publication)));
+            }
+            for slot in &cluster.slots {
+                lines.push(format!("  slot         {}", slot_label(slot)));
+            }
+            for subscription in &cluster.subscriptions {
+                lines.push(format!(
+                    "  subscription {}",
+                    subscription_label(subscription)
+                ));
+            }
+            for origin in &cluster.origins {
+                lines.push(format!("  origin       {}", origin_label(origin)));
+            }
+        }
+        lines.push("Streams:".to_string());
+        let streams = self.streams();
+        if streams.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        for stream in &streams {
+            let (cluster_index, index) = stream.subscription;
+            let cluster = &self.clusters[cluster_index];
+            let subscription = &cluster.subscriptions[index];
+            let upstream = match stream.slot {
+                Some((other, slot)) => {
+                    let upstream = &self.clusters[other];
+                    let slot = &upstream.slots[slot];
+                    let publications = stream
+                        .publications
+                        .iter()
+                        .map(|(c, p)| self.clusters[*c].publications[*p].name.clone())
+                        .collect::<Vec<_>>();
+                    format!(
+                        "{} {}.[{}] → slot {}{}",
+                        upstream.label,
+                        slot.database.as_deref().unwrap_or("-"),
+                        publications.join(", "),
+                        slot.name,
+                        if slot.active { "" } else { " (inactive)" }
+                    )
+                }
+                None => format!(
+                    "slot {} (not found)",
+                    subscription.slot.as_deref().unwrap_or("NONE")
+                ),
+            };
+            lines.push(format!(
+                "  {} → {} {}.{}{}",
+                upstream,
+                cluster.label,
+                subscription.database,
+                subscription.name,
+                if subscription.enabled {
+                    ""
+                } else {
+                    " (disabled)"
+                }
+            ));
+        }
+        lines
+    }
+
+    /// Graphviz dot: one subgraph per cluster, edges publication → slot → subscription
+    pub fn render_dot(&self) -> String {
+        let mut out = String::from("digraph replication {\n  rankdir=LR;\n  node [fontsize=10];\n");
+        for (c, cluster) in self.clusters.iter().enumerate() {
+            let _ = writeln!(out, "  subgraph cluster_{} {{", c);
+            let _ = writeln!(
+                out,
+                "    label=\"{}\";",
+                dot_escape(&format!("{} ({})", cluster.label, cluster.endpoint))
+            );
+            for (i, publication) in cluster.publications.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {} [shape=box, label=\"{}\"];",
+                    node_id("pub", (c, i)),
+                    dot_escape(&format!("publication\n{}", publication_label(publication)))
+                );
+            }
+            for (i, slot) in cluster.slots.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {} [shape=ellipse{}, label=\"{}\"];",
+                    node_id("slot", (c, i)),
+                    if slot.active { "" } else { ", style=dashed" },
+                    dot_escape(&format!("slot\n{}", slot_label(slot)))
+                );
+            }
+            for (i, subscription) in cluster.subscriptions.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {} [shape=box, style=rounded{}, label=\"{}\"];",
+                    node_id("sub", (c, i)),
+                    if subscription.enabled {
+                        ""
+                    } else {
+                        ", color=gray"
+                    },
+                    dot_escape(&format!(
+                        "subscription\n{}",
+                        subscription_label(subscription)
+                    ))
+                );
+            }
+            for (i, origin) in cluster.origins.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {} [shape=note, label=\"{}\"];",
+                    node_id("origin", (c, i)),
+                    dot_escape(&format!("origin\n{}", origin_label(origin)))
+                );
+            }
+            out.push_str("  }\n");
+        }
+        for (from, to) in self.edges() {
+            let _ = writeln!(out, "  {} -> {};", from, to);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Mermaid flowchart: one subgraph per cluster, edges publication → slot → subscription
+    pub fn render_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (c, cluster) in self.clusters.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "  subgraph cluster_{}[\"{}\"]",
+                c,
+                mermaid_escape(&format!("{} ({})", cluster.label, cluster.endpoint))
+            );
+            for (i, publication) in cluster.publications.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {}[\"{}\"]",
+                    node_id("pub", (c, i)),
+                    mermaid_escape(&format!(
+                        "publication<br/>{}",
+                        publication_label(publication)
+                    ))
+                );
+            }
+            for (i, slot) in cluster.slots.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {}([\"{}\"])",
+                    node_id("slot", (c, i)),
+                    mermaid_escape(&format!("slot<br/>{}", slot_label(slot)))
+                );
+            }
+            for (i, subscription) in cluster.subscriptions.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {}(\"{}\")",
+                    node_id("sub", (c, i)),
+                    mermaid_escape(&format!(
+                        "subscription<br/>{}",
+                        subscription_label(subscription)
+                    ))
+                );
+            }
+            for (i, origin) in cluster.origins.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "    {}[/\"{}\"/]",
+                    node_id("origin", (c, i)),
+                    mermaid_escape(&format!("origin<br/>{}", origin_label(origin)))
+                );
+            }
+            out.push_str("  end\n");
+        }
+        for (from, to) in self.edges() {
+            let _ = writeln!(out, "  {} --> {}", from, to);
+        }
+        out
+    }
+
+    /// (from, to) node ids: publication → slot and slot → subscription
+    fn edges(&self) -> Vec<(String, String)> {
+        let mut edges = Vec::new();
+        for stream in self.streams() {
+            let subscription = node_id("sub", stream.subscription);
+            let Some(slot) = stream.slot else {
+                continue;
+            };
+            let slot = node_id("slot", slot);
+            for publication in &stream.publications {
+                let edge = (node_id("pub", *publication), slot.clone());
+                if !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+            edges.push((slot, subscription));
+        }
+        edges
+    }
+}
+
+fn node_id(kind: &str, (cluster, index): NodeRef) -> String {
+    format!("c{}_{}_{}", cluster, kind, index)
+}
+
+fn publication_label(publication: &Publication) -> String {
+    format!(
+        "{}.{} ({})",
+        publication.database,
+        publication.name,
+        if publication.all_tables {
+            "all tables".to_string()
+        } else {
+            format!("{} table(s)", publication.tables)
+        }
+    )
+}
+
+fn slot_label(slot: &Slot) -> String {
+    let mut details = vec![slot.slot_type.clone()];
+    if let Some(plugin) = &slot.plugin {
+        details.push(plugin.clone());
+    }
+    details.push(if slot.active { "active" } else { "inactive" }.to_string());
+    if let Some(bytes) = slot.retained_bytes {
+        details.push(format!(
+            "{} retained",
+            crate::migration::format_bytes(bytes)
+        ));
+    }
+    match &slot.database {
+        Some(database) => format!("{}.{} ({})", database, slot.name, details.join(", ")),
+        None => format!("{} ({})", slot.name, details.join(", ")),
+    }
+}
+
+fn subscription_label(subscription: &Subscription) -> String {
+    let mut details = vec![if subscription.enabled {
+        "enabled"
+    } else {
+        "disabled"
+    }
+    .to_string()];
+    if let Some(lsn) = &subscription.remote_lsn {
+        details.push(format!("at {}", lsn));
+    }
+    format!(
+        "{}.{} ({})",
+        subscription.database,
+        subscription.name,
+        details.join(", ")
+    )
+}
+
+fn origin_label(origin: &Origin) -> String {
+    match &origin.remote_lsn {
+        Some(lsn) => format!("{} (at {})", origin.name, lsn),
+        None => origin.name.clone(),
+    }
+}
+
+fn dot_escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn mermaid_escape(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topology() -> Topology {
+        Topology {
+            clusters: vec![
+                ClusterTopology {
+                    label: "source".to_string(),
+                    endpoint: "src:5432".to_string(),
+                    publications: vec![
+                        Publication {
+                            database: "app".to_string(),
+                            name: "seren_migration_pub".to_string(),
+                            all_tables: true,
+                            tables: 4,
+                        },
+                        Publication {
+                            database: "app".to_string(),
+                            name: "unused_pub".to_string(),
+                            all_tables: false,
+                            tables: 1,
+                        },
+                    ],
+                    slots: vec![
+                        Slot {
+                            name: "seren_migration_sub".to_string(),
+                            database: Some("app".to_string()),
+                            plugin: Some("pgoutput".to_string()),
+                            slot_type: "logical".to_string(),
+                            active: true,
+                            retained_bytes: Some(2048),
+                        },
+                        Slot {
+                            name: "old_slot".to_string(),
+                            database: Some("app".to_string()),
+                            plugin: Some("pgoutput".to_string()),
+                            slot_type: "logical".to_string(),
+                            active: false,
+                            retained_bytes: None,
+                        },
+                    ],
+                    subscriptions: vec![],
+                    origins: vec![],
+                },
+                ClusterTopology {
+                    label: "target".to_string(),
+                    endpoint: "tgt:5432".to_string(),
+                    publications: vec![],
+                    slots: vec![],
+                    subscriptions: vec![
+                        Subscription {
+                            database: "app".to_string(),
+                            name: "seren_migration_sub".to_string(),
+                            enabled: true,
+                            publications: vec!["seren_migration_pub".to_string()],
+                            slot: Some("seren_migration_sub".to_string()),
+                            remote_lsn: Some("0/16B3748".to_string()),
+                        },
+                        Subscription {
+                            database: "app".to_string(),
+                            name: "elsewhere".to_string(),
+                            enabled: false,
+                            publications: vec!["p".to_string()],
+                            slot: Some("remote_slot".to_string()),
+                            remote_lsn: None,
+                        },
+                    ],
+                    origins: vec![Origin {
+                        name: "\"external\" tool".to_string(),
+                        remote_lsn: None,
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_streams_link_subscriptions_to_slots_and_publications() {
+        let streams = topology().streams();
+        assert_eq!(
+            streams,
+            vec![
+                Stream {
+                    subscription: (1, 0),
+                    slot: Some((0, 0)),
+                    publications: vec![(0, 0)],
+                },
+                Stream {
+                    subscription: (1, 1),
+                    slot: None,
+                    publications: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warnings_flag_unread_slots_and_missing_slots() {
+        let warnings = topology().warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("Slot 'old_slot' on source"));
+        assert!(warnings[0].contains("inactive"));
+        assert!(warnings[1].contains("'remote_slot', which is on none of these clusters"));
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let dot = topology().render_dot();
+        assert!(dot.starts_with("digraph replication {"));
+        assert!(dot.contains("subgraph cluster_0 {"));
+        assert!(dot.contains("c0_pub_0 -> c0_slot_0;"));
+        assert!(dot.contains("c0_slot_0 -> c1_sub_0;"));
+        assert!(!dot.contains("c0_pub_1 ->"));
+        assert!(dot.contains("c0_slot_1 [shape=ellipse, style=dashed"));
+        assert!(dot.contains("origin\\n\\\"external\\\" tool"));
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let mermaid = topology().render_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("  subgraph cluster_1[\"target (tgt:5432)\"]"));
+        assert!(mermaid.contains("  c0_pub_0 --> c0_slot_0\n"));
+        assert!(mermaid.contains("  c0_slot_0 --> c1_sub_0\n"));
+        assert!(mermaid.contains("#quot;external#quot; tool"));
+    }
+
+    #[test]
+    fn test_render_text_lists_streams() {
+        let lines = topology().render_text();
+        assert!(lines.contains(
+            &"  source app.[seren_migration_pub] → slot seren_migration_sub → target app.seren_migration_sub"
+                .to_string()
+        ));
+        assert!(lines.contains(
+            &"  slot remote_slot (not found) → target app.elsewhere (disabled)".to_string()
+        ));
+    }
+}
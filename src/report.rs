@@ -0,0 +1,394 @@
+// ABOUTME: Standalone HTML report of a run's copy and verify results and resource usage, for stakeholders and chargeback
+// ABOUTME: Collected through the events observer interface and written by `--report-html` when the command ends
+
+use crate::events::{Phase, Progress, ReplicationObserver, TableEvent, TableOutcome};
+use crate::resources::ResourceSummary;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// One finished table in the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+    pub phase: Phase,
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+    pub outcome: TableOutcome,
+    pub duration: Option<Duration>,
+}
+
+/// Collects events for the HTML report
+///
+/// Register it with [`crate::events::register`] for the length of the
+/// command, then call [`HtmlReport::write`] with the command's result.
+pub struct HtmlReport {
+    command: String,
+    started_at: u64,
+    started: Instant,
+    running: Mutex<HashMap<(Phase, String, String, String), Instant>>,
+    tables: Mutex<Vec<TableRow>>,
+    databases: Mutex<Vec<String>>,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl HtmlReport {
+    pub fn new(command: &str) -> Self {
+        HtmlReport {
+            command: command.to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            started: Instant::now(),
+            running: Mutex::new(HashMap::new()),
+            tables: Mutex::new(Vec::new()),
+            databases: Mutex::new(Vec::new()),
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Write the report for a command that finished with `result`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, path: &Path, result: &Result<()>) -> Result<()> {
+        let html = self.render(result.as_ref().err().map(|e| format!("{:#}", e)));
+        std::fs::write(path, html)
+            .with_context(|| format!("Failed to write HTML report to {}", path.display()))?;
+        tracing::info!("✓ HTML report written to {}", path.display());
+        Ok(())
+    }
+
+    /// Add the run's collected warnings, skipping any an observer already reported
+    pub fn add_warnings(&self, collected: &[String]) {
+        let mut warnings = lock(&self.warnings);
+        let reported = warnings.clone();
+        warnings.extend(
+            collected
+                .iter()
+                .filter(|warning| !reported.contains(warning))
+                .cloned(),
+        );
+    }
+
+    /// Render the report; `error` is the command's failure, if it failed
+    pub fn render(&self, error: Option<String>) -> String {
+        let tables = lock(&self.tables).clone();
+        let databases = lock(&self.databases).clone();
+        let warnings = lock(&self.warnings).clone();
+        let count = |f: fn(&TableOutcome) -> bool| tables.iter().filter(|t| f(&t.outcome)).count();
+        let succeeded = count(|o| matches!(o, TableOutcome::Success { .. }));
+        let mismatched = count(|o| matches!(o, TableOutcome::Mismatch { .. }));
+        let failed = count(|o| matches!(o, TableOutcome::Failed { .. }));
+        let passed = error.is_none() && mismatched == 0 && failed == 0;
+
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>seren-replicator {command} report</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>seren-replicator {command}</h1>\n\
+             <p class=\"meta\">Started {started} UTC &middot; took {elapsed}</p>\n\
+             <p class=\"status {class}\">{status}</p>\n",
+            command = escape(&self.command),
+            started = crate::audit::format_utc(self.started_at),
+            elapsed = crate::migration::format_duration(self.started.elapsed()),
+            class = if passed { "ok" } else { "bad" },
+            status = if passed { "&#10003; Passed" } else { "&#10007; Failed" },
+        );
+        if let Some(error) = &error {
+            let _ = writeln!(html, "<pre class=\"error\">{}</pre>", escape(error));
+        }
+
+        html.push_str("<h2>Summary</h2>\n<table class=\"summary\">\n");
+        if !databases.is_empty() {
+            let _ = writeln!(
+                html,
+                "<tr><th>Databases completed</th><td>{}</td></tr>",
+                databases.len()
+            );
+        }
+        let _ = write!(
+            html,
+            "<tr><th>Tables</th><td>{}</td></tr>\n\
+             <tr><th>Succeeded</th><td class=\"ok\">{}</td></tr>\n\
+             <tr><th>Mismatched</th><td class=\"{}\">{}</td></tr>\n\
+             <tr><th>Failed</th><td class=\"{}\">{}</td></tr>\n\
+             <tr><th>Warnings</th><td>{}</td></tr>\n</table>\n",
+            tables.len(),
+            succeeded,
+            if mismatched > 0 { "bad" } else { "ok" },
+            mismatched,
+            if failed > 0 { "bad" } else { "ok" },
+            failed,
+            warnings.len(),
+        );
+
+        render_resources(&mut html, &ResourceSummary::collect(self.started.elapsed()));
+
+        if !databases.is_empty() {
+            html.push_str("<h2>Databases</h2>\n<ul>\n");
+            for database in &databases {
+                let _ = writeln!(html, "<li>{}</li>", escape(database));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        if !tables.is_empty() {
+            html.push_str(
+                "<h2>Tables</h2>\n<p class=\"meta\">Click a column heading to sort.</p>\n\
+                 <table class=\"sortable\">\n<thead><tr><th>Phase</th><th>Database</th><th>Schema</th>\
+                 <th>Table</th><th>Status</th><th>Rows</th><th>Seconds</th><th>Detail</th></tr></thead>\n<tbody>\n",
+            );
+            for row in &tables {
+                let (class, status, rows, detail) = match &row.outcome {
+                    TableOutcome::Success { rows } => {
+                        ("ok", "OK", rows.map(|r| r.to_string()), String::new())
+                    }
+                    TableOutcome::Mismatch { detail } => ("bad", "Mismatch", None, detail.clone()),
+                    TableOutcome::Failed { error } => ("bad", "Failed", None, error.clone()),
+                };
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td>\
+                     <td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td></tr>",
+                    row.phase.as_str(),
+                    escape(&row.database),
+                    escape(&row.schema),
+                    escape(&row.table),
+                    class,
+                    status,
+                    rows.unwrap_or_default(),
+                    row.duration
+                        .map(|d| format!("{:.1}", d.as_secs_f64()))
+                        .unwrap_or_default(),
+                    escape(&detail),
+                );
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+
+        html.push_str("<h2>Warnings</h2>\n");
+        if warnings.is_empty() {
+            html.push_str("<p class=\"meta\">No warnings.</p>\n");
+        } else {
+            html.push_str("<ul class=\"warnings\">\n");
+            for warning in &warnings {
+                let _ = writeln!(html, "<li>{}</li>", escape(warning));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        let _ = write!(html, "<script>{SCRIPT}</script>\n</body>\n</html>\n");
+        html
+    }
+}
+
+impl ReplicationObserver for HtmlReport {
+    fn on_table_start(&self, table: &TableEvent) {
+        lock(&self.running).insert(key(table), Instant::now());
+    }
+
+    fn on_table_complete(&self, table: &TableEvent, outcome: &TableOutcome) {
+        let started = lock(&self.running).remove(&key(table));
+        lock(&self.tables).push(TableRow {
+            phase: table.phase,
+            database: table.database.clone(),
+            schema: table.schema.clone(),
+            table: table.table.clone(),
+            outcome: outcome.clone(),
+            duration: started.map(|s| s.elapsed()),
+        });
+    }
+
+    fn on_progress(&self, progress: &Progress) {
+        // Whole-database units report each finished database by name
+        if progress.database.is_empty() && progress.completed > 0 {
+            lock(&self.databases).push(progress.message.clone());
+        }
+    }
+
+    fn on_warning(&self, message: &str) {
+        lock(&self.warnings).push(message.to_string());
+    }
+}
+
+/// The Resources table: what the invocation moved and consumed, for chargeback
+fn render_resources(html: &mut String, resources: &ResourceSummary) {
+    let bytes = |b: u64| crate::migration::format_bytes(b as i64);
+    let unknown = || "n/a".to_string();
+    let _ = write!(
+        html,
+        "<h2>Resources</h2>\n<table class=\"resources\">\n\
+         <tr><th>Bytes read from source</th><td class=\"num\">{}</td></tr>\n\
+         <tr><th>Bytes written to target</th><td class=\"num\">{}</td></tr>\n\
+         <tr><th>Write rate</th><td class=\"num\">{}</td></tr>\n\
+         <tr><th>Wall-clock</th><td class=\"num\">{}</td></tr>\n\
+         <tr><th>Peak memory</th><td class=\"num\">{}</td></tr>\n\
+         <tr><th>Subprocess CPU time</th><td class=\"num\">{}</td></tr>\n</table>\n",
+        bytes(resources.source_bytes_read),
+        bytes(resources.target_bytes_written),
+        resources
+            .target_rate()
+            .map(|rate| format!("{}/s", bytes(rate as u64)))
+            .unwrap_or_else(unknown),
+        crate::migration::format_duration(resources.wall_clock),
+        resources
+            .peak_memory_bytes
+            .map(bytes)
+            .unwrap_or_else(unknown),
+        resources
+            .subprocess_cpu
+            .map(|cpu| format!("{:.1}s", cpu.as_secs_f64()))
+            .unwrap_or_else(unknown),
+    );
+}
+
+fn key(table: &TableEvent) -> (Phase, String, String, String) {
+    (
+        table.phase,
+        table.database.clone(),
+        table.schema.clone(),
+        table.table.clone(),
+    )
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Escape text for HTML element content and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.meta { color: #666; }
+.status { display: inline-block; padding: 0.4em 1em; border-radius: 4px; font-size: 1.3em; font-weight: bold; }
+.status.ok { background: #e6f4ea; color: #1e7e34; }
+.status.bad { background: #fdecea; color: #b71c1c; }
+td.ok { color: #1e7e34; font-weight: bold; }
+td.bad { color: #b71c1c; font-weight: bold; }
+table { border-collapse: collapse; margin: 0.5em 0 1.5em; }
+th, td { border: 1px solid #ddd; padding: 0.3em 0.7em; text-align: left; vertical-align: top; }
+table.sortable th { background: #f4f4f4; cursor: pointer; user-select: none; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+pre.error { background: #fdecea; padding: 1em; white-space: pre-wrap; }
+ul.warnings li { color: #8a6d00; }
+";
+
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable').forEach(function (table) {
+  table.querySelectorAll('th').forEach(function (th, col) {
+    var ascending = true;
+    th.addEventListener('click', function () {
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (a, b) {
+        var x = a.cells[col].textContent, y = b.cells[col].textContent;
+        var nx = parseFloat(x), ny = parseFloat(y);
+        var cmp = (!isNaN(nx) && !isNaN(ny)) ? nx - ny : x.localeCompare(y);
+        return ascending ? cmp : -cmp;
+      });
+      ascending = !ascending;
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_marks_mismatches_failed() {
+        let report = HtmlReport::new("verify");
+        let clean = TableEvent::new(Phase::Verify, "app", "public", "users");
+        report.on_table_start(&clean);
+        report.on_table_complete(&clean, &TableOutcome::Success { rows: Some(42) });
+        let dirty = TableEvent::new(Phase::Verify, "app", "public", "<orders>");
+        report.on_table_complete(
+            &dirty,
+            &TableOutcome::Mismatch {
+                detail: "source 3 rows, target 2 rows".to_string(),
+            },
+        );
+        report.on_warning("row count differs & more");
+
+        let html = report.render(None);
+        assert!(html.contains("<p class=\"status bad\">&#10007; Failed</p>"));
+        assert!(html.contains("<td>&lt;orders&gt;</td>"));
+        assert!(html.contains("<td class=\"num\">42</td>"));
+        assert!(html.contains("<li>row count differs &amp; more</li>"));
+        assert!(html.contains("table class=\"sortable\""));
+    }
+
+    #[test]
+    fn test_add_warnings_skips_reported_ones() {
+        let report = HtmlReport::new("init");
+        assert!(report.render(None).contains("No warnings."));
+        report.on_warning("lag high");
+        report.add_warnings(&["lag high".to_string(), "sequence skipped".to_string()]);
+        let html = report.render(None);
+        assert_eq!(html.matches("<li>lag high</li>").count(), 1);
+        assert!(html.contains("<li>sequence skipped</li>"));
+    }
+
+    #[test]
+    fn test_render_passed_and_error() {
+        let report = HtmlReport::new("init");
+        report.on_progress(&Progress {
+            phase: Phase::Copy,
+            database: String::new(),
+            completed: 1,
+            total: 1,
+            message: "Replicated database 'app'".to_string(),
+        });
+        let html = report.render(None);
+        assert!(html.contains("<p class=\"status ok\">&#10003; Passed</p>"));
+        assert!(html.contains("<li>Replicated database &#39;app&#39;</li>"));
+
+        let html = report.render(Some("connection refused".to_string()));
+        assert!(html.contains("status bad"));
+        assert!(html.contains("<pre class=\"error\">connection refused</pre>"));
+        assert!(html.contains("<h2>Resources</h2>"));
+    }
+
+    #[test]
+    fn test_render_resources() {
+        let mut html = String::new();
+        render_resources(
+            &mut html,
+            &ResourceSummary {
+                source_bytes_read: 2048,
+                target_bytes_written: 4096,
+                wall_clock: Duration::from_secs(2),
+                peak_memory_bytes: Some(50 * 1024 * 1024),
+                subprocess_cpu: None,
+            },
+        );
+        assert!(html.contains("<th>Bytes written to target</th><td class=\"num\">4.0 KB</td>"));
+        assert!(html.contains("<th>Write rate</th><td class=\"num\">2.0 KB/s</td>"));
+        assert!(html.contains("<th>Peak memory</th><td class=\"num\">50.0 MB</td>"));
+        assert!(html.contains("<th>Subprocess CPU time</th><td class=\"num\">n/a</td>"));
+    }
+}
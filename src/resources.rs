@@ -0,0 +1,140 @@
+// ABOUTME: Per-invocation resource accounting: bytes moved, wall-clock, peak memory, subprocess CPU time
+// ABOUTME: Summarized in the --report-html artifact so migration costs can be attributed to their owners
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Bytes read from the source since startup
+static SOURCE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes written to the target since startup
+static TARGET_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Clock ticks per second of the CPU times in /proc (USER_HZ, 100 on every Linux platform)
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+/// Account for `bytes` read from the source
+pub fn record_source_read(bytes: u64) {
+    SOURCE_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Account for `bytes` written to the target
+pub fn record_target_written(bytes: u64) {
+    TARGET_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// `12.0 MB in 4s (3.0 MB/s)`: a transfer's size and measured throughput, for logs
+pub fn describe_transfer(bytes: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64().max(0.001);
+    format!(
+        "{} in {} ({}/s)",
+        crate::migration::format_bytes(bytes as i64),
+        crate::migration::format_duration(elapsed),
+        crate::migration::format_bytes((bytes as f64 / secs) as i64)
+    )
+}
+
+/// Resources one invocation has used so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceSummary {
+    /// COPY data read from the source, plus the size of pg_dump data dumps
+    pub source_bytes_read: u64,
+    /// COPY and JSONB insert data written to the target, plus the size of restored dumps
+    pub target_bytes_written: u64,
+    pub wall_clock: Duration,
+    /// Peak resident memory of this process; None where /proc is unavailable
+    pub peak_memory_bytes: Option<u64>,
+    /// User plus system CPU time of finished subprocesses (pg_dump, pg_restore, psql, hooks)
+    pub subprocess_cpu: Option<Duration>,
+}
+
+impl ResourceSummary {
+    /// Snapshot the counters, for a command that has run for `wall_clock`
+    pub fn collect(wall_clock: Duration) -> Self {
+        ResourceSummary {
+            source_bytes_read: SOURCE_BYTES.load(Ordering::Relaxed),
+            target_bytes_written: TARGET_BYTES.load(Ordering::Relaxed),
+            wall_clock,
+            peak_memory_bytes: std::fs::read_to_string("/proc/self/status")
+                .ok()
+                .and_then(|status| parse_peak_memory(&status)),
+            subprocess_cpu: std::fs::read_to_string("/proc/self/stat")
+                .ok()
+                .and_then(|stat| parse_subprocess_cpu(&stat)),
+        }
+    }
+
+    /// Average rate at which data was written to the target, in bytes per second
+    pub fn target_rate(&self) -> Option<f64> {
+        let secs = self.wall_clock.as_secs_f64();
+        (secs > 0.0 && self.target_bytes_written > 0)
+            .then(|| self.target_bytes_written as f64 / secs)
+    }
+}
+
+/// `VmHWM` (peak resident set) from /proc/self/status, in bytes
+fn parse_peak_memory(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// `cutime + cstime` from /proc/self/stat: CPU time of waited-for children
+fn parse_subprocess_cpu(stat: &str) -> Option<Duration> {
+    // The command name in parentheses may contain spaces, so count fields after it
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+    // Fields 16 and 17 of the file; fields[0] here is field 3 (state)
+    let cutime: u64 = fields.get(13)?.parse().ok()?;
+    let cstime: u64 = fields.get(14)?.parse().ok()?;
+    Some(Duration::from_secs_f64(
+        (cutime + cstime) as f64 / CLOCK_TICKS_PER_SEC,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        let status = "Name:\tseren-replicator\nVmPeak:\t  900000 kB\nVmHWM:\t   51200 kB\nVmRSS:\t   40000 kB\n";
+        assert_eq!(parse_peak_memory(status), Some(51200 * 1024));
+        assert_eq!(parse_peak_memory("Name:\tx\n"), None);
+
+        let stat = "4242 (seren (replicator)) S 1 4242 4242 0 -1 4194560 100 200 0 0 \
+                    35 12 250 50 20 0 8 0 1000 0 0";
+        assert_eq!(parse_subprocess_cpu(stat), Some(Duration::from_secs(3)));
+        assert_eq!(parse_subprocess_cpu("4242 (x) S 1"), None);
+    }
+
+    #[test]
+    fn test_target_rate() {
+        let summary = ResourceSummary {
+            source_bytes_read: 0,
+            target_bytes_written: 1000,
+            wall_clock: Duration::from_secs(4),
+            peak_memory_bytes: None,
+            subprocess_cpu: None,
+        };
+        assert_eq!(summary.target_rate(), Some(250.0));
+        let idle = ResourceSummary {
+            target_bytes_written: 0,
+            ..summary
+        };
+        assert_eq!(idle.target_rate(), None);
+    }
+
+    #[test]
+    fn test_describe_transfer() {
+        assert_eq!(
+            describe_transfer(12 * 1024 * 1024, Duration::from_secs(4)),
+            format!(
+                "12.0 MB in {} (3.0 MB/s)",
+                crate::migration::format_duration(Duration::from_secs(4))
+            )
+        );
+    }
+}
@@ -0,0 +1,326 @@
+// ABOUTME: Retry policy of a run, shared by its connection and subprocess retries
+// ABOUTME: Computes capped backoff with jitter, classifies fatal errors, and trips circuit breakers
+
+use rand::Rng;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Error message fragments that indicate retrying cannot help
+///
+/// Authentication and authorization failures will fail the same way on every
+/// attempt, so retrying them only delays the error and risks account lockouts.
+const NON_RETRYABLE_PATTERNS: &[&str] = &[
+    "password authentication failed",
+    "Authentication failed",
+    "no pg_hba.conf entry",
+    "Access denied",
+    "permission denied",
+    "Invalid connection string",
+];
+
+/// Retry behaviour for connections and subprocess invocations
+///
+/// Delays grow exponentially from `base_delay`, are capped at `max_delay`, and
+/// when `jitter` is enabled are randomised between half and the full delay so
+/// that parallel workers do not retry in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retry attempts (0 = no retries, just the initial attempt)
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound for any single delay
+    pub max_delay: Duration,
+    /// Randomise each delay between 50% and 100% of its computed value
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Default policy: 3 retries starting at 1 second, capped at 60 seconds, with jitter
+    pub const DEFAULT: RetryPolicy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(60),
+        jitter: true,
+    };
+
+    /// Delay to wait after the given failed attempt (0-based)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use seren_replicator::retry::RetryPolicy;
+    /// # use std::time::Duration;
+    /// let policy = RetryPolicy { jitter: false, ..RetryPolicy::DEFAULT };
+    /// assert_eq!(policy.delay_for_attempt(0), Duration::from_secs(1));
+    /// assert_eq!(policy.delay_for_attempt(2), Duration::from_secs(4));
+    /// assert_eq!(policy.delay_for_attempt(10), Duration::from_secs(60));
+    /// ```
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.min(31));
+        let delay = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        if self.jitter && !delay.is_zero() {
+            let half = delay / 2;
+            let extra = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+            half + Duration::from_millis(extra)
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// `[retry]` section of replication-config.toml
+///
+/// All fields are optional; missing fields keep their current values.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RetryConfig {
+    pub max_retries: Option<u32>,
+    pub base_delay_secs: Option<u64>,
+    pub max_delay_secs: Option<u64>,
+    pub jitter: Option<bool>,
+}
+
+impl RetryConfig {
+    /// Overlay the fields set in this config onto an existing policy
+    pub fn apply_to(&self, policy: RetryPolicy) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries.unwrap_or(policy.max_retries),
+            base_delay: self
+                .base_delay_secs
+                .map(Duration::from_secs)
+                .unwrap_or(policy.base_delay),
+            max_delay: self
+                .max_delay_secs
+                .map(Duration::from_secs)
+                .unwrap_or(policy.max_delay),
+            jitter: self.jitter.unwrap_or(policy.jitter),
+        }
+    }
+}
+
+/// Whether an error may succeed if the operation is retried
+///
+/// Returns false for authentication and authorization failures anywhere in the
+/// error chain, so callers fail fast instead of retrying a bad password, and for
+/// catalogued errors ([`crate::errors::ReplicatorError`]) that need a config change.
+///
+/// # Examples
+///
+/// ```
+/// # use seren_replicator::retry::is_retryable;
+/// assert!(is_retryable(&anyhow::anyhow!("Connection refused")));
+/// assert!(!is_retryable(&anyhow::anyhow!("password authentication failed for user \"app\"")));
+/// ```
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    if crate::errors::find(error).is_some_and(|e| !e.is_retryable()) {
+        return false;
+    }
+    !error.chain().any(|cause| {
+        let message = cause.to_string();
+        NON_RETRYABLE_PATTERNS
+            .iter()
+            .any(|pattern| message.contains(pattern))
+    })
+}
+
+/// State of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Operations run at their normal cadence
+    Closed,
+    /// Too many consecutive failures; probe only at the long open interval
+    Open,
+}
+
+/// Circuit breaker for loops that repeatedly probe an endpoint
+///
+/// After `failure_threshold` consecutive failures the circuit opens and callers
+/// should wait `open_interval` between probes instead of their normal cadence, so
+/// a down endpoint is not hammered. The first successful probe closes the circuit.
+///
+/// # Examples
+///
+/// ```
+/// # use seren_replicator::retry::{CircuitBreaker, CircuitState};
+/// # use std::time::Duration;
+/// let mut breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+/// assert!(!breaker.record_failure());
+/// assert!(breaker.record_failure()); // second failure trips the breaker
+/// assert_eq!(breaker.state(), CircuitState::Open);
+/// assert_eq!(breaker.next_delay(Duration::from_secs(2)), Duration::from_secs(30));
+/// assert!(breaker.record_success()); // recovered
+/// assert_eq!(breaker.state(), CircuitState::Closed);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_interval: Duration,
+    consecutive_failures: u32,
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    /// Create a closed breaker that opens after `failure_threshold` consecutive failures
+    pub fn new(failure_threshold: u32, open_interval: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_interval,
+            consecutive_failures: 0,
+            state: CircuitState::Closed,
+        }
+    }
+
+    /// Current breaker state
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Number of failures since the last success
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Record a failed probe
+    ///
+    /// Returns true if this failure tripped the breaker (Closed → Open), so the
+    /// caller can raise an alert exactly once per outage.
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.state == CircuitState::Closed && self.consecutive_failures >= self.failure_threshold
+        {
+            self.state = CircuitState::Open;
+            return true;
+        }
+        false
+    }
+
+    /// Record a successful probe
+    ///
+    /// Returns true if the breaker was open and has now closed (endpoint recovered).
+    pub fn record_success(&mut self) -> bool {
+        self.consecutive_failures = 0;
+        let recovered = self.state == CircuitState::Open;
+        self.state = CircuitState::Closed;
+        recovered
+    }
+
+    /// Delay before the next probe given the caller's normal cadence
+    pub fn next_delay(&self, normal: Duration) -> Duration {
+        match self.state {
+            CircuitState::Closed => normal,
+            CircuitState::Open => self.open_interval.max(normal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_and_caps() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(3),
+            jitter: false,
+        };
+        assert_eq!(policy.delay_for_attempt(0), Duration::from_millis(500));
+        assert_eq!(policy.delay_for_attempt(1), Duration::from_secs(1));
+        assert_eq!(policy.delay_for_attempt(2), Duration::from_secs(2));
+        assert_eq!(policy.delay_for_attempt(3), Duration::from_secs(3));
+        assert_eq!(policy.delay_for_attempt(u32::MAX), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = RetryPolicy {
+            jitter: true,
+            ..RetryPolicy::DEFAULT
+        };
+        for _ in 0..100 {
+            let delay = policy.delay_for_attempt(2);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn test_config_overlay() {
+        let config = RetryConfig {
+            max_retries: Some(7),
+            base_delay_secs: None,
+            max_delay_secs: Some(120),
+            jitter: Some(false),
+        };
+        let policy = config.apply_to(RetryPolicy::DEFAULT);
+        assert_eq!(policy.max_retries, 7);
+        assert_eq!(policy.base_delay, Duration::from_secs(1));
+        assert_eq!(policy.max_delay, Duration::from_secs(120));
+        assert!(!policy.jitter);
+    }
+
+    #[test]
+    fn test_non_retryable_errors_in_chain() {
+        let auth = anyhow::anyhow!("Authentication failed: Invalid username or password.")
+            .context("Failed to connect after retries");
+        assert!(!is_retryable(&auth));
+
+        let hba = anyhow::anyhow!("Access denied: No pg_hba.conf entry for host.");
+        assert!(!is_retryable(&hba));
+
+        let timeout = anyhow::anyhow!("Connection timeout: Database server did not respond");
+        assert!(is_retryable(&timeout));
+
+        let catalogued = anyhow::Error::from(crate::errors::ReplicatorError::VersionMismatch {
+            source_version: "15.4".to_string(),
+            target_version: "16.1".to_string(),
+        });
+        assert!(!is_retryable(&catalogued));
+    }
+
+    #[test]
+    fn test_circuit_breaker_trips_once_and_recovers() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // Further failures while open do not re-trip
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.consecutive_failures(), 4);
+        assert_eq!(
+            breaker.next_delay(Duration::from_secs(2)),
+            Duration::from_secs(60)
+        );
+
+        assert!(breaker.record_success());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(
+            breaker.next_delay(Duration::from_secs(2)),
+            Duration::from_secs(2)
+        );
+        assert!(!breaker.record_success());
+    }
+
+    #[test]
+    fn test_circuit_breaker_success_resets_count() {
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_success();
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,35 @@
+// ABOUTME: Connection, retry, audit, hook, and resource settings of one run
+// ABOUTME: Built once from the config file and CLI flags and passed to every command
+
+use crate::audit::AuditSettings;
+use crate::chaos::Chaos;
+use crate::hooks::HookSet;
+use crate::memory::MemoryBudget;
+use crate::parallelism::JobBudget;
+use crate::postgres::neon::NeonSettings;
+use crate::postgres::pooler::PoolerSettings;
+use crate::retry::RetryPolicy;
+
+/// Settings shared by every command of one run
+///
+/// Two runs in one process each carry their own settings; clones share the
+/// memory and job budgets and the chaos faults already fired.
+#[derive(Debug, Clone, Default)]
+pub struct RunSettings {
+    /// Retries for connections and pg_dump/pg_restore/psql
+    pub retry: RetryPolicy,
+    /// How the target is reached when it sits behind a connection pooler
+    pub pooler: PoolerSettings,
+    /// Neon heartbeat and backup-branch behavior
+    pub neon: NeonSettings,
+    /// Where destructive operations are recorded
+    pub audit: AuditSettings,
+    /// `[hooks]` to run at each stage
+    pub hooks: HookSet,
+    /// Budget of concurrent pg_dump/pg_restore and verify jobs
+    pub jobs: JobBudget,
+    /// Budget of buffered row memory
+    pub memory: MemoryBudget,
+    /// Faults injected for resilience testing
+    pub chaos: Chaos,
+}
@@ -1,10 +1,11 @@
 // ABOUTME: SQLite to JSONB type conversion for PostgreSQL storage
 // ABOUTME: Handles all SQLite types with lossless conversion and BLOB base64 encoding
 
+use crate::jsonb::row_errors::{ConvertedRows, RowError, RowErrorPolicy};
 use anyhow::{Context, Result};
 use rusqlite::Connection;
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Convert a single SQLite value to JSON
 ///
@@ -115,14 +116,24 @@ pub fn sqlite_row_to_json(row: HashMap<String, rusqlite::types::Value>) -> Resul
 /// - Otherwise, use SQLite's rowid (every table has one)
 /// - IDs are converted to strings for consistency
 ///
+/// Rows that cannot be read or converted are handled by `on_error`
+/// (`--on-row-error`).
+///
 /// # Arguments
 ///
 /// * `conn` - SQLite database connection
 /// * `table` - Table name (must be validated)
+/// * `on_error` - What to do with a row that cannot be converted
 ///
 /// # Returns
 ///
-/// Vector of (id_string, json_data) tuples for batch insert
+/// (id_string, json_data) tuples for batch insert, plus the rows that were
+/// skipped or stubbed
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read, or a row cannot be converted
+/// under the `fail` policy.
 ///
 /// # Security
 ///
@@ -131,41 +142,55 @@ pub fn sqlite_row_to_json(row: HashMap<String, rusqlite::types::Value>) -> Resul
 /// # Examples
 ///
 /// ```no_run
+/// # use seren_replicator::jsonb::row_errors::RowErrorPolicy;
 /// # use seren_replicator::sqlite::{open_sqlite, converter::convert_table_to_jsonb};
 /// # use seren_replicator::jsonb::validate_table_name;
 /// # fn example() -> anyhow::Result<()> {
 /// let conn = open_sqlite("database.db")?;
 /// let table = "users";
 /// validate_table_name(table)?;
-/// let rows = convert_table_to_jsonb(&conn, table)?;
-/// println!("Converted {} rows to JSONB", rows.len());
+/// let converted = convert_table_to_jsonb(&conn, table, RowErrorPolicy::Fail)?;
+/// println!("Converted {} rows to JSONB", converted.rows.len());
 /// # Ok(())
 /// # }
 /// ```
-pub fn convert_table_to_jsonb(conn: &Connection, table: &str) -> Result<Vec<(String, JsonValue)>> {
+pub fn convert_table_to_jsonb(
+    conn: &Connection,
+    table: &str,
+    on_error: RowErrorPolicy,
+) -> Result<ConvertedRows> {
     // Validate table name
     crate::jsonb::validate_table_name(table).context("Invalid table name for JSONB conversion")?;
 
     tracing::info!("Converting SQLite table '{}' to JSONB", table);
 
     // Read all rows using our reader
-    let rows = crate::sqlite::reader::read_table_data(conn, table)
+    let rows = crate::sqlite::reader::read_table_rows(conn, table)
         .with_context(|| format!("Failed to read data from table '{}'", table))?;
 
     // Detect ID column
     let id_column = detect_id_column(conn, table)?;
 
-    let mut result = Vec::with_capacity(rows.len());
+    let mut converted = ConvertedRows {
+        rows: Vec::with_capacity(rows.len()),
+        errors: Vec::new(),
+    };
 
     for (row_num, row) in rows.into_iter().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(error) => {
+                converted.reject(on_error, table, error)?;
+                continue;
+            }
+        };
+
         // Extract or generate ID
         let id = if let Some(ref id_col) = id_column {
             // Use the specified ID column
-            match row.get(id_col) {
-                Some(rusqlite::types::Value::Integer(i)) => i.to_string(),
-                Some(rusqlite::types::Value::Text(s)) => s.clone(),
-                Some(rusqlite::types::Value::Real(f)) => f.to_string(),
-                _ => {
+            match id_value_to_string(row.get(id_col)) {
+                Some(id) => id,
+                None => {
                     // Fallback to row number if ID is NULL or unsupported type
                     tracing::warn!(
                         "Row {} in table '{}' has invalid ID type, using row number",
@@ -182,24 +207,80 @@ pub fn convert_table_to_jsonb(conn: &Connection, table: &str) -> Result<Vec<(Str
         };
 
         // Convert row to JSON
-        let json_data = sqlite_row_to_json(row).with_context(|| {
-            format!(
-                "Failed to convert row {} in table '{}' to JSON",
-                row_num + 1,
-                table
-            )
-        })?;
-
-        result.push((id, json_data));
+        let json_data = match sqlite_row_to_json(row) {
+            Ok(json_data) => json_data,
+            Err(e) => {
+                let error = RowError {
+                    row_number: row_num as u64 + 1,
+                    column: None,
+                    raw: Vec::new(),
+                    error: format!("{:#}", e),
+                };
+                converted.reject(on_error, table, error)?;
+                continue;
+            }
+        };
+
+        converted.rows.push((id, json_data));
     }
 
     tracing::info!(
         "Converted {} rows from table '{}' to JSONB",
-        result.len(),
+        converted.rows.len(),
         table
     );
 
-    Ok(result)
+    Ok(converted)
+}
+
+/// The JSONB row ID for an ID column value; None for NULL and unsupported types
+fn id_value_to_string(value: Option<&rusqlite::types::Value>) -> Option<String> {
+    match value? {
+        rusqlite::types::Value::Integer(i) => Some(i.to_string()),
+        rusqlite::types::Value::Text(s) => Some(s.clone()),
+        rusqlite::types::Value::Real(f) => Some(f.to_string()),
+        _ => None,
+    }
+}
+
+/// Read the JSONB row IDs of a table without converting its rows
+///
+/// The IDs are the ones [`convert_table_to_jsonb`] assigns, so they can be
+/// compared with the `id` column of the table's JSONB copy.
+///
+/// # Returns
+///
+/// None when IDs are row numbers (no ID column, or a NULL or unsupported ID
+/// value), since those change meaning when rows are deleted.
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be read.
+pub fn read_table_ids(conn: &Connection, table: &str) -> Result<Option<HashSet<String>>> {
+    crate::jsonb::validate_table_name(table).context("Invalid table name for ID reading")?;
+    let Some(id_column) = detect_id_column(conn, table)? else {
+        return Ok(None);
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT \"{}\" FROM \"{}\"",
+            id_column.replace('"', "\"\""),
+            table
+        ))
+        .with_context(|| format!("Failed to read IDs from table '{}'", table))?;
+    let values = stmt
+        .query_map([], |row| row.get::<_, rusqlite::types::Value>(0))
+        .with_context(|| format!("Failed to read IDs from table '{}'", table))?;
+    let mut ids = HashSet::new();
+    for value in values {
+        let value =
+            value.with_context(|| format!("Failed to read an ID from table '{}'", table))?;
+        match id_value_to_string(Some(&value)) {
+            Some(id) => ids.insert(id),
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(ids))
 }
 
 /// Detect the ID column for a table
@@ -316,6 +397,29 @@ mod tests {
         assert_eq!(json["notes"], JsonValue::Null);
     }
 
+    #[test]
+    fn test_read_table_ids_match_converted_ids() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (ID INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO users VALUES (3, 'a'), (7, 'b');
+             CREATE TABLE log (message TEXT);
+             INSERT INTO log VALUES ('x');",
+        )
+        .unwrap();
+
+        let converted: HashSet<String> =
+            convert_table_to_jsonb(&conn, "users", RowErrorPolicy::Fail)
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+        assert_eq!(read_table_ids(&conn, "users").unwrap(), Some(converted));
+        // Row-number IDs cannot be reconciled
+        assert_eq!(read_table_ids(&conn, "log").unwrap(), None);
+    }
+
     #[test]
     fn test_convert_table_to_jsonb() {
         // Create a test database
@@ -346,7 +450,9 @@ mod tests {
         .unwrap();
 
         // Convert to JSONB
-        let result = convert_table_to_jsonb(&conn, "users").unwrap();
+        let result = convert_table_to_jsonb(&conn, "users", RowErrorPolicy::Fail)
+            .unwrap()
+            .rows;
 
         assert_eq!(result.len(), 2);
 
@@ -386,7 +492,9 @@ mod tests {
         .unwrap();
 
         // Convert to JSONB
-        let result = convert_table_to_jsonb(&conn, "logs").unwrap();
+        let result = convert_table_to_jsonb(&conn, "logs", RowErrorPolicy::Fail)
+            .unwrap()
+            .rows;
 
         assert_eq!(result.len(), 1);
 
@@ -417,7 +525,9 @@ mod tests {
         )
         .unwrap();
 
-        let result = convert_table_to_jsonb(&conn, "users").unwrap();
+        let result = convert_table_to_jsonb(&conn, "users", RowErrorPolicy::Fail)
+            .unwrap()
+            .rows;
 
         assert_eq!(result.len(), 1);
         let (_, json) = &result[0];
@@ -447,7 +557,9 @@ mod tests {
         )
         .unwrap();
 
-        let result = convert_table_to_jsonb(&conn, "files").unwrap();
+        let result = convert_table_to_jsonb(&conn, "files", RowErrorPolicy::Fail)
+            .unwrap()
+            .rows;
 
         assert_eq!(result.len(), 1);
         let (_, json) = &result[0];
@@ -479,7 +591,33 @@ mod tests {
         conn.execute("CREATE TABLE empty (id INTEGER PRIMARY KEY)", [])
             .unwrap();
 
-        let result = convert_table_to_jsonb(&conn, "empty").unwrap();
+        let result = convert_table_to_jsonb(&conn, "empty", RowErrorPolicy::Fail)
+            .unwrap()
+            .rows;
         assert_eq!(result.len(), 0);
     }
+
+    #[test]
+    fn test_convert_table_invalid_utf8_row() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE notes (id INTEGER, body TEXT)", [])
+            .unwrap();
+        conn.execute("INSERT INTO notes VALUES (1, 'fine')", [])
+            .unwrap();
+        conn.execute("INSERT INTO notes VALUES (2, CAST(X'FFFE' AS TEXT))", [])
+            .unwrap();
+
+        let err = convert_table_to_jsonb(&conn, "notes", RowErrorPolicy::Fail).unwrap_err();
+        assert!(format!("{:#}", err).contains("--on-row-error"));
+
+        let skipped = convert_table_to_jsonb(&conn, "notes", RowErrorPolicy::Skip).unwrap();
+        assert_eq!(skipped.rows.len(), 1);
+        assert_eq!(skipped.errors[0].row_number, 2);
+        assert_eq!(skipped.errors[0].column.as_deref(), Some("body"));
+        assert_eq!(skipped.errors[0].raw, vec![0xFF, 0xFE]);
+
+        let stubbed = convert_table_to_jsonb(&conn, "notes", RowErrorPolicy::Stub).unwrap();
+        assert_eq!(stubbed.rows.len(), 2);
+        assert_eq!(stubbed.rows[1].0, "_row_error_2");
+    }
 }
@@ -1,7 +1,9 @@
 // ABOUTME: SQLite database introspection and data reading
-// ABOUTME: Functions to list tables, count rows, and read table data
+// ABOUTME: Functions to list tables, count and measure rows, and read table data
 
+use crate::jsonb::row_errors::RowError;
 use anyhow::{Context, Result};
+use rusqlite::types::ValueRef;
 use rusqlite::Connection;
 use std::collections::HashMap;
 
@@ -104,6 +106,41 @@ pub fn get_table_row_count(conn: &Connection, table: &str) -> Result<usize> {
     Ok(count as usize)
 }
 
+/// Average size in bytes of a table's rows, summing each column's stored value
+///
+/// Numbers count as their text form and NULLs as nothing, so this tracks the
+/// converted JSON payload more closely than SQLite's page usage. None for an
+/// empty table.
+///
+/// # Errors
+///
+/// Returns an error if the table name is invalid or the table cannot be read.
+pub fn get_average_row_size(conn: &Connection, table: &str) -> Result<Option<u64>> {
+    crate::jsonb::validate_table_name(table).context("Invalid table name for row size query")?;
+
+    let sizes: Option<String> = conn
+        .query_row(
+            "SELECT group_concat('coalesce(length(CAST(\"' || replace(name, '\"', '\"\"') || '\" AS BLOB)), 0)', ' + ')
+             FROM pragma_table_info(?1)",
+            [table],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("Failed to read the columns of table '{}'", table))?;
+    let Some(sizes) = sizes else {
+        return Ok(None);
+    };
+
+    // Note: table name is validated above, and column names are quoted
+    let average: Option<f64> = conn
+        .query_row(
+            &format!("SELECT avg({}) FROM \"{}\"", sizes, table),
+            [],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("Failed to measure rows in table '{}'", table))?;
+    Ok(average.map(|average| average.round() as u64))
+}
+
 /// Read all data from a SQLite table
 ///
 /// Returns all rows as a vector of HashMaps, where each HashMap maps
@@ -145,6 +182,43 @@ pub fn read_table_data(
     conn: &Connection,
     table: &str,
 ) -> Result<Vec<HashMap<String, rusqlite::types::Value>>> {
+    read_table_rows(conn, table)?
+        .into_iter()
+        .map(|row| {
+            row.map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to read row {} from table '{}': {}",
+                    e.row_number,
+                    table,
+                    e.error
+                )
+            })
+        })
+        .collect()
+}
+
+/// Read all data from a SQLite table, keeping rows that cannot be read
+///
+/// Like [`read_table_data`], but a row with an unreadable value (such as
+/// TEXT that is not valid UTF-8) comes back as a [`RowError`] carrying the
+/// value's raw bytes instead of failing the whole table.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection
+/// * `table` - Table name (should be validated)
+///
+/// # Returns
+///
+/// One entry per source row, in table order
+///
+/// # Errors
+///
+/// Returns an error if the table name is invalid or the table cannot be queried.
+pub fn read_table_rows(
+    conn: &Connection,
+    table: &str,
+) -> Result<Vec<std::result::Result<HashMap<String, rusqlite::types::Value>, RowError>>> {
     // Validate table name to prevent SQL injection
     crate::jsonb::validate_table_name(table).context("Invalid table name for data reading")?;
 
@@ -168,18 +242,32 @@ pub fn read_table_data(
     );
 
     // Read all rows
+    let mut row_number = 0u64;
     let rows = stmt
         .query_map([], |row| {
+            row_number += 1;
             let mut row_map = HashMap::new();
 
             for (idx, col_name) in column_names.iter().enumerate() {
-                // Get value from row
                 // rusqlite::types::Value represents all SQLite types
-                let value: rusqlite::types::Value = row.get(idx)?;
+                let value = match row.get_ref(idx)? {
+                    ValueRef::Text(bytes) => match std::str::from_utf8(bytes) {
+                        Ok(text) => rusqlite::types::Value::Text(text.to_string()),
+                        Err(e) => {
+                            return Ok(Err(RowError {
+                                row_number,
+                                column: Some(col_name.clone()),
+                                raw: bytes.to_vec(),
+                                error: format!("TEXT value is not valid UTF-8: {}", e),
+                            }))
+                        }
+                    },
+                    other => other.into(),
+                };
                 row_map.insert(col_name.clone(), value);
             }
 
-            Ok(row_map)
+            Ok(Ok(row_map))
         })
         .with_context(|| format!("Failed to query rows from table '{}'", table))?
         .collect::<Result<Vec<_>, _>>()
@@ -292,6 +380,17 @@ mod tests {
         assert_eq!(posts_count, 1);
     }
 
+    #[test]
+    fn test_get_average_row_size() {
+        let (_temp_dir, db_path) = create_test_db();
+        let conn = Connection::open(db_path).unwrap();
+
+        // (1 + 5 + 17 + 2) + (1 + 3 + 15 + 2) + (1 + 7 + 19 + NULL) over 3 rows
+        assert_eq!(get_average_row_size(&conn, "users").unwrap(), Some(24));
+        conn.execute("CREATE TABLE empty (id INTEGER)", []).unwrap();
+        assert_eq!(get_average_row_size(&conn, "empty").unwrap(), None);
+    }
+
     #[test]
     fn test_get_table_row_count_invalid_table() {
         let (_temp_dir, db_path) = create_test_db();
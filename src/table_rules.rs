@@ -6,6 +6,8 @@ use crate::utils::quote_ident;
 use anyhow::{anyhow, bail, Context, Result};
 use sha2::{Digest, Sha256};
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
 
 /// Represents a fully-qualified table identifier with optional database and schema
 /// Supports parsing from: `database.schema.table`, `schema.table`, or `table`
@@ -27,46 +29,33 @@ impl QualifiedTable {
             bail!("Table specification cannot be empty");
         }
 
-        let parts: Vec<&str> = trimmed.split('.').collect();
-        match parts.len() {
-            1 => {
-                // Just table name: defaults to public schema, no database
-                let table = non_empty(parts[0], "table")?;
-                utils::validate_postgres_identifier(&table)?;
-                Ok(QualifiedTable {
-                    database: None,
-                    schema: "public".to_string(),
-                    table,
-                })
-            }
-            2 => {
-                // schema.table OR database.table
-                // We treat as schema.table for consistency
-                // Can be disambiguated later if database is provided separately
-                let first = non_empty(parts[0], "schema")?;
-                let second = non_empty(parts[1], "table")?;
-                utils::validate_postgres_identifier(&first)?;
-                utils::validate_postgres_identifier(&second)?;
-                Ok(QualifiedTable {
-                    database: None,
-                    schema: first,
-                    table: second,
-                })
-            }
-            3 => {
-                // database.schema.table
-                let database = non_empty(parts[0], "database")?;
-                let schema = non_empty(parts[1], "schema")?;
-                let table = non_empty(parts[2], "table")?;
-                utils::validate_postgres_identifier(&database)?;
-                utils::validate_postgres_identifier(&schema)?;
-                utils::validate_postgres_identifier(&table)?;
-                Ok(QualifiedTable {
-                    database: Some(database),
-                    schema,
-                    table,
-                })
-            }
+        let parts = utils::split_qualified_name(trimmed)
+            .with_context(|| format!("Invalid table specification '{}'", spec))?;
+        let mut names = Vec::with_capacity(parts.len());
+        for part in &parts {
+            names.push(checked_part(part)?);
+        }
+        match names.as_slice() {
+            // Just table name: defaults to public schema, no database
+            [table] => Ok(QualifiedTable {
+                database: None,
+                schema: "public".to_string(),
+                table: table.clone(),
+            }),
+            // schema.table OR database.table
+            // We treat as schema.table for consistency
+            // Can be disambiguated later if database is provided separately
+            [schema, table] => Ok(QualifiedTable {
+                database: None,
+                schema: schema.clone(),
+                table: table.clone(),
+            }),
+            // database.schema.table
+            [database, schema, table] => Ok(QualifiedTable {
+                database: Some(database.clone()),
+                schema: schema.clone(),
+                table: table.clone(),
+            }),
             _ => bail!(
                 "Invalid table specification '{}': must be 'table', 'schema.table', or 'database.schema.table'",
                 spec
@@ -165,10 +154,172 @@ impl TimeFilterRule {
     }
 }
 
+/// Period covered by each partition created for a `--partition-table` rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionInterval {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl PartitionInterval {
+    pub const ALL: [PartitionInterval; 4] = [
+        PartitionInterval::Daily,
+        PartitionInterval::Weekly,
+        PartitionInterval::Monthly,
+        PartitionInterval::Yearly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartitionInterval::Daily => "daily",
+            PartitionInterval::Weekly => "weekly",
+            PartitionInterval::Monthly => "monthly",
+            PartitionInterval::Yearly => "yearly",
+        }
+    }
+
+    /// Field passed to `date_trunc` to find a partition's lower bound
+    pub fn date_trunc_unit(&self) -> &'static str {
+        match self {
+            PartitionInterval::Daily => "day",
+            PartitionInterval::Weekly => "week",
+            PartitionInterval::Monthly => "month",
+            PartitionInterval::Yearly => "year",
+        }
+    }
+
+    /// Width of one partition as an `interval` literal
+    pub fn interval(&self) -> &'static str {
+        match self {
+            PartitionInterval::Daily => "1 day",
+            PartitionInterval::Weekly => "1 week",
+            PartitionInterval::Monthly => "1 month",
+            PartitionInterval::Yearly => "1 year",
+        }
+    }
+
+    /// `to_char` pattern for the partition name suffix (`events_p202401`)
+    pub fn name_format(&self) -> &'static str {
+        match self {
+            PartitionInterval::Daily | PartitionInterval::Weekly => "YYYYMMDD",
+            PartitionInterval::Monthly => "YYYYMM",
+            PartitionInterval::Yearly => "YYYY",
+        }
+    }
+}
+
+impl fmt::Display for PartitionInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PartitionInterval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PartitionInterval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == s.trim().to_ascii_lowercase())
+            .with_context(|| {
+                format!(
+                    "Unknown partition interval '{}'. Expected one of: daily, weekly, monthly, yearly",
+                    s
+                )
+            })
+    }
+}
+
+/// Order in which init restores tables that share a priority (`--copy-order`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyOrder {
+    /// pg_restore's own scheduling
+    #[default]
+    Default,
+    /// Smallest dumped table first, so many tables become usable early
+    SmallestFirst,
+    /// Largest dumped table first, so the long copies start right away
+    LargestFirst,
+}
+
+impl CopyOrder {
+    pub const ALL: [CopyOrder; 3] = [
+        CopyOrder::Default,
+        CopyOrder::SmallestFirst,
+        CopyOrder::LargestFirst,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CopyOrder::Default => "default",
+            CopyOrder::SmallestFirst => "smallest-first",
+            CopyOrder::LargestFirst => "largest-first",
+        }
+    }
+}
+
+impl fmt::Display for CopyOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CopyOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        CopyOrder::ALL
+            .into_iter()
+            .find(|order| order.as_str() == s.trim().to_ascii_lowercase())
+            .with_context(|| {
+                format!(
+                    "Unknown copy order '{}'. Expected one of: default, smallest-first, largest-first",
+                    s
+                )
+            })
+    }
+}
+
+/// Range-partition a table on the target by a date or timestamp column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionRule {
+    pub column: String,
+    pub interval: PartitionInterval,
+}
+
+impl PartitionRule {
+    /// Parse the part after the table name: `range(column):interval`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (strategy, interval) = spec
+            .split_once(':')
+            .with_context(|| format!("Partition rule '{}' must be range(column):interval", spec))?;
+        let strategy = strategy.trim();
+        let column = strategy
+            .strip_prefix("range(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .with_context(|| {
+                format!(
+                    "Unsupported partitioning '{}': only range(column) is supported",
+                    strategy
+                )
+            })?
+            .trim();
+        utils::validate_postgres_identifier(column)?;
+        Ok(PartitionRule {
+            column: column.to_string(),
+            interval: interval.parse()?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TableRuleKind {
     SchemaOnly,
     Predicate(String),
+    /// Copied at init through a user-supplied `SELECT`
+    Transform(String),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -176,6 +327,10 @@ pub struct TableRules {
     schema_only: ScopedTableSet,
     table_filters: ScopedTableMap<String>,
     time_filters: ScopedTableMap<TimeFilterRule>,
+    transforms: ScopedTableMap<String>,
+    partitions: ScopedTableMap<PartitionRule>,
+    priorities: ScopedTableMap<i64>,
+    copy_order: CopyOrder,
 }
 
 type ScopedTableSet = BTreeMap<ScopeKey, BTreeSet<SchemaTableKey>>;
@@ -254,6 +409,74 @@ impl TableRules {
         Ok(())
     }
 
+    /// Copy a table at init through `select` instead of copying it verbatim
+    ///
+    /// The query runs on the source database; its output columns are matched by
+    /// name to the target table's columns.
+    pub fn add_transform(&mut self, qualified: QualifiedTable, select: String) -> Result<()> {
+        if select.trim().is_empty() {
+            bail!(
+                "Transform query cannot be empty for '{}'",
+                qualified.schema_qualified()
+            );
+        }
+        let scope = ScopeKey::from_option(qualified.database.clone());
+        let key = SchemaTableKey::from_qualified(&qualified);
+        ensure_schema_only_free(&self.schema_only, &qualified, "transform")?;
+        let has_filter = self
+            .table_filters
+            .get(&scope)
+            .is_some_and(|inner| inner.contains_key(&key))
+            || self
+                .time_filters
+                .get(&scope)
+                .is_some_and(|inner| inner.contains_key(&key));
+        if has_filter {
+            bail!(
+                "Cannot apply transform to table '{}' because a table or time filter already exists; \
+                 put the WHERE clause in the transform query instead",
+                qualified.schema_qualified()
+            );
+        }
+        self.transforms.entry(scope).or_default().insert(
+            key,
+            select.trim().trim_end_matches(';').trim_end().to_string(),
+        );
+        Ok(())
+    }
+
+    /// Create the table on the target partitioned by range of `rule.column`
+    pub fn add_partition_rule(
+        &mut self,
+        qualified: QualifiedTable,
+        rule: PartitionRule,
+    ) -> Result<()> {
+        let scope = ScopeKey::from_option(qualified.database.clone());
+        let key = SchemaTableKey::from_qualified(&qualified);
+        self.partitions.entry(scope).or_default().insert(key, rule);
+        Ok(())
+    }
+
+    /// Restore a table at init ahead of tables with a lower priority
+    ///
+    /// Tables without a priority rank as 0, so a negative priority moves a
+    /// table behind the rest.
+    pub fn add_priority(&mut self, qualified: QualifiedTable, priority: i64) -> Result<()> {
+        let scope = ScopeKey::from_option(qualified.database.clone());
+        let key = SchemaTableKey::from_qualified(&qualified);
+        ensure_schema_only_free(&self.schema_only, &qualified, "priority")?;
+        self.priorities
+            .entry(scope)
+            .or_default()
+            .insert(key, priority);
+        Ok(())
+    }
+
+    /// Order tables of equal priority by size
+    pub fn set_copy_order(&mut self, order: CopyOrder) {
+        self.copy_order = order;
+    }
+
     pub fn apply_schema_only_cli(&mut self, specs: &[String]) -> Result<()> {
         for spec in specs {
             let qualified = QualifiedTable::parse(spec)?;
@@ -300,6 +523,51 @@ impl TableRules {
         Ok(())
     }
 
+    pub fn apply_transform_cli(&mut self, specs: &[String]) -> Result<()> {
+        for spec in specs {
+            let (table_part, select) = spec
+                .split_once(':')
+                .with_context(|| format!("Transform '{}' missing ':' separator", spec))?;
+            if select.trim().is_empty() {
+                bail!("Transform '{}' must include a SELECT query after ':'", spec);
+            }
+            let qualified = QualifiedTable::parse(table_part)?;
+            self.add_transform(qualified, select.trim().to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_partition_cli(&mut self, specs: &[String]) -> Result<()> {
+        for spec in specs {
+            let (table_part, rule) = spec.split_once(':').with_context(|| {
+                format!(
+                    "Partition rule '{}' must be table:range(column):interval",
+                    spec
+                )
+            })?;
+            let qualified = QualifiedTable::parse(table_part)?;
+            self.add_partition_rule(qualified, PartitionRule::parse(rule)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_priority_cli(&mut self, specs: &[String]) -> Result<()> {
+        for spec in specs {
+            let (table_part, priority) = spec.rsplit_once(':').with_context(|| {
+                format!("Table priority '{}' must be [db.]table:priority", spec)
+            })?;
+            let priority: i64 = priority.trim().parse().with_context(|| {
+                format!(
+                    "Table priority '{}' must end in an integer priority (e.g. orders:10)",
+                    spec
+                )
+            })?;
+            let qualified = QualifiedTable::parse(table_part)?;
+            self.add_priority(qualified, priority)?;
+        }
+        Ok(())
+    }
+
     pub fn schema_only_tables(&self, database: &str) -> Vec<String> {
         collect_tables(&self.schema_only, database)
     }
@@ -317,6 +585,55 @@ impl TableRules {
         lookup_scoped(&self.time_filters, database, schema, table)
     }
 
+    pub fn transform(&self, database: &str, schema: &str, table: &str) -> Option<&String> {
+        lookup_scoped(&self.transforms, database, schema, table)
+    }
+
+    /// Tables copied through a transform query, as (`"schema"."table"`, query)
+    pub fn transform_tables(&self, database: &str) -> Vec<(String, String)> {
+        let schema_only: BTreeSet<String> = self.schema_only_tables(database).into_iter().collect();
+        scoped_map_values(&self.transforms, database)
+            .into_iter()
+            .filter(|(table, _)| !schema_only.contains(table))
+            .collect()
+    }
+
+    /// Priority of a table at init; tables without one rank as 0
+    pub fn priority(&self, database: &str, schema: &str, table: &str) -> i64 {
+        lookup_scoped(&self.priorities, database, schema, table)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn copy_order(&self) -> CopyOrder {
+        self.copy_order
+    }
+
+    /// Whether init should schedule the tables of `database` itself rather
+    /// than leave the order to pg_restore
+    pub fn orders_copy(&self, database: &str) -> bool {
+        self.copy_order != CopyOrder::Default
+            || [ScopeKey::Global, ScopeKey::database(database)]
+                .iter()
+                .any(|scope| self.priorities.get(scope).is_some_and(|t| !t.is_empty()))
+    }
+
+    /// Tables to partition on the target, as (schema, table, rule)
+    pub fn partition_rules(&self, database: &str) -> Vec<(String, String, PartitionRule)> {
+        let mut rules = BTreeMap::new();
+        for scope in [ScopeKey::Global, ScopeKey::database(database)] {
+            if let Some(tables) = self.partitions.get(&scope) {
+                for (key, rule) in tables {
+                    rules.insert((key.schema.clone(), key.table.clone()), rule.clone());
+                }
+            }
+        }
+        rules
+            .into_iter()
+            .map(|((schema, table), rule)| (schema, table, rule))
+            .collect()
+    }
+
     pub fn predicate_tables(&self, database: &str) -> Vec<(String, String)> {
         let schema_only: BTreeSet<String> = self.schema_only_tables(database).into_iter().collect();
         let mut combined = BTreeMap::new();
@@ -347,6 +664,9 @@ impl TableRules {
         if has_schema_only_rule(&self.schema_only, database, schema, table) {
             return Some(TableRuleKind::SchemaOnly);
         }
+        if let Some(select) = self.transform(database, schema, table) {
+            return Some(TableRuleKind::Transform(select.clone()));
+        }
         if let Some(predicate) = self.table_filter(database, schema, table) {
             return Some(TableRuleKind::Predicate(predicate.clone()));
         }
@@ -360,8 +680,18 @@ impl TableRules {
         merge_sets(&mut self.schema_only, other.schema_only);
         merge_maps(&mut self.table_filters, other.table_filters);
         merge_maps(&mut self.time_filters, other.time_filters);
+        merge_maps(&mut self.transforms, other.transforms);
+        merge_maps(&mut self.partitions, other.partitions);
+        merge_maps(&mut self.priorities, other.priorities);
+        if other.copy_order != CopyOrder::Default {
+            self.copy_order = other.copy_order;
+        }
     }
 
+    /// Hash of the rules that decide what is copied
+    ///
+    /// Priorities and the copy order only decide when a table is copied, so
+    /// changing them does not invalidate an init checkpoint.
     pub fn fingerprint(&self) -> String {
         let mut hasher = Sha256::new();
         hash_scoped_set(&mut hasher, &self.schema_only);
@@ -369,20 +699,42 @@ impl TableRules {
         hash_scoped_map(&mut hasher, &self.time_filters, |value| {
             format!("{}|{}", value.column, value.interval)
         });
+        hash_scoped_map(&mut hasher, &self.transforms, |value| value.clone());
+        hash_scoped_map(&mut hasher, &self.partitions, |value| {
+            format!("range({})|{}", value.column, value.interval)
+        });
         format!("{:x}", hasher.finalize())
     }
 
     pub fn is_empty(&self) -> bool {
-        self.schema_only.is_empty() && self.table_filters.is_empty() && self.time_filters.is_empty()
+        self.schema_only.is_empty()
+            && self.table_filters.is_empty()
+            && self.time_filters.is_empty()
+            && self.transforms.is_empty()
+            && self.partitions.is_empty()
+            && self.priorities.is_empty()
+            && self.copy_order == CopyOrder::Default
     }
 }
 
-fn non_empty(value: &str, label: &str) -> Result<String> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        bail!("{} name cannot be empty", label);
+/// Validate one part of a table specification
+///
+/// Unquoted parts must be plain identifiers; quoted parts (`"Order.Items"`) may
+/// hold any character and are only checked against the 63-byte name limit.
+fn checked_part(part: &utils::NamePart) -> Result<String> {
+    if !part.quoted {
+        let name = part.name.trim();
+        utils::validate_postgres_identifier(name)?;
+        return Ok(name.to_string());
+    }
+    if part.name.len() > 63 {
+        bail!(
+            "Identifier '{}' exceeds maximum length of 63 characters (got {})",
+            part.name,
+            part.name.len()
+        );
     }
-    Ok(trimmed.to_string())
+    Ok(part.name.clone())
 }
 
 fn collect_tables(map: &ScopedTableSet, database: &str) -> Vec<String> {
@@ -625,6 +977,21 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("must be"));
     }
 
+    #[test]
+    fn test_qualified_table_parse_quoted_parts() {
+        let t = QualifiedTable::parse(r#"db1."Sales"."order.items""#).unwrap();
+        assert_eq!(t.database, Some("db1".to_string()));
+        assert_eq!(t.schema, "Sales");
+        assert_eq!(t.table, "order.items");
+        assert_eq!(t.schema_qualified(), r#""Sales"."order.items""#);
+
+        let t = QualifiedTable::parse(r#""user""#).unwrap();
+        assert_eq!(t.schema_qualified(), r#""public"."user""#);
+
+        assert!(QualifiedTable::parse("public.order-items").is_err());
+        assert!(QualifiedTable::parse(r#"public."orders"#).is_err());
+    }
+
     #[test]
     fn test_qualified_table_schema_qualified() {
         let t = QualifiedTable::new(None, "analytics".into(), "orders".into());
@@ -762,6 +1129,74 @@ mod tests {
             .is_err());
     }
 
+    #[test]
+    fn transforms_conflict_with_filters_and_schema_only() {
+        let mut rules = TableRules::default();
+        rules
+            .apply_transform_cli(&[
+                "db1.users:SELECT id, lower(email) AS email FROM public.users".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(
+            rules.transform_tables("db1"),
+            vec![(
+                "\"db1\".\"users\"".to_string(),
+                "SELECT id, lower(email) AS email FROM public.users".to_string()
+            )]
+        );
+        assert!(matches!(
+            rules.rule_for_table("any", "db1", "users"),
+            Some(TableRuleKind::Transform(_))
+        ));
+        assert!(rules
+            .apply_transform_cli(&["db1.users: ".to_string()])
+            .is_err());
+
+        let mut rules = TableRules::default();
+        rules
+            .apply_table_filter_cli(&["db1.orders:id > 10".to_string()])
+            .unwrap();
+        assert!(rules
+            .apply_transform_cli(&["db1.orders:SELECT * FROM public.orders".to_string()])
+            .is_err());
+
+        let mut rules = TableRules::default();
+        rules
+            .apply_schema_only_cli(&["db1.audit".to_string()])
+            .unwrap();
+        assert!(rules
+            .apply_transform_cli(&["db1.audit:SELECT * FROM public.audit".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn partition_rules_parse() {
+        let mut rules = TableRules::default();
+        rules
+            .apply_partition_cli(&["analytics.events:range(created_at):monthly".to_string()])
+            .unwrap();
+        assert_eq!(
+            rules.partition_rules("db1"),
+            vec![(
+                "analytics".to_string(),
+                "events".to_string(),
+                PartitionRule {
+                    column: "created_at".to_string(),
+                    interval: PartitionInterval::Monthly,
+                }
+            )]
+        );
+        assert!(rules
+            .apply_partition_cli(&["events:list(region):monthly".to_string()])
+            .is_err());
+        assert!(rules
+            .apply_partition_cli(&["events:range(created_at):hourly".to_string()])
+            .is_err());
+        assert!(rules
+            .apply_partition_cli(&["events:range(created_at)".to_string()])
+            .is_err());
+    }
+
     #[test]
     fn predicate_tables_include_time_filters() {
         let mut rules = TableRules::default();
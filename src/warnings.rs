@@ -0,0 +1,241 @@
+// ABOUTME: Warning budget - collects every non-fatal warning logged during a run for the end-of-run summary
+// ABOUTME: A tracing layer records WARN events; `--strict` turns a non-empty budget into a failure
+
+use anyhow::{bail, Result};
+use std::fmt;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Distinct warnings kept for the summary; later new messages are only counted
+pub const MAX_DISTINCT_WARNINGS: usize = 500;
+
+static COLLECTED: Mutex<WarningBudget> = Mutex::new(WarningBudget::new());
+
+/// The warnings of a run, each distinct message once with how often it occurred
+///
+/// A run that logs one warning per row must not grow without bound, so only
+/// the first [`MAX_DISTINCT_WARNINGS`] messages are kept; the rest are counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarningBudget {
+    /// Distinct messages with their counts, in first-seen order
+    pub warnings: Vec<(String, usize)>,
+    /// Occurrences of messages past the cap
+    pub unlisted: usize,
+}
+
+impl WarningBudget {
+    pub const fn new() -> Self {
+        WarningBudget {
+            warnings: Vec::new(),
+            unlisted: 0,
+        }
+    }
+
+    fn record(&mut self, message: &str) {
+        if let Some((_, count)) = self.warnings.iter_mut().find(|(seen, _)| seen == message) {
+            *count += 1;
+        } else if self.warnings.len() < MAX_DISTINCT_WARNINGS {
+            self.warnings.push((message.to_string(), 1));
+        } else {
+            self.unlisted += 1;
+        }
+    }
+
+    /// Every warning logged, repeats included
+    pub fn total(&self) -> usize {
+        self.warnings.iter().map(|(_, count)| count).sum::<usize>() + self.unlisted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// The distinct messages, in first-seen order
+    pub fn messages(&self) -> Vec<String> {
+        self.warnings
+            .iter()
+            .map(|(message, _)| message.clone())
+            .collect()
+    }
+}
+
+/// Add a warning to the budget
+///
+/// [`WarningLayer`] calls this for every WARN event; the log's indentation
+/// and leading `⚠` are dropped so the same warning reads the same everywhere.
+pub fn record(message: &str) {
+    let message = message.trim_start().trim_start_matches('⚠').trim();
+    if message.is_empty() {
+        return;
+    }
+    COLLECTED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .record(message);
+}
+
+/// Every warning recorded so far
+pub fn collected() -> WarningBudget {
+    COLLECTED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Records every WARN event from this crate for the end-of-run summary
+///
+/// Warnings from dependencies (driver and TLS internals) are not the run's
+/// warnings and are left to the log. Add it to the subscriber without the log filter, so `RUST_LOG=error`
+/// hides warnings from the log but not from the budget.
+pub struct WarningLayer;
+
+impl<S: Subscriber> Layer<S> for WarningLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if let Some(message) = warning_message(event) {
+            record(&message);
+        }
+    }
+}
+
+/// The message of a WARN event from this crate without its leading `⚠`
+///
+/// Returns `None` for other levels and for events from dependencies.
+pub fn warning_message(event: &Event<'_>) -> Option<String> {
+    let metadata = event.metadata();
+    if *metadata.level() != Level::WARN || !metadata.target().starts_with(CRATE_TARGET) {
+        return None;
+    }
+    let mut message = MessageVisitor(String::new());
+    event.record(&mut message);
+    let message = message.0.trim_start().trim_start_matches('⚠').trim();
+    (!message.is_empty()).then(|| message.to_string())
+}
+
+/// Target prefix of events logged by the library and the binary
+const CRATE_TARGET: &str = "seren_replicator";
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = value.to_string();
+        }
+    }
+}
+
+/// Log the warning summary that ends every run
+pub fn log_summary(budget: &WarningBudget) {
+    if budget.is_empty() {
+        tracing::info!("✓ Warning summary: no warnings");
+        return;
+    }
+    // Logged at INFO so the summary itself is not counted as a warning
+    tracing::info!("⚠ Warning summary: {} warning(s)", budget.total());
+    for (warning, count) in &budget.warnings {
+        let first_line = warning.lines().next().unwrap_or_default();
+        if *count > 1 {
+            tracing::info!("  ⚠ {} (x{})", first_line, count);
+        } else {
+            tracing::info!("  ⚠ {}", first_line);
+        }
+    }
+    if budget.unlisted > 0 {
+        tracing::info!(
+            "  ⚠ {} more warning(s) not listed; see the log",
+            budget.unlisted
+        );
+    }
+}
+
+/// Fail a run that logged warnings under `--strict`
+///
+/// # Errors
+///
+/// Returns an error if `strict` is set and `budget` is not empty.
+pub fn check_strict(budget: &WarningBudget, strict: bool) -> Result<()> {
+    if strict && !budget.is_empty() {
+        bail!(
+            "--strict: the run logged {} warning(s); see the warning summary above.\n\
+             Resolve the warnings, or rerun without --strict to accept them.",
+            budget.total()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_counts_repeats_in_first_seen_order() {
+        let mut budget = WarningBudget::new();
+        for warning in ["slot lag high", "sequence skipped", "slot lag high"] {
+            budget.record(warning);
+        }
+        assert_eq!(
+            budget.warnings,
+            vec![
+                ("slot lag high".to_string(), 2),
+                ("sequence skipped".to_string(), 1)
+            ]
+        );
+        assert_eq!(budget.total(), 3);
+        assert!(WarningBudget::new().is_empty());
+    }
+
+    #[test]
+    fn test_budget_caps_distinct_messages() {
+        let mut budget = WarningBudget::new();
+        for row in 0..MAX_DISTINCT_WARNINGS + 10 {
+            budget.record(&format!("row {} sanitized", row));
+        }
+        budget.record("row 0 sanitized");
+        assert_eq!(budget.warnings.len(), MAX_DISTINCT_WARNINGS);
+        assert_eq!(budget.warnings[0].1, 2);
+        assert_eq!(budget.unlisted, 10);
+        assert_eq!(budget.total(), MAX_DISTINCT_WARNINGS + 11);
+    }
+
+    #[test]
+    fn test_layer_records_warn_events_without_marker() {
+        use tracing_subscriber::layer::SubscriberExt;
+        let subscriber = tracing_subscriber::registry().with(WarningLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!("  ⚠ budget-test: {} skipped", "orders");
+            tracing::info!("budget-test: not a warning");
+        });
+        let collected = collected().messages();
+        assert!(collected.contains(&"budget-test: orders skipped".to_string()));
+        assert!(!collected.iter().any(|w| w.contains("not a warning")));
+    }
+
+    #[test]
+    fn test_layer_ignores_dependency_warnings() {
+        use tracing_subscriber::layer::SubscriberExt;
+        let subscriber = tracing_subscriber::registry().with(WarningLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "rustls::conn", "budget-test: dependency warning");
+        });
+        assert!(!collected()
+            .messages()
+            .iter()
+            .any(|w| w.contains("dependency warning")));
+    }
+
+    #[test]
+    fn test_check_strict_fails_only_with_warnings() {
+        let mut budget = WarningBudget::new();
+        budget.record("lag");
+        assert!(check_strict(&budget, false).is_ok());
+        assert!(check_strict(&WarningBudget::new(), true).is_ok());
+        let err = check_strict(&budget, true).unwrap_err().to_string();
+        assert!(err.contains("1 warning(s)"));
+    }
+}
@@ -34,7 +34,7 @@ async fn test_remote_job_submission() {
 
     // Create a job spec for validation (safe, read-only)
     let job_spec = JobSpec {
-        version: "1.0".to_string(),
+        version: seren_replicator::remote::JOB_SPEC_VERSION.to_string(),
         command: "validate".to_string(),
         source_url,
         target_url,
@@ -84,7 +84,7 @@ async fn test_remote_job_polling() {
 
     // Create and submit a job spec for validation (safe, read-only)
     let job_spec = JobSpec {
-        version: "1.0".to_string(),
+        version: seren_replicator::remote::JOB_SPEC_VERSION.to_string(),
         command: "validate".to_string(),
         source_url,
         target_url,
@@ -179,7 +179,7 @@ async fn test_remote_job_poll_until_complete() {
 
     // Create and submit a job spec for validation (safe, read-only)
     let job_spec = JobSpec {
-        version: "1.0".to_string(),
+        version: seren_replicator::remote::JOB_SPEC_VERSION.to_string(),
         command: "validate".to_string(),
         source_url,
         target_url,
@@ -259,13 +259,11 @@ async fn test_remote_job_submission_with_filters() {
     let client = RemoteClient::new(api_url).expect("Failed to create remote client");
 
     // Create a job spec with database filters
-    let filter = seren_replicator::remote::FilterSpec {
-        include_databases: Some(vec!["postgres".to_string()]),
-        exclude_tables: None,
-    };
+    let filter =
+        seren_replicator::remote::FilterSpec::new(Some(vec!["postgres".to_string()]), None);
 
     let job_spec = JobSpec {
-        version: "1.0".to_string(),
+        version: seren_replicator::remote::JOB_SPEC_VERSION.to_string(),
         command: "validate".to_string(),
         source_url,
         target_url,
@@ -312,7 +310,7 @@ async fn test_remote_job_submission_with_options() {
     );
 
     let job_spec = JobSpec {
-        version: "1.0".to_string(),
+        version: seren_replicator::remote::JOB_SPEC_VERSION.to_string(),
         command: "validate".to_string(),
         source_url,
         target_url,
@@ -154,6 +154,12 @@ async fn test_mysql_full_replication_integration() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -195,6 +201,12 @@ async fn test_mysql_null_and_blob_handling() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -240,6 +252,12 @@ async fn test_mysql_empty_table_replication() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -331,6 +349,12 @@ async fn test_mysql_all_data_types() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -368,6 +392,12 @@ async fn test_mysql_empty_database_fails_gracefully() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -400,6 +430,12 @@ async fn test_mysql_invalid_url_fails() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -438,6 +474,12 @@ async fn test_mysql_missing_database_name_fails() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -507,6 +549,12 @@ async fn test_mysql_decimal_and_datetime_precision() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
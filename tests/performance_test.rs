@@ -198,6 +198,12 @@ async fn benchmark_sqlite_small_migration() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -238,6 +244,12 @@ async fn benchmark_sqlite_medium_migration() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -278,6 +290,12 @@ async fn benchmark_sqlite_large_migration() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -321,6 +339,12 @@ async fn benchmark_mongodb_small_collection() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -354,6 +378,12 @@ async fn benchmark_mongodb_medium_collection() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -391,6 +421,12 @@ async fn benchmark_mysql_small_table() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -424,6 +460,12 @@ async fn benchmark_mysql_medium_table() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -462,6 +504,12 @@ async fn benchmark_jsonb_batch_insert() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
@@ -560,6 +608,12 @@ async fn benchmark_many_small_tables() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
     let elapsed = start.elapsed();
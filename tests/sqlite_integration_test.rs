@@ -89,6 +89,12 @@ async fn test_sqlite_full_migration_integration() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -127,6 +133,12 @@ async fn test_sqlite_null_and_blob_handling() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -169,6 +181,12 @@ async fn test_sqlite_empty_table_migration() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -230,6 +248,12 @@ async fn test_sqlite_all_data_types() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -269,6 +293,12 @@ async fn test_sqlite_empty_database() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -303,6 +333,12 @@ async fn test_sqlite_invalid_path_fails() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 
@@ -334,6 +370,12 @@ async fn test_sqlite_path_traversal_prevention() {
         false,
         false,
         true,
+        seren_replicator::checkpoint::InitScope::Full,
+        false,
+        &seren_replicator::commands::init::InitOptions::default(),
+        None,
+        &Default::default(),
+        &seren_replicator::cancel::CancellationToken::new(),
     )
     .await;
 